@@ -0,0 +1,155 @@
+// The hot path of the watcher: a check of an unchanged file, loads of
+// small to large configurations, a reload of the same bytes, and the
+// diff they are made of. The hashing is internal to the crate: its cost
+// shows in the reload of the same bytes.
+//
+//   cargo bench --bench read_path
+//
+// Compare two revisions with criterion's baselines:
+//
+//   cargo bench --bench read_path -- --save-baseline before
+//   cargo bench --bench read_path -- --baseline before
+
+use config_watcher::diff;
+use config_watcher::{ConfigWatcher, Emitter, OutputFormat, Verbosity};
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use serde_json::{Map, Value, json};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::runtime::Runtime;
+
+/// Approximate sizes of the generated files
+const SIZES: [(&str, usize); 3] = [("small", 1 << 10), ("medium", 1 << 20), ("large", 20 << 20)];
+
+/// A valid configuration of about `size` bytes, grown through its features
+fn config(size: usize) -> Value {
+    let mut features = Map::new();
+    // `"feature_0000000": true, ` is 25 bytes
+    for i in 0..size / 25 {
+        features.insert(format!("feature_{i:07}"), Value::Bool(i % 3 == 0));
+    }
+    json!({
+        "app_name": "bench",
+        "version": "1.0.0",
+        "environment": "production",
+        "server": {
+            "host": "localhost",
+            "port": 8080,
+            "allowed_ips": ["10.0.0.0/8", "::1"],
+            "max_connections": 100,
+        },
+        "database": {
+            "connection_string": "postgres://localhost/bench",
+            "pool_size": 10,
+            "timeout_seconds": 30,
+        },
+        "features": features,
+    })
+}
+
+/// Writes the configuration of each size under the target directory, once
+fn files() -> Vec<(&'static str, PathBuf)> {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("read_path");
+    std::fs::create_dir_all(&dir).unwrap();
+    SIZES
+        .iter()
+        .map(|&(name, size)| {
+            let path = dir.join(format!("{name}.json"));
+            if !path.exists() {
+                std::fs::write(&path, serde_json::to_vec_pretty(&config(size)).unwrap()).unwrap();
+            }
+            (name, path)
+        })
+        .collect()
+}
+
+/// A watcher that reports nothing, so only the work itself is measured
+fn watcher(path: &Path) -> ConfigWatcher {
+    let emitter = Emitter::new(OutputFormat::Json).with_verbosity(Verbosity::Quiet);
+    ConfigWatcher::new(path, 1).with_emitter(emitter)
+}
+
+fn bench_tick(c: &mut Criterion, rt: &Runtime, files: &[(&str, PathBuf)]) {
+    let (_, ref path) = files[0];
+    let mut watcher = watcher(path);
+    rt.block_on(watcher.load_initial()).unwrap();
+    // The file does not change: one stat per check
+    c.bench_function("tick/stat_only", |b| {
+        b.iter_custom(|iters| {
+            rt.block_on(async {
+                let started = Instant::now();
+                for _ in 0..iters {
+                    watcher.tick().await.unwrap();
+                }
+                started.elapsed()
+            })
+        })
+    });
+}
+
+fn bench_load(c: &mut Criterion, rt: &Runtime, files: &[(&str, PathBuf)]) {
+    let mut group = c.benchmark_group("load");
+    group.sample_size(10);
+    for (name, path) in files {
+        group.throughput(Throughput::Bytes(std::fs::metadata(path).unwrap().len()));
+        group.bench_function(*name, |b| {
+            b.iter_batched(
+                || watcher(path),
+                |mut watcher| assert!(rt.block_on(watcher.load_initial()).unwrap()),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_reload_unchanged(c: &mut Criterion, rt: &Runtime, files: &[(&str, PathBuf)]) {
+    let mut group = c.benchmark_group("reload/unchanged");
+    group.sample_size(10);
+    for (name, path) in files {
+        let mut watcher = watcher(path);
+        rt.block_on(watcher.load_initial()).unwrap();
+        let file = File::options().write(true).open(path).unwrap();
+        let mut touched = SystemTime::now();
+        group.bench_function(*name, |b| {
+            b.iter_custom(|iters| {
+                let mut elapsed = Duration::ZERO;
+                for _ in 0..iters {
+                    // A save of the same bytes, as far as the stat can tell
+                    touched += Duration::from_secs(1);
+                    file.set_modified(touched).unwrap();
+                    let started = Instant::now();
+                    rt.block_on(watcher.tick()).unwrap();
+                    elapsed += started.elapsed();
+                }
+                elapsed
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_diff(c: &mut Criterion) {
+    let (_, size) = SIZES[2];
+    let old = config(size);
+    let mut new = old.clone();
+    new["features"]["feature_0000000"] = Value::Bool(false);
+    new["server"]["port"] = json!(9090);
+    let mut group = c.benchmark_group("diff");
+    group.sample_size(10);
+    group.bench_function("large", |b| b.iter(|| diff::diff(&old, &new)));
+    group.finish();
+}
+
+fn read_path(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let files = files();
+    bench_tick(c, &rt, &files);
+    bench_load(c, &rt, &files);
+    bench_reload_unchanged(c, &rt, &files);
+    bench_diff(c);
+}
+
+criterion_group!(benches, read_path);
+criterion_main!(benches);
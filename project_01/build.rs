@@ -0,0 +1,18 @@
+// Generates the gRPC messages, server and client of
+// proto/config_watcher.proto for the `grpc` feature. protox parses the
+// file in Rust, so the build needs no protoc.
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    grpc();
+}
+
+#[cfg(feature = "grpc")]
+fn grpc() {
+    const PROTO: &str = "proto/config_watcher.proto";
+    println!("cargo:rerun-if-changed={PROTO}");
+    let files = protox::compile([PROTO], ["proto"]).unwrap_or_else(|e| panic!("{PROTO}: {e}"));
+    tonic_prost_build::configure()
+        .compile_fds(files)
+        .unwrap_or_else(|e| panic!("{PROTO}: {e}"));
+}
@@ -0,0 +1,65 @@
+// Runs the watcher inside another program and reacts to its events:
+//
+//   cargo run --example embed_and_subscribe
+//
+// The watcher checks a scratch file every second; the example rewrites it
+// once, prints the events the watcher reports, and stops after the reload.
+
+use config_watcher::prelude::*;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+const V1: &str = r#"{ "app_name": "embedded", "version": "1.0.0", "features": { "beta": false } }"#;
+const V2: &str = r#"{ "app_name": "embedded", "version": "1.1.0", "features": { "beta": true } }"#;
+
+/// Writes `contents` and moves the modification time forward, so that the
+/// change is seen whatever the resolution of the file system
+fn write(path: &Path, contents: &str, modified: SystemTime) -> std::io::Result<()> {
+    std::fs::write(path, contents)?;
+    std::fs::File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(modified)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let file = tempfile::NamedTempFile::new()?;
+    let now = SystemTime::now();
+    write(file.path(), V1, now)?;
+
+    // Events arrive on the channel whatever the verbosity; nothing is printed
+    let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+    let emitter = Emitter::new(OutputFormat::Json)
+        .with_verbosity(Verbosity::Quiet)
+        .with_events(sender);
+    let mut watcher = ConfigWatcher::new(file.path(), 1).with_emitter(emitter);
+    let watching = tokio::spawn(async move { watcher.watch().await });
+
+    while let Some(event) = events.recv().await {
+        match event.kind {
+            EventKind::Initial | EventKind::Reload => {
+                let config: &AppConfig = event
+                    .config
+                    .as_ref()
+                    .expect("loads carry the configuration");
+                println!(
+                    "{:?}: {} {} (beta: {})",
+                    event.kind,
+                    config.app_name,
+                    config.version,
+                    config.features.get("beta").copied().unwrap_or_default()
+                );
+                if event.kind == EventKind::Reload {
+                    break;
+                }
+                write(file.path(), V2, now + Duration::from_secs(1))?;
+            }
+            // The JSON form carries the error and its findings
+            _ => println!("{:?}: {}", event.kind, event.data),
+        }
+    }
+
+    watching.abort();
+    Ok(())
+}
@@ -0,0 +1,41 @@
+// Checks a configuration file once, the way `config-watcher validate` does,
+// and prints every finding:
+//
+//   cargo run --example load_and_validate -- ../prj01_example_config.json
+//
+// Exits with the same status as the command: 0 when valid, 5 when a rule
+// fails, 3 or 4 when the file cannot be read or parsed.
+
+use config_watcher::commands::validate::{describe, validate};
+use config_watcher::{exit, report};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let Some(path) = std::env::args_os().nth(1).map(PathBuf::from) else {
+        eprintln!("usage: load_and_validate FILE");
+        return ExitCode::from(exit::USAGE);
+    };
+
+    match validate(&path, None) {
+        Ok(report) => {
+            // A valid file may still have warnings
+            for finding in report.findings() {
+                println!("{}", describe(finding));
+            }
+            println!("{} is valid", path.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!(
+                "{} is not valid ({}): {e:#}",
+                path.display(),
+                report::code(&e)
+            );
+            for finding in report::findings(&e) {
+                eprintln!("  {}", describe(&finding));
+            }
+            ExitCode::from(exit::for_error(&e))
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! The check both targets run on the bytes they build
+
+use config_watcher::commands::validate::validate_bytes;
+use config_watcher::config::AppConfig;
+use config_watcher::report::ErrorReport;
+use std::path::Path;
+
+/// Name the bytes are validated under; file references resolve next to it
+const PATH: &str = "fuzz.json";
+
+/// Runs the pipeline on `bytes`, true when it accepts them
+///
+/// A panic anywhere, the error messages and reports included, is a
+/// finding; so is a document accepted here that does not parse and
+/// validate on its own.
+pub fn accepts(bytes: &[u8]) -> bool {
+    let path = Path::new(PATH);
+    match validate_bytes(path, bytes, None) {
+        Ok(_) => {
+            let config: AppConfig =
+                serde_json::from_slice(bytes).expect("accepted, yet does not parse");
+            config.validate().expect("accepted, yet does not validate");
+            true
+        }
+        Err(e) => {
+            let _ = format!("{e:#}");
+            let _ = ErrorReport::new(path, &e).to_json_line();
+            false
+        }
+    }
+}
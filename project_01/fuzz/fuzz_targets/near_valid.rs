@@ -0,0 +1,293 @@
+//! Near-valid documents: typed pieces of a configuration, serialized, then
+//! damaged the way hostile or broken writers do
+//!
+//! The pieces mostly take values the rules care about (semver-ish
+//! versions, the known environments, CIDR blocks, duration strings at the
+//! edge of overflow), so the fuzzer spends its time past the parser.
+
+#![no_main]
+
+mod common;
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use serde_json::{Map, Value};
+
+/// Strings the rules treat specially; the rest come from the fuzzer
+const INTERESTING: &[&str] = &[
+    "",
+    " ",
+    "1",
+    "1.0.0",
+    "development",
+    "staging",
+    "production",
+    "localhost",
+    "::1",
+    "10.0.0.0/8",
+    "10.0.0.0/33",
+    "0.0.0.0/0",
+    "postgres://localhost/db",
+    "30s",
+    "0s",
+    "1h 1ns",
+    "18446744073709551615s",
+    "584942417355years",
+    "\u{feff}",
+    "é\u{301}",
+    "../secret.pem",
+];
+
+#[derive(Debug, Arbitrary)]
+enum Text {
+    Known(u8),
+    Any(String),
+}
+
+impl Text {
+    fn value(&self) -> Value {
+        match self {
+            Text::Known(i) => Value::from(INTERESTING[*i as usize % INTERESTING.len()]),
+            Text::Any(text) => Value::from(text.as_str()),
+        }
+    }
+}
+
+/// A duration as the configuration may spell it
+#[derive(Debug, Arbitrary)]
+enum Span {
+    Seconds(u64),
+    Text(Text),
+}
+
+impl Span {
+    fn value(&self) -> Value {
+        match self {
+            Span::Seconds(secs) => Value::from(*secs),
+            Span::Text(text) => text.value(),
+        }
+    }
+}
+
+#[derive(Debug, Arbitrary)]
+struct Server {
+    host: Text,
+    // Wider than the u16 of the field, to reach its bounds check
+    port: u32,
+    enable_ssl: Option<bool>,
+    allowed_ips: Vec<Text>,
+    denied_ips: Vec<Text>,
+    max_connections: Option<i64>,
+    keep_alive: Option<Span>,
+    request_timeout: Option<Span>,
+    shutdown_grace: Option<Span>,
+}
+
+#[derive(Debug, Arbitrary)]
+struct Database {
+    connection_string: Text,
+    pool_size: Option<i64>,
+    timeout_seconds: Option<i64>,
+}
+
+/// A JSON value of whatever type the field does not expect
+#[derive(Debug, Arbitrary)]
+enum Scalar {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(Text),
+    Array,
+    Object,
+}
+
+impl Scalar {
+    fn value(&self) -> Value {
+        match self {
+            Scalar::Null => Value::Null,
+            Scalar::Bool(b) => Value::from(*b),
+            Scalar::Int(n) => Value::from(*n),
+            // NaN and the infinities become null
+            Scalar::Float(x) => Value::from(*x),
+            Scalar::Text(text) => text.value(),
+            Scalar::Array => Value::Array(Vec::new()),
+            Scalar::Object => Value::Object(Map::new()),
+        }
+    }
+}
+
+/// Fields the mutations aim at, as JSON Pointers
+const FIELDS: &[&str] = &[
+    "",
+    "/app_name",
+    "/version",
+    "/environment",
+    "/server",
+    "/server/host",
+    "/server/port",
+    "/server/allowed_ips",
+    "/server/keep_alive",
+    "/server/max_connections",
+    "/database",
+    "/database/pool_size",
+    "/features",
+];
+
+#[derive(Debug, Arbitrary)]
+enum Mutation {
+    /// Replaces a field with a value of another type
+    Retype { field: u8, value: Scalar },
+    /// Removes a field, required or not
+    Remove { field: u8 },
+    /// Wraps a field in `depth` arrays, past the parser's recursion limit
+    Nest { field: u8, depth: u8 },
+    /// Cuts the serialized document, like a write caught half-way
+    Truncate { at: u16 },
+    /// Inserts a byte in the serialized document
+    Insert { at: u16, byte: u8 },
+}
+
+#[derive(Debug, Arbitrary)]
+struct NearValid {
+    app_name: Text,
+    version: Text,
+    environment: Option<Text>,
+    server: Option<Server>,
+    database: Option<Database>,
+    features: Vec<(Text, bool)>,
+    mutations: Vec<Mutation>,
+}
+
+impl NearValid {
+    fn document(&self) -> Value {
+        let mut doc = Map::new();
+        doc.insert("app_name".into(), self.app_name.value());
+        doc.insert("version".into(), self.version.value());
+        if let Some(ref environment) = self.environment {
+            doc.insert("environment".into(), environment.value());
+        }
+        if let Some(ref server) = self.server {
+            doc.insert("server".into(), server.value());
+        }
+        if let Some(ref database) = self.database {
+            let mut db = Map::new();
+            db.insert(
+                "connection_string".into(),
+                database.connection_string.value(),
+            );
+            optional(&mut db, "pool_size", database.pool_size.map(Value::from));
+            optional(
+                &mut db,
+                "timeout_seconds",
+                database.timeout_seconds.map(Value::from),
+            );
+            doc.insert("database".into(), Value::Object(db));
+        }
+        let features = self
+            .features
+            .iter()
+            .filter_map(|(name, on)| Some((name.value().as_str()?.to_string(), Value::from(*on))))
+            .collect();
+        doc.insert("features".into(), Value::Object(features));
+        Value::Object(doc)
+    }
+}
+
+impl Server {
+    fn value(&self) -> Value {
+        let list = |entries: &[Text]| Value::Array(entries.iter().map(Text::value).collect());
+        let mut server = Map::new();
+        server.insert("host".into(), self.host.value());
+        server.insert("port".into(), Value::from(self.port));
+        optional(&mut server, "enable_ssl", self.enable_ssl.map(Value::from));
+        server.insert("allowed_ips".into(), list(&self.allowed_ips));
+        server.insert("denied_ips".into(), list(&self.denied_ips));
+        optional(
+            &mut server,
+            "max_connections",
+            self.max_connections.map(Value::from),
+        );
+        for (key, span) in [
+            ("keep_alive", &self.keep_alive),
+            ("request_timeout", &self.request_timeout),
+            ("shutdown_grace", &self.shutdown_grace),
+        ] {
+            optional(&mut server, key, span.as_ref().map(Span::value));
+        }
+        Value::Object(server)
+    }
+}
+
+fn optional(map: &mut Map<String, Value>, key: &str, value: Option<Value>) {
+    if let Some(value) = value {
+        map.insert(key.into(), value);
+    }
+}
+
+fn field(index: u8) -> &'static str {
+    FIELDS[index as usize % FIELDS.len()]
+}
+
+/// Applies the mutations: those on the tree first, then those on the bytes
+///
+/// Returns the bytes, and whether they are a strict prefix of a whole
+/// document and nothing else (a truncation without inserts).
+fn damage(mut doc: Value, mutations: &[Mutation]) -> (Vec<u8>, bool) {
+    for mutation in mutations {
+        match *mutation {
+            Mutation::Retype {
+                field: i,
+                ref value,
+            } => {
+                if let Some(target) = doc.pointer_mut(field(i)) {
+                    *target = value.value();
+                }
+            }
+            Mutation::Remove { field: i } => {
+                if let Some((parent, key)) = field(i).rsplit_once('/')
+                    && let Some(Value::Object(map)) = doc.pointer_mut(parent)
+                {
+                    map.remove(key);
+                }
+            }
+            Mutation::Nest { field: i, depth } => {
+                if let Some(target) = doc.pointer_mut(field(i)) {
+                    for _ in 0..depth {
+                        *target = Value::Array(vec![target.take()]);
+                    }
+                }
+            }
+            Mutation::Truncate { .. } | Mutation::Insert { .. } => {}
+        }
+    }
+
+    let mut bytes = serde_json::to_vec(&doc).expect("a Value always serializes");
+    let mut truncated = false;
+    let mut inserted = false;
+    for mutation in mutations {
+        match *mutation {
+            Mutation::Truncate { at } if (at as usize) < bytes.len() => {
+                bytes.truncate(at as usize);
+                truncated = true;
+            }
+            Mutation::Insert { at, byte } => {
+                bytes.insert((at as usize).min(bytes.len()), byte);
+                inserted = true;
+            }
+            _ => {}
+        }
+    }
+    (bytes, truncated && !inserted)
+}
+
+fuzz_target!(|input: NearValid| {
+    let (bytes, truncated) = damage(input.document(), &input.mutations);
+    let accepted = common::accepts(&bytes);
+    // A strict prefix of an object never closes it
+    assert!(
+        !(truncated && accepted),
+        "accepted a truncated document: {}",
+        String::from_utf8_lossy(&bytes)
+    );
+});
@@ -0,0 +1,15 @@
+//! Arbitrary bytes into the read-bytes → decode → deserialize → validate
+//! pipeline
+//!
+//! Seeds live in `fuzz/seeds/pipeline`: truncated writes, other formats,
+//! absurd nesting, out-of-range numbers and durations, invalid UTF-8.
+
+#![no_main]
+
+mod common;
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    common::accepts(data);
+});
@@ -0,0 +1,211 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`ipnet::IpNet`**: One type for IPv4 and IPv6 CIDR blocks
+- **`IpNet::contains`**: Works for addresses and for nested blocks
+- **`Iterator::enumerate`**: Keeps the entry index for error paths
+
+**Design decisions**:
+- Entries stay strings in `ServerConfig`, so the file round-trips exactly
+  as written; they are parsed during validation
+- A single IP is a block of one address (`/32` or `/128`)
+- Deny wins over allow, and an empty allow list allows everyone
+- Client addresses are canonicalized first: a dual-stack listener sees an
+  IPv4 client as `::ffff:a.b.c.d`, which must still hit the IPv4 blocks
+- Overlapping blocks within one list are only a warning: redundant, not
+  wrong
+
+******************************************************************************/
+
+use crate::config::ServerConfig;
+use crate::validation::ValidationReport;
+use ipnet::IpNet;
+use std::net::IpAddr;
+
+/// Parses an IP address or CIDR block
+pub fn parse_entry(entry: &str) -> Result<IpNet, ipnet::AddrParseError> {
+    let entry = entry.trim();
+    match entry.parse::<IpAddr>() {
+        Ok(addr) => Ok(IpNet::from(addr)),
+        Err(_) => entry.parse::<IpNet>().map(|net| net.trunc()),
+    }
+}
+
+impl ServerConfig {
+    /// Whether a client at `addr` may connect
+    ///
+    /// Denied blocks always win; otherwise an empty allow list allows
+    /// every address. Entries that do not parse are ignored (validation
+    /// reports them). An IPv4-mapped IPv6 address is checked as IPv4.
+    pub fn ip_allowed(&self, addr: IpAddr) -> bool {
+        let addr = addr.to_canonical();
+        let hits = |list: &[String]| {
+            list.iter()
+                .filter_map(|entry| parse_entry(entry).ok())
+                .any(|net| net.contains(&addr))
+        };
+        if hits(&self.denied_ips) {
+            return false;
+        }
+        self.allowed_ips.is_empty() || hits(&self.allowed_ips)
+    }
+
+    /// Records findings for `allowed_ips` and `denied_ips`
+    pub fn check_acl(&self, report: &mut ValidationReport) {
+        let allowed = parse_list("server.allowed_ips", &self.allowed_ips, report);
+        let denied = parse_list("server.denied_ips", &self.denied_ips, report);
+
+        for (j, deny) in &denied {
+            if let Some((i, _)) = allowed.iter().find(|(_, allow)| allow == deny) {
+                report.error(
+                    format!("server.denied_ips[{j}]"),
+                    format!("'{deny}' is also listed in server.allowed_ips[{i}]"),
+                );
+            }
+        }
+    }
+}
+
+/// Parses every entry of a list, reporting bad entries and overlaps
+fn parse_list(
+    field: &str,
+    entries: &[String],
+    report: &mut ValidationReport,
+) -> Vec<(usize, IpNet)> {
+    let mut parsed: Vec<(usize, IpNet)> = Vec::new();
+    for (j, entry) in entries.iter().enumerate() {
+        let net = match parse_entry(entry) {
+            Ok(net) => net,
+            Err(e) => {
+                report.error(
+                    format!("{field}[{j}]"),
+                    format!("'{entry}' is not an IP address or CIDR block: {e}"),
+                );
+                continue;
+            }
+        };
+        if let Some((i, other)) = parsed
+            .iter()
+            .find(|(_, other)| other.contains(&net) || net.contains(other))
+        {
+            report.warning(
+                format!("{field}[{j}]"),
+                format!("'{net}' overlaps {field}[{i}] ('{other}')"),
+            );
+        }
+        parsed.push((j, net));
+    }
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+
+    fn server(allowed: &[&str], denied: &[&str]) -> ServerConfig {
+        let list = |entries: &[&str]| entries.iter().map(|s| s.to_string()).collect();
+        ServerConfig {
+            host: "localhost".to_string(),
+            port: 8080,
+            enable_ssl: false,
+            allowed_ips: list(allowed),
+            denied_ips: list(denied),
+            ..AppConfig::example().server.unwrap()
+        }
+    }
+
+    fn findings(server: &ServerConfig) -> ValidationReport {
+        let mut report = ValidationReport::new();
+        server.check_acl(&mut report);
+        report
+    }
+
+    #[test]
+    fn test_parse_v4_and_v6_entries() {
+        let cases = [
+            ("10.0.0.0/8", Some("10.0.0.0/8")),
+            ("10.1.2.3", Some("10.1.2.3/32")),
+            ("10.1.2.3/8", Some("10.0.0.0/8")),
+            ("2001:db8::/32", Some("2001:db8::/32")),
+            ("::1", Some("::1/128")),
+            ("10.0.0.0/33", None),
+            ("2001:db8::/129", None),
+            ("localhost", None),
+            ("", None),
+        ];
+        for (entry, expected) in cases {
+            assert_eq!(
+                parse_entry(entry)
+                    .ok()
+                    .map(|net| net.to_string())
+                    .as_deref(),
+                expected,
+                "{entry:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_bad_entries_report_index() {
+        let report = findings(&server(&["10.0.0.0/8", "nope"], &["1.2.3.4/99"]));
+        let paths: Vec<_> = report.errors().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["server.allowed_ips[1]", "server.denied_ips[0]"]);
+    }
+
+    #[test]
+    fn test_overlaps_within_a_list_warn() {
+        let report = findings(&server(
+            &["10.0.0.0/8", "192.168.0.0/16", "10.1.0.0/16"],
+            &[],
+        ));
+        assert!(!report.has_errors());
+        let warnings: Vec<_> = report.warnings().collect();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, "server.allowed_ips[2]");
+        assert!(warnings[0].message.contains("server.allowed_ips[0]"));
+    }
+
+    #[test]
+    fn test_entry_in_both_lists_is_an_error() {
+        let report = findings(&server(&["10.0.0.0/8", "10.1.2.3"], &["10.1.2.3/32"]));
+        let errors: Vec<_> = report.errors().collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "server.denied_ips[0]");
+        assert!(errors[0].message.contains("server.allowed_ips[1]"));
+    }
+
+    #[test]
+    fn test_ip_allowed_decision_table() {
+        let open = server(&[], &[]);
+        let allow_only = server(&["10.0.0.0/8", "2001:db8::/32"], &[]);
+        let deny_only = server(&[], &["10.0.0.13"]);
+        let both = server(&["10.0.0.0/8"], &["10.0.0.0/24"]);
+
+        let cases = [
+            (&open, "203.0.113.7", true),
+            (&allow_only, "10.9.8.7", true),
+            (&allow_only, "2001:db8::1", true),
+            (&allow_only, "203.0.113.7", false),
+            (&deny_only, "10.0.0.13", false),
+            (&deny_only, "10.0.0.14", true),
+            (&both, "10.0.0.5", false),
+            (&both, "10.0.1.5", true),
+            (&both, "11.0.0.1", false),
+            // IPv4 clients of a dual-stack listener
+            (&deny_only, "::ffff:10.0.0.13", false),
+            (&deny_only, "::ffff:10.0.0.14", true),
+            (&allow_only, "::ffff:10.9.8.7", true),
+            (&both, "::ffff:10.0.0.5", false),
+        ];
+        for (config, addr, expected) in cases {
+            assert_eq!(
+                config.ip_allowed(addr.parse().unwrap()),
+                expected,
+                "{addr} with allow={:?} deny={:?}",
+                config.allowed_ips,
+                config.denied_ips
+            );
+        }
+    }
+}
@@ -0,0 +1,459 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **Recursion over `serde_json::Value`**: Every string of the document is
+  visited with its `FieldPath`, the path the errors and redaction use
+- **`thiserror` on a small enum**: `DecryptError` says what went wrong
+  with one value, in words that never include the value itself
+- **`zeroize::Zeroizing`**: The identity file's text is wiped when it is
+  dropped, like the secrets parsed from it
+- **`#[cfg(feature = "age")]`**: The module, and the `age` and `zeroize`
+  crates, are only built with the `age` feature
+
+**Design decisions**:
+- `--age-identity FILE` loads the identities of an `age-keygen` file
+  (`AGE-SECRET-KEY-1...` lines, `#` comments); the flag can be repeated.
+  Only X25519 identities exist here: no passphrases, no plugins, no SSH
+  keys
+- A string value `age:<base64>` holds a whole age file (binary, not
+  armored) encrypted to one of the identities; it is replaced by its
+  plaintext right after the document is read, before overrides and
+  validation, so everything else sees an ordinary string
+- A value that cannot be decrypted fails the load, with one line per
+  field path and a reason (`no identity matches`, `corrupted`...), never
+  the ciphertext or a byte of plaintext; every failing field is listed,
+  not only the first
+- Every decrypted path is registered with `redact::mark_secret`, so
+  summaries, diffs, events and the HTTP endpoints hide it like a field
+  the schema flags as secret
+- The format and its ciphers are the `age` crate's; this module parses
+  the identity file line by line, so an error can name the line, and
+  maps the crate's errors to the reasons above. The secret keys are
+  `x25519-dalek` secrets, zeroed on drop. The tests decrypt fixtures made
+  by an independent implementation (`tests/fixtures/age/generate.py`)
+
+******************************************************************************/
+
+use crate::error::{ConfigError, Result};
+use crate::path::{FieldPath, Segment};
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use serde_json::Value;
+use std::io::Read;
+use std::path::Path;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Marks a string value as an age-encrypted secret
+pub const PREFIX: &str = "age:";
+
+/// First line of every age file
+const INTRO: &str = "age-encryption.org/v1\n";
+
+/// Why one value cannot be decrypted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum DecryptError {
+    #[error("not base64")]
+    NotBase64,
+    #[error("not an age-encrypted file")]
+    NotAge,
+    #[error("malformed age header")]
+    MalformedHeader,
+    #[error("no identity matches (encrypted to other recipients)")]
+    NoMatchingIdentity,
+    #[error("corrupted or tampered header")]
+    HeaderMac,
+    #[error("corrupted, tampered or truncated payload")]
+    Payload,
+    #[error("the plaintext is not UTF-8")]
+    NotUtf8,
+}
+
+impl From<age::DecryptError> for DecryptError {
+    fn from(error: age::DecryptError) -> Self {
+        match error {
+            age::DecryptError::UnknownFormat => Self::NotAge,
+            age::DecryptError::InvalidMac => Self::HeaderMac,
+            age::DecryptError::DecryptionFailed => Self::Payload,
+            // Passphrase-only files, which no X25519 identity opens
+            age::DecryptError::NoMatchingKeys
+            | age::DecryptError::KeyDecryptionFailed
+            | age::DecryptError::ExcessiveWork { .. } => Self::NoMatchingIdentity,
+            // `InvalidHeader`, or an `Io` error: reading a slice only fails
+            // on a header cut short
+            _ => Self::MalformedHeader,
+        }
+    }
+}
+
+/// An X25519 identity: the secret key of one recipient
+#[derive(Clone)]
+pub struct Identity(age::x25519::Identity);
+
+impl Identity {
+    /// The identity of an `AGE-SECRET-KEY-1...` string; None when it is not
+    /// one
+    pub fn parse(text: &str) -> Option<Self> {
+        text.parse().ok().map(Self)
+    }
+
+    /// The matching recipient, `age1...`
+    pub fn recipient(&self) -> String {
+        self.0.to_public().to_string()
+    }
+}
+
+impl std::fmt::Debug for Identity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Identity")
+            .field("recipient", &self.recipient())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The identities of an identity file's text; the error names the line,
+/// never its content
+pub fn parse_identities(text: &str) -> std::result::Result<Vec<Identity>, String> {
+    let mut identities = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let identity = Identity::parse(line).ok_or_else(|| {
+            format!(
+                "line {}: not an X25519 identity (AGE-SECRET-KEY-1...)",
+                number + 1
+            )
+        })?;
+        identities.push(identity);
+    }
+    if identities.is_empty() {
+        return Err("no identity in the file".to_string());
+    }
+    Ok(identities)
+}
+
+/// Reads the identities of `--age-identity`
+pub fn load(path: &Path) -> Result<Vec<Identity>> {
+    let invalid = |reason: String| ConfigError::InvalidIdentity {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let text = Zeroizing::new(std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?);
+    parse_identities(&text).map_err(invalid)
+}
+
+/// Decrypts the age file `data` with the first identity that matches
+pub fn decrypt(identities: &[Identity], data: &[u8]) -> std::result::Result<Vec<u8>, DecryptError> {
+    // The crate calls anything else a malformed header
+    if !data.starts_with(INTRO.as_bytes()) {
+        return Err(DecryptError::NotAge);
+    }
+    let decryptor = age::Decryptor::new_buffered(data)?;
+    let mut reader = decryptor.decrypt(
+        identities
+            .iter()
+            .map(|identity| &identity.0 as &dyn age::Identity),
+    )?;
+    let mut plaintext = Vec::new();
+    if reader.read_to_end(&mut plaintext).is_err() {
+        plaintext.zeroize();
+        return Err(DecryptError::Payload);
+    }
+    Ok(plaintext)
+}
+
+/// The plaintext of one `age:` value (the text after the prefix)
+pub fn decrypt_value(
+    identities: &[Identity],
+    encoded: &str,
+) -> std::result::Result<String, DecryptError> {
+    let data = STANDARD
+        .decode(encoded)
+        .or_else(|_| STANDARD_NO_PAD.decode(encoded))
+        .map_err(|_| DecryptError::NotBase64)?;
+    String::from_utf8(decrypt(identities, &data)?).map_err(|e| {
+        e.into_bytes().zeroize();
+        DecryptError::NotUtf8
+    })
+}
+
+/// Replaces every `age:` string of `doc` with its plaintext and returns
+/// their paths
+///
+/// Nothing is left half done for the caller to use: on error, the
+/// `DecryptionFailed` lists every field that failed.
+pub fn decrypt_document(
+    file: &Path,
+    doc: &mut Value,
+    identities: &[Identity],
+) -> Result<Vec<FieldPath>> {
+    let mut decrypted = Vec::new();
+    let mut failures = Vec::new();
+    walk(
+        &FieldPath::root(),
+        doc,
+        identities,
+        &mut decrypted,
+        &mut failures,
+    );
+    if !failures.is_empty() {
+        return Err(ConfigError::DecryptionFailed {
+            path: file.to_path_buf(),
+            failures,
+        });
+    }
+    Ok(decrypted)
+}
+
+fn walk(
+    path: &FieldPath,
+    value: &mut Value,
+    identities: &[Identity],
+    decrypted: &mut Vec<FieldPath>,
+    failures: &mut Vec<(String, String)>,
+) {
+    match value {
+        Value::String(text) => {
+            let Some(encoded) = text.strip_prefix(PREFIX) else {
+                return;
+            };
+            match decrypt_value(identities, encoded) {
+                Ok(plaintext) => {
+                    *text = plaintext;
+                    decrypted.push(path.clone());
+                }
+                Err(e) => failures.push((path.to_string(), e.to_string())),
+            }
+        }
+        Value::Object(map) => {
+            for (key, child) in map {
+                let path = path.child(Segment::Key(key.clone()));
+                walk(&path, child, identities, decrypted, failures);
+            }
+        }
+        Value::Array(items) => {
+            for (index, child) in items.iter_mut().enumerate() {
+                let path = path.child(Segment::Index(index));
+                walk(&path, child, identities, decrypted, failures);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+
+    /// Plaintext bytes per payload chunk, and the tag after each
+    const CHUNK: usize = 64 * 1024;
+    const TAG_LEN: usize = 16;
+
+    const IDENTITY: &str = include_str!("../tests/fixtures/age/identity.txt");
+    const OTHER_IDENTITY: &str = include_str!("../tests/fixtures/age/other_identity.txt");
+    const VALUES: &str = include_str!("../tests/fixtures/age/values.json");
+
+    fn identities(text: &str) -> Vec<Identity> {
+        parse_identities(text).unwrap()
+    }
+
+    fn value(name: &str) -> String {
+        let values: Value = serde_json::from_str(VALUES).unwrap();
+        values[name].as_str().unwrap().to_string()
+    }
+
+    fn decrypt_fixture(
+        identities: &[Identity],
+        name: &str,
+    ) -> std::result::Result<String, DecryptError> {
+        decrypt_value(identities, value(name).strip_prefix(PREFIX).unwrap())
+    }
+
+    /// An age file for `recipient`: for payloads too large for a fixture
+    fn encrypt(recipient: &Identity, plaintext: &[u8]) -> Vec<u8> {
+        let recipient = recipient.0.to_public();
+        let encryptor =
+            age::Encryptor::with_recipients(std::iter::once(&recipient as &dyn age::Recipient))
+                .unwrap();
+        let mut out = Vec::new();
+        let mut writer = encryptor.wrap_output(&mut out).unwrap();
+        writer.write_all(plaintext).unwrap();
+        writer.finish().unwrap();
+        out
+    }
+
+    #[test]
+    fn test_identity_file() {
+        let ours = identities(IDENTITY);
+        assert_eq!(ours.len(), 1);
+        let values: Value = serde_json::from_str(VALUES).unwrap();
+        assert_eq!(ours[0].recipient(), values["recipient"]);
+        // The comment age-keygen writes names the same recipient
+        assert!(IDENTITY.contains(&ours[0].recipient()));
+        assert!(!format!("{:?}", ours[0]).contains("AGE-SECRET-KEY"));
+
+        let both = identities(&format!("{OTHER_IDENTITY}\n{IDENTITY}"));
+        assert_eq!(both.len(), 2);
+
+        let error = parse_identities("# created\nAGE-SECRET-KEY-1QQQQ\n").unwrap_err();
+        assert!(error.starts_with("line 2:"), "{error}");
+        assert!(!error.contains("QQQQ"), "{error}");
+        assert!(parse_identities("# only comments\n").is_err());
+        // A recipient is not an identity
+        assert!(Identity::parse(values["recipient"].as_str().unwrap()).is_none());
+
+        // Either case, not both; one character changed fails the checksum
+        let secret = IDENTITY
+            .lines()
+            .find(|line| !line.starts_with('#'))
+            .unwrap();
+        assert!(Identity::parse(&secret.to_ascii_lowercase()).is_some());
+        let mixed = format!("{}{}", &secret[..20], secret[20..].to_ascii_lowercase());
+        assert!(Identity::parse(&mixed).is_none());
+        let mut flipped = secret.to_string().into_bytes();
+        let at = flipped.len() - 10;
+        flipped[at] = if flipped[at] == b'Q' { b'P' } else { b'Q' };
+        assert!(Identity::parse(&String::from_utf8(flipped).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_decrypts_the_fixtures() {
+        let ours = identities(IDENTITY);
+        assert_eq!(
+            decrypt_fixture(&ours, "connection_string").unwrap(),
+            "postgres://app:s3cr3t@db/app"
+        );
+        assert_eq!(decrypt_fixture(&ours, "empty").unwrap(), "");
+        // Two stanzas, ours second
+        assert_eq!(
+            decrypt_fixture(&ours, "both_recipients").unwrap(),
+            "shared secret"
+        );
+        let theirs = identities(OTHER_IDENTITY);
+        assert_eq!(
+            decrypt_fixture(&theirs, "both_recipients").unwrap(),
+            "shared secret"
+        );
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        assert_eq!(
+            decrypt_fixture(&identities(OTHER_IDENTITY), "connection_string"),
+            Err(DecryptError::NoMatchingIdentity)
+        );
+        assert_eq!(
+            decrypt_fixture(&identities(IDENTITY), "other_recipient"),
+            Err(DecryptError::NoMatchingIdentity)
+        );
+    }
+
+    #[test]
+    fn test_corrupted_values() {
+        let ours = identities(IDENTITY);
+        let encoded = value("connection_string");
+        let data = STANDARD
+            .decode(encoded.strip_prefix(PREFIX).unwrap())
+            .unwrap();
+        let header_end = data.windows(4).position(|w| w == b"\n---").unwrap();
+
+        let corrupt = |at: usize| {
+            let mut data = data.clone();
+            data[at] ^= 1;
+            decrypt(&ours, &data)
+        };
+        // The wrapped file key, the MAC, the payload
+        assert_eq!(
+            corrupt(header_end - 2),
+            Err(DecryptError::NoMatchingIdentity)
+        );
+        assert_eq!(corrupt(header_end + 6), Err(DecryptError::HeaderMac));
+        assert_eq!(corrupt(data.len() - 1), Err(DecryptError::Payload));
+        assert_eq!(
+            decrypt(&ours, &data[..data.len() - 1]),
+            Err(DecryptError::Payload)
+        );
+        assert_eq!(
+            decrypt(&ours, &data[..40]),
+            Err(DecryptError::MalformedHeader)
+        );
+        assert_eq!(decrypt(&ours, b"hello\n"), Err(DecryptError::NotAge));
+        assert_eq!(
+            decrypt_value(&ours, "not base64!"),
+            Err(DecryptError::NotBase64)
+        );
+    }
+
+    #[test]
+    fn test_multiple_chunks() {
+        let ours = identities(IDENTITY);
+        let plaintext: Vec<u8> = (0..CHUNK * 2 + 100).map(|i| i as u8).collect();
+        let data = encrypt(&ours[0], &plaintext);
+        assert_eq!(decrypt(&ours, &data), Ok(plaintext));
+
+        // A file cut at a chunk boundary lacks its last chunk
+        assert_eq!(
+            decrypt(&ours, &data[..data.len() - 100 - TAG_LEN]),
+            Err(DecryptError::Payload)
+        );
+        // Exactly one full chunk
+        let full = vec![b'x'; CHUNK];
+        assert_eq!(decrypt(&ours, &encrypt(&ours[0], &full)), Ok(full));
+    }
+
+    #[test]
+    fn test_decrypt_document() {
+        let ours = identities(IDENTITY);
+        let mut doc = json!({
+            "app_name": "TestApp",
+            "database": { "connection_string": value("connection_string") },
+            "messaging": { "brokers": ["kafka:9092", value("both_recipients")] },
+        });
+        let decrypted = decrypt_document(Path::new("config.json"), &mut doc, &ours).unwrap();
+        let paths: Vec<String> = decrypted.iter().map(|path| path.to_string()).collect();
+        assert_eq!(
+            paths,
+            ["database.connection_string", "messaging.brokers[1]"]
+        );
+        assert_eq!(
+            doc["database"]["connection_string"],
+            "postgres://app:s3cr3t@db/app"
+        );
+        assert_eq!(doc["messaging"]["brokers"][1], "shared secret");
+        assert_eq!(doc["app_name"], "TestApp");
+    }
+
+    #[test]
+    fn test_failures_name_every_field_and_no_value() {
+        let theirs = identities(OTHER_IDENTITY);
+        let mut doc = json!({
+            "database": { "connection_string": value("connection_string") },
+            "proxy": { "http": "age:AAAA", "https": value("other_recipient") },
+        });
+        let error = decrypt_document(Path::new("config.json"), &mut doc, &theirs).unwrap_err();
+        assert_eq!(error.code(), "decryption_failed");
+        let ConfigError::DecryptionFailed { ref failures, .. } = error else {
+            panic!("{error:?}");
+        };
+        let fields: Vec<&str> = failures.iter().map(|(field, _)| field.as_str()).collect();
+        assert_eq!(fields, ["database.connection_string", "proxy.http"]);
+
+        let message = error.to_string();
+        assert!(
+            message.contains("database.connection_string: no identity matches"),
+            "{message}"
+        );
+        assert!(
+            message.contains("proxy.http: not an age-encrypted file"),
+            "{message}"
+        );
+        assert!(!message.contains("postgres"), "{message}");
+        assert!(
+            !message.contains(&value("connection_string")[10..30]),
+            "{message}"
+        );
+    }
+}
@@ -0,0 +1,124 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`str::replace` chains**: Enough for the small escaping tables below
+- **`Option::map_or`**: Optional parts of a line
+
+**Design decisions**:
+- Findings are printed as GitHub Actions workflow commands, e.g.
+  `::error file=config.json,line=14,col=22::server.port: ...`, which GitHub
+  turns into inline annotations on the pull request
+- Positions are not known for every finding; those are pinned to line 1 so
+  the annotation still lands on the right file
+- Escaping follows the workflow-command rules: `%`, CR and LF in the
+  message; additionally `:` and `,` in property values
+
+******************************************************************************/
+
+use crate::validation::{Finding, Severity};
+use clap::ValueEnum;
+use std::path::Path;
+
+/// Values of `--output-annotations`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnnotationFormat {
+    /// No annotations, even on GitHub Actions
+    None,
+    /// GitHub Actions workflow commands
+    Github,
+}
+
+impl AnnotationFormat {
+    /// The requested format, or GitHub when running under GitHub Actions
+    pub fn resolve(requested: Option<Self>, github_actions: Option<&str>) -> Self {
+        match (requested, github_actions) {
+            (Some(format), _) => format,
+            (None, Some("true")) => AnnotationFormat::Github,
+            (None, _) => AnnotationFormat::None,
+        }
+    }
+}
+
+/// One finding as a GitHub workflow command
+pub fn github(file: &Path, finding: &Finding) -> String {
+    let command = match finding.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    github_command(
+        command,
+        file,
+        finding.line,
+        finding.column,
+        &finding.to_string(),
+    )
+}
+
+/// A workflow command for `file`; the line defaults to 1
+pub fn github_command(
+    command: &str,
+    file: &Path,
+    line: Option<usize>,
+    column: Option<usize>,
+    message: &str,
+) -> String {
+    format!(
+        "::{command} file={},line={}{}::{}",
+        escape_property(&file.display().to_string()),
+        line.unwrap_or(1),
+        column.map_or(String::new(), |column| format!(",col={column}")),
+        escape_data(message)
+    )
+}
+
+/// Escapes the message part of a workflow command
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property value of a workflow command
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_and_escaping() {
+        let finding = Finding {
+            severity: Severity::Error,
+            path: "server.port".to_string(),
+            message: "invalid type: 100%\nbad".to_string(),
+            line: Some(14),
+            column: Some(22),
+        };
+        assert_eq!(
+            github(Path::new("a,b:c.json"), &finding),
+            "::error file=a%2Cb%3Ac.json,line=14,col=22::server.port: invalid type: 100%25%0Abad"
+        );
+
+        let finding = Finding {
+            severity: Severity::Warning,
+            line: None,
+            column: None,
+            ..finding
+        };
+        assert!(
+            github(Path::new("c.json"), &finding).starts_with("::warning file=c.json,line=1::")
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        use AnnotationFormat as F;
+        assert_eq!(F::resolve(None, Some("true")), F::Github);
+        assert_eq!(F::resolve(None, Some("false")), F::None);
+        assert_eq!(F::resolve(None, None), F::None);
+        assert_eq!(F::resolve(Some(F::None), Some("true")), F::None);
+        assert_eq!(F::resolve(Some(F::Github), None), F::Github);
+    }
+}
@@ -0,0 +1,1589 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`tokio::runtime::Runtime`**: Built explicitly in `main`, once
+  `--daemonize` has forked and `--sandbox` is up
+- **`tokio::select!`**: Runs multiple futures concurrently, proceeds with first to complete
+- **Signal streams**: Ctrl+C and SIGTERM (CTRL_CLOSE on Windows) are
+  streams, still listened for once the first one started the shutdown
+- **`anyhow::Result`**: Top-level error type for applications
+- **`ExitCode`**: Subcommands decide the process exit status; errors that
+  reach `main` are mapped through `exit::for_error`
+- **`futures::future::try_join_all`**: Drives one watch loop per file
+
+**Design decisions**:
+- Graceful shutdown on Ctrl+C or SIGTERM (CTRL_CLOSE or a service stop on
+  Windows) using `tokio::select!`; what must be undone on the way out
+  (locks, `--pid-file`, audit log) is held in guards, dropped on every
+  return path of `watch`, errors included. The cleanup gets
+  --shutdown-timeout, then is abandoned (`shutdown::Deadline`); a second
+  Ctrl+C or SIGTERM abandons it at once. Either way the pid and status
+  files are still removed, best effort
+- `--daemonize` forks before anything else (`daemon`); the daemon keeps
+  handling SIGHUP and SIGTERM like a foreground process
+- SIGHUP reloads every file at once (Unix); under systemd the emitter also
+  reports readiness and status through `sd_notify`. SIGUSR1 prints a
+  status dump (`dump`), SIGUSR2 moves to the next verbosity, the log
+  filter along with it
+- In a terminal, single keys do the same (`keys`): `r` reloads, `d` dumps,
+  `p` pauses the checks, `q` stops like Ctrl+C
+- Contextual error messages throughout
+- Clean separation of concerns (CLI, logic, errors)
+- The command line lives in the library, behind `config_watcher::run`:
+  `main.rs` only calls it, so the modules stay private to the crate
+- Watch options may come from a settings file (`settings`); flags and
+  `CONFIG_WATCHER_*` variables win over it
+- Watch output goes through an `Emitter`, in text or JSON (`--output`);
+  diagnostics go through `tracing` (`--log-level`, `RUST_LOG`,
+  `--log-format`)
+
+******************************************************************************/
+
+use crate::audit::AuditLog;
+use crate::autocommit::GitAutocommit;
+use crate::cli::{
+    Cli, Command, OutputFormat, PermissionLevel, ServiceCommand, TimestampFormat, WatchArgs,
+};
+use crate::commands;
+use crate::confirm::Confirm;
+use crate::daemon;
+use crate::discovery;
+use crate::dump::DumpRequests;
+use crate::error::ConfigError;
+use crate::exit;
+use crate::export_env::ExportEnv;
+use crate::external_schema::ExternalSchema;
+use crate::git::{GitError, WorkTree};
+use crate::hook::{OnChange, OnChangeNotifier};
+use crate::instance_lock::{self, InstanceLock};
+use crate::keys::{self, Action, RawMode};
+use crate::logging::{self, LogFormat, LogOptions, LogTarget};
+use crate::metrics::{self, Metrics};
+use crate::normalized::WriteNormalized;
+use crate::notify::Notifiers;
+use crate::output::{Emitter, Event, EventLog, ShutdownReason};
+use crate::overrides::Overrides;
+use crate::permissions::PermissionAudit;
+use crate::pid_file::PidFile;
+use crate::probes::Probes;
+use crate::proxy::CurrentProxy;
+use crate::sandbox::{self, Access, Enforcement};
+use crate::settings::{self, Settings};
+use crate::shutdown::{Abandoned, Deadline};
+use crate::signal_pid::Target;
+use crate::signature::TrustedKey;
+use crate::slack::{SlackNotifier, Templates};
+use crate::status::{StatusBoard, StatusFile};
+use crate::style::Style;
+use crate::supervisor::{Ended, Supervisor, SupervisorConfig};
+use crate::timestamp::{self, Timestamps};
+use crate::tui::{self, Dashboard, Feed};
+use crate::validation::Severity;
+use crate::watcher::{ConfigWatcher, Pause, ReloadRequests};
+use crate::webhook::{Webhook, WebhookConfig};
+use anyhow::Context;
+use clap::ArgMatches;
+use futures::future::try_join_all;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio::signal;
+use tokio::sync::mpsc;
+
+pub(crate) fn main() -> ExitCode {
+    // Parse command-line arguments (clap exits with code 2 on usage errors)
+    let (mut cli, matches) = Cli::parse_with_matches();
+    // The service control manager calls the watch back on a thread of its own
+    #[cfg(all(windows, feature = "windows-service"))]
+    if let Some(Command::Service(ServiceCommand::Run(ref args))) = cli.command {
+        let (name, directory) = (args.service.name.clone(), args.directory.clone());
+        return service(name, directory, cli, matches);
+    }
+    // fork(2) only copies the calling thread: --daemonize goes first, while
+    // it is the only one
+    let daemon = daemonize(&mut cli);
+    // Landlock confines the calling thread and the threads it starts
+    // afterwards: --sandbox goes up before the runtime starts its workers
+    let sandbox = sandbox(&cli);
+    start(cli, matches, daemon, sandbox)
+}
+
+/// Runs the command on a runtime of its own; a failure is reported and
+/// mapped to its exit code
+fn start(
+    cli: Cli,
+    matches: ArgMatches,
+    daemon: anyhow::Result<()>,
+    sandbox: anyhow::Result<Option<Enforcement>>,
+) -> ExitCode {
+    let runtime = tokio::runtime::Runtime::new().expect("cannot start the tokio runtime");
+    runtime.block_on(async {
+        let code = match run(cli, matches, daemon, sandbox).await {
+            Ok(code) => code,
+            Err(e) => {
+                logging::fatal(&e);
+                let code = exit::for_error(&e);
+                // The process that was started exits with it
+                daemon::failed(code, &format!("{e:#}"));
+                ExitCode::from(code)
+            }
+        };
+        logging::shutdown();
+        code
+    })
+}
+
+/// `service run`: the watch runs once the service control manager calls it
+/// back, in `directory`, and reports how it ended
+#[cfg(all(windows, feature = "windows-service"))]
+fn service(name: String, directory: Option<PathBuf>, cli: Cli, matches: ArgMatches) -> ExitCode {
+    use crate::service::{self, ExitStatus};
+
+    let dispatched = service::dispatch(&name, move || {
+        if let Some(directory) = directory
+            && let Err(e) = std::env::set_current_dir(directory)
+        {
+            return ExitStatus::from_io(&e);
+        }
+        let code = start(cli, matches, Ok(()), Ok(None));
+        // `ExitCode` does not give its value back
+        let code = (0..=u8::MAX)
+            .find(|&n| ExitCode::from(n) == code)
+            .unwrap_or(exit::INTERNAL);
+        ExitStatus::from_code(code)
+    });
+    match dispatched {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!(
+                "Error: `service run` is started by the service control manager; register it with `service install` ({e})"
+            );
+            ExitCode::from(exit::USAGE)
+        }
+    }
+}
+
+/// Detaches from the terminal for --daemonize; only the daemon returns
+///
+/// Runs before logging is set up: the caller reports the outcome. The log
+/// file is made absolute, as the daemon leaves its working directory.
+fn daemonize(cli: &mut Cli) -> anyhow::Result<()> {
+    let daemonize = match cli.command {
+        None => cli.watch.daemonize,
+        Some(Command::Watch(ref args)) => args.daemonize,
+        Some(Command::Run(ref args)) if args.watch.daemonize => {
+            anyhow::bail!("--daemonize is not supported by run");
+        }
+        Some(_) => false,
+    };
+    if !daemonize {
+        return Ok(());
+    }
+    let Some(ref log_file) = cli.log_file else {
+        anyhow::bail!("--daemonize needs --log-file, where its output goes");
+    };
+    let log_file = std::path::absolute(log_file).context("Invalid --log-file")?;
+    daemon::detach(&log_file, Style::detect(cli.watch.color))?;
+    cli.log_file = Some(log_file);
+    Ok(())
+}
+
+/// Confines the process for --sandbox, to the paths of the watch options
+///
+/// Runs before logging is set up: the caller reports the outcome.
+fn sandbox(cli: &Cli) -> anyhow::Result<Option<Enforcement>> {
+    let args = match cli.command {
+        None => &cli.watch,
+        Some(Command::Watch(ref args)) => args,
+        Some(Command::Run(ref args)) => &args.watch,
+        Some(_) => return Ok(None),
+    };
+    if !args.sandbox {
+        return Ok(None);
+    }
+    let files = if args.from_env.is_some() {
+        Vec::new()
+    } else if args.config_file.is_empty() {
+        discovery::discover_default().into_iter().collect()
+    } else {
+        args.config_file.clone()
+    };
+    let settings_file = settings::locate_default();
+    let mut sandbox = sandbox::plan(
+        args,
+        &files,
+        settings_file.as_deref(),
+        cli.log_file.as_deref(),
+    );
+    // A schema named by the settings file, which `watch` applies later
+    if args.schema.is_none()
+        && let Some(schema) = settings_file
+            .as_deref()
+            .and_then(|path| Settings::load(path).ok())
+            .and_then(|(settings, _)| settings.schema)
+    {
+        sandbox.allow(&schema, Access::Read);
+    }
+    // The zone files cannot be read once confined
+    if timestamp::shows_local(text_timestamps(args), args.utc) || (args.tui && !args.utc) {
+        let _ = timestamp::local_zone();
+    }
+    let enforcement = sandbox.enforce().context("Cannot set up --sandbox")?;
+    Ok(Some(enforcement))
+}
+
+/// Dispatches the subcommand
+async fn run(
+    cli: Cli,
+    matches: ArgMatches,
+    daemon: anyhow::Result<()>,
+    sandbox: anyhow::Result<Option<Enforcement>>,
+) -> anyhow::Result<ExitCode> {
+    // The `proxy` section loaded last, for the outbound HTTP clients
+    let proxy = CurrentProxy::default();
+    let log = LogOptions {
+        level: cli.log_level,
+        format: cli.log_format,
+        file: cli.log_file.as_ref().map(|path| logging::FileTarget {
+            path: path.clone(),
+            max_size: cli.log_max_size,
+            keep: cli.log_keep,
+        }),
+        target: cli.log_target,
+        facility: cli.syslog_facility,
+        otlp: cli
+            .otlp_endpoint
+            .as_ref()
+            .map(|endpoint| logging::OtlpTarget {
+                endpoint: endpoint.clone(),
+                metrics: Metrics::default(),
+                proxy: proxy.clone(),
+            }),
+    };
+    logging::init(&log).map_err(exit::usage)?;
+    daemon.map_err(exit::usage)?;
+    match sandbox.map_err(exit::usage)? {
+        Some(Enforcement::Enforced { abi }) => tracing::info!(abi, "sandbox enforced"),
+        Some(Enforcement::Unsupported { reason }) => {
+            tracing::warn!("--sandbox is not enforced: {reason}");
+        }
+        None => {}
+    }
+    let event_log = match (log.target, log.format) {
+        (LogTarget::Stderr, LogFormat::Text) => EventLog::Off,
+        (LogTarget::Stderr, LogFormat::Json) => EventLog::Failures,
+        (LogTarget::Syslog | LogTarget::Journald, _) => EventLog::All,
+    };
+    // No console under the service control manager: events go to the log
+    let event_log = if under_service_manager() {
+        EventLog::All
+    } else {
+        event_log
+    };
+
+    // Subcommands other than `watch` print through it; `watch` detects its
+    // own once the settings file had its say
+    let style = Style::detect(cli.watch.color);
+    match cli.into_command() {
+        Command::Watch(args) => {
+            let metrics = log.otlp.map(|otlp| otlp.metrics);
+            watch(
+                *args,
+                Cli::watch_matches(&matches),
+                event_log,
+                metrics,
+                proxy,
+                None,
+            )
+            .await
+        }
+        Command::Run(args) => {
+            args.validate()
+                .context("Invalid command-line arguments")
+                .map_err(exit::usage)?;
+            let metrics = log.otlp.map(|otlp| otlp.metrics);
+            let supervisor = args.supervisor();
+            let matches = Cli::watch_matches(&matches);
+            watch(
+                args.watch,
+                matches,
+                event_log,
+                metrics,
+                proxy,
+                Some(supervisor),
+            )
+            .await
+        }
+        Command::Validate(args) => commands::validate::run(&args, style),
+        Command::Lint(args) => commands::lint::run(&args),
+        Command::Get(args) => commands::get::run(&args),
+        Command::Show(args) => commands::show::run(&args),
+        Command::Set(args) => commands::set::run(&args, style),
+        Command::Rollback(args) => commands::rollback::run(&args, style),
+        Command::Patch(args) => commands::patch::run(&args, style),
+        Command::Explain(args) => commands::explain::run(&args),
+        Command::Diff(args) => commands::diff::run(&args),
+        Command::ExplainSource(args) => commands::explain_source::run(&args),
+        Command::Doctor(args) => commands::doctor::run(&args, style).await,
+        Command::Schema(args) => commands::schema::run(&args, style),
+        Command::Docs(args) => commands::docs::run(&args, style),
+        Command::Healthcheck(args) => commands::healthcheck::run(&args),
+        Command::Service(ServiceCommand::Run(args)) if under_service_manager() => {
+            let metrics = log.otlp.map(|otlp| otlp.metrics);
+            let matches = Cli::watch_matches(&matches);
+            watch(args.watch, matches, event_log, metrics, proxy, None).await
+        }
+        Command::Service(command) => commands::service::run(&command, style),
+        Command::Completions(args) => commands::completions::run(&args),
+        Command::Complete(args) => commands::completions::run_helper(&args),
+    }
+}
+
+/// Runs the watch loop until Ctrl+C, supervising the command of `run`
+async fn watch(
+    mut args: WatchArgs,
+    matches: &ArgMatches,
+    event_log: EventLog,
+    otlp_metrics: Option<Metrics>,
+    proxy: CurrentProxy,
+    supervise: Option<SupervisorConfig>,
+) -> anyhow::Result<ExitCode> {
+    // Declared first, dropped last: it covers every guard below
+    let mut deadline = Deadline::new(args.shutdown_timeout);
+    // Settings file, below flags and environment variables
+    let settings_file = settings::locate_default();
+    let (settings, warnings) = match settings_file {
+        Some(ref path) => Settings::load(path).map_err(exit::usage)?,
+        None => Default::default(),
+    };
+    for warning in warnings {
+        tracing::warn!("{warning}");
+    }
+    let origins = settings.apply(&mut args, matches).map_err(exit::usage)?;
+    if args.show_settings {
+        print!(
+            "{}",
+            settings::render(&args, &origins, settings_file.as_deref())
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // Validate arguments
+    args.validate()
+        .context("Invalid command-line arguments")
+        .map_err(exit::usage)?;
+    // Before anything is opened by path: the daemon leaves its directory
+    if args.daemonize {
+        args.make_paths_absolute()
+            .context("Cannot resolve the paths for --daemonize")?;
+    }
+
+    // One set of counters for /metrics, the OTLP exporter and the notifiers
+    let metrics = otlp_metrics.or_else(|| args.metrics_addr.map(|_| Metrics::default()));
+
+    let emitter = Emitter::new(args.output)
+        .with_style(Style::detect(args.color))
+        .with_verbosity(args.verbosity())
+        .with_list_threshold(args.list_limit)
+        .with_timestamps(Timestamps::new(text_timestamps(&args), args.utc))
+        .with_error_format(args.error_format)
+        .with_event_log(event_log);
+    // The guard writes the pending audit entries when dropped, on every
+    // return path
+    let (emitter, _audit_guard) = match args.audit_log {
+        Some(ref path) => {
+            let (audit, guard) = AuditLog::open(path, args.audit_max_size, args.audit_keep)
+                .with_context(|| format!("Cannot open audit log {}", path.display()))
+                .map_err(exit::usage)?;
+            (emitter.with_audit_log(audit), Some(guard))
+        }
+        None => (emitter, None),
+    };
+    let (emitter, _event_db_guard) = match args.event_db {
+        Some(ref path) => event_db(emitter, path, args.event_db_keep)?,
+        None => (emitter, None),
+    };
+
+    // Files given on the command line, or the discovered default
+    let files = if args.from_env.is_some() {
+        Vec::new()
+    } else if args.config_file.is_empty() {
+        let path = discovery::discover_default()?;
+        emitter.emit(&Event::Discovered { file: &path });
+        vec![path]
+    } else {
+        args.config_file.clone()
+    };
+    let files = if args.daemonize {
+        let files = files
+            .iter()
+            .map(std::path::absolute)
+            .collect::<Result<Vec<_>, _>>()
+            .context("Cannot resolve the paths for --daemonize")?;
+        daemon::leave_working_directory().context("Cannot change to /")?;
+        files
+    } else {
+        files
+    };
+
+    // Nobody to answer: refused before anything starts
+    let confirm = if args.confirm {
+        let confirm = Confirm::stdio().map_err(exit::usage)?;
+        Some(match args.confirm_timeout {
+            Some(timeout) => confirm.with_timeout(timeout, args.on_confirm_timeout),
+            None => confirm,
+        })
+    } else {
+        None
+    };
+
+    // One instance per file: the locks are released when `watch` returns
+    let _locks = if args.no_lock || args.once {
+        Vec::new()
+    } else {
+        let dir = match args.lock_dir {
+            Some(ref dir) => dir.clone(),
+            None => instance_lock::default_dir(args.state_dir.as_deref())
+                .context("Cannot prepare the lock directory")?,
+        };
+        files
+            .iter()
+            .map(|file| InstanceLock::acquire(file, &dir))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    // Removed when `watch` returns, whichever way, before the locks
+    let pid_file = args.pid_file.as_deref().map(PidFile::create).transpose()?;
+    // The process started with --daemonize exits 0 from here on
+    daemon::ready();
+
+    // A no-op unless started by systemd with NOTIFY_SOCKET set
+    let watchers = files.len() + usize::from(args.from_env.is_some());
+    // The loop heartbeats behind the probes and the systemd watchdog
+    let probes = Probes::new(Duration::from_secs(args.interval()))
+        .with_max_failures(args.ready_max_failures);
+    let emitter = systemd(emitter, watchers, args.require_initial, &probes);
+    // Running once every watcher made its first attempt, under Windows
+    let emitter = service_status(emitter, watchers);
+    // --tui: the text output goes to the dashboard once it shows
+    let feed = tui_feed(&args);
+    let emitter = match feed {
+        Some(ref feed) => emitter.with_feed(feed.clone()),
+        None => emitter,
+    };
+    // SIGHUP and RELOAD on the push socket
+    let reload_requests = ReloadRequests::new(emitter.clone());
+
+    // Overrides: environment first, so the command line wins
+    let mut overrides = Overrides::from_env(std::env::vars()).map_err(exit::usage)?;
+    overrides.extend(
+        Overrides::from_args(&args.overrides, &args.override_strings)
+            .context("Invalid --override")
+            .map_err(exit::usage)?,
+    );
+
+    // A key that does not parse is a startup error, not a failed load
+    let trusted_key = args
+        .public_key
+        .as_deref()
+        .map(TrustedKey::load)
+        .transpose()?;
+    // Unit without the `age` feature, still checked for --age-identity
+    #[cfg_attr(not(feature = "age"), allow(clippy::let_unit_value))]
+    let age_identities = age_identities(&args.age_identity)?;
+    let permission_audit = permission_audit(&args);
+
+    // Create one watcher per file, or a single one for --from-env
+    let labels = labels(&files);
+    let mut sources: Vec<ConfigWatcher> = files
+        .iter()
+        .zip(labels)
+        .map(|(file, label)| {
+            let watcher = ConfigWatcher::new(file, args.interval()).with_emitter(emitter.clone());
+            if files.len() > 1 {
+                watcher.with_label(label)
+            } else {
+                watcher
+            }
+        })
+        .collect();
+    if let Some(ref prefix) = args.from_env {
+        sources
+            .push(ConfigWatcher::from_env(prefix, args.interval()).with_emitter(emitter.clone()));
+    }
+
+    // Notifiers get the events of every watcher. --on-change reports
+    // through its watcher's emitter, which has no notifiers
+    let mut notifiers = Notifiers::new();
+    if let Some(ref url) = args.webhook_url {
+        notifiers = notifiers.with(Webhook::new(WebhookConfig {
+            secret: args.webhook_secret.clone(),
+            timeout: args.webhook_timeout,
+            proxy: proxy.clone(),
+            ..WebhookConfig::new(url.clone(), args.webhook_events.clone())
+        }));
+    }
+    if let Some(ref url) = args.slack_webhook_url {
+        let templates = match args.slack_template {
+            Some(ref path) => Templates::load(path).map_err(exit::usage)?,
+            None => Templates::default(),
+        };
+        notifiers = notifiers.with(
+            SlackNotifier::new(url.clone(), args.slack_channel.clone(), templates)
+                .with_proxy(proxy.clone()),
+        );
+    }
+    if args.notify_desktop {
+        notifiers = desktop(notifiers, args.notify_interval)?;
+    }
+    if let Some(ref url) = args.mqtt_url {
+        notifiers = mqtt(notifiers, url, &args)?;
+    }
+    if let Some(ref url) = args.redis_url {
+        notifiers = redis(notifiers, url, &args)?;
+    }
+    if let Some(ref path) = args.export_env {
+        notifiers = notifiers.with(ExportEnv {
+            format: args.export_env_format,
+            prefix: args.export_env_prefix.clone(),
+            secrets: args.export_env_secrets,
+            ..ExportEnv::new(path)
+        });
+    }
+    if let Some(ref path) = args.write_normalized {
+        notifiers = notifiers.with(WriteNormalized {
+            redacted: args.write_normalized_redacted,
+            ..WriteNormalized::new(path)
+        });
+    }
+    if let Some(target) = signal_target(&args) {
+        notifiers = signal_pid(notifiers, target, &args, &emitter)?;
+    }
+    // Started by the first valid configuration
+    let mut supervisor = supervise.map(Supervisor::spawn);
+    if let Some(ref supervisor) = supervisor {
+        notifiers = notifiers.with(supervisor.notifier());
+    }
+    let (notifiers, events) = event_stream(notifiers, &args);
+    let (notifiers, grpc_server) = grpc_server(notifiers, &args).await?;
+    // Removes the socket file when dropped, i.e. when the watch ends
+    let (mut notifiers, _push_socket) = match args.push_socket {
+        Some(ref path) => push_socket(notifiers, path, &args, &reload_requests).await?,
+        None => (notifiers, None),
+    };
+    if let Some(ref command) = args.on_change {
+        for source in &sources {
+            notifiers = notifiers.with(OnChangeNotifier::new(
+                OnChange::new(command, args.on_change_timeout),
+                source.file_path(),
+                source.emitter().clone(),
+            ));
+        }
+    }
+    if args.git_autocommit {
+        for source in &sources {
+            notifiers = git_autocommit(notifiers, source.file_path())?;
+        }
+    }
+    if let Some(limit) = args.notify_rate_limit {
+        notifiers = notifiers.with_rate_limit(limit);
+    }
+    if let Some(ref metrics) = metrics {
+        notifiers = notifiers.with_metrics(metrics.clone());
+    }
+    // Handles what is queued when dropped, after the shutdown event
+    let (dispatcher, notifier_guard) = if notifiers.is_empty() {
+        (None, None)
+    } else {
+        let (dispatcher, guard) = notifiers
+            .start()
+            .context("Cannot start the notifier thread")?;
+        (Some(dispatcher), Some(guard))
+    };
+    let emitter = match dispatcher {
+        Some(ref dispatcher) => emitter.with_notifiers(dispatcher.clone()),
+        None => emitter,
+    };
+
+    let status_file = args.status_file.as_ref().map(|path| {
+        StatusFile::new(path, Duration::from_secs(args.interval())).with_mode(args.status_file_mode)
+    });
+    if let (Some(addr), Some(metrics)) = (args.metrics_addr, &metrics) {
+        let bound = metrics::serve(addr, metrics.clone())
+            .await
+            .with_context(|| format!("Failed to serve metrics on {addr}"))
+            .map_err(exit::usage)?;
+        tracing::info!(addr = %bound, "serving metrics");
+    }
+    // Fed in any case, for the status dumps
+    let status_board = StatusBoard::new(Duration::from_secs(args.interval()));
+    let dump_requests = DumpRequests::new(
+        emitter.clone(),
+        status_board.clone(),
+        settings::effective(&args, &origins),
+    );
+    // Closed when the watch ends, or stopped when dropped on an error
+    let board = args.http_addr.map(|_| status_board.clone());
+    let http_servers = http_servers(&args, &files, &board, &probes, events, &dump_requests).await?;
+    // `p` in the terminal
+    let pause = Pause::new(emitter.clone());
+    let mut watchers = Vec::new();
+    for watcher in sources {
+        let mut watcher = watcher
+            .with_overrides(overrides.clone())
+            .with_reload_requests(&reload_requests)
+            .with_pause(&pause);
+        if let Some(ref status_file) = status_file {
+            watcher = watcher.with_status_file(status_file.clone());
+        }
+        watcher = watcher
+            .with_status_board(status_board.clone())
+            .with_proxy(proxy.clone());
+        let probe = probes.register(watcher.file_path());
+        watcher = watcher.with_probe(probe);
+        if let Some(ref metrics) = metrics {
+            watcher = watcher.with_metrics(metrics.clone());
+        }
+        if let Some(ref schema) = args.schema {
+            let schema = ExternalSchema::load(schema).context("Failed to compile JSON Schema")?;
+            watcher = watcher.with_schema(schema);
+        }
+        if args.verify_checksum {
+            watcher = watcher.with_checksum();
+        }
+        if let Some(ref key) = trusted_key {
+            watcher = watcher.with_signature_key(key.clone());
+        }
+        watcher = with_age_identities(watcher, &age_identities);
+        if let Some(ref audit) = permission_audit {
+            watcher = watcher.with_permission_audit(audit.clone());
+        }
+        if args.reread_env {
+            watcher = watcher.with_env_reread();
+        }
+        if args.fail_fast {
+            watcher = watcher.with_fail_fast();
+        }
+        if let Some(ref fallback) = args.fallback_config {
+            watcher = watcher.with_fallback(fallback);
+        }
+        if let Some(ref dir) = args.state_dir {
+            watcher = watcher.with_state_dir(dir);
+        }
+        if let Some(keep) = args.keep_versions {
+            watcher = watcher.with_keep_versions(keep as usize);
+        }
+        if args.promote_next {
+            watcher = watcher
+                .with_promote_next()
+                .with_backups(args.backup.backups());
+        }
+        if let Some(ref confirm) = confirm {
+            watcher = watcher.with_confirm(confirm.clone());
+        }
+        if let Some(max_duration) = args.max_duration {
+            watcher = watcher.with_max_duration(max_duration);
+        }
+        if let Some(heartbeat) = args.heartbeat {
+            watcher = watcher.with_heartbeat(heartbeat);
+        }
+        if let Some(ref dispatcher) = dispatcher {
+            watcher = watcher.with_notifiers(dispatcher.clone());
+        }
+        watchers.push(watcher);
+    }
+
+    // --once: the initial load only, no ticker and no signal handling.
+    // --from-env without --interval behaves the same
+    if args.once || (args.from_env.is_some() && args.interval.is_none()) {
+        // The most severe failure decides the exit status
+        let mut status = exit::SUCCESS;
+        for watcher in &mut watchers {
+            if !watcher.load_initial().await? {
+                let code = watcher.last_error_code().unwrap_or("other");
+                status = status.max(exit::for_code(code));
+            }
+        }
+        return Ok(ExitCode::from(status));
+    }
+
+    let mut interrupts = Interrupts::listen()?;
+    let dashboard = feed.map(|feed| Dashboard::new(status_board.clone(), feed, pause.clone()));
+    // Back to the normal screen once the watch stops
+    let shown = dashboard
+        .as_ref()
+        .map(Dashboard::show)
+        .transpose()
+        .context("Cannot show the dashboard")?;
+    // Back to its modes when `watch` returns, whichever way
+    let raw_mode = read_keys(
+        &args,
+        &mut interrupts,
+        &reload_requests,
+        dump_requests.clone(),
+        pause,
+        dashboard,
+    )?;
+    reload_on_sighup(reload_requests)?;
+    dump_on_sigusr1(dump_requests)?;
+    cycle_verbosity_on_sigusr2(emitter.clone())?;
+
+    // Setup graceful shutdown
+    // This uses tokio::select! to race between watch loops and Ctrl+C
+    let outcome = tokio::select! {
+        result = try_join_all(watchers.iter_mut().map(|watcher| watcher.watch())) => {
+            // Watch loops only end on error or when --max-duration elapsed
+            match result {
+                Ok(_) => Ok((ShutdownReason::MaxDuration, None)),
+                Err(e) if matches!(e.downcast_ref(), Some(ConfigError::ReloadFailed { .. })) => {
+                    Ok((ShutdownReason::FailFast, Some(e)))
+                }
+                Err(e) => Err(e.context("Watcher error")),
+            }
+        }
+        ended = supervised_exit(&mut supervisor) => {
+            let error = match ended {
+                Ended::Exited(status) if status.success() => None,
+                Ended::Exited(status) => Some(anyhow::anyhow!("Command {status}")),
+                Ended::Stopped => Some(anyhow::anyhow!("Command supervision failed")),
+            };
+            Ok((ShutdownReason::ChildExited, error))
+        }
+        _ = stop_requested(&mut interrupts) => Ok((ShutdownReason::Signal, None)),
+    };
+    // Past --shutdown-timeout, or on the next signal, what is left of the
+    // cleanup is abandoned; these files are still removed
+    let leftovers = Leftovers {
+        pid_file: pid_file
+            .as_ref()
+            .map(|pid_file| pid_file.path().to_path_buf()),
+        status_file: status_file
+            .clone()
+            .filter(|_| matches!(outcome, Ok((reason, _)) if reason != ShutdownReason::FailFast)),
+    };
+    let timeout = args.shutdown_timeout;
+    drop(shown);
+    let force = deadline.start(move |why, stage| abandon(why, timeout, stage, leftovers));
+    tokio::spawn(async move {
+        interrupts.recv().await;
+        force.now();
+    });
+    if matches!(outcome, Ok((ShutdownReason::Signal, _))) && std::io::stderr().is_terminal() {
+        let force = match raw_mode {
+            Some(_) => "Ctrl+C or q",
+            None => "Ctrl+C",
+        };
+        emitter.emit(&Event::Stopping { force });
+    }
+
+    // The command stops before the watcher reports its own shutdown
+    deadline.stage("stopping the command");
+    if let Some(supervisor) = supervisor {
+        supervisor.stop().await;
+    }
+    let (reason, error) = outcome?;
+
+    let files: Vec<_> = watchers.iter().map(ConfigWatcher::status).collect();
+    emitter.emit(&Event::Shutdown {
+        reason,
+        error: error.as_ref(),
+        files: &files,
+    });
+
+    // A status file left behind means the process did not stop cleanly
+    deadline.stage("removing the status file");
+    if let Some(ref status_file) = status_file
+        && reason != ShutdownReason::FailFast
+        && let Err(e) = status_file.remove()
+    {
+        tracing::warn!(status_file = %status_file.path().display(), error = %e, "status file not removed");
+    }
+    // The shutdown event reaches the WebSocket clients before their close
+    deadline.stage("draining the notifier queue");
+    drop(notifier_guard);
+    deadline.stage("closing the HTTP servers");
+    close_http_servers(http_servers).await;
+    deadline.stage("closing the gRPC server");
+    close_grpc_server(grpc_server).await;
+    // The guards, dropped on return
+    deadline.stage("writing the audit log and event history, removing the pid file");
+
+    Ok(match reason {
+        ShutdownReason::Signal => ExitCode::SUCCESS,
+        ShutdownReason::MaxDuration => ExitCode::from(args.max_duration_exit_code),
+        ShutdownReason::FailFast => ExitCode::from(exit::FAIL_FAST),
+        ShutdownReason::ChildExited if error.is_some() => ExitCode::from(exit::CHILD_FAILED),
+        ShutdownReason::ChildExited => ExitCode::SUCCESS,
+    })
+}
+
+/// What an abandoned cleanup still removes
+struct Leftovers {
+    pid_file: Option<PathBuf>,
+    /// None when it is meant to stay (--fail-fast, an error)
+    status_file: Option<StatusFile>,
+}
+
+/// Ends the process when the cleanup outlasted --shutdown-timeout or was
+/// forced by a second signal, naming the stage it was stuck in
+fn abandon(why: Abandoned, timeout: Duration, stage: &str, leftovers: Leftovers) {
+    let code = match why {
+        Abandoned::TimedOut => {
+            tracing::error!(
+                abandoned = stage,
+                "shutdown did not finish within --shutdown-timeout {}, exiting",
+                humantime::format_duration(timeout)
+            );
+            exit::SHUTDOWN_TIMEOUT
+        }
+        Abandoned::Forced => {
+            tracing::error!(
+                abandoned = stage,
+                "shutdown forced by a second signal, exiting"
+            );
+            exit::FORCED_SHUTDOWN
+        }
+    };
+    if let Some(status_file) = leftovers.status_file
+        && let Err(e) = status_file.remove()
+    {
+        tracing::warn!(status_file = %status_file.path().display(), error = %e, "status file not removed");
+    }
+    if let Some(pid_file) = leftovers.pid_file {
+        PidFile::remove(&pid_file);
+    }
+    keys::restore();
+    logging::shutdown();
+    std::process::exit(code.into());
+}
+
+/// Resolves when the command supervised by `run` ended for good; never
+/// without one
+async fn supervised_exit(supervisor: &mut Option<Supervisor>) -> Ended {
+    match supervisor {
+        Some(supervisor) => supervisor.exited().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// The audit of --permission-audit, which also covers the --age-identity
+/// files; None when it is off
+fn permission_audit(args: &WatchArgs) -> Option<PermissionAudit> {
+    let severity = match args.permission_audit {
+        PermissionLevel::Error => Severity::Error,
+        PermissionLevel::Warn => Severity::Warning,
+        PermissionLevel::Off => return None,
+    };
+    let audit = PermissionAudit::new(severity);
+    Some(args.age_identity.iter().fold(audit, |audit, file| {
+        audit.with_secret_file("--age-identity", file)
+    }))
+}
+
+/// Loads the identities of every --age-identity file; one that does not
+/// parse is a startup error, not a failed load
+#[cfg(feature = "age")]
+fn age_identities(files: &[PathBuf]) -> anyhow::Result<Vec<crate::age::Identity>> {
+    let mut identities = Vec::new();
+    for file in files {
+        let loaded = crate::age::load(file)?;
+        let recipients: Vec<String> = loaded.iter().map(|identity| identity.recipient()).collect();
+        tracing::info!(
+            file = %file.display(),
+            recipients = recipients.join(","),
+            "age identities loaded"
+        );
+        identities.extend(loaded);
+    }
+    Ok(identities)
+}
+
+/// Without the `age` feature, there is nothing to decrypt with
+#[cfg(not(feature = "age"))]
+fn age_identities(files: &[PathBuf]) -> anyhow::Result<()> {
+    if files.is_empty() {
+        return Ok(());
+    }
+    Err(exit::usage(anyhow::anyhow!(
+        "--age-identity needs a build with the `age` feature"
+    )))
+}
+
+#[cfg(feature = "age")]
+fn with_age_identities(
+    watcher: ConfigWatcher,
+    identities: &[crate::age::Identity],
+) -> ConfigWatcher {
+    if identities.is_empty() {
+        return watcher;
+    }
+    watcher.with_age_identities(identities.to_vec())
+}
+
+#[cfg(not(feature = "age"))]
+fn with_age_identities(watcher: ConfigWatcher, _: &()) -> ConfigWatcher {
+    watcher
+}
+
+/// Attaches the event database; failing to open it is only a warning
+#[cfg(feature = "event-db")]
+fn event_db(
+    emitter: Emitter,
+    path: &std::path::Path,
+    keep: Option<Duration>,
+) -> anyhow::Result<(Emitter, Option<crate::event_db::EventDbGuard>)> {
+    match crate::event_db::EventDb::open(path, keep) {
+        Ok((event_db, guard)) => Ok((emitter.with_event_db(event_db), Some(guard))),
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "event database disabled");
+            Ok((emitter, None))
+        }
+    }
+}
+
+/// Without the `event-db` feature, there is no database to write to
+#[cfg(not(feature = "event-db"))]
+fn event_db(
+    _: Emitter,
+    _: &std::path::Path,
+    _: Option<Duration>,
+) -> anyhow::Result<(Emitter, Option<()>)> {
+    Err(exit::usage(anyhow::anyhow!(
+        "--event-db needs a build with the `event-db` feature"
+    )))
+}
+
+/// Adds desktop notifications
+#[cfg(feature = "desktop-notify")]
+fn desktop(notifiers: Notifiers, interval: Duration) -> anyhow::Result<Notifiers> {
+    use crate::desktop::DesktopNotifier;
+    Ok(notifiers.with(DesktopNotifier::new(interval)))
+}
+
+/// Without the `desktop-notify` feature, there is no desktop to notify
+#[cfg(not(feature = "desktop-notify"))]
+fn desktop(_: Notifiers, _: Duration) -> anyhow::Result<Notifiers> {
+    Err(exit::usage(anyhow::anyhow!(
+        "--notify-desktop needs a build with the `desktop-notify` feature"
+    )))
+}
+
+/// Commits the reloads of `file`; without git, only a warning
+fn git_autocommit(notifiers: Notifiers, file: &std::path::Path) -> anyhow::Result<Notifiers> {
+    match WorkTree::of(file) {
+        Ok(tree) => {
+            if tree.is_ignored(file) {
+                tracing::warn!(file = %file.display(), "git ignores the file, --git-autocommit will not commit it");
+            }
+            tracing::info!(file = %file.display(), repository = %tree.root().display(), "committing reloads");
+            Ok(notifiers.with(GitAutocommit::new(tree, file)))
+        }
+        Err(GitError::NotInstalled) => {
+            tracing::warn!("git is not installed, --git-autocommit is off");
+            Ok(notifiers)
+        }
+        Err(e) => Err(exit::usage(
+            anyhow::Error::new(e).context("--git-autocommit"),
+        )),
+    }
+}
+
+/// The process `--signal-pid` / `--signal-pidfile` names, if any
+fn signal_target(args: &WatchArgs) -> Option<Target> {
+    match (args.signal_pid, &args.signal_pidfile) {
+        (Some(pid), _) => Some(Target::Pid(pid)),
+        (None, Some(path)) => Some(Target::Pidfile(path.clone())),
+        (None, None) => None,
+    }
+}
+
+/// Signals a process after every reload, reporting through `emitter`
+#[cfg(unix)]
+fn signal_pid(
+    notifiers: Notifiers,
+    target: Target,
+    args: &WatchArgs,
+    emitter: &Emitter,
+) -> anyhow::Result<Notifiers> {
+    use crate::signal_pid::SignalNotifier;
+    let notifier = SignalNotifier::new(target, args.signal, emitter.clone())
+        .with_min_interval(args.signal_min_interval);
+    Ok(notifiers.with(notifier))
+}
+
+/// Windows has no signals to send
+#[cfg(not(unix))]
+fn signal_pid(_: Notifiers, _: Target, _: &WatchArgs, _: &Emitter) -> anyhow::Result<Notifiers> {
+    Err(exit::usage(anyhow::anyhow!(
+        "--signal-pid and --signal-pidfile are only supported on Unix"
+    )))
+}
+
+/// Adds the MQTT publisher; the broker is reached with the first event
+#[cfg(feature = "mqtt")]
+fn mqtt(notifiers: Notifiers, url: &url::Url, args: &WatchArgs) -> anyhow::Result<Notifiers> {
+    use crate::mqtt::{MqttConfig, MqttNotifier, Qos, Topic};
+
+    let topic = Topic::parse(&args.mqtt_topic).map_err(exit::usage)?;
+    let config = MqttConfig {
+        qos: Qos::try_from(args.mqtt_qos).map_err(exit::usage)?,
+        ..MqttConfig::new(url, topic).map_err(exit::usage)?
+    };
+    tracing::info!(
+        broker = config.broker(),
+        topic = args.mqtt_topic,
+        "publishing to MQTT"
+    );
+    Ok(notifiers.with(MqttNotifier::new(config)))
+}
+
+/// Without the `mqtt` feature, there is no broker to publish to
+#[cfg(not(feature = "mqtt"))]
+fn mqtt(_: Notifiers, _: &url::Url, _: &WatchArgs) -> anyhow::Result<Notifiers> {
+    Err(exit::usage(anyhow::anyhow!(
+        "--mqtt-url needs a build with the `mqtt` feature"
+    )))
+}
+
+/// Adds the Redis announcements; the server is reached with the first load
+#[cfg(feature = "redis")]
+fn redis(notifiers: Notifiers, url: &url::Url, args: &WatchArgs) -> anyhow::Result<Notifiers> {
+    use crate::redis::{RedisConfig, RedisNotifier, Template};
+
+    let channel = Template::parse(&args.redis_channel).map_err(exit::usage)?;
+    let config = RedisConfig {
+        version_key: args
+            .redis_version_key
+            .as_deref()
+            .map(Template::parse)
+            .transpose()
+            .map_err(exit::usage)?,
+        include_config: args.redis_include_config,
+        ..RedisConfig::new(url, channel).map_err(exit::usage)?
+    };
+    tracing::info!(
+        server = config.server(),
+        channel = args.redis_channel,
+        "announcing on Redis"
+    );
+    Ok(notifiers.with(RedisNotifier::new(config)))
+}
+
+/// Without the `redis` feature, there is no server to announce on
+#[cfg(not(feature = "redis"))]
+fn redis(_: Notifiers, _: &url::Url, _: &WatchArgs) -> anyhow::Result<Notifiers> {
+    Err(exit::usage(anyhow::anyhow!(
+        "--redis-url needs a build with the `redis` feature"
+    )))
+}
+
+/// Listens on `--push-socket` and adds the notifier feeding its clients
+#[cfg(unix)]
+async fn push_socket(
+    notifiers: Notifiers,
+    path: &std::path::Path,
+    args: &WatchArgs,
+    reload_requests: &ReloadRequests,
+) -> anyhow::Result<(Notifiers, Option<crate::push::PushSocket>)> {
+    use crate::push::{self, PushOptions};
+
+    let options = PushOptions {
+        mode: args.push_socket_mode,
+        redact: args.push_redact,
+        reload: args.push_allow_reload.then(|| reload_requests.clone()),
+    };
+    let socket = push::serve(path, options)
+        .await
+        .with_context(|| format!("Cannot listen on push socket {}", path.display()))
+        .map_err(exit::usage)?;
+    tracing::info!(path = %path.display(), "pushing configurations");
+    Ok((notifiers.with(socket.notifier()), Some(socket)))
+}
+
+/// There are no Unix sockets to push on
+#[cfg(not(unix))]
+async fn push_socket(
+    _: Notifiers,
+    _: &std::path::Path,
+    _: &WatchArgs,
+    _: &ReloadRequests,
+) -> anyhow::Result<(Notifiers, Option<()>)> {
+    Err(exit::usage(anyhow::anyhow!(
+        "--push-socket is only available on Unix"
+    )))
+}
+
+/// Reports to systemd when started with `NOTIFY_SOCKET`, and pings its
+/// watchdog when `WATCHDOG_USEC` asks for it
+#[cfg(all(unix, feature = "systemd"))]
+fn systemd(emitter: Emitter, watchers: usize, require_initial: bool, probes: &Probes) -> Emitter {
+    use crate::systemd::{self, Systemd};
+
+    let Some(systemd) = Systemd::from_env(watchers, require_initial) else {
+        return emitter;
+    };
+    let env = |name| std::env::var(name).ok();
+    if let Some(every) = systemd::watchdog_interval(
+        env("WATCHDOG_USEC").as_deref(),
+        env("WATCHDOG_PID").as_deref(),
+        std::process::id(),
+    ) {
+        tracing::debug!(every = ?every, "pinging the systemd watchdog");
+        systemd.spawn_watchdog(every, probes.clone());
+    }
+    emitter.with_systemd(systemd)
+}
+
+/// Without the `systemd` feature (or off Unix), `NOTIFY_SOCKET` is ignored
+#[cfg(not(all(unix, feature = "systemd")))]
+fn systemd(emitter: Emitter, _: usize, _: bool, _: &Probes) -> Emitter {
+    emitter
+}
+
+/// Reports the state of the watchers to the service control manager; a
+/// no-op outside a Windows service
+#[cfg(feature = "windows-service")]
+fn service_status(emitter: Emitter, watchers: usize) -> Emitter {
+    match crate::service::current() {
+        Some(controller) => {
+            controller.expect(watchers);
+            emitter.with_service(controller.clone())
+        }
+        None => emitter,
+    }
+}
+
+#[cfg(not(feature = "windows-service"))]
+fn service_status(emitter: Emitter, _: usize) -> Emitter {
+    emitter
+}
+
+/// Whether the service control manager started the watch
+#[cfg(feature = "windows-service")]
+fn under_service_manager() -> bool {
+    crate::service::current().is_some()
+}
+
+#[cfg(not(feature = "windows-service"))]
+fn under_service_manager() -> bool {
+    false
+}
+
+/// Resolves on Stop or Shutdown from the service control manager; never
+/// outside a Windows service
+#[cfg(feature = "windows-service")]
+async fn service_stop() {
+    match crate::service::current() {
+        Some(controller) => controller.stop_requested().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(not(feature = "windows-service"))]
+async fn service_stop() {
+    std::future::pending().await
+}
+
+/// Asks every watcher to reload on SIGHUP
+#[cfg(unix)]
+fn reload_on_sighup(requests: ReloadRequests) -> anyhow::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangups = signal(SignalKind::hangup()).context("Cannot listen for SIGHUP")?;
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            requests.request("SIGHUP");
+        }
+    });
+    Ok(())
+}
+
+/// There is no SIGHUP off Unix
+#[cfg(not(unix))]
+fn reload_on_sighup(_: ReloadRequests) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// Prints a status dump on SIGUSR1
+#[cfg(unix)]
+fn dump_on_sigusr1(dumps: DumpRequests) -> anyhow::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut requests = signal(SignalKind::user_defined1()).context("Cannot listen for SIGUSR1")?;
+    tokio::spawn(async move {
+        while requests.recv().await.is_some() {
+            dumps.request("SIGUSR1");
+        }
+    });
+    Ok(())
+}
+
+/// There is no SIGUSR1 off Unix: `POST /dump` asks for a dump instead
+#[cfg(not(unix))]
+fn dump_on_sigusr1(_: DumpRequests) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// Moves to the next verbosity on SIGUSR2
+#[cfg(unix)]
+fn cycle_verbosity_on_sigusr2(emitter: Emitter) -> anyhow::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut requests = signal(SignalKind::user_defined2()).context("Cannot listen for SIGUSR2")?;
+    tokio::spawn(async move {
+        while requests.recv().await.is_some() {
+            cycle_verbosity(&emitter, "SIGUSR2");
+        }
+    });
+    Ok(())
+}
+
+/// There is no SIGUSR2 off Unix
+#[cfg(not(unix))]
+fn cycle_verbosity_on_sigusr2(_: Emitter) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// Quiet, normal, verbose, debug, then quiet again; the log filter follows:
+/// debug then trace for this crate at the two upper levels, the startup
+/// filter below
+#[cfg(unix)]
+fn cycle_verbosity(emitter: &Emitter, by: &str) {
+    use crate::logging::LogLevel;
+    use crate::output::Verbosity;
+
+    let verbosity = emitter.verbosity().next();
+    logging::set_level(match verbosity {
+        Verbosity::Quiet | Verbosity::Normal => None,
+        Verbosity::Verbose => Some(LogLevel::Debug),
+        Verbosity::Debug => Some(LogLevel::Trace),
+    });
+    emitter.set_verbosity(verbosity, by);
+}
+
+/// The feed of the --tui dashboard, when there is a terminal to draw it on
+fn tui_feed(args: &WatchArgs) -> Option<Feed> {
+    if !args.tui {
+        return None;
+    }
+    if !tui::supported() {
+        tracing::warn!(
+            "--tui needs a terminal on stdin and stdout (Unix only), showing the usual output"
+        );
+        return None;
+    }
+    Some(Feed::new(Timestamps::new(
+        TimestampFormat::TimeOnly,
+        args.utc,
+    )))
+}
+
+/// The format of the timestamps on text event lines: none with
+/// `--output json`, whose events carry their own
+fn text_timestamps(args: &WatchArgs) -> TimestampFormat {
+    match args.output {
+        OutputFormat::Text => args.timestamp,
+        OutputFormat::Json => TimestampFormat::Off,
+    }
+}
+
+/// Reads the single-key commands typed in the terminal, unless --no-keys
+/// or --confirm (which asks on stdin); `q` goes through `interrupts`, like
+/// Ctrl+C, and the scrolling keys to the --tui dashboard
+fn read_keys(
+    args: &WatchArgs,
+    interrupts: &mut Interrupts,
+    reloads: &ReloadRequests,
+    dumps: DumpRequests,
+    pause: Pause,
+    dashboard: Option<Dashboard>,
+) -> anyhow::Result<Option<RawMode>> {
+    if args.no_keys || args.confirm {
+        return Ok(None);
+    }
+    let Some((raw_mode, mut actions)) =
+        keys::listen().context("Cannot read keys from the terminal")?
+    else {
+        return Ok(None);
+    };
+    let shown = dashboard.is_some();
+    let (quits, quit_requests) = mpsc::unbounded_channel();
+    interrupts.quits = Some(quit_requests);
+    let reloads = reloads.clone();
+    tokio::spawn(async move {
+        while let Some(action) = actions.recv().await {
+            match action {
+                Action::Reload => reloads.request("keyboard"),
+                Action::Dump => {
+                    dumps.request("keyboard");
+                }
+                Action::Pause => {
+                    pause.toggle("keyboard");
+                }
+                Action::Quit => {
+                    let _ = quits.send(());
+                }
+                // The dashboard lists the keys at the bottom
+                Action::Help if dashboard.is_none() => eprintln!("{}", keys::HELP),
+                Action::ScrollUp | Action::ScrollDown => {
+                    if let Some(ref dashboard) = dashboard {
+                        dashboard.scroll(if action == Action::ScrollUp { 1 } else { -1 });
+                    }
+                }
+                Action::Help => {}
+            }
+        }
+    });
+    if !shown {
+        eprintln!("Press ? for the keys");
+    }
+    Ok(Some(raw_mode))
+}
+
+/// Resolves on `q`, or never without the keys
+async fn next_quit(quits: &mut Option<mpsc::UnboundedReceiver<()>>) {
+    if let Some(quits) = quits
+        && quits.recv().await.is_some()
+    {
+        return;
+    }
+    std::future::pending().await
+}
+
+/// Resolves on Ctrl+C, SIGTERM or a service stop, all a clean shutdown
+async fn stop_requested(interrupts: &mut Interrupts) {
+    tokio::select! {
+        _ = interrupts.recv() => {}
+        _ = service_stop() => {}
+    }
+}
+
+/// Ctrl+C and SIGTERM (and `q`), each time one arrives: the first stops
+/// the watch, the next one forces the shutdown
+///
+/// Listened for from `watch` on: without a handler SIGTERM would kill the
+/// process before its guards clean up.
+#[cfg(unix)]
+struct Interrupts {
+    interrupts: signal::unix::Signal,
+    terminations: signal::unix::Signal,
+    quits: Option<mpsc::UnboundedReceiver<()>>,
+}
+
+#[cfg(unix)]
+impl Interrupts {
+    fn listen() -> anyhow::Result<Self> {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        Ok(Self {
+            interrupts: signal(SignalKind::interrupt()).context("Cannot listen for Ctrl+C")?,
+            terminations: signal(SignalKind::terminate()).context("Cannot listen for SIGTERM")?,
+            quits: None,
+        })
+    }
+
+    async fn recv(&mut self) {
+        tokio::select! {
+            _ = self.interrupts.recv() => {}
+            _ = self.terminations.recv() => {}
+            _ = next_quit(&mut self.quits) => {}
+        }
+    }
+}
+
+/// Ctrl+C and the console window closing (CTRL_CLOSE) on Windows, and `q`
+///
+/// Windows ends the process about 5 seconds after CTRL_CLOSE, whatever
+/// --shutdown-timeout says.
+#[cfg(not(unix))]
+struct Interrupts {
+    interrupts: signal::windows::CtrlC,
+    closes: signal::windows::CtrlClose,
+    quits: Option<mpsc::UnboundedReceiver<()>>,
+}
+
+#[cfg(not(unix))]
+impl Interrupts {
+    fn listen() -> anyhow::Result<Self> {
+        Ok(Self {
+            interrupts: signal::windows::ctrl_c().context("Cannot listen for Ctrl+C")?,
+            closes: signal::windows::ctrl_close().context("Cannot listen for CTRL_CLOSE")?,
+            quits: None,
+        })
+    }
+
+    async fn recv(&mut self) {
+        tokio::select! {
+            _ = self.interrupts.recv() => {}
+            _ = self.closes.recv() => {}
+            _ = next_quit(&mut self.quits) => {}
+        }
+    }
+}
+
+/// The events streamed on `GET /ws`, with `--http-addr`
+#[cfg(feature = "http-server")]
+fn event_stream(
+    notifiers: Notifiers,
+    args: &WatchArgs,
+) -> (Notifiers, Option<crate::websocket::EventStream>) {
+    match args.http_addr {
+        Some(_) => {
+            let events = crate::websocket::EventStream::new();
+            (notifiers.with(events.notifier()), Some(events))
+        }
+        None => (notifiers, None),
+    }
+}
+
+/// Without the `http-server` feature, there is nothing to stream to
+#[cfg(not(feature = "http-server"))]
+fn event_stream(notifiers: Notifiers, _: &WatchArgs) -> (Notifiers, Option<()>) {
+    (notifiers, None)
+}
+
+/// Starts the `--http-addr` server (`/config`, `/status`, the probes,
+/// `/ws`, `PUT /config` with `--http-allow-write`) and the
+/// `--health-addr` one (the probes only)
+#[cfg(feature = "http-server")]
+async fn http_servers(
+    args: &WatchArgs,
+    files: &[PathBuf],
+    board: &Option<StatusBoard>,
+    probes: &Probes,
+    events: Option<crate::websocket::EventStream>,
+    dumps: &DumpRequests,
+) -> anyhow::Result<Vec<crate::server::Server>> {
+    use crate::server::{self, ConfigWriter, Endpoints};
+
+    let health = Endpoints::default().with_probes(probes.clone());
+    let mut endpoints = Endpoints::default().with_probes(probes.clone());
+    if let Some(board) = board {
+        endpoints = endpoints
+            .with_board(board.clone())
+            .with_dumps(dumps.clone());
+    }
+    if let Some(events) = events {
+        endpoints = endpoints.with_events(events);
+    }
+    if let (true, Some(token), [file]) = (args.http_allow_write, &args.http_token, files) {
+        let mut writer = ConfigWriter::new(file, token).with_backups(args.backup.backups());
+        if let Some(ref schema) = args.schema {
+            let schema = ExternalSchema::load(schema).context("Failed to compile JSON Schema")?;
+            writer = writer.with_schema(schema);
+        }
+        endpoints = endpoints.with_writer(writer);
+    }
+
+    let mut servers = Vec::new();
+    for (addr, endpoints, what) in [
+        (args.http_addr, endpoints, "configuration and status"),
+        (args.health_addr, health, "health probes"),
+    ] {
+        let Some(addr) = addr else { continue };
+        let server = server::serve(addr, endpoints)
+            .await
+            .with_context(|| format!("Failed to serve HTTP on {addr}"))
+            .map_err(exit::usage)?;
+        tracing::info!(addr = %server.addr(), "serving {what}");
+        servers.push(server);
+    }
+    Ok(servers)
+}
+
+/// Without the `http-server` feature, there is nothing to serve with
+#[cfg(not(feature = "http-server"))]
+async fn http_servers(
+    args: &WatchArgs,
+    _: &[PathBuf],
+    _: &Option<StatusBoard>,
+    _: &Probes,
+    _: Option<()>,
+    _: &DumpRequests,
+) -> anyhow::Result<Vec<()>> {
+    if args.http_addr.is_some() || args.health_addr.is_some() {
+        return Err(exit::usage(anyhow::anyhow!(
+            "--http-addr and --health-addr need a build with the `http-server` feature"
+        )));
+    }
+    Ok(Vec::new())
+}
+
+/// Serves `--grpc-addr`, fed like any notifier
+#[cfg(feature = "grpc")]
+async fn grpc_server(
+    notifiers: Notifiers,
+    args: &WatchArgs,
+) -> anyhow::Result<(Notifiers, Option<crate::grpc::GrpcServer>)> {
+    use crate::grpc::{self, ConfigFeed};
+
+    let Some(addr) = args.grpc_addr else {
+        return Ok((notifiers, None));
+    };
+    let feed = ConfigFeed::new();
+    let server = grpc::serve(addr, feed.clone())
+        .await
+        .with_context(|| format!("Failed to serve gRPC on {addr}"))
+        .map_err(exit::usage)?;
+    tracing::info!(addr = %server.addr(), "serving gRPC");
+    Ok((notifiers.with(feed.notifier()), Some(server)))
+}
+
+/// Without the `grpc` feature, there is nothing to serve with
+#[cfg(not(feature = "grpc"))]
+async fn grpc_server(
+    notifiers: Notifiers,
+    args: &WatchArgs,
+) -> anyhow::Result<(Notifiers, Option<()>)> {
+    if args.grpc_addr.is_some() {
+        return Err(exit::usage(anyhow::anyhow!(
+            "--grpc-addr needs a build with the `grpc` feature"
+        )));
+    }
+    Ok((notifiers, None))
+}
+
+/// Ends the gRPC calls, then stops the server
+#[cfg(feature = "grpc")]
+async fn close_grpc_server(server: Option<crate::grpc::GrpcServer>) {
+    if let Some(server) = server {
+        server.shutdown().await;
+    }
+}
+
+#[cfg(not(feature = "grpc"))]
+async fn close_grpc_server(_: Option<()>) {}
+
+/// Closes the WebSocket connections of the servers, then stops them
+#[cfg(feature = "http-server")]
+async fn close_http_servers(servers: Vec<crate::server::Server>) {
+    futures::future::join_all(servers.into_iter().map(|server| server.shutdown())).await;
+}
+
+#[cfg(not(feature = "http-server"))]
+async fn close_http_servers(_: Vec<()>) {}
+
+/// Output prefix for each file: its name, or the full path when two
+/// files share a name
+fn labels(files: &[PathBuf]) -> Vec<String> {
+    let name = |file: &PathBuf| {
+        file.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| file.display().to_string())
+    };
+    files
+        .iter()
+        .map(|file| {
+            let shared = files
+                .iter()
+                .filter(|other| name(other) == name(file))
+                .count()
+                > 1;
+            if shared {
+                file.display().to_string()
+            } else {
+                name(file)
+            }
+        })
+        .collect()
+}
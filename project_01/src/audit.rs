@@ -0,0 +1,303 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`log_file::LogFile`**: The audit log reuses the log file's writer thread
+  and size-based rotation, so recording an entry never blocks the watcher
+- **`Arc<Mutex<HashMap<...>>>`**: Every watcher shares one audit log and
+  its per-file trail (served version, last rejection)
+- **Pattern matching on `Event`**: Only the events that matter for an audit
+  become entries; the rest are ignored
+
+**Design decisions**:
+- One JSON object per line (JSONL): `timestamp`, `event`, `file`, then the
+  event's own fields, ready for `jq` or a log shipper
+- Entries: `started`, `loaded` (first valid load), `changed` (accepted
+  reload, with the field-level diff), `unchanged` (file rewritten with the
+  same content), `rejected` (with the error), `file_error` and `shutdown`
+- `version` is always the version being served, so a rejection records
+  which configuration stayed in place
+- Secrets never reach the log: the diff redacts them (`diff::diff`) and the
+  summary is not recorded
+- A failing file is retried on every tick; the same rejection is recorded
+  once, until the file is accepted again or fails differently
+- Each entry is written with a single `write(2)` in append mode as soon as
+  the writer thread gets it: a killed process loses at most the entries
+  still queued, and never leaves half a line behind
+- The file is opened (and created) at startup, so an unwritable path stops
+  the watcher before it watches anything
+
+******************************************************************************/
+
+use crate::log_file::{LogFile, WriterGuard};
+use crate::output::{Event, ShutdownReason, error_json};
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// What the audit log remembers about a file between entries
+#[derive(Debug, Default)]
+struct Trail {
+    version: u64,
+    /// Message of the last recorded rejection, cleared on success
+    rejection: Option<String>,
+}
+
+/// Append-only JSONL record of configuration events
+///
+/// Cloning is cheap: every clone writes to the same file.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    file: LogFile,
+    trails: Arc<Mutex<HashMap<PathBuf, Trail>>>,
+}
+
+impl AuditLog {
+    /// Opens (or creates) `path` for appending and starts its writer thread
+    ///
+    /// The file rotates before growing past `max_size`, keeping `keep` old
+    /// files. Dropping the guard writes the pending entries.
+    pub fn open(path: &Path, max_size: u64, keep: u32) -> io::Result<(Self, WriterGuard)> {
+        let (file, guard) = LogFile::spawn(path, max_size, keep)?;
+        Ok((
+            Self {
+                file,
+                trails: Arc::default(),
+            },
+            guard,
+        ))
+    }
+
+    /// Queues the entry for `event`, if it is one the audit log records
+    pub fn record(&self, event: &Event<'_>) {
+        let entry = {
+            let mut trails = self.trails.lock().unwrap_or_else(|e| e.into_inner());
+            entry(event, &mut trails)
+        };
+        if let Some(entry) = entry {
+            let mut line = serde_json::to_string(&entry).unwrap_or_default();
+            line.push('\n');
+            // Only fails once the writer thread is gone, at exit
+            let _ = self.file.clone().write_all(line.as_bytes());
+        }
+    }
+}
+
+/// Builds the entry for `event`, updating the trail of its file
+fn entry(event: &Event<'_>, trails: &mut HashMap<PathBuf, Trail>) -> Option<Value> {
+    let (name, file, fields) = match *event {
+        Event::Started { file, interval, .. } => (
+            "started",
+            Some(file),
+            json!({ "interval_secs": interval.as_secs_f64() }),
+        ),
+        Event::Loaded {
+            file,
+            version,
+            initial,
+            changes,
+            ..
+        } => {
+            let trail = trails.entry(file.to_path_buf()).or_default();
+            trail.version = version;
+            trail.rejection = None;
+            let mut fields = json!({ "version": version, "accepted": true });
+            if let Some(changes) = changes {
+                fields["diff"] = json!(changes);
+            }
+            let name = if initial { "loaded" } else { "changed" };
+            (name, Some(file), fields)
+        }
+        Event::Unchanged { file, version } => {
+            trails.entry(file.to_path_buf()).or_default().rejection = None;
+            (
+                "unchanged",
+                Some(file),
+                json!({ "version": version, "accepted": true }),
+            )
+        }
+        Event::LoadFailed { file, error, .. } | Event::FileError { file, error } => {
+            let name = match event {
+                Event::FileError { .. } => "file_error",
+                _ => "rejected",
+            };
+            let trail = trails.entry(file.to_path_buf()).or_default();
+            let message = format!("{name}: {error:#}");
+            if trail.rejection.as_ref() == Some(&message) {
+                return None;
+            }
+            trail.rejection = Some(message);
+            (
+                name,
+                Some(file),
+                json!({
+                    "version": trail.version,
+                    "accepted": false,
+                    "error": error_json(error),
+                }),
+            )
+        }
+        Event::Shutdown { reason, .. } => {
+            let reason = match reason {
+                ShutdownReason::Signal => "signal",
+                ShutdownReason::MaxDuration => "max_duration",
+                ShutdownReason::FailFast => "fail_fast",
+                ShutdownReason::ChildExited => "child_exited",
+            };
+            ("shutdown", None, json!({ "reason": reason }))
+        }
+        _ => return None,
+    };
+
+    let mut record = Map::new();
+    record.insert(
+        "timestamp".to_string(),
+        json!(humantime::format_rfc3339_millis(SystemTime::now()).to_string()),
+    );
+    record.insert("event".to_string(), json!(name));
+    if let Some(file) = file {
+        record.insert("file".to_string(), json!(file.display().to_string()));
+    }
+    if let Value::Object(fields) = fields {
+        record.extend(fields);
+    }
+    Some(Value::Object(record))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::diff::Change;
+    use crate::error::ConfigError;
+    use crate::output::Summary;
+    use crate::overrides::Overrides;
+    use std::time::Duration;
+
+    fn read(path: &Path) -> Vec<Value> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_entries_follow_the_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let file = Path::new("app.json");
+        let config = AppConfig::example();
+        let overrides = Overrides::default();
+        let summary = Summary {
+            config: &config,
+            overrides: &overrides,
+        };
+        let error = anyhow::Error::new(ConfigError::FileNotFound {
+            path: file.to_path_buf(),
+        });
+        let changes = [Change {
+            path: "database.connection_string".to_string(),
+            old: Some(json!("<redacted>")),
+            new: Some(json!("<redacted>")),
+        }];
+
+        let (audit, mut guard) = AuditLog::open(&path, 1 << 20, 1).unwrap();
+        let events = [
+            Event::Started {
+                file,
+                interval: Duration::from_secs(2),
+                overrides: &overrides,
+            },
+            Event::Loaded {
+                file,
+                version: 1,
+                initial: true,
+                summary,
+                changes: None,
+                flags: None,
+                stand_in: None,
+            },
+            Event::ChangeDetected { file },
+            Event::LoadFailed {
+                file,
+                initial: false,
+                retrying: true,
+                error: &error,
+            },
+            // Retried on the next tick: not recorded again
+            Event::LoadFailed {
+                file,
+                initial: false,
+                retrying: true,
+                error: &error,
+            },
+            Event::Loaded {
+                file,
+                version: 2,
+                initial: false,
+                summary,
+                changes: Some(&changes),
+                flags: None,
+                stand_in: None,
+            },
+            Event::Unchanged { file, version: 2 },
+            Event::Shutdown {
+                reason: ShutdownReason::Signal,
+                error: None,
+                files: &[],
+            },
+        ];
+        for event in &events {
+            audit.record(event);
+        }
+        guard.shutdown();
+
+        let entries = read(&path);
+        let names: Vec<_> = entries
+            .iter()
+            .map(|e| e["event"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "started",
+                "loaded",
+                "rejected",
+                "changed",
+                "unchanged",
+                "shutdown"
+            ]
+        );
+        assert_eq!(entries[2]["version"], 1);
+        assert_eq!(entries[2]["accepted"], false);
+        assert_eq!(entries[2]["error"]["code"], "file_not_found");
+        assert_eq!(entries[3]["diff"][0]["path"], "database.connection_string");
+        assert_eq!(entries[3]["diff"][0]["new"], "<redacted>");
+        assert!(entries.iter().all(|e| e.get("summary").is_none()));
+    }
+
+    #[test]
+    fn test_rollover_and_unwritable_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let (audit, mut guard) = AuditLog::open(&path, 200, 2).unwrap();
+        for version in 1..=6 {
+            audit.record(&Event::Unchanged {
+                file: Path::new("app.json"),
+                version,
+            });
+        }
+        guard.shutdown();
+
+        let rotated = crate::log_file::RotatingFile::rotated(&path, 1);
+        let total = read(&path).len() + read(&rotated).len();
+        assert!(total < 6, "the oldest entries rotate out");
+        assert!(std::fs::metadata(&path).unwrap().len() <= 200);
+
+        let missing = dir.path().join("missing").join("audit.jsonl");
+        assert!(AuditLog::open(&missing, 200, 2).is_err());
+    }
+}
@@ -0,0 +1,414 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`url::Url`**: Parses issuer and endpoint URLs and exposes their scheme
+- **Manual `impl Debug`**: `f.debug_struct` with the secret fields swapped
+  for the redaction marker, so `{:?}` never shows them
+- **Matching on a tuple**: `(jwks_url, secret_file)` covers the
+  exactly-one rule in one `match`
+
+**Design decisions**:
+- Issuer and endpoints must be https; plain http is only accepted in
+  development, where a local identity provider rarely has a certificate
+- A JWT key comes from exactly one place: a JWKS endpoint (asymmetric
+  keys) or a secret file (HMAC). Both set is refused rather than
+  preferring one, since the other would be silently ignored
+- `clock_skew` is capped at 5 minutes: more would keep expired tokens
+  valid for too long
+- Secrets are never in the configuration itself, only the paths of their
+  files; those paths are still flagged secret in `schema::FIELDS`, so
+  `get`, summaries and diffs hide them, and `Debug` does the same
+- Secret files must exist when the watcher loads the configuration
+  ([`AuthConfig::check_files`], relative to its directory); their modes
+  and owners are checked by the permission audit, which treats every
+  `*_file` field as a secret file
+
+******************************************************************************/
+
+use crate::config::{AuthConfig, JwtConfig, MAX_CLOCK_SKEW, OAuthConfig};
+use crate::redact::REDACTED;
+use crate::validation::ValidationReport;
+use std::fmt;
+use std::path::Path;
+use url::Url;
+
+impl AuthConfig {
+    /// Records auth findings under `auth.*`
+    pub fn check(&self, environment: &str, report: &mut ValidationReport) {
+        if self.jwt.is_none() && self.oauth.is_none() {
+            report.error("auth", "needs a jwt or an oauth section");
+        }
+        if let Some(ref jwt) = self.jwt {
+            jwt.check(environment, report);
+        }
+        if let Some(ref oauth) = self.oauth {
+            oauth.check(environment, report);
+        }
+    }
+
+    /// The secret files referenced by the section, with their field paths
+    pub fn secret_files(&self) -> Vec<(&'static str, &str)> {
+        let jwt = self
+            .jwt
+            .as_ref()
+            .and_then(|jwt| jwt.secret_file.as_deref())
+            .map(|file| ("auth.jwt.secret_file", file));
+        let oauth = self.oauth.as_ref().map(|oauth| {
+            (
+                "auth.oauth.client_secret_file",
+                oauth.client_secret_file.as_str(),
+            )
+        });
+        jwt.into_iter().chain(oauth).collect()
+    }
+
+    /// Records an error for each secret file missing from `base`
+    pub fn check_files(&self, base: &Path, report: &mut ValidationReport) {
+        for (field, file) in self.secret_files() {
+            if file.trim().is_empty() {
+                continue;
+            }
+            let path = base.join(file);
+            match std::fs::metadata(&path) {
+                Ok(metadata) if metadata.is_file() => {}
+                Ok(_) => report.error(field, format!("{} is not a file", path.display())),
+                Err(e) => report.error(field, format!("cannot access {}: {e}", path.display())),
+            }
+        }
+    }
+}
+
+impl JwtConfig {
+    fn check(&self, environment: &str, report: &mut ValidationReport) {
+        check_url("auth.jwt.issuer", &self.issuer, environment, report);
+
+        if self.audience.is_empty() {
+            report.error("auth.jwt.audience", "at least one audience is required");
+        }
+        for (i, audience) in self.audience.iter().enumerate() {
+            if audience.trim().is_empty() {
+                report.error(format!("auth.jwt.audience[{i}]"), "cannot be empty");
+            }
+        }
+
+        match (&self.jwks_url, &self.secret_file) {
+            (Some(url), None) => check_url("auth.jwt.jwks_url", url, environment, report),
+            (None, Some(file)) if file.trim().is_empty() => {
+                report.error("auth.jwt.secret_file", "cannot be empty");
+            }
+            (None, Some(_)) => {}
+            (Some(_), Some(_)) => {
+                report.error("auth.jwt", "set either jwks_url or secret_file, not both");
+            }
+            (None, None) => report.error("auth.jwt", "needs jwks_url or secret_file"),
+        }
+
+        if self.clock_skew > MAX_CLOCK_SKEW {
+            report.error("auth.jwt.clock_skew", "must be at most 5m");
+        }
+    }
+}
+
+impl OAuthConfig {
+    fn check(&self, environment: &str, report: &mut ValidationReport) {
+        check_url(
+            "auth.oauth.authorize_url",
+            &self.authorize_url,
+            environment,
+            report,
+        );
+        check_url("auth.oauth.token_url", &self.token_url, environment, report);
+        if self.client_id.trim().is_empty() {
+            report.error("auth.oauth.client_id", "cannot be empty");
+        }
+        if self.client_secret_file.trim().is_empty() {
+            report.error("auth.oauth.client_secret_file", "cannot be empty");
+        }
+    }
+}
+
+/// An https URL with a host; http is accepted in development
+fn check_url(field: &str, raw: &str, environment: &str, report: &mut ValidationReport) {
+    let url = match Url::parse(raw) {
+        Ok(url) => url,
+        Err(e) => {
+            report.error(field, format!("'{raw}' is not a valid URL: {e}"));
+            return;
+        }
+    };
+    match url.scheme() {
+        "https" => {}
+        "http" if environment == "development" => {}
+        "http" => report.error(
+            field,
+            format!("must use https in {environment} (http is only allowed in development)"),
+        ),
+        scheme => report.error(
+            field,
+            format!("scheme '{scheme}' is not supported (use https)"),
+        ),
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        report.error(field, "has no host");
+    }
+}
+
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("jwt", &self.jwt)
+            .field("oauth", &self.oauth)
+            .finish()
+    }
+}
+
+impl fmt::Debug for JwtConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtConfig")
+            .field("issuer", &self.issuer)
+            .field("audience", &self.audience)
+            .field("jwks_url", &self.jwks_url)
+            .field("secret_file", &self.secret_file.as_ref().map(|_| REDACTED))
+            .field("clock_skew", &self.clock_skew)
+            .finish()
+    }
+}
+
+impl fmt::Debug for OAuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuthConfig")
+            .field("authorize_url", &self.authorize_url)
+            .field("token_url", &self.token_url)
+            .field("client_id", &self.client_id)
+            .field("client_secret_file", &REDACTED)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn jwt() -> JwtConfig {
+        JwtConfig {
+            issuer: "https://auth.example.com/".to_string(),
+            audience: vec!["api".to_string()],
+            jwks_url: Some("https://auth.example.com/jwks.json".to_string()),
+            secret_file: None,
+            clock_skew: Duration::from_secs(60),
+        }
+    }
+
+    fn oauth() -> OAuthConfig {
+        OAuthConfig {
+            authorize_url: "https://auth.example.com/authorize".to_string(),
+            token_url: "https://auth.example.com/token".to_string(),
+            client_id: "app".to_string(),
+            client_secret_file: "client.secret".to_string(),
+        }
+    }
+
+    fn auth(jwt: Option<JwtConfig>, oauth: Option<OAuthConfig>) -> AuthConfig {
+        AuthConfig { jwt, oauth }
+    }
+
+    fn errors(config: &AuthConfig, environment: &str) -> Vec<(String, String)> {
+        let mut report = ValidationReport::new();
+        config.check(environment, &mut report);
+        report
+            .errors()
+            .map(|f| (f.path.clone(), f.message.clone()))
+            .collect()
+    }
+
+    fn paths(config: &AuthConfig, environment: &str) -> Vec<String> {
+        errors(config, environment)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_sections() {
+        let both = auth(Some(jwt()), Some(oauth()));
+        assert!(paths(&both, "production").is_empty());
+
+        let hmac = JwtConfig {
+            jwks_url: None,
+            secret_file: Some("jwt.secret".to_string()),
+            ..jwt()
+        };
+        assert!(paths(&auth(Some(hmac), None), "production").is_empty());
+        assert_eq!(paths(&auth(None, None), "production"), ["auth"]);
+    }
+
+    #[test]
+    fn test_exactly_one_key_source() {
+        let both = JwtConfig {
+            secret_file: Some("jwt.secret".to_string()),
+            ..jwt()
+        };
+        let errors_both = errors(&auth(Some(both), None), "production");
+        assert_eq!(errors_both.len(), 1);
+        assert_eq!(errors_both[0].0, "auth.jwt");
+        assert!(errors_both[0].1.contains("not both"), "{errors_both:?}");
+
+        let neither = JwtConfig {
+            jwks_url: None,
+            ..jwt()
+        };
+        let errors_neither = errors(&auth(Some(neither), None), "production");
+        assert_eq!(
+            errors_neither,
+            [(
+                "auth.jwt".to_string(),
+                "needs jwks_url or secret_file".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_http_only_in_development() {
+        let plain = auth(
+            Some(JwtConfig {
+                issuer: "http://localhost:8080/".to_string(),
+                jwks_url: Some("http://localhost:8080/jwks.json".to_string()),
+                ..jwt()
+            }),
+            Some(OAuthConfig {
+                authorize_url: "http://localhost:8080/authorize".to_string(),
+                token_url: "http://localhost:8080/token".to_string(),
+                ..oauth()
+            }),
+        );
+        assert!(paths(&plain, "development").is_empty());
+
+        let all = [
+            "auth.jwt.issuer",
+            "auth.jwt.jwks_url",
+            "auth.oauth.authorize_url",
+            "auth.oauth.token_url",
+        ];
+        for environment in ["staging", "production"] {
+            let found = errors(&plain, environment);
+            assert_eq!(
+                found
+                    .iter()
+                    .map(|(path, _)| path.as_str())
+                    .collect::<Vec<_>>(),
+                all
+            );
+            assert!(
+                found[0]
+                    .1
+                    .contains(&format!("must use https in {environment}"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_failures_name_their_field() {
+        let broken = auth(
+            Some(JwtConfig {
+                issuer: "auth.example.com".to_string(),
+                audience: vec!["api".to_string(), " ".to_string()],
+                jwks_url: Some("ftp://auth.example.com/jwks".to_string()),
+                clock_skew: Duration::from_secs(301),
+                ..jwt()
+            }),
+            Some(OAuthConfig {
+                authorize_url: "https://".to_string(),
+                token_url: "not a url".to_string(),
+                client_id: String::new(),
+                client_secret_file: String::new(),
+            }),
+        );
+        let found = errors(&broken, "development");
+        assert_eq!(
+            found
+                .iter()
+                .map(|(path, _)| path.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "auth.jwt.issuer",
+                "auth.jwt.audience[1]",
+                "auth.jwt.jwks_url",
+                "auth.jwt.clock_skew",
+                "auth.oauth.authorize_url",
+                "auth.oauth.token_url",
+                "auth.oauth.client_id",
+                "auth.oauth.client_secret_file",
+            ],
+            "{found:?}"
+        );
+        assert!(found[0].1.contains("is not a valid URL"), "{found:?}");
+        assert!(found[2].1.contains("scheme 'ftp'"), "{found:?}");
+
+        let no_audience = JwtConfig {
+            audience: Vec::new(),
+            ..jwt()
+        };
+        assert_eq!(
+            paths(&auth(Some(no_audience), None), "production"),
+            ["auth.jwt.audience"]
+        );
+    }
+
+    #[test]
+    fn test_clock_skew_limit() {
+        let at_limit = JwtConfig {
+            clock_skew: Duration::from_secs(300),
+            ..jwt()
+        };
+        assert!(paths(&auth(Some(at_limit), None), "production").is_empty());
+    }
+
+    #[test]
+    fn test_check_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = auth(
+            Some(JwtConfig {
+                jwks_url: None,
+                secret_file: Some("jwt.secret".to_string()),
+                ..jwt()
+            }),
+            Some(oauth()),
+        );
+
+        let mut report = ValidationReport::new();
+        config.check_files(dir.path(), &mut report);
+        let missing: Vec<_> = report.errors().map(|f| f.path.clone()).collect();
+        assert_eq!(
+            missing,
+            ["auth.jwt.secret_file", "auth.oauth.client_secret_file"]
+        );
+
+        std::fs::write(dir.path().join("jwt.secret"), "s3cr3t").unwrap();
+        std::fs::create_dir(dir.path().join("client.secret")).unwrap();
+        let mut report = ValidationReport::new();
+        config.check_files(dir.path(), &mut report);
+        let found: Vec<_> = report.errors().map(|f| f.to_string()).collect();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].contains("is not a file"), "{found:?}");
+    }
+
+    #[test]
+    fn test_debug_hides_secret_files() {
+        let config = auth(
+            Some(JwtConfig {
+                jwks_url: None,
+                secret_file: Some("/run/secrets/jwt".to_string()),
+                ..jwt()
+            }),
+            Some(OAuthConfig {
+                client_secret_file: "/run/secrets/oauth".to_string(),
+                ..oauth()
+            }),
+        );
+        let debug = format!("{config:?}");
+        assert!(!debug.contains("/run/secrets"), "{debug}");
+        assert!(debug.contains(REDACTED), "{debug}");
+        assert!(debug.contains("https://auth.example.com/"), "{debug}");
+    }
+}
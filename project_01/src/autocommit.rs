@@ -0,0 +1,199 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`impl Notifier`**: Committing is one of the dispatcher's notifiers,
+  one per watched file; a failed commit is a failure of the notifier,
+  logged as a warning, never a rejected configuration
+- **`tokio::task::spawn_blocking`**: The git invocations are blocking
+  processes, kept off the notifiers' runtime thread
+
+**Design decisions**:
+- `--git-autocommit` commits the watched file after every reload that
+  passed validation, and only then: a load failure is no notifier event,
+  so a broken intermediate state never reaches the history
+- The file is read and validated once more just before staging; if it
+  changed again in between and does not pass, nothing is committed and
+  the next reload decides
+- The message says what changed: `config-watcher: TestApp 1.2.0→1.3.0;
+  changed server.port, features.dark_mode` (the version arrow only when
+  the version changed). Paths, never values: secrets stay out of history
+  messages
+- A reload that leaves the file as committed (an edit undone) makes no
+  empty commit
+- The work tree is found at startup (`git::WorkTree::of`): a file outside
+  one is a usage error; git missing is a warning and autocommit is off.
+  Conflicts, a locked index or a refusing hook are warnings at the commit
+
+******************************************************************************/
+
+use crate::cli::EventKind;
+use crate::commands::validate;
+use crate::git::WorkTree;
+use crate::notify::{ConfigEvent, Notifier};
+use std::path::PathBuf;
+
+/// Commits the reloads of one watched file
+#[derive(Debug, Clone)]
+pub struct GitAutocommit {
+    tree: WorkTree,
+    file: PathBuf,
+}
+
+impl GitAutocommit {
+    /// Commits `file` into `tree`
+    pub fn new(tree: WorkTree, file: impl Into<PathBuf>) -> Self {
+        Self {
+            tree,
+            file: file.into(),
+        }
+    }
+
+    /// Validates the file as it is now and commits it; false when nothing
+    /// was committed
+    fn commit(&self, message: &str) -> anyhow::Result<bool> {
+        let contents = std::fs::read_to_string(&self.file)?;
+        if let Err(e) = validate::validate_contents(&self.file, &contents, None) {
+            tracing::info!(
+                file = %self.file.display(),
+                error = format!("{e:#}"),
+                "file changed since its reload and is not valid, not committed"
+            );
+            return Ok(false);
+        }
+        Ok(self.tree.commit_file(&self.file, message)?)
+    }
+}
+
+/// The commit message for a reload
+pub fn message(event: &ConfigEvent) -> Option<String> {
+    let config = event.config.as_ref()?;
+    let changes = event.data["diff"].as_array().cloned().unwrap_or_default();
+    let version = changes
+        .iter()
+        .find(|change| change["path"] == "version")
+        .and_then(|change| Some((change["old"].as_str()?, change["new"].as_str()?)));
+    let mut message = match version {
+        Some((old, new)) => format!("config-watcher: {} {old}→{new}", config.app_name),
+        None => format!("config-watcher: {} {}", config.app_name, config.version),
+    };
+    let paths: Vec<&str> = changes
+        .iter()
+        .filter_map(|change| change["path"].as_str())
+        .filter(|path| *path != "version")
+        .collect();
+    if !paths.is_empty() {
+        message.push_str("; changed ");
+        message.push_str(&paths.join(", "));
+    }
+    Some(message)
+}
+
+impl Notifier for GitAutocommit {
+    fn name(&self) -> &str {
+        "git-autocommit"
+    }
+
+    fn accepts(&self, event: &ConfigEvent) -> bool {
+        event.kind == EventKind::Reload && event.file.as_deref() == Some(&self.file)
+    }
+
+    async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+        let Some(message) = message(event) else {
+            return Ok(());
+        };
+        let this = self.clone();
+        let committed = tokio::task::spawn_blocking(move || this.commit(&message)).await??;
+        tracing::debug!(file = %self.file.display(), committed, "git autocommit");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::diff;
+    use crate::git::tests::git;
+    use crate::output::{Event, Summary};
+    use crate::overrides::Overrides;
+    use std::path::Path;
+    use std::time::SystemTime;
+
+    fn reload(file: &Path, old: &AppConfig, new: &AppConfig) -> ConfigEvent {
+        let changes = diff::diff(
+            &serde_json::to_value(old).unwrap(),
+            &serde_json::to_value(new).unwrap(),
+        );
+        let overrides = Overrides::default();
+        let event = Event::Loaded {
+            file,
+            version: 2,
+            initial: false,
+            summary: Summary {
+                config: new,
+                overrides: &overrides,
+            },
+            changes: Some(&changes),
+            flags: None,
+            stand_in: None,
+        };
+        ConfigEvent::new(&event, SystemTime::UNIX_EPOCH).unwrap()
+    }
+
+    #[test]
+    fn test_message_lists_the_changed_paths() {
+        let old = AppConfig::example();
+        let mut new = old.clone();
+        new.version = "1.3.0".to_string();
+        new.server.as_mut().unwrap().port = 9090;
+        let event = reload(Path::new("app.json"), &old, &new);
+        assert_eq!(
+            message(&event).unwrap(),
+            format!(
+                "config-watcher: MyAwesomeApp {}→1.3.0; changed server.port",
+                old.version
+            )
+        );
+
+        let mut same_version = old.clone();
+        same_version.server.as_mut().unwrap().port = 9090;
+        let event = reload(Path::new("app.json"), &old, &same_version);
+        assert_eq!(
+            message(&event).unwrap(),
+            format!(
+                "config-watcher: MyAwesomeApp {}; changed server.port",
+                old.version
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commits_valid_reloads_only() {
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q"]);
+        let file = dir.path().join("app.json");
+        // The secret file of the example's auth section, checked on commit
+        std::fs::create_dir(dir.path().join("secrets")).unwrap();
+        std::fs::write(dir.path().join("secrets/oauth_client_secret"), "s3cr3t").unwrap();
+        let old = AppConfig::example();
+        let mut new = old.clone();
+        new.server.as_mut().unwrap().port = 9090;
+        std::fs::write(&file, serde_json::to_string(&new).unwrap()).unwrap();
+        let autocommit = GitAutocommit::new(WorkTree::of(&file).unwrap(), &file);
+
+        autocommit.notify(&reload(&file, &old, &new)).await.unwrap();
+        let log = git(dir.path(), &["log", "--format=%s"]);
+        assert_eq!(
+            log,
+            format!(
+                "config-watcher: MyAwesomeApp {}; changed server.port\n",
+                old.version
+            )
+        );
+
+        // Broken again before the commit: nothing committed
+        std::fs::write(&file, "{ not json").unwrap();
+        autocommit.notify(&reload(&file, &new, &old)).await.unwrap();
+        assert_eq!(git(dir.path(), &["log", "--format=%s"]), log);
+    }
+}
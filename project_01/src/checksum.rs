@@ -0,0 +1,189 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`Option<&str>` for a missing file**: `verify` is pure, the caller
+  says whether the sidecar exists
+- **`u8::is_ascii_hexdigit`**: The sidecar hash is checked before it is
+  compared
+
+**Design decisions**:
+- `--verify-checksum` checks `config.json` against `config.json.sha256`
+  before parsing, over the exact bytes read: a transfer that mangled a
+  value but kept the JSON well-formed is rejected like a parse error, and
+  the last valid configuration stays in place
+- The sidecar is the `sha256sum` format, `HASH  filename` (or
+  `HASH *filename`); only the hash is compared, so a sidecar written with a
+  full path or none still matches. Upper- or lowercase hex
+- A missing or garbled sidecar is its own error (`checksum_unavailable`),
+  apart from a digest that differs (`checksum_mismatch`); both exit 3
+- The distributor writes the two files one after the other, in either
+  order, so a reload may see a new file with the old sidecar or the
+  reverse: the watcher reads through `fs_util::read_consistent`, which
+  retries a disagreement a few times before it counts. A writer slower
+  than that is caught at the next tick, since a failed load leaves the
+  change pending
+
+******************************************************************************/
+
+use crate::error::{ConfigError, Result};
+use crate::fs_util;
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Appended to the configuration path to find its checksum
+pub const SUFFIX: &str = ".sha256";
+
+/// The sidecar of `file`: the same path plus `.sha256`
+pub fn sidecar(file: &Path) -> PathBuf {
+    fs_util::companion(file, SUFFIX)
+}
+
+/// The hash of a `sha256sum` line, lowercased; None when garbled
+pub fn parse(text: &str) -> Option<String> {
+    let line = text.lines().find(|line| !line.trim().is_empty())?;
+    let hash = line.split_whitespace().next()?;
+    (hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit()))
+        .then(|| hash.to_ascii_lowercase())
+}
+
+/// Checks `contents` against the sidecar text (None when it is missing)
+pub fn verify(file: &Path, contents: &[u8], sidecar_text: Option<&str>) -> Result<()> {
+    let unavailable = |reason: &str| ConfigError::ChecksumUnavailable {
+        path: file.to_path_buf(),
+        reason: reason.to_string(),
+    };
+    let text = sidecar_text.ok_or_else(|| unavailable("no .sha256 sidecar file"))?;
+    let expected =
+        parse(text).ok_or_else(|| unavailable("the .sha256 sidecar is not a SHA-256"))?;
+    let actual = format!("{:x}", Sha256::digest(contents));
+    if actual != expected {
+        return Err(ConfigError::ChecksumMismatch {
+            path: file.to_path_buf(),
+            expected,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Checks `contents`, just read from `file`, against its sidecar as it is
+/// now on disk
+pub fn check(file: &Path, contents: &[u8]) -> Result<()> {
+    let sidecar_text = match std::fs::read_to_string(sidecar(file)) {
+        Ok(text) => Some(text),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(ConfigError::ChecksumUnavailable {
+                path: file.to_path_buf(),
+                reason: format!("cannot read the .sha256 sidecar: {e}"),
+            });
+        }
+    };
+    verify(file, contents, sidecar_text.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{ "app_name": "TestApp", "version": "1.0.0" }"#;
+
+    fn line(contents: &str) -> String {
+        format!("{:x}  config.json\n", Sha256::digest(contents.as_bytes()))
+    }
+
+    #[test]
+    fn test_sha256_known_answers() {
+        for (input, expected) in [
+            (
+                "",
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            ),
+            (
+                "abc",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+        ] {
+            assert_eq!(
+                line(input),
+                format!("{expected}  config.json\n"),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sidecar_path_and_parse() {
+        assert_eq!(
+            sidecar(Path::new("/etc/app/config.json")),
+            Path::new("/etc/app/config.json.sha256")
+        );
+        let hash = "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855";
+        assert_eq!(
+            parse(&format!("{hash} *config.json\n")),
+            Some(hash.to_ascii_lowercase())
+        );
+        assert_eq!(parse(&format!("\n{hash}")), Some(hash.to_ascii_lowercase()));
+        assert_eq!(parse("abc123  config.json"), None);
+        assert_eq!(parse(&format!("{}  config.json", &hash[1..])), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn test_verify() {
+        let file = Path::new("config.json");
+        assert!(verify(file, CONFIG.as_bytes(), Some(&line(CONFIG))).is_ok());
+
+        let mangled = CONFIG.replace("1.0.0", "1.0.1");
+        match verify(file, mangled.as_bytes(), Some(&line(CONFIG))) {
+            Err(ConfigError::ChecksumMismatch {
+                expected, actual, ..
+            }) => {
+                assert_eq!(format!("{expected}  config.json\n"), line(CONFIG));
+                assert_eq!(format!("{actual}  config.json\n"), line(&mangled));
+            }
+            other => panic!("expected a mismatch, got {other:?}"),
+        }
+
+        for sidecar in [None, Some("garbage\n")] {
+            let error = verify(file, CONFIG.as_bytes(), sidecar).unwrap_err();
+            assert_eq!(error.code(), "checksum_unavailable");
+        }
+    }
+
+    async fn read_verified(file: &Path) -> Result<String> {
+        fs_util::read_consistent(file, |contents| check(file, contents)).await
+    }
+
+    #[tokio::test]
+    async fn test_read_consistent_waits_for_the_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config.json");
+        std::fs::write(&file, CONFIG).unwrap();
+        std::fs::write(sidecar(&file), line(CONFIG)).unwrap();
+        assert_eq!(read_verified(&file).await.unwrap(), CONFIG);
+
+        // The new file is in place, its sidecar comes a moment later
+        let updated = CONFIG.replace("1.0.0", "2.0.0");
+        std::fs::write(&file, &updated).unwrap();
+        let writer = {
+            let (sidecar, line) = (sidecar(&file), line(&updated));
+            tokio::spawn(async move {
+                tokio::time::sleep(fs_util::COMPANION_RETRY_DELAY * 2).await;
+                std::fs::write(sidecar, line).unwrap();
+            })
+        };
+        assert_eq!(read_verified(&file).await.unwrap(), updated);
+        writer.await.unwrap();
+
+        // A mismatch that persists fails after the retries
+        std::fs::write(&file, CONFIG).unwrap();
+        let error = read_verified(&file).await.unwrap_err();
+        assert_eq!(error.code(), "checksum_mismatch");
+    }
+}
@@ -2,6 +2,8 @@
 
 **Key Rust concepts**:
 - **`#[derive(Parser)]`**: Generates CLI parsing code
+- **`#[derive(Subcommand)]`**: Each enum variant becomes a subcommand
+- **`#[command(flatten)]`**: Reuses an `Args` struct inside another parser
 - **`#[command(...)]`**: Configures command metadata
 - **`#[arg(...)]`**: Configures individual arguments
 - **`anyhow::bail!`**: Early return with error (similar to `return Err()`)
@@ -9,13 +11,25 @@
 **Design decisions**:
 - Using long and short flags (`-f` and `--file`)
 - Providing sensible defaults
+- Watching stays the default: `config-watcher -f x.json` and
+  `config-watcher watch -f x.json` are equivalent
 - Validation in separate method for testability
 - Comprehensive help text for user experience
 
 ******************************************************************************/
 
-use clap::Parser;
-use std::path::PathBuf;
+use crate::annotations::AnnotationFormat;
+use crate::fs_util::Backups;
+use crate::logging::{Facility, LogFormat, LogLevel, LogTarget};
+use crate::output::Verbosity;
+use crate::versions::Selector;
+use clap::{
+    ArgAction, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+    ValueHint,
+};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 
 /// A tool to watch and validate JSON configuration files in real-time
 ///
@@ -27,43 +41,1925 @@ use std::path::PathBuf;
 #[command(author = "Your Name <your.email@example.com>")]
 #[command(version = "0.1.0")]
 #[command(about = "Watch and validate JSON configuration files", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Cli {
-    /// Path to the configuration file to watch
+    /// Subcommand to run (defaults to `watch`)
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Options used when no subcommand is given
+    #[command(flatten)]
+    pub watch: WatchArgs,
+
+    /// Diagnostics level for this crate (overrides RUST_LOG)
     ///
-    /// This should be a JSON file matching the expected schema
-    #[arg(short = 'f', long = "file", value_name = "FILE")]
-    pub config_file: PathBuf,
+    /// Diagnostics go to stderr; watch events are not affected
+    #[arg(long, global = true, value_enum, value_name = "LEVEL")]
+    pub log_level: Option<LogLevel>,
+
+    /// Format of the diagnostics on stderr
+    ///
+    /// With `json`, every stderr line is a JSON object, including watch
+    /// failures; the event output (`--output`) is not affected
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Write the diagnostics to this file instead of stderr, with rotation
+    ///
+    /// Falls back to stderr, with a warning, if the file cannot be opened
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Rotate the log file before it grows past this size (e.g. 512K, 10MiB)
+    #[arg(
+        long,
+        global = true,
+        value_name = "SIZE",
+        default_value = crate::log_file::DEFAULT_MAX_SIZE,
+        value_parser = crate::log_file::parse_size,
+        requires = "log_file"
+    )]
+    pub log_max_size: u64,
+
+    /// Number of rotated log files to keep (FILE.1 is the newest)
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        default_value_t = crate::log_file::DEFAULT_KEEP,
+        requires = "log_file"
+    )]
+    pub log_keep: u32,
+
+    /// Where the diagnostics go; syslog and journald are Unix only
+    ///
+    /// The system log also receives loads, failures, degraded state and
+    /// shutdown, each at its own priority
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = LogTarget::Stderr,
+        conflicts_with = "log_file"
+    )]
+    pub log_target: LogTarget,
+
+    /// Syslog facility for `--log-target syslog`
+    #[arg(long, global = true, value_enum, default_value_t = Facility::Daemon)]
+    pub syslog_facility: Facility,
+
+    /// Export reload spans and metrics to this OpenTelemetry collector
+    ///
+    /// OTLP/HTTP with JSON encoding (usually port 4318); the other
+    /// OTEL_EXPORTER_OTLP_* variables and OTEL_SERVICE_NAME apply too
+    #[arg(
+        long,
+        global = true,
+        value_name = "URL",
+        env = "OTEL_EXPORTER_OTLP_ENDPOINT",
+        value_hint = ValueHint::Url
+    )]
+    pub otlp_endpoint: Option<String>,
+}
+
+/// Available subcommands
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Watch a configuration file and validate it on every change
+    Watch(Box<WatchArgs>),
+
+    /// Watch a configuration file and run a command, restarted on every change
+    ///
+    /// The command gets the configuration in its environment
+    /// (CW__SERVER__PORT...) plus CONFIG_PATH and CONFIG_VERSION. Example:
+    /// config-watcher run -f config.json -- ./server --verbose
+    Run(Box<RunArgs>),
+
+    /// Load and validate a configuration file once, listing every finding
+    Validate(ValidateArgs),
+
+    /// Report style and plausibility issues that are not hard errors
+    Lint(LintArgs),
+
+    /// Print the value of a single field, addressed by dotted path
+    Get(GetArgs),
+
+    /// Print the effective configuration, every default filled in
+    Show(ShowArgs),
+
+    /// Change a single field and write the file back atomically
+    Set(SetArgs),
+
+    /// List the versions kept by --keep-versions, or write one back
+    ///
+    /// The version is validated again before it replaces the file,
+    /// atomically; a running watcher then loads it like any other change.
+    /// Example: config-watcher rollback -f config.json --state-dir state --to latest-1
+    Rollback(RollbackArgs),
+
+    /// Apply an RFC 6902 JSON Patch to a configuration file
+    Patch(PatchArgs),
+
+    /// Describe the known fields, their types and defaults
+    Explain(ExplainArgs),
+
+    /// Compare a configuration with another file or a committed revision
+    Diff(DiffArgs),
+
+    /// Show which layer (default, file, environment, command line) sets a field
+    ExplainSource(ExplainSourceArgs),
+
+    /// Validate the file, then check the resources it points at
+    Doctor(DoctorArgs),
+
+    /// Export a JSON Schema describing the configuration file
+    Schema(SchemaArgs),
+
+    /// Generate a Markdown reference of every configuration field
+    Docs(DocsArgs),
+
+    /// Check the status file of a running watcher (for container health checks)
+    Healthcheck(HealthcheckArgs),
+
+    /// Install, remove or run the Windows service (Windows only)
+    ///
+    /// Example, from an elevated prompt:
+    /// config-watcher service install -- -f C:\myapp\config.json --log-file C:\ProgramData\config-watcher.log
+    #[command(subcommand)]
+    Service(ServiceCommand),
+
+    /// Print a shell completion script
+    Completions(CompletionsArgs),
 
-    /// Check interval in seconds
+    /// Print completion candidates (used by the completion scripts)
+    #[command(name = "__complete", hide = true)]
+    Complete(CompleteArgs),
+}
+
+/// Options of the `run` command
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub watch: WatchArgs,
+
+    /// Time the command has to exit after SIGTERM before it is killed
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = crate::supervisor::DEFAULT_KILL_TIMEOUT,
+        value_parser = humantime::parse_duration
+    )]
+    pub kill_timeout: std::time::Duration,
+
+    /// Start the command again when it exits on its own
+    ///
+    /// Without it, the watcher stops with the command: status 0 after a
+    /// clean exit, 8 after a failure
+    #[arg(long)]
+    pub restart_on_exit: bool,
+
+    /// Prefix of the configuration variables in the command's environment
+    #[arg(long, value_name = "PREFIX", default_value = crate::env_config::DEFAULT_PREFIX)]
+    pub env_prefix: String,
+
+    /// The command to run and its arguments, after `--`
+    #[arg(last = true, required = true, value_name = "COMMAND")]
+    pub command: Vec<String>,
+}
+
+/// Options of the `watch` command
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+    /// Path to a configuration file to watch (may be repeated)
+    ///
+    /// This should be a JSON file matching the expected schema. With several
+    /// files, each keeps its own last valid configuration and output lines
+    /// are prefixed with the file name; a watcher error on any file stops
+    /// them all. When omitted, ./config.json, ./config/config.json and
+    /// $XDG_CONFIG_HOME/config-watcher/config.json are tried in order
+    #[arg(short = 'f', long = "file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub config_file: Vec<PathBuf>,
+
+    /// Build the configuration from PREFIX__* environment variables, no file
+    ///
+    /// Names follow the overrides: CW__APP_NAME, CW__SERVER__PORT,
+    /// CW__FEATURES__DARK_MODE (PREFIX defaults to CW; pass another one as
+    /// --from-env=APP). Loads once and exits unless --interval is given, in
+    /// which case the environment is re-read at that interval
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = crate::env_config::DEFAULT_PREFIX,
+        conflicts_with = "config_file"
+    )]
+    pub from_env: Option<String>,
+
+    /// Check interval in seconds [default: 2]
     ///
     /// How frequently to check if the file has been modified
-    #[arg(short = 'i', long = "interval", default_value = "2", value_name = "SECONDS")]
-    pub interval: u64,
+    #[arg(
+        short = 'i',
+        long = "interval",
+        value_name = "SECONDS",
+        env = "CONFIG_WATCHER_INTERVAL"
+    )]
+    pub interval: Option<u64>,
+
+    /// Load and validate once, print the summary, then exit (0 if valid)
+    #[arg(long)]
+    pub once: bool,
+
+    /// Configuration to run on when the file cannot be loaded at startup
+    /// (missing, unparseable or invalid)
+    ///
+    /// The fallback goes through the same checks as the file. The file is
+    /// still watched and replaces the fallback as soon as it is valid; the
+    /// fallback is never used again after that. Status, health and
+    /// heartbeats report "fallback" meanwhile. Needs a single watched file
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        conflicts_with = "from_env",
+        env = "CONFIG_WATCHER_FALLBACK_CONFIG"
+    )]
+    pub fallback_config: Option<PathBuf>,
+
+    /// Existing directory where the last good configuration of each file
+    /// is saved, and restored from when the file cannot be loaded at
+    /// startup
+    ///
+    /// Saved after every accepted load, atomically and with mode 0600 (it
+    /// holds the configuration in clear). The restored configuration is
+    /// served until the file is valid, like --fallback-config, which it
+    /// takes precedence over; status, health and heartbeats report
+    /// "restored" meanwhile. A corrupted snapshot is ignored with a warning
+    #[arg(
+        long,
+        value_name = "DIR",
+        value_hint = ValueHint::DirPath,
+        env = "CONFIG_WATCHER_STATE_DIR"
+    )]
+    pub state_dir: Option<PathBuf>,
+
+    /// Also keep a copy of the N last configurations of the file in
+    /// STATE_DIR/versions, for `config-watcher rollback`
+    ///
+    /// The file is copied as written, `age:` values still encrypted, after
+    /// every accepted load that changed it; the oldest copies go beyond N.
+    /// Needs --state-dir and a single watched file
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "state_dir",
+        conflicts_with = "from_env",
+        value_parser = clap::value_parser!(u32).range(1..),
+        env = "CONFIG_WATCHER_KEEP_VERSIONS"
+    )]
+    pub keep_versions: Option<u32>,
+
+    /// Stage changes in FILE.next: validate it whenever it appears or
+    /// changes, and only if it passes, rename it over FILE and apply it
+    ///
+    /// The candidate goes through the same checks as the file and takes
+    /// its permissions (not its owner). A rejected candidate is reported
+    /// ("rejected" event, with its findings) and both files stay as they
+    /// are. Direct edits of FILE keep working. The companion files of
+    /// --verify-checksum and --verify-signature would not follow the
+    /// rename, hence the conflicts
+    #[arg(
+        long,
+        conflicts_with_all = ["from_env", "verify_checksum", "verify_signature"],
+        env = "CONFIG_WATCHER_PROMOTE_NEXT"
+    )]
+    pub promote_next: bool,
+
+    #[command(flatten)]
+    pub backup: BackupArgs,
+
+    /// Ask on the terminal before applying each change: the diff, then
+    /// "apply this configuration? [y/N/d(etails)]"
+    ///
+    /// Only a yes applies the change and fires the notifiers. A declined
+    /// configuration is not asked about again until the file changes
+    /// further. Stdin must be a terminal; the prompt goes to stderr
+    #[arg(long, conflicts_with = "once", env = "CONFIG_WATCHER_CONFIRM")]
+    pub confirm: bool,
+
+    /// How long --confirm waits for an answer, e.g. `5m` (default: as long
+    /// as it takes)
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = humantime::parse_duration,
+        requires = "confirm",
+        env = "CONFIG_WATCHER_CONFIRM_TIMEOUT"
+    )]
+    pub confirm_timeout: Option<std::time::Duration>,
+
+    /// What an unanswered --confirm does once --confirm-timeout elapses
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ANSWER",
+        default_value_t = OnConfirmTimeout::Reject,
+        requires = "confirm_timeout",
+        env = "CONFIG_WATCHER_ON_CONFIRM_TIMEOUT"
+    )]
+    pub on_confirm_timeout: OnConfirmTimeout,
+
+    /// Exit with code 3 on the first reload failure after a good load
+    ///
+    /// Parse errors, validation errors and a removed file all count, on any
+    /// of the watched files. A file that fails is re-read once after a short
+    /// delay first, so a save in progress does not trigger it
+    #[arg(long, env = "CONFIG_WATCHER_FAIL_FAST")]
+    pub fail_fast: bool,
+
+    /// Stop watching after this long, e.g. `30s` or `5m`
+    ///
+    /// Shuts down like Ctrl+C does. The exit code is 0 unless
+    /// --max-duration-exit-code says otherwise
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = humantime::parse_duration,
+        env = "CONFIG_WATCHER_MAX_DURATION"
+    )]
+    pub max_duration: Option<std::time::Duration>,
+
+    /// Exit code used when --max-duration ends the watch
+    #[arg(
+        long,
+        value_name = "CODE",
+        default_value = "0",
+        requires = "max_duration",
+        env = "CONFIG_WATCHER_MAX_DURATION_EXIT_CODE"
+    )]
+    pub max_duration_exit_code: u8,
+
+    /// Longest cleanup once the watch stops (signal, --max-duration...)
+    ///
+    /// Stopping the `run` command, draining the notifier queue, removing
+    /// the status and pid files, closing the servers. Past it, what is
+    /// left is abandoned and the exit code is 10
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = crate::shutdown::DEFAULT_TIMEOUT,
+        value_parser = humantime::parse_duration,
+        env = "CONFIG_WATCHER_SHUTDOWN_TIMEOUT"
+    )]
+    pub shutdown_timeout: std::time::Duration,
+
+    /// Show a live dashboard instead of the usual output
+    ///
+    /// Header with app, version, environment and health; the state of each
+    /// file; the last diff; the recent events, scrollable with j/k or the
+    /// arrows. Keys: q quit, p pause/resume, r reload, d dump. Without a
+    /// terminal on stdin and stdout (or off Unix), the usual output
+    #[arg(
+        long,
+        conflicts_with_all = ["once", "confirm", "no_keys"],
+        env = "CONFIG_WATCHER_TUI"
+    )]
+    pub tui: bool,
+
+    /// Do not read single-key commands from the terminal
+    ///
+    /// By default, when stdin is a terminal, `r` reloads, `d` prints a
+    /// status dump, `p` pauses or resumes the checks, `q` stops and `?`
+    /// lists the keys. Never read with --confirm, which asks on stdin
+    #[arg(long, env = "CONFIG_WATCHER_NO_KEYS")]
+    pub no_keys: bool,
+
+    /// Print a summary of the watcher's state this often, e.g. `10m`
+    ///
+    /// Config version, app, health, time since the last change and counts
+    /// since the previous heartbeat; `0` (the default) disables it
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = humantime::parse_duration,
+        env = "CONFIG_WATCHER_HEARTBEAT"
+    )]
+    pub heartbeat: Option<std::time::Duration>,
+
+    /// Run this shell command after every reload that changes the config
+    ///
+    /// It gets CONFIG_PATH, CONFIG_VERSION, APP_NAME, ENVIRONMENT and
+    /// CHANGED_PATHS (comma-separated) in its environment and the new
+    /// configuration as JSON on stdin. A failure is reported as a warning;
+    /// reloads during a run queue a single new run
+    #[arg(long, value_name = "CMD", env = "CONFIG_WATCHER_ON_CHANGE")]
+    pub on_change: Option<String>,
+
+    /// Kill the --on-change command after this long
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = crate::hook::DEFAULT_TIMEOUT,
+        value_parser = humantime::parse_duration,
+        requires = "on_change",
+        env = "CONFIG_WATCHER_ON_CHANGE_TIMEOUT"
+    )]
+    pub on_change_timeout: std::time::Duration,
+
+    /// POST a JSON payload to this http:// or https:// URL on
+    /// configuration events
+    ///
+    /// The payload is the JSON event (secrets redacted) plus the host
+    /// name. Delivery runs in the background and never delays watching,
+    /// through the proxy of the configuration's `proxy` section
+    #[arg(
+        long,
+        value_name = "URL",
+        value_parser = crate::http_client::parse_url,
+        env = "CONFIG_WATCHER_WEBHOOK_URL"
+    )]
+    pub webhook_url: Option<url::Url>,
+
+    /// Events sent to --webhook-url, comma-separated
+    #[arg(
+        long,
+        value_enum,
+        value_name = "EVENTS",
+        value_delimiter = ',',
+        default_value = "reload,failure",
+        requires = "webhook_url",
+        env = "CONFIG_WATCHER_WEBHOOK_EVENTS"
+    )]
+    pub webhook_events: Vec<EventKind>,
+
+    /// Sign webhook payloads with HMAC-SHA256 and this secret
+    ///
+    /// The signature is sent as `X-Signature-256: sha256=<hex>`
+    #[arg(
+        long,
+        value_name = "SECRET",
+        requires = "webhook_url",
+        hide_env_values = true,
+        env = "CONFIG_WATCHER_WEBHOOK_SECRET"
+    )]
+    pub webhook_secret: Option<String>,
+
+    /// Give up on a webhook request after this long
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = crate::webhook::DEFAULT_TIMEOUT,
+        value_parser = humantime::parse_duration,
+        requires = "webhook_url",
+        env = "CONFIG_WATCHER_WEBHOOK_TIMEOUT"
+    )]
+    pub webhook_timeout: std::time::Duration,
+
+    /// Post reloads and failures to this Slack incoming webhook
+    ///
+    /// Usually https://hooks.slack.com/services/..., reached through the
+    /// proxy of the configuration's `proxy` section
+    #[arg(
+        long,
+        value_name = "URL",
+        value_parser = crate::http_client::parse_url,
+        env = "CONFIG_WATCHER_SLACK_WEBHOOK_URL"
+    )]
+    pub slack_webhook_url: Option<url::Url>,
+
+    /// Post into this channel instead of the webhook's own
+    #[arg(
+        long,
+        value_name = "CHANNEL",
+        requires = "slack_webhook_url",
+        env = "CONFIG_WATCHER_SLACK_CHANNEL"
+    )]
+    pub slack_channel: Option<String>,
+
+    /// TOML file with `reload` and/or `failure` message templates
+    ///
+    /// Placeholders: {app_name}, {version}, {environment}, {host}, {file},
+    /// {changes}, {changed_paths}, {error}, {findings}, {event}
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "slack_webhook_url",
+        env = "CONFIG_WATCHER_SLACK_TEMPLATE"
+    )]
+    pub slack_template: Option<PathBuf>,
+
+    /// Publish loads and failures to this MQTT broker
+    /// (mqtt://[user:password@]host[:1883], or mqtts://...[:8883] over TLS)
+    ///
+    /// Each load publishes the redacted configuration, retained; a failed
+    /// load publishes its error, not retained. An unreachable broker is
+    /// retried in the background and never delays watching (needs a build
+    /// with the `mqtt` feature)
+    #[arg(
+        long,
+        value_name = "URL",
+        hide_env_values = true,
+        env = "CONFIG_WATCHER_MQTT_URL"
+    )]
+    pub mqtt_url: Option<url::Url>,
+
+    /// Topic to publish to; {app_name} and {environment} come from the
+    /// loaded configuration
+    #[arg(
+        long,
+        value_name = "TOPIC",
+        default_value = "config/{app_name}/events",
+        requires = "mqtt_url",
+        env = "CONFIG_WATCHER_MQTT_TOPIC"
+    )]
+    pub mqtt_topic: String,
+
+    /// MQTT quality of service: 0 (at most once) or 1 (at least once)
+    #[arg(
+        long,
+        value_name = "QOS",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u8).range(0..=1),
+        requires = "mqtt_url",
+        env = "CONFIG_WATCHER_MQTT_QOS"
+    )]
+    pub mqtt_qos: u8,
+
+    /// Announce each load on a Redis channel
+    /// (redis://[[user]:password@]host[:6379][/db], or rediss://... over
+    /// TLS)
+    ///
+    /// PUBLISH of a compact JSON line: event, file, version, timestamp and
+    /// changed_paths. Redis never delays watching; failures are logged
+    /// (needs a build with the `redis` feature)
+    #[arg(
+        long,
+        value_name = "URL",
+        hide_env_values = true,
+        env = "CONFIG_WATCHER_REDIS_URL"
+    )]
+    pub redis_url: Option<url::Url>,
+
+    /// Channel to publish on; {app_name} and {environment} come from the
+    /// loaded configuration
+    #[arg(
+        long,
+        value_name = "CHANNEL",
+        default_value = "config:{app_name}",
+        requires = "redis_url",
+        env = "CONFIG_WATCHER_REDIS_CHANNEL"
+    )]
+    pub redis_channel: String,
+
+    /// Also SET the latest announcement at this key, for instances that
+    /// start later (same placeholders as --redis-channel)
+    #[arg(
+        long,
+        value_name = "KEY",
+        requires = "redis_url",
+        env = "CONFIG_WATCHER_REDIS_VERSION_KEY"
+    )]
+    pub redis_version_key: Option<String>,
+
+    /// Add the configuration (secrets redacted) to Redis announcements
+    #[arg(
+        long,
+        requires = "redis_url",
+        env = "CONFIG_WATCHER_REDIS_INCLUDE_CONFIG"
+    )]
+    pub redis_include_config: bool,
+
+    /// Rewrite this environment file after every load
+    ///
+    /// One NAME=value line per field, named like --from-env reads them
+    /// (CW__SERVER__PORT), replaced atomically. Secrets are left out. A
+    /// failed write is logged; the configuration is still accepted
+    #[arg(long, value_name = "FILE", env = "CONFIG_WATCHER_EXPORT_ENV")]
+    pub export_env: Option<PathBuf>,
+
+    /// dotenv (NAME="value") or shell (export NAME='value', for `source`)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t,
+        requires = "export_env",
+        env = "CONFIG_WATCHER_EXPORT_ENV_FORMAT"
+    )]
+    pub export_env_format: EnvFormat,
+
+    /// Prefix of the exported variable names
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        default_value = crate::env_config::DEFAULT_PREFIX,
+        requires = "export_env",
+        env = "CONFIG_WATCHER_EXPORT_ENV_PREFIX"
+    )]
+    pub export_env_prefix: String,
+
+    /// Also export secret fields (e.g. database.connection_string)
+    #[arg(
+        long,
+        requires = "export_env",
+        env = "CONFIG_WATCHER_EXPORT_ENV_SECRETS"
+    )]
+    pub export_env_secrets: bool,
+
+    /// Write the effective configuration to this file after every load
+    ///
+    /// Defaults materialized and overrides applied, as `show
+    /// --reveal-secrets` prints it; pretty JSON, mode 600, replaced
+    /// atomically and only when its content changes. Secrets included
+    #[arg(long, value_name = "FILE", env = "CONFIG_WATCHER_WRITE_NORMALIZED")]
+    pub write_normalized: Option<PathBuf>,
+
+    /// Redact secrets in the --write-normalized file
+    #[arg(
+        long,
+        requires = "write_normalized",
+        env = "CONFIG_WATCHER_WRITE_NORMALIZED_REDACTED"
+    )]
+    pub write_normalized_redacted: bool,
+
+    /// Commit the watched file to its git repository after every reload
+    ///
+    /// Only configurations that passed validation are committed, with a
+    /// message naming the version and the changed fields. The file must be
+    /// in a git work tree; a failed commit is a warning
+    #[arg(long, env = "CONFIG_WATCHER_GIT_AUTOCOMMIT")]
+    pub git_autocommit: bool,
+
+    /// Send --signal to this process after every reload (Unix only)
+    ///
+    /// For daemons that reload on a signal, like nginx or haproxy. A
+    /// process that is gone or not ours is reported, never fatal
+    #[arg(long, value_name = "PID", conflicts_with = "signal_pidfile")]
+    pub signal_pid: Option<u32>,
+
+    /// Like --signal-pid, with the pid read from FILE at every reload
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub signal_pidfile: Option<PathBuf>,
+
+    /// Signal sent by --signal-pid / --signal-pidfile (SIGHUP, SIGUSR1...)
+    #[arg(long, value_name = "SIGNAL", default_value = "SIGHUP")]
+    pub signal: crate::signal_pid::Signal,
+
+    /// Minimum time between two signals; reloads in between give one signal
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = crate::signal_pid::DEFAULT_MIN_INTERVAL,
+        value_parser = humantime::parse_duration
+    )]
+    pub signal_min_interval: std::time::Duration,
+
+    /// Raise a desktop notification when a reload succeeds or fails
+    ///
+    /// Needs the `desktop-notify` feature and a notification daemon
+    /// (notify-send on Linux)
+    #[arg(long, env = "CONFIG_WATCHER_NOTIFY_DESKTOP")]
+    pub notify_desktop: bool,
+
+    /// At most one desktop notification per kind (reload, failure) this
+    /// often
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "10s",
+        value_parser = humantime::parse_duration,
+        requires = "notify_desktop",
+        env = "CONFIG_WATCHER_NOTIFY_INTERVAL"
+    )]
+    pub notify_interval: std::time::Duration,
+
+    /// At most COUNT/DURATION events for each notifier (--on-change,
+    /// --webhook-url, --notify-desktop), e.g. 10/1m
+    ///
+    /// A token bucket: bursts of COUNT, refilled over DURATION. Events
+    /// beyond it are dropped and counted
+    #[arg(
+        long,
+        value_name = "COUNT/DURATION",
+        env = "CONFIG_WATCHER_NOTIFY_RATE_LIMIT"
+    )]
+    pub notify_rate_limit: Option<crate::notify::RateLimit>,
+
+    /// More output: -v adds per-check lines, timings and change detection
+    /// decisions; -vv adds raw file metadata and state transitions
+    #[arg(short = 'v', long = "verbose", action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Entries of a long list shown in text output, e.g. feature flags
+    ///
+    /// Applies to the summary and to each category of a diff; the rest is
+    /// counted in a last line. -v shows ten times as many, -vv all of them
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = crate::listing::DEFAULT_THRESHOLD,
+        env = "CONFIG_WATCHER_LIST_LIMIT"
+    )]
+    pub list_limit: usize,
+
+    /// Only print errors and the final shutdown line
+    #[arg(short = 'q', long = "quiet", conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Also validate against this JSON Schema
+    ///
+    /// The schema is compiled at startup and reloaded when the file changes
+    #[arg(
+        long = "schema",
+        value_name = "SCHEMA_FILE",
+        value_hint = ValueHint::FilePath,
+        env = "CONFIG_WATCHER_SCHEMA"
+    )]
+    pub schema: Option<PathBuf>,
+
+    /// Check every load against the FILE.sha256 sidecar (`sha256sum`
+    /// format) before parsing
+    ///
+    /// A file that does not match, or has no readable sidecar, is rejected
+    /// and the last valid configuration kept. A disagreement is re-read a
+    /// few times first, for a writer between the two files
+    #[arg(long, env = "CONFIG_WATCHER_VERIFY_CHECKSUM")]
+    pub verify_checksum: bool,
+
+    /// Only load files signed with --public-key: FILE.minisig (minisign)
+    /// or FILE.sig (raw Ed25519) next to each file
+    ///
+    /// A missing or failing signature rejects the load and keeps the last
+    /// valid configuration
+    #[arg(long, requires = "public_key", env = "CONFIG_WATCHER_VERIFY_SIGNATURE")]
+    pub verify_signature: bool,
+
+    /// Public key for --verify-signature: a minisign public key, or a raw
+    /// Ed25519 key (base64 or 32 bytes)
+    #[arg(
+        long,
+        value_name = "KEY_FILE",
+        value_hint = ValueHint::FilePath,
+        requires = "verify_signature",
+        env = "CONFIG_WATCHER_PUBLIC_KEY"
+    )]
+    pub public_key: Option<PathBuf>,
+
+    /// Decrypt `age:<base64>` string values with the identities of FILE,
+    /// as written by `age-keygen`; can be repeated
+    ///
+    /// A value that does not decrypt rejects the load and keeps the last
+    /// valid configuration; decrypted values are redacted like secrets
+    /// (needs a build with the `age` feature)
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        env = "CONFIG_WATCHER_AGE_IDENTITY"
+    )]
+    pub age_identity: Vec<PathBuf>,
+
+    /// How secret files with loose permissions are reported on every load:
+    /// `*_file` and TLS key fields, and --age-identity files
+    ///
+    /// A secret file readable by group or others, or not owned by the
+    /// current user, is an error (the load is rejected) or a warning; a
+    /// group- or world-writable configuration is always a warning. Unix
+    /// only: Windows has no mode bits to check
+    #[arg(
+        long,
+        value_name = "LEVEL",
+        default_value = "error",
+        env = "CONFIG_WATCHER_PERMISSION_AUDIT"
+    )]
+    pub permission_audit: PermissionLevel,
+
+    /// Override a field after every load, e.g. `server.port=9090`
+    ///
+    /// The value's JSON type is inferred (numbers, booleans, null, quoted
+    /// strings); may be repeated
+    #[arg(long = "override", value_name = "PATH=VALUE")]
+    pub overrides: Vec<String>,
+
+    /// Like --override, but the value is always a string
+    #[arg(long = "override-string", value_name = "PATH=VALUE")]
+    pub override_strings: Vec<String>,
+
+    /// Re-read CW_OVERRIDE__* environment variables on every reload
+    ///
+    /// By default they are collected once at startup
+    #[arg(long = "reread-env", env = "CONFIG_WATCHER_REREAD_ENV")]
+    pub reread_env: bool,
+
+    /// Output format of watch events
+    ///
+    /// `json` prints one JSON object per line on stdout (NDJSON), with
+    /// secrets redacted
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = OutputFormat::Text,
+        env = "CONFIG_WATCHER_OUTPUT"
+    )]
+    pub output: OutputFormat,
+
+    /// When to use colors and emoji in text output, by every subcommand
+    ///
+    /// `auto` uses them when stdout is a terminal and NO_COLOR is unset;
+    /// otherwise lines start with ASCII tags such as [OK] and [ERR]
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "WHEN",
+        default_value_t = ColorChoice::Auto,
+        env = "CONFIG_WATCHER_COLOR"
+    )]
+    pub color: ColorChoice,
+
+    /// Timestamp at the start of every text event line
+    ///
+    /// `relative` counts seconds since startup; `off` prints no timestamp.
+    /// JSON events always carry an RFC 3339 UTC `timestamp`
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = TimestampFormat::Rfc3339,
+        env = "CONFIG_WATCHER_TIMESTAMP"
+    )]
+    pub timestamp: TimestampFormat,
+
+    /// Timestamps in UTC instead of local time
+    #[arg(long, env = "CONFIG_WATCHER_UTC")]
+    pub utc: bool,
+
+    /// How parse and validation failures are reported
+    ///
+    /// `json` prints one object per failure on stderr: a stable error code,
+    /// the file, and every finding with its position when known
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = ErrorFormat::Text,
+        env = "CONFIG_WATCHER_ERROR_FORMAT"
+    )]
+    pub error_format: ErrorFormat,
+
+    /// Rewrite this file after every check with the watcher's health
+    ///
+    /// JSON with the pid, the overall health and, per file, the version,
+    /// the last successful and failed reloads and the last error. Written
+    /// atomically and removed on a clean shutdown; read it with
+    /// `config-watcher healthcheck`
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub status_file: Option<PathBuf>,
+
+    /// Permissions of the status file, in octal, e.g. 600 (Unix only)
+    #[arg(
+        long,
+        value_name = "MODE",
+        default_value = "644",
+        value_parser = crate::status::parse_mode,
+        requires = "status_file"
+    )]
+    pub status_file_mode: u32,
+
+    /// Watch even when another instance already watches the same file
+    ///
+    /// By default each watched file is locked (flock/LockFileEx) for the
+    /// lifetime of the watcher, and a second instance exits with code 9,
+    /// naming the pid of the first. `--once` takes no lock
+    #[arg(long, env = "CONFIG_WATCHER_NO_LOCK")]
+    pub no_lock: bool,
+
+    /// Directory of the lock files, one per watched file (default: the
+    /// state directory, else $XDG_RUNTIME_DIR, else a private directory of
+    /// the system temporary directory)
+    #[arg(
+        long,
+        value_name = "DIR",
+        value_hint = ValueHint::DirPath,
+        conflicts_with = "no_lock",
+        env = "CONFIG_WATCHER_LOCK_DIR"
+    )]
+    pub lock_dir: Option<PathBuf>,
+
+    /// Write the pid of this process to PATH, for init systems; removed on
+    /// shutdown
+    ///
+    /// Refused when the file names a process that is still running (exit
+    /// code 9); the file of a process that died is taken over. A relative
+    /// PATH is resolved against the working directory at startup
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_hint = ValueHint::FilePath,
+        env = "CONFIG_WATCHER_PID_FILE"
+    )]
+    pub pid_file: Option<PathBuf>,
+
+    /// Run in the background, detached from the terminal (Unix)
+    ///
+    /// Double fork and new session; stdin is /dev/null, stdout and stderr
+    /// go to --log-file, which is required. Relative paths are resolved,
+    /// then the working directory becomes /. The command returns once the
+    /// daemon is up (locks and --pid-file in place), printing its pid, or
+    /// with the code and message of its startup failure. Not supported by
+    /// `run`
+    #[arg(
+        long,
+        conflicts_with_all = ["once", "confirm"],
+        env = "CONFIG_WATCHER_DAEMONIZE"
+    )]
+    pub daemonize: bool,
+
+    /// Confine the watcher to the paths it needs, with Landlock (Linux)
+    ///
+    /// Read access to the watched files' directories and the files given
+    /// by flags, write access only to the directories of the files it
+    /// writes (status, audit, logs, exports, locks, state). Commands
+    /// cannot run, hence the conflicting flags. Without Landlock in the
+    /// kernel, the watcher runs unconfined with a warning
+    #[arg(
+        long,
+        env = "CONFIG_WATCHER_SANDBOX",
+        conflicts_with_all = ["on_change", "event_db", "git_autocommit", "notify_desktop"]
+    )]
+    pub sandbox: bool,
+
+    /// Serve Prometheus metrics on this address, at /metrics
+    ///
+    /// Reload counts by outcome, reload durations, the loaded version and
+    /// watch errors; no configuration value other than app_name, version
+    /// and environment is exposed
+    #[arg(long, value_name = "ADDR")]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Serve the configuration and status over HTTP on this address
+    ///
+    /// GET /config returns the current configuration, secrets redacted
+    /// (503 until one is loaded); GET /status returns the health, version,
+    /// reload times and failure counts of each file. There is no
+    /// authentication: prefer 127.0.0.1
+    #[arg(long, value_name = "ADDR", env = "CONFIG_WATCHER_HTTP_ADDR")]
+    pub http_addr: Option<SocketAddr>,
+
+    /// Serve only the /livez and /readyz probes on this address
+    ///
+    /// /livez fails (500) when a watch loop has not ticked for 3 intervals;
+    /// /readyz fails (503) until a valid configuration is loaded, and after
+    /// --ready-max-failures failed checks in a row. Also served on
+    /// --http-addr
+    #[arg(long, value_name = "ADDR", env = "CONFIG_WATCHER_HEALTH_ADDR")]
+    pub health_addr: Option<SocketAddr>,
+
+    /// Failed checks in a row after which /readyz fails even though an
+    /// older configuration is still served
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = crate::probes::DEFAULT_MAX_FAILURES,
+        env = "CONFIG_WATCHER_READY_MAX_FAILURES"
+    )]
+    pub ready_max_failures: u64,
+
+    /// Under systemd (Type=notify), report readiness only once every file
+    /// has loaded successfully
+    ///
+    /// By default READY=1 is sent after the first load attempt, valid or not
+    #[arg(long, env = "CONFIG_WATCHER_REQUIRE_INITIAL")]
+    pub require_initial: bool,
+
+    /// Accept PUT /config: a JSON Merge Patch (RFC 7386) applied to the file
+    ///
+    /// The patched file is validated, then written atomically; the watcher
+    /// reloads it at its next check. Needs --http-token and a single
+    /// watched file
+    #[arg(
+        long,
+        requires_all = ["http_addr", "http_token"],
+        env = "CONFIG_WATCHER_HTTP_ALLOW_WRITE"
+    )]
+    pub http_allow_write: bool,
+
+    /// Bearer token that PUT /config requests must carry
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        requires = "http_addr",
+        hide_env_values = true,
+        env = "CONFIG_WATCHER_HTTP_TOKEN"
+    )]
+    pub http_token: Option<String>,
+
+    /// Serve GetConfig and WatchConfig over gRPC on this address
+    ///
+    /// The config_watcher.v1.ConfigWatcher service of
+    /// proto/config_watcher.proto, over HTTP/2 without TLS. Configurations
+    /// are redacted. No authentication: prefer 127.0.0.1 (needs a build
+    /// with the `grpc` feature)
+    #[arg(long, value_name = "ADDR", env = "CONFIG_WATCHER_GRPC_ADDR")]
+    pub grpc_addr: Option<SocketAddr>,
+
+    /// Push the configuration to clients of this Unix socket
+    ///
+    /// Each client gets length-prefixed JSON frames (4-byte big-endian
+    /// length): the current configuration of every file on connect, then
+    /// one per reload that changes it. A client that stops reading is
+    /// disconnected. The socket file is removed on exit (Unix only)
+    #[arg(long, value_name = "PATH", env = "CONFIG_WATCHER_PUSH_SOCKET")]
+    pub push_socket: Option<PathBuf>,
+
+    /// Permissions of the push socket, in octal: who may connect
+    #[arg(
+        long,
+        value_name = "MODE",
+        default_value = "660",
+        value_parser = crate::status::parse_mode,
+        requires = "push_socket"
+    )]
+    pub push_socket_mode: u32,
+
+    /// Redact secrets in the pushed configuration
+    #[arg(long, requires = "push_socket", env = "CONFIG_WATCHER_PUSH_REDACT")]
+    pub push_redact: bool,
+
+    /// Let push socket clients send RELOAD to reload every file at once
+    #[arg(
+        long,
+        requires = "push_socket",
+        env = "CONFIG_WATCHER_PUSH_ALLOW_RELOAD"
+    )]
+    pub push_allow_reload: bool,
+
+    /// Append every configuration event to this JSONL file
+    ///
+    /// Loads, accepted and rejected reloads (with the field-level diff,
+    /// secrets redacted, or the error), file errors, start and shutdown.
+    /// Created if missing; the watcher refuses to start if it cannot be
+    /// opened
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub audit_log: Option<PathBuf>,
+
+    /// Roll the audit log over before it grows past this size (e.g. 100MiB)
+    #[arg(
+        long,
+        value_name = "SIZE",
+        default_value = crate::log_file::DEFAULT_MAX_SIZE,
+        value_parser = crate::log_file::parse_size,
+        requires = "audit_log"
+    )]
+    pub audit_max_size: u64,
+
+    /// Number of rolled-over audit logs to keep (FILE.1 is the newest)
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = crate::log_file::DEFAULT_KEEP,
+        requires = "audit_log"
+    )]
+    pub audit_keep: u32,
+
+    /// Record every event in this SQLite database (created if missing)
+    ///
+    /// Table `events`: timestamp, event, file, version, diff, error and the
+    /// whole JSON event. Needs a build with the `event-db` feature; database
+    /// errors are logged and never stop the watcher
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub event_db: Option<PathBuf>,
+
+    /// Delete events older than this from the database (e.g. 90d)
+    ///
+    /// Checked at startup, then every hour; events are kept forever by
+    /// default
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = humantime::parse_duration,
+        requires = "event_db"
+    )]
+    pub event_db_keep: Option<std::time::Duration>,
+
+    /// Print the effective watcher settings and where they come from, then exit
+    ///
+    /// Settings are read from ./.config-watcher.toml or
+    /// $XDG_CONFIG_HOME/config-watcher/config.toml; command-line flags win
+    /// over CONFIG_WATCHER_* variables, which win over the settings file
+    #[arg(long)]
+    pub show_settings: bool,
+}
+
+/// Options of the `validate` command
+#[derive(Args, Debug)]
+pub struct ValidateArgs {
+    /// Path to the configuration file to validate
+    #[arg(
+        short = 'f',
+        long = "file",
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        required_unless_present = "stdin",
+        conflicts_with = "stdin"
+    )]
+    pub config_file: Option<PathBuf>,
+
+    /// Read the document from stdin instead (e.g. staged content in a pre-commit hook)
+    #[arg(long, requires = "assume_path")]
+    pub stdin: bool,
+
+    /// Path the stdin document stands for: used for format detection,
+    /// relative file references and error reports
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath, requires = "stdin")]
+    pub assume_path: Option<PathBuf>,
+
+    /// Also validate against this JSON Schema
+    #[arg(long = "schema", value_name = "SCHEMA_FILE", value_hint = ValueHint::FilePath)]
+    pub schema: Option<PathBuf>,
+
+    /// How a failure is reported; `json` prints one object on stdout
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = ErrorFormat::Text)]
+    pub error_format: ErrorFormat,
 
-    /// Enable verbose output
+    /// Also write the findings as a SARIF 2.1.0 log, even when the file is valid
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub report_sarif: Option<PathBuf>,
+
+    /// Print findings as CI annotations on stdout
     ///
-    /// Shows detailed information about configuration changes
-    #[arg(short = 'v', long = "verbose")]
-    pub verbose: bool,
+    /// Defaults to `github` when GITHUB_ACTIONS=true, `none` otherwise.
+    /// Human-readable output then goes to stderr
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub output_annotations: Option<AnnotationFormat>,
+}
+
+/// Options of the `lint` command
+#[derive(Args, Debug)]
+pub struct LintArgs {
+    /// Path to the configuration file to lint
+    #[arg(short = 'f', long = "file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub config_file: PathBuf,
+
+    /// Report this rule as an error (repeatable)
+    #[arg(
+        long,
+        value_name = "RULE",
+        value_parser = clap::builder::PossibleValuesParser::new(crate::lint::rule_ids())
+    )]
+    pub deny: Vec<String>,
+
+    /// Also write the findings as a SARIF 2.1.0 log
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub report_sarif: Option<PathBuf>,
+}
+
+/// Options of the `get` command
+#[derive(Args, Debug)]
+pub struct GetArgs {
+    /// Path to the configuration file to read
+    #[arg(short = 'f', long = "file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub config_file: PathBuf,
+
+    /// Dotted path of the field, e.g. `server.port` or `features["a.b"]`
+    #[arg(value_name = "PATH")]
+    pub path: String,
+
+    /// Print strings without quotes (handy in `$(...)`)
+    #[arg(long)]
+    pub raw: bool,
+
+    /// Value to print when the path does not exist
+    #[arg(long, value_name = "VALUE")]
+    pub default: Option<String>,
+
+    /// Print secret values instead of redacting them
+    #[arg(long)]
+    pub reveal_secrets: bool,
+}
+
+/// Options of the `diff` command
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// The configuration file to compare, the new side
+    #[arg(short = 'f', long = "file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub config_file: PathBuf,
+
+    /// Compare with this file, the old side
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        required_unless_present = "against_git",
+        conflicts_with = "against_git"
+    )]
+    pub against: Option<PathBuf>,
+
+    /// Compare with the file as committed at REV (HEAD, a tag, main~3...)
+    #[arg(long, value_name = "REV")]
+    pub against_git: Option<String>,
+
+    /// Output format
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+}
+
+/// Options of the `show` command
+#[derive(Args, Debug)]
+pub struct ShowArgs {
+    /// Path to the configuration file to resolve
+    #[arg(short = 'f', long = "file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub config_file: PathBuf,
+
+    /// Output format
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = ShowFormat::Json)]
+    pub format: ShowFormat,
+
+    /// Print secret values instead of redacting them
+    #[arg(long)]
+    pub reveal_secrets: bool,
+
+    /// List every field as explicitly set or taken from its default
+    #[arg(long)]
+    pub diff_defaults: bool,
+
+    /// Override a field, as for `watch`, e.g. `server.port=9090`
+    #[arg(long = "override", value_name = "PATH=VALUE")]
+    pub overrides: Vec<String>,
+
+    /// Like --override, but the value is always a string
+    #[arg(long = "override-string", value_name = "PATH=VALUE")]
+    pub override_strings: Vec<String>,
+}
+
+/// Formats of the `show` output
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShowFormat {
+    /// Pretty-printed JSON
+    #[default]
+    Json,
+    /// TOML (absent optional values are omitted)
+    Toml,
+}
+
+/// Options of the `set` command
+#[derive(Args, Debug)]
+pub struct SetArgs {
+    /// Path to the configuration file to modify
+    #[arg(short = 'f', long = "file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub config_file: PathBuf,
+
+    /// Dotted path of the field, e.g. `server.port`
+    #[arg(value_name = "PATH")]
+    pub path: String,
+
+    /// New value (numbers, booleans and null are detected automatically)
+    #[arg(value_name = "VALUE", allow_hyphen_values = true)]
+    pub value: String,
+
+    /// How to interpret VALUE
+    #[arg(long = "type", value_enum, default_value_t = ValueType::Auto)]
+    pub value_type: ValueType,
+
+    /// Write the file even if the result does not validate
+    #[arg(long)]
+    pub no_validate: bool,
+
+    #[command(flatten)]
+    pub backup: BackupArgs,
+}
+
+/// Options of the `rollback` command
+#[derive(Args, Debug)]
+pub struct RollbackArgs {
+    /// Path to the watched configuration file
+    #[arg(short = 'f', long = "file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub config_file: PathBuf,
+
+    /// The --state-dir of the watcher
+    #[arg(
+        long,
+        value_name = "DIR",
+        value_hint = ValueHint::DirPath,
+        env = "CONFIG_WATCHER_STATE_DIR"
+    )]
+    pub state_dir: PathBuf,
+
+    /// The version to write back: its number, `latest`, or `latest-N` for
+    /// N versions before the newest
+    #[arg(
+        long,
+        value_name = "VERSION",
+        required_unless_present = "list",
+        conflicts_with = "list"
+    )]
+    pub to: Option<Selector>,
+
+    /// List the kept versions, newest first
+    #[arg(long)]
+    pub list: bool,
+
+    #[command(flatten)]
+    pub backup: BackupArgs,
+}
+
+/// Backups of the commands that rewrite the configuration file (`set`,
+/// `patch`, `rollback`, and `watch` for --promote-next and PUT /config)
+#[derive(Args, Debug, Clone, Copy)]
+pub struct BackupArgs {
+    /// Backups of the file kept before each rewrite: FILE.bak, or
+    /// FILE.bak.1 (newest) to FILE.bak.K for more than one
+    ///
+    /// A backup has the permissions and modification time of the file it
+    /// copies
+    #[arg(
+        long,
+        value_name = "K",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..),
+        env = "CONFIG_WATCHER_BACKUPS"
+    )]
+    pub backups: u32,
+
+    /// Rewrite the file without backing it up first
+    #[arg(long, conflicts_with = "backups", env = "CONFIG_WATCHER_NO_BACKUP")]
+    pub no_backup: bool,
+}
+
+impl BackupArgs {
+    /// The backups to keep
+    pub fn backups(&self) -> Backups {
+        if self.no_backup {
+            Backups::NONE
+        } else {
+            Backups(self.backups)
+        }
+    }
+}
+
+/// Options of the `patch` command
+#[derive(Args, Debug)]
+pub struct PatchArgs {
+    /// Path to the configuration file to modify
+    #[arg(short = 'f', long = "file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub config_file: PathBuf,
+
+    /// JSON Patch document: an array of add/remove/replace/move/copy/test operations
+    #[arg(long, value_name = "PATCH_FILE", value_hint = ValueHint::FilePath)]
+    pub patch: PathBuf,
+
+    /// Write the file even if the result does not validate
+    #[arg(long)]
+    pub no_validate: bool,
+
+    /// Print the patched document instead of writing it
+    #[arg(long)]
+    pub dry_run: bool,
+
+    #[command(flatten)]
+    pub backup: BackupArgs,
+}
+
+/// Options of the `explain-source` command
+#[derive(Args, Debug)]
+pub struct ExplainSourceArgs {
+    /// Dotted path of the field, e.g. `server.port`
+    #[arg(value_name = "PATH")]
+    pub path: String,
+
+    /// Path to the configuration file
+    #[arg(short = 'f', long = "file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub config_file: PathBuf,
+
+    /// Override a field, as for `watch`, e.g. `server.port=9090`
+    #[arg(long = "override", value_name = "PATH=VALUE")]
+    pub overrides: Vec<String>,
+
+    /// Like --override, but the value is always a string
+    #[arg(long = "override-string", value_name = "PATH=VALUE")]
+    pub override_strings: Vec<String>,
+}
+
+/// Options of the `explain` command
+#[derive(Args, Debug)]
+pub struct ExplainArgs {
+    /// Dotted path of a field to describe in detail (all fields if omitted)
+    #[arg(value_name = "PATH")]
+    pub path: Option<String>,
+}
+
+/// Options of the `doctor` command
+#[derive(Args, Debug)]
+pub struct DoctorArgs {
+    /// Path to the configuration file to check
+    #[arg(short = 'f', long = "file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub config_file: PathBuf,
+
+    /// Also try to connect to the server and database hosts
+    #[arg(long)]
+    pub network: bool,
+
+    /// Time limit for each individual check
+    #[arg(long, default_value = "3", value_name = "SECONDS")]
+    pub timeout: u64,
+}
+
+/// Options of the `healthcheck` command
+#[derive(Args, Debug)]
+pub struct HealthcheckArgs {
+    /// Status file written by `--status-file`
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub status_file: PathBuf,
+
+    /// The file is stale after this many check intervals
+    #[arg(long, value_name = "N", default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    pub stale_after: u32,
+
+    /// Fixed staleness limit, e.g. `30s` (instead of --stale-after)
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, conflicts_with = "stale_after")]
+    pub max_age: Option<std::time::Duration>,
+
+    /// Fail when the last reload failed, even though a previous
+    /// configuration is still served
+    #[arg(long)]
+    pub fail_on_degraded: bool,
+
+    /// Give up (unhealthy) if the file cannot be read within this time
+    #[arg(long, value_name = "DURATION", default_value = "2s", value_parser = humantime::parse_duration)]
+    pub timeout: std::time::Duration,
+}
+
+/// Subcommands of `service`
+#[derive(Subcommand, Debug)]
+pub enum ServiceCommand {
+    /// Register the service, started at boot, watching with the arguments
+    /// given after `--`
+    ///
+    /// They are the arguments of `service run`, --log-file included: a
+    /// service has no console. Relative paths are resolved from the
+    /// current directory.
+    Install(ServiceInstallArgs),
+
+    /// Stop the service if it runs, then remove it
+    Uninstall(ServiceNameArgs),
+
+    /// Watch as the service (started by the service control manager)
+    Run(Box<ServiceRunArgs>),
+}
+
+/// The name of the service, for every `service` subcommand
+#[derive(Args, Debug)]
+pub struct ServiceNameArgs {
+    /// Name of the service
+    #[arg(long, default_value = DEFAULT_SERVICE_NAME)]
+    pub name: String,
+}
+
+/// Name of the Windows service when `--name` is not given
+pub const DEFAULT_SERVICE_NAME: &str = "config-watcher";
+
+/// Options of `service install`
+#[derive(Args, Debug)]
+pub struct ServiceInstallArgs {
+    #[command(flatten)]
+    pub service: ServiceNameArgs,
+
+    /// Name shown in the Services console
+    #[arg(long, default_value = "Config Watcher")]
+    pub display_name: String,
+
+    /// Started on demand (`sc start`) instead of at boot
+    #[arg(long)]
+    pub manual: bool,
+
+    /// Arguments of the service: watch options and --log-file
+    #[arg(last = true, required = true, value_name = "ARGS")]
+    pub args: Vec<std::ffi::OsString>,
+}
+
+/// Options of `service run`
+#[derive(Args, Debug)]
+pub struct ServiceRunArgs {
+    #[command(flatten)]
+    pub service: ServiceNameArgs,
+
+    /// Directory the relative paths are resolved from (set by `install`;
+    /// a service starts in the system directory)
+    #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+    pub directory: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub watch: WatchArgs,
+}
+
+/// Options of the `schema` command
+#[derive(Args, Debug)]
+pub struct SchemaArgs {
+    /// Write the schema to this file instead of stdout
+    #[arg(short = 'o', long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+/// Options of the `docs` command
+#[derive(Args, Debug)]
+pub struct DocsArgs {
+    /// Write the Markdown to this file instead of stdout
+    #[arg(short = 'o', long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+/// Options of the `completions` command
+#[derive(Args, Debug)]
+pub struct CompletionsArgs {
+    /// Shell to generate the script for
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+/// Shells supported by `completions`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// Values of `--export-env-format`
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EnvFormat {
+    /// `NAME=value`, double-quoted when needed
+    #[default]
+    Dotenv,
+    /// `export NAME='value'`, for a POSIX shell's `source`
+    Shell,
+}
+
+/// Formats of the watch output
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Values of `--permission-audit`
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PermissionLevel {
+    /// Loose secret files reject the load
+    #[default]
+    Error,
+    /// Loose secret files are logged as warnings
+    Warn,
+    /// No permission checks
+    Off,
+}
+
+/// How load failures are reported
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorFormat {
+    /// The error chain as a sentence
+    #[default]
+    Text,
+    /// One JSON object with a stable code and the findings
+    Json,
+}
+
+/// Values of `--on-confirm-timeout`
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnConfirmTimeout {
+    /// Decline the change, as a "no" would
+    #[default]
+    Reject,
+    /// Apply the change, as a "yes" would
+    Apply,
+}
+
+/// Values of `--color`
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// Colors and emoji on a terminal, unless NO_COLOR is set
+    #[default]
+    Auto,
+    /// Always colors and emoji
+    Always,
+    /// Plain ASCII output
+    Never,
+}
+
+/// Values of `--timestamp`
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimestampFormat {
+    /// `2026-10-15T10:30:05.250+02:00`
+    #[default]
+    Rfc3339,
+    /// `10:30:05.250`
+    TimeOnly,
+    /// Seconds since startup, `+12.345s`
+    Relative,
+    /// No timestamp
+    Off,
+}
+
+/// Events notifiers act on, also the values of `--webhook-events`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum EventKind {
+    /// The first valid load
+    Initial,
+    /// A reload that changed the configuration
+    Reload,
+    /// A load that failed, initial or not
+    Failure,
+    /// The file could not be checked, e.g. it was removed
+    FileError,
+    /// The watcher stops
+    Shutdown,
+}
+
+/// Options of the hidden `__complete` helper
+#[derive(Args, Debug)]
+pub struct CompleteArgs {
+    /// What kind of value is being completed
+    #[arg(value_enum)]
+    pub kind: CompleteKind,
+
+    /// The partial word typed so far
+    #[arg(allow_hyphen_values = true)]
+    pub current: Option<String>,
+}
+
+/// Values the completion helper knows about
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompleteKind {
+    /// Dotted field paths
+    Path,
+    /// Configuration files
+    File,
+}
+
+/// How a value given on the command line is converted to JSON
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueType {
+    /// Infer from the text: numbers, booleans, null, JSON literals, else string
+    Auto,
+    /// Always a string
+    String,
+    /// Must be a number
+    Number,
+    /// Must be `true` or `false`
+    Bool,
+    /// Any JSON document (objects, arrays...)
+    Json,
 }
 
 impl Cli {
-    /// Parses command-line arguments
-    pub fn parse_args() -> Self {
-        Self::parse()
+    /// Parses command-line arguments, keeping the matches
+    ///
+    /// The matches tell where each value came from (flag, environment or
+    /// default), which the settings file needs to know.
+    pub fn parse_with_matches() -> (Self, ArgMatches) {
+        let matches = Self::command().get_matches();
+        let cli = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        (cli, matches)
+    }
+
+    /// Matches of the watch options: those of `watch`, `run` or `service
+    /// run`, or the top level ones when no subcommand was given
+    pub fn watch_matches(matches: &ArgMatches) -> &ArgMatches {
+        matches
+            .subcommand_matches("watch")
+            .or_else(|| matches.subcommand_matches("run"))
+            .or_else(|| {
+                matches
+                    .subcommand_matches("service")
+                    .and_then(|service| service.subcommand_matches("run"))
+            })
+            .unwrap_or(matches)
+    }
+
+    /// Returns the selected subcommand, `watch` when none was given
+    pub fn into_command(self) -> Command {
+        self.command.unwrap_or(Command::Watch(Box::new(self.watch)))
+    }
+}
+
+/// Check interval used when `--interval` is not given
+pub const DEFAULT_INTERVAL: u64 = 2;
+
+impl ValidateArgs {
+    /// The file being validated: `--file`, or `--assume-path` with `--stdin`
+    pub fn target(&self) -> &Path {
+        self.config_file
+            .as_deref()
+            .or(self.assume_path.as_deref())
+            .unwrap_or(Path::new("-"))
+    }
+}
+
+impl RunArgs {
+    /// `run` supervises one command for one configuration, for as long as
+    /// it watches
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.watch.config_file.len() > 1 {
+            anyhow::bail!("`run` watches a single file");
+        }
+        if self.watch.once {
+            anyhow::bail!("--once has no effect with `run`");
+        }
+        if self.watch.from_env.is_some() && self.watch.interval.is_none() {
+            anyhow::bail!("`run` with --from-env needs --interval");
+        }
+        if self.watch.sandbox {
+            anyhow::bail!("`run` cannot use --sandbox: the command would run inside it");
+        }
+        Ok(())
+    }
+
+    /// What the supervisor runs and how
+    pub fn supervisor(&self) -> crate::supervisor::SupervisorConfig {
+        crate::supervisor::SupervisorConfig {
+            kill_timeout: self.kill_timeout,
+            restart_on_exit: self.restart_on_exit,
+            prefix: self.env_prefix.clone(),
+            ..crate::supervisor::SupervisorConfig::new(self.command.clone())
+        }
+    }
+}
+
+impl WatchArgs {
+    /// Check interval in seconds, `--interval` or the default
+    pub fn interval(&self) -> u64 {
+        self.interval.unwrap_or(DEFAULT_INTERVAL)
+    }
+
+    /// Output level selected by -q / -v / -vv
+    pub fn verbosity(&self) -> Verbosity {
+        match (self.quiet, self.verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        }
+    }
+
+    /// Makes every path option absolute, against the current working
+    /// directory, which --daemonize leaves
+    pub fn make_paths_absolute(&mut self) -> std::io::Result<()> {
+        let paths = self.config_file.iter_mut().chain(&mut self.age_identity);
+        let options = [
+            &mut self.fallback_config,
+            &mut self.state_dir,
+            &mut self.slack_template,
+            &mut self.export_env,
+            &mut self.write_normalized,
+            &mut self.signal_pidfile,
+            &mut self.schema,
+            &mut self.public_key,
+            &mut self.status_file,
+            &mut self.lock_dir,
+            &mut self.pid_file,
+            &mut self.push_socket,
+            &mut self.audit_log,
+            &mut self.event_db,
+        ];
+        for path in paths.chain(options.into_iter().flatten()) {
+            *path = std::path::absolute(&*path)?;
+        }
+        Ok(())
     }
 
     /// Validates CLI arguments
+    ///
+    /// A missing `--file` is fine: the file is then discovered in the
+    /// default locations (see `discovery.rs`). The same file given twice
+    /// is rejected.
     pub fn validate(&self) -> anyhow::Result<()> {
+        for (i, file) in self.config_file.iter().enumerate() {
+            let canonical = |path: &PathBuf| path.canonicalize().unwrap_or_else(|_| path.clone());
+            if self.config_file[..i]
+                .iter()
+                .any(|other| canonical(other) == canonical(file))
+            {
+                anyhow::bail!("{} is given more than once", file.display());
+            }
+        }
+
         // Validate interval is reasonable
-        if self.interval == 0 {
+        let interval = self.interval();
+        if interval == 0 {
             anyhow::bail!("Interval must be greater than 0 seconds");
         }
 
-        if self.interval > 3600 {
+        if interval > 3600 {
             anyhow::bail!("Interval cannot exceed 3600 seconds (1 hour)");
         }
 
+        // Options that only make sense while watching
+        if self.once {
+            if self.interval.is_some() {
+                anyhow::bail!("--interval has no effect with --once");
+            }
+            if self.reread_env {
+                anyhow::bail!("--reread-env has no effect with --once");
+            }
+            if self.max_duration.is_some() {
+                anyhow::bail!("--max-duration has no effect with --once");
+            }
+            if self.fail_fast {
+                anyhow::bail!("--fail-fast has no effect with --once");
+            }
+        }
+
+        if self.verify_checksum && self.from_env.is_some() {
+            anyhow::bail!("--verify-checksum needs a watched file, not --from-env");
+        }
+        if self.verify_signature && self.from_env.is_some() {
+            anyhow::bail!("--verify-signature needs a watched file, not --from-env");
+        }
+        if self.git_autocommit && self.from_env.is_some() {
+            anyhow::bail!("--git-autocommit needs a watched file, not --from-env");
+        }
+
+        if self.fallback_config.is_some() && self.config_file.len() > 1 {
+            anyhow::bail!("--fallback-config needs a single watched file");
+        }
+        if self.keep_versions.is_some() && self.config_file.len() > 1 {
+            anyhow::bail!("--keep-versions needs a single watched file");
+        }
+
+        // PUT /config needs to know which file to write
+        if self.http_allow_write && (self.config_file.len() > 1 || self.from_env.is_some()) {
+            anyhow::bail!("--http-allow-write needs a single watched file");
+        }
+        if self
+            .http_token
+            .as_deref()
+            .is_some_and(|token| token.trim().is_empty())
+        {
+            anyhow::bail!("--http-token cannot be empty");
+        }
+
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watch_args(args: &[&str]) -> WatchArgs {
+        let cli =
+            Cli::try_parse_from(std::iter::once("config-watcher").chain(args.iter().copied()))
+                .unwrap();
+        match cli.into_command() {
+            Command::Watch(args) => *args,
+            other => panic!("expected watch, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_multiple_files() {
+        let args = watch_args(&["-f", "a.json", "--file", "b.json"]);
+        assert_eq!(
+            args.config_file,
+            [PathBuf::from("a.json"), PathBuf::from("b.json")]
+        );
+        assert!(args.validate().is_ok());
+
+        assert!(watch_args(&[]).validate().is_ok());
+        assert!(
+            watch_args(&["-f", "a.json", "-f", "a.json"])
+                .validate()
+                .is_err()
+        );
+        // --once is a single load, so watch-only options are refused
+        assert!(watch_args(&["--once"]).validate().is_ok());
+        assert!(watch_args(&["--once", "-i", "5"]).validate().is_err());
+        assert!(watch_args(&["--once", "--reread-env"]).validate().is_err());
+
+        // clap itself refuses an empty path
+        assert!(Cli::try_parse_from(["config-watcher", "-f", ""]).is_err());
+    }
+    #[test]
+    fn test_verbosity_flags() {
+        assert_eq!(watch_args(&[]).verbosity(), Verbosity::Normal);
+        assert_eq!(watch_args(&["-q"]).verbosity(), Verbosity::Quiet);
+        assert_eq!(watch_args(&["-v"]).verbosity(), Verbosity::Verbose);
+        assert_eq!(watch_args(&["-vv"]).verbosity(), Verbosity::Debug);
+        assert_eq!(
+            watch_args(&["-v", "-v", "-v"]).verbosity(),
+            Verbosity::Debug
+        );
+        assert!(Cli::try_parse_from(["config-watcher", "-q", "-v"]).is_err());
+    }
+}
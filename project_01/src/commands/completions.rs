@@ -0,0 +1,278 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`clap::CommandFactory`**: Gives access to the `Command` built by derive
+- **`clap_complete::generate`**: Writes the stock completion script
+- **`Vec<u8>` as `io::Write`**: The script is generated into memory, then
+  extended with hand-written hooks
+
+**Design decisions**:
+- Enum-valued flags (`--type`, shells...) are completed by the stock
+  script since they are `ValueEnum`s
+- Values that clap cannot know statically (config files filtered by
+  extension, dotted field paths) come from the hidden `__complete`
+  subcommand, which the generated scripts call at completion time
+- Field paths come from `schema::FIELDS`, the same table as `explain`
+
+******************************************************************************/
+
+use crate::cli::{Cli, CompleteArgs, CompleteKind, CompletionsArgs, Shell};
+use crate::config::SUPPORTED_EXTENSIONS;
+use crate::schema;
+use clap::CommandFactory;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Name the scripts register completions for
+const BIN_NAME: &str = "config-watcher";
+
+/// Runs `config-watcher completions <shell>`
+pub fn run(args: &CompletionsArgs) -> anyhow::Result<ExitCode> {
+    print!("{}", script(args.shell)?);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Runs the hidden `config-watcher __complete <kind> [current]` helper
+pub fn run_helper(args: &CompleteArgs) -> anyhow::Result<ExitCode> {
+    let current = args.current.as_deref().unwrap_or("");
+    let candidates = match args.kind {
+        CompleteKind::Path => path_candidates(current),
+        CompleteKind::File => file_candidates(current),
+    };
+    for candidate in candidates {
+        println!("{candidate}");
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Builds the full completion script for `shell`
+pub fn script(shell: Shell) -> anyhow::Result<String> {
+    let mut command = Cli::command();
+    let mut buffer = Vec::new();
+    let generator = match shell {
+        Shell::Bash => clap_complete::Shell::Bash,
+        Shell::Zsh => clap_complete::Shell::Zsh,
+        Shell::Fish => clap_complete::Shell::Fish,
+        Shell::Powershell => clap_complete::Shell::PowerShell,
+    };
+    clap_complete::generate(generator, &mut command, BIN_NAME, &mut buffer);
+    let stock = String::from_utf8(buffer)?;
+
+    Ok(match shell {
+        Shell::Bash => format!("{stock}{BASH_HOOK}"),
+        Shell::Zsh => zsh_script(&stock),
+        Shell::Fish => format!("{stock}{FISH_HOOK}"),
+        Shell::Powershell => powershell_script(&stock),
+    })
+}
+
+/// Known field paths starting with `current`
+///
+/// Map entries (`features.*`) cannot be enumerated, so only their section
+/// prefix is offered.
+pub fn path_candidates(current: &str) -> Vec<String> {
+    let mut candidates: Vec<String> = schema::FIELDS
+        .iter()
+        .map(|info| info.path.trim_end_matches('*').to_string())
+        .filter(|path| path.starts_with(current))
+        .collect();
+    candidates.dedup();
+    candidates
+}
+
+/// Directories and supported config files matching a partial path
+pub fn file_candidates(current: &str) -> Vec<String> {
+    let (dir, prefix) = match current.rfind('/') {
+        Some(i) => (&current[..=i], &current[i + 1..]),
+        None => ("", current),
+    };
+    let search_dir = if dir.is_empty() {
+        Path::new(".")
+    } else {
+        Path::new(dir)
+    };
+
+    let Ok(entries) = std::fs::read_dir(search_dir) else {
+        return Vec::new();
+    };
+
+    let mut candidates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                Some(format!("{dir}{name}/"))
+            } else {
+                let supported = path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| SUPPORTED_EXTENSIONS.contains(&ext));
+                supported.then(|| format!("{dir}{name}"))
+            }
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+/// Wraps the stock bash completion: file and field-path values are
+/// delegated to the helper, everything else to the generated function.
+const BASH_HOOK: &str = r#"
+# --- dynamic completion hooks ---
+_config_watcher_dynamic() {
+    local cur prev sub i positional
+    cur="${COMP_WORDS[COMP_CWORD]}"
+    prev="${COMP_WORDS[COMP_CWORD-1]}"
+    if [[ "$prev" == "-f" || "$prev" == "--file" || "$prev" == "--schema" ]]; then
+        COMPREPLY=( $(config-watcher __complete file "$cur") )
+        compopt -o nospace 2>/dev/null
+        return 0
+    fi
+    sub="${COMP_WORDS[1]}"
+    if [[ "$sub" == "get" || "$sub" == "set" || "$sub" == "explain" ]] && [[ "$cur" != -* ]]; then
+        positional=0
+        for (( i=2; i<COMP_CWORD; i++ )); do
+            case "${COMP_WORDS[i]}" in
+                -f|--file|--default|--type) (( i++ )) ;;
+                -*) ;;
+                *) (( positional++ )) ;;
+            esac
+        done
+        if (( positional == 0 )); then
+            COMPREPLY=( $(config-watcher __complete path "$cur") )
+            compopt -o nospace 2>/dev/null
+            return 0
+        fi
+    fi
+    _config-watcher "$@"
+}
+complete -F _config_watcher_dynamic -o bashdefault -o default config-watcher
+"#;
+
+/// Fish completions are additive, so the hooks just add candidates
+const FISH_HOOK: &str = r#"
+# --- dynamic completion hooks ---
+complete -c config-watcher -n "__fish_seen_subcommand_from get set explain" -f -a "(config-watcher __complete path (commandline -ct))"
+complete -c config-watcher -s f -l file -r -f -a "(config-watcher __complete file (commandline -ct))"
+"#;
+
+/// Zsh: route the path positional and file options through helper functions
+fn zsh_script(stock: &str) -> String {
+    let mut script = String::from(
+        r#"# --- dynamic completion hooks ---
+_config_watcher_paths() {
+    local -a candidates
+    candidates=(${(f)"$(config-watcher __complete path "$PREFIX")"})
+    compadd -S '' -- $candidates
+}
+_config_watcher_files() {
+    local -a candidates
+    candidates=(${(f)"$(config-watcher __complete file "$PREFIX")"})
+    compadd -S '' -- $candidates
+}
+
+"#,
+    );
+    for line in stock.lines() {
+        let hooked = if line.contains(":path -- ") {
+            line.replace(":_default'", ":_config_watcher_paths'")
+        } else if line.contains("--file=[") || line.contains("--schema=[") {
+            line.replace(":_files'", ":_config_watcher_files'")
+        } else {
+            line.to_string()
+        };
+        script.push_str(&hooked);
+        script.push('\n');
+    }
+    script
+}
+
+/// PowerShell: answer file and path values before the stock logic runs
+fn powershell_script(stock: &str) -> String {
+    const PARAMS: &str = "param($wordToComplete, $commandAst, $cursorPosition)";
+    const HOOK: &str = r#"
+    # --- dynamic completion hooks ---
+    $cwWords = @($commandAst.CommandElements | ForEach-Object { $_.ToString() })
+    $cwPrev = if ($wordToComplete) { $cwWords[-2] } else { $cwWords[-1] }
+    $cwKind = $null
+    if ($cwPrev -in @('-f', '--file', '--schema')) { $cwKind = 'file' }
+    elseif ($cwWords.Count -gt 1 -and $cwWords[1] -in @('get', 'set', 'explain') -and -not $wordToComplete.StartsWith('-')) { $cwKind = 'path' }
+    if ($cwKind) {
+        config-watcher __complete $cwKind $wordToComplete | ForEach-Object {
+            [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+        }
+        return
+    }
+"#;
+    match stock.find(PARAMS) {
+        Some(i) => {
+            let split = i + PARAMS.len();
+            format!("{}{}{}", &stock[..split], HOOK, &stock[split..])
+        }
+        None => stock.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_candidates() {
+        assert_eq!(
+            path_candidates("server."),
+            [
+                "server.host",
+                "server.port",
+                "server.enable_ssl",
+                "server.allowed_ips",
+                "server.denied_ips",
+                "server.max_connections",
+                "server.keep_alive",
+                "server.request_timeout",
+                "server.shutdown_grace"
+            ]
+        );
+        assert_eq!(path_candidates("feat"), ["features", "features."]);
+        assert!(path_candidates("nope").is_empty());
+    }
+
+    #[test]
+    fn test_file_candidates_filter_extensions() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.json"), "{}").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+        std::fs::create_dir(dir.path().join("configs")).unwrap();
+
+        let base = format!("{}/", dir.path().display());
+        let candidates = file_candidates(&base);
+        assert_eq!(
+            candidates,
+            [format!("{base}app.json"), format!("{base}configs/")]
+        );
+        assert_eq!(file_candidates(&format!("{base}no")), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_scripts_contain_hooks() {
+        let bash = script(Shell::Bash).unwrap();
+        assert!(bash.contains("complete -F _config_watcher_dynamic"));
+        assert!(bash.contains("config-watcher __complete path"));
+        // Enum-valued flags are completed by the stock script
+        assert!(bash.contains("auto string number bool json"));
+
+        let zsh = script(Shell::Zsh).unwrap();
+        assert!(zsh.contains(":_config_watcher_paths'"));
+        assert!(zsh.contains(":_config_watcher_files'"));
+
+        let fish = script(Shell::Fish).unwrap();
+        assert!(fish.contains("__complete path (commandline -ct)"));
+
+        let powershell = script(Shell::Powershell).unwrap();
+        assert!(powershell.contains("config-watcher __complete $cwKind"));
+    }
+}
@@ -0,0 +1,100 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`ExitCode`**: 0 when the configurations are the same, 1 when they
+  differ, like `diff(1)`; failures keep their usual codes
+- **Error enums as data**: A `GitError` picks its own exit status and
+  message instead of collapsing into one "git failed"
+
+**Design decisions**:
+- Both sides are compared in their effective form (parsed through
+  `AppConfig`, defaults filled in), with the watcher's field-level diff:
+  reformatting, reordering keys or spelling out a default is no
+  difference
+- `--against FILE` compares with another file; `--against-git REV` with
+  the file as committed at REV (`HEAD`, a tag, `main~3`), read through the
+  git CLI (`git::WorkTree::show`)
+- A file outside a work tree or not under version control, and a file
+  missing at REV, are input errors (3); an unknown revision is a usage
+  error (2). Each has its own message
+- Text output is a `---`/`+++` header and one line per change, secrets
+  redacted; `--output json` prints `{old, new, identical, diff}`
+
+******************************************************************************/
+
+use crate::cli::{DiffArgs, OutputFormat};
+use crate::diff;
+use crate::exit;
+use crate::git::{GitError, WorkTree};
+use crate::listing::ListLimit;
+use crate::pipeline;
+use anyhow::Context;
+use serde_json::json;
+use std::process::ExitCode;
+
+/// Runs `config-watcher diff`
+pub fn run(args: &DiffArgs) -> anyhow::Result<ExitCode> {
+    let file = &args.config_file;
+    let new_label = file.display().to_string();
+    let new_text = super::read_file(file)
+        .with_context(|| format!("Failed to read configuration file: {new_label}"))?;
+    let (old_label, old_text) = match (&args.against, &args.against_git) {
+        (Some(path), _) => (
+            path.display().to_string(),
+            super::read_file(path).with_context(|| {
+                format!("Failed to read configuration file: {}", path.display())
+            })?,
+        ),
+        (None, Some(revision)) => {
+            match WorkTree::of(file).and_then(|tree| tree.show(revision, file)) {
+                Ok(text) => (format!("{revision}:{new_label}"), text),
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    return Ok(ExitCode::from(status(&e)));
+                }
+            }
+        }
+        (None, None) => anyhow::bail!("Nothing to compare with"),
+    };
+
+    let old = pipeline::effective(&old_text).with_context(|| format!("In {old_label}"))?;
+    let new = pipeline::effective(&new_text).with_context(|| format!("In {new_label}"))?;
+    let changes = diff::diff(&old, &new);
+
+    match args.output {
+        OutputFormat::Json => println!(
+            "{}",
+            json!({
+                "old": old_label,
+                "new": new_label,
+                "identical": changes.is_empty(),
+                "diff": changes,
+            })
+        ),
+        OutputFormat::Text if changes.is_empty() => {}
+        OutputFormat::Text => {
+            println!("--- {old_label}");
+            println!("+++ {new_label}");
+            for line in diff::render(&changes, ListLimit::UNLIMITED) {
+                println!("{line}");
+            }
+        }
+    }
+    Ok(if changes.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(exit::DIFFERENT)
+    })
+}
+
+/// Exit status of a failure to read the committed version
+fn status(error: &GitError) -> u8 {
+    match error {
+        GitError::UnknownRevision(_) => exit::USAGE,
+        GitError::NotInstalled
+        | GitError::NotAWorkTree(_)
+        | GitError::NotTracked(_)
+        | GitError::NotAtRevision { .. } => exit::INPUT,
+        GitError::Failed { .. } => exit::INTERNAL,
+    }
+}
@@ -0,0 +1,163 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`String` as a buffer**: `writeln!` into a `String` via `std::fmt::Write`
+- **Golden-file testing**: The committed `docs/CONFIG.md` must equal the
+  freshly generated text
+
+**Design decisions**:
+- Generated from `schema::FIELDS`, the same table `explain` uses
+- One table per section, the top-level fields first; nested sections
+  (`auth.jwt`) get their own table after their parent's
+- Output is fully deterministic (table order, sorted example) so it can
+  be committed and diffed
+
+******************************************************************************/
+
+use crate::cli::DocsArgs;
+use crate::config::AppConfig;
+use crate::fs_util;
+use crate::schema::{self, FieldInfo};
+use crate::style::{Icon, Style};
+use anyhow::Context;
+use std::fmt::Write;
+use std::process::ExitCode;
+
+/// Runs `config-watcher docs`
+pub fn run(args: &DocsArgs, style: Style) -> anyhow::Result<ExitCode> {
+    let markdown = render_markdown()?;
+    match &args.output {
+        Some(path) => {
+            fs_util::write_atomic(path, markdown.as_bytes())
+                .context("Failed to write documentation")?;
+            let written = format_args!("Field reference written to {}", path.display());
+            println!("{}", style.line(Icon::Ok, written));
+        }
+        None => print!("{markdown}"),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Renders the Markdown field reference
+pub fn render_markdown() -> anyhow::Result<String> {
+    let mut out = String::new();
+    writeln!(out, "# Configuration reference")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "<!-- Generated by `config-watcher docs`. Do not edit by hand. -->"
+    )?;
+    writeln!(out)?;
+
+    let top_level: Vec<&FieldInfo> = schema::FIELDS.iter().filter(|f| f.depth() == 0).collect();
+    writeln!(out, "## Top level")?;
+    writeln!(out)?;
+    write_table(&mut out, &top_level)?;
+
+    for section in schema::FIELDS {
+        let prefix = format!("{}.", section.path);
+        let children: Vec<&FieldInfo> = schema::FIELDS
+            .iter()
+            .filter(|f| f.depth() == section.depth() + 1 && f.path.starts_with(&prefix))
+            .collect();
+        if children.is_empty() {
+            continue;
+        }
+
+        writeln!(out)?;
+        writeln!(out, "## `{}`", section.path)?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{}. {} section.",
+            section.description,
+            if section.required {
+                "Required"
+            } else {
+                "Optional"
+            }
+        )?;
+        writeln!(out)?;
+        write_table(&mut out, &children)?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "## Example")?;
+    writeln!(out)?;
+    writeln!(out, "```json")?;
+    writeln!(
+        out,
+        "{}",
+        serde_json::to_string_pretty(&AppConfig::example())?
+    )?;
+    writeln!(out, "```")?;
+    Ok(out)
+}
+
+fn write_table(out: &mut String, fields: &[&FieldInfo]) -> std::fmt::Result {
+    writeln!(out, "| Field | Type | Required | Default | Description |")?;
+    writeln!(out, "|-------|------|----------|---------|-------------|")?;
+    for info in fields {
+        let name = match info.name() {
+            "*" => "*name*".to_string(),
+            name => format!("`{name}`"),
+        };
+        let default = info
+            .default
+            .map(|d| format!("`{d}`"))
+            .unwrap_or_else(|| "-".to_string());
+        let mut description = info.description.to_string();
+        if !info.rules.is_empty() {
+            description.push_str(&format!(" ({})", info.rules.join("; ")));
+        }
+        if info.secret {
+            description.push_str(". Secret: redacted in output");
+        }
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            name,
+            escape(info.ty),
+            if info.required { "yes" } else { "no" },
+            default,
+            escape(&description)
+        )?;
+    }
+    Ok(())
+}
+
+/// Escapes characters that would break a Markdown table cell
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Regenerate with `cargo run -p config_watcher -- docs --output project_01/docs/CONFIG.md`
+    /// or by running the tests with `UPDATE_GOLDEN=1`.
+    #[test]
+    fn test_generated_docs_match_golden_file() {
+        let golden_path = concat!(env!("CARGO_MANIFEST_DIR"), "/docs/CONFIG.md");
+        let generated = render_markdown().unwrap();
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(golden_path, &generated).unwrap();
+        }
+
+        let golden = std::fs::read_to_string(golden_path).unwrap_or_default();
+        assert!(
+            golden == generated,
+            "docs/CONFIG.md is out of date; regenerate it with \
+             `cargo run -p config_watcher -- docs --output project_01/docs/CONFIG.md`"
+        );
+    }
+
+    #[test]
+    fn test_docs_are_deterministic() {
+        assert_eq!(render_markdown().unwrap(), render_markdown().unwrap());
+    }
+}
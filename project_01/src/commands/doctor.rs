@@ -0,0 +1,396 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`tokio::task::JoinSet`**: Runs every check concurrently
+- **`tokio::time::timeout`**: Caps each check individually
+- **`tokio::net::lookup_host`**: Async DNS resolution
+- **`Ord` on enums**: `Status` derives `Ord` so the worst result is a `max()`
+
+**Design decisions**:
+- Validation runs first; environment checks only make sense on a config
+  that parses
+- Intrusive checks (connecting to remote hosts) are opt-in via `--network`
+- Exit codes follow `exit`: 0 pass, 1 warnings only, 5 any failure
+- Fields whose name ends in `_file`, `_path` or `_dir` are treated as
+  filesystem references, resolved relative to the config file
+
+******************************************************************************/
+
+use crate::cli::DoctorArgs;
+use crate::config::AppConfig;
+use crate::exit;
+use crate::style::{Icon, Style};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::task::JoinSet;
+use tokio::time::timeout;
+
+/// Outcome of one check, ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    /// Process exit code for a worst-case status
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Status::Pass => exit::SUCCESS,
+            Status::Warn => exit::UNHEALTHY,
+            Status::Fail => exit::VALIDATION,
+        }
+    }
+
+    /// Marker of a result line with this status
+    fn icon(self) -> Icon {
+        match self {
+            Status::Pass => Icon::Ok,
+            Status::Warn => Icon::Warning,
+            Status::Fail => Icon::Error,
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Pass => write!(f, "PASS"),
+            Status::Warn => write!(f, "WARN"),
+            Status::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+/// Result of a single check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl CheckResult {
+    fn new(name: impl Into<String>, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Settings shared by all checks
+#[derive(Debug, Clone)]
+pub struct DoctorOptions {
+    /// Directory used to resolve relative file references
+    pub base_dir: PathBuf,
+    /// Connect to remote hosts
+    pub network: bool,
+    /// Upper bound for each individual check
+    pub check_timeout: Duration,
+}
+
+/// Runs `config-watcher doctor`
+pub async fn run(args: &DoctorArgs, style: Style) -> anyhow::Result<ExitCode> {
+    let checking = format_args!("Checking {}", args.config_file.display());
+    println!("{}", style.line(Icon::Check, checking));
+
+    let options = DoctorOptions {
+        base_dir: args
+            .config_file
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+        network: args.network,
+        check_timeout: Duration::from_secs(args.timeout),
+    };
+    let results = diagnose(&args.config_file, &options).await;
+
+    for result in &results {
+        let line = format_args!("{} {}: {}", result.status, result.name, result.detail);
+        println!("   {}", style.line(result.status.icon(), line));
+    }
+
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    println!(
+        "\n{} passed, {} warning(s), {} failed",
+        count(Status::Pass),
+        count(Status::Warn),
+        count(Status::Fail)
+    );
+
+    let worst = results
+        .iter()
+        .map(|r| r.status)
+        .max()
+        .unwrap_or(Status::Pass);
+    Ok(ExitCode::from(worst.exit_code()))
+}
+
+/// Validates the file, then runs every environment check concurrently
+///
+/// Results come back in a stable order: validation first, then the checks
+/// in the order they were scheduled.
+pub async fn diagnose(config_file: &Path, options: &DoctorOptions) -> Vec<CheckResult> {
+    let (doc, config) = match super::load_document(config_file)
+        .and_then(|(_, doc)| super::check_document(&doc).map(|config| (doc, config)))
+    {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            return vec![CheckResult::new(
+                "validation",
+                Status::Fail,
+                format!("{e:#}"),
+            )];
+        }
+    };
+
+    let mut results = vec![CheckResult::new(
+        "validation",
+        Status::Pass,
+        "configuration is valid",
+    )];
+
+    let mut checks = JoinSet::new();
+    let mut scheduled = 0;
+    let mut schedule = |name: String, check: CheckFuture| {
+        let limit = options.check_timeout;
+        let index = scheduled;
+        scheduled += 1;
+        checks.spawn(async move {
+            let result = match timeout(limit, check).await {
+                Ok((status, detail)) => CheckResult::new(name, status, detail),
+                Err(_) => {
+                    CheckResult::new(name, Status::Fail, format!("timed out after {limit:?}"))
+                }
+            };
+            (index, result)
+        });
+    };
+
+    for (field, path) in super::file_references(&doc, "") {
+        let resolved = options.base_dir.join(&path);
+        schedule(format!("file {field}"), Box::pin(check_file(resolved)));
+    }
+
+    for (label, host, port) in hosts(&config) {
+        schedule(format!("dns {label}"), Box::pin(check_dns(host.clone())));
+        if options.network {
+            let status_if_down = if label == "server" {
+                Status::Warn
+            } else {
+                Status::Fail
+            };
+            schedule(
+                format!("connect {label}"),
+                Box::pin(check_connect(host, port, status_if_down)),
+            );
+        }
+    }
+
+    if let Some(server) = &config.server {
+        schedule(
+            format!("port {}", server.port),
+            Box::pin(check_port_free(server.port)),
+        );
+    }
+
+    let mut checked = Vec::new();
+    while let Some(joined) = checks.join_next().await {
+        match joined {
+            Ok(entry) => checked.push(entry),
+            Err(e) => results.push(CheckResult::new("internal", Status::Fail, e.to_string())),
+        }
+    }
+    checked.sort_by_key(|(index, _)| *index);
+    results.extend(checked.into_iter().map(|(_, result)| result));
+    results
+}
+
+type CheckFuture = std::pin::Pin<Box<dyn Future<Output = (Status, String)> + Send>>;
+
+/// Hosts referenced by the configuration: (label, host, port)
+fn hosts(config: &AppConfig) -> Vec<(String, String, u16)> {
+    let mut hosts = Vec::new();
+    if let Some(server) = &config.server {
+        hosts.push(("server".to_string(), server.host.clone(), server.port));
+    }
+    if let Some(db) = &config.database
+        && let Some((host, port)) = database_host(&db.connection_string)
+    {
+        hosts.push(("database".to_string(), host, port));
+    }
+    hosts
+}
+
+/// Extracts host and port from a URL-style connection string
+fn database_host(connection_string: &str) -> Option<(String, u16)> {
+    let url = url::Url::parse(connection_string).ok()?;
+    let host = url.host_str()?.trim_matches(['[', ']']).to_string();
+    let default_port = match url.scheme() {
+        "postgres" | "postgresql" => 5432,
+        "mysql" | "mariadb" => 3306,
+        "mongodb" => 27017,
+        "redis" => 6379,
+        _ => return url.port().map(|port| (host, port)),
+    };
+    Some((host, url.port().unwrap_or(default_port)))
+}
+
+async fn check_file(path: PathBuf) -> (Status, String) {
+    match tokio::fs::File::open(&path).await {
+        Ok(_) => (Status::Pass, format!("{} is readable", path.display())),
+        Err(e) => (Status::Fail, format!("{}: {}", path.display(), e)),
+    }
+}
+
+async fn check_dns(host: String) -> (Status, String) {
+    match tokio::net::lookup_host((host.as_str(), 0)).await {
+        Ok(mut addrs) => match addrs.next() {
+            Some(addr) => (Status::Pass, format!("{host} resolves to {}", addr.ip())),
+            None => (Status::Fail, format!("{host} has no addresses")),
+        },
+        Err(e) => (Status::Fail, format!("cannot resolve {host}: {e}")),
+    }
+}
+
+async fn check_connect(host: String, port: u16, status_if_down: Status) -> (Status, String) {
+    match TcpStream::connect((host.as_str(), port)).await {
+        Ok(_) => (Status::Pass, format!("{host}:{port} accepts connections")),
+        Err(e) => (
+            status_if_down,
+            format!("cannot connect to {host}:{port}: {e}"),
+        ),
+    }
+}
+
+async fn check_port_free(port: u16) -> (Status, String) {
+    match TcpStream::connect(("127.0.0.1", port)).await {
+        Ok(_) => (
+            Status::Warn,
+            format!("something is already listening on 127.0.0.1:{port}"),
+        ),
+        Err(_) => (Status::Pass, format!("nothing is listening on port {port}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tokio::net::TcpListener;
+
+    fn options(dir: &Path, network: bool) -> DoctorOptions {
+        DoctorOptions {
+            base_dir: dir.to_path_buf(),
+            network,
+            check_timeout: Duration::from_secs(2),
+        }
+    }
+
+    fn status_of<'a>(results: &'a [CheckResult], name: &str) -> &'a CheckResult {
+        results
+            .iter()
+            .find(|r| r.name == name)
+            .unwrap_or_else(|| panic!("no check named {name} in {results:?}"))
+    }
+
+    #[tokio::test]
+    async fn test_invalid_config_fails_validation_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, r#"{ "app_name": "", "version": "1.0" }"#).unwrap();
+
+        let results = diagnose(&path, &options(dir.path(), false)).await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status, Status::Fail);
+    }
+
+    #[tokio::test]
+    async fn test_port_in_use_and_network_checks() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        let config = format!(
+            r#"{{
+                "app_name": "TestApp", "version": "1.0.0",
+                "server": {{ "host": "127.0.0.1", "port": {port} }},
+                "database": {{ "connection_string": "postgres://u@127.0.0.1:1/db" }}
+            }}"#
+        );
+        fs::write(&path, config).unwrap();
+
+        let results = diagnose(&path, &options(dir.path(), true)).await;
+        assert_eq!(status_of(&results, "validation").status, Status::Pass);
+        assert_eq!(status_of(&results, "dns server").status, Status::Pass);
+        assert_eq!(
+            status_of(&results, &format!("port {port}")).status,
+            Status::Warn
+        );
+        assert_eq!(status_of(&results, "connect server").status, Status::Pass);
+        // Nothing listens on port 1
+        assert_eq!(status_of(&results, "connect database").status, Status::Fail);
+    }
+
+    #[tokio::test]
+    async fn test_free_port_passes_without_network_checks() {
+        // Grab a free port, then release it
+        let port = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().port()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        let config = format!(
+            r#"{{ "app_name": "TestApp", "version": "1.0.0",
+                 "server": {{ "host": "localhost", "port": {port} }} }}"#
+        );
+        fs::write(&path, config).unwrap();
+
+        let results = diagnose(&path, &options(dir.path(), false)).await;
+        assert_eq!(
+            status_of(&results, &format!("port {port}")).status,
+            Status::Pass
+        );
+        assert!(results.iter().all(|r| !r.name.starts_with("connect")));
+    }
+
+    #[tokio::test]
+    async fn test_file_references_are_checked_relative_to_config() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("present.pem"), "cert").unwrap();
+
+        let doc = serde_json::json!({
+            "server": { "cert_file": "present.pem", "key_file": "missing.pem" }
+        });
+        let refs = crate::commands::file_references(&doc, "");
+        assert_eq!(refs.len(), 2);
+
+        let (status, _) = check_file(dir.path().join(&refs[0].1)).await;
+        assert_eq!(status, Status::Pass);
+        let (status, detail) = check_file(dir.path().join(&refs[1].1)).await;
+        assert_eq!(status, Status::Fail);
+        assert!(detail.contains("missing.pem"));
+    }
+
+    #[test]
+    fn test_database_host_parsing() {
+        assert_eq!(
+            database_host("postgres://user:pw@db.internal/app"),
+            Some(("db.internal".to_string(), 5432))
+        );
+        assert_eq!(
+            database_host("mysql://[::1]:3307/app"),
+            Some(("::1".to_string(), 3307))
+        );
+        assert_eq!(database_host("not a url"), None);
+    }
+}
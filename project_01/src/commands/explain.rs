@@ -0,0 +1,135 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`Option<String>` positional**: `explain` and `explain server.port`
+- **Format width specifiers**: `{:<width$}` aligns the tree columns
+
+**Design decisions**:
+- Everything printed comes from `schema::FIELDS`, never from hand-written
+  text, so the help cannot drift from the real structs
+- Unknown paths exit with code 2 (like `get`) and suggest close matches
+
+******************************************************************************/
+
+use crate::cli::ExplainArgs;
+use crate::exit;
+use crate::path::FieldPath;
+use crate::schema::{self, FieldInfo};
+use std::process::ExitCode;
+
+/// Runs `config-watcher explain`
+pub fn run(args: &ExplainArgs) -> anyhow::Result<ExitCode> {
+    let Some(raw) = &args.path else {
+        print!("{}", render_tree());
+        return Ok(ExitCode::SUCCESS);
+    };
+
+    let found = FieldPath::parse(raw)
+        .ok()
+        .and_then(|path| schema::lookup(&path));
+    match found {
+        Some(info) => {
+            print!("{}", render_field(info));
+            Ok(ExitCode::SUCCESS)
+        }
+        None => {
+            eprintln!("Error: unknown field '{raw}'");
+            let suggestions = schema::suggest(raw);
+            if !suggestions.is_empty() {
+                eprintln!("   Did you mean: {}?", suggestions.join(", "));
+            }
+            Ok(ExitCode::from(exit::USAGE))
+        }
+    }
+}
+
+/// Renders every known field as an indented tree
+pub fn render_tree() -> String {
+    let width = schema::FIELDS
+        .iter()
+        .map(|f| f.depth() * 2 + f.name().len())
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    for info in schema::FIELDS {
+        let name = match info.name() {
+            "*" => "<name>",
+            name => name,
+        };
+        let label = format!("{}{}", "  ".repeat(info.depth()), name);
+        let mut line = format!(
+            "{label:<width$}  {:<22} {}",
+            info.ty,
+            if info.required {
+                "required"
+            } else {
+                "optional"
+            }
+        );
+        if let Some(default) = info.default {
+            line.push_str(&format!("  (default: {default})"));
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// Renders the detailed description of a single field
+pub fn render_field(info: &FieldInfo) -> String {
+    let mut out = format!("{}\n", info.path);
+    out.push_str(&format!("   Description: {}\n", info.description));
+    out.push_str(&format!("   Type:        {}\n", info.ty));
+
+    let required = match (info.required, info.depth()) {
+        (false, _) => "no".to_string(),
+        (true, 0) => "yes".to_string(),
+        (true, _) => {
+            let parent = &info.path[..info.path.len() - info.name().len() - 1];
+            format!("yes (when {parent} is present)")
+        }
+    };
+    out.push_str(&format!("   Required:    {required}\n"));
+    out.push_str(&format!(
+        "   Default:     {}\n",
+        info.default.unwrap_or("none")
+    ));
+    if info.secret {
+        out.push_str("   Secret:      yes (redacted unless --reveal-secrets)\n");
+    }
+    if !info.rules.is_empty() {
+        out.push_str("   Rules:\n");
+        for rule in info.rules {
+            out.push_str(&format!("     - {rule}\n"));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_lists_nested_fields_with_defaults() {
+        let tree = render_tree();
+        assert!(tree.contains("  port"));
+        assert!(tree.contains("(default: \"development\")"));
+        assert!(
+            tree.lines()
+                .any(|l| l.starts_with("  pool_size") && l.contains("(default: 10)"))
+        );
+    }
+
+    #[test]
+    fn test_field_details_include_rules() {
+        let info = schema::lookup(&FieldPath::parse("environment").unwrap()).unwrap();
+        let text = render_field(info);
+        assert!(text.contains("one of: development, staging, production"));
+        assert!(text.contains("Required:    no"));
+
+        let info = schema::lookup(&FieldPath::parse("server.port").unwrap()).unwrap();
+        assert!(render_field(info).contains("yes (when server is present)"));
+    }
+}
@@ -0,0 +1,65 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`ExitCode::from(u8)`**: Non-zero exit without going through `anyhow`
+
+**Design decisions**:
+- Loads the file like `show` (environment overrides, then `--override`
+  flags) and prints the winning layer of one field, then what every layer
+  contributed, lowest first
+- Secret values are redacted; the layer names are still shown
+- Exit code 2 for a bad or absent path, as in `get`
+
+******************************************************************************/
+
+use crate::cli::ExplainSourceArgs;
+use crate::exit;
+use crate::overrides::Overrides;
+use crate::path::FieldPath;
+use crate::provenance::{self, Provenance};
+use anyhow::Context;
+use std::process::ExitCode;
+
+/// Runs `config-watcher explain-source`
+pub fn run(args: &ExplainSourceArgs) -> anyhow::Result<ExitCode> {
+    let path = match FieldPath::parse(&args.path) {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            return Ok(ExitCode::from(exit::USAGE));
+        }
+    };
+
+    let mut overrides = Overrides::from_env(std::env::vars()).map_err(exit::usage)?;
+    overrides.extend(
+        Overrides::from_args(&args.overrides, &args.override_strings)
+            .context("Invalid --override")
+            .map_err(exit::usage)?,
+    );
+    let (_, raw) = super::load_document(&args.config_file)?;
+    let mut layered = raw.clone();
+    overrides.apply(&mut layered)?;
+    let config = super::check_document(&layered)?;
+    let effective = serde_json::to_value(&config).context("Failed to serialize configuration")?;
+    let provenance = Provenance::trace(&args.config_file, &raw, &overrides, &effective);
+
+    let Some(chain) = provenance.chain(&path) else {
+        eprintln!("Error: '{path}' is not set in the effective configuration");
+        return Ok(ExitCode::from(exit::USAGE));
+    };
+    let last = chain.len() - 1;
+    println!(
+        "{path} = {}  (from {})",
+        provenance::shown(&path, &chain[last].value),
+        chain[last].layer
+    );
+    for (i, contribution) in chain.iter().enumerate() {
+        let marker = if i == last { "  <- wins" } else { "" };
+        println!(
+            "  {}: {}{marker}",
+            contribution.layer,
+            provenance::shown(&path, &contribution.value)
+        );
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,65 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **Pattern guards on error variants**: only `PathNotFound` is recoverable
+- **`ExitCode::from(u8)`**: Non-zero exit without going through `anyhow`
+
+**Design decisions**:
+- Reads the effective config (defaults filled in), not the raw file
+- Secrets are redacted before the lookup, so even `get database` hides them
+- Exit code 2 for a bad or missing path so scripts can tell it apart from
+  an unreadable file (exit 1)
+
+******************************************************************************/
+
+use crate::cli::GetArgs;
+use crate::error::ConfigError;
+use crate::exit;
+use crate::path::FieldPath;
+use crate::redact;
+use serde_json::Value;
+use std::process::ExitCode;
+
+/// Runs `config-watcher get`
+pub fn run(args: &GetArgs) -> anyhow::Result<ExitCode> {
+    let path = match FieldPath::parse(&args.path) {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            return Ok(ExitCode::from(exit::USAGE));
+        }
+    };
+
+    let mut doc = super::load_effective(&args.config_file)?;
+    if !args.reveal_secrets {
+        redact::redact(&mut doc);
+    }
+
+    match path.resolve(&doc) {
+        Ok(value) => println!("{}", render(value, args.raw)),
+        Err(e @ ConfigError::PathNotFound { .. }) => match &args.default {
+            Some(default) => println!("{default}"),
+            None => {
+                eprintln!("Error: {e}");
+                return Ok(ExitCode::from(exit::USAGE));
+            }
+        },
+        Err(e) => return Err(e.into()),
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Formats a value for printing
+///
+/// Scalars print on one line; objects and arrays are pretty-printed.
+/// With `raw`, strings lose their JSON quotes.
+fn render(value: &Value, raw: bool) -> String {
+    match value {
+        Value::String(s) if raw => s.clone(),
+        Value::Object(_) | Value::Array(_) => {
+            serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+        }
+        _ => value.to_string(),
+    }
+}
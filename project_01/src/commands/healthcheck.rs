@@ -0,0 +1,65 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`std::thread::spawn` + `mpsc::Receiver::recv_timeout`**: The file is
+  read on a helper thread, so a stuck file system cannot hang the probe
+- **`ExitCode`**: 0 healthy, 1 anything else
+
+**Design decisions**:
+- Meant for `HEALTHCHECK` / exec probes: one line of output, a bounded run
+  time, and no dependency on the watcher's stdout
+- A missing or unreadable status file is unhealthy; so is one older than
+  `--stale-after` intervals (or `--max-age`), whatever health it records
+- Degraded (a reload failed, the previous configuration is still served)
+  passes unless `--fail-on-degraded` is given
+
+******************************************************************************/
+
+use crate::cli::HealthcheckArgs;
+use crate::exit;
+use crate::status::{self, StatusRecord, Thresholds};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime};
+
+/// Runs `config-watcher healthcheck`
+pub fn run(args: &HealthcheckArgs) -> anyhow::Result<ExitCode> {
+    let thresholds = Thresholds {
+        max_age: args.max_age,
+        stale_after: args.stale_after,
+        fail_on_degraded: args.fail_on_degraded,
+    };
+    let verdict = read_record(args.status_file.clone(), args.timeout)
+        .and_then(|record| status::evaluate(&record, SystemTime::now(), &thresholds));
+
+    Ok(match verdict {
+        Ok(detail) => {
+            println!("OK: {detail}");
+            ExitCode::SUCCESS
+        }
+        Err(detail) => {
+            println!("FAIL: {detail}");
+            ExitCode::from(exit::UNHEALTHY)
+        }
+    })
+}
+
+/// Reads and parses the status file, giving up after `timeout`
+fn read_record(path: PathBuf, timeout: Duration) -> Result<StatusRecord, String> {
+    let (sender, receiver) = mpsc::channel();
+    let display = path.display().to_string();
+    std::thread::spawn(move || {
+        let result = std::fs::read_to_string(&path)
+            .map_err(|e| format!("cannot read {}: {e}", path.display()))
+            .and_then(|text| {
+                serde_json::from_str(&text)
+                    .map_err(|e| format!("invalid status file {}: {e}", path.display()))
+            });
+        // The receiver is gone if we timed out, nothing left to do
+        let _ = sender.send(result);
+    });
+    receiver
+        .recv_timeout(timeout)
+        .unwrap_or_else(|_| Err(format!("timed out reading {display}")))
+}
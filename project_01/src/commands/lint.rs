@@ -0,0 +1,57 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`ExitCode`**: 0 when no finding is an error, 1 otherwise
+
+**Design decisions**:
+- Lint reads the raw document, so a file that `validate` rejects can still be
+  linted; the `structure` rule then points at `validate` for the details
+- One line per finding on stdout, `severity[rule-id] path: message`, in the
+  style of compiler diagnostics, then a one-line summary
+- `--deny RULE` turns a rule's warnings into errors; the exit status only
+  depends on errors, so CI can adopt rules one at a time
+
+******************************************************************************/
+
+use crate::cli::LintArgs;
+use crate::exit;
+use crate::lint::{self, LintFinding};
+use crate::sarif;
+use crate::validation::Severity;
+use anyhow::Context;
+use std::process::ExitCode;
+
+/// Runs `config-watcher lint`
+pub fn run(args: &LintArgs) -> anyhow::Result<ExitCode> {
+    let (_, doc) = super::load_document(&args.config_file)?;
+    let findings = lint::lint(&doc, &args.deny);
+
+    if let Some(ref out) = args.report_sarif {
+        sarif::write_log(out, &sarif::lint_log(&args.config_file, &findings))
+            .context("Failed to write the SARIF report")?;
+    }
+
+    for finding in &findings {
+        println!("{}", describe(finding));
+    }
+    let errors = findings
+        .iter()
+        .filter(|f| f.finding.severity == Severity::Error)
+        .count();
+    let warnings = findings.len() - errors;
+    println!(
+        "{}: {errors} error(s), {warnings} warning(s)",
+        args.config_file.display()
+    );
+
+    Ok(if errors == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(exit::VALIDATION)
+    })
+}
+
+/// `warning[rule-id] path: message`
+fn describe(lint: &LintFinding) -> String {
+    format!("{}[{}] {}", lint.finding.severity, lint.rule, lint.finding)
+}
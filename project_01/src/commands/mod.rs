@@ -0,0 +1,172 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`std::process::ExitCode`**: Subcommands report their own exit status
+- **Module directories**: `commands/mod.rs` plus one file per subcommand
+
+**Design decisions**:
+- Every one-shot subcommand lives in its own file with a `run` entry point
+- Helpers shared by several subcommands (loading a file into its
+  effective JSON form) live here rather than being duplicated
+- Commands that write the file back (`set`, `patch`) never store an
+  `age:` value in clear: the watcher decrypts those, so the plaintext of
+  a secret would end up on disk
+
+******************************************************************************/
+
+pub(crate) mod completions;
+pub(crate) mod diff;
+pub(crate) mod docs;
+pub(crate) mod doctor;
+pub(crate) mod explain;
+pub(crate) mod explain_source;
+pub(crate) mod get;
+pub(crate) mod healthcheck;
+pub(crate) mod lint;
+pub(crate) mod patch;
+pub(crate) mod rollback;
+pub(crate) mod schema;
+pub(crate) mod service;
+pub(crate) mod set;
+pub(crate) mod show;
+/// `config-watcher validate`: a file checked once
+pub mod validate;
+
+use crate::config::AppConfig;
+use crate::error::ConfigError;
+use crate::path::{FieldPath, Segment};
+use crate::pipeline;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// Reads a configuration file and returns its effective JSON form
+///
+/// The document goes through `AppConfig` so that serde defaults are
+/// materialized (e.g. `environment` is present even if the file omits it).
+pub fn load_effective(path: &Path) -> anyhow::Result<Value> {
+    let contents = read_file(path)
+        .with_context(|| format!("Failed to read configuration file: {}", path.display()))?;
+    pipeline::effective(&contents)
+}
+
+/// Finds string fields that name files (`*_file`, `*_path`, `*_dir`)
+pub fn file_references(value: &Value, prefix: &str) -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    if let Value::Object(map) = value {
+        for (key, child) in map {
+            let field = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{prefix}.{key}")
+            };
+            match child {
+                Value::String(path)
+                    if key.ends_with("_file")
+                        || key.ends_with("_path")
+                        || key.ends_with("_dir") =>
+                {
+                    found.push((field, path.clone()));
+                }
+                Value::Object(_) => found.extend(file_references(child, &field)),
+                _ => {}
+            }
+        }
+    }
+    found
+}
+
+/// Reads a configuration file, telling a missing file from other failures
+pub fn read_file(path: &Path) -> crate::error::Result<String> {
+    std::fs::read_to_string(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => ConfigError::FileNotFound {
+            path: path.to_path_buf(),
+        },
+        _ => ConfigError::ReadError {
+            path: path.to_path_buf(),
+            source: e,
+        },
+    })
+}
+
+/// Reads a configuration file as a raw JSON document, without defaults
+pub fn load_document(path: &Path) -> anyhow::Result<(String, Value)> {
+    let contents = read_file(path)
+        .with_context(|| format!("Failed to read configuration file: {}", path.display()))?;
+    let doc = serde_json::from_str(&contents).context("Failed to parse JSON configuration")?;
+    Ok((contents, doc))
+}
+
+/// Serializes a document for writing back, keeping the original indentation
+///
+/// `original` is the previous file content; its first indented line decides
+/// the indent width (falling back to four spaces).
+pub fn render_document(doc: &Value, original: &str) -> anyhow::Result<String> {
+    let indent: String = original
+        .lines()
+        .skip(1)
+        .find(|line| line.starts_with([' ', '\t']))
+        .map(|line| {
+            line.chars()
+                .take_while(|c| *c == ' ' || *c == '\t')
+                .collect()
+        })
+        .unwrap_or_else(|| "    ".to_string());
+
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut out = Vec::new();
+    let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+    doc.serialize(&mut serializer)
+        .context("Failed to serialize configuration")?;
+    out.push(b'\n');
+    Ok(String::from_utf8(out)?)
+}
+
+/// Checks that a raw document deserializes into a valid `AppConfig`
+pub fn check_document(doc: &Value) -> anyhow::Result<AppConfig> {
+    let config = AppConfig::deserialize(doc).context("Configuration does not match the schema")?;
+    config
+        .validate()
+        .context("Configuration validation failed")?;
+    Ok(config)
+}
+
+/// Prefix of an age-encrypted value, as `age::PREFIX` (which needs the
+/// `age` feature)
+const ENCRYPTED_PREFIX: &str = "age:";
+
+/// Fields holding an `age:` value in `before` that `after` stores in clear
+///
+/// `set`, `patch` and `PUT /config` refuse to write those: the watcher
+/// decrypts them, so the file would then hold the plaintext of a secret.
+pub fn exposed_secrets(before: &Value, after: &Value) -> Vec<String> {
+    let mut encrypted = Vec::new();
+    encrypted_fields(&FieldPath::root(), before, &mut encrypted);
+    encrypted
+        .into_iter()
+        .filter(|path| match path.resolve(after) {
+            Ok(Value::String(text)) => !text.starts_with(ENCRYPTED_PREFIX),
+            Ok(value) => !value.is_null(),
+            Err(_) => false,
+        })
+        .map(|path| path.to_string())
+        .collect()
+}
+
+fn encrypted_fields(path: &FieldPath, value: &Value, out: &mut Vec<FieldPath>) {
+    match value {
+        Value::String(text) if text.starts_with(ENCRYPTED_PREFIX) => out.push(path.clone()),
+        Value::Object(map) => {
+            for (key, child) in map {
+                encrypted_fields(&path.child(Segment::Key(key.clone())), child, out);
+            }
+        }
+        Value::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                encrypted_fields(&path.child(Segment::Index(index)), child, out);
+            }
+        }
+        _ => {}
+    }
+}
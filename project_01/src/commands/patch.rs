@@ -0,0 +1,116 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **Atomic writes**: Delegated to `fs_util::safe_write`, which backs the
+  file up first
+- **`ExitCode`**: 0 when the patch applied (and validated), 1 otherwise
+
+**Design decisions**:
+- Same flow as `set`: edit the raw document, re-validate, write back with
+  the original indentation; an invalid result is refused unless
+  `--no-validate` is given
+- The patch applies all or nothing (see `patch.rs`); an error names the
+  failing operation by its index in the patch array
+- Like `set`, refuses to replace an encrypted value (`age:...`) with
+  plaintext, even with `--dry-run`
+- `--dry-run` prints the patched document on stdout and the validation
+  verdict on stderr, and never touches the file
+
+******************************************************************************/
+
+use crate::cli::PatchArgs;
+use crate::error::ConfigError;
+use crate::exit;
+use crate::fs_util;
+use crate::patch;
+use crate::style::{Icon, Style};
+use anyhow::Context;
+use serde_json::Value;
+use std::process::ExitCode;
+
+/// Runs `config-watcher patch`
+pub fn run(args: &PatchArgs, style: Style) -> anyhow::Result<ExitCode> {
+    let text = std::fs::read_to_string(&args.patch)
+        .with_context(|| format!("Failed to read patch file: {}", args.patch.display()))?;
+    let patch: Value = serde_json::from_str(&text)
+        .map_err(ConfigError::from)
+        .context("Failed to parse JSON Patch")?;
+    let operations = patch::parse(&patch)?;
+
+    let (original, mut doc) = super::load_document(&args.config_file)?;
+    let before = doc.clone();
+    patch::apply(&mut doc, &operations)?;
+
+    let exposed = super::exposed_secrets(&before, &doc);
+    if !exposed.is_empty() {
+        eprintln!(
+            "{}",
+            style.line(
+                Icon::Error,
+                format_args!(
+                    "Refusing to patch {}: {} holds an encrypted (age:) value",
+                    args.config_file.display(),
+                    exposed.join(", ")
+                )
+            )
+        );
+        eprintln!("   Replace it with a value encrypted with age, as age:<base64>");
+        return Ok(ExitCode::from(exit::VALIDATION));
+    }
+
+    let invalid = if args.no_validate {
+        None
+    } else {
+        super::check_document(&doc).err()
+    };
+    let rendered = super::render_document(&doc, &original)?;
+
+    if args.dry_run {
+        print!("{rendered}");
+        return Ok(match invalid {
+            Some(e) => {
+                eprintln!(
+                    "{}",
+                    style.line(
+                        Icon::Error,
+                        format_args!("The patched configuration is invalid: {e:#}")
+                    )
+                );
+                ExitCode::from(exit::VALIDATION)
+            }
+            None => ExitCode::SUCCESS,
+        });
+    }
+
+    if let Some(e) = invalid {
+        eprintln!(
+            "{}",
+            style.line(
+                Icon::Error,
+                format_args!("Refusing to write {}: {e:#}", args.config_file.display())
+            )
+        );
+        eprintln!("   Use --no-validate to write it anyway");
+        return Ok(ExitCode::from(exit::VALIDATION));
+    }
+
+    fs_util::safe_write(
+        &args.config_file,
+        rendered.as_bytes(),
+        args.backup.backups(),
+    )
+    .context("Failed to save configuration")?;
+
+    println!(
+        "{}",
+        style.line(
+            Icon::Ok,
+            format_args!(
+                "Applied {} operation(s) to {}",
+                operations.len(),
+                args.config_file.display()
+            )
+        )
+    );
+    Ok(ExitCode::SUCCESS)
+}
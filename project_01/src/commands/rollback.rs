@@ -0,0 +1,134 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`FromStr`**: `--to` reaches `run` already parsed into a
+  `versions::Selector`
+- **Atomic writes**: Delegated to `fs_util::safe_write`, which backs the
+  file up first
+
+**Design decisions**:
+- The version is checked the way `validate` checks a file (parse,
+  business rules, file references resolved next to the file), so rules
+  that changed since it was kept apply to it. One that fails is refused
+  with its findings and the file is left alone
+- Writing the file is all it does: a running watcher loads it on its next
+  check like any other change, notifiers and diff included, and keeps a
+  new copy of it. Nothing is removed from the versions directory
+- Writing back the content the file already holds is a no-op
+- `--list` marks the version the file currently holds
+
+******************************************************************************/
+
+use super::validate;
+use crate::cli::RollbackArgs;
+use crate::exit;
+use crate::fs_util;
+use crate::report;
+use crate::style::{Icon, Style};
+use crate::versions::{self, Version};
+use anyhow::Context;
+use std::process::ExitCode;
+
+/// Runs `config-watcher rollback`
+pub fn run(args: &RollbackArgs, style: Style) -> anyhow::Result<ExitCode> {
+    let kept = versions::list(&args.state_dir)?;
+    let Some(selector) = args.to else {
+        return Ok(list(args, &kept));
+    };
+    let Some(version) = selector.find(&kept) else {
+        return Err(exit::usage(anyhow::anyhow!(
+            "{selector} is not among the {} versions kept in {}",
+            kept.len(),
+            versions::dir(&args.state_dir).display()
+        )));
+    };
+
+    let contents = std::fs::read_to_string(&version.path)
+        .with_context(|| format!("Cannot read {}", version.path.display()))?;
+    if let Err(e) = validate::validate_contents(&args.config_file, &contents, None) {
+        eprintln!(
+            "{}",
+            style.line(
+                Icon::Error,
+                format_args!(
+                    "Refusing to roll back {} to version {}: {e:#}",
+                    args.config_file.display(),
+                    version.number
+                )
+            )
+        );
+        for finding in report::findings(&e) {
+            eprintln!("   {}", validate::describe(&finding));
+        }
+        return Ok(ExitCode::from(exit::for_error(&e)));
+    }
+
+    if is_current(args, version) {
+        println!(
+            "{}",
+            style.line(
+                Icon::Ok,
+                format_args!(
+                    "{} already holds version {}",
+                    args.config_file.display(),
+                    version.number
+                )
+            )
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
+    fs_util::safe_write(
+        &args.config_file,
+        contents.as_bytes(),
+        args.backup.backups(),
+    )
+    .context("Failed to save configuration")?;
+    println!(
+        "{}",
+        style.line(
+            Icon::Ok,
+            format_args!(
+                "Rolled back {} to version {} (kept at {})",
+                args.config_file.display(),
+                version.number,
+                version.saved_at_rfc3339()
+            )
+        )
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints the kept versions, newest first
+fn list(args: &RollbackArgs, kept: &[Version]) -> ExitCode {
+    if kept.is_empty() {
+        eprintln!(
+            "No versions kept in {}",
+            versions::dir(&args.state_dir).display()
+        );
+    }
+    for version in kept.iter().rev() {
+        let current = if is_current(args, version) {
+            "  (current)"
+        } else {
+            ""
+        };
+        println!(
+            "{:>6}  {}{}",
+            version.number,
+            version.saved_at_rfc3339(),
+            current
+        );
+    }
+    ExitCode::SUCCESS
+}
+
+/// Whether the file holds the content of `version`
+fn is_current(args: &RollbackArgs, version: &Version) -> bool {
+    match (
+        std::fs::read(&args.config_file),
+        std::fs::read(&version.path),
+    ) {
+        (Ok(file), Ok(kept)) => file == kept,
+        _ => false,
+    }
+}
@@ -0,0 +1,88 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`schemars::schema_for!`**: Builds the schema from `#[derive(JsonSchema)]`
+- **`Option<PathBuf>` output**: stdout unless `--output` is given
+
+**Design decisions**:
+- The schema is derived from the structs, so it is always in sync
+- Draft 2020-12 (the schemars default), with descriptions taken from the
+  doc comments and defaults taken from the serde default functions
+
+******************************************************************************/
+
+use crate::cli::SchemaArgs;
+use crate::config::AppConfig;
+use crate::fs_util;
+use crate::style::{Icon, Style};
+use anyhow::Context;
+use serde_json::Value;
+use std::process::ExitCode;
+
+/// Runs `config-watcher schema`
+pub fn run(args: &SchemaArgs, style: Style) -> anyhow::Result<ExitCode> {
+    let mut rendered = serde_json::to_string_pretty(&json_schema())?;
+    rendered.push('\n');
+
+    match &args.output {
+        Some(path) => {
+            fs_util::write_atomic(path, rendered.as_bytes())
+                .context("Failed to write JSON Schema")?;
+            let written = format_args!("JSON Schema written to {}", path.display());
+            println!("{}", style.line(Icon::Ok, written));
+        }
+        None => print!("{rendered}"),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The JSON Schema describing `AppConfig`
+pub fn json_schema() -> Value {
+    schemars::schema_for!(AppConfig).to_value()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_example_config_validates_against_schema() {
+        let validator = jsonschema::draft202012::new(&json_schema()).unwrap();
+        let example = serde_json::to_value(AppConfig::example()).unwrap();
+        assert!(validator.is_valid(&example));
+
+        let sample: Value =
+            serde_json::from_str(include_str!("../../../prj01_example_config.json")).unwrap();
+        assert!(validator.is_valid(&sample));
+    }
+
+    #[test]
+    fn test_schema_constraints() {
+        let schema = json_schema();
+        let validator = jsonschema::draft202012::new(&schema).unwrap();
+
+        assert_eq!(
+            schema["$schema"],
+            "https://json-schema.org/draft/2020-12/schema"
+        );
+        assert_eq!(
+            schema["properties"]["environment"]["default"],
+            "development"
+        );
+
+        // Minimal document: optional sections may be absent or null
+        assert!(validator.is_valid(&json!({ "app_name": "a", "version": "1.0" })));
+        assert!(validator.is_valid(&json!({ "app_name": "a", "version": "1.0", "server": null })));
+
+        // Enum and required fields are enforced
+        assert!(
+            !validator
+                .is_valid(&json!({ "app_name": "a", "version": "1.0", "environment": "moon" }))
+        );
+        assert!(!validator.is_valid(&json!({ "app_name": "a" })));
+        assert!(!validator.is_valid(&json!({
+            "app_name": "a", "version": "1.0", "server": { "host": "h", "port": 0 }
+        })));
+    }
+}
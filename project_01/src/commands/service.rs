@@ -0,0 +1,269 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`Parser::try_parse_from`**: The arguments of `service install` are
+  parsed as a `service run` command line, so a typo fails at install time
+  rather than at the next boot
+- **`#[cfg(all(windows, feature = "windows-service"))]`**: Only the calls
+  to the service control manager are Windows-only; the checks run (and
+  are tested) everywhere
+
+**Design decisions**:
+- `install` registers `config-watcher service run --name NAME --directory
+  DIR ARGS...`: the service starts in the system directory, so the one
+  `install` ran in is recorded for the relative paths of ARGS
+- ARGS must include --log-file: a service has no console, its events and
+  diagnostics would be lost
+- `service run` started by hand, from a console, is a usage error; `main`
+  hands it to the service control manager (`service::dispatch`)
+- Access denied (not elevated), a name already taken and an unknown
+  service are usage errors; the Windows message is kept
+
+******************************************************************************/
+
+use crate::cli::{Cli, Command, ServiceCommand, ServiceInstallArgs, ServiceNameArgs};
+use crate::exit;
+use crate::style::{Icon, Style};
+use anyhow::Context;
+use clap::Parser;
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Runs `config-watcher service install|uninstall`
+pub fn run(command: &ServiceCommand, style: Style) -> anyhow::Result<ExitCode> {
+    match command {
+        ServiceCommand::Install(args) => install(args, style),
+        ServiceCommand::Uninstall(args) => {
+            remove(args)?;
+            let removed = format_args!("Removed service {}", args.name);
+            println!("{}", style.line(Icon::Ok, removed));
+            Ok(ExitCode::SUCCESS)
+        }
+        ServiceCommand::Run(_) => Err(exit::usage(anyhow::anyhow!(
+            "`service run` is started by the service control manager; register it with `service install`"
+        ))),
+    }
+}
+
+fn install(args: &ServiceInstallArgs, style: Style) -> anyhow::Result<ExitCode> {
+    let directory = std::env::current_dir().context("Cannot read the current directory")?;
+    let launch_arguments = launch_arguments(args, &directory).map_err(exit::usage)?;
+    register(args, launch_arguments)?;
+    let start = if args.manual { "on demand" } else { "at boot" };
+    let installed = format_args!(
+        "Installed service {}, started {start}; start it now with: sc start {}",
+        args.service.name, args.service.name
+    );
+    println!("{}", style.line(Icon::Ok, installed));
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The command line the service is started with, once checked
+pub fn launch_arguments(
+    args: &ServiceInstallArgs,
+    directory: &Path,
+) -> anyhow::Result<Vec<OsString>> {
+    let mut launch: Vec<OsString> = vec![
+        "service".into(),
+        "run".into(),
+        "--name".into(),
+        args.service.name.clone().into(),
+        "--directory".into(),
+        directory.into(),
+    ];
+    launch.extend(args.args.iter().cloned());
+
+    let program = OsString::from("config-watcher");
+    let cli = Cli::try_parse_from(std::iter::once(&program).chain(&launch)).map_err(|e| {
+        let message = e.to_string();
+        let first = message.lines().next().unwrap_or_default();
+        anyhow::anyhow!(
+            "Invalid service arguments: {}",
+            first.trim_start_matches("error: ")
+        )
+    })?;
+    let Some(Command::Service(ServiceCommand::Run(ref run))) = cli.command else {
+        unreachable!("parsed as `service run`");
+    };
+    run.watch.validate().context("Invalid service arguments")?;
+    if cli.log_file.is_none() {
+        anyhow::bail!("The service needs --log-file among its arguments: it has no console");
+    }
+    Ok(launch)
+}
+
+/// Creates the service, started with `launch_arguments`
+#[cfg(all(windows, feature = "windows-service"))]
+fn register(args: &ServiceInstallArgs, launch_arguments: Vec<OsString>) -> anyhow::Result<()> {
+    use windows_service::service::{
+        ServiceAccess, ServiceErrorControl, ServiceInfo, ServiceStartType, ServiceType,
+    };
+    use windows_service::service_manager::ServiceManagerAccess;
+
+    let info = ServiceInfo {
+        name: args.service.name.clone().into(),
+        display_name: args.display_name.clone().into(),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: if args.manual {
+            ServiceStartType::OnDemand
+        } else {
+            ServiceStartType::AutoStart
+        },
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe().context("Cannot locate the executable")?,
+        launch_arguments,
+        dependencies: Vec::new(),
+        // LocalSystem
+        account_name: None,
+        account_password: None,
+    };
+    let what = || format!("Cannot install service {}", args.service.name);
+    let service = manager(ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE)?
+        .create_service(&info, ServiceAccess::CHANGE_CONFIG)
+        .map_err(|e| windows_error(e, what()))?;
+    service
+        .set_description("Watches and validates configuration files")
+        .map_err(|e| windows_error(e, what()))
+}
+
+/// Stops the service if needed, then deletes it (gone once stopped)
+#[cfg(all(windows, feature = "windows-service"))]
+fn remove(args: &ServiceNameArgs) -> anyhow::Result<()> {
+    use windows_service::service::{ServiceAccess, ServiceState};
+    use windows_service::service_manager::ServiceManagerAccess;
+
+    let what = || format!("Cannot remove service {}", args.name);
+    let access = ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE;
+    let service = manager(ServiceManagerAccess::CONNECT)?
+        .open_service(&args.name, access)
+        .map_err(|e| windows_error(e, what()))?;
+    let status = service
+        .query_status()
+        .map_err(|e| windows_error(e, what()))?;
+    if matches!(
+        status.current_state,
+        ServiceState::Running | ServiceState::StartPending
+    ) {
+        service.stop().map_err(|e| windows_error(e, what()))?;
+    }
+    service.delete().map_err(|e| windows_error(e, what()))
+}
+
+#[cfg(all(windows, feature = "windows-service"))]
+fn manager(
+    access: windows_service::service_manager::ServiceManagerAccess,
+) -> anyhow::Result<windows_service::service_manager::ServiceManager> {
+    windows_service::service_manager::ServiceManager::local_computer(None::<&str>, access)
+        .map_err(|e| windows_error(e, "Cannot connect to the service control manager".into()))
+}
+
+/// Access denied, an existing or unknown service are usage errors
+#[cfg(all(windows, feature = "windows-service"))]
+fn windows_error(error: windows_service::Error, what: String) -> anyhow::Error {
+    const ERROR_ACCESS_DENIED: i32 = 5;
+    const ERROR_SERVICE_DOES_NOT_EXIST: i32 = 1060;
+    const ERROR_SERVICE_EXISTS: i32 = 1073;
+
+    let code = match error {
+        windows_service::Error::Winapi(ref e) => e.raw_os_error(),
+        _ => None,
+    };
+    let error = anyhow::Error::new(error).context(what);
+    match code {
+        Some(ERROR_ACCESS_DENIED) => {
+            exit::usage(error.context("Run it from an elevated (administrator) prompt"))
+        }
+        Some(ERROR_SERVICE_DOES_NOT_EXIST | ERROR_SERVICE_EXISTS) => exit::usage(error),
+        _ => error,
+    }
+}
+
+#[cfg(not(all(windows, feature = "windows-service")))]
+fn register(_: &ServiceInstallArgs, _: Vec<OsString>) -> anyhow::Result<()> {
+    Err(unsupported())
+}
+
+#[cfg(not(all(windows, feature = "windows-service")))]
+fn remove(_: &ServiceNameArgs) -> anyhow::Result<()> {
+    Err(unsupported())
+}
+
+#[cfg(not(all(windows, feature = "windows-service")))]
+fn unsupported() -> anyhow::Error {
+    exit::usage(if cfg!(windows) {
+        anyhow::anyhow!("`service` needs a build with the `windows-service` feature")
+    } else {
+        anyhow::anyhow!("`service` is only supported on Windows")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn install_args(args: &[&str]) -> ServiceInstallArgs {
+        let mut command = vec!["config-watcher", "service", "install", "--name", "cw", "--"];
+        command.extend(args);
+        match Cli::parse_from(command).command {
+            Some(Command::Service(ServiceCommand::Install(args))) => args,
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_launch_arguments_run_the_service_from_the_install_directory() {
+        let args = install_args(&["-f", "app.json", "--interval", "5", "--log-file", "cw.log"]);
+        let launch = launch_arguments(&args, Path::new("/srv/app")).unwrap();
+        assert_eq!(
+            launch,
+            [
+                "service",
+                "run",
+                "--name",
+                "cw",
+                "--directory",
+                "/srv/app",
+                "-f",
+                "app.json",
+                "--interval",
+                "5",
+                "--log-file",
+                "cw.log"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_arguments_are_refused_at_install() {
+        let err = launch_arguments(&install_args(&["-f", "app.json"]), Path::new("/"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("--log-file"), "{err}");
+
+        let err = launch_arguments(
+            &install_args(&["--log-file", "cw.log", "--no-such-flag"]),
+            Path::new("/"),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("--no-such-flag"), "{err}");
+
+        // Checked like the watch options of the command line
+        let err = launch_arguments(
+            &install_args(&[
+                "-f",
+                "a.json",
+                "-f",
+                "b.json",
+                "--fallback-config",
+                "default.json",
+                "--log-file",
+                "cw.log",
+            ]),
+            Path::new("/"),
+        )
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("--fallback-config"), "{err:#}");
+    }
+}
@@ -0,0 +1,78 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`ValueEnum`**: `--type` is parsed straight into `ValueType`
+- **Atomic writes**: Delegated to `fs_util::safe_write`, which backs the
+  file up first
+
+**Design decisions**:
+- Edits the raw document, so defaults are never written into the file
+- Re-validates before writing; an invalid result is refused unless
+  `--no-validate` is given
+- An encrypted value (`age:...`) can only be replaced by another one,
+  never by plaintext, `--no-validate` or not
+- Missing or `null` parent sections are created on the fly
+- Value typing is shared with `--override` (see `overrides.rs`)
+
+******************************************************************************/
+
+use crate::cli::SetArgs;
+use crate::exit;
+use crate::fs_util;
+use crate::overrides::parse_value;
+use crate::path::FieldPath;
+use crate::style::{Icon, Style};
+use anyhow::Context;
+use std::process::ExitCode;
+
+/// Runs `config-watcher set`
+pub fn run(args: &SetArgs, style: Style) -> anyhow::Result<ExitCode> {
+    let path = FieldPath::parse(&args.path)?;
+    let value = parse_value(&args.value, args.value_type)?;
+
+    let (original, mut doc) = super::load_document(&args.config_file)?;
+    let before = doc.clone();
+    path.set(&mut doc, value.clone())?;
+
+    let exposed = super::exposed_secrets(&before, &doc);
+    if !exposed.is_empty() {
+        eprintln!(
+            "{}",
+            style.line(
+                Icon::Error,
+                format_args!(
+                    "Refusing to write {}: {} holds an encrypted (age:) value",
+                    args.config_file.display(),
+                    exposed.join(", ")
+                )
+            )
+        );
+        eprintln!("   Set it to a value encrypted with age, as age:<base64>");
+        return Ok(ExitCode::from(exit::VALIDATION));
+    }
+
+    if !args.no_validate
+        && let Err(e) = super::check_document(&doc)
+    {
+        eprintln!(
+            "{}",
+            style.line(
+                Icon::Error,
+                format_args!("Refusing to write {}: {e:#}", args.config_file.display())
+            )
+        );
+        eprintln!("   Use --no-validate to write it anyway");
+        return Ok(ExitCode::from(exit::VALIDATION));
+    }
+
+    let rendered = super::render_document(&doc, &original)?;
+    fs_util::safe_write(
+        &args.config_file,
+        rendered.as_bytes(),
+        args.backup.backups(),
+    )
+    .context("Failed to save configuration")?;
+
+    println!("{}", style.line(Icon::Ok, format_args!("{path} = {value}")));
+    Ok(ExitCode::SUCCESS)
+}
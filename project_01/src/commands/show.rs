@@ -0,0 +1,163 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`toml::Value::try_from`**: Any `Serialize` value converts to TOML, as
+  long as it holds no `null`
+- **Recursion over `serde_json::Value`**: `--diff-defaults` walks the leaves
+  of the effective document
+
+**Design decisions**:
+- Same pipeline as a watcher load: raw document, environment overrides,
+  then `--override` flags, then `AppConfig` with its serde defaults and the
+  business rules; what is printed is what `watch` would run with
+- Secrets are redacted unless `--reveal-secrets`, as in `get`
+- A field counts as explicitly set when the layered document (file plus
+  overrides) contains it; everything else came from a default
+
+******************************************************************************/
+
+use crate::cli::{ShowArgs, ShowFormat};
+use crate::exit;
+use crate::overrides::Overrides;
+use crate::path::FieldPath;
+use crate::provenance::leaves;
+use crate::redact;
+use anyhow::Context;
+use serde_json::Value;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Runs `config-watcher show`
+pub fn run(args: &ShowArgs) -> anyhow::Result<ExitCode> {
+    let mut overrides = Overrides::from_env(std::env::vars()).map_err(exit::usage)?;
+    overrides.extend(
+        Overrides::from_args(&args.overrides, &args.override_strings)
+            .context("Invalid --override")
+            .map_err(exit::usage)?,
+    );
+    let (layered, mut effective) = resolve(&args.config_file, &overrides)?;
+    if !args.reveal_secrets {
+        redact::redact(&mut effective);
+    }
+
+    if args.diff_defaults {
+        for (path, value) in leaves(&FieldPath::root(), &effective) {
+            let origin = if path.resolve(&layered).is_ok() {
+                "set"
+            } else {
+                "default"
+            };
+            println!("{path} = {value}  # {origin}");
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    match args.format {
+        ShowFormat::Json => println!("{}", serde_json::to_string_pretty(&effective)?),
+        ShowFormat::Toml => {
+            let value = toml::Value::try_from(without_nulls(effective))
+                .context("Failed to convert the configuration to TOML")?;
+            print!("{}", toml::to_string_pretty(&value)?);
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Loads `path` with `overrides` applied
+///
+/// Returns the layered raw document and the effective one, every default
+/// materialized.
+pub fn resolve(path: &Path, overrides: &Overrides) -> anyhow::Result<(Value, Value)> {
+    let (_, mut layered) = super::load_document(path)?;
+    overrides.apply(&mut layered)?;
+    let config = super::check_document(&layered)?;
+    let effective = serde_json::to_value(&config).context("Failed to serialize configuration")?;
+    Ok((layered, effective))
+}
+
+/// TOML has no `null`: absent optional values are dropped
+fn without_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, child)| !child.is_null())
+                .map(|(key, child)| (key, without_nulls(child)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(without_nulls).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_minimal_file_resolves_every_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.json");
+        std::fs::write(
+            &file,
+            r#"{ "app_name": "A", "version": "1.0.0",
+                 "server": { "host": "h", "port": 80 },
+                 "database": { "connection_string": "postgres://localhost/db" } }"#,
+        )
+        .unwrap();
+
+        let (layered, effective) = resolve(&file, &Overrides::default()).unwrap();
+        assert_eq!(
+            effective,
+            json!({
+                "app_name": "A",
+                "version": "1.0.0",
+                "environment": "development",
+                "server": {
+                    "host": "h",
+                    "port": 80,
+                    "enable_ssl": true,
+                    "allowed_ips": [],
+                    "denied_ips": [],
+                    "max_connections": 1024,
+                    "keep_alive": "1m 15s",
+                    "request_timeout": "30s",
+                    "shutdown_grace": "30s"
+                },
+                "database": {
+                    "connection_string": "postgres://localhost/db",
+                    "pool_size": 10,
+                    "timeout_seconds": 30
+                },
+                "features": {}
+            })
+        );
+
+        let origins: Vec<_> = leaves(&FieldPath::root(), &effective)
+            .into_iter()
+            .map(|(path, _)| (path.to_string(), path.resolve(&layered).is_ok()))
+            .collect();
+        assert!(origins.contains(&("server.port".to_string(), true)));
+        assert!(origins.contains(&("server.enable_ssl".to_string(), false)));
+        assert!(origins.contains(&("environment".to_string(), false)));
+    }
+
+    #[test]
+    fn test_overrides_are_layered() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.json");
+        std::fs::write(&file, r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+
+        let overrides = Overrides::from_args(&["environment=staging".to_string()], &[]).unwrap();
+        let (_, effective) = resolve(&file, &overrides).unwrap();
+        assert_eq!(effective["environment"], "staging");
+    }
+
+    #[test]
+    fn test_without_nulls() {
+        assert_eq!(
+            without_nulls(json!({ "a": null, "b": { "c": null, "d": 1 } })),
+            json!({ "b": { "d": 1 } })
+        );
+    }
+}
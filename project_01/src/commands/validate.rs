@@ -0,0 +1,232 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`std::io::ErrorKind::NotFound`**: Turned into `ConfigError::FileNotFound`
+  so it gets its own error code
+- **`ExitCode`**: 0 when the file is valid, 1 otherwise
+
+**Design decisions**:
+- Same checks as a watcher load (parse, business rules, optional external
+  schema), but the warnings are kept and shown instead of being dropped
+- With `--error-format json` a failure prints one `ErrorReport` on stdout,
+  the command's result channel; human-readable lines go to stderr
+- With `--output-annotations github` (the default under GitHub Actions) every
+  finding is also printed on stdout as a workflow command, and the success
+  line moves to stderr so stdout carries annotations only
+- `--report-sarif` is written before anything is printed, whatever the
+  outcome, so code scanning always has a file to upload
+- `--stdin --assume-path` validates staged content in a pre-commit hook: the
+  document comes from stdin, everything else (format, relative file
+  references, reports) from the assumed path
+- File references (`*_file`, `*_path`, `*_dir`, as in `doctor`) must exist,
+  resolved relative to the file's directory
+- Inputs above `MAX_CONFIG_SIZE` are rejected before parsing
+- `validate_bytes` is the pure bytes-to-report pipeline, the entry point of
+  the `fuzz/` targets
+
+******************************************************************************/
+
+use crate::annotations::{self, AnnotationFormat};
+use crate::cli::{ErrorFormat, ValidateArgs};
+use crate::config::{MAX_CONFIG_SIZE, SUPPORTED_EXTENSIONS};
+use crate::error::ConfigError;
+use crate::exit;
+use crate::external_schema::ExternalSchema;
+use crate::pipeline;
+use crate::report::{self, ErrorReport};
+use crate::sarif;
+use crate::style::{Icon, Style};
+use crate::validation::{Finding, ValidationReport};
+use anyhow::Context;
+use std::io::Read;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Runs `config-watcher validate`
+pub fn run(args: &ValidateArgs, style: Style) -> anyhow::Result<ExitCode> {
+    let schema = match args.schema {
+        Some(ref path) => {
+            Some(ExternalSchema::load(path).context("Failed to compile JSON Schema")?)
+        }
+        None => None,
+    };
+    let annotate = AnnotationFormat::resolve(
+        args.output_annotations,
+        std::env::var("GITHUB_ACTIONS").ok().as_deref(),
+    ) == AnnotationFormat::Github;
+
+    let target = args.target();
+    let outcome = if args.stdin {
+        read_stdin(target)
+            .and_then(|contents| validate_contents(target, &contents, schema.as_ref()))
+    } else {
+        validate(target, schema.as_ref())
+    };
+    if let Some(ref out) = args.report_sarif {
+        sarif::write(out, target, outcome.as_ref()).context("Failed to write the SARIF report")?;
+    }
+
+    match outcome {
+        Ok(report) => {
+            for finding in report.warnings() {
+                eprintln!("{}", style.line(Icon::Warning, describe(finding)));
+                if annotate {
+                    println!("{}", annotations::github(target, finding));
+                }
+            }
+            let valid = style.line(Icon::Ok, format_args!("{} is valid", target.display()));
+            if annotate {
+                eprintln!("{valid}");
+            } else {
+                println!("{valid}");
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(e) => {
+            if annotate {
+                annotate_failure(target, &e);
+            }
+            match args.error_format {
+                ErrorFormat::Json => println!("{}", ErrorReport::new(target, &e).to_json_line()),
+                ErrorFormat::Text => {
+                    eprintln!(
+                        "{}",
+                        style.line(Icon::Error, format_args!("{}: {e:#}", target.display()))
+                    );
+                    for finding in report::findings(&e) {
+                        eprintln!("   {}", describe(&finding));
+                    }
+                }
+            }
+            Ok(ExitCode::from(exit::for_error(&e)))
+        }
+    }
+}
+
+/// Loads and checks `path`; the report holds the warnings of a valid file
+pub fn validate(path: &Path, schema: Option<&ExternalSchema>) -> anyhow::Result<ValidationReport> {
+    let read_error = |e: std::io::Error| match e.kind() {
+        std::io::ErrorKind::NotFound => ConfigError::FileNotFound {
+            path: path.to_path_buf(),
+        },
+        _ => ConfigError::ReadError {
+            path: path.to_path_buf(),
+            source: e,
+        },
+    };
+    let size = std::fs::metadata(path).map_err(read_error)?.len();
+    if size > MAX_CONFIG_SIZE {
+        return Err(too_large(path).into());
+    }
+    let bytes = std::fs::read(path).map_err(read_error)?;
+    validate_bytes(path, &bytes, schema)
+}
+
+/// Checks raw `bytes` as if they had been read from `path`
+///
+/// The whole pipeline short of the read itself: size limit, UTF-8
+/// decoding, then [`validate_contents`]. Hostile input must come back as
+/// an error, never a panic; the `fuzz/` targets feed it arbitrary bytes.
+pub fn validate_bytes(
+    path: &Path,
+    bytes: &[u8],
+    schema: Option<&ExternalSchema>,
+) -> anyhow::Result<ValidationReport> {
+    if bytes.len() as u64 > MAX_CONFIG_SIZE {
+        return Err(too_large(path).into());
+    }
+    let contents = std::str::from_utf8(bytes).map_err(|e| ConfigError::ReadError {
+        path: path.to_path_buf(),
+        source: std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+    })?;
+    validate_contents(path, contents, schema)
+}
+
+/// Checks `contents` as if they had been read from `path`
+///
+/// `path` decides the format and the directory file references are
+/// resolved against; it is never read.
+pub fn validate_contents(
+    path: &Path,
+    contents: &str,
+    schema: Option<&ExternalSchema>,
+) -> anyhow::Result<ValidationReport> {
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str())
+        && !SUPPORTED_EXTENSIONS.contains(&ext)
+    {
+        return Err(ConfigError::UnsupportedFormat {
+            path: path.to_path_buf(),
+        }
+        .into());
+    }
+
+    let (doc, config) = pipeline::parse(contents)?;
+
+    let mut report = ValidationReport::new();
+    if let Some(schema) = schema {
+        report.merge(schema.check(&doc));
+    }
+    report.merge(config.check());
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    for (field, reference) in super::file_references(&doc, "") {
+        let resolved = base_dir.join(&reference);
+        if let Err(e) = std::fs::metadata(&resolved) {
+            report.error(field, format!("cannot access {}: {e}", resolved.display()));
+        }
+    }
+    Ok(report.into_result()?)
+}
+
+/// Reads the whole of stdin, within the size limit
+fn read_stdin(path: &Path) -> anyhow::Result<String> {
+    let mut contents = String::new();
+    std::io::stdin()
+        .lock()
+        .take(MAX_CONFIG_SIZE + 1)
+        .read_to_string(&mut contents)
+        .map_err(|source| ConfigError::ReadError {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    if contents.len() as u64 > MAX_CONFIG_SIZE {
+        return Err(too_large(path).into());
+    }
+    if contents.trim().is_empty() {
+        return Err(ConfigError::EmptyInput {
+            path: path.to_path_buf(),
+        }
+        .into());
+    }
+    Ok(contents)
+}
+
+fn too_large(path: &Path) -> ConfigError {
+    ConfigError::TooLarge {
+        path: path.to_path_buf(),
+        limit: MAX_CONFIG_SIZE,
+    }
+}
+
+/// Annotations for a failed file; errors without findings get one of their own
+fn annotate_failure(path: &Path, error: &anyhow::Error) {
+    let findings = report::findings(error);
+    if findings.is_empty() {
+        let message = format!("{error:#}");
+        println!(
+            "{}",
+            annotations::github_command("error", path, None, None, &message)
+        );
+    }
+    for finding in &findings {
+        println!("{}", annotations::github(path, finding));
+    }
+}
+
+/// One finding on one line, with its position when known
+pub fn describe(finding: &Finding) -> String {
+    let position = match (finding.line, finding.column) {
+        (Some(line), Some(column)) => format!(" (line {line}, column {column})"),
+        _ => String::new(),
+    };
+    format!("{}: {}{}", finding.severity, finding, position)
+}
@@ -4,24 +4,30 @@
 - **`#[derive(Serialize, Deserialize)]`**: Automatic serde implementation
 - **`#[serde(default)]`**: Uses Default trait or custom function if field is missing
 - **`#[serde(skip_serializing_if)]`**: Omits field from output if condition is true
+- **`#[derive(JsonSchema)]`**: Derives a JSON Schema from the same definitions
 - **`impl` blocks**: Methods associated with the struct
-- **Business validation**: Separate from type validation
+- **Business validation**: Separate from type validation, collected into a
+  `ValidationReport` rather than stopping at the first problem
 
 **Design decisions**:
 - Using `Option<T>` for optional configuration sections
 - Providing sensible defaults with `#[serde(default)]`
 - Validation logic separate from deserialization (business rules vs. type safety)
+- Doc comments double as descriptions in the exported JSON Schema
 
 ******************************************************************************/
 
+use crate::validation::ValidationReport;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
 
 /// Application configuration structure
 ///
 /// This represents the expected schema of our JSON config file.
 /// Serde will handle serialization/deserialization automatically.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct AppConfig {
     /// Application name (required)
     pub app_name: String,
@@ -31,6 +37,7 @@ pub struct AppConfig {
 
     /// Environment (development, staging, production)
     #[serde(default = "default_environment")]
+    #[schemars(extend("enum" = ENVIRONMENTS))]
     pub environment: String,
 
     /// Server configuration (optional)
@@ -41,31 +48,225 @@ pub struct AppConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub database: Option<DatabaseConfig>,
 
+    /// Outbound proxy settings (optional)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyConfig>,
+
+    /// Message broker settings (optional)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub messaging: Option<MessagingConfig>,
+
+    /// Authentication settings (optional)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthConfig>,
+
     /// Feature flags (optional)
-    #[serde(default)]
+    #[serde(default, serialize_with = "sorted_map")]
     pub features: HashMap<String, bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Server configuration section
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct ServerConfig {
+    /// Host name or address to bind
     pub host: String,
+
+    /// TCP port to listen on
+    #[schemars(range(min = 1))]
     pub port: u16,
 
+    /// Serve over TLS
     #[serde(default = "default_true")]
     pub enable_ssl: bool,
+
+    /// Client IPs or CIDR blocks allowed to connect (empty allows all)
+    #[serde(default)]
+    pub allowed_ips: Vec<String>,
+
+    /// Client IPs or CIDR blocks always refused, even if allowed
+    #[serde(default)]
+    pub denied_ips: Vec<String>,
+
+    /// Maximum number of simultaneous client connections
+    #[serde(default = "default_max_connections")]
+    #[schemars(range(min = 1))]
+    pub max_connections: u32,
+
+    /// How long an idle connection is kept open
+    #[serde(default = "default_keep_alive", with = "crate::duration")]
+    #[schemars(with = "String")]
+    pub keep_alive: Duration,
+
+    /// How long a single request may take
+    #[serde(default = "default_request_timeout", with = "crate::duration")]
+    #[schemars(with = "String")]
+    pub request_timeout: Duration,
+
+    /// How long in-flight requests get to finish on shutdown
+    #[serde(default = "default_shutdown_grace", with = "crate::duration")]
+    #[schemars(with = "String")]
+    pub shutdown_grace: Duration,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Database configuration section
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct DatabaseConfig {
+    /// Database connection URL (may contain credentials)
     pub connection_string: String,
 
+    /// Maximum number of pooled connections
     #[serde(default = "default_pool_size")]
+    #[schemars(range(min = 1))]
     pub pool_size: u32,
 
+    /// Connection timeout in seconds
     #[serde(default = "default_timeout")]
     pub timeout_seconds: u64,
 }
 
+/// Proxy configuration section
+///
+/// Validation and `no_proxy` matching live in `proxy.rs`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ProxyConfig {
+    /// Proxy URL for plain HTTP requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http: Option<String>,
+
+    /// Proxy URL for HTTPS requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub https: Option<String>,
+
+    /// Hosts, IPs, CIDR ranges or .domain suffixes reached directly
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+}
+
+/// Message broker configuration section
+///
+/// Validation and broker address parsing live in `messaging.rs`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct MessagingConfig {
+    /// Broker protocol
+    pub kind: MessagingKind,
+
+    /// Broker addresses, `host:port` or `[ipv6]:port`
+    #[serde(default)]
+    pub brokers: Vec<String>,
+
+    /// Prefix of every topic or queue name
+    #[schemars(regex(pattern = r"^[a-zA-Z0-9._-]+$"))]
+    pub topic_prefix: String,
+
+    /// Connect to the brokers over TLS
+    #[serde(default = "default_true")]
+    pub tls: bool,
+
+    /// Consumer group (Kafka only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consumer_group: Option<String>,
+}
+
+/// Supported message brokers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum MessagingKind {
+    /// Apache Kafka, which needs a `consumer_group`
+    Kafka,
+    /// AMQP (RabbitMQ and the like), with no consumer group
+    Amqp,
+}
+
+/// Authentication configuration section
+///
+/// Validation and secret file checks live in `auth.rs`, as do the `Debug`
+/// implementations, which hide the secret file paths.
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct AuthConfig {
+    /// JSON Web Token verification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwt: Option<JwtConfig>,
+
+    /// OAuth 2.0 client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth: Option<OAuthConfig>,
+}
+
+/// JWT verification settings
+#[derive(Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct JwtConfig {
+    /// Expected `iss` claim, an https URL
+    pub issuer: String,
+
+    /// Accepted `aud` claims
+    #[serde(default)]
+    pub audience: Vec<String>,
+
+    /// Where the signing keys are published (set this or `secret_file`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwks_url: Option<String>,
+
+    /// File holding the HMAC secret (set this or `jwks_url`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_file: Option<String>,
+
+    /// Tolerance on `exp` and `nbf` for clocks out of sync
+    #[serde(default = "default_clock_skew", with = "crate::duration")]
+    #[schemars(with = "String")]
+    pub clock_skew: Duration,
+}
+
+/// OAuth 2.0 client settings
+#[derive(Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct OAuthConfig {
+    /// Authorization endpoint
+    pub authorize_url: String,
+
+    /// Token endpoint
+    pub token_url: String,
+
+    /// Client identifier registered with the provider
+    pub client_id: String,
+
+    /// File holding the client secret
+    pub client_secret_file: String,
+}
+
+/// File extensions the watcher knows how to read
+pub const SUPPORTED_EXTENSIONS: &[&str] = &["json"];
+
+/// Largest configuration accepted by `validate`, in bytes
+pub const MAX_CONFIG_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Allowed values for `AppConfig::environment`
+pub const ENVIRONMENTS: [&str; 3] = ["development", "staging", "production"];
+
+/// Above this many connections, `server.max_connections` gets a warning
+pub const MAX_CONNECTIONS_WARNING: u32 = 100_000;
+
+/// Allowed range for `server.keep_alive` and `server.request_timeout`
+pub const TIMEOUT_RANGE: std::ops::RangeInclusive<Duration> =
+    Duration::from_secs(1)..=Duration::from_secs(3600);
+
+/// Upper bound for `server.shutdown_grace`
+pub const MAX_SHUTDOWN_GRACE: Duration = Duration::from_secs(600);
+
+/// Upper bound for `auth.jwt.clock_skew`
+pub const MAX_CLOCK_SKEW: Duration = Duration::from_secs(300);
+
+/// Serializes a map with its keys in alphabetical order
+///
+/// `HashMap` iteration order is random; sorting keeps every output
+/// (get, docs, diffs...) stable from one run to the next.
+fn sorted_map<S: serde::Serializer>(
+    map: &HashMap<String, bool>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    let sorted: std::collections::BTreeMap<_, _> = map.iter().collect();
+    sorted.serialize(serializer)
+}
+
 // Default value functions for serde
 fn default_environment() -> String {
     "development".to_string()
@@ -83,73 +284,220 @@ fn default_timeout() -> u64 {
     30
 }
 
+fn default_max_connections() -> u32 {
+    1024
+}
+
+fn default_keep_alive() -> Duration {
+    Duration::from_secs(75)
+}
+
+fn default_request_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_shutdown_grace() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_clock_skew() -> Duration {
+    Duration::from_secs(60)
+}
+
 impl AppConfig {
+    /// A complete sample configuration with every section filled in
+    ///
+    /// Used as the example in generated documentation and in tests
+    pub fn example() -> Self {
+        Self {
+            app_name: "MyAwesomeApp".to_string(),
+            version: "1.0.0".to_string(),
+            environment: default_environment(),
+            server: Some(ServerConfig {
+                host: "localhost".to_string(),
+                port: 8080,
+                enable_ssl: false,
+                allowed_ips: vec!["10.0.0.0/8".to_string(), "2001:db8::/32".to_string()],
+                denied_ips: vec!["10.0.0.13".to_string()],
+                max_connections: 2048,
+                keep_alive: Duration::from_secs(90),
+                request_timeout: Duration::from_secs(20),
+                shutdown_grace: Duration::from_secs(45),
+            }),
+            database: Some(DatabaseConfig {
+                connection_string: "postgres://localhost/mydb".to_string(),
+                pool_size: 15,
+                timeout_seconds: 45,
+            }),
+            proxy: Some(ProxyConfig {
+                http: Some("http://proxy.example.com:3128".to_string()),
+                https: Some("http://proxy.example.com:3128".to_string()),
+                no_proxy: vec!["localhost".to_string(), ".example.com".to_string()],
+            }),
+            messaging: Some(MessagingConfig {
+                kind: MessagingKind::Kafka,
+                brokers: vec![
+                    "kafka-1.example.com:9092".to_string(),
+                    "[2001:db8::10]:9092".to_string(),
+                ],
+                topic_prefix: "myapp.".to_string(),
+                tls: true,
+                consumer_group: Some("myapp-consumers".to_string()),
+            }),
+            auth: Some(AuthConfig {
+                jwt: Some(JwtConfig {
+                    issuer: "https://auth.example.com/".to_string(),
+                    audience: vec!["myapp-api".to_string()],
+                    jwks_url: Some("https://auth.example.com/.well-known/jwks.json".to_string()),
+                    secret_file: None,
+                    clock_skew: Duration::from_secs(30),
+                }),
+                oauth: Some(OAuthConfig {
+                    authorize_url: "https://auth.example.com/oauth/authorize".to_string(),
+                    token_url: "https://auth.example.com/oauth/token".to_string(),
+                    client_id: "myapp".to_string(),
+                    client_secret_file: "secrets/oauth_client_secret".to_string(),
+                }),
+            }),
+            features: HashMap::from([
+                ("enable_caching".to_string(), true),
+                ("enable_analytics".to_string(), false),
+                ("debug_mode".to_string(), true),
+            ]),
+        }
+    }
+
     /// Validates the configuration structure
     ///
-    /// This goes beyond serde's type checking to enforce business rules
+    /// This goes beyond serde's type checking to enforce business rules.
+    /// Warnings are ignored here; use [`AppConfig::check`] to see them.
     pub fn validate(&self) -> crate::error::Result<()> {
-        use crate::error::ConfigError;
+        self.check().into_result().map(|_| ())
+    }
+
+    /// Runs every business rule and collects the findings
+    ///
+    /// ```
+    /// use config_watcher::{AppConfig, Severity};
+    ///
+    /// let config: AppConfig = serde_json::from_str(
+    ///     r#"{ "app_name": "demo", "version": "1.0.0", "environment": "qa" }"#,
+    /// )?;
+    /// let report = config.check();
+    /// let finding = report.errors().next().unwrap();
+    /// assert_eq!((finding.severity, finding.path.as_str()), (Severity::Error, "environment"));
+    /// # Ok::<(), serde_json::Error>(())
+    /// ```
+    pub fn check(&self) -> ValidationReport {
+        let mut report = ValidationReport::new();
 
         // Validate app_name is not empty
         if self.app_name.trim().is_empty() {
-            return Err(ConfigError::ValidationFailed {
-                reason: "app_name cannot be empty".to_string(),
-            });
+            report.error("app_name", "cannot be empty");
         }
 
         // Validate version format (basic semver check)
         if !self.version.contains('.') {
-            return Err(ConfigError::ValidationFailed {
-                reason: format!(
-                    "version '{}' should follow semver format (e.g., 1.0.0)",
+            report.error(
+                "version",
+                format!(
+                    "'{}' should follow semver format (e.g., 1.0.0)",
                     self.version
                 ),
-            });
+            );
         }
 
         // Validate environment values
-        let valid_envs = ["development", "staging", "production"];
-        if !valid_envs.contains(&self.environment.as_str()) {
-            return Err(ConfigError::ValidationFailed {
-                reason: format!("environment must be one of: {}", valid_envs.join(", ")),
-            });
+        if !ENVIRONMENTS.contains(&self.environment.as_str()) {
+            report.error(
+                "environment",
+                format!("must be one of: {}", ENVIRONMENTS.join(", ")),
+            );
         }
 
         // Validate server config if present
         if let Some(ref server) = self.server {
             if server.host.trim().is_empty() {
-                return Err(ConfigError::ValidationFailed {
-                    reason: "server.host cannot be empty".to_string(),
-                });
+                report.error("server.host", "cannot be empty");
             }
             if server.port == 0 {
-                return Err(ConfigError::ValidationFailed {
-                    reason: "server.port must be greater than 0".to_string(),
-                });
+                report.error("server.port", "must be greater than 0");
             }
+            server.check_acl(&mut report);
+            server.check_tuning(&mut report);
         }
 
         // Validate database config if present
         if let Some(ref db) = self.database {
             if db.connection_string.trim().is_empty() {
-                return Err(ConfigError::ValidationFailed {
-                    reason: "database.connection_string cannot be empty".to_string(),
-                });
+                report.error("database.connection_string", "cannot be empty");
             }
             if db.pool_size == 0 {
-                return Err(ConfigError::ValidationFailed {
-                    reason: "database.pool_size must be greater than 0".to_string(),
-                });
+                report.error("database.pool_size", "must be greater than 0");
             }
         }
 
-        Ok(())
+        // Validate proxy config if present
+        if let Some(ref proxy) = self.proxy {
+            proxy.check(&mut report);
+        }
+
+        // Validate messaging config if present
+        if let Some(ref messaging) = self.messaging {
+            messaging.check(&self.environment, &mut report);
+        }
+
+        // Validate auth config if present
+        if let Some(ref auth) = self.auth {
+            auth.check(&self.environment, &mut report);
+        }
+
+        report
+    }
+}
+
+impl ServerConfig {
+    /// Checks connection limits and timeouts
+    fn check_tuning(&self, report: &mut ValidationReport) {
+        if self.max_connections == 0 {
+            report.error("server.max_connections", "must be at least 1");
+        } else if self.max_connections > MAX_CONNECTIONS_WARNING {
+            report.warning(
+                "server.max_connections",
+                format!(
+                    "{} is unusually high (above {MAX_CONNECTIONS_WARNING})",
+                    self.max_connections
+                ),
+            );
+        }
+
+        for (path, value) in [
+            ("server.keep_alive", self.keep_alive),
+            ("server.request_timeout", self.request_timeout),
+        ] {
+            if !TIMEOUT_RANGE.contains(&value) {
+                report.error(path, "must be between 1s and 1h");
+            }
+        }
+
+        if self.shutdown_grace > MAX_SHUTDOWN_GRACE {
+            report.error("server.shutdown_grace", "must be at most 10m");
+        }
+
+        if self.request_timeout >= self.keep_alive {
+            report.warning(
+                "server.request_timeout",
+                "should be shorter than server.keep_alive",
+            );
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::{app_config, valid_app_config};
+    use proptest::prelude::*;
 
     #[test]
     fn test_valid_config_deserialization() {
@@ -189,6 +537,9 @@ mod tests {
             environment: "development".to_string(),
             server: None,
             database: None,
+            proxy: None,
+            messaging: None,
+            auth: None,
             features: HashMap::new(),
         };
 
@@ -203,6 +554,9 @@ mod tests {
             environment: "development".to_string(),
             server: None,
             database: None,
+            proxy: None,
+            messaging: None,
+            auth: None,
             features: HashMap::new(),
         };
 
@@ -217,6 +571,9 @@ mod tests {
             environment: "invalid".to_string(),
             server: None,
             database: None,
+            proxy: None,
+            messaging: None,
+            auth: None,
             features: HashMap::new(),
         };
 
@@ -233,8 +590,17 @@ mod tests {
                 host: "".to_string(), // Empty host should fail
                 port: 8080,
                 enable_ssl: false,
+                allowed_ips: Vec::new(),
+                denied_ips: Vec::new(),
+                max_connections: 1024,
+                keep_alive: Duration::from_secs(75),
+                request_timeout: Duration::from_secs(30),
+                shutdown_grace: Duration::from_secs(30),
             }),
             database: None,
+            proxy: None,
+            messaging: None,
+            auth: None,
             features: HashMap::new(),
         };
 
@@ -253,12 +619,37 @@ mod tests {
                 pool_size: 0, // Zero pool size should fail
                 timeout_seconds: 30,
             }),
+            proxy: None,
+            messaging: None,
+            auth: None,
             features: HashMap::new(),
         };
 
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_check_collects_every_finding() {
+        let config = AppConfig {
+            app_name: "".to_string(),
+            version: "1".to_string(),
+            environment: "invalid".to_string(),
+            server: None,
+            database: None,
+            proxy: None,
+            messaging: None,
+            auth: None,
+            features: HashMap::new(),
+        };
+
+        let paths: Vec<_> = config
+            .check()
+            .errors()
+            .map(|finding| finding.path.clone())
+            .collect();
+        assert_eq!(paths, ["app_name", "version", "environment"]);
+    }
+
     #[test]
     fn test_valid_complete_config() {
         let config = AppConfig {
@@ -269,12 +660,21 @@ mod tests {
                 host: "localhost".to_string(),
                 port: 8080,
                 enable_ssl: true,
+                allowed_ips: Vec::new(),
+                denied_ips: Vec::new(),
+                max_connections: 1024,
+                keep_alive: Duration::from_secs(75),
+                request_timeout: Duration::from_secs(30),
+                shutdown_grace: Duration::from_secs(30),
             }),
             database: Some(DatabaseConfig {
                 connection_string: "postgres://localhost/db".to_string(),
                 pool_size: 10,
                 timeout_seconds: 30,
             }),
+            proxy: None,
+            messaging: None,
+            auth: None,
             features: HashMap::from([
                 ("feature1".to_string(), true),
                 ("feature2".to_string(), false),
@@ -283,4 +683,111 @@ mod tests {
 
         assert!(config.validate().is_ok());
     }
+
+    fn server(tuning: serde_json::Value) -> ServerConfig {
+        let mut doc = serde_json::json!({ "host": "localhost", "port": 8080 });
+        doc.as_object_mut()
+            .unwrap()
+            .extend(tuning.as_object().unwrap().clone());
+        serde_json::from_value(doc).unwrap()
+    }
+
+    fn tuning_findings(server: ServerConfig) -> (Vec<String>, Vec<String>) {
+        let mut report = ValidationReport::new();
+        server.check_tuning(&mut report);
+        (
+            report.errors().map(|f| f.path.clone()).collect(),
+            report.warnings().map(|f| f.path.clone()).collect(),
+        )
+    }
+
+    #[test]
+    fn test_server_tuning_defaults_and_round_trip() {
+        let defaults = server(serde_json::json!({}));
+        assert_eq!(defaults.max_connections, 1024);
+        assert_eq!(defaults.keep_alive, Duration::from_secs(75));
+        assert_eq!(defaults.request_timeout, Duration::from_secs(30));
+        assert_eq!(defaults.shutdown_grace, Duration::from_secs(30));
+
+        let tuned = server(serde_json::json!({
+            "max_connections": 10, "keep_alive": "2m", "request_timeout": 5, "shutdown_grace": "1m 30s"
+        }));
+        let json = serde_json::to_value(&tuned).unwrap();
+        assert_eq!(json["keep_alive"], "2m");
+        assert_eq!(json["request_timeout"], "5s");
+        assert_eq!(json["shutdown_grace"], "1m 30s");
+        let back: ServerConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(back, tuned);
+    }
+
+    #[test]
+    fn test_server_tuning_rules() {
+        let cases = [
+            (serde_json::json!({}), vec![], vec![]),
+            (
+                serde_json::json!({ "max_connections": 0 }),
+                vec!["server.max_connections"],
+                vec![],
+            ),
+            (
+                serde_json::json!({ "max_connections": 100_001 }),
+                vec![],
+                vec!["server.max_connections"],
+            ),
+            (
+                serde_json::json!({ "keep_alive": "500ms", "request_timeout": "100ms" }),
+                vec!["server.keep_alive", "server.request_timeout"],
+                vec![],
+            ),
+            (
+                serde_json::json!({ "keep_alive": "2h", "request_timeout": "1h" }),
+                vec!["server.keep_alive"],
+                vec![],
+            ),
+            (
+                serde_json::json!({ "shutdown_grace": "11m" }),
+                vec!["server.shutdown_grace"],
+                vec![],
+            ),
+            (
+                serde_json::json!({ "shutdown_grace": "10m" }),
+                vec![],
+                vec![],
+            ),
+            (
+                serde_json::json!({ "keep_alive": "30s", "request_timeout": "30s" }),
+                vec![],
+                vec!["server.request_timeout"],
+            ),
+        ];
+        for (tuning, errors, warnings) in cases {
+            let (found_errors, found_warnings) = tuning_findings(server(tuning.clone()));
+            assert_eq!(found_errors, errors, "{tuning}");
+            assert_eq!(found_warnings, warnings, "{tuning}");
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip_is_lossless(config in any::<AppConfig>()) {
+            let json = serde_json::to_string(&config).unwrap();
+            let back: AppConfig = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(back, config);
+        }
+
+        #[test]
+        fn test_round_trip_keeps_the_findings(
+            config in prop_oneof![valid_app_config(), app_config()],
+        ) {
+            let json = serde_json::to_string(&config).unwrap();
+            let back: AppConfig = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(back.validate().is_ok(), config.validate().is_ok());
+            prop_assert_eq!(back.check(), config.check());
+        }
+
+        #[test]
+        fn test_valid_strategy_passes_validation(config in valid_app_config()) {
+            prop_assert!(config.validate().is_ok(), "{}", config.check());
+        }
+    }
 }
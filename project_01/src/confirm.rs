@@ -0,0 +1,287 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **A reader thread and an `mpsc` channel**: The blocking `read_line` runs
+  on its own thread and sends each line; a question awaits the next one,
+  under `tokio::time::timeout` when there is a limit, so the watch loop
+  stays async and a timeout leaves nothing to cancel
+- **`Box<dyn BufRead + Send>`**: Stdin for real, a script in tests
+- **`std::io::IsTerminal`**: Refuses to start when nobody can answer
+
+**Design decisions**:
+- Only changes ask: the initial load, and a reload identical to the
+  configuration in use, apply as usual. The prompt shows the diff the
+  loaded event would (`diff::render`, secrets redacted); `d` prints the
+  whole candidate, redacted, and asks again
+- `y`/`yes` approves; `n`/`no`, an empty line (the default) and the end of
+  the input decline; anything else asks again
+- Without an answer within `--confirm-timeout` (counted from each prompt,
+  so reading the details buys time), `--on-confirm-timeout` decides
+- One prompt for the process: the watchers of several files take turns
+  (`tokio::sync::Mutex`)
+- The prompt goes to stderr, so `--output json` keeps stdout clean
+
+******************************************************************************/
+
+use crate::cli::OnConfirmTimeout;
+use crate::config::AppConfig;
+use crate::diff::{self, Change};
+use crate::listing::ListLimit;
+use crate::redact;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, mpsc};
+
+/// How a question was settled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    Approved,
+    Declined,
+    /// Nobody answered in time; the `--on-confirm-timeout` choice applies
+    TimedOut(OnConfirmTimeout),
+}
+
+impl Answer {
+    /// Whether the configuration may apply
+    pub fn approves(self) -> bool {
+        matches!(
+            self,
+            Answer::Approved | Answer::TimedOut(OnConfirmTimeout::Apply)
+        )
+    }
+}
+
+/// Asks a human before each change applies; clones share one prompt
+#[derive(Clone)]
+pub struct Confirm {
+    prompt: Arc<Mutex<Prompt>>,
+    timeout: Option<Duration>,
+    on_timeout: OnConfirmTimeout,
+}
+
+struct Prompt {
+    /// Lines read so far; closed at the end of the input
+    lines: mpsc::UnboundedReceiver<String>,
+    output: Box<dyn Write + Send>,
+}
+
+impl std::fmt::Debug for Confirm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Confirm")
+            .field("timeout", &self.timeout)
+            .field("on_timeout", &self.on_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Confirm {
+    /// Reads answers from `input` and writes questions to `output`,
+    /// waiting as long as it takes
+    pub fn new(input: impl BufRead + Send + 'static, output: impl Write + Send + 'static) -> Self {
+        let (sender, lines) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            for line in input.lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            prompt: Arc::new(Mutex::new(Prompt {
+                lines,
+                output: Box::new(output),
+            })),
+            timeout: None,
+            on_timeout: OnConfirmTimeout::default(),
+        }
+    }
+
+    /// Asks on stderr and reads stdin, which must be a terminal
+    pub fn stdio() -> anyhow::Result<Self> {
+        if !std::io::stdin().is_terminal() {
+            anyhow::bail!("--confirm needs an interactive terminal on stdin");
+        }
+        Ok(Self::new(
+            std::io::BufReader::new(std::io::stdin()),
+            std::io::stderr(),
+        ))
+    }
+
+    /// Settles a question nobody answered within `timeout` with
+    /// `on_timeout`
+    pub fn with_timeout(mut self, timeout: Duration, on_timeout: OnConfirmTimeout) -> Self {
+        self.timeout = Some(timeout);
+        self.on_timeout = on_timeout;
+        self
+    }
+
+    /// Shows `changes` and asks whether `config`, the new configuration of
+    /// `file`, may apply
+    pub async fn ask(
+        &self,
+        file: &Path,
+        changes: &[Change],
+        config: &AppConfig,
+    ) -> std::io::Result<Answer> {
+        let mut prompt = self.prompt.lock().await;
+        let Prompt { lines, output } = &mut *prompt;
+        writeln!(
+            output,
+            "{} changed ({} field{}):",
+            file.display(),
+            changes.len(),
+            if changes.len() == 1 { "" } else { "s" }
+        )?;
+        for line in diff::render(changes, ListLimit::UNLIMITED) {
+            writeln!(output, "   {line}")?;
+        }
+        loop {
+            write!(output, "Apply this configuration? [y/N/d(etails)] ")?;
+            output.flush()?;
+            let line = match self.timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, lines.recv()).await {
+                    Ok(line) => line,
+                    Err(_) => {
+                        writeln!(
+                            output,
+                            "\nNo answer within {}, {}",
+                            humantime::format_duration(timeout),
+                            match self.on_timeout {
+                                OnConfirmTimeout::Reject => "declined",
+                                OnConfirmTimeout::Apply => "applying",
+                            }
+                        )?;
+                        return Ok(Answer::TimedOut(self.on_timeout));
+                    }
+                },
+                None => lines.recv().await,
+            };
+            let Some(line) = line else {
+                writeln!(output, "\nNo more input, declined")?;
+                return Ok(Answer::Declined);
+            };
+            match line.trim().to_ascii_lowercase().as_str() {
+                "y" | "yes" => return Ok(Answer::Approved),
+                "" | "n" | "no" => return Ok(Answer::Declined),
+                "d" | "details" => {
+                    let mut doc = serde_json::to_value(config).unwrap_or_default();
+                    redact::redact(&mut doc);
+                    let pretty = serde_json::to_string_pretty(&doc).unwrap_or_default();
+                    writeln!(output, "{pretty}")?;
+                }
+                other => writeln!(output, "Unknown answer '{other}': y, n or d")?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// An output the test can read back
+    #[derive(Clone, Default)]
+    struct Shared(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Shared {
+        fn text(&self) -> String {
+            String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+        }
+    }
+
+    fn change() -> (AppConfig, Vec<Change>) {
+        let old = AppConfig::example();
+        let mut new = old.clone();
+        new.app_name = "Renamed".to_string();
+        let changes = diff::diff(
+            &serde_json::to_value(&old).unwrap(),
+            &serde_json::to_value(&new).unwrap(),
+        );
+        (new, changes)
+    }
+
+    async fn ask(confirm: &Confirm) -> Answer {
+        let (config, changes) = change();
+        confirm
+            .ask(Path::new("app.json"), &changes, &config)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_answers() {
+        let cases = [
+            ("y\n", Answer::Approved),
+            ("YES\n", Answer::Approved),
+            ("n\n", Answer::Declined),
+            ("\n", Answer::Declined),
+            ("", Answer::Declined),
+            ("maybe\ny\n", Answer::Approved),
+        ];
+        for (input, expected) in cases {
+            let output = Shared::default();
+            let confirm = Confirm::new(Cursor::new(input), output.clone());
+            assert_eq!(ask(&confirm).await, expected, "{input:?}");
+            let text = output.text();
+            assert!(text.starts_with("app.json changed (1 field):\n"), "{text}");
+            assert!(text.contains("app_name"), "{text}");
+            assert!(text.contains("Apply this configuration? [y/N/d(etails)]"));
+        }
+        assert!(Answer::Approved.approves());
+        assert!(!Answer::Declined.approves());
+    }
+
+    #[tokio::test]
+    async fn test_details_show_the_redacted_candidate_then_ask_again() {
+        let output = Shared::default();
+        let confirm = Confirm::new(Cursor::new("d\nn\n"), output.clone());
+        assert_eq!(ask(&confirm).await, Answer::Declined);
+
+        let text = output.text();
+        assert_eq!(text.matches("Apply this configuration?").count(), 2);
+        assert!(text.contains(r#""app_name": "Renamed""#), "{text}");
+        let (config, _) = change();
+        let secret = config.database.unwrap().connection_string;
+        assert!(!text.contains(&secret), "{text}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timeout_settles_with_the_chosen_answer() {
+        for (on_timeout, approves) in [
+            (OnConfirmTimeout::Reject, false),
+            (OnConfirmTimeout::Apply, true),
+        ] {
+            // Nothing ever typed, input still open
+            let (reader, writer) = std::io::pipe().unwrap();
+            let output = Shared::default();
+            let confirm = Confirm::new(std::io::BufReader::new(reader), output.clone())
+                .with_timeout(Duration::from_secs(300), on_timeout);
+
+            let started = tokio::time::Instant::now();
+            let answer = ask(&confirm).await;
+            assert_eq!(answer, Answer::TimedOut(on_timeout));
+            assert_eq!(answer.approves(), approves);
+            assert_eq!(started.elapsed(), Duration::from_secs(300));
+            assert!(
+                output.text().contains("No answer within 5m"),
+                "{}",
+                output.text()
+            );
+            drop(writer);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`unsafe` system calls**: `fork(2)` and `_exit(2)` have no safe
+  wrapper; each call is one line with its justification. `waitpid(2)` and
+  `dup2(2)` are rustix's safe ones
+- **`std::io::pipe`**: The daemon reports its startup to the process that
+  was started, which waits on the read end; both ends are close-on-exec,
+  so hooks and supervised commands never inherit them
+- **`static Mutex<Option<_>>`**: The write end is taken by the first
+  report, so the outcome is sent once whatever calls it
+
+**Design decisions**:
+- The classic double fork: the first child calls `setsid` to leave the
+  terminal's session, then forks the daemon and exits, so the daemon is
+  not a session leader and can never acquire a controlling terminal again
+- Everything happens before the tokio runtime (and the `--sandbox`): a
+  fork only copies the calling thread, so it must be the only one
+- The daemon's stdin is `/dev/null`; stdout (the watch events) and stderr
+  go to the `--log-file`, opened for appending, which is why it is
+  mandatory. Rotating that log does not move them: they stay on the file
+  opened at startup
+- The working directory becomes `/` only once `watch` has resolved its
+  settings file, the default configuration file and every path argument
+  (`leave_working_directory`), which all depend on it
+- The process that was started waits until the daemon is ready (locks and
+  pid file in place) and exits 0, printing its pid, or with the exit code
+  and message of its startup failure: a broken command line fails in the
+  terminal, not silently in a log
+- `run` is not supported: its command would start in `/`, where a
+  relative one (`./server`) is not found
+
+******************************************************************************/
+
+use crate::style::Style;
+use std::path::Path;
+#[cfg(unix)]
+use std::sync::Mutex;
+
+/// The report pipe to the process that was started, until used
+#[cfg(unix)]
+static PARENT: Mutex<Option<std::io::PipeWriter>> = Mutex::new(None);
+
+/// Detaches the process from its terminal, with `log_file` as stdout and
+/// stderr
+///
+/// Returns in the daemon only: the process that was started waits for its
+/// report and exits, printing it in `style`. Fails before forking if the
+/// pipe cannot be created.
+#[cfg(unix)]
+pub fn detach(log_file: &Path, style: Style) -> anyhow::Result<()> {
+    use anyhow::Context;
+    let (reader, writer) = std::io::pipe().context("Cannot create the daemon's report pipe")?;
+
+    // SAFETY: called before the runtime and the sandbox, while the main
+    // thread is the only one
+    match unsafe { libc::fork() } {
+        -1 => return Err(std::io::Error::last_os_error()).context("Cannot fork"),
+        0 => {}
+        child => {
+            drop(writer);
+            // Our own child, which exits right after its fork
+            let child = rustix::process::Pid::from_raw(child);
+            let _ = rustix::process::waitpid(child, rustix::process::WaitOptions::empty());
+            std::process::exit(wait_for_report(reader, style).into());
+        }
+    }
+
+    drop(reader);
+    if let Err(e) = rustix::process::setsid() {
+        fail_early(writer, &format!("Cannot start a new session: {e}"));
+    }
+    // SAFETY: still the only thread, in the child
+    match unsafe { libc::fork() } {
+        -1 => fail_early(
+            writer,
+            &format!("Cannot fork: {}", std::io::Error::last_os_error()),
+        ),
+        0 => {}
+        // SAFETY: the first child leaves at once, without running the
+        // destructors it shares with the daemon
+        _ => unsafe { libc::_exit(0) },
+    }
+
+    if let Err(e) = redirect_stdio(log_file) {
+        fail_early(
+            writer,
+            &format!("Cannot redirect output to {}: {e}", log_file.display()),
+        );
+    }
+    *PARENT.lock().unwrap_or_else(|e| e.into_inner()) = Some(writer);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn detach(_: &Path, _: Style) -> anyhow::Result<()> {
+    anyhow::bail!("--daemonize is only supported on Unix")
+}
+
+/// Makes `/` the working directory of the daemon
+pub fn leave_working_directory() -> std::io::Result<()> {
+    std::env::set_current_dir("/")
+}
+
+/// Tells the process that was started that the daemon is up; a no-op
+/// when not daemonized or already reported
+pub fn ready() {
+    report(crate::exit::SUCCESS, &std::process::id().to_string());
+}
+
+/// Tells the process that was started that the daemon stops with `code`
+/// before being ready; a no-op when not daemonized or already reported
+pub fn failed(code: u8, message: &str) {
+    report(code, message);
+}
+
+/// One byte of exit code, then the pid or the error message
+#[cfg(unix)]
+fn report(code: u8, message: &str) {
+    use std::io::Write;
+    let writer = PARENT.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(mut writer) = writer {
+        // Nobody to tell if the parent is gone
+        let _ = writer.write_all(&[code]);
+        let _ = writer.write_all(message.as_bytes());
+    }
+}
+
+#[cfg(not(unix))]
+fn report(_: u8, _: &str) {}
+
+/// Reads the daemon's report, in the process that was started; returns the
+/// exit code to leave with
+#[cfg(unix)]
+fn wait_for_report(mut reader: std::io::PipeReader, style: Style) -> u8 {
+    use std::io::Read;
+    let mut report = Vec::new();
+    let _ = reader.read_to_end(&mut report);
+    let message = report
+        .get(1..)
+        .map(String::from_utf8_lossy)
+        .unwrap_or_default();
+    match report.first() {
+        Some(&crate::exit::SUCCESS) => {
+            let running = format_args!("config-watcher running in the background (pid {message})");
+            println!("{}", style.line(crate::style::Icon::Ok, running));
+            crate::exit::SUCCESS
+        }
+        Some(&code) => {
+            eprintln!("Error: {message}");
+            code
+        }
+        None => {
+            eprintln!("Error: the daemon exited before it was ready; see its log file");
+            crate::exit::INTERNAL
+        }
+    }
+}
+
+/// stdin from `/dev/null`, stdout and stderr to the end of `log_file`
+#[cfg(unix)]
+fn redirect_stdio(log_file: &Path) -> std::io::Result<()> {
+    use rustix::stdio::{dup2_stderr, dup2_stdin, dup2_stdout};
+    let null = std::fs::File::open("/dev/null")?;
+    let log = std::fs::File::options()
+        .create(true)
+        .append(true)
+        .open(log_file)?;
+    // The standard descriptors are replaced atomically
+    dup2_stdin(&null)?;
+    dup2_stdout(&log)?;
+    dup2_stderr(&log)?;
+    Ok(())
+}
+
+/// Reports a failure between the forks, then leaves
+#[cfg(unix)]
+fn fail_early(writer: std::io::PipeWriter, message: &str) -> ! {
+    *PARENT.lock().unwrap_or_else(|e| e.into_inner()) = Some(writer);
+    failed(crate::exit::INTERNAL, message);
+    std::process::exit(crate::exit::INTERNAL.into())
+}
@@ -0,0 +1,339 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`impl Notifier`**: Desktop notifications are one of the dispatcher's
+  notifiers, so they never run on the watch loop
+- **`tokio::process::Command`**: The notification tool runs without
+  blocking the notifiers' runtime; the dispatcher abandons it after
+  `TIMEOUT`
+- **`#[cfg(feature = "desktop-notify")]`**: The whole module is left out
+  of builds without the feature
+
+**Design decisions**:
+- No notification crate is vendored with this crate, so notifications go
+  through the desktop's own command-line tools: `notify-send` (Linux and
+  BSDs), `osascript` (macOS), PowerShell (Windows)
+- Only reloads and failed loads raise a notification: the initial load
+  happens while the user is looking at the terminal
+- Texts come from the redacted summary (application name and version) and
+  from the first finding of a failure, never from raw configuration values
+- At most one notification per kind every `--notify-interval` (10s): a
+  broken file retried every second must not flood the desktop
+- A missing tool or an unreachable daemon is a failure like any other
+  notifier's: logged once as a warning, then at debug level
+
+******************************************************************************/
+
+use crate::cli::EventKind;
+use crate::notify::{ConfigEvent, Notifier, NotifierOptions};
+use anyhow::bail;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+/// Time allowed to show one notification
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a notification is about; each kind is rate-limited on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Reload,
+    Failure,
+}
+
+/// One desktop notification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub kind: Kind,
+    pub title: String,
+    pub body: String,
+}
+
+#[cfg(target_os = "macos")]
+fn command(notification: &Notification) -> Command {
+    // Texts as arguments, so that quotes need no escaping
+    let mut command = Command::new("osascript");
+    command
+        .args(["-e", "on run argv"])
+        .args([
+            "-e",
+            "display notification (item 2 of argv) with title (item 1 of argv)",
+        ])
+        .args(["-e", "end run"])
+        .args([&notification.title, &notification.body]);
+    command
+}
+
+#[cfg(windows)]
+fn command(notification: &Notification) -> Command {
+    const SCRIPT: &str = "Add-Type -AssemblyName System.Windows.Forms; \
+        $icon = New-Object System.Windows.Forms.NotifyIcon; \
+        $icon.Icon = [System.Drawing.SystemIcons]::Information; \
+        $icon.Visible = $true; \
+        $icon.ShowBalloonTip(5000, $env:NOTIFY_TITLE, $env:NOTIFY_BODY, 'None'); \
+        Start-Sleep -Seconds 5; \
+        $icon.Dispose()";
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+        .env("NOTIFY_TITLE", &notification.title)
+        .env("NOTIFY_BODY", &notification.body);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn command(notification: &Notification) -> Command {
+    let urgency = match notification.kind {
+        Kind::Reload => "normal",
+        Kind::Failure => "critical",
+    };
+    let mut command = Command::new("notify-send");
+    command
+        .args(["--app-name=config-watcher", "--urgency", urgency, "--"])
+        .args([&notification.title, &notification.body]);
+    command
+}
+
+/// The notification for `event`, if it deserves one
+pub fn notification(event: &ConfigEvent) -> Option<Notification> {
+    let data = &event.data;
+    let file = event.file.as_deref().unwrap_or(Path::new(""));
+    match event.kind {
+        EventKind::Reload => {
+            // The summary is the redacted one
+            let field = |key: &str| data["summary"][key].as_str().unwrap_or_default();
+            let mut body = file.display().to_string();
+            let changes = data["diff"].as_array().map_or(0, Vec::len);
+            if changes > 0 {
+                let s = if changes == 1 { "" } else { "s" };
+                body.push_str(&format!(": {changes} field{s} changed"));
+            }
+            Some(Notification {
+                kind: Kind::Reload,
+                title: format!(
+                    "config-watcher: {} v{} reloaded",
+                    field("app_name"),
+                    field("version")
+                ),
+                body,
+            })
+        }
+        EventKind::Failure => {
+            let name = file
+                .file_name()
+                .unwrap_or(file.as_os_str())
+                .to_string_lossy();
+            let what = if data["initial"] == true {
+                "load"
+            } else {
+                "reload"
+            };
+            // The first finding says what to fix; the message is a fallback
+            let error = &data["error"];
+            let body = match error["findings"].get(0) {
+                Some(finding) => format!(
+                    "{}: {}",
+                    finding["path"].as_str().unwrap_or_default(),
+                    finding["message"].as_str().unwrap_or_default()
+                ),
+                None => error["message"].as_str().unwrap_or_default().to_string(),
+            };
+            Some(Notification {
+                kind: Kind::Failure,
+                title: format!("config-watcher: {name} {what} failed"),
+                body,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Raises desktop notifications for reloads and failures, at most one per
+/// kind every `interval`
+#[derive(Debug)]
+pub struct DesktopNotifier {
+    limiter: Mutex<Limiter>,
+}
+
+/// When each kind was last shown
+#[derive(Debug)]
+struct Limiter {
+    interval: Duration,
+    last: HashMap<Kind, Instant>,
+}
+
+impl Limiter {
+    /// Whether a notification of `kind` may be shown `now`; if so, it
+    /// counts as shown
+    fn allow(&mut self, kind: Kind, now: Instant) -> bool {
+        match self.last.get(&kind) {
+            Some(last) if now.duration_since(*last) < self.interval => false,
+            _ => {
+                self.last.insert(kind, now);
+                true
+            }
+        }
+    }
+}
+
+impl DesktopNotifier {
+    /// At most one notification per kind every `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            limiter: Mutex::new(Limiter {
+                interval,
+                last: HashMap::new(),
+            }),
+        }
+    }
+
+    fn accepts_at(&self, event: &ConfigEvent, now: Instant) -> bool {
+        notification(event).is_some_and(|notification| {
+            self.limiter
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .allow(notification.kind, now)
+        })
+    }
+}
+
+impl Notifier for DesktopNotifier {
+    fn name(&self) -> &str {
+        "desktop"
+    }
+
+    fn options(&self) -> NotifierOptions {
+        NotifierOptions {
+            timeout: TIMEOUT,
+            ..NotifierOptions::default()
+        }
+    }
+
+    /// Reloads and failures, unless one of the same kind was shown less
+    /// than `interval` ago
+    fn accepts(&self, event: &ConfigEvent) -> bool {
+        self.accepts_at(event, Instant::now())
+    }
+
+    async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+        let Some(notification) = notification(event) else {
+            return Ok(());
+        };
+        let mut command = command(&notification);
+        let program = command.as_std().get_program().to_string_lossy().to_string();
+        let output = match command
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output()
+            .await
+        {
+            Ok(output) => output,
+            Err(e) => bail!("cannot run {program}: {e}"),
+        };
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("{program} failed ({}): {}", output.status, stderr.trim());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::diff::Change;
+    use crate::error::ConfigError;
+    use crate::output::{Event, Summary};
+    use crate::overrides::Overrides;
+    use crate::validation::ValidationReport;
+    use std::time::SystemTime;
+
+    fn config() -> AppConfig {
+        serde_json::from_str(r#"{ "app_name": "TestApp", "version": "2.0.0" }"#).unwrap()
+    }
+
+    fn loaded(initial: bool) -> ConfigEvent {
+        let (config, overrides) = (config(), Overrides::default());
+        let changes = [Change {
+            path: "version".to_string(),
+            old: Some("1.0.0".into()),
+            new: Some("2.0.0".into()),
+        }];
+        let event = Event::Loaded {
+            file: Path::new("/etc/app/config.json"),
+            version: 2,
+            initial,
+            summary: Summary {
+                config: &config,
+                overrides: &overrides,
+            },
+            changes: (!initial).then_some(&changes[..]),
+            flags: None,
+            stand_in: None,
+        };
+        ConfigEvent::new(&event, SystemTime::now()).unwrap()
+    }
+
+    fn failed(error: anyhow::Error, initial: bool) -> ConfigEvent {
+        let event = Event::LoadFailed {
+            file: Path::new("/etc/app/config.json"),
+            error: &error,
+            initial,
+            retrying: !initial,
+        };
+        ConfigEvent::new(&event, SystemTime::now()).unwrap()
+    }
+
+    fn validation_error() -> anyhow::Error {
+        let mut report = ValidationReport::new();
+        report.error("server.port", "must be between 1 and 65535");
+        report.error("database.pool_size", "must be positive");
+        ConfigError::ValidationFailed { report }.into()
+    }
+
+    #[test]
+    fn test_reload_notification_uses_the_summary() {
+        let shown = notification(&loaded(false)).unwrap();
+        assert_eq!(shown.kind, Kind::Reload);
+        assert_eq!(shown.title, "config-watcher: TestApp v2.0.0 reloaded");
+        assert_eq!(shown.body, "/etc/app/config.json: 1 field changed");
+
+        // Nobody needs to be told about the first load
+        assert_eq!(notification(&loaded(true)), None);
+    }
+
+    #[test]
+    fn test_failure_notification_shows_the_first_finding() {
+        let shown = notification(&failed(validation_error(), false)).unwrap();
+        assert_eq!(shown.kind, Kind::Failure);
+        assert_eq!(shown.title, "config-watcher: config.json reload failed");
+        assert_eq!(shown.body, "server.port: must be between 1 and 65535");
+
+        // No findings: the error message
+        let shown = notification(&failed(anyhow::anyhow!("Permission denied"), true)).unwrap();
+        assert_eq!(shown.title, "config-watcher: config.json load failed");
+        assert_eq!(shown.body, "Permission denied");
+    }
+
+    #[test]
+    fn test_rate_limited_per_kind() {
+        let desktop = DesktopNotifier::new(Duration::from_secs(10));
+        let (reload, failure) = (loaded(false), failed(validation_error(), false));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(desktop.accepts_at(&failure, at(0)));
+        assert!(!desktop.accepts_at(&failure, at(1)));
+        // Another kind has its own limit
+        assert!(desktop.accepts_at(&reload, at(2)));
+        assert!(!desktop.accepts_at(&failure, at(9)));
+        assert!(desktop.accepts_at(&failure, at(10)));
+        // Never shown, never limited
+        assert!(!desktop.accepts_at(&loaded(true), at(10)));
+        assert!(desktop.accepts_at(&reload, at(12)));
+    }
+}
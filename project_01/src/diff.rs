@@ -0,0 +1,515 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **Recursion over `serde_json::Value`**: Objects are walked key by key
+- **`Option<Value>`**: `None` means "absent", distinct from JSON `null`
+- **`#[derive(Serialize)]`**: Changes go straight into JSON events
+
+**Design decisions**:
+- Configurations are compared in their JSON form, so the diff uses the
+  same paths as `get`/`set` (`server.port`, `features["a.b"]`)
+- Arrays and scalars are compared as a whole; only objects are descended
+- Secret values are redacted, but a changed secret is still reported; so
+  are secrets inside a section that was added or removed as a whole
+- `render` writes one line per change for text output, groups the entries
+  of a map (`features`) under one header with counts per category, and
+  describes whole sections (`section added`) rather than printing them;
+  only maps can grow unbounded, so only their categories are truncated
+- `patch` turns the same changes into an RFC 6902 JSON Patch
+  (`patch::Operation`): one change is one operation, so secrets stay
+  redacted with their path visible
+
+******************************************************************************/
+
+use crate::listing::{ListLimit, thousands};
+use crate::patch::Operation;
+use crate::path::{FieldPath, Segment};
+use crate::redact::{self, REDACTED, is_secret};
+use crate::schema;
+use serde::Serialize;
+use serde_json::Value;
+
+/// One field that differs between two configurations
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    /// Canonical field path
+    pub path: String,
+    /// Previous value, `None` when the field was added
+    pub old: Option<Value>,
+    /// New value, `None` when the field was removed
+    pub new: Option<Value>,
+}
+
+/// Lists every field that differs, in document order
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    walk(&FieldPath::root(), Some(old), Some(new), &mut changes);
+    changes
+}
+
+fn walk(path: &FieldPath, old: Option<&Value>, new: Option<&Value>, out: &mut Vec<Change>) {
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            for (key, old_value) in old {
+                let child = path.child(Segment::Key(key.clone()));
+                walk(&child, Some(old_value), new.get(key), out);
+            }
+            for (key, new_value) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+                let child = path.child(Segment::Key(key.clone()));
+                walk(&child, None, Some(new_value), out);
+            }
+        }
+        (old, new) if old != new => {
+            let hide = |value: Option<&Value>| {
+                value.map(|value| {
+                    if is_secret(path) && !value.is_null() {
+                        Value::String(REDACTED.to_string())
+                    } else {
+                        let mut value = value.clone();
+                        redact_below(path, &mut value);
+                        value
+                    }
+                })
+            };
+            out.push(Change {
+                path: path.to_string(),
+                old: hide(old),
+                new: hide(new),
+            });
+        }
+        _ => {}
+    }
+}
+
+/// The JSON Patch turning the old document into the new one
+///
+/// Applied in order, the operations replay `changes` exactly, except that
+/// redacted secrets carry the redaction marker instead of their value.
+pub fn patch(changes: &[Change]) -> Vec<Operation> {
+    changes
+        .iter()
+        .map(|change| {
+            // Only the root fails to parse (`<root>`), and its pointer is ""
+            let path = FieldPath::parse(&change.path)
+                .map(|path| path.to_pointer())
+                .unwrap_or_default();
+            match (&change.old, &change.new) {
+                (None, Some(new)) => Operation::Add {
+                    path,
+                    value: new.clone(),
+                },
+                (Some(_), Some(new)) => Operation::Replace {
+                    path,
+                    value: new.clone(),
+                },
+                (_, None) => Operation::Remove { path },
+            }
+        })
+        .collect()
+}
+
+/// Redacts the secrets inside `value`, found at `path`
+fn redact_below(path: &FieldPath, value: &mut Value) {
+    if !value.is_object() {
+        return;
+    }
+    for secret in redact::secret_paths() {
+        let Some(rest) = secret.segments().strip_prefix(path.segments()) else {
+            continue;
+        };
+        let relative = rest.iter().fold(FieldPath::root(), |path, segment| {
+            path.child(segment.clone())
+        });
+        if matches!(relative.resolve(value), Ok(found) if !found.is_null()) {
+            let _ = relative.set(value, Value::String(REDACTED.to_string()));
+        }
+    }
+}
+
+/// A map and its consecutive entries, or a lone change (`None`)
+type Group<'a> = (Option<String>, Vec<(String, &'a Change)>);
+
+/// Text lines describing `changes`, in order
+///
+/// Entries of a map are grouped under a `map: 2 added, 1 changed` header,
+/// then listed by category (added, removed, changed) in key order, each
+/// category cut at `limit`.
+pub fn render(changes: &[Change], limit: ListLimit) -> Vec<String> {
+    // Consecutive entries of the same map form one group
+    let mut groups: Vec<Group> = Vec::new();
+    for change in changes {
+        let (map, label) = match map_entry(&change.path) {
+            Some((map, key)) => (Some(map), key),
+            None => (None, change.path.clone()),
+        };
+        match groups.last_mut() {
+            Some((last, entries)) if map.is_some() && *last == map => {
+                entries.push((label, change));
+            }
+            _ => groups.push((map, vec![(label, change)])),
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (map, mut entries) in groups {
+        match map {
+            Some(map) => {
+                lines.push(format!("{map}: {}", tally(&entries)));
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                for category in CATEGORIES {
+                    let listed: Vec<_> = entries
+                        .iter()
+                        .filter(|(_, change)| (category.is)(change))
+                        .collect();
+                    let (shown, more) = limit.split(&listed);
+                    lines.extend(
+                        shown
+                            .iter()
+                            .map(|(label, change)| format!("  {}", describe(label, change))),
+                    );
+                    lines.extend(more.map(|more| format!("  {more}")));
+                }
+            }
+            None => lines.extend(
+                entries
+                    .into_iter()
+                    .map(|(label, change)| describe(&label, change)),
+            ),
+        }
+    }
+    lines
+}
+
+/// A kind of change within a map
+struct Category {
+    name: &'static str,
+    is: fn(&Change) -> bool,
+}
+
+/// In the order they are counted and listed
+const CATEGORIES: [Category; 3] = [
+    Category {
+        name: "added",
+        is: |c| c.old.is_none(),
+    },
+    Category {
+        name: "removed",
+        is: |c| c.new.is_none(),
+    },
+    Category {
+        name: "changed",
+        is: |c| c.old.is_some() && c.new.is_some(),
+    },
+];
+
+/// The map holding `path` and the entry's key, if `path` is a map entry
+fn map_entry(path: &str) -> Option<(String, String)> {
+    let path = FieldPath::parse(path).ok()?;
+    let info = schema::lookup(&path)?;
+    if !info.path.ends_with(".*") {
+        return None;
+    }
+    let (last, parent) = path.segments().split_last()?;
+    let map = parent.iter().fold(FieldPath::root(), |path, segment| {
+        path.child(segment.clone())
+    });
+    let key = FieldPath::root().child(last.clone()).to_string();
+    Some((map.to_string(), key))
+}
+
+/// `2 added, 1 removed, 1 changed`, leaving out zero counts
+fn tally(entries: &[(String, &Change)]) -> String {
+    CATEGORIES
+        .iter()
+        .map(|category| {
+            let count = entries.iter().filter(|(_, c)| (category.is)(c)).count();
+            (count, category.name)
+        })
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, what)| format!("{} {what}", thousands(n)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// One change as `label: old -> new`, `label: added (value)`...
+fn describe(label: &str, change: &Change) -> String {
+    let show = |value: &Value| value.to_string();
+    let redacted = |value: &Value| value.as_str() == Some(REDACTED);
+    match (&change.old, &change.new) {
+        (None, Some(new)) if new.is_object() => format!("{label}: section added"),
+        (None, Some(new)) => format!("{label}: added ({})", show(new)),
+        (Some(old), None) if old.is_object() => format!("{label}: section removed"),
+        (Some(old), None) => format!("{label}: removed (was {})", show(old)),
+        (Some(old), Some(new)) if redacted(old) && redacted(new) => {
+            format!("{label}: changed (secret)")
+        }
+        (Some(old), Some(new)) => format!("{label}: {} -> {}", show(old), show(new)),
+        (None, None) => format!("{label}: changed"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_reports_changed_added_and_removed_fields() {
+        let old = json!({
+            "app_name": "A",
+            "server": { "host": "h", "port": 80 },
+            "features": { "a.b": true, "gone": false }
+        });
+        let new = json!({
+            "app_name": "A",
+            "server": { "host": "h", "port": 90 },
+            "features": { "a.b": false },
+            "environment": "staging"
+        });
+
+        let changes: Vec<_> = diff(&old, &new)
+            .into_iter()
+            .map(|c| (c.path, c.old, c.new))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("server.port".to_string(), Some(json!(80)), Some(json!(90))),
+                (
+                    r#"features["a.b"]"#.to_string(),
+                    Some(json!(true)),
+                    Some(json!(false))
+                ),
+                ("features.gone".to_string(), Some(json!(false)), None),
+                ("environment".to_string(), None, Some(json!("staging"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changed_secret_is_reported_but_redacted() {
+        let old = json!({ "database": { "connection_string": "postgres://a" } });
+        let new = json!({ "database": { "connection_string": "postgres://b" } });
+
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "database.connection_string");
+        assert_eq!(changes[0].old, Some(json!(REDACTED)));
+        assert_eq!(changes[0].new, Some(json!(REDACTED)));
+    }
+
+    #[test]
+    fn test_removed_section_hides_its_secrets() {
+        let old =
+            json!({ "database": { "connection_string": "postgres://u:p@h/db", "pool_size": 5 } });
+        let changes = diff(&old, &json!({}));
+        assert_eq!(
+            changes[0].old,
+            Some(json!({ "connection_string": REDACTED, "pool_size": 5 }))
+        );
+    }
+
+    #[test]
+    fn test_patch_operations_and_pointers() {
+        let old = json!({
+            "server": { "port": 80 },
+            "features": { "a/b": true, "~x": false },
+            "database": { "connection_string": "postgres://a" }
+        });
+        let new = json!({
+            "server": { "port": 90 },
+            "features": { "a/b": true, "c.d": true },
+            "database": { "connection_string": "postgres://b" }
+        });
+
+        let ops = serde_json::to_value(patch(&diff(&old, &new))).unwrap();
+        assert_eq!(
+            ops,
+            json!([
+                { "op": "replace", "path": "/server/port", "value": 90 },
+                { "op": "remove", "path": "/features/~0x" },
+                { "op": "add", "path": "/features/c.d", "value": true },
+                { "op": "replace", "path": "/database/connection_string", "value": REDACTED },
+            ])
+        );
+    }
+
+    /// A random document; the keys exercise pointer escaping but never
+    /// match a schema field, so nothing is redacted
+    fn document(rng: &mut fastrand::Rng, depth: u32) -> Value {
+        const KEYS: [&str; 8] = ["x", "y", "a/b", "~0", "m~n/o", "p.q", "", "r s"];
+        match rng.u8(..if depth == 0 { 5 } else { 7 }) {
+            0 => Value::Null,
+            1 => json!(rng.bool()),
+            2 => json!(rng.i32(-5..5)),
+            3 => json!(KEYS[rng.usize(..KEYS.len())]),
+            4 => json!([rng.u8(..3), rng.u8(..3)]),
+            _ => {
+                let mut map = serde_json::Map::new();
+                for _ in 0..rng.usize(..5) {
+                    let key = KEYS[rng.usize(..KEYS.len())].to_string();
+                    map.insert(key, document(rng, depth - 1));
+                }
+                Value::Object(map)
+            }
+        }
+    }
+
+    /// `doc` with some members removed, replaced or added
+    fn mutate(rng: &mut fastrand::Rng, doc: &Value) -> Value {
+        let Value::Object(map) = doc else {
+            return if rng.u8(..3) == 0 {
+                document(rng, 2)
+            } else {
+                doc.clone()
+            };
+        };
+        let mut out = serde_json::Map::new();
+        for (key, value) in map {
+            match rng.u8(..4) {
+                0 => {}
+                1 => {
+                    out.insert(key.clone(), mutate(rng, value));
+                }
+                _ => {
+                    out.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        if rng.bool() {
+            out.insert(format!("new/{}", rng.u8(..3)), document(rng, 2));
+        }
+        Value::Object(out)
+    }
+
+    #[test]
+    fn test_patch_replays_random_changes() {
+        for seed in 0..500 {
+            let mut rng = fastrand::Rng::with_seed(seed);
+            let old = json!({ "doc": document(&mut rng, 4) });
+            let new = mutate(&mut rng, &old);
+
+            let mut replayed = old.clone();
+            crate::patch::apply(&mut replayed, &patch(&diff(&old, &new))).unwrap();
+            assert_eq!(replayed, new, "seed {seed}");
+        }
+    }
+
+    fn rendered(old: Value, new: Value, limit: ListLimit) -> String {
+        render(&diff(&old, &new), limit).join("\n")
+    }
+
+    #[test]
+    fn test_render_scalar_changes() {
+        let old = json!({
+            "version": "1.0.0",
+            "server": { "host": "h", "port": 8080 },
+            "database": { "connection_string": "postgres://a", "pool_size": 5 }
+        });
+        let new = json!({
+            "version": "1.1.0",
+            "server": { "host": "h", "port": 9090 },
+            "database": { "connection_string": "postgres://b", "pool_size": 5 },
+            "environment": "staging"
+        });
+        assert_eq!(
+            rendered(old, new, ListLimit::UNLIMITED),
+            "\
+version: \"1.0.0\" -> \"1.1.0\"
+server.port: 8080 -> 9090
+database.connection_string: changed (secret)
+environment: added (\"staging\")"
+        );
+    }
+
+    #[test]
+    fn test_render_section_added_and_removed() {
+        let old = json!({ "database": { "connection_string": "postgres://a" } });
+        let new = json!({ "server": { "host": "h", "port": 1 } });
+        assert_eq!(
+            rendered(old, new, ListLimit::UNLIMITED),
+            "database: section removed\nserver: section added"
+        );
+    }
+
+    #[test]
+    fn test_render_groups_map_churn() {
+        let old = json!({
+            "features": { "cache": true, "beta": false, "old_ui": true },
+            "version": "1.0.0"
+        });
+        let new = json!({
+            "features": { "cache": false, "beta": false, "dark_mode": true, "a.b": true },
+            "version": "1.0.0"
+        });
+        assert_eq!(
+            rendered(old, new, ListLimit::UNLIMITED),
+            "\
+features: 2 added, 1 removed, 1 changed
+  [\"a.b\"]: added (true)
+  dark_mode: added (true)
+  old_ui: removed (was true)
+  cache: true -> false"
+        );
+    }
+
+    #[test]
+    fn test_render_truncates_each_category() {
+        let flags = |count: usize, value: bool| {
+            let map: serde_json::Map<String, Value> = (0..count)
+                .map(|i| (format!("f{i:04}"), json!(value)))
+                .collect();
+            json!({ "features": map })
+        };
+        let limit = ListLimit::default();
+
+        // 8,000 flags flipped, 10 more added
+        let text = rendered(flags(8000, false), flags(8010, true), limit);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "features: 10 added, 8,000 changed");
+        assert_eq!(lines[1], "  f8000: added (true)");
+        assert_eq!(lines[11], "  f0000: false -> true");
+        assert_eq!(lines[60], "  f0049: false -> true");
+        assert_eq!(lines[61], "  ... and 7,950 more (use -v to list)");
+        assert_eq!(lines.len(), 62);
+
+        // Exactly at the threshold: nothing is cut
+        let text = rendered(flags(50, false), flags(50, true), limit);
+        assert_eq!(text.lines().count(), 51);
+        assert!(!text.contains("more"));
+
+        let all = rendered(flags(8000, false), flags(8010, true), ListLimit::UNLIMITED);
+        assert_eq!(all.lines().count(), 1 + 8010);
+    }
+
+    #[test]
+    fn test_identical_documents_have_no_changes() {
+        let doc = json!({ "app_name": "A", "features": {} });
+        assert!(diff(&doc, &doc).is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn test_a_config_has_no_changes_with_itself(config in any::<AppConfig>()) {
+            let doc = serde_json::to_value(&config).unwrap();
+            prop_assert!(diff(&doc, &doc).is_empty());
+        }
+
+        #[test]
+        fn test_diff_then_patch_rebuilds_the_target(
+            old in any::<AppConfig>(),
+            new in any::<AppConfig>(),
+        ) {
+            let old = serde_json::to_value(&old).unwrap();
+            let mut target = serde_json::to_value(&new).unwrap();
+            let mut rebuilt = old.clone();
+            crate::patch::apply(&mut rebuilt, &patch(&diff(&old, &target))).unwrap();
+            // A changed secret travels redacted: compare with secrets hidden
+            redact::redact(&mut rebuilt);
+            redact::redact(&mut target);
+            prop_assert_eq!(rebuilt, target);
+        }
+    }
+}
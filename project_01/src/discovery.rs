@@ -0,0 +1,161 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`impl Fn(&Path) -> bool`**: The file system is injected as a closure, so
+  tests run against an in-memory set of paths
+- **`Path::join`**: Builds candidate paths portably
+
+**Design decisions**:
+- Used only when `--file` is omitted; an explicit path is never second-guessed
+- Search order: `./config.<ext>`, `./config/config.<ext>`, then
+  `$XDG_CONFIG_HOME/config-watcher/config.<ext>` (`~/.config` when unset)
+- The first directory holding a candidate wins; two candidates in that
+  directory (e.g. `.json` and `.toml`) is an error rather than a guess
+- When nothing is found, the error lists every path that was tried
+
+******************************************************************************/
+
+use crate::config::SUPPORTED_EXTENSIONS;
+use crate::error::{ConfigError, Result};
+use std::path::{Path, PathBuf};
+
+/// Directory name used under the XDG config directory
+pub const APP_DIR: &str = "config-watcher";
+
+/// File stem looked for in every directory
+const STEM: &str = "config";
+
+/// Directories searched, in order
+///
+/// `root` is the working directory; `xdg_config_home` the user's config
+/// directory, if known.
+pub fn search_dirs(root: &Path, xdg_config_home: Option<&Path>) -> Vec<PathBuf> {
+    let mut dirs = vec![root.to_path_buf(), root.join("config")];
+    if let Some(xdg) = xdg_config_home {
+        dirs.push(xdg.join(APP_DIR));
+    }
+    dirs
+}
+
+/// `$XDG_CONFIG_HOME`, or `~/.config` when unset or empty
+pub fn xdg_config_home() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+}
+
+/// Finds the configuration file to use when `--file` is omitted
+///
+/// `extensions` are the supported file extensions, normally
+/// [`SUPPORTED_EXTENSIONS`].
+pub fn discover(
+    root: &Path,
+    xdg_config_home: Option<&Path>,
+    extensions: &[&str],
+    is_file: impl Fn(&Path) -> bool,
+) -> Result<PathBuf> {
+    let mut tried = Vec::new();
+    for dir in search_dirs(root, xdg_config_home) {
+        let candidates: Vec<PathBuf> = extensions
+            .iter()
+            .map(|ext| dir.join(format!("{STEM}.{ext}")))
+            .collect();
+        let mut found: Vec<PathBuf> = candidates
+            .iter()
+            .filter(|path| is_file(path))
+            .cloned()
+            .collect();
+        match found.len() {
+            0 => tried.extend(candidates),
+            1 => return Ok(found.swap_remove(0)),
+            _ => return Err(ConfigError::AmbiguousConfig { candidates: found }),
+        }
+    }
+    Err(ConfigError::NoConfigFound { tried })
+}
+
+/// `discover` against the real file system and environment
+pub fn discover_default() -> Result<PathBuf> {
+    discover(
+        Path::new("."),
+        xdg_config_home().as_deref(),
+        SUPPORTED_EXTENSIONS,
+        Path::is_file,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn fs(files: &[&str]) -> impl Fn(&Path) -> bool {
+        let files: HashSet<PathBuf> = files.iter().map(PathBuf::from).collect();
+        move |path| files.contains(path)
+    }
+
+    #[test]
+    fn test_search_order() {
+        let xdg = Path::new("/home/u/.config");
+        let cases = [
+            (
+                vec!["/w/config.json", "/w/config/config.json"],
+                "/w/config.json",
+            ),
+            (
+                vec![
+                    "/w/config/config.json",
+                    "/home/u/.config/config-watcher/config.json",
+                ],
+                "/w/config/config.json",
+            ),
+            (
+                vec!["/home/u/.config/config-watcher/config.json"],
+                "/home/u/.config/config-watcher/config.json",
+            ),
+        ];
+        for (files, expected) in cases {
+            let found = discover(Path::new("/w"), Some(xdg), &["json"], fs(&files)).unwrap();
+            assert_eq!(found, Path::new(expected), "{files:?}");
+        }
+    }
+
+    #[test]
+    fn test_not_found_lists_every_path() {
+        let err = discover(Path::new("/w"), Some(Path::new("/x")), &["json"], fs(&[])).unwrap_err();
+        let message = err.to_string();
+        for path in [
+            "/w/config.json",
+            "/w/config/config.json",
+            "/x/config-watcher/config.json",
+        ] {
+            assert!(message.contains(path), "{message}");
+        }
+
+        // Without an XDG directory only the local candidates are tried
+        let err = discover(Path::new("/w"), None, &["json"], fs(&[])).unwrap_err();
+        assert!(!err.to_string().contains("config-watcher/"), "{err}");
+    }
+
+    #[test]
+    fn test_ambiguity_is_an_error() {
+        let extensions = ["json", "toml"];
+        let files = ["/w/config/config.json", "/w/config/config.toml"];
+
+        let err = discover(Path::new("/w"), None, &extensions, fs(&files)).unwrap_err();
+        assert!(matches!(err, ConfigError::AmbiguousConfig { .. }));
+        let message = err.to_string();
+        assert!(message.contains("/w/config/config.toml"), "{message}");
+        assert!(message.contains("--file"), "{message}");
+
+        // A single match in an earlier directory is not ambiguous
+        let files = [
+            "/w/config.toml",
+            "/w/config/config.json",
+            "/w/config/config.toml",
+        ];
+        let found = discover(Path::new("/w"), None, &extensions, fs(&files)).unwrap();
+        assert_eq!(found, Path::new("/w/config.toml"));
+    }
+}
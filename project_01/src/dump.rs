@@ -0,0 +1,109 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`Arc<Mutex<Option<Instant>>>`**: Every clone of `DumpRequests` (the
+  SIGUSR1 task, the HTTP server) shares the time of the last dump, so the
+  rate limit holds whoever asks
+- **`Arc<[Effective]>`**: The settings are fixed for the whole watch,
+  rendered once and shared by every dump
+
+**Design decisions**:
+- A dump is an event (`Event::Dump`), printed by the `Emitter` like any
+  other: text or JSON following --output, to the log file of a daemon, to
+  the journal with --log-target journald
+- It is built from the `StatusBoard`, the in-memory twin of the status
+  file: health, version, last error and counters of every file, with its
+  configuration redacted. The watch loops are never asked anything, so a
+  dump does not wait for a busy one and does not delay it either
+- At most one dump per `MIN_INTERVAL`: a flood of SIGUSR1 prints one dump,
+  the rest are dropped with a debug line
+- SIGUSR1 on Unix (see `main`); `POST /dump` with --http-addr everywhere,
+  which is how a Windows watcher is asked
+
+******************************************************************************/
+
+use crate::output::{Emitter, Event};
+use crate::settings::Effective;
+use crate::status::{StatusBoard, StatusRecord};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Shortest time between two dumps
+pub const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What a dump shows
+#[derive(Debug, Clone)]
+pub struct Dump {
+    /// Health, version, last error and counters of every file
+    pub status: StatusRecord,
+    /// The redacted JSON of each file's configuration, for the files that
+    /// have one
+    pub configs: Vec<(PathBuf, Arc<str>)>,
+    /// The effective watcher settings
+    pub settings: Arc<[Effective]>,
+}
+
+/// Prints a status dump on request (SIGUSR1, `POST /dump`)
+#[derive(Debug, Clone)]
+pub struct DumpRequests {
+    emitter: Emitter,
+    board: StatusBoard,
+    settings: Arc<[Effective]>,
+    last: Arc<Mutex<Option<Instant>>>,
+}
+
+impl DumpRequests {
+    /// Dumps of what `board` holds, printed through `emitter`
+    pub fn new(emitter: Emitter, board: StatusBoard, settings: Vec<Effective>) -> Self {
+        Self {
+            emitter,
+            board,
+            settings: settings.into(),
+            last: Arc::default(),
+        }
+    }
+
+    /// Prints a dump, unless one was printed less than `MIN_INTERVAL` ago;
+    /// returns whether it did
+    pub fn request(&self, by: &str) -> bool {
+        {
+            let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            if last.is_some_and(|last| now.duration_since(last) < MIN_INTERVAL) {
+                tracing::debug!(by, "status dump skipped, one was just printed");
+                return false;
+            }
+            *last = Some(now);
+        }
+        let dump = self.dump();
+        self.emitter.emit(&Event::Dump { by, dump: &dump });
+        true
+    }
+
+    /// What is dumped, as of now
+    pub fn dump(&self) -> Dump {
+        Dump {
+            status: self.board.record(),
+            configs: self.board.configs(),
+            settings: Arc::clone(&self.settings),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::OutputFormat;
+
+    #[test]
+    fn test_requests_are_rate_limited() {
+        let board = StatusBoard::new(Duration::from_secs(1));
+        let dumps = DumpRequests::new(Emitter::new(OutputFormat::Json), board, Vec::new());
+        assert!(dumps.request("SIGUSR1"));
+        // Shared by the clones
+        assert!(!dumps.clone().request("SIGUSR1"));
+        *dumps.last.lock().unwrap() = Some(Instant::now() - MIN_INTERVAL);
+        assert!(dumps.request("POST /dump"));
+    }
+}
@@ -0,0 +1,65 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`#[serde(with = "...")]`**: Points a field at custom (de)serialize functions
+- **`#[serde(untagged)]`**: Accepts either of two JSON shapes for one value
+
+**Design decisions**:
+- Durations are written as human-readable strings (`"30s"`, `"1m 15s"`)
+  parsed by `humantime`, the same syntax as `--max-duration`
+- A bare number is read as seconds, for configs written by other tools
+- Serialization always produces the string form, so `set` and the docs
+  show one canonical spelling
+
+******************************************************************************/
+
+use serde::{Deserialize, Deserializer, Serializer};
+use std::time::Duration;
+
+/// Writes `duration` as a humantime string
+pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&humantime::format_duration(*duration).to_string())
+}
+
+/// Reads a humantime string, or a number of seconds
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Seconds(u64),
+        Text(String),
+    }
+
+    match Raw::deserialize(deserializer)? {
+        Raw::Seconds(secs) => Ok(Duration::from_secs(secs)),
+        Raw::Text(text) => humantime::parse_duration(&text)
+            .map_err(|e| serde::de::Error::custom(format!("invalid duration '{text}': {e}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "super")] Duration);
+
+    #[test]
+    fn test_strings_and_seconds() {
+        let parse = |json: &str| serde_json::from_str::<Wrapper>(json).map(|w| w.0);
+        assert_eq!(parse(r#""75s""#).unwrap(), Duration::from_secs(75));
+        assert_eq!(parse(r#""1m 15s""#).unwrap(), Duration::from_secs(75));
+        assert_eq!(parse("30").unwrap(), Duration::from_secs(30));
+        assert!(parse(r#""soon""#).is_err());
+        assert!(parse("-1").is_err());
+    }
+
+    #[test]
+    fn test_round_trip_uses_the_string_form() {
+        let json = serde_json::to_string(&Wrapper(Duration::from_secs(75))).unwrap();
+        assert_eq!(json, r#""1m 15s""#);
+        let back: Wrapper = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.0, Duration::from_secs(75));
+    }
+}
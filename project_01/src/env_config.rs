@@ -0,0 +1,235 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`impl IntoIterator<Item = (String, String)>`**: The environment is passed
+  in, so tests never touch the real process environment
+- **`serde_json::Value` as an intermediate form**: Variables are written into
+  an empty document which then goes through the usual deserialization
+
+**Design decisions**:
+- `--from-env [PREFIX]` builds the whole configuration from variables named
+  like the overrides: `CW__APP_NAME`, `CW__SERVER__PORT`,
+  `CW__FEATURES__DARK_MODE` (default prefix `CW`); each variable is one
+  `Override`, so typing and unknown-field errors are shared and name the
+  variable
+- Serde defaults fill in everything unset; missing required fields are
+  listed by the variable names that would provide them, not by serde's
+  `missing field` message
+- A required field of an optional section (e.g. `server.host`) is only
+  expected once some variable of that section is set
+- Missing or mistyped variables are usage errors (exit code 2), like a bad
+  `--override`
+
+******************************************************************************/
+
+use crate::exit;
+use crate::overrides::Overrides;
+use crate::path::FieldPath;
+use crate::schema::FIELDS;
+use serde_json::Value;
+
+/// Prefix used by `--from-env` without a value
+pub const DEFAULT_PREFIX: &str = "CW";
+
+/// Name of the variable holding `path`, e.g. `CW__SERVER__PORT`
+pub fn variable(prefix: &str, path: &str) -> String {
+    let mut name = prefix.to_string();
+    for segment in path.split('.') {
+        name.push_str("__");
+        name.push_str(&segment.to_ascii_uppercase());
+    }
+    name
+}
+
+/// The variables belonging to `prefix`, sorted by name
+///
+/// Two equal snapshots mean the configuration has not changed.
+pub fn snapshot(
+    prefix: &str,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Vec<(String, String)> {
+    let start = format!("{prefix}__");
+    let mut vars: Vec<_> = vars
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(&start))
+        .collect();
+    vars.sort();
+    vars
+}
+
+/// The variables `--from-env PREFIX` reads `doc` back from, sorted by name
+///
+/// One per scalar (strings as they are), arrays as JSON; nulls are left
+/// out.
+pub fn to_env_vars(prefix: &str, doc: &Value) -> Vec<(String, String)> {
+    fn flatten(name: String, value: &Value, vars: &mut Vec<(String, String)>) {
+        match value {
+            Value::Object(fields) => {
+                for (key, value) in fields {
+                    flatten(format!("{name}__{}", key.to_ascii_uppercase()), value, vars);
+                }
+            }
+            Value::Null => {}
+            Value::String(text) => vars.push((name, text.clone())),
+            value => vars.push((name, value.to_string())),
+        }
+    }
+    let mut vars = Vec::new();
+    flatten(prefix.to_string(), doc, &mut vars);
+    vars.sort();
+    vars
+}
+
+/// Builds the raw configuration document from the variables of `prefix`
+pub fn document(
+    prefix: &str,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> anyhow::Result<Value> {
+    let overrides =
+        Overrides::from_prefixed_env(&format!("{prefix}__"), vars).map_err(exit::usage)?;
+    let mut doc = Value::Object(Default::default());
+    overrides.apply(&mut doc)?;
+    Ok(doc)
+}
+
+/// Fails with the names of the variables that required fields are missing
+///
+/// Run on the final document, after the other overrides were applied.
+pub fn check_required(prefix: &str, doc: &Value) -> anyhow::Result<()> {
+    let exists = |path: &str| {
+        FieldPath::parse(path)
+            .ok()
+            .and_then(|path| path.resolve(doc).ok())
+            .is_some_and(|value| !value.is_null())
+    };
+    let missing: Vec<String> = FIELDS
+        .iter()
+        .filter(|info| info.required && !info.path.contains('*'))
+        .filter(|info| match info.path.rsplit_once('.') {
+            Some((section, _)) => exists(section),
+            None => true,
+        })
+        .filter(|info| !exists(info.path))
+        .map(|info| variable(prefix, info.path))
+        .collect();
+    if !missing.is_empty() {
+        return Err(exit::usage(anyhow::anyhow!(
+            "Missing required environment variables: {}",
+            missing.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use serde_json::json;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn load(pairs: &[(&str, &str)]) -> anyhow::Result<AppConfig> {
+        let doc = document("APP", vars(pairs))?;
+        check_required("APP", &doc)?;
+        Ok(serde_json::from_value(doc)?)
+    }
+
+    #[test]
+    fn test_full_environment() {
+        let config = load(&[
+            ("APP__APP_NAME", "FromEnv"),
+            ("APP__VERSION", "2.0.0"),
+            ("APP__ENVIRONMENT", "staging"),
+            ("APP__SERVER__HOST", "0.0.0.0"),
+            ("APP__SERVER__PORT", "8080"),
+            ("APP__FEATURES__DARK_MODE", "true"),
+            ("APP__FEATURES__BETA", "false"),
+            ("HOME", "/root"),
+        ])
+        .unwrap();
+
+        assert_eq!(config.app_name, "FromEnv");
+        assert_eq!(config.environment, "staging");
+        let server = config.server.unwrap();
+        assert_eq!((server.host.as_str(), server.port), ("0.0.0.0", 8080));
+        // Serde defaults for anything unset
+        assert_eq!(server.max_connections, 1024);
+        assert_eq!(
+            serde_json::to_value(&config.features).unwrap(),
+            json!({ "beta": false, "dark_mode": true })
+        );
+    }
+
+    #[test]
+    fn test_missing_required_fields_name_the_variables() {
+        let err = load(&[("APP__SERVER__PORT", "8080")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Missing required environment variables: \
+             APP__APP_NAME, APP__VERSION, APP__SERVER__HOST"
+        );
+
+        // Sections that are not mentioned at all are not required
+        let config = load(&[("APP__APP_NAME", "A"), ("APP__VERSION", "1.0.0")]).unwrap();
+        assert!(config.server.is_none());
+    }
+
+    #[test]
+    fn test_type_errors_name_the_variable() {
+        let err = load(&[
+            ("APP__APP_NAME", "A"),
+            ("APP__VERSION", "1.0.0"),
+            ("APP__SERVER__PORT", "eighty"),
+        ])
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("APP__SERVER__PORT"), "{err:#}");
+
+        let err = load(&[("APP__SERVR__PORT", "80")]).unwrap_err();
+        assert!(format!("{err:#}").contains("APP__SERVR__PORT"), "{err:#}");
+    }
+
+    #[test]
+    fn test_to_env_vars_reads_back() {
+        let doc = serde_json::to_value(AppConfig::example()).unwrap();
+        let vars = to_env_vars("APP", &doc);
+        let names: Vec<&str> = vars.iter().map(|(name, _)| name.as_str()).collect();
+        assert!(names.is_sorted());
+        assert!(names.contains(&"APP__SERVER__ALLOWED_IPS"));
+        assert!(vars.contains(&("APP__DATABASE__POOL_SIZE".to_string(), "15".to_string())));
+
+        // Arrays are written as JSON, which --from-env reads as strings
+        let scalars: Vec<_> = vars
+            .into_iter()
+            .filter(|(_, v)| !v.starts_with('['))
+            .collect();
+        let config: AppConfig = serde_json::from_value(document("APP", scalars).unwrap()).unwrap();
+        let mut expected = AppConfig::example();
+        let server = expected.server.as_mut().unwrap();
+        server.allowed_ips.clear();
+        server.denied_ips.clear();
+        expected.proxy.as_mut().unwrap().no_proxy.clear();
+        expected.messaging.as_mut().unwrap().brokers.clear();
+        let auth = expected.auth.as_mut().unwrap();
+        auth.jwt.as_mut().unwrap().audience.clear();
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn test_snapshot_and_names() {
+        let snapshot = snapshot(
+            "APP",
+            vars(&[("APP__B", "2"), ("APPLE", "x"), ("APP__A", "1")]),
+        );
+        assert_eq!(snapshot, vars(&[("APP__A", "1"), ("APP__B", "2")]));
+        assert_eq!(
+            variable("CW", "server.keep_alive"),
+            "CW__SERVER__KEEP_ALIVE"
+        );
+    }
+}
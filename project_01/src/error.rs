@@ -10,9 +10,11 @@
 - Structured errors with context (file paths, reasons)
 - Separate error variants for different failure modes
 - Using `#[from]` for JSON errors since they're common
+- Every variant has a stable `code()` for machine-readable reports
 
 ******************************************************************************/
 
+use crate::validation::ValidationReport;
 use std::path::PathBuf;
 use thiserror::Error;
 
@@ -20,16 +22,25 @@ use thiserror::Error;
 ///
 /// Using thiserror reduces boilerplate by automatically implementing
 /// std::error::Error, Display, and From conversions
+///
+/// New variants are added over time, hence `#[non_exhaustive]`: match on
+/// [`ConfigError::code`] or keep a `_` arm.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum ConfigError {
     /// Occurs when the configuration file cannot be found
     #[error("Configuration file not found: {path}")]
-    FileNotFound { path: PathBuf },
+    FileNotFound {
+        /// The missing file
+        path: PathBuf,
+    },
 
     /// Occurs when file metadata cannot be read (permissions, etc.)
     #[error("Cannot access file metadata: {path}")]
     MetadataError {
+        /// The file
         path: PathBuf,
+        /// What the system reported
         #[source]
         source: std::io::Error,
     },
@@ -37,21 +48,303 @@ pub enum ConfigError {
     /// Occurs when JSON parsing fails
     #[error("Invalid JSON in configuration file")]
     InvalidJson {
+        /// The parser's error, with its line and column
         #[from]
         source: serde_json::Error,
     },
 
     /// Occurs when the config structure doesn't match expected schema
-    #[error("Configuration validation failed: {reason}")]
-    ValidationFailed { reason: String },
+    ///
+    /// The report holds every finding, not just the first one
+    #[error("Configuration validation failed: {report}")]
+    ValidationFailed {
+        /// Every finding, errors and warnings
+        report: ValidationReport,
+    },
 
     /// Occurs when file read operation fails
     #[error("Failed to read configuration file: {path}")]
     ReadError {
+        /// The file
+        path: PathBuf,
+        /// What the system reported
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// Occurs when writing the configuration file back fails
+    #[error("Failed to write configuration file: {path}")]
+    WriteError {
+        /// The file
         path: PathBuf,
+        /// What the system reported
         #[source]
         source: std::io::Error,
     },
+
+    /// Occurs when an external JSON Schema cannot be compiled
+    #[error("Invalid JSON Schema {path}: {reason}")]
+    InvalidSchema {
+        /// The schema file
+        path: PathBuf,
+        /// Why it does not compile
+        reason: String,
+    },
+
+    /// Occurs when a dotted field path cannot be parsed
+    #[error("Invalid field path '{path}': {reason}")]
+    InvalidPath {
+        /// The path as given
+        path: String,
+        /// What is wrong with it
+        reason: String,
+    },
+
+    /// Occurs when a field path does not exist in the document
+    #[error("Field '{path}' not found (deepest existing ancestor: {ancestor})")]
+    PathNotFound {
+        /// The path as given
+        path: String,
+        /// Its longest prefix that does exist
+        ancestor: String,
+    },
+
+    /// Occurs when a reload fails while fail-fast is enabled
+    #[error(
+        "Reload of {path} failed with --fail-fast (last good configuration loaded at {last_good})"
+    )]
+    ReloadFailed {
+        /// The watched file
+        path: PathBuf,
+        /// When the configuration still in use was loaded
+        last_good: String,
+    },
+
+    /// Occurs when `--file` is omitted and no default location has a file
+    #[error("No configuration file found; tried:{}", list_paths(.tried))]
+    NoConfigFound {
+        /// Every location looked at, in order
+        tried: Vec<PathBuf>,
+    },
+
+    /// Occurs when one default location holds several candidate files
+    #[error(
+        "Several configuration files found, use --file to pick one:{}",
+        list_paths(.candidates)
+    )]
+    AmbiguousConfig {
+        /// The files found
+        candidates: Vec<PathBuf>,
+    },
+
+    /// Occurs when the input exceeds `MAX_CONFIG_SIZE`
+    #[error("Configuration {path} is larger than the {limit}-byte limit")]
+    TooLarge {
+        /// The file
+        path: PathBuf,
+        /// The limit, in bytes
+        limit: u64,
+    },
+
+    /// Occurs when `--stdin` receives no content at all
+    #[error("No content on stdin (expected the contents of {path})")]
+    EmptyInput {
+        /// The file stdin stands for
+        path: PathBuf,
+    },
+
+    /// Occurs when the file extension names a format that cannot be read
+    #[error("Unsupported configuration format: {path} (supported: {})", crate::config::SUPPORTED_EXTENSIONS.join(", "))]
+    UnsupportedFormat {
+        /// The file
+        path: PathBuf,
+    },
+
+    /// Occurs when a JSON Patch operation cannot be applied
+    #[error("Patch operation {index} ({op}) failed: {reason}")]
+    PatchFailed {
+        /// Position of the operation in the patch, from 0
+        index: usize,
+        /// Its `op`, e.g. `replace`
+        op: String,
+        /// Why it failed
+        reason: String,
+    },
+
+    /// Occurs when the file does not match its `.sha256` sidecar
+    #[error("Checksum mismatch for {path}: expected SHA-256 {expected}, got {actual}")]
+    ChecksumMismatch {
+        /// The file
+        path: PathBuf,
+        /// The hash in the sidecar, hex
+        expected: String,
+        /// The hash of the contents, hex
+        actual: String,
+    },
+
+    /// Occurs when the `.sha256` sidecar is missing, unreadable or garbled
+    #[error("Cannot verify the checksum of {path}: {reason}")]
+    ChecksumUnavailable {
+        /// The file
+        path: PathBuf,
+        /// What is wrong with the sidecar
+        reason: String,
+    },
+
+    /// Occurs when `--verify-signature` finds no signature next to the file
+    #[error("No signature for {path} (expected a .minisig or .sig next to it)")]
+    SignatureMissing {
+        /// The file
+        path: PathBuf,
+    },
+
+    /// Occurs when the detached signature does not verify
+    #[error("Invalid signature for {path}: {reason}")]
+    SignatureInvalid {
+        /// The file
+        path: PathBuf,
+        /// Why it does not verify
+        reason: String,
+    },
+
+    /// Occurs when the `--public-key` file cannot be read or parsed
+    #[error("Invalid public key {path}: {reason}")]
+    InvalidPublicKey {
+        /// The key file
+        path: PathBuf,
+        /// What is wrong with it
+        reason: String,
+    },
+
+    /// Occurs when `age:` values cannot be decrypted; one entry per field
+    /// path, with the reason and never the value
+    #[error("Cannot decrypt {} of {path}:{}", plural(.failures.len(), "value"), list_failures(.failures))]
+    DecryptionFailed {
+        /// The file
+        path: PathBuf,
+        /// `(field path, reason)` pairs
+        failures: Vec<(String, String)>,
+    },
+
+    /// Occurs when the `--age-identity` file cannot be read or parsed
+    #[error("Invalid age identity file {path}: {reason}")]
+    InvalidIdentity {
+        /// The identity file
+        path: PathBuf,
+        /// What is wrong with it
+        reason: String,
+    },
+
+    /// Occurs when another process already watches the file (its lock
+    /// file is held); `pid` is the one recorded in the lock file
+    #[error("{path} is already watched by another config-watcher{} (lock file {lock}); stop it or pass --no-lock", pid_suffix(.pid))]
+    AlreadyRunning {
+        /// The watched file
+        path: PathBuf,
+        /// The lock file held
+        lock: PathBuf,
+        /// The holder, if it recorded itself
+        pid: Option<u32>,
+    },
+
+    /// Occurs when the `--pid-file` names a process that is still running
+    #[error(
+        "Pid file {path} belongs to process {pid}, which is still running; stop it or use another --pid-file"
+    )]
+    PidFileInUse {
+        /// The pid file
+        path: PathBuf,
+        /// The running process
+        pid: u32,
+    },
+
+    /// Occurs when a file to read resolves outside the `--sandbox` set up
+    /// at startup, which the kernel would refuse with a bare EACCES
+    #[error(
+        "{path} resolves to {resolved}, outside the --sandbox set up at startup; restart the watcher to allow it"
+    )]
+    OutsideSandbox {
+        /// The path as given
+        path: PathBuf,
+        /// Where its links lead
+        resolved: PathBuf,
+    },
+
+    /// Occurs when flags, environment variables or the settings file are
+    /// invalid; the message says which
+    #[error("{message}")]
+    InvalidUsage {
+        /// What is wrong, for the user
+        message: String,
+    },
+}
+
+impl ConfigError {
+    /// Stable identifier of the error kind, for machine-readable reports
+    ///
+    /// These strings are part of the `--error-format json` contract: never
+    /// rename one, only add new ones.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConfigError::FileNotFound { .. } => "file_not_found",
+            ConfigError::MetadataError { .. } => "metadata_error",
+            ConfigError::InvalidJson { .. } => "invalid_json",
+            ConfigError::ValidationFailed { .. } => "validation_failed",
+            ConfigError::ReadError { .. } => "read_error",
+            ConfigError::WriteError { .. } => "write_error",
+            ConfigError::InvalidSchema { .. } => "invalid_schema",
+            ConfigError::InvalidPath { .. } => "invalid_path",
+            ConfigError::PathNotFound { .. } => "path_not_found",
+            ConfigError::ReloadFailed { .. } => "reload_failed",
+            ConfigError::NoConfigFound { .. } => "no_config_found",
+            ConfigError::AmbiguousConfig { .. } => "ambiguous_config",
+            ConfigError::TooLarge { .. } => "too_large",
+            ConfigError::EmptyInput { .. } => "empty_input",
+            ConfigError::UnsupportedFormat { .. } => "unsupported_format",
+            ConfigError::PatchFailed { .. } => "patch_failed",
+            ConfigError::ChecksumMismatch { .. } => "checksum_mismatch",
+            ConfigError::ChecksumUnavailable { .. } => "checksum_unavailable",
+            ConfigError::SignatureMissing { .. } => "signature_missing",
+            ConfigError::SignatureInvalid { .. } => "signature_invalid",
+            ConfigError::InvalidPublicKey { .. } => "invalid_public_key",
+            ConfigError::DecryptionFailed { .. } => "decryption_failed",
+            ConfigError::InvalidIdentity { .. } => "invalid_identity",
+            ConfigError::AlreadyRunning { .. } => "already_running",
+            ConfigError::PidFileInUse { .. } => "pid_file_in_use",
+            ConfigError::OutsideSandbox { .. } => "outside_sandbox",
+            ConfigError::InvalidUsage { .. } => "invalid_usage",
+        }
+    }
+}
+
+/// Renders paths one per line for multi-path error messages
+fn list_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| format!("\n  - {}", path.display()))
+        .collect()
+}
+
+/// Renders `field: reason` pairs one per line
+fn list_failures(failures: &[(String, String)]) -> String {
+    failures
+        .iter()
+        .map(|(field, reason)| format!("\n  - {field}: {reason}"))
+        .collect()
+}
+
+/// ` (pid 1234)` when the pid is known
+fn pid_suffix(pid: &Option<u32>) -> String {
+    pid.map(|pid| format!(" (pid {pid})")).unwrap_or_default()
+}
+
+/// `1 value`, `2 values`
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{count} {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
 }
 
 /// Result type alias for operations that return ConfigError
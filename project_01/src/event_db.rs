@@ -0,0 +1,358 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`rusqlite::Connection`**: An embedded SQLite (the `bundled` build), so
+  no system library or shell is needed
+- **`Connection::prepare_cached`**: The insert is compiled once and its
+  values are bound as parameters, never spliced into the SQL
+- **`mpsc::Receiver::recv_timeout`**: The writer thread waits for rows and
+  wakes up on its own to prune old ones
+- **`#[cfg(feature = "event-db")]`**: The whole module, and SQLite with it,
+  is left out of builds without the feature
+
+**Design decisions**:
+- Every event the watcher prints at normal verbosity becomes a row: its
+  type, file, configuration version, diff (JSON, secrets redacted), error
+  text and the whole JSON event in `data`, for `json_extract`
+- The schema is created on first run (`CREATE TABLE IF NOT EXISTS`); each
+  insert is its own transaction, so a row is on disk once it returns
+- Writes happen on a dedicated thread that owns the connection: the
+  watcher only queues a row
+- The database never stops the watcher: a failed insert or prune is
+  logged as a warning and the next row is tried anyway
+- `--event-db-keep` deletes rows older than the limit at startup, then
+  every hour
+- Not in the default features: it compiles SQLite from source
+
+******************************************************************************/
+
+use crate::output::{Event, Verbosity, to_json};
+use anyhow::Context;
+use rusqlite::{Connection, params};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often rows older than `--event-db-keep` are deleted
+pub const PRUNE_EVERY: Duration = Duration::from_secs(3600);
+
+/// Created on first run; `unix_ms` makes pruning a range delete
+const SCHEMA: &str = "\
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY,
+    timestamp TEXT NOT NULL,
+    unix_ms INTEGER NOT NULL,
+    event TEXT NOT NULL,
+    file TEXT,
+    version INTEGER,
+    diff TEXT,
+    error TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS events_unix_ms ON events (unix_ms);
+";
+
+const INSERT: &str = "\
+INSERT INTO events (timestamp, unix_ms, event, file, version, diff, error, data)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+
+const PRUNE: &str = "DELETE FROM events WHERE unix_ms < ?1";
+
+/// One row of the `events` table
+#[derive(Debug, Clone, PartialEq)]
+struct Row {
+    unix_ms: i64,
+    data: Value,
+}
+
+/// What the writer thread receives
+enum Message {
+    Row(Row),
+    Shutdown,
+}
+
+/// Records events in a SQLite database
+///
+/// Cloning is cheap: every clone sends to the same writer thread.
+#[derive(Debug, Clone)]
+pub struct EventDb {
+    sender: Sender<Message>,
+}
+
+/// Stops the writer thread once every queued row is written
+#[derive(Debug)]
+pub struct EventDbGuard {
+    sender: Sender<Message>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl EventDb {
+    /// Opens (or creates) the database at `path` and starts the writer
+    ///
+    /// Rows older than `keep`, if set, are deleted now and every
+    /// [`PRUNE_EVERY`]. Fails when the database cannot be opened or its
+    /// schema created.
+    pub fn open(path: &Path, keep: Option<Duration>) -> anyhow::Result<(Self, EventDbGuard)> {
+        let connection = Connection::open(path)
+            .and_then(|connection| {
+                // WAL lets readers query the history while the watcher writes
+                connection.pragma_update(None, "journal_mode", "WAL")?;
+                connection.execute_batch(SCHEMA)?;
+                Ok(connection)
+            })
+            .with_context(|| format!("cannot open {}", path.display()))?;
+
+        let (sender, receiver) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("event-db".to_string())
+            .spawn(move || run(connection, receiver, keep))?;
+        Ok((
+            Self {
+                sender: sender.clone(),
+            },
+            EventDbGuard {
+                sender,
+                thread: Some(thread),
+            },
+        ))
+    }
+
+    /// Queues a row for `event`, unless it is per-check detail
+    pub fn record(&self, event: &Event<'_>) {
+        if event.level() <= Verbosity::Normal {
+            self.record_at(event, SystemTime::now());
+        }
+    }
+
+    fn record_at(&self, event: &Event<'_>, at: SystemTime) {
+        let mut data = to_json(event);
+        data["timestamp"] = humantime::format_rfc3339_millis(at).to_string().into();
+        let unix_ms = at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64);
+        // Only fails once the writer is gone, at exit
+        let _ = self.sender.send(Message::Row(Row { unix_ms, data }));
+    }
+}
+
+/// Writes rows until told to stop or every sender is gone
+fn run(connection: Connection, receiver: Receiver<Message>, keep: Option<Duration>) {
+    let prune = |keep| {
+        if let Err(e) = connection.execute(PRUNE, [cutoff(keep, SystemTime::now())]) {
+            tracing::warn!(error = %e, "event database: old events not pruned");
+        }
+    };
+
+    let mut pruned = Instant::now();
+    if let Some(keep) = keep {
+        prune(keep);
+    }
+    loop {
+        match receiver.recv_timeout(PRUNE_EVERY) {
+            Ok(Message::Row(row)) => {
+                if let Err(e) = insert(&connection, &row) {
+                    tracing::warn!(error = %e, "event database: event not recorded");
+                }
+            }
+            Ok(Message::Shutdown) | Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+        if let Some(keep) = keep
+            && pruned.elapsed() >= PRUNE_EVERY
+        {
+            prune(keep);
+            pruned = Instant::now();
+        }
+    }
+}
+
+impl EventDbGuard {
+    /// Writes every queued row and stops the writer
+    pub fn shutdown(&mut self) {
+        let _ = self.sender.send(Message::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for EventDbGuard {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn insert(connection: &Connection, row: &Row) -> rusqlite::Result<()> {
+    let data = &row.data;
+    let text = |value: &Value| match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    };
+    let error = data.get("error").and_then(|error| {
+        let chain = error.get("chain")?.as_array()?;
+        let chain: Vec<&str> = chain.iter().filter_map(Value::as_str).collect();
+        Some(chain.join(": "))
+    });
+    connection.prepare_cached(INSERT)?.execute(params![
+        data.get("timestamp").and_then(text),
+        row.unix_ms,
+        data.get("event").and_then(text),
+        data.get("file").and_then(text),
+        data.get("version").and_then(Value::as_i64),
+        data.get("diff").and_then(text),
+        error,
+        data.to_string(),
+    ])?;
+    Ok(())
+}
+
+/// Milliseconds since the epoch before which rows are deleted
+fn cutoff(keep: Duration, now: SystemTime) -> i64 {
+    now.checked_sub(keep)
+        .and_then(|at| at.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::Change;
+    use crate::error::ConfigError;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    /// Every row of `sql`, as JSON objects keyed by column name
+    fn query(path: &Path, sql: &str) -> Vec<Value> {
+        let connection = Connection::open(path).unwrap();
+        let mut statement = connection.prepare(sql).unwrap();
+        let names: Vec<String> = statement
+            .column_names()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        statement
+            .query_map([], |row| {
+                let mut object = serde_json::Map::new();
+                for (i, name) in names.iter().enumerate() {
+                    let value = match row.get_ref(i)? {
+                        rusqlite::types::ValueRef::Null => Value::Null,
+                        rusqlite::types::ValueRef::Integer(n) => json!(n),
+                        rusqlite::types::ValueRef::Text(text) => {
+                            json!(String::from_utf8_lossy(text))
+                        }
+                        other => panic!("unexpected column type {other:?}"),
+                    };
+                    object.insert(name.clone(), value);
+                }
+                Ok(Value::Object(object))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rows_follow_the_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.sqlite");
+        // A quote must not end a string literal: values are bound, not spliced
+        let file = PathBuf::from("app's.json");
+        let error = anyhow::Error::new(ConfigError::FileNotFound { path: file.clone() })
+            .context("Reload failed");
+        let changes = [Change {
+            path: "server.port".to_string(),
+            old: Some(json!(8080)),
+            new: Some(json!(9090)),
+        }];
+        let config = crate::config::AppConfig::example();
+        let overrides = crate::overrides::Overrides::default();
+        let summary = crate::output::Summary {
+            config: &config,
+            overrides: &overrides,
+        };
+
+        let (db, mut guard) = EventDb::open(&path, None).unwrap();
+        for event in [
+            Event::ChangeDetected { file: &file },
+            Event::LoadFailed {
+                file: &file,
+                initial: false,
+                retrying: true,
+                error: &error,
+            },
+            // Per-check detail: not recorded
+            Event::Unmodified { file: &file },
+            Event::Loaded {
+                file: &file,
+                version: 2,
+                initial: false,
+                summary,
+                changes: Some(&changes),
+                flags: None,
+                stand_in: None,
+            },
+        ] {
+            db.record(&event);
+        }
+        guard.shutdown();
+
+        let rows = query(
+            &path,
+            "SELECT event, file, version, diff, error FROM events ORDER BY id",
+        );
+        assert_eq!(
+            rows,
+            [
+                json!({ "event": "change_detected", "file": "app's.json", "version": null, "diff": null, "error": null }),
+                json!({
+                    "event": "load_failed", "file": "app's.json", "version": null, "diff": null,
+                    "error": "Reload failed: Configuration file not found: app's.json"
+                }),
+                json!({
+                    "event": "loaded", "file": "app's.json", "version": 2,
+                    "diff": r#"[{"path":"server.port","old":8080,"new":9090}]"#, "error": null
+                }),
+            ]
+        );
+        let data = query(
+            &path,
+            "SELECT json_extract(data, '$.summary.app_name') AS app FROM events WHERE event = 'loaded'",
+        );
+        assert_eq!(data, [json!({ "app": config.app_name })]);
+    }
+
+    #[test]
+    fn test_old_rows_are_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.sqlite");
+        let file = Path::new("app.json");
+        let day = Duration::from_secs(86_400);
+
+        let (db, mut guard) = EventDb::open(&path, None).unwrap();
+        let now = SystemTime::now();
+        for age in [100, 91, 89, 0] {
+            db.record_at(&Event::ChangeDetected { file }, now - day * age);
+        }
+        guard.shutdown();
+        assert_eq!(query(&path, "SELECT id FROM events").len(), 4);
+
+        // Pruned on startup
+        let (_db, mut guard) = EventDb::open(&path, Some(day * 90)).unwrap();
+        guard.shutdown();
+        let rows = query(&path, "SELECT id FROM events ORDER BY id");
+        assert_eq!(rows, [json!({ "id": 3 }), json!({ "id": 4 })]);
+    }
+
+    #[test]
+    fn test_unusable_path_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = EventDb::open(dir.path(), None).unwrap_err();
+        assert!(
+            format!("{err:#}").contains(&dir.path().display().to_string()),
+            "{err:#}"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`std::process::ExitCode`**: Built from these constants with
+  `ExitCode::from(u8)`
+- **Error chain inspection**: The exit status of a failure comes from the
+  stable error code (`report::code`), not from the message
+
+**Design decisions**:
+- One table for every subcommand, so supervisors and scripts can branch on
+  the status without parsing output:
+
+  | Code | Meaning                                                      |
+  |------|--------------------------------------------------------------|
+  | 0    | Success, clean shutdown                                      |
+  | 1    | A probe reports a problem (`healthcheck`, `doctor` warnings) |
+  |      | or `diff` found differences                                  |
+  | 2    | Usage error: bad flags, unknown field path, invalid settings |
+  | 3    | Input missing or unreadable (or failing --verify-checksum,   |
+  |      | --verify-signature, an `age:` value that won't decrypt, or a |
+  |      | file resolving outside the --sandbox)                        |
+  | 4    | Input does not parse                                         |
+  | 5    | Validation failure (also lint errors, refused writes)        |
+  | 6    | Stopped by `--fail-fast`                                     |
+  | 7    | Internal error                                               |
+  | 8    | The command supervised by `run` failed                       |
+  | 9    | Another instance already watches the file (its lock is held) |
+  |      | or holds the --pid-file                                      |
+  | 10   | The cleanup outlasted --shutdown-timeout and was abandoned   |
+  | 11   | The cleanup was abandoned on a second Ctrl+C or SIGTERM      |
+
+- Code 1 stays reserved for probes because Docker's `HEALTHCHECK` only
+  understands 0 and 1
+- `--max-duration-exit-code` is the user's choice and is not remapped
+
+******************************************************************************/
+
+use crate::error::ConfigError;
+use crate::report;
+
+/// Success, or a clean shutdown
+pub const SUCCESS: u8 = 0;
+/// A probe (`healthcheck`, `doctor`) reports a problem
+pub const UNHEALTHY: u8 = 1;
+/// `diff` found differences, as `diff(1)` does
+pub const DIFFERENT: u8 = 1;
+/// Invalid flags, field paths, environment variables or settings file
+pub const USAGE: u8 = 2;
+/// The input is missing or cannot be read
+pub const INPUT: u8 = 3;
+/// The input does not parse
+pub const PARSE: u8 = 4;
+/// The input parses but is rejected
+pub const VALIDATION: u8 = 5;
+/// `--fail-fast` stopped the watcher
+pub const FAIL_FAST: u8 = 6;
+/// Anything else
+pub const INTERNAL: u8 = 7;
+/// The command supervised by `run` exited with a failure, was killed or
+/// could not be started
+pub const CHILD_FAILED: u8 = 8;
+/// Another process holds the lock of a watched file, or the pid file
+pub const ALREADY_RUNNING: u8 = 9;
+/// The cleanup after the watch did not finish within --shutdown-timeout
+pub const SHUTDOWN_TIMEOUT: u8 = 10;
+/// The cleanup after the watch was cut short by a second signal
+pub const FORCED_SHUTDOWN: u8 = 11;
+
+/// Exit status for an error code of `report::CODES`
+pub fn for_code(code: &str) -> u8 {
+    match code {
+        "invalid_usage" | "invalid_path" | "path_not_found" | "ambiguous_config"
+        | "invalid_public_key" | "invalid_identity" => USAGE,
+        "file_not_found"
+        | "metadata_error"
+        | "read_error"
+        | "write_error"
+        | "no_config_found"
+        | "too_large"
+        | "checksum_mismatch"
+        | "checksum_unavailable"
+        | "signature_missing"
+        | "signature_invalid"
+        | "decryption_failed"
+        | "outside_sandbox" => INPUT,
+        "invalid_json" | "schema_mismatch" | "empty_input" | "unsupported_format"
+        | "invalid_schema" => PARSE,
+        "validation_failed" | "validation_warning" | "patch_failed" => VALIDATION,
+        "reload_failed" => FAIL_FAST,
+        "already_running" | "pid_file_in_use" => ALREADY_RUNNING,
+        _ => INTERNAL,
+    }
+}
+
+/// Exit status for a failure
+pub fn for_error(error: &anyhow::Error) -> u8 {
+    for_code(report::code(error))
+}
+
+/// Marks `error` as a usage error (exit code 2), keeping its message
+pub fn usage(error: anyhow::Error) -> anyhow::Error {
+    ConfigError::InvalidUsage {
+        message: format!("{error:#}"),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::CODES;
+
+    #[test]
+    fn test_every_code_is_mapped() {
+        for (code, _) in CODES {
+            let status = for_code(code);
+            assert!(
+                (USAGE..=INTERNAL).contains(&status) || status == ALREADY_RUNNING,
+                "{code}"
+            );
+            // Only the catch-all is an internal error
+            assert_eq!(status == INTERNAL, *code == "other", "{code}");
+        }
+    }
+}
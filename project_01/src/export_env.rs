@@ -0,0 +1,336 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`impl Notifier`**: Writing the file is one of the dispatcher's
+  notifiers: it runs off the watch loop, and a failure is counted and
+  logged like any notifier's, never turned into a rejected configuration
+- **`tokio::task::spawn_blocking`**: The write (temp file, `fsync`,
+  rename) is blocking I/O, kept off the notifiers' runtime thread
+
+**Design decisions**:
+- `--export-env FILE` is rewritten after every load, initial or not, with
+  one variable per field, named like `--from-env` reads them
+  (`env_config::to_env_vars`: `CW__SERVER__PORT`, prefix from
+  `--export-env-prefix`). With several files watched, it holds the
+  configuration loaded last
+- `--export-env-format dotenv` writes `NAME=value`, double-quoting values
+  that need it (`\\`, `\"`, `\$`, `\n` escapes); `shell` writes
+  `export NAME='value'`, single-quoted, for `source` / `.`. Plain values
+  (letters, digits, `_-.,:/@%+=`) stay unquoted in both
+- Secrets (`schema::FIELDS`, and values decrypted from `age:`) are left
+  out unless `--export-env-secrets`: an unset variable is safer than a
+  placeholder the application might use
+- The file is replaced atomically (`fs_util::write_atomic`): a process
+  sourcing it sees the old or the new version, never half of one. A new
+  file gets mode 600; an existing file keeps its permissions
+- Fields whose name cannot be a variable name (e.g. a feature flag with a
+  `-`) are skipped with a warning
+
+******************************************************************************/
+
+use crate::cli::{EnvFormat, EventKind};
+use crate::env_config;
+use crate::fs_util;
+use crate::notify::{ConfigEvent, Notifier, NotifierOptions};
+use crate::redact;
+use crate::timestamp;
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Writes the loaded configuration as environment variables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportEnv {
+    pub path: PathBuf,
+    pub prefix: String,
+    pub format: EnvFormat,
+    /// Writes the secret fields too
+    pub secrets: bool,
+}
+
+impl ExportEnv {
+    /// Writes to `path`, as dotenv, with the `--from-env` prefix and
+    /// without secrets
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            prefix: env_config::DEFAULT_PREFIX.to_string(),
+            format: EnvFormat::default(),
+            secrets: false,
+        }
+    }
+
+    /// The file's content for `doc`, the configuration's JSON form, and
+    /// the variables skipped for their names
+    pub fn render(&self, doc: &Value, header: &str) -> (String, Vec<String>) {
+        let mut doc = doc.clone();
+        if !self.secrets {
+            // Last first, so that removing an array element does not
+            // shift the next one
+            for path in redact::secret_paths().iter().rev() {
+                let _ = path.remove(&mut doc);
+            }
+        }
+        let mut out = format!("# {header}\n");
+        let mut skipped = Vec::new();
+        for (name, value) in env_config::to_env_vars(&self.prefix, &doc) {
+            if !is_name(&name) {
+                skipped.push(name);
+                continue;
+            }
+            let line = match self.format {
+                EnvFormat::Dotenv => format!("{name}={}\n", dotenv_quote(&value)),
+                EnvFormat::Shell => format!("export {name}={}\n", shell_quote(&value)),
+            };
+            out.push_str(&line);
+        }
+        (out, skipped)
+    }
+}
+
+impl Notifier for ExportEnv {
+    fn name(&self) -> &str {
+        "export-env"
+    }
+
+    /// Only the latest configuration is worth writing
+    fn options(&self) -> NotifierOptions {
+        NotifierOptions {
+            queue: 1,
+            ..NotifierOptions::default()
+        }
+    }
+
+    fn accepts(&self, event: &ConfigEvent) -> bool {
+        matches!(event.kind, EventKind::Initial | EventKind::Reload) && event.config.is_some()
+    }
+
+    async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+        let Some(ref config) = event.config else {
+            return Ok(());
+        };
+        let header = format!(
+            "Written by config-watcher from {} (version {}) at {}; do not edit",
+            event.file.clone().unwrap_or_default().display(),
+            event.version().unwrap_or_default(),
+            timestamp::utc(event.at)
+        );
+        let (text, skipped) = self.render(&serde_json::to_value(config)?, &header);
+        if !skipped.is_empty() {
+            tracing::warn!(
+                file = %self.path.display(),
+                skipped = skipped.join(", "),
+                "fields without a valid variable name left out of the environment file"
+            );
+        }
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || fs_util::write_atomic(&path, text.as_bytes()))
+            .await??;
+        tracing::debug!(file = %self.path.display(), "environment file written");
+        Ok(())
+    }
+}
+
+/// A portable variable name: a letter or `_`, then letters, digits, `_`
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// True when `value` means the same to every reader without quotes
+fn is_plain(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.,:/@%+=".contains(c))
+}
+
+/// `value` for a dotenv file: as is when plain, else double-quoted
+pub fn dotenv_quote(value: &str) -> String {
+    if is_plain(value) {
+        return value.to_string();
+    }
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '$' => out.push_str("\\$"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `value` for a POSIX shell: as is when plain, else single-quoted (the
+/// only character to escape is `'` itself, as `'\''`)
+pub fn shell_quote(value: &str) -> String {
+    if is_plain(value) {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::output::{Event, Summary};
+    use crate::overrides::Overrides;
+    use std::path::Path;
+    use std::process::Command;
+    use std::time::SystemTime;
+
+    const TRICKY: [&str; 7] = [
+        "two words",
+        "it's",
+        r#"say "hi""#,
+        "line 1\nline 2",
+        "$HOME and `date`",
+        r"back\slash",
+        "",
+    ];
+
+    fn loaded(config: &AppConfig) -> ConfigEvent {
+        let overrides = Overrides::default();
+        let event = Event::Loaded {
+            file: Path::new("/etc/app.json"),
+            version: 3,
+            initial: false,
+            summary: Summary {
+                config,
+                overrides: &overrides,
+            },
+            changes: Some(&[]),
+            flags: None,
+            stand_in: None,
+        };
+        ConfigEvent::new(&event, SystemTime::UNIX_EPOCH).unwrap()
+    }
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(
+            dotenv_quote("postgres://db:5432/app"),
+            "postgres://db:5432/app"
+        );
+        assert_eq!(shell_quote("8080"), "8080");
+        assert_eq!(dotenv_quote("two words"), r#""two words""#);
+        assert_eq!(dotenv_quote("line 1\nline 2"), r#""line 1\nline 2""#);
+        assert_eq!(dotenv_quote(r#"a "b" $c \d"#), r#""a \"b\" \$c \\d""#);
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+        assert!(is_name("CW__SERVER__PORT"));
+        assert!(!is_name("CW__FEATURES__DARK-MODE"));
+        assert!(!is_name("9LIVES"));
+    }
+
+    /// What `sh` reads back from the shell format, byte for byte
+    #[cfg(unix)]
+    #[test]
+    fn test_shell_format_reads_back_in_sh() {
+        let export = ExportEnv {
+            format: EnvFormat::Shell,
+            ..ExportEnv::new("unused")
+        };
+        for value in TRICKY {
+            let (text, _) = export.render(&serde_json::json!({ "v": value }), "test");
+            assert_eq!(
+                text.lines().nth(1).unwrap().split_once('=').unwrap().0,
+                "export CW__V"
+            );
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(format!("{text}printf '%s' \"$CW__V\""))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), value, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_dotenv_format() {
+        let export = ExportEnv {
+            prefix: "APP".to_string(),
+            ..ExportEnv::new("unused")
+        };
+        let doc = serde_json::json!({
+            "app_name": "My App",
+            "server": { "port": 8080, "allowed_ips": ["10.0.0.0/8"] },
+            "notes": null,
+            "features": { "dark-mode": true, "beta": false },
+        });
+        let (text, skipped) = export.render(&doc, "header");
+        assert_eq!(
+            text,
+            "# header\n\
+             APP__APP_NAME=\"My App\"\n\
+             APP__FEATURES__BETA=false\n\
+             APP__SERVER__ALLOWED_IPS=\"[\\\"10.0.0.0/8\\\"]\"\n\
+             APP__SERVER__PORT=8080\n"
+        );
+        assert_eq!(skipped, ["APP__FEATURES__DARK-MODE"]);
+    }
+
+    #[tokio::test]
+    async fn test_writes_atomically_without_secrets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.env");
+        let export = ExportEnv::new(&path);
+        let mut config = AppConfig {
+            app_name: "it's \"quoted\"\nacross lines".to_string(),
+            ..AppConfig::example()
+        };
+        export.notify(&loaded(&config)).await.unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with(
+            "# Written by config-watcher from /etc/app.json (version 3) at 1970-01-01T00:00:00.000Z"
+        ));
+        assert!(
+            text.contains(r#"CW__APP_NAME="it's \"quoted\"\nacross lines""#),
+            "{text}"
+        );
+        assert!(text.contains("CW__DATABASE__POOL_SIZE=15\n"), "{text}");
+        assert!(!text.contains("CONNECTION_STRING"), "{text}");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // Replaced, never appended to, and no temp file left behind
+        config.version = "2.0.0".to_string();
+        let export = ExportEnv {
+            secrets: true,
+            ..export
+        };
+        export.notify(&loaded(&config)).await.unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.matches("CW__VERSION=").count(), 1);
+        assert!(text.contains("CW__VERSION=2.0.0\n"), "{text}");
+        assert!(
+            text.contains("CW__DATABASE__CONNECTION_STRING=postgres://localhost/mydb\n"),
+            "{text}"
+        );
+        let entries = std::fs::read_dir(dir.path()).unwrap().count();
+        assert_eq!(entries, 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_failure_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let export = ExportEnv::new(dir.path().join("missing").join("app.env"));
+        let error = export
+            .notify(&loaded(&AppConfig::example()))
+            .await
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("app.env"), "{error:#}");
+    }
+}
@@ -0,0 +1,143 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`jsonschema::validator_for`**: Compiles a schema once, validates many times
+- **`iter_errors`**: Lazily yields every violation, not just the first
+- **`SystemTime` comparison**: Detects when the schema file was edited
+
+**Design decisions**:
+- A schema that does not compile is a startup error, never a silent no-op
+- Violations become `Finding`s keyed by the JSON Pointer of the offending
+  value, merged into the normal validation report
+- The schema remembers its file's mtime so the watcher can treat it as a
+  companion file and revalidate when it changes
+
+******************************************************************************/
+
+use crate::error::{ConfigError, Result};
+use crate::validation::ValidationReport;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A user-supplied JSON Schema enforced on top of the built-in rules
+pub struct ExternalSchema {
+    path: PathBuf,
+    validator: jsonschema::Validator,
+    modified: Option<SystemTime>,
+}
+
+impl ExternalSchema {
+    /// Reads and compiles the schema at `path`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let invalid = |reason: String| ConfigError::InvalidSchema {
+            path: path.clone(),
+            reason,
+        };
+
+        let contents = std::fs::read_to_string(&path).map_err(|e| ConfigError::ReadError {
+            path: path.clone(),
+            source: e,
+        })?;
+        let schema: Value =
+            serde_json::from_str(&contents).map_err(|e| invalid(format!("invalid JSON: {e}")))?;
+        let validator = jsonschema::validator_for(&schema).map_err(|e| invalid(e.to_string()))?;
+
+        Ok(Self {
+            modified: modified_time(&path),
+            path,
+            validator,
+        })
+    }
+
+    /// Location of the schema file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// True when the file on disk is newer than the compiled schema
+    pub fn is_stale(&self) -> bool {
+        match (modified_time(&self.path), self.modified) {
+            (Some(current), Some(loaded)) => current > loaded,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
+    /// Validates a parsed document, one finding per violation
+    pub fn check(&self, doc: &Value) -> ValidationReport {
+        let mut report = ValidationReport::new();
+        for error in self.validator.iter_errors(doc) {
+            let pointer = error.instance_path.to_string();
+            let pointer = if pointer.is_empty() {
+                "/".to_string()
+            } else {
+                pointer
+            };
+            report.error(pointer, format!("{error} (schema)"));
+        }
+        report
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    const SCHEMA: &str = r#"{
+        "type": "object",
+        "required": ["owner"],
+        "properties": {
+            "app_name": { "type": "string", "pattern": "^[A-Z][A-Za-z]+$" },
+            "owner": { "type": "string" }
+        }
+    }"#;
+
+    fn schema_file(contents: &str) -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(file.path(), contents).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_conforming_document_passes() {
+        let file = schema_file(SCHEMA);
+        let schema = ExternalSchema::load(file.path()).unwrap();
+
+        let doc = json!({ "app_name": "TestApp", "version": "1.0.0", "owner": "team-a" });
+        assert!(schema.check(&doc).is_empty());
+    }
+
+    #[test]
+    fn test_violations_carry_json_pointers() {
+        let file = schema_file(SCHEMA);
+        let schema = ExternalSchema::load(file.path()).unwrap();
+
+        let doc = json!({ "app_name": "test app", "version": "1.0.0" });
+        let report = schema.check(&doc);
+        let paths: Vec<_> = report.errors().map(|f| f.path.as_str()).collect();
+        assert_eq!(report.errors().count(), 2);
+        assert!(paths.contains(&"/app_name"), "{paths:?}");
+        assert!(paths.contains(&"/"), "{paths:?}");
+        assert!(report.to_string().contains("owner"));
+    }
+
+    #[test]
+    fn test_uncompilable_schema_is_an_error() {
+        let file = schema_file(r#"{ "type": "no-such-type" }"#);
+        assert!(matches!(
+            ExternalSchema::load(file.path()),
+            Err(ConfigError::InvalidSchema { .. })
+        ));
+
+        let file = schema_file("not json");
+        assert!(ExternalSchema::load(file.path()).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`BTreeMap`/sorted `Vec`**: The flags come out in alphabetical order,
+  whatever the order of the `HashMap` they were read from
+- **`#[derive(Serialize)]`**: The breakdown goes as-is into JSON events
+
+**Design decisions**:
+- `feature_diff` only compares; `render` is the text form, so the two can
+  be tested separately
+- A flag that appears is `added`, whatever its value; `enabled` and
+  `disabled` are for flags present on both sides
+- Empty categories are left out of the text line but kept (empty) in JSON,
+  so consumers always find the four keys
+
+******************************************************************************/
+
+use crate::config::AppConfig;
+use crate::diff::Change;
+use crate::path::{FieldPath, Segment};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// How the feature flags moved between two configurations
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FeatureDiff {
+    /// Flags turned on
+    pub enabled: Vec<String>,
+    /// Flags turned off
+    pub disabled: Vec<String>,
+    /// New flags and their value
+    pub added: BTreeMap<String, bool>,
+    /// Flags that are gone
+    pub removed: Vec<String>,
+}
+
+impl FeatureDiff {
+    /// True when no flag moved
+    pub fn is_empty(&self) -> bool {
+        self.enabled.is_empty()
+            && self.disabled.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+    }
+
+    /// `flags enabled: a, b; disabled: c; added: d(off); removed: e`
+    pub fn render(&self) -> String {
+        let added: Vec<_> = self
+            .added
+            .iter()
+            .map(|(name, &on)| format!("{name}({})", if on { "on" } else { "off" }))
+            .collect();
+        let parts: Vec<_> = [
+            ("enabled", &self.enabled),
+            ("disabled", &self.disabled),
+            ("added", &added),
+            ("removed", &self.removed),
+        ]
+        .into_iter()
+        .filter(|(_, names)| !names.is_empty())
+        .map(|(what, names)| format!("{what}: {}", names.join(", ")))
+        .collect();
+        format!("flags {}", parts.join("; "))
+    }
+}
+
+/// True for a change under `features`
+pub fn is_flag_change(change: &Change) -> bool {
+    FieldPath::parse(&change.path).is_ok_and(
+        |path| matches!(path.segments().first(), Some(Segment::Key(key)) if key == "features"),
+    )
+}
+
+impl AppConfig {
+    /// The feature flags that changed from `self` to `new`
+    pub fn feature_diff(&self, new: &AppConfig) -> FeatureDiff {
+        let mut diff = FeatureDiff::default();
+        for (name, &on) in &new.features {
+            match self.features.get(name) {
+                None => {
+                    diff.added.insert(name.clone(), on);
+                }
+                Some(&was) if was != on => {
+                    let list = if on {
+                        &mut diff.enabled
+                    } else {
+                        &mut diff.disabled
+                    };
+                    list.push(name.clone());
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = self
+            .features
+            .keys()
+            .filter(|name| !new.features.contains_key(*name))
+            .cloned()
+            .collect();
+        diff.enabled.sort();
+        diff.disabled.sort();
+        diff.removed.sort();
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn with_flags(flags: &[(&str, bool)]) -> AppConfig {
+        AppConfig {
+            features: flags
+                .iter()
+                .map(|&(name, on)| (name.to_string(), on))
+                .collect::<HashMap<_, _>>(),
+            ..AppConfig::example()
+        }
+    }
+
+    #[test]
+    fn test_each_category() {
+        let old = with_flags(&[
+            ("dark_mode", false),
+            ("new_checkout", false),
+            ("legacy_auth", true),
+            ("old_flag", true),
+            ("stable", true),
+        ]);
+        let new = with_flags(&[
+            ("new_checkout", true),
+            ("dark_mode", true),
+            ("legacy_auth", false),
+            ("beta_search", false),
+            ("stable", true),
+        ]);
+
+        let diff = old.feature_diff(&new);
+        assert_eq!(diff.enabled, ["dark_mode", "new_checkout"]);
+        assert_eq!(diff.disabled, ["legacy_auth"]);
+        assert_eq!(
+            diff.added,
+            BTreeMap::from([("beta_search".to_string(), false)])
+        );
+        assert_eq!(diff.removed, ["old_flag"]);
+        assert_eq!(
+            diff.render(),
+            "flags enabled: dark_mode, new_checkout; disabled: legacy_auth; \
+             added: beta_search(off); removed: old_flag"
+        );
+    }
+
+    #[test]
+    fn test_only_moved_categories_are_rendered() {
+        let old = with_flags(&[("a", true)]);
+        let new = with_flags(&[("a", true), ("z", true), ("b", true)]);
+        let diff = old.feature_diff(&new);
+        assert_eq!(diff.render(), "flags added: b(on), z(on)");
+
+        assert!(old.feature_diff(&old).is_empty());
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_json_keeps_every_key() {
+        let old = with_flags(&[("a", true)]);
+        let new = with_flags(&[("a", false)]);
+        assert_eq!(
+            serde_json::to_value(old.feature_diff(&new)).unwrap(),
+            serde_json::json!({ "enabled": [], "disabled": ["a"], "added": {}, "removed": [] })
+        );
+    }
+}
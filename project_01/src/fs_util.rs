@@ -0,0 +1,375 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`tempfile::NamedTempFile::new_in`**: Temp file on the same filesystem
+- **`persist`**: Atomic `rename(2)` over the destination
+- **`sync_all`**: Flushes data to disk before the rename
+- **`impl Fn(&[u8]) -> Result<()>`**: `read_consistent` takes the check
+  to repeat as a closure
+- **`File::set_times`**: A backup gets the modification time of the file
+  it copies
+
+**Design decisions**:
+- Readers (including our own watcher) see either the old or the new file,
+  never a half-written one
+- Permissions of the file being replaced are carried over to the new one,
+  unless the caller asks for a mode, which is set before the rename
+- Every rewrite of a configuration file goes through `safe_write` (or
+  `backup`, before a rename of another file over it), so none can forget
+  the backup: `FILE.bak` by default, `FILE.bak.1` (newest) to `FILE.bak.K`
+  with `--backups K`, none with `--no-backup`
+- `safe_write` stages the new content in a temporary file before it backs
+  up the file, and renames last: any failure leaves the file as it was,
+  and the temporary files are removed on the way out. A backup is itself
+  written to a temporary file and renamed, so it is never partial either
+- A file distributed with companions (a `.sha256`, a signature) is written
+  one file after the other, in any order: `read_consistent` re-reads until
+  the check of file against companions passes, a few times
+  `COMPANION_RETRY_DELAY` apart, before the disagreement counts
+- `content_hash` is SipHash, not SHA-256: it runs on every read of the
+  watched file and is only ever compared within one run
+
+******************************************************************************/
+
+use crate::error::{ConfigError, Result};
+use std::hash::Hasher;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Reads of a file and its companions before a disagreement counts
+pub const COMPANION_ATTEMPTS: u32 = 5;
+
+/// Wait between two reads of a file and its companions
+pub const COMPANION_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// The companion of `file`: the same path plus `suffix` (`.sha256`...)
+pub fn companion(file: &Path, suffix: &str) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Reads `path` once `check` accepts its bytes
+///
+/// `check` reads the companions itself, so each attempt sees a fresh pair.
+/// A file that cannot be read fails right away; the last failure of
+/// `check` is returned after `COMPANION_ATTEMPTS` reads.
+pub async fn read_consistent(path: &Path, check: impl Fn(&[u8]) -> Result<()>) -> Result<String> {
+    let mut attempt = 1;
+    loop {
+        let contents = tokio::fs::read(path)
+            .await
+            .map_err(|e| ConfigError::ReadError {
+                path: path.to_path_buf(),
+                source: e,
+            })?;
+        match check(&contents) {
+            Ok(()) => {
+                return String::from_utf8(contents).map_err(|e| ConfigError::ReadError {
+                    path: path.to_path_buf(),
+                    source: std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+                });
+            }
+            Err(e) if attempt < COMPANION_ATTEMPTS => {
+                tracing::debug!(attempt, error = %e, "companion file disagrees, re-reading");
+                tokio::time::sleep(COMPANION_RETRY_DELAY).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// A 64-bit hash of `bytes`, to tell whether a file changed
+///
+/// SipHash with fixed keys: fast, and the same for the same bytes within
+/// a run, which is all the watcher compares. Never persisted: SHA-256 is
+/// for that.
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    let mut hasher = std::hash::DefaultHasher::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// Atomically replaces `path` with `contents`
+///
+/// The data is written to a temporary file in the same directory, synced,
+/// given the permissions of the existing file (if any), then renamed over
+/// the destination.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    write(path, contents, None)
+}
+
+/// How many backups of a configuration file to keep when it is rewritten
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backups(pub u32);
+
+impl Backups {
+    /// `--no-backup`
+    pub const NONE: Backups = Backups(0);
+
+    /// Where the newest backup of `file` goes; `None` without backups
+    pub fn newest(&self, file: &Path) -> Option<PathBuf> {
+        match self.0 {
+            0 => None,
+            1 => Some(companion(file, ".bak")),
+            _ => Some(self.rotated(file, 1)),
+        }
+    }
+
+    /// `FILE.bak.N`
+    fn rotated(&self, file: &Path, n: u32) -> PathBuf {
+        companion(file, &format!(".bak.{n}"))
+    }
+}
+
+impl Default for Backups {
+    fn default() -> Self {
+        Backups(1)
+    }
+}
+
+/// [`write_atomic`], once `path` is backed up as `backups` asks
+///
+/// The one way to rewrite a configuration file. Nothing changes on
+/// failure: the new content is staged before the backup is made.
+pub fn safe_write(path: &Path, contents: &[u8], backups: Backups) -> Result<()> {
+    let staged = stage(path, contents, None)?;
+    backup(path, backups)?;
+    staged.persist(path).map_err(|e| ConfigError::WriteError {
+        path: path.to_path_buf(),
+        source: e.error,
+    })?;
+    Ok(())
+}
+
+/// Copies `path` to its newest backup, with its permissions and
+/// modification time, rotating the older ones
+///
+/// A missing `path` has nothing to back up.
+pub fn backup(path: &Path, backups: Backups) -> Result<()> {
+    let Some(newest) = backups.newest(path) else {
+        return Ok(());
+    };
+    let mut original = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(source) => {
+            return Err(ConfigError::ReadError {
+                path: path.to_path_buf(),
+                source,
+            });
+        }
+    };
+    let write_error = |source: std::io::Error| ConfigError::WriteError {
+        path: newest.clone(),
+        source,
+    };
+
+    let metadata = original.metadata().map_err(write_error)?;
+    let mut temp = tempfile::NamedTempFile::new_in(directory(path)).map_err(write_error)?;
+    std::io::copy(&mut original, temp.as_file_mut()).map_err(write_error)?;
+    temp.as_file().sync_all().map_err(write_error)?;
+    let mut times =
+        std::fs::FileTimes::new().set_modified(metadata.modified().map_err(write_error)?);
+    if let Ok(accessed) = metadata.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    temp.as_file().set_times(times).map_err(write_error)?;
+    temp.as_file()
+        .set_permissions(metadata.permissions())
+        .map_err(write_error)?;
+
+    // FILE.bak.K-1 over FILE.bak.K, and so on down to FILE.bak.1
+    for n in (1..backups.0).rev() {
+        let from = backups.rotated(path, n);
+        match std::fs::rename(&from, backups.rotated(path, n + 1)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(ConfigError::WriteError {
+                    path: from,
+                    source: e,
+                });
+            }
+            _ => {}
+        }
+    }
+    temp.persist(&newest).map_err(|e| write_error(e.error))?;
+    Ok(())
+}
+
+/// [`write_atomic`], with `mode` as the permissions of the new file
+///
+/// The mode is ignored outside Unix.
+pub fn write_atomic_with_mode(path: &Path, contents: &[u8], mode: u32) -> Result<()> {
+    write(path, contents, Some(mode))
+}
+
+fn write(path: &Path, contents: &[u8], mode: Option<u32>) -> Result<()> {
+    stage(path, contents, mode)?
+        .persist(path)
+        .map_err(|e| ConfigError::WriteError {
+            path: path.to_path_buf(),
+            source: e.error,
+        })?;
+    Ok(())
+}
+
+/// `contents` in a synced temporary file next to `path`, with the
+/// permissions it will have; removed if dropped before `persist`
+fn stage(path: &Path, contents: &[u8], mode: Option<u32>) -> Result<tempfile::NamedTempFile> {
+    let write_error = |source: std::io::Error| ConfigError::WriteError {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    let mut temp = tempfile::NamedTempFile::new_in(directory(path)).map_err(write_error)?;
+    temp.write_all(contents).map_err(write_error)?;
+    temp.as_file().sync_all().map_err(write_error)?;
+
+    let permissions = match mode {
+        #[cfg(unix)]
+        Some(mode) => Some(std::os::unix::fs::PermissionsExt::from_mode(mode)),
+        #[cfg(not(unix))]
+        Some(_) => None,
+        None => std::fs::metadata(path).ok().map(|m| m.permissions()),
+    };
+    if let Some(permissions) = permissions {
+        temp.as_file()
+            .set_permissions(permissions)
+            .map_err(write_error)?;
+    }
+
+    Ok(temp)
+}
+
+/// The directory of `path`, `.` for a bare file name
+fn directory(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic_replaces_content_and_leaves_no_temp_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, "old").unwrap();
+
+        write_atomic(&path, b"new").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_preserves_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, "old").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+        write_atomic(&path, b"new").unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    fn read(path: PathBuf) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_safe_write_keeps_a_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        let bak = companion(&path, ".bak");
+
+        // Nothing to back up yet
+        safe_write(&path, b"v1", Backups::default()).unwrap();
+        assert!(!bak.exists());
+
+        safe_write(&path, b"v2", Backups::default()).unwrap();
+        safe_write(&path, b"v3", Backups::default()).unwrap();
+        assert_eq!(read(path.clone()), "v3");
+        assert_eq!(read(bak), "v2");
+
+        // --no-backup
+        std::fs::remove_file(companion(&path, ".bak")).unwrap();
+        safe_write(&path, b"v4", Backups::NONE).unwrap();
+        assert_eq!(read(path.clone()), "v4");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_backups_rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, "v0").unwrap();
+
+        for version in 1..=4 {
+            safe_write(&path, format!("v{version}").as_bytes(), Backups(3)).unwrap();
+        }
+        assert_eq!(read(path.clone()), "v4");
+        let kept: Vec<_> = (1..=3)
+            .map(|n| read(companion(&path, &format!(".bak.{n}"))))
+            .collect();
+        assert_eq!(kept, ["v3", "v2", "v1"]);
+        assert!(!companion(&path, ".bak.4").exists());
+        assert!(!companion(&path, ".bak").exists());
+    }
+
+    #[test]
+    fn test_backup_keeps_permissions_and_modification_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, "old").unwrap();
+        let modified = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+        }
+
+        safe_write(&path, b"new", Backups::default()).unwrap();
+
+        let backup = std::fs::metadata(companion(&path, ".bak")).unwrap();
+        assert_eq!(backup.modified().unwrap(), modified);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(backup.permissions().mode() & 0o777, 0o640);
+        }
+    }
+
+    #[test]
+    fn test_failed_backup_leaves_the_file_intact() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, "old").unwrap();
+        // A non-empty directory where the backup goes: the rename fails
+        let bak = companion(&path, ".bak");
+        std::fs::create_dir(&bak).unwrap();
+        std::fs::write(bak.join("keep"), "").unwrap();
+
+        let err = safe_write(&path, b"new", Backups::default()).unwrap_err();
+        assert!(matches!(err, ConfigError::WriteError { ref path, .. } if *path == bak));
+        assert_eq!(read(path.clone()), "old");
+        // No temporary file left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+}
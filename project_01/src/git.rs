@@ -0,0 +1,317 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`std::process::Command`**: Every operation is one `git` invocation
+  with `-C <work tree>`, its stderr kept for the error message
+- **`io::ErrorKind::NotFound`** on spawn: Git is not installed, told apart
+  from a git that refuses
+
+**Design decisions**:
+- The git CLI rather than a library: it is what the operators use on the
+  same repository, with their configuration, hooks and credentials, and it
+  keeps the build free of a second git implementation
+- A file belongs to the work tree `git rev-parse --show-toplevel` names
+  from its directory; paths given to git are relative to that root, with
+  `/` separators, symlinks resolved on both sides
+- Commits name only the file (`git commit -- FILE`), so whatever else is
+  staged in the index is left as it was
+- `show` tells the ways a revision can lack the file apart: an unknown
+  revision, a file git never tracked, a file added after that revision
+- Without a configured identity (a server nobody ran `git config` on), the
+  commit is authored by `config-watcher <config-watcher@localhost>` rather
+  than refused
+
+******************************************************************************/
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Identity used when the repository has none configured
+const FALLBACK_NAME: &str = "config-watcher";
+const FALLBACK_EMAIL: &str = "config-watcher@localhost";
+
+/// Why a git operation failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitError {
+    /// No `git` program on the PATH
+    NotInstalled,
+    /// The file is not inside a git work tree
+    NotAWorkTree(PathBuf),
+    /// The file is in a work tree, but git does not track it
+    NotTracked(PathBuf),
+    /// No commit by that name
+    UnknownRevision(String),
+    /// The commit exists, the file did not at that point
+    NotAtRevision { revision: String, path: String },
+    /// Git ran and refused; `stderr` holds its reason
+    Failed { command: String, stderr: String },
+}
+
+impl fmt::Display for GitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitError::NotInstalled => write!(f, "git is not installed (not found on PATH)"),
+            GitError::NotAWorkTree(path) => {
+                write!(f, "{} is not inside a git work tree", path.display())
+            }
+            GitError::NotTracked(path) => {
+                write!(f, "{} is not under version control", path.display())
+            }
+            GitError::UnknownRevision(revision) => write!(f, "unknown revision '{revision}'"),
+            GitError::NotAtRevision { revision, path } => {
+                write!(f, "{path} does not exist at revision '{revision}'")
+            }
+            GitError::Failed { command, stderr } => write!(f, "git {command} failed: {stderr}"),
+        }
+    }
+}
+
+impl std::error::Error for GitError {}
+
+/// The work tree of a watched file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkTree {
+    root: PathBuf,
+}
+
+impl WorkTree {
+    /// The work tree `file` is in
+    pub fn of(file: &Path) -> Result<Self, GitError> {
+        let absolute = std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf());
+        let dir = absolute.parent().unwrap_or(Path::new("."));
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["rev-parse", "--show-toplevel"])
+            .output()
+            .map_err(spawn_error)?;
+        if !output.status.success() {
+            return Err(GitError::NotAWorkTree(file.to_path_buf()));
+        }
+        let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok(Self {
+            root: PathBuf::from(root),
+        })
+    }
+
+    /// Root directory of the work tree
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// `file` relative to the root, as git spells it
+    pub fn relative(&self, file: &Path) -> Result<String, GitError> {
+        let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        // The file may be gone; its directory is enough
+        let absolute = std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf());
+        let path = match (absolute.parent(), absolute.file_name()) {
+            (Some(dir), Some(name)) => canonical(dir).join(name),
+            _ => canonical(&absolute),
+        };
+        let relative = path
+            .strip_prefix(canonical(&self.root))
+            .map_err(|_| GitError::NotAWorkTree(file.to_path_buf()))?;
+        let parts: Vec<_> = relative
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect();
+        Ok(parts.join("/"))
+    }
+
+    /// True when `.gitignore` rules exclude `file`
+    pub fn is_ignored(&self, file: &Path) -> bool {
+        self.relative(file)
+            .is_ok_and(|relative| self.succeeds(&["check-ignore", "-q", "--", &relative]))
+    }
+
+    /// Stages `file` and commits it alone with `message`; false when it
+    /// does not differ from the last commit
+    pub fn commit_file(&self, file: &Path, message: &str) -> Result<bool, GitError> {
+        let relative = self.relative(file)?;
+        self.run(&["add", "--", &relative])?;
+        let status = self.run(&["status", "--porcelain", "--", &relative])?;
+        if status.stdout.is_empty() {
+            return Ok(false);
+        }
+        let mut commit = self.git();
+        commit.args(["commit", "--quiet", "-m", message, "--", &relative]);
+        if !self.has_identity() {
+            for (name, value) in [
+                ("GIT_AUTHOR_NAME", FALLBACK_NAME),
+                ("GIT_AUTHOR_EMAIL", FALLBACK_EMAIL),
+                ("GIT_COMMITTER_NAME", FALLBACK_NAME),
+                ("GIT_COMMITTER_EMAIL", FALLBACK_EMAIL),
+            ] {
+                commit.env(name, value);
+            }
+        }
+        check("commit", commit.output().map_err(spawn_error)?)?;
+        Ok(true)
+    }
+
+    /// The content of `file` as committed at `revision` (a commit, branch,
+    /// tag or `HEAD~2`)
+    pub fn show(&self, revision: &str, file: &Path) -> Result<String, GitError> {
+        let relative = self.relative(file)?;
+        let commit = format!("{revision}^{{commit}}");
+        if !self.succeeds(&["rev-parse", "--verify", "--quiet", &commit]) {
+            return Err(GitError::UnknownRevision(revision.to_string()));
+        }
+        let object = format!("{revision}:{relative}");
+        if !self.succeeds(&["cat-file", "-e", &object]) {
+            return Err(
+                if self.succeeds(&["ls-files", "--error-unmatch", "--", &relative]) {
+                    GitError::NotAtRevision {
+                        revision: revision.to_string(),
+                        path: relative,
+                    }
+                } else {
+                    GitError::NotTracked(file.to_path_buf())
+                },
+            );
+        }
+        let output = self.run(&["cat-file", "blob", &object])?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// True when user.name and user.email are both configured
+    fn has_identity(&self) -> bool {
+        ["user.name", "user.email"]
+            .iter()
+            .all(|key| self.succeeds(&["config", "--get", key]))
+    }
+
+    /// `git -C <root>`
+    fn git(&self) -> Command {
+        let mut git = Command::new("git");
+        git.arg("-C").arg(&self.root);
+        git
+    }
+
+    /// True when `git args` exits with 0, its output discarded
+    fn succeeds(&self, args: &[&str]) -> bool {
+        self.git()
+            .args(args)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    /// Runs `git args`, failing on a non-zero status
+    fn run(&self, args: &[&str]) -> Result<Output, GitError> {
+        let output = self.git().args(args).output().map_err(spawn_error)?;
+        check(args[0], output)
+    }
+}
+
+/// `output` if git succeeded
+fn check(command: &str, output: Output) -> Result<Output, GitError> {
+    if output.status.success() {
+        return Ok(output);
+    }
+    Err(GitError::Failed {
+        command: command.to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    })
+}
+
+fn spawn_error(e: io::Error) -> GitError {
+    match e.kind() {
+        io::ErrorKind::NotFound => GitError::NotInstalled,
+        _ => GitError::Failed {
+            command: String::new(),
+            stderr: e.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// `git` in `dir` without the user's global configuration
+    pub(crate) fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?}: {output:?}");
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn test_work_tree_and_relative_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = WorkTree::of(&dir.path().join("config.json"));
+        assert_eq!(
+            outside,
+            Err(GitError::NotAWorkTree(dir.path().join("config.json")))
+        );
+
+        git(dir.path(), &["init", "-q"]);
+        std::fs::create_dir(dir.path().join("conf")).unwrap();
+        let file = dir.path().join("conf").join("app.json");
+        let tree = WorkTree::of(&file).unwrap();
+        assert_eq!(tree.relative(&file).unwrap(), "conf/app.json");
+
+        std::fs::write(dir.path().join(".gitignore"), "*.local.json\n").unwrap();
+        assert!(tree.is_ignored(&dir.path().join("app.local.json")));
+        assert!(!tree.is_ignored(&file));
+    }
+
+    #[test]
+    fn test_commits_only_the_file_and_only_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q"]);
+        let file = dir.path().join("app.json");
+        let other = dir.path().join("other.txt");
+        std::fs::write(&file, "{}").unwrap();
+        std::fs::write(&other, "staged").unwrap();
+        git(dir.path(), &["add", "other.txt"]);
+
+        let tree = WorkTree::of(&file).unwrap();
+        assert!(tree.commit_file(&file, "first").unwrap());
+        assert!(!tree.commit_file(&file, "again").unwrap());
+        assert_eq!(git(dir.path(), &["log", "--format=%s"]), "first\n");
+        // The other staged file stays staged, not committed
+        assert_eq!(
+            git(dir.path(), &["diff", "--cached", "--name-only"]),
+            "other.txt\n"
+        );
+    }
+
+    #[test]
+    fn test_show_tells_the_failures_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q"]);
+        let file = dir.path().join("app.json");
+        std::fs::write(&file, "{ \"v\": 1 }").unwrap();
+        let tree = WorkTree::of(&file).unwrap();
+        tree.commit_file(&file, "first").unwrap();
+        std::fs::write(&file, "{ \"v\": 2 }").unwrap();
+
+        assert_eq!(tree.show("HEAD", &file).unwrap(), "{ \"v\": 1 }");
+        assert_eq!(
+            tree.show("nope", &file),
+            Err(GitError::UnknownRevision("nope".to_string()))
+        );
+        let untracked = dir.path().join("new.json");
+        std::fs::write(&untracked, "{}").unwrap();
+        assert_eq!(
+            tree.show("HEAD", &untracked),
+            Err(GitError::NotTracked(untracked.clone()))
+        );
+        tree.commit_file(&untracked, "second").unwrap();
+        assert_eq!(
+            tree.show("HEAD~1", &untracked),
+            Err(GitError::NotAtRevision {
+                revision: "HEAD~1".to_string(),
+                path: "new.json".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,515 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`tonic::include_proto!`**: `build.rs` turns `proto/config_watcher.proto`
+  into prost messages, a server trait and a client (`proto`); protox
+  parses the file, so building needs no `protoc`
+- **`tokio::sync::broadcast`**: Every `WatchConfig` stream reads the
+  loads at its own pace; one that falls behind is told by
+  `RecvError::Lagged` and catches up from the latest configurations,
+  without ever slowing the notifier
+- **`mpsc::channel` as a response stream**: Each `WatchConfig` call is a
+  task sending into a small bounded channel; a client that does not read
+  fills it, and HTTP/2 flow control then holds the task, not the watcher
+- **`#[cfg(feature = "grpc")]`**: The module is only built with the
+  feature, which is not in the default build
+
+**Design decisions**:
+- `--grpc-addr` serves `config_watcher.v1.ConfigWatcher` with tonic, over
+  HTTP/2 without TLS ("h2c", what gRPC clients use for `http://` targets)
+- `GetConfig` returns the last valid configuration of a file as redacted
+  JSON bytes, with its version and load time. `file` may be left empty
+  when a single file is watched
+- `WatchConfig` sends the current configuration of every file (or of
+  `file`), then one message per load that changes one. With `patches`, a
+  reload carries an RFC 6902 JSON Patch against the previous version
+  instead of the whole document; a client that missed a version gets the
+  whole document again
+- Streams read from a `broadcast` channel of `STREAM_BUFFER` loads: a
+  stream that lags resumes from the latest configurations rather than
+  replaying what it missed
+- On shutdown every stream ends with `UNAVAILABLE` after what is already
+  queued, then the server stops (GOAWAY) and waits at most
+  `CLOSE_TIMEOUT` for the connections to close
+- Bind to 127.0.0.1 unless the configuration is meant for the network:
+  there is no authentication, as for `--http-addr`
+
+******************************************************************************/
+
+use crate::cli::EventKind;
+use crate::notify::{ConfigEvent, Notifier};
+use crate::timestamp;
+use futures::Stream;
+use proto::config::Payload;
+use proto::config_watcher_server::{ConfigWatcher, ConfigWatcherServer};
+use proto::{Config, GetConfigRequest, WatchConfigRequest};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+
+/// The messages, server and client of `proto/config_watcher.proto`
+pub mod proto {
+    tonic::include_proto!("config_watcher.v1");
+}
+
+/// Loads buffered for a `WatchConfig` stream before it lags
+pub const STREAM_BUFFER: usize = 16;
+
+/// Time given to connections to close on shutdown
+pub const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Messages of a `WatchConfig` call waiting for the client
+const CALL_QUEUE: usize = 4;
+
+/// The latest configurations and the loads to come, fed by a
+/// [`GrpcNotifier`]
+#[derive(Debug, Clone)]
+pub struct ConfigFeed {
+    latest: Arc<Mutex<BTreeMap<String, Arc<Update>>>>,
+    sender: broadcast::Sender<Arc<Update>>,
+}
+
+/// One load of one file
+#[derive(Debug)]
+struct Update {
+    file: String,
+    version: u64,
+    loaded_at: String,
+    /// Redacted JSON
+    config: String,
+    /// From the previous version, redacted; none for an initial load
+    patch: Option<String>,
+}
+
+impl Default for ConfigFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigFeed {
+    /// A feed with no load yet
+    pub fn new() -> Self {
+        Self {
+            latest: Arc::default(),
+            sender: broadcast::channel(STREAM_BUFFER).0,
+        }
+    }
+
+    /// The notifier feeding this feed
+    pub fn notifier(&self) -> GrpcNotifier {
+        GrpcNotifier { feed: self.clone() }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Arc<Update>>> {
+        self.latest.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn publish(&self, update: Update) {
+        let update = Arc::new(update);
+        let mut latest = self.lock();
+        if latest
+            .get(&update.file)
+            .is_some_and(|known| known.version >= update.version)
+        {
+            return;
+        }
+        latest.insert(update.file.clone(), update.clone());
+        // Nobody watching is fine
+        let _ = self.sender.send(update);
+    }
+
+    fn snapshot(&self) -> Vec<Arc<Update>> {
+        self.lock().values().cloned().collect()
+    }
+
+    /// The answer to `GetConfig`
+    fn get_config(&self, request: &GetConfigRequest) -> Result<Config, Status> {
+        let latest = self.lock();
+        let update = match (request.file.as_str(), latest.len()) {
+            ("", 0) => Err(Status::unavailable("no valid configuration loaded yet")),
+            ("", 1) => Ok(latest
+                .values()
+                .next()
+                .cloned()
+                .unwrap_or_else(|| unreachable!())),
+            ("", _) => {
+                let files: Vec<&str> = latest.keys().map(String::as_str).collect();
+                Err(Status::invalid_argument(format!(
+                    "several files are watched, set file to one of {}",
+                    files.join(", ")
+                )))
+            }
+            (file, _) => latest
+                .get(file)
+                .cloned()
+                .ok_or_else(|| Status::not_found(format!("no valid configuration for '{file}'"))),
+        }?;
+        Ok(config(&update, false))
+    }
+}
+
+/// The message for `update`, with its patch if `patch` and it has one
+fn config(update: &Update, patch: bool) -> Config {
+    let payload = match update.patch {
+        Some(ref json) if patch => Payload::Patch(json.clone().into_bytes()),
+        _ => Payload::Config(update.config.clone().into_bytes()),
+    };
+    Config {
+        file: update.file.clone(),
+        version: update.version,
+        loaded_at: update.loaded_at.clone(),
+        payload: Some(payload),
+    }
+}
+
+/// Hands the loads to a [`ConfigFeed`]
+#[derive(Debug)]
+pub struct GrpcNotifier {
+    feed: ConfigFeed,
+}
+
+impl Notifier for GrpcNotifier {
+    fn name(&self) -> &str {
+        "grpc"
+    }
+
+    /// Loads, initial or not, that changed the configuration
+    fn accepts(&self, event: &ConfigEvent) -> bool {
+        matches!(event.kind, EventKind::Initial | EventKind::Reload) && event.config.is_some()
+    }
+
+    async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+        let (Some(file), Some(config), Some(version)) =
+            (&event.file, &event.config, event.version())
+        else {
+            return Ok(());
+        };
+        let mut config = serde_json::to_value(config)?;
+        crate::redact::redact(&mut config);
+        self.feed.publish(Update {
+            file: file.display().to_string(),
+            version,
+            loaded_at: timestamp::utc(event.at),
+            config: config.to_string(),
+            patch: event.data.get("patch").map(Value::to_string),
+        });
+        Ok(())
+    }
+}
+
+/// A `WatchConfig` call: sends messages until the client goes away, or
+/// `UNAVAILABLE` once `closing` turns true
+async fn watch_config(
+    feed: ConfigFeed,
+    request: WatchConfigRequest,
+    out: mpsc::Sender<Result<Config, Status>>,
+    mut closing: watch::Receiver<bool>,
+) {
+    let mut updates = feed.sender.subscribe();
+    // Version sent per file: what the client has
+    let mut sent: HashMap<String, u64> = HashMap::new();
+    let wanted = |update: &Update, sent: &HashMap<String, u64>| {
+        (request.file.is_empty() || request.file == update.file)
+            && sent
+                .get(&update.file)
+                .is_none_or(|&version| version < update.version)
+    };
+    loop {
+        // Subscribed first: a load in between is in both, and sent once
+        for update in feed.snapshot() {
+            if wanted(&update, &sent) {
+                sent.insert(update.file.clone(), update.version);
+                if out.send(Ok(config(&update, false))).await.is_err() {
+                    return;
+                }
+            }
+        }
+        loop {
+            let update = tokio::select! {
+                update = updates.recv() => update,
+                _ = closed(&mut closing) => {
+                    let _ = out.send(Err(Status::unavailable("the watch is over"))).await;
+                    return;
+                }
+                // The client went away
+                _ = out.closed() => return,
+            };
+            let update = match update {
+                Ok(update) => update,
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::debug!(missed, "WatchConfig lagged, resending the latest");
+                    break;
+                }
+                Err(RecvError::Closed) => return,
+            };
+            if !wanted(&update, &sent) {
+                continue;
+            }
+            // A patch only applies to the version before
+            let follows = sent
+                .get(&update.file)
+                .is_some_and(|&version| version + 1 == update.version);
+            sent.insert(update.file.clone(), update.version);
+            let message = config(&update, request.patches && follows);
+            if out.send(Ok(message)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Returns once `closing` turns true, or its sender is gone
+async fn closed(closing: &mut watch::Receiver<bool>) {
+    let _ = closing.wait_for(|closing| *closing).await;
+}
+
+/// The `ConfigWatcher` service over a feed
+#[derive(Debug, Clone)]
+struct Service {
+    feed: ConfigFeed,
+    closing: watch::Receiver<bool>,
+}
+
+#[tonic::async_trait]
+impl ConfigWatcher for Service {
+    async fn get_config(
+        &self,
+        request: Request<GetConfigRequest>,
+    ) -> Result<Response<Config>, Status> {
+        self.feed.get_config(request.get_ref()).map(Response::new)
+    }
+
+    type WatchConfigStream = Pin<Box<dyn Stream<Item = Result<Config, Status>> + Send>>;
+
+    async fn watch_config(
+        &self,
+        request: Request<WatchConfigRequest>,
+    ) -> Result<Response<Self::WatchConfigStream>, Status> {
+        let (out, messages) = mpsc::channel(CALL_QUEUE);
+        tokio::spawn(watch_config(
+            self.feed.clone(),
+            request.into_inner(),
+            out,
+            self.closing.clone(),
+        ));
+        let stream = futures::stream::unfold(messages, |mut messages| async move {
+            messages.recv().await.map(|message| (message, messages))
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+/// The running server; dropping it stops it at once
+#[derive(Debug)]
+pub struct GrpcServer {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+    closing: watch::Sender<bool>,
+}
+
+impl GrpcServer {
+    /// The bound address, useful with port 0
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Ends the streams, then stops the server, waiting at most
+    /// `CLOSE_TIMEOUT` for the connections to close
+    pub async fn shutdown(mut self) {
+        self.closing.send_replace(true);
+        let _ = tokio::time::timeout(CLOSE_TIMEOUT, &mut self.task).await;
+    }
+}
+
+impl Drop for GrpcServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Serves `feed` on `addr` until the returned `GrpcServer` is shut down
+/// or dropped
+pub async fn serve(addr: SocketAddr, feed: ConfigFeed) -> io::Result<GrpcServer> {
+    let listener = TcpListener::bind(addr).await?;
+    let addr = listener.local_addr()?;
+    let (closing, mut closing_receiver) = watch::channel(false);
+    let service = ConfigWatcherServer::new(Service {
+        feed,
+        closing: closing_receiver.clone(),
+    });
+    let task = tokio::spawn(async move {
+        let shutdown = async move { closed(&mut closing_receiver).await };
+        let served = tonic::transport::Server::builder()
+            .add_service(service)
+            .serve_with_incoming_shutdown(TcpIncoming::from(listener), shutdown)
+            .await;
+        if let Err(e) = served {
+            tracing::warn!(error = %e, "gRPC server stopped");
+        }
+    });
+    Ok(GrpcServer {
+        addr,
+        task,
+        closing,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proto::config_watcher_client::ConfigWatcherClient;
+    use tonic::Code;
+
+    fn update(file: &str, version: u64, patch: Option<&str>) -> Update {
+        Update {
+            file: file.to_string(),
+            version,
+            loaded_at: "2026-01-01T00:00:00Z".to_string(),
+            config: format!(r#"{{"version":"{version}.0.0"}}"#),
+            patch: patch.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_get_config() {
+        let feed = ConfigFeed::new();
+        let request = |file: &str| GetConfigRequest {
+            file: file.to_string(),
+        };
+        assert_eq!(
+            feed.get_config(&request("")).unwrap_err().code(),
+            Code::Unavailable
+        );
+
+        feed.publish(update("a.json", 1, None));
+        feed.publish(update("a.json", 2, Some("[]")));
+        // Older versions are ignored
+        feed.publish(update("a.json", 1, None));
+        let config = feed.get_config(&request("")).unwrap();
+        assert_eq!(config.version, 2);
+        assert_eq!(
+            config.payload,
+            Some(Payload::Config(br#"{"version":"2.0.0"}"#.to_vec()))
+        );
+
+        feed.publish(update("b.json", 1, None));
+        assert_eq!(
+            feed.get_config(&request("")).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(feed.get_config(&request("b.json")).unwrap().version, 1);
+        assert_eq!(
+            feed.get_config(&request("c.json")).unwrap_err().code(),
+            Code::NotFound
+        );
+    }
+
+    /// The versions and payload kinds a `WatchConfig` call sends
+    async fn received(
+        messages: &mut mpsc::Receiver<Result<Config, Status>>,
+        n: usize,
+    ) -> Vec<(u64, bool)> {
+        let mut received = Vec::new();
+        for _ in 0..n {
+            let config = messages.recv().await.unwrap().unwrap();
+            let patch = matches!(config.payload, Some(Payload::Patch(_)));
+            received.push((config.version, patch));
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn test_watch_patches_then_catches_up_after_lagging() {
+        let feed = ConfigFeed::new();
+        feed.publish(update("a.json", 1, None));
+        feed.publish(update("b.json", 1, None));
+        let (out, mut messages) = mpsc::channel(1);
+        let request = WatchConfigRequest {
+            file: "a.json".to_string(),
+            patches: true,
+        };
+        let (_closing, closed) = watch::channel(false);
+        tokio::spawn(watch_config(feed.clone(), request, out, closed));
+
+        // The snapshot of the file asked for, then patches
+        assert_eq!(received(&mut messages, 1).await, [(1, false)]);
+        feed.publish(update("b.json", 2, Some("[]")));
+        feed.publish(update("a.json", 2, Some("[]")));
+        assert_eq!(received(&mut messages, 1).await, [(2, true)]);
+
+        // Far more than the stream buffers while nobody reads
+        for version in 3..3 + STREAM_BUFFER as u64 * 2 {
+            feed.publish(update("a.json", version, Some("[]")));
+        }
+        let last = 2 + STREAM_BUFFER as u64 * 2;
+        let mut versions = Vec::new();
+        while versions.last().is_none_or(|&(version, _)| version != last) {
+            versions.extend(received(&mut messages, 1).await);
+        }
+        // Whole again after the gap, then only what follows
+        let resync = versions.iter().position(|&(_, patch)| !patch).unwrap();
+        assert!(versions[resync].0 > 3, "{versions:?}");
+        assert!(versions[resync + 1..].iter().all(|&(_, patch)| patch));
+        assert!(versions.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[tokio::test]
+    async fn test_generated_client_until_shutdown() {
+        let feed = ConfigFeed::new();
+        feed.publish(update("a.json", 1, None));
+        let server = serve("127.0.0.1:0".parse().unwrap(), feed.clone())
+            .await
+            .unwrap();
+        let mut client = ConfigWatcherClient::connect(format!("http://{}", server.addr()))
+            .await
+            .unwrap();
+
+        let config = client
+            .get_config(GetConfigRequest::default())
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!((config.file.as_str(), config.version), ("a.json", 1));
+        let status = client
+            .get_config(GetConfigRequest {
+                file: "c.json".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+
+        let mut stream = client
+            .watch_config(WatchConfigRequest {
+                file: String::new(),
+                patches: true,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(stream.message().await.unwrap().unwrap().version, 1);
+        feed.publish(update("a.json", 2, Some("[]")));
+        let patch = stream.message().await.unwrap().unwrap();
+        assert_eq!(patch.version, 2);
+        assert_eq!(patch.payload, Some(Payload::Patch(b"[]".to_vec())));
+
+        // The stream ends with UNAVAILABLE, then the server stops
+        let shutdown = tokio::spawn(server.shutdown());
+        let status = stream.message().await.unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+        tokio::time::timeout(CLOSE_TIMEOUT * 2, shutdown)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -0,0 +1,454 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`tokio::process::Command`**: Runs the command without blocking the watch
+  loop; `kill_on_drop` kills it when the timeout drops the run
+- **`impl Notifier`**: `--on-change` is one of the dispatcher's notifiers,
+  one per watched file
+- **`tokio::join!`**: Feeds stdin while the output is read, so a large
+  document cannot fill a pipe both sides wait on
+
+**Design decisions**:
+- `--on-change` goes through the shell (`sh -c`, `cmd /C` on Windows), so
+  pipes and `&&` work as typed
+- It runs after every reload that changes the configuration, not after the
+  initial load nor after a rewrite with identical content
+- The command learns about the reload from its environment (CONFIG_PATH,
+  CONFIG_VERSION, APP_NAME, ENVIRONMENT, CHANGED_PATHS) and reads the new
+  configuration, secrets included, as JSON on stdin
+- Runs of one watched file never overlap: its notifier runs them in turn,
+  with a queue of one. Reloads during a run collapse into one pending run,
+  with the latest configuration
+- The outcome is an event like any other; a failure or a timeout is a
+  warning, never a reason to stop watching
+
+******************************************************************************/
+
+use crate::cli::EventKind;
+use crate::config::AppConfig;
+use crate::diff::Change;
+use crate::notify::{ConfigEvent, Notifier, NotifierOptions};
+use crate::output::{Emitter, Event};
+use anyhow::bail;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::field::Empty;
+use tracing::{Instrument, debug_span};
+
+/// Default `--on-change-timeout`
+pub const DEFAULT_TIMEOUT: &str = "30s";
+
+/// A shell command run after every reload that changes the configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnChange {
+    command: String,
+    timeout: Duration,
+}
+
+/// What the command is told about one reload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trigger {
+    pub version: u64,
+    pub app_name: String,
+    pub environment: String,
+    /// Paths of the changed fields, e.g. `server.port`
+    pub changed_paths: Vec<String>,
+    /// The new configuration as JSON
+    pub config: String,
+}
+
+/// How a run ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookStatus {
+    /// Exited with this code; 0 is a success
+    Exited(i32),
+    /// Killed by a signal
+    Signaled,
+    /// Still running after this long, then killed
+    TimedOut(Duration),
+    /// Could not be started
+    SpawnFailed(String),
+}
+
+/// The result of one run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookOutcome {
+    /// Configuration version the run was for
+    pub version: u64,
+    pub status: HookStatus,
+    pub duration: Duration,
+    /// Standard output then standard error, trimmed
+    pub output: String,
+}
+
+impl Trigger {
+    /// The trigger for `config`, loaded as `version` with `changes`
+    pub fn new(version: u64, config: &AppConfig, changes: &[Change]) -> Self {
+        Self {
+            version,
+            app_name: config.app_name.clone(),
+            environment: config.environment.clone(),
+            changed_paths: changes.iter().map(|change| change.path.clone()).collect(),
+            config: serde_json::to_string(config).unwrap_or_default(),
+        }
+    }
+
+    /// The trigger for a reload event, if it is one
+    pub fn from_event(event: &ConfigEvent) -> Option<Self> {
+        let config = event
+            .config
+            .as_ref()
+            .filter(|_| event.kind == EventKind::Reload)?;
+        let changed_paths = event.data["diff"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|change| change["path"].as_str())
+            .map(str::to_string)
+            .collect();
+        Some(Self {
+            changed_paths,
+            ..Self::new(event.version()?, config, &[])
+        })
+    }
+}
+
+impl HookStatus {
+    /// True for an exit status of 0
+    pub fn is_success(&self) -> bool {
+        *self == HookStatus::Exited(0)
+    }
+
+    /// `ok`, `failed`, `timeout` or `error`, for JSON and spans
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookStatus::Exited(0) => "ok",
+            HookStatus::Exited(_) | HookStatus::Signaled => "failed",
+            HookStatus::TimedOut(_) => "timeout",
+            HookStatus::SpawnFailed(_) => "error",
+        }
+    }
+
+    /// The exit code, when the command exited on its own
+    pub fn code(&self) -> Option<i32> {
+        match *self {
+            HookStatus::Exited(code) => Some(code),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for HookStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookStatus::Exited(0) => write!(f, "succeeded"),
+            HookStatus::Exited(code) => write!(f, "exited with status {code}"),
+            HookStatus::Signaled => write!(f, "was killed by a signal"),
+            HookStatus::TimedOut(limit) => write!(
+                f,
+                "timed out after {} and was killed",
+                humantime::format_duration(*limit)
+            ),
+            HookStatus::SpawnFailed(error) => write!(f, "could not start: {error}"),
+        }
+    }
+}
+
+impl OnChange {
+    /// Runs `command` through the shell, killing it after `timeout`
+    pub fn new(command: impl Into<String>, timeout: Duration) -> Self {
+        Self {
+            command: command.into(),
+            timeout,
+        }
+    }
+
+    /// The command as typed
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Runs the command once for a reload of `file`
+    pub async fn run(&self, file: &Path, trigger: &Trigger) -> HookOutcome {
+        let started = Instant::now();
+        let (status, output) =
+            match tokio::time::timeout(self.timeout, self.execute(file, trigger)).await {
+                Ok(Ok(done)) => done,
+                Ok(Err(e)) => (HookStatus::SpawnFailed(e.to_string()), String::new()),
+                Err(_) => (HookStatus::TimedOut(self.timeout), String::new()),
+            };
+        HookOutcome {
+            version: trigger.version,
+            status,
+            duration: started.elapsed(),
+            output,
+        }
+    }
+
+    async fn execute(&self, file: &Path, trigger: &Trigger) -> io::Result<(HookStatus, String)> {
+        let mut child = shell(&self.command)
+            .env("CONFIG_PATH", file)
+            .env("CONFIG_VERSION", trigger.version.to_string())
+            .env("APP_NAME", &trigger.app_name)
+            .env("ENVIRONMENT", &trigger.environment)
+            .env("CHANGED_PATHS", trigger.changed_paths.join(","))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let feed = async move {
+            // A command that ignores stdin may exit before reading it
+            let _ = stdin.write_all(trigger.config.as_bytes()).await;
+        };
+        let ((), output) = tokio::join!(feed, child.wait_with_output());
+        let output = output?;
+
+        let status = match output.status.code() {
+            Some(code) => HookStatus::Exited(code),
+            None => HookStatus::Signaled,
+        };
+        let text: Vec<_> = [&output.stdout, &output.stderr]
+            .into_iter()
+            .map(|bytes| String::from_utf8_lossy(bytes).trim().to_string())
+            .filter(|text| !text.is_empty())
+            .collect();
+        Ok((status, text.join("\n")))
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// Runs an [`OnChange`] after the reloads of one file, one run at a time
+#[derive(Debug)]
+pub struct OnChangeNotifier {
+    hook: OnChange,
+    file: PathBuf,
+    emitter: Emitter,
+}
+
+impl OnChangeNotifier {
+    /// Runs `hook` for the reloads of `file`, reporting to `emitter`
+    ///
+    /// `emitter` should not dispatch to notifiers itself: the outcome is
+    /// printed, not notified.
+    pub fn new(hook: OnChange, file: impl Into<PathBuf>, emitter: Emitter) -> Self {
+        Self {
+            hook,
+            file: file.into(),
+            emitter,
+        }
+    }
+}
+
+impl Notifier for OnChangeNotifier {
+    fn name(&self) -> &str {
+        "on-change"
+    }
+
+    /// One pending run at most; the command's own timeout comes first
+    fn options(&self) -> NotifierOptions {
+        NotifierOptions {
+            timeout: self.hook.timeout + Duration::from_secs(5),
+            queue: 1,
+            ..NotifierOptions::default()
+        }
+    }
+
+    fn accepts(&self, event: &ConfigEvent) -> bool {
+        event.kind == EventKind::Reload && event.file.as_deref() == Some(&self.file)
+    }
+
+    /// Runs the command in an `on_change` span, a child of the reload's
+    async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+        let Some(trigger) = Trigger::from_event(event) else {
+            return Ok(());
+        };
+        let span = debug_span!(
+            "on_change",
+            version = trigger.version,
+            duration_ms = Empty,
+            outcome = Empty
+        );
+        let outcome = self
+            .hook
+            .run(&self.file, &trigger)
+            .instrument(span.clone())
+            .await;
+        if !span.is_disabled() {
+            span.record("duration_ms", outcome.duration.as_secs_f64() * 1000.0);
+            span.record("outcome", outcome.status.name());
+        }
+        self.emitter.emit(&Event::OnChange {
+            file: &self.file,
+            command: self.hook.command(),
+            outcome: &outcome,
+        });
+        if !outcome.status.is_success() {
+            bail!("'{}' {}", self.hook.command(), outcome.status);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::notify::Notifiers;
+    use serde_json::json;
+    use std::time::SystemTime;
+    use tracing::Span;
+
+    fn trigger(version: u64) -> Trigger {
+        let old = AppConfig::example();
+        let new = AppConfig {
+            version: "2.0.0".to_string(),
+            environment: "staging".to_string(),
+            ..AppConfig::example()
+        };
+        let changes = crate::diff::diff(
+            &serde_json::to_value(&old).unwrap(),
+            &serde_json::to_value(&new).unwrap(),
+        );
+        Trigger::new(version, &new, &changes)
+    }
+
+    /// A reload of `file` to `version`
+    fn reload(file: &str, version: u64) -> ConfigEvent {
+        ConfigEvent {
+            kind: EventKind::Reload,
+            at: SystemTime::now(),
+            file: Some(PathBuf::from(file)),
+            data: json!({ "version": version, "diff": [] }),
+            config: Some(AppConfig::example()),
+            span: Span::none(),
+        }
+    }
+
+    async fn run(command: &str, timeout: Duration) -> HookOutcome {
+        OnChange::new(command, timeout)
+            .run(Path::new("/etc/app.json"), &trigger(7))
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_success_gets_the_env_and_stdin() {
+        let outcome = run(
+            r#"echo "$CONFIG_PATH|$CONFIG_VERSION|$APP_NAME|$ENVIRONMENT|$CHANGED_PATHS"; cat"#,
+            Duration::from_secs(10),
+        )
+        .await;
+        assert_eq!(outcome.status, HookStatus::Exited(0));
+        assert!(outcome.status.is_success());
+        assert_eq!(outcome.version, 7);
+
+        let (env, stdin) = outcome.output.split_once('\n').unwrap();
+        assert_eq!(
+            env,
+            format!(
+                "/etc/app.json|7|{}|staging|version,environment",
+                AppConfig::example().app_name
+            )
+        );
+        let config: AppConfig = serde_json::from_str(stdin).unwrap();
+        assert_eq!(config.version, "2.0.0");
+    }
+
+    #[test]
+    fn test_trigger_from_a_reload_event() {
+        let event = ConfigEvent {
+            data: json!({ "version": 5, "diff": [{ "path": "server.port" }, { "path": "version" }] }),
+            ..reload("app.json", 0)
+        };
+        let trigger = Trigger::from_event(&event).unwrap();
+        assert_eq!(trigger.version, 5);
+        assert_eq!(trigger.changed_paths, ["server.port", "version"]);
+        assert_eq!(trigger.app_name, AppConfig::example().app_name);
+
+        // Only reloads run the command
+        let initial = ConfigEvent {
+            kind: EventKind::Initial,
+            ..event
+        };
+        assert_eq!(Trigger::from_event(&initial), None);
+    }
+
+    #[tokio::test]
+    async fn test_failure_reports_status_and_output() {
+        let outcome = run(
+            "echo partial; echo broken >&2; exit 3",
+            Duration::from_secs(10),
+        )
+        .await;
+        assert_eq!(outcome.status, HookStatus::Exited(3));
+        assert_eq!(outcome.status.name(), "failed");
+        assert_eq!(outcome.status.code(), Some(3));
+        assert_eq!(outcome.output, "partial\nbroken");
+        assert_eq!(outcome.status.to_string(), "exited with status 3");
+    }
+
+    #[tokio::test]
+    async fn test_hanging_command_times_out() {
+        let outcome = run("exec sleep 30", Duration::from_millis(200)).await;
+        assert_eq!(
+            outcome.status,
+            HookStatus::TimedOut(Duration::from_millis(200))
+        );
+        assert_eq!(outcome.status.name(), "timeout");
+        assert!(outcome.duration < Duration::from_secs(5));
+        assert_eq!(
+            outcome.status.to_string(),
+            "timed out after 200ms and was killed"
+        );
+    }
+
+    #[test]
+    fn test_runs_never_overlap_and_one_stays_pending() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("runs.log");
+        let command = format!(
+            "echo start $CONFIG_VERSION >> {0}; sleep 0.3; echo end $CONFIG_VERSION >> {0}",
+            log.display()
+        );
+        let (dispatcher, mut guard) = Notifiers::new()
+            .with(OnChangeNotifier::new(
+                OnChange::new(command, Duration::from_secs(10)),
+                "app.json",
+                Emitter::default().with_verbosity(crate::output::Verbosity::Quiet),
+            ))
+            .start()
+            .unwrap();
+
+        dispatcher.send(reload("app.json", 1));
+        std::thread::sleep(Duration::from_millis(100));
+        // Both arrive during the first run: only the latest one runs
+        dispatcher.send(reload("app.json", 2));
+        dispatcher.send(reload("app.json", 3));
+        // Another file's reloads are not for this command
+        dispatcher.send(reload("other.json", 4));
+        assert!(guard.shutdown(Duration::from_secs(5)));
+
+        assert_eq!(
+            std::fs::read_to_string(&log).unwrap(),
+            "start 1\nend 1\nstart 3\nend 3\n"
+        );
+    }
+}
@@ -0,0 +1,153 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`ureq::Agent`**: One blocking HTTP/1.1 request per delivery, made from
+  an exporter or notifier thread, never from the watch loop
+- **`ureq::Proxy`**: The proxy picked by the `proxy` section for the URL,
+  set on the agent of that request
+
+**Design decisions**:
+- Just enough HTTP to POST JSON to a collector or a webhook receiver: a
+  `Content-Length` body, no redirects followed, and only the status line
+  of the response looked at
+- `http://` and `https://` URLs; TLS is rustls with the Mozilla roots
+  (`webpki-roots`), so nothing depends on the system's certificate store
+- Outbound requests go through the proxy [`ProxyConfig::proxy_for`] picks
+  from the configuration loaded last ([`CurrentProxy`]), and never through
+  the `HTTP_PROXY` variables
+- What a status means (success, retry, give up) is left to the caller
+
+[`ProxyConfig::proxy_for`]: crate::config::ProxyConfig::proxy_for
+[`CurrentProxy`]: crate::proxy::CurrentProxy
+
+******************************************************************************/
+
+use anyhow::{Context, bail};
+use std::io;
+use std::time::Duration;
+use url::Url;
+
+/// The status line of a response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// 0 when the status line could not be read
+    pub status: u16,
+    /// e.g. `HTTP/1.1 503 Service Unavailable`
+    pub line: String,
+}
+
+impl Response {
+    /// True for a 2xx status
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// An `http://` or `https://` URL with a host
+pub fn parse_url(text: &str) -> anyhow::Result<Url> {
+    let url = Url::parse(text).with_context(|| format!("'{text}' is not a URL"))?;
+    match url.scheme() {
+        "http" | "https" if url.host_str().is_some() => Ok(url),
+        "http" | "https" => bail!("'{text}' has no host"),
+        scheme => bail!("'{text}': only http:// and https:// are supported, not {scheme}://"),
+    }
+}
+
+/// One HTTP/1.1 POST of a JSON `body`, with extra `headers`, through
+/// `proxy` if any
+///
+/// `timeout` bounds the whole request: connection, TLS handshake, proxy
+/// negotiation and response.
+pub fn post(
+    url: &Url,
+    headers: &[(String, String)],
+    timeout: Duration,
+    proxy: Option<&str>,
+    body: &str,
+) -> io::Result<Response> {
+    let proxy = proxy
+        .map(ureq::Proxy::new)
+        .transpose()
+        .map_err(io::Error::other)?;
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .proxy(proxy)
+        .timeout_global(Some(timeout))
+        .http_status_as_error(false)
+        .max_redirects(0)
+        .build()
+        .into();
+    let mut request = agent
+        .post(url.as_str())
+        .header("Content-Type", "application/json");
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    let response = request.send(body).map_err(|e| match e {
+        ureq::Error::Io(e) => e,
+        e => io::Error::other(e),
+    })?;
+    let status = response.status();
+    Ok(Response {
+        status: status.as_u16(),
+        line: format!("{:?} {status}", response.version()),
+    })
+}
+
+/// A fake HTTP receiver for the notifiers' tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::mpsc;
+    use url::Url;
+
+    /// A fake receiver answering with `statuses` in turn (then 200), and
+    /// handing over each request's headers and body
+    pub(crate) fn receiver(statuses: &[u16]) -> (Url, mpsc::Receiver<(String, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!(
+            "http://{}/hooks/config",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        let statuses = statuses.to_vec();
+        let (sender, requests) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut statuses = statuses.into_iter();
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let request = read_request(&mut stream);
+                let status = statuses.next().unwrap_or(200);
+                let _ = write!(stream, "HTTP/1.1 {status} X\r\nContent-Length: 0\r\n\r\n");
+                if sender.send(request).is_err() {
+                    break;
+                }
+            }
+        });
+        (url, requests)
+    }
+
+    fn read_request(stream: &mut TcpStream) -> (String, String) {
+        let mut data = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            data.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&data).to_string();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length: usize = head
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+                    .unwrap()
+                    .1
+                    .trim()
+                    .parse()
+                    .unwrap();
+                if body.len() >= length {
+                    return (head.to_string(), body.to_string());
+                }
+            }
+        }
+    }
+}
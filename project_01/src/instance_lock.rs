@@ -0,0 +1,269 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`File::try_lock`**: An exclusive advisory lock without blocking,
+  `flock(2)` on Unix and `LockFileEx` on Windows, from the standard library
+- **`Drop`**: The guard clears the pid when it goes out of scope, on every
+  return path; the lock itself is the open file, so closing it (or the
+  process dying) releases it
+- **`TryLockError::WouldBlock`**: Tells "someone else holds it" apart from
+  an I/O error
+- **`OpenOptionsExt`**: `O_NOFOLLOW` and mode 0600 on Unix, so the open
+  never goes through a planted symlink and the file is nobody else's to
+  write
+
+**Design decisions**:
+- One lock per watched file, named after its canonicalized path: the same
+  file reached through a symlink or a relative path is the same lock. The
+  name is the file name plus a SHA-256 prefix of the path
+- Lock files go to `--lock-dir`, else to `--state-dir`, else to
+  `$XDG_RUNTIME_DIR`, all of them private to the user. Without any of
+  them, they go to a `config-watcher-UID` directory of the system
+  temporary directory, created with mode 0700 and refused if another user
+  owns it or can write to it: in a shared /tmp, a name anyone can guess
+  would let another user squat the lock or redirect its writes
+- The lock file holds the pid of its owner, so the error can say which
+  process to stop. The pid is only informational: the kernel lock decides.
+  A lock file left by a process that died is not locked any more and is
+  simply taken over, its pid overwritten; a live process with a reused pid
+  cannot fool it
+- The file is never deleted: deleting it on shutdown would let a starting
+  instance lock the removed file while a third one creates a new one
+- On Windows the lock also blocks reads, so the error cannot name the pid
+  there
+
+******************************************************************************/
+
+use crate::error::{ConfigError, Result};
+use sha2::{Digest, Sha256};
+use std::fs::{File, TryLockError};
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// An exclusive lock on a watched file, held until dropped
+#[derive(Debug)]
+pub struct InstanceLock {
+    file: File,
+}
+
+impl InstanceLock {
+    /// Locks `config` for this process, with a lock file in `dir`
+    ///
+    /// Fails with `ConfigError::AlreadyRunning` when another process
+    /// holds it.
+    pub fn acquire(config: &Path, dir: &Path) -> Result<Self> {
+        let path = lock_path(config, dir);
+        let write_error = |source| ConfigError::WriteError {
+            path: path.clone(),
+            source,
+        };
+        let mut options = File::options();
+        options.read(true).write(true).create(true).truncate(false);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options
+                .mode(0o600)
+                .custom_flags(libc::O_NOFOLLOW | libc::O_CLOEXEC);
+        }
+        let mut file = options.open(&path).map_err(write_error)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut text = String::new();
+                let pid = file
+                    .read_to_string(&mut text)
+                    .ok()
+                    .and_then(|_| text.trim().parse().ok());
+                return Err(ConfigError::AlreadyRunning {
+                    path: config.to_path_buf(),
+                    lock: path,
+                    pid,
+                });
+            }
+            Err(TryLockError::Error(e)) => return Err(write_error(e)),
+        }
+        // Ours now, whoever wrote the pid before
+        file.set_len(0).map_err(write_error)?;
+        file.rewind().map_err(write_error)?;
+        writeln!(file, "{}", std::process::id()).map_err(write_error)?;
+        Ok(Self { file })
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // Best effort: a stale pid is harmless, the lock is what counts
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+    }
+}
+
+/// The lock file of `config` in `dir`: `config-watcher-NAME-HASH.lock`
+pub fn lock_path(config: &Path, dir: &Path) -> PathBuf {
+    dir.join(format!("{}.lock", file_key(config)))
+}
+
+/// `config-watcher-NAME-HASH`, the same for every path to `config`
+///
+/// Also names the snapshots of `last_good`.
+pub fn file_key(config: &Path) -> String {
+    let canonical = config
+        .canonicalize()
+        .unwrap_or_else(|_| std::path::absolute(config).unwrap_or_else(|_| config.into()));
+    let hash = format!(
+        "{:x}",
+        Sha256::digest(canonical.as_os_str().as_encoded_bytes())
+    );
+    let name = canonical
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    format!("config-watcher-{name}-{}", &hash[..16])
+}
+
+/// Where lock files go without `--lock-dir`: `state_dir`, else
+/// `$XDG_RUNTIME_DIR`, else the user's private directory in the system
+/// temporary directory, created if need be
+pub fn default_dir(state_dir: Option<&Path>) -> io::Result<PathBuf> {
+    if let Some(dir) = state_dir {
+        return Ok(dir.to_path_buf());
+    }
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => Ok(dir.into()),
+        _ => private_temp_dir(),
+    }
+}
+
+/// `config-watcher-UID` in the system temporary directory, mode 0700
+#[cfg(unix)]
+fn private_temp_dir() -> io::Result<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    let uid = rustix::process::getuid().as_raw();
+    let dir = std::env::temp_dir().join(format!("config-watcher-{uid}"));
+    match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    // Made by someone else, or a symlink to somewhere else
+    let metadata = std::fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a directory private to this user", dir.display()),
+        ));
+    }
+    Ok(dir)
+}
+
+/// The system temporary directory, which is the user's own on Windows
+#[cfg(not(unix))]
+fn private_temp_dir() -> io::Result<PathBuf> {
+    Ok(std::env::temp_dir())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_path_follows_the_canonical_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config.json");
+        std::fs::write(&config, "{}").unwrap();
+        let locks = Path::new("/run/locks");
+
+        let path = lock_path(&config, locks);
+        assert_eq!(path.parent(), Some(locks));
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("config-watcher-config.json-"), "{name}");
+        assert!(name.ends_with(".lock"), "{name}");
+
+        let dotted = dir.path().join(".").join("config.json");
+        assert_eq!(lock_path(&dotted, locks), path);
+        let other = dir.path().join("other.json");
+        std::fs::write(&other, "{}").unwrap();
+        assert_ne!(lock_path(&other, locks), path);
+    }
+
+    #[test]
+    fn test_second_lock_fails_until_the_first_is_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config.json");
+        std::fs::write(&config, "{}").unwrap();
+        let path = lock_path(&config, dir.path());
+
+        let first = InstanceLock::acquire(&config, dir.path()).unwrap();
+        let pid = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(pid.trim(), std::process::id().to_string());
+
+        let err = InstanceLock::acquire(&config, dir.path()).unwrap_err();
+        match err {
+            ConfigError::AlreadyRunning { ref lock, pid, .. } => {
+                assert_eq!(*lock, path);
+                if cfg!(unix) {
+                    assert_eq!(pid, Some(std::process::id()));
+                }
+            }
+            ref other => panic!("{other:?}"),
+        }
+        assert_eq!(err.code(), "already_running");
+
+        // Released and cleared on drop, but still there for the next one
+        drop(first);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+        let _second = InstanceLock::acquire(&config, dir.path()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+    }
+
+    #[test]
+    fn test_stale_lock_file_is_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config.json");
+        std::fs::write(&config, "{}").unwrap();
+
+        // Left by a process that died without clearing it
+        let path = lock_path(&config, dir.path());
+        std::fs::write(&path, "999999999\n").unwrap();
+        let _lock = InstanceLock::acquire(&config, dir.path()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_lock_file_is_private_and_never_a_symlink() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config.json");
+        std::fs::write(&config, "{}").unwrap();
+        let path = lock_path(&config, dir.path());
+
+        let lock = InstanceLock::acquire(&config, dir.path()).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        drop(lock);
+
+        // A symlink planted at the lock path is not followed
+        std::fs::remove_file(&path).unwrap();
+        let victim = dir.path().join("victim");
+        std::fs::write(&victim, "precious").unwrap();
+        std::os::unix::fs::symlink(&victim, &path).unwrap();
+        assert!(InstanceLock::acquire(&config, dir.path()).is_err());
+        assert_eq!(std::fs::read_to_string(&victim).unwrap(), "precious");
+    }
+
+    #[test]
+    fn test_default_dir_prefers_the_state_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(default_dir(Some(dir.path())).unwrap(), dir.path());
+        assert!(default_dir(None).unwrap().is_dir());
+    }
+}
@@ -0,0 +1,311 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **A reader thread and an `mpsc` channel**: The blocking reads of stdin
+  run on their own thread, which sends each action to the runtime; the
+  thread is never joined, the process ends with it still reading
+- **`Box<dyn Terminal>`**: The terminal modes of stdin for real, a
+  recorder in tests
+- **`std::panic::set_hook`**: Chained in front of the default hook, so the
+  terminal is back in its modes before the panic message is printed
+- **`Drop`**: `RawMode` restores the terminal on every return path
+
+**Design decisions**:
+- Only when stdin is a terminal: piped, `/dev/null` (--daemonize, a
+  service) or taken by the --confirm prompts, nothing is read and nothing
+  changes. --no-keys opts out
+- Non-canonical without echo, signals kept: each key is read as it is
+  typed, and Ctrl+C still sends SIGINT. Off Unix the console stays line
+  buffered, a key then Enter
+- `r` reloads every file (like SIGHUP), `d` prints a status dump (like
+  SIGUSR1), `p` pauses or resumes the checks, `q` stops like Ctrl+C, a
+  second `q` forces it like a second Ctrl+C, `?` prints the keys; `k`/`j`
+  and the arrows scroll the events of --tui. Other keys are ignored; case
+  does not matter
+- Restored exactly once, whichever comes first: the guard dropped, a
+  panic, or `restore` right before `std::process::exit`, which skips the
+  destructors
+
+******************************************************************************/
+
+use std::io::{self, BufRead, IsTerminal};
+use std::sync::{Arc, Mutex, MutexGuard, Once};
+use tokio::sync::mpsc;
+
+/// What `?` prints
+pub const HELP: &str = "Keys: r reload now, d status dump, p pause/resume, q quit, ? this help";
+
+/// What a key asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Reload,
+    Dump,
+    Pause,
+    Quit,
+    Help,
+    /// Back through the events of --tui
+    ScrollUp,
+    ScrollDown,
+}
+
+impl Action {
+    /// The action of `key`, if it has one
+    pub fn for_key(key: u8) -> Option<Self> {
+        match key.to_ascii_lowercase() {
+            b'r' => Some(Action::Reload),
+            b'd' => Some(Action::Dump),
+            b'p' => Some(Action::Pause),
+            b'q' => Some(Action::Quit),
+            b'?' | b'h' => Some(Action::Help),
+            b'k' => Some(Action::ScrollUp),
+            b'j' => Some(Action::ScrollDown),
+            _ => None,
+        }
+    }
+}
+
+/// Dispatches the action of every key read from `input`, until it ends;
+/// the up and down arrows (`ESC [ A`, `ESC [ B`) scroll
+pub fn read(input: impl BufRead, mut dispatch: impl FnMut(Action)) -> io::Result<()> {
+    // Bytes of an escape sequence read so far
+    let mut escape = 0;
+    for key in input.bytes() {
+        let action = match (escape, key?) {
+            (0 | 1, 0x1b) => {
+                escape = 1;
+                continue;
+            }
+            (1, b'[') => {
+                escape = 2;
+                continue;
+            }
+            (2, b'A') => Some(Action::ScrollUp),
+            (2, b'B') => Some(Action::ScrollDown),
+            (2, _) => None,
+            (_, key) => Action::for_key(key),
+        };
+        escape = 0;
+        if let Some(action) = action {
+            dispatch(action);
+        }
+    }
+    Ok(())
+}
+
+/// Switches a terminal to single-key input and back
+pub trait Terminal: Send {
+    /// Non-canonical mode, without echo
+    fn raw(&mut self) -> io::Result<()>;
+    /// The modes from before `raw`
+    fn restore(&mut self) -> io::Result<()>;
+}
+
+/// A terminal in raw mode, taken out by the first restore
+type Shared = Arc<Mutex<Option<Box<dyn Terminal>>>>;
+
+/// Every terminal in raw mode, for `restore` and the panic hook
+static ACTIVE: Mutex<Vec<Shared>> = Mutex::new(Vec::new());
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A terminal in raw mode until dropped
+pub struct RawMode {
+    terminal: Shared,
+}
+
+impl std::fmt::Debug for RawMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RawMode").finish_non_exhaustive()
+    }
+}
+
+impl RawMode {
+    /// Switches `terminal` to raw mode, restored when the guard is dropped,
+    /// on a panic or by `restore`
+    pub fn enter(mut terminal: Box<dyn Terminal>) -> io::Result<Self> {
+        static HOOK: Once = Once::new();
+        HOOK.call_once(|| {
+            let previous = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                restore();
+                previous(info);
+            }));
+        });
+        terminal.raw()?;
+        let terminal: Shared = Arc::new(Mutex::new(Some(terminal)));
+        lock(&ACTIVE).push(Arc::clone(&terminal));
+        Ok(Self { terminal })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        lock(&ACTIVE).retain(|active| !Arc::ptr_eq(active, &self.terminal));
+        restore_one(&self.terminal);
+    }
+}
+
+/// Puts every terminal in raw mode back in its modes, for the exits that
+/// skip the destructors
+pub fn restore() {
+    let active = lock(&ACTIVE).clone();
+    for terminal in &active {
+        restore_one(terminal);
+    }
+}
+
+fn restore_one(terminal: &Shared) {
+    if let Some(mut terminal) = lock(terminal).take()
+        && let Err(e) = terminal.restore()
+    {
+        tracing::warn!(error = %e, "terminal modes not restored");
+    }
+}
+
+/// Stdin, as a terminal
+#[derive(Debug, Default)]
+pub struct Stdin {
+    #[cfg(unix)]
+    saved: Option<rustix::termios::Termios>,
+}
+
+#[cfg(unix)]
+impl Terminal for Stdin {
+    fn raw(&mut self) -> io::Result<()> {
+        use rustix::termios::{LocalModes, OptionalActions, SpecialCodeIndex};
+
+        let stdin = io::stdin();
+        let saved = rustix::termios::tcgetattr(&stdin)?;
+        let mut raw = saved.clone();
+        raw.local_modes
+            .remove(LocalModes::ICANON | LocalModes::ECHO);
+        raw.special_codes[SpecialCodeIndex::VMIN] = 1;
+        raw.special_codes[SpecialCodeIndex::VTIME] = 0;
+        rustix::termios::tcsetattr(&stdin, OptionalActions::Now, &raw)?;
+        self.saved = Some(saved);
+        Ok(())
+    }
+
+    fn restore(&mut self) -> io::Result<()> {
+        if let Some(saved) = self.saved.take() {
+            let now = rustix::termios::OptionalActions::Now;
+            rustix::termios::tcsetattr(io::stdin(), now, &saved)?;
+        }
+        Ok(())
+    }
+}
+
+/// The console stays line buffered: nothing to switch
+#[cfg(not(unix))]
+impl Terminal for Stdin {
+    fn raw(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn restore(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads the keys typed on stdin, in raw mode until the guard is dropped;
+/// None when stdin is not a terminal
+pub fn listen() -> io::Result<Option<(RawMode, mpsc::UnboundedReceiver<Action>)>> {
+    if !io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    let raw_mode = RawMode::enter(Box::new(Stdin::default()))?;
+    let (sender, actions) = mpsc::unbounded_channel();
+    std::thread::Builder::new()
+        .name("keys".into())
+        .spawn(move || {
+            let sent = |action| {
+                let _ = sender.send(action);
+            };
+            if let Err(e) = read(io::stdin().lock(), sent) {
+                tracing::warn!(error = %e, "no more keys read from the terminal");
+            }
+        })?;
+    Ok(Some((raw_mode, actions)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records the calls made to it
+    struct Recorder(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Terminal for Recorder {
+        fn raw(&mut self) -> io::Result<()> {
+            lock(&self.0).push("raw");
+            Ok(())
+        }
+
+        fn restore(&mut self) -> io::Result<()> {
+            lock(&self.0).push("restore");
+            Ok(())
+        }
+    }
+
+    fn recorder() -> (Box<dyn Terminal>, Arc<Mutex<Vec<&'static str>>>) {
+        let calls = Arc::default();
+        (Box::new(Recorder(Arc::clone(&calls))), calls)
+    }
+
+    #[test]
+    fn test_keys_dispatch_their_actions() {
+        let mut actions = Vec::new();
+        read(&b"rDp?x\nq q\x1b[Aj\x1b[C\x1b[Bk"[..], |action| {
+            actions.push(action)
+        })
+        .unwrap();
+        assert_eq!(
+            actions,
+            [
+                Action::Reload,
+                Action::Dump,
+                Action::Pause,
+                Action::Help,
+                Action::Quit,
+                Action::Quit,
+                Action::ScrollUp,
+                Action::ScrollDown,
+                Action::ScrollDown,
+                Action::ScrollUp
+            ]
+        );
+    }
+
+    #[test]
+    fn test_terminal_restored_when_dropped() {
+        let (terminal, calls) = recorder();
+        let raw_mode = RawMode::enter(terminal).unwrap();
+        assert_eq!(*lock(&calls), ["raw"]);
+        drop(raw_mode);
+        assert_eq!(*lock(&calls), ["raw", "restore"]);
+    }
+
+    #[test]
+    fn test_terminal_restored_once_before_an_exit() {
+        let (terminal, calls) = recorder();
+        let raw_mode = RawMode::enter(terminal).unwrap();
+        // As before `std::process::exit`, then dropped anyway
+        restore();
+        assert_eq!(*lock(&calls), ["raw", "restore"]);
+        drop(raw_mode);
+        assert_eq!(*lock(&calls), ["raw", "restore"]);
+    }
+
+    #[test]
+    fn test_terminal_restored_on_a_panic() {
+        let (terminal, calls) = recorder();
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+            let _raw_mode = RawMode::enter(terminal).unwrap();
+            panic!("while reading keys");
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(*lock(&calls), ["raw", "restore"]);
+    }
+}
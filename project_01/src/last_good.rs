@@ -0,0 +1,188 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`serde` round trip**: `AppConfig` serializes to the JSON it parses
+  from, so a snapshot is just the configuration plus a few facts about it
+- **`fs_util::write_atomic_with_mode`**: A crash mid-write leaves the
+  previous snapshot, never half of one
+- **`anyhow::Context`**: A snapshot that cannot be used is only ever
+  logged, so it needs no `ConfigError` variant or code
+
+**Design decisions**:
+- One snapshot per watched file in `--state-dir`, named like its lock file
+  (`instance_lock::file_key`) with a `.last-good.json` suffix, rewritten
+  after every accepted load of the file (never of a stand-in)
+- The snapshot holds the effective configuration, overrides applied and
+  `age:` values decrypted, so it is written with mode 0600
+- On restore the configuration is validated again (`AppConfig::check`):
+  a snapshot from an older version whose rules have changed since is as
+  unusable as a corrupted one. Neither is deleted, the next accepted load
+  replaces it
+- The modification time and SHA-256 of the source are informational: the
+  file is read again right after its load to hash it, so a write in
+  between makes the hash that of the newer content
+
+******************************************************************************/
+
+use crate::config::AppConfig;
+use crate::fs_util;
+use crate::instance_lock;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The last configuration a watched file loaded, as saved on disk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// The configuration version it had in its run
+    pub version: u64,
+    /// The watched file it came from
+    pub source: PathBuf,
+    /// Modification time of the source when it loaded (RFC 3339)
+    #[serde(default)]
+    pub modified: Option<String>,
+    /// SHA-256 of the source, hex
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// When the snapshot was saved (RFC 3339)
+    pub saved_at: String,
+    pub config: AppConfig,
+}
+
+impl Snapshot {
+    /// A snapshot of `config`, loaded from `source` with this `version`
+    pub fn new(
+        source: &Path,
+        version: u64,
+        modified: Option<SystemTime>,
+        config: AppConfig,
+    ) -> Self {
+        let sha256 = std::fs::read(source)
+            .ok()
+            .map(|bytes| format!("{:x}", Sha256::digest(&bytes)));
+        Self {
+            version,
+            source: source.to_path_buf(),
+            modified: modified.map(timestamp),
+            sha256,
+            saved_at: timestamp(SystemTime::now()),
+            config,
+        }
+    }
+
+    /// When it was saved
+    pub fn saved_at(&self) -> Result<SystemTime, humantime::TimestampError> {
+        humantime::parse_rfc3339(&self.saved_at)
+    }
+}
+
+/// Where the snapshot of `config` goes in `dir`
+pub fn path(config: &Path, dir: &Path) -> PathBuf {
+    dir.join(format!(
+        "{}.last-good.json",
+        instance_lock::file_key(config)
+    ))
+}
+
+/// Saves `snapshot` in `dir`, replacing the previous one
+pub fn save(dir: &Path, snapshot: &Snapshot) -> crate::error::Result<()> {
+    let json = serde_json::to_vec_pretty(snapshot)?;
+    fs_util::write_atomic_with_mode(&path(&snapshot.source, dir), &json, 0o600)
+}
+
+/// The snapshot of `config` in `dir`, `None` when there is none
+///
+/// Fails when it cannot be read, parsed or validated.
+pub fn load(config: &Path, dir: &Path) -> anyhow::Result<Option<Snapshot>> {
+    let path = path(config, dir);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Cannot read snapshot {}", path.display()));
+        }
+    };
+    let snapshot: Snapshot = serde_json::from_str(&text)
+        .with_context(|| format!("Corrupted snapshot {}", path.display()))?;
+    snapshot
+        .saved_at()
+        .with_context(|| format!("Corrupted snapshot {}", path.display()))?;
+    snapshot
+        .config
+        .check()
+        .into_result()
+        .with_context(|| format!("Snapshot {} is no longer valid", path.display()))?;
+    Ok(Some(snapshot))
+}
+
+fn timestamp(at: SystemTime) -> String {
+    humantime::format_rfc3339_millis(at).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(app_name: &str) -> AppConfig {
+        serde_json::from_str(&format!(
+            r#"{{ "app_name": "{app_name}", "version": "1.0.0", "server": {{ "host": "h", "port": 80, "keep_alive": "75s" }} }}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_save_then_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("app.json");
+        std::fs::write(&source, "{}").unwrap();
+
+        assert_eq!(load(&source, dir.path()).unwrap(), None);
+        let snapshot = Snapshot::new(&source, 3, Some(SystemTime::UNIX_EPOCH), config("A"));
+        save(dir.path(), &snapshot).unwrap();
+        assert_eq!(load(&source, dir.path()).unwrap(), Some(snapshot.clone()));
+        assert_eq!(
+            snapshot.modified.as_deref(),
+            Some("1970-01-01T00:00:00.000Z")
+        );
+        assert_eq!(
+            snapshot.sha256.as_deref(),
+            Some(format!("{:x}", Sha256::digest(b"{}")).as_str())
+        );
+        assert!(snapshot.saved_at().is_ok());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(path(&source, dir.path()))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // Replaced by the next one
+        save(dir.path(), &Snapshot::new(&source, 4, None, config("B"))).unwrap();
+        let loaded = load(&source, dir.path()).unwrap().unwrap();
+        assert_eq!((loaded.version, loaded.config.app_name.as_str()), (4, "B"));
+    }
+
+    #[test]
+    fn test_unusable_snapshots_fail_to_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("app.json");
+        let snapshot = path(&source, dir.path());
+
+        std::fs::write(&snapshot, "{ truncated").unwrap();
+        let err = load(&source, dir.path()).unwrap_err();
+        assert!(format!("{err:#}").contains("Corrupted snapshot"), "{err:#}");
+
+        // Valid JSON, but no longer a valid configuration
+        let mut invalid = Snapshot::new(&source, 1, None, config("A"));
+        invalid.config.app_name.clear();
+        std::fs::write(&snapshot, serde_json::to_string(&invalid).unwrap()).unwrap();
+        let err = load(&source, dir.path()).unwrap_err();
+        assert!(format!("{err:#}").contains("no longer valid"), "{err:#}");
+    }
+}
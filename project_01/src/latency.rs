@@ -0,0 +1,121 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`VecDeque`**: A ring of the latest samples, oldest dropped first
+- **`SystemTime::duration_since`**: Fails when the mtime is later than
+  "now", which is how clock skew shows up
+
+**Design decisions**:
+- Latency is measured from the file's mtime to the moment the reload was
+  applied: it covers the wait for the next check plus the reload itself,
+  which is what `--interval` tuning needs
+- `min` and `avg` cover every sample since startup; `p95` the latest
+  [`WINDOW`] ones, so memory stays bounded on a long-running watcher
+- An mtime in the future (clock skew, a copied file) counts as 0 rather
+  than being dropped, so the count still matches the reloads
+
+******************************************************************************/
+
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+/// Samples kept for the percentile
+pub const WINDOW: usize = 1000;
+
+/// How long changes took to be applied after they were written
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyStats {
+    recent: VecDeque<Duration>,
+    count: u64,
+    sum: Duration,
+    min: Option<Duration>,
+}
+
+impl LatencyStats {
+    /// Adds one sample
+    pub fn record(&mut self, latency: Duration) {
+        if self.recent.len() == WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(latency);
+        self.count += 1;
+        self.sum += latency;
+        self.min = Some(self.min.map_or(latency, |min| min.min(latency)));
+    }
+
+    /// Samples recorded since startup
+    #[cfg(test)]
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.min
+    }
+
+    pub fn avg(&self) -> Option<Duration> {
+        u32::try_from(self.count)
+            .ok()
+            .filter(|&count| count > 0)
+            .map(|count| self.sum / count)
+    }
+
+    /// 95th percentile (nearest rank) of the latest samples
+    pub fn p95(&self) -> Option<Duration> {
+        let mut sorted: Vec<_> = self.recent.iter().copied().collect();
+        sorted.sort();
+        let rank = (sorted.len() * 95).div_ceil(100);
+        sorted.get(rank.checked_sub(1)?).copied()
+    }
+}
+
+/// Time from `modified` to `applied`; `None` when `modified` is later
+pub fn between(modified: SystemTime, applied: SystemTime) -> Option<Duration> {
+    applied.duration_since(modified).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_avg_p95() {
+        let mut stats = LatencyStats::default();
+        assert_eq!((stats.min(), stats.avg(), stats.p95()), (None, None, None));
+
+        for ms in (1..=100).rev() {
+            stats.record(Duration::from_millis(ms * 10));
+        }
+        assert_eq!(stats.count(), 100);
+        assert_eq!(stats.min(), Some(Duration::from_millis(10)));
+        assert_eq!(stats.avg(), Some(Duration::from_millis(505)));
+        assert_eq!(stats.p95(), Some(Duration::from_millis(950)));
+
+        let mut one = LatencyStats::default();
+        one.record(Duration::from_secs(2));
+        assert_eq!(one.p95(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_p95_only_looks_at_the_window() {
+        let mut stats = LatencyStats::default();
+        stats.record(Duration::from_secs(60));
+        for _ in 0..WINDOW {
+            stats.record(Duration::from_secs(1));
+        }
+        assert_eq!(stats.p95(), Some(Duration::from_secs(1)));
+        assert_eq!(stats.min(), Some(Duration::from_secs(1)));
+        assert!(stats.avg().unwrap() > Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_between_controlled_mtimes() {
+        let applied = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(
+            between(applied - Duration::from_millis(1700), applied),
+            Some(Duration::from_millis(1700))
+        );
+        assert_eq!(between(applied, applied), Some(Duration::ZERO));
+        assert_eq!(between(applied + Duration::from_secs(5), applied), None);
+    }
+}
@@ -1,4 +1,233 @@
-pub mod cli;
+//! Watches a JSON configuration file, validates every version of it, and
+//! tells the rest of the program about the ones that pass
+//!
+//! The `config-watcher` binary is built on this library; a program can
+//! embed the same [`ConfigWatcher`] and observe its [`ConfigEvent`]s. The
+//! types most programs need are re-exported here and in [`prelude`].
+//!
+//! Loading a file once, as `config-watcher --once` does:
+//!
+//! ```
+//! use config_watcher::prelude::*;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let file = tempfile::NamedTempFile::new()?;
+//! std::fs::write(file.path(), r#"{ "app_name": "demo", "version": "1.0.0" }"#)?;
+//!
+//! let quiet = Emitter::new(OutputFormat::Json).with_verbosity(Verbosity::Quiet);
+//! let mut watcher = ConfigWatcher::new(file.path(), 1).with_emitter(quiet);
+//! assert!(watcher.load_initial().await?);
+//! assert_eq!(watcher.last_valid_config().unwrap().app_name, "demo");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! `examples/` has a program that subscribes to the events of a running
+//! watcher, and one that validates a file and prints its findings.
+
+#![deny(missing_docs)]
+// The wasm32 build uses a slice of the pure core, through `wasm`; the rest
+// of it is there for the watcher, left out of that build
+#![cfg_attr(target_arch = "wasm32", allow(dead_code))]
+
+#[cfg(not(target_arch = "wasm32"))]
+mod app;
+
+pub(crate) mod acl;
+#[cfg(all(not(target_arch = "wasm32"), feature = "age"))]
+pub(crate) mod age;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod annotations;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod audit;
+pub(crate) mod auth;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod autocommit;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod checksum;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod cli;
+/// The one-shot subcommands; `validate` is the check of
+/// `config-watcher validate`, for programs and fuzzers
+#[cfg(not(target_arch = "wasm32"))]
+pub mod commands;
+/// The configuration types and their business rules
 pub mod config;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod confirm;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod daemon;
+#[cfg(all(not(target_arch = "wasm32"), feature = "desktop-notify"))]
+pub(crate) mod desktop;
+/// Differences between two configurations, field by field
+pub mod diff;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod discovery;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod dump;
+pub(crate) mod duration;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod env_config;
+/// [`ConfigError`], and its `Result` alias
 pub mod error;
+#[cfg(all(not(target_arch = "wasm32"), feature = "event-db"))]
+pub(crate) mod event_db;
+/// The exit statuses of the binary, and the one an error maps to
+#[cfg(not(target_arch = "wasm32"))]
+pub mod exit;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod export_env;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod external_schema;
+pub(crate) mod features;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod fs_util;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod git;
+/// GetConfig and WatchConfig over gRPC; `proto` has the messages and a
+/// client generated from `proto/config_watcher.proto`
+#[cfg(all(not(target_arch = "wasm32"), feature = "grpc"))]
+pub mod grpc;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod hook;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod http_client;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod instance_lock;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod keys;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod last_good;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod latency;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod lint;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod listener;
+pub(crate) mod listing;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod log_file;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod logging;
+pub(crate) mod messaging;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod metrics;
+#[cfg(all(not(target_arch = "wasm32"), feature = "mqtt"))]
+pub(crate) mod mqtt;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod normalized;
+/// [`ConfigEvent`]s, and the [`Notifier`]s that act on them
+#[cfg(not(target_arch = "wasm32"))]
+pub mod notify;
+#[cfg(all(not(target_arch = "wasm32"), feature = "otlp"))]
+pub(crate) mod otlp;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod output;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod overrides;
+pub(crate) mod patch;
+pub(crate) mod path;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod permissions;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod pid_file;
+/// The load pipeline short of any I/O: parse, validate, normalize
+pub mod pipeline;
+/// The types most programs embedding the watcher need, in one `use`
+#[cfg(not(target_arch = "wasm32"))]
+pub mod prelude;
+// Read by the health endpoints and the systemd watchdog only
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg_attr(
+    not(any(feature = "http-server", all(unix, feature = "systemd"))),
+    allow(dead_code)
+)]
+pub(crate) mod probes;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod provenance;
+pub(crate) mod proxy;
+#[cfg(unix)]
+pub(crate) mod push;
+pub(crate) mod redact;
+#[cfg(all(not(target_arch = "wasm32"), feature = "redis"))]
+pub(crate) mod redis;
+/// Machine-readable reports of load errors and their findings
+pub mod report;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod sandbox;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod sarif;
+pub(crate) mod schema;
+#[cfg(all(not(target_arch = "wasm32"), feature = "http-server"))]
+pub(crate) mod server;
+// Only the Windows entry point creates the controller; elsewhere the state
+// machine is there for its tests
+#[cfg(all(not(target_arch = "wasm32"), feature = "windows-service"))]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) mod service;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod settings;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod shutdown;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod signal_pid;
+/// Detached Ed25519 and minisign signatures of configuration files
+#[cfg(not(target_arch = "wasm32"))]
+pub mod signature;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod slack;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod status;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod style;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod supervisor;
+#[cfg(all(unix, feature = "system-log"))]
+pub(crate) mod system_log;
+#[cfg(all(unix, feature = "systemd"))]
+pub(crate) mod systemd;
+/// proptest strategies and `Arbitrary` for the configuration types, for
+/// the tests of programs built on them
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod timestamp;
+#[cfg(all(not(target_arch = "wasm32"), any(feature = "mqtt", feature = "redis")))]
+pub(crate) mod tls;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod tui;
+/// Findings of a validation, and their [`Severity`]
+pub mod validation;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod versions;
+/// `validateJson` and `diffJson` for JavaScript
+#[cfg(feature = "wasm")]
+pub mod wasm;
+/// [`ConfigWatcher`], the watch loop and its controls
+#[cfg(not(target_arch = "wasm32"))]
 pub mod watcher;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod webhook;
+#[cfg(all(not(target_arch = "wasm32"), feature = "http-server"))]
+pub(crate) mod websocket;
+
+pub use config::{AppConfig, DatabaseConfig, ServerConfig};
+pub use error::ConfigError;
+pub use validation::{Severity, ValidationReport};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use cli::{EventKind, OutputFormat};
+#[cfg(not(target_arch = "wasm32"))]
+pub use notify::{ConfigEvent, Dispatcher, DispatcherGuard, Notifier, NotifierOptions, Notifiers};
+#[cfg(not(target_arch = "wasm32"))]
+pub use output::{Emitter, Verbosity};
+#[cfg(not(target_arch = "wasm32"))]
+pub use watcher::ConfigWatcher;
+
+/// Runs the `config-watcher` command line on the process's arguments, as
+/// the binary does, and returns its exit status
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run() -> std::process::ExitCode {
+    app::main()
+}
@@ -0,0 +1,169 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`hyper::server::conn::http1`**: hyper parses the requests and writes
+  the responses of each connection: keep-alive, chunked bodies, upgrades
+- **`service_fn`**: A plain async function of the request is the service;
+  it cannot fail, every problem being an answer (404, 413...)
+- **`TokioIo`**: Adapts a tokio `TcpStream` to hyper's I/O traits
+- **`Connection::graceful_shutdown`**: On `closing`, a connection finishes
+  the request in flight, then closes instead of waiting for the next one
+
+**Design decisions**:
+- One accept loop for every listener (`--metrics-addr`, `--http-addr`,
+  `--health-addr`), each connection on a task of its own
+- A client has `READ_TIMEOUT` to send a request head, so a slow or idle
+  one cannot hold a connection task forever; handlers that read a body
+  bound it the same way
+- Answers are whole bodies (`Full<Bytes>`), JSON or text, built by
+  `respond`
+
+******************************************************************************/
+
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{CONTENT_TYPE, HeaderValue};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::{TokioIo, TokioTimer};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Time a client has to send a request head, or a handler its body
+pub const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The body of every answer
+pub type Body = Full<Bytes>;
+
+/// Serves `handler` on `addr`, until the returned task is aborted; the
+/// connections close gracefully once `closing` changes, if given
+///
+/// Returns the bound address, useful with port 0.
+pub async fn listen<H, F>(
+    addr: SocketAddr,
+    closing: Option<watch::Receiver<bool>>,
+    handler: H,
+) -> std::io::Result<(SocketAddr, JoinHandle<()>)>
+where
+    H: Fn(Request<Incoming>) -> F + Clone + Send + Sync + 'static,
+    F: Future<Output = Response<Body>> + Send + 'static,
+{
+    let listener = TcpListener::bind(addr).await?;
+    let bound = listener.local_addr()?;
+    let task = tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let handler = handler.clone();
+            let closing = closing.clone();
+            tokio::spawn(async move {
+                if let Err(e) = connection(stream, closing, handler).await {
+                    tracing::debug!(error = %e, "http connection failed");
+                }
+            });
+        }
+    });
+    Ok((bound, task))
+}
+
+/// Answers the requests of one connection
+async fn connection<H, F>(
+    stream: tokio::net::TcpStream,
+    closing: Option<watch::Receiver<bool>>,
+    handler: H,
+) -> hyper::Result<()>
+where
+    H: Fn(Request<Incoming>) -> F + Send + Sync + 'static,
+    F: Future<Output = Response<Body>> + Send + 'static,
+{
+    let service = service_fn(move |request| {
+        let response = handler(request);
+        async move { Ok::<_, Infallible>(response.await) }
+    });
+    let connection = http1::Builder::new()
+        .timer(TokioTimer::new())
+        .header_read_timeout(READ_TIMEOUT)
+        .serve_connection(TokioIo::new(stream), service)
+        .with_upgrades();
+    let mut connection = std::pin::pin!(connection);
+    let Some(mut closing) = closing else {
+        return connection.await;
+    };
+    let closed = async move {
+        let _ = closing.wait_for(|closing| *closing).await;
+    };
+    tokio::select! {
+        result = connection.as_mut() => result,
+        () = closed => {
+            connection.as_mut().graceful_shutdown();
+            connection.await
+        }
+    }
+}
+
+/// A whole answer with `status`, of type `content_type`
+pub fn respond(status: StatusCode, content_type: &'static str, body: String) -> Response<Body> {
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    async fn echo_path(request: Request<Incoming>) -> Response<Body> {
+        respond(StatusCode::OK, "text/plain", request.uri().path().into())
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_then_graceful_close() {
+        let (closing, closing_receiver) = watch::channel(false);
+        let (addr, _task) = listen(
+            "127.0.0.1:0".parse().unwrap(),
+            Some(closing_receiver),
+            echo_path,
+        )
+        .await
+        .unwrap();
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0; 1024];
+        for path in ["/a", "/b"] {
+            let request = format!("GET {path} HTTP/1.1\r\nHost: test\r\n\r\n");
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let n = stream.read(&mut buf).await.unwrap();
+            let response = String::from_utf8_lossy(&buf[..n]);
+            assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+            assert!(response.ends_with(path), "{response}");
+        }
+
+        // The idle connection is closed
+        closing.send_replace(true);
+        assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_client_times_out() {
+        let (addr, _task) = listen("127.0.0.1:0".parse().unwrap(), None, echo_path)
+            .await
+            .unwrap();
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: x\r\n")
+            .await
+            .unwrap();
+        let started = tokio::time::Instant::now();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(started.elapsed() >= READ_TIMEOUT);
+        assert!(!response.contains("200 OK"), "{response}");
+    }
+}
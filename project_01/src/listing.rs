@@ -0,0 +1,152 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **Slices**: `split` hands back the shown part of a list without copying
+- **`Copy` value type**: A `ListLimit` is passed around by value, like
+  `Style`
+
+**Design decisions**:
+- One helper truncates every long list of text output (summary features,
+  diff categories), so they all cut at the same place and say the same thing
+- A list is shown whole up to the threshold; above it, the first
+  `threshold` entries are shown and a last line counts the rest
+- `-v` raises the threshold tenfold and `-vv` removes it; the last line
+  names the flag that would show more
+- JSON output is never truncated: only text goes through here
+- The verbosity levels come from `output`, which the wasm32 build leaves
+  out; there only `ListLimit::UNLIMITED` exists
+
+******************************************************************************/
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::output::Verbosity;
+
+/// Entries shown per list by default (`--list-limit`)
+pub const DEFAULT_THRESHOLD: usize = 50;
+
+/// How much `-v` raises the threshold
+#[cfg(not(target_arch = "wasm32"))]
+const VERBOSE_FACTOR: usize = 10;
+
+/// How many entries of a long list text output shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListLimit {
+    max: Option<usize>,
+    /// Flag that shows more, for the "... and N more" line
+    hint: &'static str,
+}
+
+impl ListLimit {
+    /// Shows every entry
+    pub const UNLIMITED: Self = Self {
+        max: None,
+        hint: "",
+    };
+
+    /// The limit for `threshold` at `verbosity`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(threshold: usize, verbosity: Verbosity) -> Self {
+        match verbosity {
+            Verbosity::Quiet | Verbosity::Normal => Self {
+                max: Some(threshold),
+                hint: "-v",
+            },
+            Verbosity::Verbose => Self {
+                max: Some(threshold.saturating_mul(VERBOSE_FACTOR)),
+                hint: "-vv",
+            },
+            Verbosity::Debug => Self::UNLIMITED,
+        }
+    }
+
+    /// The entries to show, and the line counting the hidden ones if any
+    pub fn split<T>(self, items: &[T]) -> (&[T], Option<String>) {
+        match self.max {
+            Some(max) if items.len() > max => (
+                &items[..max],
+                Some(format!(
+                    "... and {} more (use {} to list)",
+                    thousands(items.len() - max),
+                    self.hint
+                )),
+            ),
+            _ => (items, None),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for ListLimit {
+    fn default() -> Self {
+        Self::new(DEFAULT_THRESHOLD, Verbosity::Normal)
+    }
+}
+
+/// `7950` as `7,950`
+pub fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_at_the_boundaries() {
+        let limit = ListLimit::new(50, Verbosity::Normal);
+        for len in [0, 1, 49, 50] {
+            let items: Vec<usize> = (0..len).collect();
+            assert_eq!(limit.split(&items), (&items[..], None), "{len}");
+        }
+
+        let items: Vec<usize> = (0..51).collect();
+        let (shown, more) = limit.split(&items);
+        assert_eq!(shown, &items[..50]);
+        assert_eq!(more.as_deref(), Some("... and 1 more (use -v to list)"));
+
+        let items: Vec<usize> = (0..8000).collect();
+        let (shown, more) = limit.split(&items);
+        assert_eq!(shown.len(), 50);
+        assert_eq!(more.as_deref(), Some("... and 7,950 more (use -v to list)"));
+    }
+
+    #[test]
+    fn test_verbosity_raises_then_removes_the_threshold() {
+        let items: Vec<usize> = (0..8000).collect();
+
+        let (shown, more) = ListLimit::new(50, Verbosity::Verbose).split(&items);
+        assert_eq!(shown.len(), 500);
+        assert_eq!(
+            more.as_deref(),
+            Some("... and 7,500 more (use -vv to list)")
+        );
+        let (shown, more) = ListLimit::new(50, Verbosity::Debug).split(&items);
+        assert_eq!((shown.len(), more), (8000, None));
+
+        assert_eq!(ListLimit::new(50, Verbosity::Quiet), ListLimit::default());
+        let (shown, more) = ListLimit::new(0, Verbosity::Normal).split(&items[..1]);
+        assert_eq!((shown.len(), more.is_some()), (0, true));
+    }
+
+    #[test]
+    fn test_thousands() {
+        for (n, text) in [
+            (0, "0"),
+            (999, "999"),
+            (1000, "1,000"),
+            (7950, "7,950"),
+            (123_456, "123,456"),
+            (1_234_567, "1,234,567"),
+        ] {
+            assert_eq!(thousands(n), text);
+        }
+    }
+}
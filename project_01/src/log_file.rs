@@ -0,0 +1,340 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`std::sync::mpsc`**: Log lines travel through an unbounded channel to a
+  dedicated writer thread, so logging never blocks on the disk
+- **`MakeWriter`**: `LogFile` plugs into any `tracing_subscriber` layer
+- **`fs::rename`**: Rotation shifts `app.log.1` to `app.log.2` and so on,
+  then moves the live file to `app.log.1`
+
+**Design decisions**:
+- One thread owns the file: rotating (rename, then reopen) and writing
+  never interleave, so no line is lost or split across a rotation
+- A line is never cut in two: the file rotates before the line that would
+  take it past `--log-max-size`, so a file only exceeds the limit when a
+  single line is larger than the limit
+- `--log-keep 0` keeps no old file: the live file is simply started over
+- A failed rotation keeps writing to the current file and is retried with
+  the next line; logging must not fail the watcher
+- Sizes accept binary suffixes, case-insensitive: `B`, `K`/`KB`/`KiB`,
+  `M`/`MB`/`MiB`, `G`/`GB`/`GiB`, all powers of 1024
+
+******************************************************************************/
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Default of `--log-max-size`
+pub const DEFAULT_MAX_SIZE: &str = "10MiB";
+
+/// Default of `--log-keep`
+pub const DEFAULT_KEEP: u32 = 5;
+
+/// Parses a size such as `512K` or `10MiB` into bytes
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{text}': expected a number and a unit, e.g. 10MiB"))?;
+    let factor: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        other => return Err(format!("invalid size '{text}': unknown unit '{other}'")),
+    };
+    let size = number
+        .checked_mul(factor)
+        .ok_or_else(|| format!("invalid size '{text}': too large"))?;
+    if size == 0 {
+        return Err(format!("invalid size '{text}': must be greater than zero"));
+    }
+    Ok(size)
+}
+
+/// A file that rotates once it reaches a size
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    keep: u32,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Opens `path` for appending, creating it if needed
+    pub fn open(path: &Path, max_size: u64, keep: u32) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_size,
+            keep,
+            file,
+            size,
+        })
+    }
+
+    /// Appends one line, rotating first if it would not fit
+    pub fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            // Retried with the next line when it fails
+            let _ = self.rotate();
+        }
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Name of the `n`th old file, e.g. `app.log.2`
+    pub fn rotated(path: &Path, n: u32) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+            self.size = 0;
+            return Ok(());
+        }
+
+        // Drop the oldest, shift the others up, then move the live file
+        match fs::remove_file(Self::rotated(&self.path, self.keep)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        for n in (1..self.keep).rev() {
+            match fs::rename(
+                Self::rotated(&self.path, n),
+                Self::rotated(&self.path, n + 1),
+            ) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        fs::rename(&self.path, Self::rotated(&self.path, 1))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// What the writer thread receives
+enum Message {
+    Line(Vec<u8>),
+    Shutdown,
+}
+
+/// A rotating log file written by a dedicated thread
+///
+/// Cloning is cheap: every clone sends to the same thread.
+#[derive(Debug, Clone)]
+pub struct LogFile {
+    sender: Sender<Message>,
+}
+
+/// Stops the writer thread once every pending line is written
+#[derive(Debug)]
+pub struct WriterGuard {
+    sender: Sender<Message>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LogFile {
+    /// Opens `path` and starts the writer thread
+    ///
+    /// Fails when the file cannot be opened; nothing is started then.
+    pub fn spawn(path: &Path, max_size: u64, keep: u32) -> io::Result<(Self, WriterGuard)> {
+        let file = RotatingFile::open(path, max_size, keep)?;
+        let (sender, receiver) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("log-file".to_string())
+            .spawn(move || run(file, receiver))?;
+        Ok((
+            Self {
+                sender: sender.clone(),
+            },
+            WriterGuard {
+                sender,
+                thread: Some(thread),
+            },
+        ))
+    }
+}
+
+/// Writes lines until told to stop or every sender is gone
+fn run(mut file: RotatingFile, receiver: Receiver<Message>) {
+    while let Ok(Message::Line(line)) = receiver.recv() {
+        // A line that cannot be written is dropped; the next one may succeed
+        let _ = file.write_line(&line);
+    }
+    let _ = file.file.flush();
+}
+
+impl WriterGuard {
+    /// Writes every pending line and stops the thread
+    pub fn shutdown(&mut self) {
+        let _ = self.sender.send(Message::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for WriterGuard {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl Write for LogFile {
+    /// Queues `buf` as one line; never blocks
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sender
+            .send(Message::Line(buf.to_vec()))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "log writer stopped"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogFile {
+    type Writer = LogFile;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// A size for people: `512 B`, `3.5 KiB`, `1.2 MiB`
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{bytes} B"),
+        1024..1_048_576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        1_048_576..1_073_741_824 => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+        _ => format!("{:.1} GiB", bytes as f64 / 1_073_741_824.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("100"), Ok(100));
+        assert_eq!(parse_size("100B"), Ok(100));
+        assert_eq!(parse_size("512k"), Ok(512 * 1024));
+        assert_eq!(parse_size("10MiB"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("1 GB"), Ok(1024 * 1024 * 1024));
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("10 parsecs").is_err());
+        assert!(parse_size("99999999999999G").is_err());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(3584), "3.5 KiB");
+        assert_eq!(format_size(1_258_291), "1.2 MiB");
+        assert_eq!(format_size(5 << 30), "5.0 GiB");
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_rotates_before_the_line_that_does_not_fit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+
+        file.write_line(b"1234\n").unwrap();
+        file.write_line(b"5678\n").unwrap();
+        // Exactly at the limit: no rotation yet
+        assert_eq!(read(&path), "1234\n5678\n");
+        assert!(!RotatingFile::rotated(&path, 1).exists());
+
+        file.write_line(b"a\n").unwrap();
+        assert_eq!(read(&path), "a\n");
+        assert_eq!(read(&RotatingFile::rotated(&path, 1)), "1234\n5678\n");
+
+        // A line larger than the limit still goes out whole
+        file.write_line(b"0123456789abc\n").unwrap();
+        file.write_line(b"b\n").unwrap();
+        file.write_line(b"c\n").unwrap();
+        assert_eq!(read(&path), "b\nc\n");
+        assert_eq!(read(&RotatingFile::rotated(&path, 1)), "0123456789abc\n");
+        assert_eq!(read(&RotatingFile::rotated(&path, 2)), "a\n");
+        // Only `keep` old files
+        assert!(!RotatingFile::rotated(&path, 3).exists());
+    }
+
+    #[test]
+    fn test_keep_zero_starts_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        let mut file = RotatingFile::open(&path, 4, 0).unwrap();
+        file.write_line(b"abc\n").unwrap();
+        file.write_line(b"def\n").unwrap();
+        assert_eq!(read(&path), "def\n");
+        assert!(!RotatingFile::rotated(&path, 1).exists());
+    }
+
+    #[test]
+    fn test_appends_to_an_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "old\n").unwrap();
+        let mut file = RotatingFile::open(&path, 100, 1).unwrap();
+        file.write_line(b"new\n").unwrap();
+        assert_eq!(read(&path), "old\nnew\n");
+    }
+
+    #[test]
+    fn test_writer_thread_loses_no_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        let (log, mut guard) = LogFile::spawn(&path, 64, 100).unwrap();
+        let lines: Vec<String> = (0..200).map(|i| format!("line {i:03}\n")).collect();
+        for line in &lines {
+            log.make_writer().write_all(line.as_bytes()).unwrap();
+        }
+        guard.shutdown();
+
+        // Oldest file first, then the live one
+        let mut written = String::new();
+        for n in (1..=100).rev() {
+            written.push_str(&read(&RotatingFile::rotated(&path, n)));
+        }
+        written.push_str(&read(&path));
+        assert_eq!(written, lines.concat());
+    }
+
+    #[test]
+    fn test_spawn_fails_on_a_missing_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(LogFile::spawn(&dir.path().join("missing/app.log"), 64, 1).is_err());
+    }
+}
@@ -0,0 +1,661 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`tracing`**: Structured diagnostics: events carry typed fields and live
+  inside spans (`read_config`, `reload`)
+- **`EnvFilter`**: Per-crate level directives, the `RUST_LOG` syntax
+- **`tracing_subscriber::fmt`**: Renders events as text lines
+- **`Layer`**: `JsonLayer` renders the same events as JSON lines; span
+  fields live in the span's extensions until an event needs them
+- **`OnceLock`**: The installed format, for the error that ends `main`
+- **`BoxMakeWriter`**: stderr or the log file, chosen at runtime
+
+**Design decisions**:
+- Diagnostics go to stderr and are separate from the watch events printed by
+  the `Emitter`: the log filter never hides or changes those lines
+- `--log-level` wins over `RUST_LOG`; without either, dependencies log at
+  `warn` and this crate at `info`
+- Routine steps log at `debug`, so the default level stays silent unless
+  something is unusual
+- `--log-format json` makes stderr a stream of JSON lines: timestamp,
+  level, target, message, then the fields of the enclosing spans and of the
+  event (`path`, `duration_ms`, `error.kind`, ...). A multi-line string
+  becomes an array of its lines, so no line ever embeds a newline
+- Under `--log-format json` the watch events that normally go to stderr
+  (failures) are logged instead, and so is the error ending the process;
+  what subcommands print on stderr is their output, not diagnostics
+- `--log-file` sends the diagnostics to a rotating file (`log_file`)
+  instead of stderr; a file that cannot be opened falls back to stderr
+  with a warning rather than losing the logs or failing the run. The
+  error ending the process is still printed on stderr in text mode
+- `--log-target syslog|journald` hands the diagnostics to the system log
+  (`system_log`, Unix builds with the `system-log` feature); the watch
+  events that matter to an operator (loads, failures, degraded state,
+  shutdown) are logged too, at their own level, since the system log does
+  not see stdout. A target that is unavailable fails the start
+- `--otlp-endpoint` adds an OpenTelemetry exporter (`otlp`, behind the
+  `otlp` feature) next to whichever target is chosen; each has its own
+  filter, so the exported spans do not depend on `--log-level`
+- The filter of the target sits in a `reload::Layer`: `set_level` (SIGUSR2,
+  along with the watch output's verbosity) raises this crate's level at
+  runtime, then restores the filter the process started with
+
+******************************************************************************/
+
+use crate::log_file::{LogFile, WriterGuard};
+use crate::metrics::Metrics;
+use crate::proxy::CurrentProxy;
+use crate::report;
+use clap::ValueEnum;
+use serde_json::{Map, Value, json};
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+/// Filter used when neither `--log-level` nor `RUST_LOG` is set
+pub const DEFAULT_FILTER: &str = "warn,config_watcher=info";
+
+/// Values of `--log-level`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// Values of `--log-format`
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Values of `--log-target`
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogTarget {
+    /// Standard error, or `--log-file`
+    #[default]
+    Stderr,
+    /// The local syslog daemon (`/dev/log`)
+    Syslog,
+    /// journald's native protocol
+    Journald,
+}
+
+impl LogTarget {
+    /// Name on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            LogTarget::Stderr => "stderr",
+            LogTarget::Syslog => "syslog",
+            LogTarget::Journald => "journald",
+        }
+    }
+}
+
+/// Values of `--syslog-facility`
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Facility {
+    User,
+    #[default]
+    Daemon,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+impl Facility {
+    /// Numeric code from RFC 5424
+    #[cfg(all(unix, feature = "system-log"))]
+    pub fn code(self) -> u8 {
+        match self {
+            Facility::User => 1,
+            Facility::Daemon => 3,
+            Facility::Local0 => 16,
+            Facility::Local1 => 17,
+            Facility::Local2 => 18,
+            Facility::Local3 => 19,
+            Facility::Local4 => 20,
+            Facility::Local5 => 21,
+            Facility::Local6 => 22,
+            Facility::Local7 => 23,
+        }
+    }
+}
+
+/// Where and how diagnostics are written
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    pub level: Option<LogLevel>,
+    pub format: LogFormat,
+    pub file: Option<FileTarget>,
+    pub target: LogTarget,
+    pub facility: Facility,
+    pub otlp: Option<OtlpTarget>,
+}
+
+/// `--otlp-endpoint`, the metrics exported with the spans and the proxy
+/// section the exports go through
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "otlp"), allow(dead_code))]
+pub struct OtlpTarget {
+    pub endpoint: String,
+    pub metrics: Metrics,
+    pub proxy: CurrentProxy,
+}
+
+/// A layer chosen at runtime, next to the main one
+type ExtraLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// `--log-file` and its rotation settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTarget {
+    pub path: PathBuf,
+    pub max_size: u64,
+    pub keep: u32,
+}
+
+/// Format installed by [`init`], and whether it writes somewhere else than
+/// stderr
+static INSTALLED: OnceLock<(LogFormat, bool)> = OnceLock::new();
+
+/// The log file's writer thread, stopped by [`shutdown`]
+static GUARD: Mutex<Option<WriterGuard>> = Mutex::new(None);
+
+/// Replaces the filter installed by [`init`]; false once it is gone
+type Reload = Box<dyn Fn(EnvFilter) -> bool + Send + Sync>;
+
+/// The filter installed by [`init`], as directives, and how to replace it
+static FILTER: OnceLock<(String, Reload)> = OnceLock::new();
+
+/// The OpenTelemetry exporter thread, flushed by [`shutdown`]
+#[cfg(feature = "otlp")]
+static EXPORTER: Mutex<Option<crate::otlp::ExporterGuard>> = Mutex::new(None);
+
+impl LogLevel {
+    fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+}
+
+/// Filter directives for `--log-level` and the `RUST_LOG` value
+///
+/// `--log-level` only raises or lowers this crate; dependencies stay at
+/// `warn` so their chatter does not drown ours.
+pub fn directives(level: Option<LogLevel>, rust_log: Option<&str>) -> String {
+    match (level, rust_log) {
+        (Some(level), _) => format!("warn,config_watcher={}", level.as_str()),
+        (None, Some(rust_log)) if !rust_log.trim().is_empty() => rust_log.to_string(),
+        (None, _) => DEFAULT_FILTER.to_string(),
+    }
+}
+
+/// Installs the global subscriber
+///
+/// Fails when the system log of `--log-target` cannot be reached, or when
+/// the OpenTelemetry settings are invalid.
+pub fn init(options: &LogOptions) -> anyhow::Result<()> {
+    let rust_log = std::env::var("RUST_LOG").ok();
+    let directives = directives(options.level, rust_log.as_deref());
+    let filter = EnvFilter::try_new(&directives).unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let otlp = options.otlp.as_ref().map(otlp_layer).transpose()?;
+    if options.target != LogTarget::Stderr {
+        let layer = system_layer(options.target, options.facility)?;
+        let _ = INSTALLED.set((options.format, true));
+        tracing_subscriber::registry()
+            .with(otlp)
+            .with(layer.with_filter(reloadable(filter, directives)))
+            .init();
+        return Ok(());
+    }
+
+    let format = options.format;
+    let file = options.file.as_ref();
+    let opened = file.map(|file| {
+        LogFile::spawn(&file.path, file.max_size, file.keep)
+            .map_err(|e| format!("cannot open log file {}: {e}", file.path.display()))
+    });
+    let stderr = || {
+        (
+            BoxMakeWriter::new(std::io::stderr),
+            std::io::stderr().is_terminal(),
+        )
+    };
+    let mut failure = None;
+    let (writer, ansi) = match opened {
+        Some(Ok((log_file, guard))) => {
+            *GUARD.lock().unwrap_or_else(|e| e.into_inner()) = Some(guard);
+            (BoxMakeWriter::new(log_file), false)
+        }
+        Some(Err(e)) => {
+            failure = Some(e);
+            stderr()
+        }
+        None => stderr(),
+    };
+    let _ = INSTALLED.set((format, file.is_some() && failure.is_none()));
+
+    match format {
+        LogFormat::Text => tracing_subscriber::registry()
+            .with(otlp)
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(writer)
+                    .with_ansi(ansi)
+                    .with_filter(reloadable(filter, directives)),
+            )
+            .init(),
+        LogFormat::Json => tracing_subscriber::registry()
+            .with(otlp)
+            .with(JsonLayer::new(writer).with_filter(reloadable(filter, directives)))
+            .init(),
+    }
+
+    if let Some(e) = failure {
+        tracing::warn!("{e}, logging to stderr instead");
+    }
+    Ok(())
+}
+
+/// `filter`, replaceable through [`set_level`]
+fn reloadable<S: 'static>(filter: EnvFilter, directives: String) -> reload::Layer<EnvFilter, S> {
+    let (filter, handle) = reload::Layer::new(filter);
+    let reload: Reload = Box::new(move |filter| handle.reload(filter).is_ok());
+    let _ = FILTER.set((directives, reload));
+    filter
+}
+
+/// Logs this crate at `level` from now on, dependencies at `warn`; `None`
+/// restores the filter of `--log-level` or `RUST_LOG`
+///
+/// Does nothing before [`init`].
+pub fn set_level(level: Option<LogLevel>) {
+    let Some((startup, reload)) = FILTER.get() else {
+        return;
+    };
+    let directives = match level {
+        Some(level) => format!("warn,config_watcher={}", level.as_str()),
+        None => startup.clone(),
+    };
+    if let Ok(filter) = EnvFilter::try_new(directives) {
+        reload(filter);
+    }
+}
+
+/// The layer writing to syslog or journald
+#[cfg(all(unix, feature = "system-log"))]
+fn system_layer(
+    target: LogTarget,
+    facility: Facility,
+) -> anyhow::Result<crate::system_log::SystemLogLayer> {
+    use crate::system_log::{JOURNALD_SOCKET, Protocol, SYSLOG_SOCKET, SystemLogLayer};
+    use anyhow::Context as _;
+    let (path, protocol) = match target {
+        LogTarget::Journald => (JOURNALD_SOCKET, Protocol::Journald),
+        _ => (SYSLOG_SOCKET, Protocol::Syslog(facility)),
+    };
+    SystemLogLayer::connect(std::path::Path::new(path), protocol)
+        .with_context(|| format!("Cannot log to {}: {path} is unavailable", target.name()))
+}
+
+/// Without the `system-log` feature, only stderr and files are available
+#[cfg(not(all(unix, feature = "system-log")))]
+fn system_layer(
+    target: LogTarget,
+    _: Facility,
+) -> anyhow::Result<tracing_subscriber::layer::Identity> {
+    anyhow::bail!(
+        "--log-target {} needs a Unix build with the `system-log` feature",
+        target.name()
+    )
+}
+
+/// The OpenTelemetry exporter, started now
+#[cfg(feature = "otlp")]
+fn otlp_layer(target: &OtlpTarget) -> anyhow::Result<ExtraLayer> {
+    use crate::otlp::{self, OtlpConfig};
+    use anyhow::Context as _;
+    let config = OtlpConfig::new(&target.endpoint, |name| std::env::var(name).ok())?;
+    let (layer, guard) = otlp::start(config, target.metrics.clone(), target.proxy.clone())
+        .context("Cannot start the OTLP exporter")?;
+    *EXPORTER.lock().unwrap_or_else(|e| e.into_inner()) = Some(guard);
+    Ok(layer)
+}
+
+/// Without the `otlp` feature, nothing can be exported
+#[cfg(not(feature = "otlp"))]
+fn otlp_layer(_: &OtlpTarget) -> anyhow::Result<ExtraLayer> {
+    anyhow::bail!("--otlp-endpoint needs a build with the `otlp` feature")
+}
+
+/// Writes the pending lines of the log file and exports the last spans,
+/// if any
+///
+/// Called once, right before the process exits.
+pub fn shutdown() {
+    #[cfg(feature = "otlp")]
+    {
+        let exporter = EXPORTER.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(mut exporter) = exporter
+            && !exporter.shutdown(crate::otlp::SHUTDOWN_TIMEOUT)
+        {
+            tracing::warn!(
+                "OTLP export did not finish within {}, last spans dropped",
+                humantime::format_duration(crate::otlp::SHUTDOWN_TIMEOUT)
+            );
+        }
+    }
+    let guard = GUARD.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(mut guard) = guard {
+        guard.shutdown();
+    }
+}
+
+/// Reports the error that ends the process
+///
+/// Printed as is in text mode, logged as one JSON line in JSON mode, and
+/// logged as well when the diagnostics go to a file or the system log.
+pub fn fatal(error: &anyhow::Error) {
+    match INSTALLED.get() {
+        Some((LogFormat::Json, _)) => log_error(false, "fatal", None, error, "exiting"),
+        Some((LogFormat::Text, elsewhere)) => {
+            eprintln!("Error: {error:?}");
+            if *elsewhere {
+                log_error(false, "fatal", None, error, "exiting");
+            }
+        }
+        None => eprintln!("Error: {error:?}"),
+    }
+}
+
+/// Logs `error` with its kind, chain and findings
+///
+/// `warning` selects the `warn` level instead of `error`. The findings are
+/// one per line, each ending with a newline, so that even a single one is
+/// rendered as an array by [`JsonLayer`].
+pub fn log_error(
+    warning: bool,
+    event: &str,
+    path: Option<&std::path::Path>,
+    error: &anyhow::Error,
+    message: &str,
+) {
+    let findings: String = report::findings(error)
+        .iter()
+        .map(|finding| format!("{finding}\n"))
+        .collect();
+    let findings = (!findings.is_empty()).then_some(findings);
+    macro_rules! log {
+        ($level:ident) => {
+            tracing::$level!(
+                path = path.map(|path| tracing::field::display(path.display())),
+                event,
+                error.kind = report::code(error),
+                error = %format_args!("{error:#}"),
+                findings = findings.as_deref(),
+                "{message}"
+            )
+        };
+    }
+    if warning {
+        log!(warn);
+    } else {
+        log!(error);
+    }
+}
+
+/// Renders events as JSON lines
+///
+/// Keys, in order: `timestamp`, `level`, `target`, `message`, the fields of
+/// the enclosing spans (outermost first), the fields of the event, and
+/// `spans`, the names of the enclosing spans.
+pub struct JsonLayer<W> {
+    make_writer: W,
+}
+
+impl<W> JsonLayer<W> {
+    /// Writes every line to a writer made by `make_writer`
+    pub fn new(make_writer: W) -> Self {
+        Self { make_writer }
+    }
+}
+
+/// Fields of an open span, kept in its extensions
+struct SpanFields(Map<String, Value>);
+
+/// Collects fields as JSON values
+pub(crate) struct JsonFields<'a>(pub(crate) &'a mut Map<String, Value>);
+
+impl Visit for JsonFields<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), text(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), text(&format!("{value:?}")));
+    }
+}
+
+/// Keeps the fields of a new span in its extensions
+///
+/// Only one layer of a subscriber may do this.
+pub(crate) fn store_span_fields<S>(attrs: &Attributes<'_>, id: &Id, ctx: &Context<'_, S>)
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let mut fields = Map::new();
+    attrs.record(&mut JsonFields(&mut fields));
+    if let Some(span) = ctx.span(id) {
+        span.extensions_mut().insert(SpanFields(fields));
+    }
+}
+
+/// Adds the fields recorded after a span was created
+pub(crate) fn update_span_fields<S>(id: &Id, values: &Record<'_>, ctx: &Context<'_, S>)
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    if let Some(span) = ctx.span(id)
+        && let Some(fields) = span.extensions_mut().get_mut::<SpanFields>()
+    {
+        values.record(&mut JsonFields(&mut fields.0));
+    }
+}
+
+/// The message of `event`, the fields of its spans (outermost first) then
+/// its own, and the names of its spans
+pub(crate) fn event_fields<S>(
+    event: &Event<'_>,
+    ctx: &Context<'_, S>,
+) -> (Option<Value>, Map<String, Value>, Vec<Value>)
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let mut own = Map::new();
+    event.record(&mut JsonFields(&mut own));
+    let message = own.remove("message");
+
+    let mut fields = Map::new();
+    let mut spans = Vec::new();
+    if let Some(scope) = ctx.event_scope(event) {
+        for span in scope.from_root() {
+            spans.push(json!(span.name()));
+            if let Some(span_fields) = span.extensions().get::<SpanFields>() {
+                fields.extend(span_fields.0.clone());
+            }
+        }
+    }
+    fields.extend(own);
+    (message, fields, spans)
+}
+
+/// A string, or the array of its non-empty lines when it has several
+fn text(value: &str) -> Value {
+    if value.contains('\n') {
+        value
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| json!(line))
+            .collect()
+    } else {
+        json!(value)
+    }
+}
+
+impl<S, W> Layer<S> for JsonLayer<W>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'a> MakeWriter<'a> + 'static,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        store_span_fields(attrs, id, &ctx);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        update_span_fields(id, values, &ctx);
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let (message, fields, spans) = event_fields(event, &ctx);
+
+        let metadata = event.metadata();
+        let mut record = Map::new();
+        record.insert(
+            "timestamp".to_string(),
+            json!(humantime::format_rfc3339_micros(SystemTime::now()).to_string()),
+        );
+        record.insert("level".to_string(), json!(metadata.level().as_str()));
+        record.insert("target".to_string(), json!(metadata.target()));
+        if let Some(message) = message {
+            record.insert("message".to_string(), message);
+        }
+        record.extend(fields);
+        if !spans.is_empty() {
+            record.insert("spans".to_string(), Value::Array(spans));
+        }
+
+        let mut line = Value::Object(record).to_string();
+        line.push('\n');
+        let _ = self.make_writer.make_writer().write_all(line.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Collects everything written, for `JsonLayer`
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Buffer;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let buffer = Buffer::default();
+        let subscriber = tracing_subscriber::registry().with(JsonLayer::new(buffer.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            let span =
+                tracing::info_span!("reload", path = "app.json", outcome = tracing::field::Empty);
+            let _entered = span.enter();
+            span.record("outcome", "error");
+            tracing::warn!(
+                duration_ms = 1.5,
+                error.kind = "validation_failed",
+                findings = "a: bad\nb: worse\n",
+                "reload failed"
+            );
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1, "{output}");
+        let line = lines[0].as_object().unwrap();
+        let keys: Vec<_> = line.keys().map(String::as_str).collect();
+        assert_eq!(
+            keys[..6],
+            ["timestamp", "level", "target", "message", "path", "outcome"]
+        );
+        assert_eq!(keys.last(), Some(&"spans"));
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["message"], "reload failed");
+        assert_eq!(line["outcome"], "error");
+        assert_eq!(line["duration_ms"], 1.5);
+        assert_eq!(line["findings"], json!(["a: bad", "b: worse"]));
+        assert_eq!(line["spans"], json!(["reload"]));
+    }
+
+    #[test]
+    fn test_directives_precedence() {
+        assert_eq!(directives(None, None), DEFAULT_FILTER);
+        assert_eq!(directives(None, Some("  ")), DEFAULT_FILTER);
+        assert_eq!(directives(None, Some("trace")), "trace");
+        assert_eq!(
+            directives(Some(LogLevel::Debug), Some("trace")),
+            "warn,config_watcher=debug"
+        );
+    }
+}
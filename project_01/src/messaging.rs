@@ -0,0 +1,277 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`str::rsplit_once`**: Splits `host:port` on the last colon
+- **`std::net::Ipv6Addr`**: IPv6 literals must be bracketed, as in URLs
+- **Matching on a tuple**: `(kind, consumer_group)` covers the
+  kind-dependent rule in one `match`
+
+**Design decisions**:
+- A broker is `host:port`, `ipv4:port` or `[ipv6]:port`; a bare IPv6
+  address is refused since its last group would read as the port
+- `consumer_group` is required for Kafka and refused for AMQP, which has
+  no such notion: a leftover group there is almost always a copy-paste
+- Plain-text brokers are only a warning, and only in production
+
+******************************************************************************/
+
+use crate::config::{MessagingConfig, MessagingKind};
+use crate::proxy::is_hostname;
+use crate::validation::ValidationReport;
+use std::net::Ipv6Addr;
+
+/// A parsed broker address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Broker {
+    /// Host name or IP address, without brackets
+    pub host: String,
+    pub port: u16,
+}
+
+impl Broker {
+    /// Parses `host:port` or `[ipv6]:port`, `None` when malformed
+    pub fn parse(address: &str) -> Option<Self> {
+        let (host, port) = address.rsplit_once(':')?;
+        let port: u16 = port.parse().ok().filter(|&port| port > 0)?;
+        let host = match host.strip_prefix('[') {
+            Some(inner) => {
+                let inner = inner.strip_suffix(']')?;
+                inner.parse::<Ipv6Addr>().ok()?;
+                inner
+            }
+            None if is_hostname(host) => host,
+            None => return None,
+        };
+        Some(Self {
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+impl MessagingKind {
+    /// Name used in the configuration and the summary
+    pub fn name(self) -> &'static str {
+        match self {
+            MessagingKind::Kafka => "kafka",
+            MessagingKind::Amqp => "amqp",
+        }
+    }
+}
+
+impl MessagingConfig {
+    /// Records messaging findings under `messaging.*`
+    pub fn check(&self, environment: &str, report: &mut ValidationReport) {
+        if self.brokers.is_empty() {
+            report.error("messaging.brokers", "at least one broker is required");
+        }
+        for (i, broker) in self.brokers.iter().enumerate() {
+            if Broker::parse(broker).is_none() {
+                report.error(
+                    format!("messaging.brokers[{i}]"),
+                    format!("'{broker}' is not host:port (write IPv6 addresses as [addr]:port)"),
+                );
+            }
+        }
+
+        if !is_topic_prefix(&self.topic_prefix) {
+            report.error(
+                "messaging.topic_prefix",
+                format!(
+                    "'{}' must only contain letters, digits, '.', '_' and '-'",
+                    self.topic_prefix
+                ),
+            );
+        }
+
+        match (self.kind, &self.consumer_group) {
+            (MessagingKind::Kafka, None) => {
+                report.error("messaging.consumer_group", "is required for kafka");
+            }
+            (MessagingKind::Kafka, Some(group)) if group.trim().is_empty() => {
+                report.error("messaging.consumer_group", "cannot be empty");
+            }
+            (MessagingKind::Amqp, Some(_)) => {
+                report.error("messaging.consumer_group", "is not used by amqp; remove it");
+            }
+            _ => {}
+        }
+
+        if !self.tls && environment == "production" {
+            report.warning(
+                "messaging.tls",
+                "is disabled in production; broker traffic is sent in clear text",
+            );
+        }
+    }
+}
+
+/// Non-empty, made of `[a-zA-Z0-9._-]`
+fn is_topic_prefix(prefix: &str) -> bool {
+    !prefix.is_empty()
+        && prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn kafka() -> MessagingConfig {
+        MessagingConfig {
+            kind: MessagingKind::Kafka,
+            brokers: vec!["kafka-1:9092".to_string()],
+            topic_prefix: "app.".to_string(),
+            tls: true,
+            consumer_group: Some("app".to_string()),
+        }
+    }
+
+    fn findings(config: &MessagingConfig, environment: &str) -> (Vec<String>, Vec<String>) {
+        let mut report = ValidationReport::new();
+        config.check(environment, &mut report);
+        (
+            report.errors().map(|f| f.path.clone()).collect(),
+            report.warnings().map(|f| f.path.clone()).collect(),
+        )
+    }
+
+    fn errors(config: &MessagingConfig) -> Vec<String> {
+        findings(config, "development").0
+    }
+
+    #[test]
+    fn test_broker_forms() {
+        let cases = [
+            ("kafka-1.example.com:9092", true),
+            ("localhost:5672", true),
+            ("10.0.0.1:9092", true),
+            ("[::1]:9092", true),
+            ("[2001:db8::10]:9093", true),
+            ("kafka-1", false),
+            ("kafka-1:", false),
+            ("kafka-1:0", false),
+            ("kafka-1:65536", false),
+            ("2001:db8::10:9092", false),
+            ("[2001:db8::10]", false),
+            ("[not-an-ip]:9092", false),
+            ("bad host:9092", false),
+            (":9092", false),
+        ];
+        for (address, valid) in cases {
+            assert_eq!(Broker::parse(address).is_some(), valid, "{address:?}");
+        }
+        assert_eq!(
+            Broker::parse("[::1]:9092"),
+            Some(Broker {
+                host: "::1".to_string(),
+                port: 9092
+            })
+        );
+    }
+
+    #[test]
+    fn test_brokers_rule() {
+        assert!(errors(&kafka()).is_empty());
+
+        let none = MessagingConfig {
+            brokers: Vec::new(),
+            ..kafka()
+        };
+        assert_eq!(errors(&none), ["messaging.brokers"]);
+
+        let bad = MessagingConfig {
+            brokers: vec!["kafka-1:9092".to_string(), "kafka-2".to_string()],
+            ..kafka()
+        };
+        assert_eq!(errors(&bad), ["messaging.brokers[1]"]);
+    }
+
+    #[test]
+    fn test_topic_prefix_rule() {
+        for prefix in ["app", "app.events_v2-", "A.b.C"] {
+            let config = MessagingConfig {
+                topic_prefix: prefix.to_string(),
+                ..kafka()
+            };
+            assert!(errors(&config).is_empty(), "{prefix}");
+        }
+        for prefix in ["", "app events", "app/", "é"] {
+            let config = MessagingConfig {
+                topic_prefix: prefix.to_string(),
+                ..kafka()
+            };
+            assert_eq!(errors(&config), ["messaging.topic_prefix"], "{prefix}");
+        }
+    }
+
+    #[test]
+    fn test_consumer_group_rule() {
+        let missing = MessagingConfig {
+            consumer_group: None,
+            ..kafka()
+        };
+        assert_eq!(errors(&missing), ["messaging.consumer_group"]);
+        let blank = MessagingConfig {
+            consumer_group: Some(" ".to_string()),
+            ..kafka()
+        };
+        assert_eq!(errors(&blank), ["messaging.consumer_group"]);
+
+        let amqp = MessagingConfig {
+            kind: MessagingKind::Amqp,
+            consumer_group: None,
+            ..kafka()
+        };
+        assert!(errors(&amqp).is_empty());
+        let amqp_with_group = MessagingConfig {
+            kind: MessagingKind::Amqp,
+            ..kafka()
+        };
+        assert_eq!(errors(&amqp_with_group), ["messaging.consumer_group"]);
+    }
+
+    #[test]
+    fn test_plain_text_warns_in_production_only() {
+        let plain = MessagingConfig {
+            tls: false,
+            ..kafka()
+        };
+        assert_eq!(
+            findings(&plain, "production"),
+            (vec![], vec!["messaging.tls".to_string()])
+        );
+        assert_eq!(findings(&plain, "staging"), (vec![], vec![]));
+        assert_eq!(findings(&kafka(), "production"), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_serde_round_trips() {
+        let kafka_doc = json!({
+            "kind": "kafka",
+            "brokers": ["kafka-1:9092", "[::1]:9092"],
+            "topic_prefix": "app.",
+            "tls": false,
+            "consumer_group": "app"
+        });
+        let config: MessagingConfig = serde_json::from_value(kafka_doc.clone()).unwrap();
+        assert_eq!(config.kind, MessagingKind::Kafka);
+        assert_eq!(serde_json::to_value(&config).unwrap(), kafka_doc);
+
+        let amqp_doc = json!({ "kind": "amqp", "brokers": ["rabbit:5672"], "topic_prefix": "app" });
+        let config: MessagingConfig = serde_json::from_value(amqp_doc).unwrap();
+        assert_eq!(config.kind, MessagingKind::Amqp);
+        assert!(config.tls, "tls defaults to true");
+        let json = serde_json::to_value(&config).unwrap();
+        assert!(json.get("consumer_group").is_none());
+        assert_eq!(
+            serde_json::from_value::<MessagingConfig>(json).unwrap(),
+            config
+        );
+
+        let unknown = json!({ "kind": "nats", "brokers": [], "topic_prefix": "x" });
+        assert!(serde_json::from_value::<MessagingConfig>(unknown).is_err());
+    }
+}
@@ -0,0 +1,615 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`Arc<Mutex<...>>`**: The watchers record into shared counters; each
+  update holds the lock for a few additions, never across an `.await`
+- **`listener::listen`**: hyper answers the scrapes, one task per
+  connection; the listener runs beside the watch loop and ends with the
+  runtime, i.e. with the process
+
+**Design decisions**:
+- `--metrics-addr` serves `GET /metrics` in the Prometheus text format;
+  any other path is a 404
+- Every load counts as a reload, the initial one included, labelled by
+  outcome: `success`, `parse_error`, `validation_error`, or `read_error`
+  for anything else (the grouping of `exit::for_code`)
+- All four outcomes are always exposed, at 0 if need be, so that a rate
+  over them never starts from a missing series
+- `configwatcher_config_info` only carries `app_name`, `version` and
+  `environment`: nothing from the configuration that could be a secret is
+  ever exposed
+- A stat failure in the watch loop counts in
+  `configwatcher_watch_errors_total`; it is not a reload
+- The step timings printed at `-v` are always recorded: the time spent in
+  each step (`configwatcher_reload_step_seconds_total`) and the size of the
+  last file read (`configwatcher_config_size_bytes`)
+- Each notifier (`on-change`, `webhook`, `desktop`...) has its failed
+  calls in `configwatcher_notifier_failures_total` and its dropped events,
+  by reason (`queue_full`, `rate_limited`), in
+  `configwatcher_notifier_dropped_total`, at 0 from its start
+- `configwatcher_detection_latency_seconds` is a histogram of the time
+  from a file's mtime to its reload being applied, with buckets sized for
+  polling intervals rather than for parse times
+
+******************************************************************************/
+
+use crate::config::AppConfig;
+use crate::exit;
+use crate::listener::{self, Body};
+use crate::output::Timings;
+use crate::report;
+use hyper::body::Incoming;
+use hyper::{Method, Request, Response, StatusCode};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Upper bounds of the reload duration buckets, in seconds
+pub const BUCKETS: [f64; 8] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+/// Outcomes of a reload, as `result` labels
+pub const RESULTS: [&str; 4] = ["success", "parse_error", "validation_error", "read_error"];
+
+/// Upper bounds of the detection latency buckets, in seconds
+pub const LATENCY_BUCKETS: [f64; 8] = [0.1, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0];
+
+/// Steps of a load, as `step` labels
+pub const STEPS: [&str; 4] = ["stat", "read", "parse", "validate"];
+
+/// Counters shared by every watcher of the process
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    reloads: BTreeMap<&'static str, u64>,
+    last_success: Option<SystemTime>,
+    info: BTreeMap<PathBuf, [String; 3]>,
+    buckets: [u64; BUCKETS.len()],
+    duration_sum: f64,
+    duration_count: u64,
+    step_seconds: [f64; STEPS.len()],
+    config_bytes: u64,
+    latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    latency_sum: f64,
+    latency_count: u64,
+    watch_errors: u64,
+    notifiers: BTreeMap<String, NotifierCounts>,
+}
+
+/// Every metric at one instant
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// Loads per outcome, in the order of [`RESULTS`]
+    pub reloads: [u64; RESULTS.len()],
+    pub last_success: Option<SystemTime>,
+    /// `[app_name, version, environment]` of the loaded files, deduplicated
+    pub info: Vec<[String; 3]>,
+    /// Cumulative counts, one per bound of [`BUCKETS`]
+    pub buckets: [u64; BUCKETS.len()],
+    pub duration_sum: f64,
+    pub duration_count: u64,
+    /// Time spent in each step, in the order of [`STEPS`]
+    pub step_seconds: [f64; STEPS.len()],
+    /// Size of the last file read, 0 if none
+    pub config_bytes: u64,
+    /// Cumulative counts, one per bound of [`LATENCY_BUCKETS`]
+    pub latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    pub latency_sum: f64,
+    pub latency_count: u64,
+    pub watch_errors: u64,
+    /// Counters of each notifier, by name
+    pub notifiers: BTreeMap<String, NotifierCounts>,
+}
+
+/// What went wrong for one notifier
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NotifierCounts {
+    pub failures: u64,
+    pub queue_full: u64,
+    pub rate_limited: u64,
+}
+
+/// The `result` label of a failed load
+pub fn result_label(error: &anyhow::Error) -> &'static str {
+    match exit::for_code(report::code(error)) {
+        exit::PARSE => "parse_error",
+        exit::VALIDATION => "validation_error",
+        _ => "read_error",
+    }
+}
+
+impl Metrics {
+    /// Records one load of `file` and how long each step took
+    pub fn record_load(
+        &self,
+        file: &Path,
+        result: Result<&AppConfig, &anyhow::Error>,
+        timings: &Timings,
+    ) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let label = match result {
+            Ok(config) => {
+                inner.last_success = Some(SystemTime::now());
+                inner.info.insert(
+                    file.to_path_buf(),
+                    [
+                        config.app_name.clone(),
+                        config.version.clone(),
+                        config.environment.clone(),
+                    ],
+                );
+                "success"
+            }
+            Err(e) => result_label(e),
+        };
+        *inner.reloads.entry(label).or_default() += 1;
+
+        let steps = [timings.stat, timings.read, timings.parse, timings.validate];
+        for (sum, step) in inner.step_seconds.iter_mut().zip(steps) {
+            *sum += step.map_or(0.0, |step| step.as_secs_f64());
+        }
+        if let Some(bytes) = timings.bytes {
+            inner.config_bytes = bytes;
+        }
+
+        let secs = timings.total.as_secs_f64();
+        observe(&mut inner.buckets, &BUCKETS, secs);
+        inner.duration_sum += secs;
+        inner.duration_count += 1;
+    }
+
+    /// Records how long after its write a change was applied
+    pub fn record_latency(&self, latency: Duration) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let secs = latency.as_secs_f64();
+        observe(&mut inner.latency_buckets, &LATENCY_BUCKETS, secs);
+        inner.latency_sum += secs;
+        inner.latency_count += 1;
+    }
+
+    /// Records a failed check of a watched file
+    pub fn record_watch_error(&self) {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .watch_errors += 1;
+    }
+
+    /// Exposes the counters of notifier `name`, at 0
+    pub fn register_notifier(&self, name: &str) {
+        self.notifier(name, |_| {});
+    }
+
+    /// Records a failed call of notifier `name`
+    pub fn record_notifier_failure(&self, name: &str) {
+        self.notifier(name, |counts| counts.failures += 1);
+    }
+
+    /// Records an event notifier `name` dropped, because its queue was
+    /// full (`queue_full`) or by its rate limit (`rate_limited`)
+    pub fn record_notifier_drop(&self, name: &str, reason: &str) {
+        self.notifier(name, |counts| match reason {
+            "rate_limited" => counts.rate_limited += 1,
+            _ => counts.queue_full += 1,
+        });
+    }
+
+    fn notifier(&self, name: &str, update: impl FnOnce(&mut NotifierCounts)) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        update(inner.notifiers.entry(name.to_string()).or_default());
+    }
+
+    /// A copy of every metric
+    pub fn snapshot(&self) -> Snapshot {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let info: BTreeSet<_> = inner.info.values().cloned().collect();
+        Snapshot {
+            reloads: RESULTS.map(|result| inner.reloads.get(result).copied().unwrap_or_default()),
+            last_success: inner.last_success,
+            info: info.into_iter().collect(),
+            buckets: inner.buckets,
+            duration_sum: inner.duration_sum,
+            duration_count: inner.duration_count,
+            step_seconds: inner.step_seconds,
+            config_bytes: inner.config_bytes,
+            latency_buckets: inner.latency_buckets,
+            latency_sum: inner.latency_sum,
+            latency_count: inner.latency_count,
+            watch_errors: inner.watch_errors,
+            notifiers: inner.notifiers.clone(),
+        }
+    }
+
+    /// Every metric in the Prometheus text format
+    pub fn render(&self) -> String {
+        let inner = self.snapshot();
+        let mut out = String::new();
+        header(
+            &mut out,
+            "configwatcher_reloads_total",
+            "counter",
+            "Configuration loads, by outcome",
+        );
+        for (result, count) in RESULTS.iter().zip(inner.reloads) {
+            let _ = writeln!(
+                out,
+                "configwatcher_reloads_total{{result=\"{result}\"}} {count}"
+            );
+        }
+
+        header(
+            &mut out,
+            "configwatcher_last_successful_reload_timestamp_seconds",
+            "gauge",
+            "Unix time of the last successful load, 0 if none",
+        );
+        let last_success = inner
+            .last_success
+            .and_then(|at| at.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map_or(0.0, |since| since.as_secs_f64());
+        let _ = writeln!(
+            out,
+            "configwatcher_last_successful_reload_timestamp_seconds {last_success}"
+        );
+
+        header(
+            &mut out,
+            "configwatcher_config_info",
+            "gauge",
+            "The loaded configuration, as labels",
+        );
+        for [app_name, version, environment] in &inner.info {
+            let _ = writeln!(
+                out,
+                "configwatcher_config_info{{app_name=\"{}\",version=\"{}\",environment=\"{}\"}} 1",
+                escape(app_name),
+                escape(version),
+                escape(environment)
+            );
+        }
+
+        histogram(
+            &mut out,
+            "configwatcher_reload_duration_seconds",
+            "Time to read, parse and validate the configuration",
+            (&inner.buckets, &BUCKETS),
+            inner.duration_sum,
+            inner.duration_count,
+        );
+        histogram(
+            &mut out,
+            "configwatcher_detection_latency_seconds",
+            "Time from a file's modification to its reload being applied",
+            (&inner.latency_buckets, &LATENCY_BUCKETS),
+            inner.latency_sum,
+            inner.latency_count,
+        );
+
+        header(
+            &mut out,
+            "configwatcher_reload_step_seconds_total",
+            "counter",
+            "Time spent in each step of the loads",
+        );
+        for (step, seconds) in STEPS.iter().zip(inner.step_seconds) {
+            let _ = writeln!(
+                out,
+                "configwatcher_reload_step_seconds_total{{step=\"{step}\"}} {seconds}"
+            );
+        }
+
+        header(
+            &mut out,
+            "configwatcher_config_size_bytes",
+            "gauge",
+            "Size of the last configuration file read, 0 if none",
+        );
+        let _ = writeln!(
+            out,
+            "configwatcher_config_size_bytes {}",
+            inner.config_bytes
+        );
+
+        header(
+            &mut out,
+            "configwatcher_watch_errors_total",
+            "counter",
+            "Failed checks of a watched file",
+        );
+        let _ = writeln!(
+            out,
+            "configwatcher_watch_errors_total {}",
+            inner.watch_errors
+        );
+
+        header(
+            &mut out,
+            "configwatcher_notifier_failures_total",
+            "counter",
+            "Notifier calls that failed or timed out",
+        );
+        for (name, counts) in &inner.notifiers {
+            let _ = writeln!(
+                out,
+                "configwatcher_notifier_failures_total{{notifier=\"{}\"}} {}",
+                escape(name),
+                counts.failures
+            );
+        }
+        header(
+            &mut out,
+            "configwatcher_notifier_dropped_total",
+            "counter",
+            "Events a notifier dropped, by reason",
+        );
+        for (name, counts) in &inner.notifiers {
+            for (reason, count) in [
+                ("queue_full", counts.queue_full),
+                ("rate_limited", counts.rate_limited),
+            ] {
+                let _ = writeln!(
+                    out,
+                    "configwatcher_notifier_dropped_total{{notifier=\"{}\",reason=\"{reason}\"}} {count}",
+                    escape(name)
+                );
+            }
+        }
+        out
+    }
+}
+
+/// The `# HELP` and `# TYPE` lines of a metric
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}");
+}
+
+/// Counts `value` in every cumulative bucket whose bound it fits under
+fn observe(buckets: &mut [u64], bounds: &[f64], value: f64) {
+    for (bucket, bound) in buckets.iter_mut().zip(bounds) {
+        if value <= *bound {
+            *bucket += 1;
+        }
+    }
+}
+
+/// A histogram with its `_bucket`, `_sum` and `_count` series
+fn histogram(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    (buckets, bounds): (&[u64], &[f64]),
+    sum: f64,
+    count: u64,
+) {
+    header(out, name, "histogram", help);
+    for (bucket, bound) in buckets.iter().zip(bounds) {
+        let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {bucket}");
+    }
+    let _ = writeln!(
+        out,
+        "{name}_bucket{{le=\"+Inf\"}} {count}\n{name}_sum {sum}\n{name}_count {count}"
+    );
+}
+
+/// A label value with `\`, `"` and newlines escaped
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+/// Serves `metrics` on `addr` until the process exits
+///
+/// Returns the bound address, useful with port 0.
+pub async fn serve(addr: SocketAddr, metrics: Metrics) -> std::io::Result<SocketAddr> {
+    let (bound, _) = listener::listen(addr, None, move |request| {
+        let answer = respond(&request, &metrics);
+        async move { answer }
+    })
+    .await?;
+    Ok(bound)
+}
+
+/// The answer to one request
+fn respond(request: &Request<Incoming>, metrics: &Metrics) -> Response<Body> {
+    let (status, body) = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => (StatusCode::OK, metrics.render()),
+        _ => (StatusCode::NOT_FOUND, "Not found\n".to_string()),
+    };
+    listener::respond(status, "text/plain; version=0.0.4; charset=utf-8", body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::watcher::ConfigWatcher;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    /// Loads `text` once through a watcher that records into `metrics`
+    async fn load(metrics: &Metrics, file: &Path, text: &str) {
+        std::fs::write(file, text).unwrap();
+        let mut watcher = ConfigWatcher::new(file, 1).with_metrics(metrics.clone());
+        watcher.load_initial().await.unwrap();
+    }
+
+    async fn scrape(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                format!("GET {path} HTTP/1.1\r\nHost: test\r\nConnection: close\r\n\r\n")
+                    .as_bytes(),
+            )
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_scrape_after_loads() {
+        let metrics = Metrics::default();
+        let addr = serve("127.0.0.1:0".parse().unwrap(), metrics.clone())
+            .await
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.json");
+
+        load(
+            &metrics,
+            &file,
+            r#"{ "app_name": "A", "version": "1.0.0" }"#,
+        )
+        .await;
+        load(&metrics, &file, "{ invalid json }").await;
+        load(&metrics, &file, r#"{ "app_name": "", "version": "1.0.0" }"#).await;
+        load(
+            &metrics,
+            &file,
+            r#"{ "app_name": "A", "version": "2.0.0", "environment": "staging",
+                 "database": { "connection_string": "postgres://u:hunter2@db/app" } }"#,
+        )
+        .await;
+        let mut watcher =
+            ConfigWatcher::new(dir.path().join("missing.json"), 1).with_metrics(metrics.clone());
+        watcher.load_initial().await.unwrap();
+
+        let response = scrape(addr, "/metrics").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.contains("content-type: text/plain; version=0.0.4"));
+        for line in [
+            r#"configwatcher_reloads_total{result="success"} 2"#,
+            r#"configwatcher_reloads_total{result="parse_error"} 1"#,
+            r#"configwatcher_reloads_total{result="validation_error"} 1"#,
+            r#"configwatcher_reloads_total{result="read_error"} 1"#,
+            r#"configwatcher_config_info{app_name="A",version="2.0.0",environment="staging"} 1"#,
+            "configwatcher_reload_duration_seconds_count 5",
+            r#"configwatcher_reload_duration_seconds_bucket{le="+Inf"} 5"#,
+            "configwatcher_watch_errors_total 0",
+            "# TYPE configwatcher_reload_duration_seconds histogram",
+        ] {
+            assert!(response.lines().any(|l| l == line), "{line} in {response}");
+        }
+        // Replaced, not added to
+        assert!(!response.contains(r#"version="1.0.0""#), "{response}");
+        assert!(!response.contains("hunter2"), "{response}");
+        let last_success = response
+            .lines()
+            .find_map(|l| l.strip_prefix("configwatcher_last_successful_reload_timestamp_seconds "))
+            .unwrap();
+        assert!(
+            last_success.parse::<f64>().unwrap() > 1.6e9,
+            "{last_success}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_other_paths_are_not_found() {
+        let addr = serve("127.0.0.1:0".parse().unwrap(), Metrics::default())
+            .await
+            .unwrap();
+        let response = scrape(addr, "/").await;
+        assert!(
+            response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+            "{response}"
+        );
+    }
+
+    #[test]
+    fn test_render_before_any_load() {
+        let text = Metrics::default().render();
+        for result in RESULTS {
+            let line = format!("configwatcher_reloads_total{{result=\"{result}\"}} 0");
+            assert!(text.lines().any(|l| l == line), "{line}");
+        }
+        assert!(text.contains("configwatcher_last_successful_reload_timestamp_seconds 0\n"));
+        assert!(!text.contains("configwatcher_config_info{"));
+    }
+
+    #[test]
+    fn test_buckets_are_cumulative() {
+        let metrics = Metrics::default();
+        let error = anyhow::anyhow!("gone");
+        for millis in [2, 20, 2000] {
+            let timings = Timings {
+                total: Duration::from_millis(millis),
+                ..Timings::default()
+            };
+            metrics.record_load(Path::new("a"), Err(&error), &timings);
+        }
+        metrics.record_watch_error();
+        let text = metrics.render();
+        for line in [
+            r#"configwatcher_reload_duration_seconds_bucket{le="0.001"} 0"#,
+            r#"configwatcher_reload_duration_seconds_bucket{le="0.005"} 1"#,
+            r#"configwatcher_reload_duration_seconds_bucket{le="0.05"} 2"#,
+            r#"configwatcher_reload_duration_seconds_bucket{le="1"} 2"#,
+            r#"configwatcher_reload_duration_seconds_bucket{le="5"} 3"#,
+            "configwatcher_reload_duration_seconds_count 3",
+            r#"configwatcher_reloads_total{result="read_error"} 3"#,
+            "configwatcher_watch_errors_total 1",
+        ] {
+            assert!(text.lines().any(|l| l == line), "{line} in {text}");
+        }
+    }
+
+    #[test]
+    fn test_step_timings_add_up() {
+        let metrics = Metrics::default();
+        let config = AppConfig::example();
+        let timings = Timings {
+            stat: Some(Duration::from_millis(1)),
+            read: Some(Duration::from_millis(250)),
+            parse: Some(Duration::from_millis(500)),
+            validate: None,
+            bytes: Some(1_258_291),
+            total: Duration::from_millis(750),
+        };
+        metrics.record_load(Path::new("a"), Ok(&config), &timings);
+        metrics.record_load(Path::new("a"), Ok(&config), &timings);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.step_seconds, [0.002, 0.5, 1.0, 0.0]);
+        let text = metrics.render();
+        for line in [
+            r#"configwatcher_reload_step_seconds_total{step="read"} 0.5"#,
+            r#"configwatcher_reload_step_seconds_total{step="validate"} 0"#,
+            "configwatcher_config_size_bytes 1258291",
+            "configwatcher_reload_duration_seconds_sum 1.5",
+        ] {
+            assert!(text.lines().any(|l| l == line), "{line} in {text}");
+        }
+    }
+
+    #[test]
+    fn test_latency_histogram() {
+        let metrics = Metrics::default();
+        for millis in [50, 1700, 90_000] {
+            metrics.record_latency(Duration::from_millis(millis));
+        }
+        let text = metrics.render();
+        for line in [
+            "# TYPE configwatcher_detection_latency_seconds histogram",
+            r#"configwatcher_detection_latency_seconds_bucket{le="0.1"} 1"#,
+            r#"configwatcher_detection_latency_seconds_bucket{le="2"} 2"#,
+            r#"configwatcher_detection_latency_seconds_bucket{le="60"} 2"#,
+            r#"configwatcher_detection_latency_seconds_bucket{le="+Inf"} 3"#,
+            "configwatcher_detection_latency_seconds_count 3",
+        ] {
+            assert!(text.lines().any(|l| l == line), "{line} in {text}");
+        }
+    }
+
+    #[test]
+    fn test_label_values_are_escaped() {
+        assert_eq!(escape("a\"b\\c\nd"), r#"a\"b\\c\nd"#);
+    }
+}
@@ -0,0 +1,197 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`impl Notifier`**: Writing the file runs on the dispatcher, off the
+  watch loop; a failed write is a failure of the notifier, logged and
+  counted, and never rejects the configuration
+- **`tokio::task::spawn_blocking`**: Reading the current file and writing
+  the new one are blocking I/O
+
+**Design decisions**:
+- `--write-normalized FILE` holds the effective configuration after every
+  load, initial or not: the file with the environment and `--override`
+  layers applied and every default materialized, what `show
+  --reveal-secrets` prints. Secrets are included, as in the configuration
+  the application runs with; `--write-normalized-redacted` hides them
+- Canonical form: pretty-printed JSON in the order of `AppConfig`'s
+  fields (feature flags sorted), two-space indent, trailing newline. The
+  same configuration always gives the same bytes
+- The file is only rewritten when those bytes differ from what it holds,
+  so tools watching it see one change per effective change: a reload that
+  only touched formatting, or a change undone by an override, writes
+  nothing
+- Written atomically (`fs_util::write_atomic_with_mode`) with mode 600,
+  whatever the previous file's
+- With several files watched, it holds the configuration loaded last
+
+******************************************************************************/
+
+use crate::cli::EventKind;
+use crate::config::AppConfig;
+use crate::fs_util;
+use crate::notify::{ConfigEvent, Notifier, NotifierOptions};
+use crate::pipeline;
+use std::path::PathBuf;
+
+/// Permissions of the written file: it may hold secrets
+pub const MODE: u32 = 0o600;
+
+/// Writes the effective configuration to a file, when it changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteNormalized {
+    pub path: PathBuf,
+    /// Hides the secret fields
+    pub redacted: bool,
+}
+
+impl WriteNormalized {
+    /// Writes to `path`, secrets included
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            redacted: false,
+        }
+    }
+
+    /// The canonical text of `config`
+    pub fn render(&self, config: &AppConfig) -> serde_json::Result<String> {
+        pipeline::canonical(config, self.redacted)
+    }
+
+    /// Writes `text` unless the file already holds it; true when written
+    pub fn write(&self, text: &str) -> crate::error::Result<bool> {
+        if std::fs::read(&self.path).is_ok_and(|current| current == text.as_bytes()) {
+            return Ok(false);
+        }
+        fs_util::write_atomic_with_mode(&self.path, text.as_bytes(), MODE)?;
+        Ok(true)
+    }
+}
+
+impl Notifier for WriteNormalized {
+    fn name(&self) -> &str {
+        "write-normalized"
+    }
+
+    /// Only the latest configuration is worth writing
+    fn options(&self) -> NotifierOptions {
+        NotifierOptions {
+            queue: 1,
+            ..NotifierOptions::default()
+        }
+    }
+
+    fn accepts(&self, event: &ConfigEvent) -> bool {
+        matches!(event.kind, EventKind::Initial | EventKind::Reload) && event.config.is_some()
+    }
+
+    async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+        let Some(ref config) = event.config else {
+            return Ok(());
+        };
+        let text = self.render(config)?;
+        let this = self.clone();
+        let written = tokio::task::spawn_blocking(move || this.write(&text)).await??;
+        tracing::debug!(
+            file = %self.path.display(),
+            written,
+            "normalized configuration"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{Event, Summary};
+    use crate::overrides::Overrides;
+    use crate::redact;
+    use std::path::Path;
+    use std::time::SystemTime;
+
+    fn loaded(config: &AppConfig) -> ConfigEvent {
+        let overrides = Overrides::default();
+        let event = Event::Loaded {
+            file: Path::new("/etc/app.json"),
+            version: 1,
+            initial: true,
+            summary: Summary {
+                config,
+                overrides: &overrides,
+            },
+            changes: None,
+            flags: None,
+            stand_in: None,
+        };
+        ConfigEvent::new(&event, SystemTime::UNIX_EPOCH).unwrap()
+    }
+
+    #[test]
+    fn test_canonical_and_redacted_forms() {
+        let config = AppConfig::example();
+        let write = WriteNormalized::new("unused");
+        let text = write.render(&config).unwrap();
+        assert_eq!(text, write.render(&config.clone()).unwrap());
+        assert!(text.starts_with("{\n  \"app_name\": \"MyAwesomeApp\",\n  \"version\""));
+        assert!(text.ends_with("}\n"));
+        assert!(text.contains("postgres://localhost/mydb"));
+        // Feature flags in name order, whatever the map's
+        let features = ["debug_mode", "enable_analytics", "enable_caching"]
+            .map(|name| text.find(name).unwrap());
+        assert!(features.is_sorted());
+        let back: AppConfig = serde_json::from_str(&text).unwrap();
+        assert_eq!(back, config);
+
+        let redacted = WriteNormalized {
+            redacted: true,
+            ..write
+        };
+        let text = redacted.render(&config).unwrap();
+        assert!(!text.contains("postgres://"));
+        assert!(text.contains(redact::REDACTED));
+    }
+
+    #[tokio::test]
+    async fn test_rewrites_only_changes_with_restrictive_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("effective-config.json");
+        std::fs::write(&path, "stale").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        }
+        let write = WriteNormalized::new(&path);
+        let mut config = AppConfig::example();
+
+        write.notify(&loaded(&config)).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            write.render(&config).unwrap()
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{MetadataExt, PermissionsExt};
+            let metadata = std::fs::metadata(&path).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, MODE);
+
+            // The same configuration again: the file is left alone (a new
+            // file would have a new inode)
+            write.notify(&loaded(&config)).await.unwrap();
+            assert_eq!(std::fs::metadata(&path).unwrap().ino(), metadata.ino());
+
+            config.version = "2.0.0".to_string();
+            write.notify(&loaded(&config)).await.unwrap();
+            assert_ne!(std::fs::metadata(&path).unwrap().ino(), metadata.ino());
+        }
+        assert!(!write.write(&write.render(&config).unwrap()).unwrap());
+        assert!(
+            std::fs::read_to_string(&path)
+                .unwrap()
+                .contains("\"version\": \"2.0.0\"")
+        );
+        // Replaced by rename: nothing else in the directory
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,880 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`-> impl Future + Send` in a trait**: Implementors write
+  `async fn notify`; the `Send` bound lets the dispatcher box the future
+  (`BoxFuture`) and hold notifiers of any type behind one `dyn` trait
+- **A thread with its own runtime**: Notifiers run on a current-thread
+  `tokio` runtime of their own, never on the watch loop's; the guard can
+  wait for them from synchronous code, e.g. when `main` returns
+- **`tokio::sync::Notify`**: Wakes a notifier's task when its queue gets an
+  event; a permit is kept if the task is busy, so no wake-up is lost
+
+**Design decisions**:
+- Every way of telling the outside world about a reload (`--on-change`,
+  `--webhook-url`, `--notify-desktop`, or a library user's own) is a
+  `Notifier`. The emitter hands each event to the `Dispatcher`, which
+  queues it for every notifier that accepts it and returns at once
+- Only loads, failed loads, file errors and the shutdown become
+  `ConfigEvent`s: they carry the JSON form of the event (secrets redacted)
+  and, for loads, the configuration itself
+- Each notifier has its own queue and task: a slow, hanging or failing one
+  never delays the others nor the watch loop. A call that takes longer
+  than its `timeout` is abandoned and counts as a failure
+- A full queue drops its oldest event: the latest state matters most. A
+  queue of one means "at most one pending call, the latest"
+- An optional token bucket per notifier (`--notify-rate-limit 10/1m`):
+  events beyond it are dropped before being queued
+- Failures, drops and rate-limited events are counted per notifier (and
+  in `Metrics`); the first failure of a streak is a warning, the next ones
+  are only logged at debug level
+- Shutdown waits at most `SHUTDOWN_TIMEOUT` for the queued events
+
+******************************************************************************/
+
+use crate::cli::EventKind;
+use crate::config::AppConfig;
+use crate::metrics::Metrics;
+use crate::output::{Event, to_json_at};
+use anyhow::{Context, bail};
+use futures::future::BoxFuture;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::Notify;
+use tracing::{Instrument, Span};
+
+/// Default time allowed for one call
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default number of events waiting for one notifier; more drop the oldest
+pub const QUEUE: usize = 64;
+
+/// Longest wait for the queued events when the process exits
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An event a notifier can act on
+#[derive(Debug, Clone)]
+pub struct ConfigEvent {
+    /// What happened
+    pub kind: EventKind,
+    /// When the emitter saw it
+    pub at: SystemTime,
+    /// The watched file (`env:PREFIX` with `--from-env`), none for shutdown
+    pub file: Option<PathBuf>,
+    /// The JSON form of the event, as `--output json` prints it, secrets
+    /// redacted: what notifiers send out
+    pub data: Value,
+    /// The configuration just loaded, secrets included: only for notifiers
+    /// that keep it on this machine, like `--on-change`
+    pub config: Option<AppConfig>,
+    /// The span current when the event was emitted; calls run inside it
+    pub span: Span,
+}
+
+impl ConfigEvent {
+    /// The notifiers' view of `event`, which happened `at`, if it has one
+    pub(crate) fn new(event: &Event<'_>, at: SystemTime) -> Option<Self> {
+        let kind = kind(event)?;
+        let (file, config) = match *event {
+            Event::Loaded { file, summary, .. } => (Some(file), Some(summary.config.clone())),
+            Event::LoadFailed { file, .. } | Event::FileError { file, .. } => (Some(file), None),
+            _ => (None, None),
+        };
+        Some(Self {
+            kind,
+            at,
+            file: file.map(|file| file.to_path_buf()),
+            data: to_json_at(event, at),
+            config,
+            span: Span::current(),
+        })
+    }
+
+    /// Configuration version, for loads
+    pub fn version(&self) -> Option<u64> {
+        self.data["version"].as_u64()
+    }
+}
+
+/// Which kind `event` is, if notifiers care about it
+fn kind(event: &Event<'_>) -> Option<EventKind> {
+    match event {
+        Event::Loaded { initial: true, .. } => Some(EventKind::Initial),
+        Event::Loaded { initial: false, .. } => Some(EventKind::Reload),
+        Event::LoadFailed { .. } => Some(EventKind::Failure),
+        Event::FileError { .. } => Some(EventKind::FileError),
+        Event::Shutdown { .. } => Some(EventKind::Shutdown),
+        _ => None,
+    }
+}
+
+/// Tells something outside the process about configuration events
+///
+/// ```
+/// use config_watcher::{ConfigEvent, Emitter, EventKind, Notifier, Notifiers};
+///
+/// struct Log;
+///
+/// impl Notifier for Log {
+///     fn name(&self) -> &str {
+///         "log"
+///     }
+///
+///     fn accepts(&self, event: &ConfigEvent) -> bool {
+///         event.kind == EventKind::Failure
+///     }
+///
+///     async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+///         eprintln!("load failed: {}", event.data["error"]);
+///         Ok(())
+///     }
+/// }
+///
+/// let (dispatcher, _guard) = Notifiers::new().with(Log).start()?;
+/// let emitter = Emitter::default().with_notifiers(dispatcher);
+/// # drop(emitter);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub trait Notifier: Send + Sync + 'static {
+    /// Short name for logs and metrics, e.g. `webhook`
+    fn name(&self) -> &str;
+
+    /// How the dispatcher runs it, unless registered with other options
+    fn options(&self) -> NotifierOptions {
+        NotifierOptions::default()
+    }
+
+    /// Whether `event` is for this notifier; other events are neither
+    /// queued nor counted against the rate limit
+    fn accepts(&self, event: &ConfigEvent) -> bool {
+        let _ = event;
+        true
+    }
+
+    /// Acts on one event
+    fn notify(&self, event: &ConfigEvent) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+/// [`Notifier`] with a boxed future, so that any of them fits in a `Box`
+///
+/// The methods have other names than [`Notifier`]'s, so that calls on a
+/// concrete notifier are never ambiguous.
+trait DynNotifier: Send + Sync {
+    fn label(&self) -> &str;
+    fn wants(&self, event: &ConfigEvent) -> bool;
+    fn call<'a>(&'a self, event: &'a ConfigEvent) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+impl<N: Notifier> DynNotifier for N {
+    fn label(&self) -> &str {
+        self.name()
+    }
+
+    fn wants(&self, event: &ConfigEvent) -> bool {
+        self.accepts(event)
+    }
+
+    fn call<'a>(&'a self, event: &'a ConfigEvent) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(self.notify(event))
+    }
+}
+
+/// How the dispatcher runs one notifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotifierOptions {
+    /// Time allowed for one call
+    pub timeout: Duration,
+    /// Events waiting for it; more drop the oldest
+    pub queue: usize,
+    /// Events accepted beyond this are dropped
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl Default for NotifierOptions {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            queue: QUEUE,
+            rate_limit: None,
+        }
+    }
+}
+
+/// A token bucket: bursts of `events`, refilled at `events` per `period`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Size of a burst
+    pub events: u32,
+    /// Time to refill a whole burst
+    pub period: Duration,
+}
+
+impl FromStr for RateLimit {
+    type Err = anyhow::Error;
+
+    /// `COUNT/DURATION`, e.g. `10/1m`
+    fn from_str(text: &str) -> anyhow::Result<Self> {
+        let (events, period) = text
+            .split_once('/')
+            .with_context(|| format!("'{text}' is not COUNT/DURATION, e.g. 10/1m"))?;
+        let events: u32 = events
+            .trim()
+            .parse()
+            .with_context(|| format!("'{events}' is not a number of events"))?;
+        let period = humantime::parse_duration(period.trim())
+            .with_context(|| format!("'{period}' is not a duration"))?;
+        if events == 0 || period.is_zero() {
+            bail!("'{text}' would drop every event");
+        }
+        Ok(Self { events, period })
+    }
+}
+
+impl fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}",
+            self.events,
+            humantime::format_duration(self.period)
+        )
+    }
+}
+
+/// The tokens left in a [`RateLimit`]
+#[derive(Debug)]
+struct Bucket {
+    limit: RateLimit,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: f64::from(limit.events),
+            updated: now,
+        }
+    }
+
+    /// Takes a token, if one is left `now`
+    fn take(&mut self, now: Instant) -> bool {
+        let capacity = f64::from(self.limit.events);
+        let refill = now.saturating_duration_since(self.updated).as_secs_f64()
+            / self.limit.period.as_secs_f64()
+            * capacity;
+        self.tokens = (self.tokens + refill).min(capacity);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// What happened to the events of one notifier
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NotifierStats {
+    /// Calls that succeeded
+    pub delivered: u64,
+    /// Calls that failed or timed out
+    pub failures: u64,
+    /// Events dropped from a full queue
+    pub dropped: u64,
+    /// Events dropped by the rate limit
+    pub rate_limited: u64,
+}
+
+/// The notifiers to start, in the order they were added
+#[derive(Default)]
+pub struct Notifiers {
+    entries: Vec<(Box<dyn DynNotifier>, NotifierOptions)>,
+    rate_limit: Option<RateLimit>,
+    metrics: Option<Metrics>,
+}
+
+impl Notifiers {
+    /// No notifiers yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `notifier`, run with its own options
+    pub fn with(self, notifier: impl Notifier) -> Self {
+        let options = notifier.options();
+        self.with_options(notifier, options)
+    }
+
+    /// Adds `notifier`, run with `options`
+    pub fn with_options(mut self, notifier: impl Notifier, options: NotifierOptions) -> Self {
+        self.entries.push((Box::new(notifier), options));
+        self
+    }
+
+    /// Limits every notifier that has no rate limit of its own
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    /// Also counts failures and drops in `metrics`
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// True when nothing was added
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Starts the thread running the notifiers
+    pub fn start(self) -> io::Result<(Dispatcher, DispatcherGuard)> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let now = Instant::now();
+        let slots: Arc<[Arc<Slot>]> = self
+            .entries
+            .into_iter()
+            .map(|(notifier, options)| {
+                let name = notifier.label().to_string();
+                if let Some(ref metrics) = self.metrics {
+                    metrics.register_notifier(&name);
+                }
+                let limit = options.rate_limit.or(self.rate_limit);
+                Arc::new(Slot {
+                    name,
+                    notifier,
+                    timeout: options.timeout,
+                    capacity: options.queue.max(1),
+                    pending: Mutex::default(),
+                    ready: Notify::new(),
+                    closed: AtomicBool::new(false),
+                    bucket: Mutex::new(limit.map(|limit| Bucket::new(limit, now))),
+                    stats: Mutex::default(),
+                    metrics: self.metrics.clone(),
+                })
+            })
+            .collect();
+
+        let (done_sender, done) = mpsc::channel();
+        let thread = {
+            let slots = Arc::clone(&slots);
+            std::thread::Builder::new()
+                .name("notifiers".to_string())
+                .spawn(move || {
+                    runtime.block_on(async {
+                        let tasks: Vec<_> = slots
+                            .iter()
+                            .map(|slot| tokio::spawn(Arc::clone(slot).run()))
+                            .collect();
+                        for task in tasks {
+                            let _ = task.await;
+                        }
+                    });
+                    let _ = done_sender.send(());
+                })?
+        };
+        Ok((
+            Dispatcher {
+                slots: Arc::clone(&slots),
+            },
+            DispatcherGuard {
+                slots,
+                done,
+                thread: Some(thread),
+            },
+        ))
+    }
+}
+
+/// Hands events to the running notifiers
+///
+/// Cloning is cheap: every clone feeds the same notifiers.
+#[derive(Clone)]
+pub struct Dispatcher {
+    slots: Arc<[Arc<Slot>]>,
+}
+
+/// Stops the notifiers after the queued events are handled
+pub struct DispatcherGuard {
+    slots: Arc<[Arc<Slot>]>,
+    done: Receiver<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// One notifier, its queue and its counters
+struct Slot {
+    name: String,
+    notifier: Box<dyn DynNotifier>,
+    timeout: Duration,
+    capacity: usize,
+    pending: Mutex<VecDeque<Arc<ConfigEvent>>>,
+    ready: Notify,
+    closed: AtomicBool,
+    bucket: Mutex<Option<Bucket>>,
+    stats: Mutex<NotifierStats>,
+    metrics: Option<Metrics>,
+}
+
+impl Dispatcher {
+    /// Queues `event`, which happened `at`, for every notifier that
+    /// accepts it
+    ///
+    /// Never waits for a notifier.
+    pub(crate) fn dispatch(&self, event: &Event<'_>, at: SystemTime) {
+        if let Some(event) = ConfigEvent::new(event, at) {
+            self.send(event);
+        }
+    }
+
+    /// Queues `event` for every notifier that accepts it
+    pub fn send(&self, event: ConfigEvent) {
+        let event = Arc::new(event);
+        let now = Instant::now();
+        for slot in self.slots.iter() {
+            if slot.notifier.wants(&event) {
+                slot.offer(Arc::clone(&event), now);
+            }
+        }
+    }
+
+    /// Each notifier's name and counters, in the order they were added
+    pub fn stats(&self) -> Vec<(String, NotifierStats)> {
+        self.slots
+            .iter()
+            .map(|slot| (slot.name.clone(), *slot.stats()))
+            .collect()
+    }
+}
+
+impl fmt::Debug for Dispatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.slots.iter().map(|slot| &slot.name))
+            .finish()
+    }
+}
+
+impl DispatcherGuard {
+    /// Handles what is queued, waiting at most `timeout`
+    ///
+    /// Returns `false` when the notifiers did not finish in time; their
+    /// thread is then abandoned.
+    pub fn shutdown(&mut self, timeout: Duration) -> bool {
+        let Some(thread) = self.thread.take() else {
+            return true;
+        };
+        for slot in self.slots.iter() {
+            slot.closed.store(true, Ordering::Relaxed);
+            slot.ready.notify_one();
+        }
+        if self.done.recv_timeout(timeout).is_err() {
+            return false;
+        }
+        let _ = thread.join();
+        true
+    }
+}
+
+impl Drop for DispatcherGuard {
+    fn drop(&mut self) {
+        self.shutdown(SHUTDOWN_TIMEOUT);
+    }
+}
+
+impl fmt::Debug for DispatcherGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DispatcherGuard")
+            .field("running", &self.thread.is_some())
+            .finish()
+    }
+}
+
+impl Slot {
+    fn stats(&self) -> std::sync::MutexGuard<'_, NotifierStats> {
+        self.stats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queues `event` unless rate-limited, dropping the oldest if full
+    fn offer(&self, event: Arc<ConfigEvent>, now: Instant) {
+        let allowed = match *self.bucket.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(ref mut bucket) => bucket.take(now),
+            None => true,
+        };
+        if !allowed {
+            let mut stats = self.stats();
+            stats.rate_limited += 1;
+            if stats.rate_limited == 1 {
+                tracing::warn!(
+                    notifier = self.name,
+                    "notifier rate limit reached, dropping events"
+                );
+            }
+            if let Some(ref metrics) = self.metrics {
+                metrics.record_notifier_drop(&self.name, "rate_limited");
+            }
+            return;
+        }
+
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if pending.len() >= self.capacity {
+            pending.pop_front();
+            let mut stats = self.stats();
+            stats.dropped += 1;
+            if stats.dropped == 1 && self.capacity > 1 {
+                tracing::warn!(
+                    notifier = self.name,
+                    capacity = self.capacity,
+                    "notifier queue full, dropping the oldest events"
+                );
+            }
+            if let Some(ref metrics) = self.metrics {
+                metrics.record_notifier_drop(&self.name, "queue_full");
+            }
+        }
+        pending.push_back(event);
+        self.ready.notify_one();
+    }
+
+    /// The next event; `None` once closed with nothing left
+    async fn next(&self) -> Option<Arc<ConfigEvent>> {
+        loop {
+            {
+                let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(event) = pending.pop_front() {
+                    return Some(event);
+                }
+                if self.closed.load(Ordering::Relaxed) {
+                    return None;
+                }
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    /// Calls the notifier for every event until closed
+    async fn run(self: Arc<Self>) {
+        let mut failing = false;
+        while let Some(event) = self.next().await {
+            let call = tokio::time::timeout(self.timeout, self.notifier.call(&event))
+                .instrument(event.span.clone())
+                .await;
+            let error = match call {
+                Ok(Ok(())) => {
+                    self.stats().delivered += 1;
+                    failing = false;
+                    continue;
+                }
+                Ok(Err(e)) => format!("{e:#}"),
+                Err(_) => format!(
+                    "no answer after {}",
+                    humantime::format_duration(self.timeout)
+                ),
+            };
+            self.stats().failures += 1;
+            if let Some(ref metrics) = self.metrics {
+                metrics.record_notifier_failure(&self.name);
+            }
+            if failing {
+                tracing::debug!(notifier = self.name, error, "notifier failed");
+            } else {
+                failing = true;
+                tracing::warn!(notifier = self.name, error, "notifier failed");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::Summary;
+    use crate::overrides::Overrides;
+    use std::path::Path;
+
+    /// Hands every event over to the test, after `delay`
+    struct Recorder {
+        name: &'static str,
+        delay: Duration,
+        sender: Mutex<mpsc::Sender<(&'static str, EventKind)>>,
+    }
+
+    impl Notifier for Recorder {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+            tokio::time::sleep(self.delay).await;
+            let _ = self.sender.lock().unwrap().send((self.name, event.kind));
+            Ok(())
+        }
+    }
+
+    /// Always fails, or never answers
+    struct Broken {
+        hang: bool,
+    }
+
+    impl Notifier for Broken {
+        fn name(&self) -> &str {
+            if self.hang { "hanging" } else { "failing" }
+        }
+
+        async fn notify(&self, _: &ConfigEvent) -> anyhow::Result<()> {
+            if self.hang {
+                std::future::pending::<()>().await;
+            }
+            bail!("receiver unreachable")
+        }
+    }
+
+    /// Only takes failures
+    struct FailuresOnly(Recorder);
+
+    impl Notifier for FailuresOnly {
+        fn name(&self) -> &str {
+            self.0.name
+        }
+
+        fn accepts(&self, event: &ConfigEvent) -> bool {
+            event.kind == EventKind::Failure
+        }
+
+        async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+            self.0.notify(event).await
+        }
+    }
+
+    fn recorder(
+        name: &'static str,
+        delay: Duration,
+    ) -> (Recorder, mpsc::Receiver<(&'static str, EventKind)>) {
+        let (sender, receiver) = mpsc::channel();
+        let recorder = Recorder {
+            name,
+            delay,
+            sender: Mutex::new(sender),
+        };
+        (recorder, receiver)
+    }
+
+    fn event(kind: EventKind) -> ConfigEvent {
+        ConfigEvent {
+            kind,
+            at: SystemTime::UNIX_EPOCH,
+            file: Some(PathBuf::from("app.json")),
+            data: Value::Null,
+            config: None,
+            span: Span::none(),
+        }
+    }
+
+    fn stats(dispatcher: &Dispatcher, name: &str) -> NotifierStats {
+        dispatcher
+            .stats()
+            .into_iter()
+            .find(|(n, _)| n == name)
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn test_config_event_from_event() {
+        let config = AppConfig::example();
+        let overrides = Overrides::default();
+        let loaded = Event::Loaded {
+            file: Path::new("app.json"),
+            version: 3,
+            initial: false,
+            summary: Summary {
+                config: &config,
+                overrides: &overrides,
+            },
+            changes: Some(&[]),
+            flags: None,
+            stand_in: None,
+        };
+        let event = ConfigEvent::new(&loaded, SystemTime::UNIX_EPOCH).unwrap();
+        assert_eq!(event.kind, EventKind::Reload);
+        assert_eq!(event.file, Some(PathBuf::from("app.json")));
+        assert_eq!(event.version(), Some(3));
+        assert_eq!(event.data["event"], "loaded");
+        assert_eq!(event.data["timestamp"], "1970-01-01T00:00:00.000Z");
+        assert_eq!(event.config, Some(config));
+
+        // Nothing to notify about
+        let detected = Event::ChangeDetected {
+            file: Path::new("app.json"),
+        };
+        assert!(ConfigEvent::new(&detected, SystemTime::UNIX_EPOCH).is_none());
+    }
+
+    #[test]
+    fn test_fan_out_to_every_notifier() {
+        let (first, first_events) = recorder("first", Duration::ZERO);
+        let (second, second_events) = recorder("second", Duration::ZERO);
+        let (only, only_events) = recorder("failures", Duration::ZERO);
+        let (dispatcher, mut guard) = Notifiers::new()
+            .with(first)
+            .with(second)
+            .with(FailuresOnly(only))
+            .start()
+            .unwrap();
+
+        dispatcher.send(event(EventKind::Reload));
+        dispatcher.send(event(EventKind::Failure));
+        assert!(guard.shutdown(Duration::from_secs(5)));
+
+        let kinds = |events: mpsc::Receiver<(&str, EventKind)>| {
+            events.try_iter().map(|(_, kind)| kind).collect::<Vec<_>>()
+        };
+        assert_eq!(kinds(first_events), [EventKind::Reload, EventKind::Failure]);
+        assert_eq!(
+            kinds(second_events),
+            [EventKind::Reload, EventKind::Failure]
+        );
+        assert_eq!(kinds(only_events), [EventKind::Failure]);
+        assert_eq!(stats(&dispatcher, "failures").delivered, 1);
+    }
+
+    #[test]
+    fn test_failing_and_hanging_notifiers_are_isolated() {
+        let (healthy, events) = recorder("healthy", Duration::ZERO);
+        let metrics = Metrics::default();
+        let (dispatcher, mut guard) = Notifiers::new()
+            .with(Broken { hang: false })
+            .with_options(
+                Broken { hang: true },
+                NotifierOptions {
+                    timeout: Duration::from_millis(50),
+                    ..NotifierOptions::default()
+                },
+            )
+            .with(healthy)
+            .with_metrics(metrics.clone())
+            .start()
+            .unwrap();
+
+        let started = Instant::now();
+        for _ in 0..3 {
+            dispatcher.send(event(EventKind::Reload));
+        }
+        // The healthy one does not wait for the hanging one
+        for _ in 0..3 {
+            events.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+        assert!(started.elapsed() < Duration::from_millis(140));
+        assert!(guard.shutdown(Duration::from_secs(5)));
+
+        assert_eq!(stats(&dispatcher, "failing").failures, 3);
+        assert_eq!(stats(&dispatcher, "hanging").failures, 3);
+        assert_eq!(stats(&dispatcher, "healthy").delivered, 3);
+        assert_eq!(stats(&dispatcher, "healthy").failures, 0);
+        let counts = &metrics.snapshot().notifiers;
+        assert_eq!(counts["failing"].failures, 3);
+        assert_eq!(counts["healthy"].failures, 0);
+    }
+
+    #[test]
+    fn test_full_queue_drops_the_oldest() {
+        let (slow, events) = recorder("slow", Duration::from_millis(200));
+        let (dispatcher, mut guard) = Notifiers::new()
+            .with_options(
+                slow,
+                NotifierOptions {
+                    queue: 2,
+                    ..NotifierOptions::default()
+                },
+            )
+            .start()
+            .unwrap();
+
+        dispatcher.send(event(EventKind::Initial));
+        std::thread::sleep(Duration::from_millis(50));
+        // The first one is in progress; of these, the last two stay queued
+        let started = Instant::now();
+        dispatcher.send(event(EventKind::Reload));
+        dispatcher.send(event(EventKind::FileError));
+        dispatcher.send(event(EventKind::Failure));
+        assert!(started.elapsed() < Duration::from_millis(50));
+        assert!(guard.shutdown(Duration::from_secs(5)));
+
+        let kinds: Vec<_> = events.try_iter().map(|(_, kind)| kind).collect();
+        assert_eq!(
+            kinds,
+            [EventKind::Initial, EventKind::FileError, EventKind::Failure]
+        );
+        assert_eq!(stats(&dispatcher, "slow").dropped, 1);
+    }
+
+    #[test]
+    fn test_token_bucket_per_notifier() {
+        let (limited, limited_events) = recorder("limited", Duration::ZERO);
+        let (own, own_events) = recorder("own", Duration::ZERO);
+        let (dispatcher, mut guard) = Notifiers::new()
+            .with(limited)
+            .with_options(
+                own,
+                NotifierOptions {
+                    rate_limit: Some("5/1h".parse().unwrap()),
+                    ..NotifierOptions::default()
+                },
+            )
+            .with_rate_limit("2/1h".parse().unwrap())
+            .start()
+            .unwrap();
+
+        for _ in 0..10 {
+            dispatcher.send(event(EventKind::Failure));
+        }
+        assert!(guard.shutdown(Duration::from_secs(5)));
+        assert_eq!(limited_events.try_iter().count(), 2);
+        assert_eq!(own_events.try_iter().count(), 5);
+        assert_eq!(stats(&dispatcher, "limited").rate_limited, 8);
+        assert_eq!(stats(&dispatcher, "own").rate_limited, 5);
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new("2/10s".parse().unwrap(), start);
+        assert!(bucket.take(start));
+        assert!(bucket.take(start));
+        assert!(!bucket.take(start));
+        // One token every 5s
+        assert!(!bucket.take(start + Duration::from_secs(4)));
+        assert!(bucket.take(start + Duration::from_secs(5)));
+        // Never more than the burst
+        let later = start + Duration::from_secs(3600);
+        assert!(bucket.take(later));
+        assert!(bucket.take(later));
+        assert!(!bucket.take(later));
+    }
+
+    #[test]
+    fn test_parse_rate_limit() {
+        let limit: RateLimit = "10/1m".parse().unwrap();
+        assert_eq!(limit.events, 10);
+        assert_eq!(limit.period, Duration::from_secs(60));
+        assert_eq!(limit.to_string(), "10/1m");
+        for bad in ["10", "x/1m", "10/soon", "0/1m", "10/0s"] {
+            assert!(bad.parse::<RateLimit>().is_err(), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,856 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`Layer::on_close`**: A finished span becomes one OTLP span; its ids,
+  start time and fields live in the span's extensions until then
+- **`std::sync::mpsc::sync_channel`**: A bounded queue to the exporter
+  thread; `try_send` drops a span rather than block the watcher
+- **`Filtered`**: The layer has its own filter (`debug` for this crate), so
+  the reload spans are exported whatever `--log-level` says
+
+**Design decisions**:
+- `--otlp-endpoint` (or `OTEL_EXPORTER_OTLP_ENDPOINT`) speaks OTLP/HTTP
+  with the JSON encoding: spans go to `/v1/traces`, metrics to
+  `/v1/metrics` (port 4318 on a stock collector), over http:// or
+  https://. gRPC (port 4317) is not built in; `OTEL_EXPORTER_OTLP_PROTOCOL`
+  other than `http/json` is refused at startup
+- The standard variables are honored: `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`
+  and `_METRICS_ENDPOINT` (used as is), `_HEADERS`, `_TIMEOUT`,
+  `OTEL_BSP_SCHEDULE_DELAY`, `OTEL_METRIC_EXPORT_INTERVAL`,
+  `OTEL_SERVICE_NAME` and `OTEL_RESOURCE_ATTRIBUTES`
+- Exports go through the proxy the configuration's `proxy` section picks
+  for the collector (`no_proxy` included), as loaded by the watchers
+- The resource is computed at every export: `service.name` is the loaded
+  `app_name` (unless `OTEL_SERVICE_NAME` is set), with `service.version`
+  and `deployment.environment` from the same configuration
+- Metrics are the counters of `metrics::Metrics`, exported as cumulative
+  sums and a histogram, so `--metrics-addr` and OTLP always agree
+- Exporting never affects watching: spans are dropped when the queue is
+  full, a failed request is dropped (the first failure of a series is
+  logged, the rest only at debug), and shutdown waits at most
+  `SHUTDOWN_TIMEOUT` for the final flush
+
+******************************************************************************/
+
+use crate::http_client::{self, parse_url};
+use crate::logging::JsonFields;
+use crate::metrics::{BUCKETS, Metrics, RESULTS};
+use crate::proxy::CurrentProxy;
+use anyhow::{Context as _, bail};
+use serde_json::{Map, Value, json};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::Registry;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+use url::Url;
+
+/// Longest wait for the final export when the process exits
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Spans waiting for the exporter; more are dropped
+const QUEUE: usize = 2048;
+
+/// Most spans sent in one request
+const BATCH: usize = 512;
+
+/// Most events kept on one span
+const MAX_EVENTS: usize = 128;
+
+/// Where and how to export
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OtlpConfig {
+    pub traces: Url,
+    pub metrics: Url,
+    pub headers: Vec<(String, String)>,
+    pub timeout: Duration,
+    pub span_delay: Duration,
+    pub metric_interval: Duration,
+    pub service_name: Option<String>,
+    pub resource: Vec<(String, String)>,
+}
+
+impl OtlpConfig {
+    /// Settings for `endpoint`, completed by the `OTEL_*` variables that
+    /// `var` returns
+    pub fn new(endpoint: &str, var: impl Fn(&str) -> Option<String>) -> anyhow::Result<Self> {
+        for name in [
+            "OTEL_EXPORTER_OTLP_PROTOCOL",
+            "OTEL_EXPORTER_OTLP_TRACES_PROTOCOL",
+            "OTEL_EXPORTER_OTLP_METRICS_PROTOCOL",
+        ] {
+            if let Some(protocol) = var(name)
+                && protocol != "http/json"
+            {
+                bail!("{name}={protocol} is not supported; only http/json is");
+            }
+        }
+        let signal = |name: &str, path: &str| match var(name) {
+            Some(url) => parse_url(&url).with_context(|| format!("Invalid {name}")),
+            None => parse_url(&format!("{}/{path}", endpoint.trim_end_matches('/')))
+                .context("Invalid --otlp-endpoint"),
+        };
+        let millis = |name: &str, default: u64| -> anyhow::Result<Duration> {
+            let millis = match var(name) {
+                Some(text) => text
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("Invalid {name}: expected milliseconds"))?,
+                None => default,
+            };
+            Ok(Duration::from_millis(millis))
+        };
+        Ok(Self {
+            traces: signal("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT", "v1/traces")?,
+            metrics: signal("OTEL_EXPORTER_OTLP_METRICS_ENDPOINT", "v1/metrics")?,
+            headers: pairs(var("OTEL_EXPORTER_OTLP_HEADERS").as_deref()),
+            timeout: millis("OTEL_EXPORTER_OTLP_TIMEOUT", 10_000)?,
+            span_delay: millis("OTEL_BSP_SCHEDULE_DELAY", 5_000)?,
+            metric_interval: millis("OTEL_METRIC_EXPORT_INTERVAL", 60_000)?,
+            service_name: var("OTEL_SERVICE_NAME").filter(|name| !name.is_empty()),
+            resource: pairs(var("OTEL_RESOURCE_ATTRIBUTES").as_deref()),
+        })
+    }
+}
+
+/// `key=value` pairs separated by commas, as in `OTEL_EXPORTER_OTLP_HEADERS`
+fn pairs(text: Option<&str>) -> Vec<(String, String)> {
+    text.unwrap_or_default()
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+/// What the exporter thread receives
+enum Message {
+    Span(Value),
+    Shutdown,
+}
+
+/// Turns finished spans into OTLP spans
+pub struct OtlpLayer {
+    sender: SyncSender<Message>,
+}
+
+/// Stops the exporter thread after a final export
+#[derive(Debug)]
+pub struct ExporterGuard {
+    sender: SyncSender<Message>,
+    stop: Arc<AtomicBool>,
+    done: Receiver<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Starts the exporter thread
+///
+/// The layer only sees this crate's spans and events, up to `debug`.
+pub fn start(
+    config: OtlpConfig,
+    metrics: Metrics,
+    proxy: CurrentProxy,
+) -> std::io::Result<(Box<dyn Layer<Registry> + Send + Sync>, ExporterGuard)> {
+    let (sender, receiver) = mpsc::sync_channel(QUEUE);
+    let (done_sender, done) = mpsc::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let exporter = Exporter {
+        config,
+        metrics,
+        proxy,
+        started: SystemTime::now(),
+        failing: false,
+    };
+    let thread = {
+        let stop = Arc::clone(&stop);
+        std::thread::Builder::new()
+            .name("otlp-exporter".to_string())
+            .spawn(move || run(exporter, receiver, &stop, done_sender))?
+    };
+    let layer = OtlpLayer {
+        sender: sender.clone(),
+    }
+    .with_filter(Targets::new().with_target("config_watcher", Level::DEBUG));
+    Ok((
+        Box::new(layer),
+        ExporterGuard {
+            sender,
+            stop,
+            done,
+            thread: Some(thread),
+        },
+    ))
+}
+
+impl ExporterGuard {
+    /// Exports what is left, waiting at most `timeout`
+    ///
+    /// Returns `false` when the export did not finish in time; the thread
+    /// is then abandoned.
+    pub fn shutdown(&mut self, timeout: Duration) -> bool {
+        let Some(thread) = self.thread.take() else {
+            return true;
+        };
+        self.stop.store(true, Ordering::Relaxed);
+        // A full queue is drained first; the flag stops the thread then
+        let _ = self.sender.try_send(Message::Shutdown);
+        if self.done.recv_timeout(timeout).is_err() {
+            return false;
+        }
+        let _ = thread.join();
+        true
+    }
+}
+
+impl Drop for ExporterGuard {
+    fn drop(&mut self) {
+        self.shutdown(SHUTDOWN_TIMEOUT);
+    }
+}
+
+/// Sends spans in batches and metrics periodically until told to stop
+fn run(mut exporter: Exporter, receiver: Receiver<Message>, stop: &AtomicBool, done: Sender<()>) {
+    let mut batch = Vec::new();
+    let mut next_spans = Instant::now() + exporter.config.span_delay;
+    let mut next_metrics = Instant::now() + exporter.config.metric_interval;
+    loop {
+        let wait = next_spans
+            .min(next_metrics)
+            .saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(wait) {
+            Ok(Message::Span(span)) => batch.push(span),
+            Ok(Message::Shutdown) | Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let now = Instant::now();
+        if batch.len() >= BATCH || now >= next_spans {
+            exporter.export_spans(std::mem::take(&mut batch));
+            next_spans = now + exporter.config.span_delay;
+        }
+        if now >= next_metrics {
+            exporter.export_metrics();
+            next_metrics = now + exporter.config.metric_interval;
+        }
+    }
+
+    while let Ok(Message::Span(span)) = receiver.try_recv() {
+        batch.push(span);
+    }
+    while !batch.is_empty() {
+        let rest = batch.split_off(batch.len().min(BATCH));
+        exporter.export_spans(std::mem::replace(&mut batch, rest));
+    }
+    exporter.export_metrics();
+    let _ = done.send(());
+}
+
+/// Builds and sends the OTLP requests
+struct Exporter {
+    config: OtlpConfig,
+    metrics: Metrics,
+    proxy: CurrentProxy,
+    started: SystemTime,
+    failing: bool,
+}
+
+impl Exporter {
+    fn export_spans(&mut self, spans: Vec<Value>) {
+        if spans.is_empty() {
+            return;
+        }
+        let body = json!({
+            "resourceSpans": [{
+                "resource": self.resource(),
+                "scopeSpans": [{ "scope": scope(), "spans": spans }],
+            }],
+        });
+        let url = self.config.traces.clone();
+        self.send(&url, &body);
+    }
+
+    fn export_metrics(&mut self) {
+        let snapshot = self.metrics.snapshot();
+        let start = nanos(self.started);
+        let now = nanos(SystemTime::now());
+        let point = |attributes: Vec<Value>, value: u64| {
+            json!({
+                "attributes": attributes,
+                "startTimeUnixNano": start,
+                "timeUnixNano": now,
+                "asInt": value.to_string(),
+            })
+        };
+        let counter = |name: &str, unit: &str, description: &str, points: Vec<Value>| {
+            json!({
+                "name": name,
+                "unit": unit,
+                "description": description,
+                "sum": {
+                    "aggregationTemporality": 2,
+                    "isMonotonic": true,
+                    "dataPoints": points,
+                },
+            })
+        };
+
+        // OTLP buckets are per bound, plus one above the last bound
+        let mut bucket_counts = Vec::new();
+        let mut below = 0;
+        for cumulative in snapshot.buckets {
+            bucket_counts.push((cumulative - below).to_string());
+            below = cumulative;
+        }
+        bucket_counts.push((snapshot.duration_count - below).to_string());
+
+        let body = json!({
+            "resourceMetrics": [{
+                "resource": self.resource(),
+                "scopeMetrics": [{
+                    "scope": scope(),
+                    "metrics": [
+                        counter(
+                            "configwatcher.reloads",
+                            "{reload}",
+                            "Configuration loads, by outcome",
+                            RESULTS
+                                .iter()
+                                .zip(snapshot.reloads)
+                                .map(|(result, count)| point(vec![attribute("result", &json!(result))], count))
+                                .collect(),
+                        ),
+                        {
+                            "name": "configwatcher.reload.duration",
+                            "unit": "s",
+                            "description": "Time to read, parse and validate the configuration",
+                            "histogram": {
+                                "aggregationTemporality": 2,
+                                "dataPoints": [{
+                                    "startTimeUnixNano": start,
+                                    "timeUnixNano": now,
+                                    "count": snapshot.duration_count.to_string(),
+                                    "sum": snapshot.duration_sum,
+                                    "bucketCounts": bucket_counts,
+                                    "explicitBounds": BUCKETS,
+                                }],
+                            },
+                        },
+                        counter(
+                            "configwatcher.watch.errors",
+                            "{error}",
+                            "Failed checks of a watched file",
+                            vec![point(Vec::new(), snapshot.watch_errors)],
+                        ),
+                        counter(
+                            "configwatcher.notifier.failures",
+                            "{call}",
+                            "Notifier calls that failed or timed out",
+                            snapshot
+                                .notifiers
+                                .iter()
+                                .map(|(name, counts)| point(vec![attribute("notifier", &json!(name))], counts.failures))
+                                .collect(),
+                        ),
+                        counter(
+                            "configwatcher.notifier.dropped",
+                            "{event}",
+                            "Events a notifier dropped, by reason",
+                            snapshot
+                                .notifiers
+                                .iter()
+                                .flat_map(|(name, counts)| {
+                                    [("queue_full", counts.queue_full), ("rate_limited", counts.rate_limited)]
+                                        .map(|(reason, count)| {
+                                            point(
+                                                vec![
+                                                    attribute("notifier", &json!(name)),
+                                                    attribute("reason", &json!(reason)),
+                                                ],
+                                                count,
+                                            )
+                                        })
+                                })
+                                .collect(),
+                        ),
+                    ],
+                }],
+            }],
+        });
+        let url = self.config.metrics.clone();
+        self.send(&url, &body);
+    }
+
+    /// Resource attributes, from the configuration loaded right now
+    fn resource(&self) -> Value {
+        let snapshot = self.metrics.snapshot();
+        let loaded = snapshot.info.first();
+        let mut attributes: Vec<(String, String)> = Vec::new();
+        let service_name = self
+            .config
+            .service_name
+            .clone()
+            .or_else(|| loaded.map(|[app_name, ..]| app_name.clone()))
+            .unwrap_or_else(|| "config-watcher".to_string());
+        attributes.push(("service.name".to_string(), service_name));
+        if let Some([_, version, environment]) = loaded {
+            attributes.push(("service.version".to_string(), version.clone()));
+            attributes.push(("deployment.environment".to_string(), environment.clone()));
+        }
+        for (key, value) in &self.config.resource {
+            if !attributes.iter().any(|(known, _)| known == key) {
+                attributes.push((key.clone(), value.clone()));
+            }
+        }
+        json!({
+            "attributes": attributes
+                .iter()
+                .map(|(key, value)| attribute(key, &json!(value)))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    /// Posts `body`; failures are logged, never returned
+    fn send(&mut self, url: &Url, body: &Value) {
+        let proxy = self.proxy.proxy_for(url);
+        match post(
+            url,
+            &self.config.headers,
+            self.config.timeout,
+            proxy.as_deref(),
+            body,
+        ) {
+            Ok(()) => self.failing = false,
+            Err(e) if self.failing => tracing::debug!(url = %url, error = %e, "OTLP export failed"),
+            Err(e) => {
+                self.failing = true;
+                tracing::warn!(url = %url, error = %e, "OTLP export failed, data dropped until the collector answers");
+            }
+        }
+    }
+}
+
+/// One POST of a JSON body, through `proxy` if any; any status but 2xx is
+/// an error
+fn post(
+    url: &Url,
+    headers: &[(String, String)],
+    timeout: Duration,
+    proxy: Option<&str>,
+    body: &Value,
+) -> std::io::Result<()> {
+    let response = http_client::post(url, headers, timeout, proxy, &body.to_string())?;
+    if response.is_success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "collector answered '{}'",
+            response.line
+        )))
+    }
+}
+
+/// Ids, timing and fields of an open span
+struct OtelSpan {
+    trace_id: u128,
+    span_id: u64,
+    parent_id: Option<u64>,
+    start: SystemTime,
+    attributes: Map<String, Value>,
+    events: Vec<Value>,
+}
+
+impl<S> Layer<S> for OtlpLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let parent = span.parent().and_then(|parent| {
+            parent
+                .extensions()
+                .get::<OtelSpan>()
+                .map(|otel| (otel.trace_id, otel.span_id))
+        });
+        let mut attributes = Map::new();
+        attrs.record(&mut JsonFields(&mut attributes));
+        span.extensions_mut().insert(OtelSpan {
+            trace_id: parent
+                .map(|(trace_id, _)| trace_id)
+                .unwrap_or_else(|| u128::from(random_id()) << 64 | u128::from(random_id())),
+            span_id: random_id(),
+            parent_id: parent.map(|(_, span_id)| span_id),
+            start: SystemTime::now(),
+            attributes,
+            events: Vec::new(),
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(otel) = span.extensions_mut().get_mut::<OtelSpan>()
+        {
+            values.record(&mut JsonFields(&mut otel.attributes));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.event_span(event)
+            && let Some(otel) = span.extensions_mut().get_mut::<OtelSpan>()
+            && otel.events.len() < MAX_EVENTS
+        {
+            let mut fields = Map::new();
+            event.record(&mut JsonFields(&mut fields));
+            let name = match fields.remove("message") {
+                Some(Value::String(message)) => message,
+                _ => event.metadata().name().to_string(),
+            };
+            otel.events.push(json!({
+                "timeUnixNano": nanos(SystemTime::now()),
+                "name": name,
+                "attributes": attributes(&fields),
+            }));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(otel) = span.extensions_mut().remove::<OtelSpan>() else {
+            return;
+        };
+        let status = match otel.attributes.get("outcome").and_then(Value::as_str) {
+            Some("ok") => json!({ "code": 1 }),
+            Some("error") => json!({ "code": 2 }),
+            _ => json!({}),
+        };
+        let mut record = json!({
+            "traceId": format!("{:032x}", otel.trace_id),
+            "spanId": format!("{:016x}", otel.span_id),
+            "name": span.name(),
+            "kind": 1,
+            "startTimeUnixNano": nanos(otel.start),
+            "endTimeUnixNano": nanos(SystemTime::now()),
+            "attributes": attributes(&otel.attributes),
+            "events": otel.events,
+            "status": status,
+        });
+        if let Some(parent_id) = otel.parent_id {
+            record["parentSpanId"] = json!(format!("{parent_id:016x}"));
+        }
+        // Dropped when the exporter is behind
+        let _ = self.sender.try_send(Message::Span(record));
+    }
+}
+
+/// A random non-zero id
+fn random_id() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    hasher.finish().max(1)
+}
+
+/// Nanoseconds since the Unix epoch, as OTLP/JSON wants them: a string
+fn nanos(at: SystemTime) -> String {
+    at.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+fn scope() -> Value {
+    json!({ "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") })
+}
+
+fn attributes(fields: &Map<String, Value>) -> Vec<Value> {
+    fields
+        .iter()
+        .map(|(key, value)| attribute(key, value))
+        .collect()
+}
+
+/// One OTLP `KeyValue`
+fn attribute(key: &str, value: &Value) -> Value {
+    json!({ "key": key, "value": any_value(value) })
+}
+
+fn any_value(value: &Value) -> Value {
+    match value {
+        Value::String(text) => json!({ "stringValue": text }),
+        Value::Bool(flag) => json!({ "boolValue": flag }),
+        Value::Number(number) if number.is_f64() => json!({ "doubleValue": number }),
+        Value::Number(number) => json!({ "intValue": number.to_string() }),
+        Value::Array(items) => {
+            json!({ "arrayValue": { "values": items.iter().map(any_value).collect::<Vec<_>>() } })
+        }
+        other => json!({ "stringValue": other.to_string() }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::watcher::ConfigWatcher;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// A fake collector: answers 200 to everything and hands over each
+    /// request's path and JSON body
+    fn collector() -> (std::net::SocketAddr, Receiver<(String, Value)>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                // Handed over before the answer: once the exporter has
+                // it, the test sees the request
+                if sender.send(read_request(&mut stream)).is_err() {
+                    break;
+                }
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+            }
+        });
+        (addr, receiver)
+    }
+
+    fn read_request(stream: &mut TcpStream) -> (String, Value) {
+        let mut data = Vec::new();
+        let mut buf = [0; 4096];
+        let (head, length) = loop {
+            let n = stream.read(&mut buf).unwrap();
+            data.extend_from_slice(&buf[..n]);
+            if let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+                let head = String::from_utf8_lossy(&data[..end]).to_string();
+                let length: usize = head
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+                    .unwrap()
+                    .1
+                    .trim()
+                    .parse()
+                    .unwrap();
+                data.drain(..end + 4);
+                break (head, length);
+            }
+        };
+        while data.len() < length {
+            let n = stream.read(&mut buf).unwrap();
+            data.extend_from_slice(&buf[..n]);
+        }
+        let path = head.split_whitespace().nth(1).unwrap().to_string();
+        (path, serde_json::from_slice(&data).unwrap())
+    }
+
+    /// The string value of attribute `key`
+    fn attribute_value<'a>(attributes: &'a Value, key: &str) -> Option<&'a Value> {
+        attributes
+            .as_array()?
+            .iter()
+            .find(|attribute| attribute["key"] == key)
+            .map(|attribute| &attribute["value"])
+    }
+
+    #[tokio::test]
+    async fn test_spans_and_metrics_reach_the_collector() {
+        let (addr, requests) = collector();
+        let config = OtlpConfig::new(&format!("http://{addr}"), |_| None).unwrap();
+        let metrics = Metrics::default();
+        let (layer, mut guard) = start(config, metrics.clone(), CurrentProxy::default()).unwrap();
+        let _default = tracing::subscriber::set_default(tracing_subscriber::registry().with(layer));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            r#"{ "app_name": "A", "version": "1.2.3", "environment": "staging" }"#,
+        )
+        .unwrap();
+        let mut watcher = ConfigWatcher::new(file.path(), 1).with_metrics(metrics);
+        assert!(watcher.load_initial().await.unwrap());
+        assert!(guard.shutdown(Duration::from_secs(5)));
+
+        let requests: Vec<_> = requests.try_iter().collect();
+        let body = |path: &str| {
+            &requests
+                .iter()
+                .find(|(request, _)| request == path)
+                .unwrap_or_else(|| panic!("nothing posted to {path}"))
+                .1
+        };
+
+        let traces = &body("/v1/traces")["resourceSpans"][0];
+        let resource = &traces["resource"]["attributes"];
+        assert_eq!(
+            attribute_value(resource, "service.name"),
+            Some(&json!({ "stringValue": "A" }))
+        );
+        assert_eq!(
+            attribute_value(resource, "deployment.environment"),
+            Some(&json!({ "stringValue": "staging" }))
+        );
+        let spans = traces["scopeSpans"][0]["spans"].as_array().unwrap();
+        let span = |name: &str| {
+            spans
+                .iter()
+                .find(|span| span["name"] == name)
+                .unwrap_or_else(|| panic!("no {name} span in {spans:?}"))
+        };
+        for name in ["read_config", "read", "parse", "validate", "apply"] {
+            span(name);
+        }
+        let (read_config, read) = (span("read_config"), span("read"));
+        assert_eq!(read["parentSpanId"], read_config["spanId"]);
+        assert_eq!(read["traceId"], read_config["traceId"]);
+        assert_eq!(
+            attribute_value(&read_config["attributes"], "path"),
+            Some(&json!({ "stringValue": file.path().display().to_string() }))
+        );
+        assert_eq!(
+            attribute_value(&read["attributes"], "outcome"),
+            Some(&json!({ "stringValue": "ok" }))
+        );
+        assert_eq!(read["status"], json!({ "code": 1 }));
+
+        let metrics = body("/v1/metrics")["resourceMetrics"][0]["scopeMetrics"][0]["metrics"]
+            .as_array()
+            .unwrap()
+            .clone();
+        let reloads = metrics
+            .iter()
+            .find(|metric| metric["name"] == "configwatcher.reloads")
+            .unwrap();
+        let success = reloads["sum"]["dataPoints"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|point| {
+                attribute_value(&point["attributes"], "result")
+                    == Some(&json!({ "stringValue": "success" }))
+            })
+            .unwrap();
+        assert_eq!(success["asInt"], "1");
+        let duration = metrics
+            .iter()
+            .find(|metric| metric["name"] == "configwatcher.reload.duration")
+            .unwrap();
+        let point = &duration["histogram"]["dataPoints"][0];
+        assert_eq!(point["count"], "1");
+        assert_eq!(
+            point["bucketCounts"].as_array().unwrap().len(),
+            BUCKETS.len() + 1
+        );
+    }
+
+    #[test]
+    fn test_unreachable_collector_is_harmless() {
+        // Nothing listens on a port that was just released
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let config = OtlpConfig::new(&format!("http://127.0.0.1:{port}"), |_| None).unwrap();
+        let (layer, mut guard) =
+            start(config, Metrics::default(), CurrentProxy::default()).unwrap();
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            for _ in 0..QUEUE * 2 {
+                tracing::debug_span!("reload").in_scope(|| {});
+            }
+        });
+        assert!(guard.shutdown(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_exports_go_through_the_loaded_proxy() {
+        // The proxy records the tunnel it is asked for and refuses it
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy_url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, asked) = mpsc::channel();
+        std::thread::spawn(move || {
+            use std::io::{BufRead, BufReader};
+            let (stream, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+            let _ = (&stream).write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n");
+            sender.send(line).unwrap();
+        });
+
+        let proxy = CurrentProxy::default();
+        proxy.set(Some(crate::config::ProxyConfig {
+            http: None,
+            https: Some(proxy_url),
+            no_proxy: Vec::new(),
+        }));
+        let config = OtlpConfig::new("https://collector.example.com:4318", |_| None).unwrap();
+        let mut exporter = Exporter {
+            config,
+            metrics: Metrics::default(),
+            proxy,
+            started: SystemTime::now(),
+            failing: false,
+        };
+        exporter.export_metrics();
+        assert!(exporter.failing);
+        let line = asked.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(
+            line.starts_with("CONNECT collector.example.com:4318 "),
+            "{line}"
+        );
+    }
+
+    #[test]
+    fn test_config_from_variables() {
+        let var = |name: &str| {
+            match name {
+                "OTEL_EXPORTER_OTLP_METRICS_ENDPOINT" => Some("http://metrics:9000/custom"),
+                "OTEL_EXPORTER_OTLP_HEADERS" => Some("api-key=secret, x-team = core"),
+                "OTEL_EXPORTER_OTLP_TIMEOUT" => Some("2500"),
+                "OTEL_SERVICE_NAME" => Some("svc"),
+                "OTEL_RESOURCE_ATTRIBUTES" => Some("team=core,region=eu"),
+                _ => None,
+            }
+            .map(String::from)
+        };
+        let config = OtlpConfig::new("http://collector:4318/", var).unwrap();
+        assert_eq!(config.traces.as_str(), "http://collector:4318/v1/traces");
+        assert_eq!(config.metrics.as_str(), "http://metrics:9000/custom");
+        assert_eq!(
+            config.headers,
+            [
+                ("api-key".to_string(), "secret".to_string()),
+                ("x-team".to_string(), "core".to_string())
+            ]
+        );
+        assert_eq!(config.timeout, Duration::from_millis(2500));
+        assert_eq!(config.service_name.as_deref(), Some("svc"));
+        assert_eq!(config.resource.len(), 2);
+
+        assert!(OtlpConfig::new("https://collector:4318", |_| None).is_ok());
+        assert!(OtlpConfig::new("grpc://collector:4317", |_| None).is_err());
+        assert!(OtlpConfig::new("collector:4318", |_| None).is_err());
+        let grpc = |name: &str| (name == "OTEL_EXPORTER_OTLP_PROTOCOL").then(|| "grpc".to_string());
+        let err = OtlpConfig::new("http://collector:4317", grpc).unwrap_err();
+        assert!(err.to_string().contains("only http/json"), "{err}");
+    }
+}
@@ -0,0 +1,2335 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **Enums with borrowed data**: `Event<'a>` describes what happened without
+  copying the configuration
+- **`serde_json::json!`**: Builds each JSON event in place
+- **`anyhow::Error::chain`**: Walks every cause of an error
+- **`Arc<AtomicU8>`**: The verbosity, read for every event and changed at
+  runtime without a lock
+
+**Design decisions**:
+- Every user-facing event of the watcher goes through one `Emitter`, so
+  text and JSON output can never drift apart
+- Text mode prints status lines through `Style` (emoji and colors, or
+  ASCII tags); errors go to stderr
+- JSON mode prints exactly one line per event on stdout (NDJSON), with
+  `timestamp` and `event` first, ready for `jq` or a log shipper
+- Secret values are redacted in both modes; the summary never shows them
+  and overrides or diffs touching a secret show `<redacted>`. The few
+  strings a summary or heartbeat prints (`app_name`, `server.host`...)
+  go through `redact::shown`, for values decrypted from `age:`
+- Each event has a level; the emitter drops events above the selected
+  verbosity, so callers never test `-q`/`-v` themselves
+- The verbosity is one cell shared by every clone of the emitter, so
+  `set_verbosity` (SIGUSR2) changes what every watcher prints from the next
+  event on
+- The audit log, the event database and the `with_events` channel, when
+  enabled, see every event before that filter
+- Under --tui the text lines (ASCII tags, whatever --output says) go to
+  the dashboard's `Feed` instead of stdout, until the dashboard closes
+
+******************************************************************************/
+
+use crate::audit::AuditLog;
+use crate::cli::{ErrorFormat, OutputFormat};
+use crate::config::AppConfig;
+use crate::diff::Change;
+use crate::dump::Dump;
+use crate::features::{FeatureDiff, is_flag_change};
+use crate::hook::{HookOutcome, HookStatus};
+use crate::latency::LatencyStats;
+use crate::listing::{self, ListLimit};
+use crate::log_file::format_size;
+use crate::logging;
+use crate::notify::{ConfigEvent, Dispatcher};
+use crate::overrides::Overrides;
+use crate::provenance::{self, Provenance};
+use crate::redact;
+use crate::report::{self, ErrorReport};
+use crate::signal_pid::SignalOutcome;
+use crate::status::Health;
+use crate::style::{Icon, Style};
+use crate::timestamp::{self, Timestamps};
+use crate::tui::Feed;
+use serde_json::{Map, Value, json};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+
+/// Something the watcher reports
+///
+/// New kinds of events come with new features: a match needs a `_` arm.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Event<'a> {
+    /// `--file` was omitted and this file was found instead
+    Discovered { file: &'a Path },
+    /// The watch loop starts
+    Started {
+        file: &'a Path,
+        interval: Duration,
+        overrides: &'a Overrides,
+    },
+    /// The file's modification time moved
+    ChangeDetected { file: &'a Path },
+    /// A valid configuration was loaded
+    Loaded {
+        file: &'a Path,
+        version: u64,
+        initial: bool,
+        summary: Summary<'a>,
+        /// Differences from the previous valid configuration, if any
+        changes: Option<&'a [Change]>,
+        /// How the feature flags moved, alongside `changes`
+        flags: Option<&'a FeatureDiff>,
+        /// What the configuration came from instead of the watched file,
+        /// the file having failed at startup
+        stand_in: Option<StandIn<'a>>,
+    },
+    /// The watched file is valid and replaces its stand-in for good
+    SwitchedOver { file: &'a Path, from: StandIn<'a> },
+    /// A valid change was not approved at the `--confirm` prompt; the
+    /// configuration in use is kept
+    Declined { file: &'a Path, timed_out: bool },
+    /// The file was rewritten with identical content
+    Unchanged { file: &'a Path, version: u64 },
+    /// Loading failed (parse, validation or I/O error)
+    LoadFailed {
+        file: &'a Path,
+        initial: bool,
+        /// Whether the watcher keeps going after this failure
+        retrying: bool,
+        error: &'a anyhow::Error,
+    },
+    /// The file could not be checked, e.g. because it was removed
+    FileError {
+        file: &'a Path,
+        error: &'a anyhow::Error,
+    },
+    /// The `.next` candidate passed validation and replaced the file
+    Promoted { file: &'a Path, candidate: &'a Path },
+    /// The `.next` candidate failed validation; both files are left as
+    /// they are
+    Rejected {
+        file: &'a Path,
+        candidate: &'a Path,
+        error: &'a anyhow::Error,
+    },
+    /// The external schema was recompiled
+    SchemaReloaded { file: &'a Path, schema: &'a Path },
+    /// The external schema no longer compiles
+    SchemaReloadFailed {
+        file: &'a Path,
+        error: &'a anyhow::Error,
+    },
+    /// Re-reading environment overrides failed
+    EnvOverridesFailed {
+        file: &'a Path,
+        error: &'a anyhow::Error,
+    },
+    /// Every watcher was asked to reload now, by SIGHUP or a push socket
+    /// client
+    ReloadRequested { by: &'a str },
+    /// A status dump was asked for, by SIGUSR1 or `POST /dump`
+    Dump { by: &'a str, dump: &'a Dump },
+    /// The verbosity was changed at runtime, by SIGUSR2
+    VerbosityChanged { by: &'a str, to: Verbosity },
+    /// The checks were paused or resumed, from the keyboard
+    Paused { by: &'a str, paused: bool },
+    /// A signal asked the process to stop and the cleanup starts; `force`
+    /// names what abandons it, e.g. `Ctrl+C`
+    Stopping { force: &'a str },
+    /// The process is about to exit
+    Shutdown {
+        reason: ShutdownReason,
+        error: Option<&'a anyhow::Error>,
+        files: &'a [FileStatus<'a>],
+    },
+    /// A periodic check found nothing to do
+    Unmodified { file: &'a Path },
+    /// Time spent in each step of a load
+    Timings {
+        file: &'a Path,
+        /// Whether a valid configuration was already loaded
+        reload: bool,
+        timings: Timings,
+    },
+    /// Why the watcher (re)loads, retries or skips
+    Decision { file: &'a Path, detail: &'a str },
+    /// A change was applied this long after the file was written
+    Latency {
+        file: &'a Path,
+        latency: Duration,
+        stats: &'a LatencyStats,
+    },
+    /// Raw metadata read on a check
+    Stat {
+        file: &'a Path,
+        modified: SystemTime,
+        len: u64,
+    },
+    /// Where every field of a valid load came from
+    Provenance {
+        file: &'a Path,
+        provenance: &'a Provenance,
+    },
+    /// The watcher moved from one state to another
+    Transition {
+        file: &'a Path,
+        from: WatchState,
+        to: WatchState,
+    },
+    /// Periodic summary, with `--heartbeat`
+    Heartbeat {
+        file: &'a Path,
+        version: u64,
+        config: Option<&'a AppConfig>,
+        state: WatchState,
+        /// Time since the configuration last changed, if it ever loaded
+        since_change: Option<Duration>,
+        counts: HeartbeatCounts,
+    },
+    /// The `--on-change` command ran after a reload
+    OnChange {
+        file: &'a Path,
+        command: &'a str,
+        outcome: &'a HookOutcome,
+    },
+    /// `--signal-pid` / `--signal-pidfile` signalled a process after a
+    /// reload
+    Signaled {
+        file: &'a Path,
+        outcome: &'a SignalOutcome,
+    },
+}
+
+/// How much is printed, from `-q` to `-vv`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Errors and the final shutdown line
+    Quiet,
+    /// Loads, reloads, failures and summaries
+    #[default]
+    Normal,
+    /// Plus per-check lines, timings and detection decisions
+    Verbose,
+    /// Plus raw metadata and state transitions
+    Debug,
+}
+
+impl Verbosity {
+    const ALL: [Verbosity; 4] = [
+        Verbosity::Quiet,
+        Verbosity::Normal,
+        Verbosity::Verbose,
+        Verbosity::Debug,
+    ];
+
+    /// The next level up, from `Debug` back to `Quiet`
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    /// `quiet`, `normal`, `verbose` or `debug`
+    pub fn name(self) -> &'static str {
+        match self {
+            Verbosity::Quiet => "quiet",
+            Verbosity::Normal => "normal",
+            Verbosity::Verbose => "verbose",
+            Verbosity::Debug => "debug",
+        }
+    }
+}
+
+/// Where a watcher stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchState {
+    /// No valid configuration yet
+    Waiting,
+    /// The file is valid
+    Valid,
+    /// The last reload failed; the previous configuration is kept
+    Failing,
+    /// The file never loaded; the fallback configuration is served
+    Fallback,
+    /// The file never loaded; the last good configuration of a previous
+    /// run is served
+    Restored,
+}
+
+impl WatchState {
+    fn name(self) -> &'static str {
+        match self {
+            WatchState::Waiting => "waiting",
+            WatchState::Valid => "valid",
+            WatchState::Failing => "failing",
+            WatchState::Fallback => "fallback",
+            WatchState::Restored => "restored",
+        }
+    }
+}
+
+/// What serves in place of a watched file that has not loaded yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StandIn<'a> {
+    /// The `--fallback-config` file
+    Fallback(&'a Path),
+    /// The last good configuration of a previous run, saved at this time
+    Restored(SystemTime),
+}
+
+impl StandIn<'_> {
+    /// `"fallback"` or `"restored"`, as in the `WatchState`
+    pub fn name(self) -> &'static str {
+        match self {
+            StandIn::Fallback(_) => "fallback",
+            StandIn::Restored(_) => "restored",
+        }
+    }
+
+    /// `the fallback FILE` or `the configuration restored from TIME`
+    fn describe(self) -> String {
+        match self {
+            StandIn::Fallback(path) => format!("the fallback {}", path.display()),
+            StandIn::Restored(saved_at) => format!(
+                "the configuration restored from {}",
+                humantime::format_rfc3339_seconds(saved_at)
+            ),
+        }
+    }
+
+    /// `{"fallback": FILE}` or `{"restored": {"saved_at": TIME}}`
+    fn to_json(self) -> Value {
+        match self {
+            StandIn::Fallback(path) => json!({ "fallback": path.display().to_string() }),
+            StandIn::Restored(saved_at) => json!({
+                "restored": {
+                    "saved_at": humantime::format_rfc3339_millis(saved_at).to_string()
+                }
+            }),
+        }
+    }
+}
+
+/// Duration of each step of a load; `None` when the step was not reached
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// The check that noticed the change, `None` for the initial load
+    pub stat: Option<Duration>,
+    pub read: Option<Duration>,
+    pub parse: Option<Duration>,
+    pub validate: Option<Duration>,
+    /// Size of the file read
+    pub bytes: Option<u64>,
+    /// Read, parse and validate together
+    pub total: Duration,
+}
+
+impl Event<'_> {
+    /// Lowest verbosity at which the event is shown
+    pub fn level(&self) -> Verbosity {
+        match self {
+            Event::LoadFailed { .. }
+            | Event::FileError { .. }
+            | Event::SchemaReloadFailed { .. }
+            | Event::Rejected { .. }
+            | Event::EnvOverridesFailed { .. }
+            | Event::Dump { .. }
+            | Event::VerbosityChanged { .. }
+            | Event::Paused { .. }
+            | Event::Stopping { .. }
+            | Event::Shutdown { .. } => Verbosity::Quiet,
+            Event::Discovered { .. }
+            | Event::Started { .. }
+            | Event::ChangeDetected { .. }
+            | Event::ReloadRequested { .. }
+            | Event::Loaded { .. }
+            | Event::SwitchedOver { .. }
+            | Event::Promoted { .. }
+            | Event::Declined { .. }
+            | Event::Unchanged { .. }
+            | Event::SchemaReloaded { .. }
+            | Event::Heartbeat { .. }
+            | Event::OnChange { .. }
+            | Event::Signaled { .. } => Verbosity::Normal,
+            Event::Unmodified { .. }
+            | Event::Timings { .. }
+            | Event::Decision { .. }
+            | Event::Latency { .. } => Verbosity::Verbose,
+            Event::Stat { .. } | Event::Provenance { .. } | Event::Transition { .. } => {
+                Verbosity::Debug
+            }
+        }
+    }
+}
+
+/// What happened since the previous heartbeat
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeartbeatCounts {
+    /// Periodic checks of the file
+    pub checks: u64,
+    /// Successful reloads, content changed or not
+    pub reloads: u64,
+    /// Failed loads and failed checks
+    pub failures: u64,
+}
+
+/// Why the watcher stops
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownReason {
+    /// Ctrl+C
+    Signal,
+    /// `--max-duration` elapsed
+    MaxDuration,
+    /// `--fail-fast` and a reload failed
+    FailFast,
+    /// The command supervised by `run` exited
+    ChildExited,
+}
+
+/// Final state of one watched file
+#[derive(Debug, Clone, Copy)]
+pub struct FileStatus<'a> {
+    pub file: &'a Path,
+    pub version: u64,
+    pub config: Option<&'a AppConfig>,
+}
+
+/// The configuration fields shown after each load
+#[derive(Debug, Clone, Copy)]
+pub struct Summary<'a> {
+    pub config: &'a AppConfig,
+    pub overrides: &'a Overrides,
+}
+
+/// Prints events in the selected format
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    format: OutputFormat,
+    style: Style,
+    /// A `Verbosity`, shared by the clones
+    verbosity: Arc<AtomicU8>,
+    /// Entries of a long list shown at normal verbosity
+    list_threshold: usize,
+    error_format: ErrorFormat,
+    event_log: EventLog,
+    label: Option<String>,
+    timestamps: Timestamps,
+    audit: Option<AuditLog>,
+    notifiers: Option<Dispatcher>,
+    /// Gets the notifiers' view of each event, as it happens
+    events: Option<mpsc::UnboundedSender<ConfigEvent>>,
+    /// Takes the text lines while --tui shows them
+    feed: Option<Feed>,
+    #[cfg(feature = "event-db")]
+    event_db: Option<crate::event_db::EventDb>,
+    #[cfg(all(unix, feature = "systemd"))]
+    systemd: Option<crate::systemd::Systemd>,
+    #[cfg(feature = "windows-service")]
+    service: Option<crate::service::Controller>,
+}
+
+/// Which events are logged through `tracing` as well as printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EventLog {
+    /// None
+    #[default]
+    Off,
+    /// Failures, instead of their stderr lines (`--log-format json`), so
+    /// that stderr only carries JSON lines
+    Failures,
+    /// Failures, loads, state changes and shutdown, whatever the verbosity
+    /// (`--log-target syslog|journald`); the output is unchanged
+    All,
+}
+
+/// Where a text line goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Self::new(OutputFormat::default())
+    }
+}
+
+impl Emitter {
+    /// Creates an emitter for `format`
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            style: Style::default(),
+            verbosity: Arc::new(AtomicU8::new(Verbosity::default() as u8)),
+            list_threshold: listing::DEFAULT_THRESHOLD,
+            error_format: ErrorFormat::default(),
+            event_log: EventLog::default(),
+            label: None,
+            timestamps: Timestamps::default(),
+            audit: None,
+            notifiers: None,
+            events: None,
+            feed: None,
+            #[cfg(feature = "event-db")]
+            event_db: None,
+            #[cfg(all(unix, feature = "systemd"))]
+            systemd: None,
+            #[cfg(feature = "windows-service")]
+            service: None,
+        }
+    }
+
+    /// Drops events above `verbosity`
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = Arc::new(AtomicU8::new(verbosity as u8));
+        self
+    }
+
+    /// Cuts long lists in text output after `threshold` entries
+    ///
+    /// `-v` shows ten times as many, `-vv` shows them all.
+    pub fn with_list_threshold(mut self, threshold: usize) -> Self {
+        self.list_threshold = threshold;
+        self
+    }
+
+    /// The selected verbosity
+    pub fn verbosity(&self) -> Verbosity {
+        Verbosity::ALL[usize::from(self.verbosity.load(Ordering::Relaxed))]
+    }
+
+    /// Changes the verbosity of this emitter and of its clones, and
+    /// announces it whatever the level
+    pub fn set_verbosity(&self, verbosity: Verbosity, by: &str) {
+        self.verbosity.store(verbosity as u8, Ordering::Relaxed);
+        self.emit(&Event::VerbosityChanged { by, to: verbosity });
+    }
+
+    /// Whether `event` would be printed
+    pub(crate) fn enabled(&self, event: &Event<'_>) -> bool {
+        event.level() <= self.verbosity()
+    }
+
+    /// Reports load failures in text output as `format`
+    ///
+    /// With `ErrorFormat::Json` a failed load prints one [`ErrorReport`]
+    /// line on stderr instead of the usual message.
+    pub(crate) fn with_error_format(mut self, format: ErrorFormat) -> Self {
+        self.error_format = format;
+        self
+    }
+
+    /// Also sends events through `tracing`, see [`EventLog`]
+    pub(crate) fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = event_log;
+        self
+    }
+
+    /// Also records events in `audit`, whatever the verbosity
+    pub(crate) fn with_audit_log(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Also hands events to the notifiers behind `dispatcher`, whatever
+    /// the verbosity
+    pub fn with_notifiers(mut self, dispatcher: Dispatcher) -> Self {
+        self.notifiers = Some(dispatcher);
+        self
+    }
+
+    /// Also sends the notifiers' view of each event (loads, failures, file
+    /// errors, shutdown) down `events`, before `emit` returns and whatever
+    /// the verbosity: what a test or an embedding program observes
+    pub fn with_events(mut self, events: mpsc::UnboundedSender<ConfigEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Hands the text lines to `feed` instead of printing them, for as long
+    /// as it is open, whatever the format
+    pub(crate) fn with_feed(mut self, feed: Feed) -> Self {
+        self.feed = Some(feed);
+        self
+    }
+
+    /// Also records events in `event_db`, whatever the verbosity
+    #[cfg(feature = "event-db")]
+    pub(crate) fn with_event_db(mut self, event_db: crate::event_db::EventDb) -> Self {
+        self.event_db = Some(event_db);
+        self
+    }
+
+    /// Also reports the state to systemd, whatever the verbosity
+    #[cfg(all(unix, feature = "systemd"))]
+    pub(crate) fn with_systemd(mut self, systemd: crate::systemd::Systemd) -> Self {
+        self.systemd = Some(systemd);
+        self
+    }
+
+    /// Also reports the state to the Windows service control manager,
+    /// whatever the verbosity
+    #[cfg(feature = "windows-service")]
+    pub(crate) fn with_service(mut self, service: crate::service::Controller) -> Self {
+        self.service = Some(service);
+        self
+    }
+
+    /// Decorates text lines with `style`
+    pub(crate) fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Prefixes every text line with `[label]`
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Starts every text line with a timestamp rendered by `timestamps`
+    ///
+    /// JSON events carry their UTC `timestamp` whatever this says.
+    pub(crate) fn with_timestamps(mut self, timestamps: Timestamps) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    /// The selected format
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    /// Prints one event
+    pub(crate) fn emit(&self, event: &Event<'_>) {
+        let at = SystemTime::now();
+        if let Some(ref audit) = self.audit {
+            audit.record(event);
+        }
+        if let Some(ref notifiers) = self.notifiers {
+            notifiers.dispatch(event, at);
+        }
+        if let Some(ref events) = self.events
+            && let Some(event) = ConfigEvent::new(event, at)
+        {
+            // Nobody listening is not an error
+            let _ = events.send(event);
+        }
+        #[cfg(feature = "event-db")]
+        if let Some(ref event_db) = self.event_db {
+            event_db.record(event);
+        }
+        #[cfg(all(unix, feature = "systemd"))]
+        if let Some(ref systemd) = self.systemd {
+            systemd.record(event);
+        }
+        #[cfg(feature = "windows-service")]
+        if let Some(ref service) = self.service {
+            service.record(event);
+        }
+        if self.event_log == EventLog::All {
+            log(event, true);
+        }
+        if !self.enabled(event) {
+            return;
+        }
+        if let Some(ref feed) = self.feed
+            && feed.is_open()
+        {
+            let limit = ListLimit::new(self.list_threshold, self.verbosity());
+            let lines = to_text(event, Style::PLAIN, limit);
+            feed.record(event, at, lines.into_iter().map(|(_, line)| line).collect());
+            return;
+        }
+        match self.format {
+            OutputFormat::Json => println!("{}", to_json_at(event, at)),
+            OutputFormat::Text => {
+                let json_logs = self.event_log == EventLog::Failures;
+                if json_logs {
+                    log(event, false);
+                } else if let (ErrorFormat::Json, Event::LoadFailed { file, error, .. }) =
+                    (self.error_format, event)
+                {
+                    eprintln!("{}", ErrorReport::new(file, error).to_json_line());
+                    return;
+                }
+                let limit = ListLimit::new(self.list_threshold, self.verbosity());
+                let stamp = self.timestamps.render(at);
+                for (stream, line) in to_text(event, self.style, limit) {
+                    if json_logs && stream == Stream::Stderr {
+                        continue;
+                    }
+                    let line = match self.label {
+                        Some(ref label) => format!("[{label}] {line}"),
+                        None => line,
+                    };
+                    let line = match stamp {
+                        Some(ref stamp) => format!("{stamp} {line}"),
+                        None => line,
+                    };
+                    match stream {
+                        Stream::Stdout => println!("{line}"),
+                        Stream::Stderr => eprintln!("{line}"),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Summary<'_> {
+    /// Marker appended to lines showing overridden fields
+    fn mark(&self, fields: &[&str]) -> &'static str {
+        if fields.iter().any(|field| self.overrides.touches(field)) {
+            " (override)"
+        } else {
+            ""
+        }
+    }
+
+    fn text_lines(&self, limit: ListLimit) -> Vec<String> {
+        let config = self.config;
+        let mut lines = vec![
+            format!(
+                "   App: {} v{}{}",
+                redact::shown("app_name", &config.app_name),
+                redact::shown("version", &config.version),
+                self.mark(&["app_name", "version"])
+            ),
+            format!(
+                "   Environment: {}{}",
+                redact::shown("environment", &config.environment),
+                self.mark(&["environment"])
+            ),
+        ];
+
+        if let Some(ref server) = config.server {
+            lines.push(format!(
+                "   Server: {}:{} (SSL: {}, max_connections: {}, request_timeout: {}){}",
+                redact::shown("server.host", &server.host),
+                server.port,
+                server.enable_ssl,
+                server.max_connections,
+                humantime::format_duration(server.request_timeout),
+                self.mark(&["server"])
+            ));
+        }
+
+        if let Some(ref db) = config.database {
+            lines.push(format!(
+                "   Database: pool_size={}, timeout={}s{}",
+                db.pool_size,
+                db.timeout_seconds,
+                self.mark(&["database"])
+            ));
+        }
+
+        if let Some(ref messaging) = config.messaging {
+            let count = messaging.brokers.len();
+            lines.push(format!(
+                "   Messaging: {}, {count} broker{}{}",
+                messaging.kind.name(),
+                if count == 1 { "" } else { "s" },
+                self.mark(&["messaging"])
+            ));
+        }
+
+        if let Some(ref auth) = config.auth {
+            let mut kinds = Vec::new();
+            if let Some(ref jwt) = auth.jwt {
+                kinds.push(format!(
+                    "jwt (issuer {})",
+                    redact::shown("auth.jwt.issuer", &jwt.issuer)
+                ));
+            }
+            if auth.oauth.is_some() {
+                kinds.push("oauth".to_string());
+            }
+            lines.push(format!(
+                "   Auth: {}{}",
+                kinds.join(", "),
+                self.mark(&["auth"])
+            ));
+        }
+
+        if !config.features.is_empty() {
+            let mut features: Vec<_> = config.features.iter().collect();
+            features.sort();
+            let enabled = features.iter().filter(|&(_, on)| **on).count();
+            lines.push(format!(
+                "   Features: {} enabled, {} disabled{}",
+                listing::thousands(enabled),
+                listing::thousands(features.len() - enabled),
+                self.mark(&["features"])
+            ));
+            let (shown, more) = limit.split(&features);
+            lines.extend(
+                shown
+                    .iter()
+                    .map(|&(name, &on)| format!("     {name}: {}", if on { "on" } else { "off" })),
+            );
+            lines.extend(more.map(|more| format!("     {more}")));
+        }
+        lines.push(String::new());
+        lines
+    }
+
+    fn to_json(self) -> Value {
+        let config = self.config;
+        let mut overridden: Vec<String> = self
+            .overrides
+            .entries()
+            .iter()
+            .map(|entry| entry.path.to_string())
+            .collect();
+        overridden.dedup();
+
+        json!({
+            "app_name": redact::shown("app_name", &config.app_name),
+            "version": redact::shown("version", &config.version),
+            "environment": redact::shown("environment", &config.environment),
+            "server": config.server.as_ref().map(|server| json!({
+                "host": redact::shown("server.host", &server.host),
+                "port": server.port,
+                "enable_ssl": server.enable_ssl,
+                "max_connections": server.max_connections,
+                "request_timeout": humantime::format_duration(server.request_timeout).to_string(),
+            })),
+            "database": config.database.as_ref().map(|db| json!({
+                "pool_size": db.pool_size,
+                "timeout_seconds": db.timeout_seconds,
+            })),
+            "messaging": config.messaging.as_ref().map(|messaging| json!({
+                "kind": messaging.kind.name(),
+                "brokers": messaging.brokers.len(),
+            })),
+            "auth": config.auth.as_ref().map(|auth| json!({
+                "jwt_issuer": auth.jwt.as_ref().map(|jwt| redact::shown("auth.jwt.issuer", &jwt.issuer)),
+                "oauth": auth.oauth.is_some(),
+            })),
+            "features_enabled": config.features.values().filter(|enabled| **enabled).count(),
+            "overridden": overridden,
+        })
+    }
+}
+
+/// Logs the failure events, which text output prints on stderr
+///
+/// With `lifecycle`, also logs loads (info), the degraded state (warning),
+/// recovery and shutdown (info).
+fn log(event: &Event<'_>, lifecycle: bool) {
+    match *event {
+        Event::LoadFailed {
+            file,
+            initial: true,
+            error,
+            ..
+        } => logging::log_error(
+            false,
+            "load_failed",
+            Some(file),
+            error,
+            "failed to load initial configuration",
+        ),
+        Event::LoadFailed { file, error, .. } => logging::log_error(
+            false,
+            "load_failed",
+            Some(file),
+            error,
+            "configuration reload failed, keeping last valid configuration",
+        ),
+        Event::FileError { file, error } => {
+            logging::log_error(true, "file_error", Some(file), error, "error checking file")
+        }
+        Event::SchemaReloadFailed { file, error } => logging::log_error(
+            false,
+            "schema_reload_failed",
+            Some(file),
+            error,
+            "schema reload failed, keeping previous schema",
+        ),
+        Event::Rejected {
+            file,
+            candidate,
+            error,
+        } => logging::log_error(
+            false,
+            "rejected",
+            Some(file),
+            error,
+            &format!(
+                "candidate {} rejected, keeping the file as it is",
+                candidate.display()
+            ),
+        ),
+        Event::EnvOverridesFailed { file, error } => logging::log_error(
+            true,
+            "env_overrides_failed",
+            Some(file),
+            error,
+            "ignoring environment overrides, keeping previous ones",
+        ),
+        Event::Shutdown {
+            error: Some(error), ..
+        } => logging::log_error(false, "shutdown", None, error, "shutting down"),
+        Event::OnChange { file, outcome, .. } if !outcome.status.is_success() => tracing::warn!(
+            path = %file.display(),
+            event = "on_change_failed",
+            version = outcome.version,
+            output = outcome.output,
+            "on-change command {}",
+            outcome.status
+        ),
+        Event::Signaled { file, outcome } => {
+            if let Err(ref error) = outcome.result {
+                tracing::warn!(
+                    path = %file.display(),
+                    event = "signal_failed",
+                    version = outcome.version,
+                    signal = %outcome.signal,
+                    "{} not sent: {error}",
+                    outcome.signal
+                );
+            }
+        }
+        _ if !lifecycle => {}
+        Event::Dump { by, dump } => tracing::info!(
+            event = "dump",
+            by,
+            dump = %dump_json(dump),
+            "status dump"
+        ),
+        Event::Loaded {
+            file,
+            stand_in: Some(stand_in),
+            ..
+        } => tracing::warn!(
+            path = %file.display(),
+            event = stand_in.name(),
+            "running on {}",
+            stand_in.describe()
+        ),
+        Event::Loaded {
+            file,
+            version,
+            initial,
+            ..
+        } => tracing::info!(
+            path = %file.display(),
+            event = "loaded",
+            version,
+            initial,
+            "configuration loaded"
+        ),
+        Event::Transition {
+            file,
+            to: WatchState::Failing,
+            ..
+        } => tracing::warn!(
+            path = %file.display(),
+            event = "degraded",
+            "degraded: serving the last valid configuration"
+        ),
+        Event::Declined { file, timed_out } => tracing::warn!(
+            path = %file.display(),
+            event = "declined",
+            timed_out,
+            "change declined, keeping the current configuration"
+        ),
+        Event::Promoted { file, candidate } => tracing::info!(
+            path = %file.display(),
+            event = "promoted",
+            candidate = %candidate.display(),
+            "candidate promoted"
+        ),
+        Event::SwitchedOver { file, from } => tracing::info!(
+            path = %file.display(),
+            event = "switched_over",
+            "switched over from {}",
+            from.describe()
+        ),
+        Event::Transition {
+            file,
+            from: WatchState::Failing,
+            to: WatchState::Valid,
+        } => tracing::info!(
+            path = %file.display(),
+            event = "recovered",
+            "recovered: the configuration is valid again"
+        ),
+        Event::Shutdown { reason, .. } => tracing::info!(
+            event = "shutdown",
+            reason = ?reason,
+            "shutting down"
+        ),
+        Event::Heartbeat {
+            file,
+            version,
+            config,
+            state,
+            since_change,
+            counts,
+        } => tracing::info!(
+            path = %file.display(),
+            event = "heartbeat",
+            version,
+            state = state.name(),
+            app_name = config.map(|config| redact::shown("app_name", &config.app_name)),
+            app_version = config.map(|config| redact::shown("version", &config.version)),
+            environment =
+                config.map(|config| redact::shown("environment", &config.environment)),
+            since_change_secs = since_change.map(|since| since.as_secs()),
+            checks = counts.checks,
+            reloads = counts.reloads,
+            failures = counts.failures,
+            "alive"
+        ),
+        _ => {}
+    }
+}
+
+/// The lines of a status dump: the process, then each file with its
+/// redacted configuration, then the settings
+fn dump_text(by: &str, dump: &Dump, style: Style) -> Vec<String> {
+    let status = &dump.status;
+    let mut lines = vec![style.line(
+        Icon::Dump,
+        format_args!(
+            "Status dump (requested by {by}): {}, pid {}",
+            health_name(status.health),
+            status.pid
+        ),
+    )];
+    for file in &status.files {
+        lines.push(format!(
+            "   {}: {}, version {}, {} reloads, {} failures",
+            file.path.display(),
+            health_name(file.health),
+            file.version,
+            file.reloads,
+            file.failures
+        ));
+        if let Some(ref fallback) = file.fallback {
+            lines.push(format!(
+                "      running on the fallback {}",
+                fallback.display()
+            ));
+        }
+        if let Some(ref saved_at) = file.restored_at {
+            lines.push(format!(
+                "      running on the configuration restored from {saved_at}"
+            ));
+        }
+        for (what, at) in [
+            ("last success", &file.last_success_at),
+            ("last failure", &file.last_failure_at),
+        ] {
+            if let Some(at) = at {
+                lines.push(format!("      {what}: {at}"));
+            }
+        }
+        if let Some(ref error) = file.last_error {
+            lines.push(format!(
+                "      last error: [{}] {}",
+                error.code, error.message
+            ));
+        }
+        if let Some((_, config)) = dump.configs.iter().find(|(path, _)| *path == file.path) {
+            lines.push(format!("      config: {config}"));
+        }
+    }
+    if !dump.settings.is_empty() {
+        lines.push("   Settings:".to_string());
+    }
+    for setting in dump.settings.iter() {
+        lines.push(match setting.value {
+            Some(ref value) => format!("      {} = {value} ({})", setting.key, setting.origin),
+            None => format!("      {} is not set", setting.key),
+        });
+    }
+    lines
+}
+
+/// The JSON fields of a status dump, `by` aside
+fn dump_json(dump: &Dump) -> Value {
+    let configs: Map<String, Value> = dump
+        .configs
+        .iter()
+        .map(|(path, config)| {
+            let config = serde_json::from_str(config).unwrap_or(Value::Null);
+            (path.display().to_string(), config)
+        })
+        .collect();
+    let settings: Map<String, Value> = dump
+        .settings
+        .iter()
+        .map(|setting| {
+            let value = setting
+                .value
+                .as_ref()
+                .and_then(|value| serde_json::to_value(value).ok());
+            (
+                setting.key.to_string(),
+                json!({ "value": value, "origin": setting.origin.to_string() }),
+            )
+        })
+        .collect();
+    json!({
+        "status": dump.status,
+        "configs": configs,
+        "settings": settings,
+    })
+}
+
+/// `healthy`, `degraded` or `unhealthy`, as in the status file
+pub(crate) fn health_name(health: Health) -> &'static str {
+    match health {
+        Health::Healthy => "healthy",
+        Health::Degraded => "degraded",
+        Health::Unhealthy => "unhealthy",
+    }
+}
+
+fn to_text(event: &Event<'_>, style: Style, limit: ListLimit) -> Vec<(Stream, String)> {
+    use Stream::{Stderr, Stdout};
+    let out = |line: String| (Stdout, line);
+    let err = |line: String| (Stderr, line);
+
+    match *event {
+        Event::Discovered { file } => vec![out(style.line(
+            Icon::File,
+            format_args!("Using configuration file: {}", file.display()),
+        ))],
+        Event::Started {
+            file,
+            interval,
+            overrides,
+        } => {
+            let mut lines = vec![
+                out(style.line(
+                    Icon::Watch,
+                    format_args!("Watching configuration file: {}", file.display()),
+                )),
+                out(style.line(
+                    Icon::Interval,
+                    format_args!("Check interval: {:?}", interval),
+                )),
+            ];
+            for entry in overrides.entries() {
+                lines.push(out(style.line(
+                    Icon::Override,
+                    format_args!(
+                        "Override: {} = {} (from {})",
+                        entry.path,
+                        provenance::shown(&entry.path, &entry.value),
+                        entry.source
+                    ),
+                )));
+            }
+            lines.push(out("Press Ctrl+C to stop\n".to_string()));
+            lines
+        }
+        Event::ChangeDetected { .. } => vec![out(
+            style.line(Icon::Change, "File change detected, reloading...")
+        )],
+        Event::ReloadRequested { by } => vec![out(style.line(
+            Icon::Change,
+            format_args!("Reload requested by {by}, reloading..."),
+        ))],
+        Event::Dump { by, dump } => dump_text(by, dump, style).into_iter().map(out).collect(),
+        Event::VerbosityChanged { by, to } => vec![out(style.line(
+            Icon::Override,
+            format_args!("Verbosity set to {} by {by}", to.name()),
+        ))],
+        Event::Paused { by, paused: true } => vec![out(style.line(
+            Icon::Stop,
+            format_args!("Watching paused by {by}, no checks until resumed"),
+        ))],
+        Event::Paused { by, paused: false } => vec![out(
+            style.line(Icon::Watch, format_args!("Watching resumed by {by}"))
+        )],
+        Event::Stopping { force } => vec![err(style.line(
+            Icon::Stop,
+            format_args!("Shutting down (press {force} again to force)..."),
+        ))],
+        Event::Loaded {
+            file,
+            summary,
+            stand_in: Some(stand_in),
+            ..
+        } => {
+            let mut lines = vec![out(style.line(
+                Icon::Warning,
+                format_args!(
+                    "Running on {} until {} is valid",
+                    stand_in.describe(),
+                    file.display()
+                ),
+            ))];
+            lines.extend(summary.text_lines(limit).into_iter().map(out));
+            lines
+        }
+        Event::SwitchedOver { file, from } => vec![out(style.line(
+            Icon::Change,
+            format_args!(
+                "{} is valid, switching over from {}",
+                file.display(),
+                from.describe()
+            ),
+        ))],
+        Event::Loaded {
+            initial,
+            summary,
+            changes,
+            flags,
+            ..
+        } => {
+            let mut lines = if initial {
+                vec![out(
+                    style.line(Icon::Ok, "Initial configuration loaded successfully")
+                )]
+            } else {
+                vec![out(
+                    style.line(Icon::Ok, "Configuration reloaded successfully")
+                )]
+            };
+            let changes = changes.filter(|_| !initial).unwrap_or_default();
+            if !changes.is_empty() {
+                lines.push(out(style.line(
+                    Icon::Update,
+                    format_args!(
+                        "Configuration has been updated ({} change{})",
+                        changes.len(),
+                        if changes.len() == 1 { "" } else { "s" }
+                    ),
+                )));
+            }
+            let mut summary = summary.text_lines(limit);
+            // Keep the summary's trailing blank line after the changes
+            let blank = summary.pop();
+            lines.extend(summary.into_iter().map(out));
+            let flags = flags.filter(|flags| !flags.is_empty());
+            // Flag toggles alone get the focused line instead of the diff
+            let only_flags = flags.is_some() && changes.iter().all(is_flag_change);
+            if !changes.is_empty() && !only_flags {
+                lines.push(out("   Changes:".to_string()));
+                lines.extend(
+                    crate::diff::render(changes, limit)
+                        .into_iter()
+                        .map(|line| out(format!("     {line}"))),
+                );
+            }
+            if let Some(flags) = flags {
+                lines.push(out(format!("   {}", flags.render())));
+            }
+            lines.extend(blank.map(out));
+            lines
+        }
+        Event::Unchanged { .. } => vec![
+            out(style.line(Icon::Ok, "Configuration reloaded successfully")),
+            out("   (File modified but content unchanged)".to_string()),
+        ],
+        Event::LoadFailed {
+            initial,
+            retrying,
+            error,
+            ..
+        } => {
+            let mut lines = if initial {
+                vec![err(style.line(
+                    Icon::Error,
+                    format_args!("Failed to load initial configuration: {:#}", error),
+                ))]
+            } else {
+                vec![err(style.line(
+                    Icon::Error,
+                    format_args!("Configuration reload failed: {:#}", error),
+                ))]
+            };
+            match (retrying, initial) {
+                (true, true) => lines.push(err("   Waiting for valid configuration...\n".into())),
+                (true, false) => lines.push(err("   Keeping last valid configuration\n".into())),
+                (false, _) => {}
+            }
+            lines
+        }
+        Event::FileError { error, .. } => vec![err(style.line(
+            Icon::Warning,
+            format_args!("Error checking file: {:#}", error),
+        ))],
+        Event::Declined { timed_out, .. } => vec![out(style.line(
+            Icon::Warning,
+            if timed_out {
+                "No answer in time, change declined: keeping the current configuration"
+            } else {
+                "Change declined: keeping the current configuration"
+            },
+        ))],
+        Event::Promoted { file, candidate } => vec![out(style.line(
+            Icon::Change,
+            format_args!(
+                "{} is valid, promoted over {}",
+                candidate.display(),
+                file.display()
+            ),
+        ))],
+        Event::Rejected {
+            file,
+            candidate,
+            error,
+        } => vec![
+            err(style.line(
+                Icon::Error,
+                format_args!("Candidate {} rejected: {:#}", candidate.display(), error),
+            )),
+            err(format!("   {} left as it is\n", file.display())),
+        ],
+        Event::SchemaReloaded { .. } => vec![out(
+            style.line(Icon::Change, "Schema change detected, revalidating...")
+        )],
+        Event::SchemaReloadFailed { error, .. } => vec![
+            err(style.line(
+                Icon::Error,
+                format_args!("Schema reload failed: {:#}", error),
+            )),
+            err("   Keeping previous schema\n".to_string()),
+        ],
+        Event::EnvOverridesFailed { error, .. } => vec![
+            err(style.line(
+                Icon::Warning,
+                format_args!("Ignoring environment overrides: {:#}", error),
+            )),
+            err("   Keeping previous overrides".to_string()),
+        ],
+        Event::Shutdown {
+            reason,
+            error,
+            files,
+        } => {
+            let mut lines = match (reason, error) {
+                (ShutdownReason::FailFast | ShutdownReason::ChildExited, Some(error)) => {
+                    vec![err(format!(
+                        "\n{}",
+                        style.line(Icon::Error, format_args!("{:#}", error))
+                    ))]
+                }
+                (ShutdownReason::MaxDuration, _) => vec![out(format!(
+                    "\n{}",
+                    style.line(
+                        Icon::Timeout,
+                        "Maximum duration reached, shutting down gracefully..."
+                    )
+                ))],
+                (ShutdownReason::ChildExited, None) => vec![out(format!(
+                    "\n{}",
+                    style.line(Icon::Stop, "Command exited, shutting down...")
+                ))],
+                _ => vec![out(format!(
+                    "\n{}",
+                    style.line(Icon::Stop, "Shutting down gracefully...")
+                ))],
+            };
+            // Per-file status is only worth a line each with several files
+            if files.len() > 1 {
+                for status in files {
+                    lines.push(out(match status.config {
+                        Some(config) => format!(
+                            "   {}: {} v{}",
+                            status.file.display(),
+                            redact::shown("app_name", &config.app_name),
+                            redact::shown("version", &config.version)
+                        ),
+                        None => format!(
+                            "   {}: no valid configuration loaded",
+                            status.file.display()
+                        ),
+                    }));
+                }
+            }
+            lines
+        }
+        Event::Unmodified { .. } => vec![out("   Checked, unchanged".to_string())],
+        Event::Timings {
+            reload, timings, ..
+        } => {
+            let step = |duration: Option<Duration>| duration.map_or("-".to_string(), millis);
+            let mut steps = Vec::new();
+            if let Some(stat) = timings.stat {
+                steps.push(format!("stat {}", millis(stat)));
+            }
+            steps.push(match timings.bytes {
+                Some(bytes) => format!("read {} ({})", step(timings.read), format_size(bytes)),
+                None => format!("read {}", step(timings.read)),
+            });
+            steps.push(format!("parse {}", step(timings.parse)));
+            steps.push(format!("validate {}", step(timings.validate)));
+            vec![out(format!(
+                "   {} in {}: {}",
+                if reload { "Reloaded" } else { "Loaded" },
+                millis(timings.total),
+                steps.join(", ")
+            ))]
+        }
+        Event::Decision { detail, .. } => vec![out(format!("   {detail}"))],
+        Event::Latency { latency, stats, .. } => {
+            let secs = |duration: Option<Duration>| {
+                duration.map_or("-".to_string(), |d| format!("{:.1}s", d.as_secs_f64()))
+            };
+            vec![out(format!(
+                "   Detected {} after write (min {}, avg {}, p95 {})",
+                secs(Some(latency)),
+                secs(stats.min()),
+                secs(stats.avg()),
+                secs(stats.p95())
+            ))]
+        }
+        Event::Stat { modified, len, .. } => vec![out(format!(
+            "   stat: modified={}, len={}",
+            humantime::format_rfc3339_nanos(modified),
+            len
+        ))],
+        Event::Provenance { provenance, .. } => std::iter::once("   provenance:".to_string())
+            .chain(
+                provenance
+                    .table()
+                    .into_iter()
+                    .map(|line| format!("     {line}")),
+            )
+            .map(out)
+            .collect(),
+        Event::Transition { from, to, .. } => {
+            vec![out(format!("   state: {} -> {}", from.name(), to.name()))]
+        }
+        Event::Heartbeat {
+            version,
+            config,
+            state,
+            since_change,
+            counts,
+            ..
+        } => {
+            let loaded = match (config, since_change) {
+                (Some(config), Some(since)) => format!(
+                    "{} v{} ({}), config version {version}, {}, last change {} ago",
+                    redact::shown("app_name", &config.app_name),
+                    redact::shown("version", &config.version),
+                    redact::shown("environment", &config.environment),
+                    state.name(),
+                    humantime::format_duration(Duration::from_secs(since.as_secs()))
+                ),
+                _ => format!("no valid configuration yet, {}", state.name()),
+            };
+            vec![
+                out(style.line(Icon::Heartbeat, format_args!("Heartbeat: {loaded}"))),
+                out(format!(
+                    "   Since last heartbeat: {} checks, {} reloads, {} failures",
+                    counts.checks, counts.reloads, counts.failures
+                )),
+            ]
+        }
+        Event::OnChange { outcome, .. } => {
+            let took = format!("{:.1}s", outcome.duration.as_secs_f64());
+            let first = match outcome.status {
+                HookStatus::Exited(0) => style.line(
+                    Icon::Ok,
+                    format_args!("On-change command succeeded in {took}"),
+                ),
+                HookStatus::TimedOut(_) | HookStatus::SpawnFailed(_) => style.line(
+                    Icon::Warning,
+                    format_args!("On-change command {}", outcome.status),
+                ),
+                HookStatus::Exited(_) | HookStatus::Signaled => style.line(
+                    Icon::Warning,
+                    format_args!("On-change command {} after {took}", outcome.status),
+                ),
+            };
+            let stream = if outcome.status.is_success() {
+                Stdout
+            } else {
+                Stderr
+            };
+            let output: Vec<&str> = outcome.output.lines().collect();
+            let (shown, more) = limit.split(&output);
+            std::iter::once(first)
+                .chain(shown.iter().map(|line| format!("   | {line}")))
+                .chain(more.map(|more| format!("   | {more}")))
+                .map(|line| (stream, line))
+                .collect()
+        }
+        Event::Signaled { outcome, .. } => match (&outcome.result, outcome.pid) {
+            (Ok(()), Some(pid)) => vec![out(style.line(
+                Icon::Ok,
+                format_args!("Sent {} to process {pid}", outcome.signal),
+            ))],
+            (result, _) => vec![err(style.line(
+                Icon::Warning,
+                format_args!(
+                    "{} not sent: {}",
+                    outcome.signal,
+                    result
+                        .as_ref()
+                        .err()
+                        .map_or(String::new(), ToString::to_string)
+                ),
+            ))],
+        },
+    }
+}
+
+/// The JSON form of an event happening now
+#[cfg(any(test, feature = "event-db"))]
+pub(crate) fn to_json(event: &Event<'_>) -> Value {
+    to_json_at(event, SystemTime::now())
+}
+
+/// The JSON form of an event that happened `at`
+pub(crate) fn to_json_at(event: &Event<'_>, at: SystemTime) -> Value {
+    let mut record = Map::new();
+    record.insert("timestamp".to_string(), json!(timestamp::utc(at)));
+
+    let (name, file, fields) = match *event {
+        Event::Discovered { file } => ("discovered", Some(file), json!({})),
+        Event::Started {
+            file,
+            interval,
+            overrides,
+        } => {
+            let overrides: Vec<Value> = overrides
+                .entries()
+                .iter()
+                .map(|entry| {
+                    json!({
+                        "path": entry.path.to_string(),
+                        "value": provenance::shown(&entry.path, &entry.value),
+                        "source": entry.source.to_string(),
+                    })
+                })
+                .collect();
+            (
+                "started",
+                Some(file),
+                json!({ "interval_secs": interval.as_secs_f64(), "overrides": overrides }),
+            )
+        }
+        Event::ChangeDetected { file } => ("change_detected", Some(file), json!({})),
+        Event::ReloadRequested { by } => ("reload_requested", None, json!({ "by": by })),
+        Event::VerbosityChanged { by, to } => (
+            "verbosity_changed",
+            None,
+            json!({ "verbosity": to.name(), "by": by }),
+        ),
+        Event::Paused { by, paused } => (
+            if paused { "paused" } else { "resumed" },
+            None,
+            json!({ "by": by }),
+        ),
+        Event::Stopping { force } => ("stopping", None, json!({ "force": force })),
+        Event::Dump { by, dump } => {
+            let mut fields = dump_json(dump);
+            fields["by"] = json!(by);
+            ("dump", None, fields)
+        }
+        Event::SwitchedOver { file, from } => ("switched_over", Some(file), from.to_json()),
+        Event::Loaded {
+            file,
+            version,
+            initial,
+            summary,
+            changes,
+            flags,
+            stand_in,
+        } => {
+            let mut fields = json!({
+                "version": version,
+                "initial": initial,
+                "summary": summary.to_json(),
+            });
+            if let (Some(stand_in), Value::Object(fields)) = (stand_in, &mut fields) {
+                fields.extend(stand_in.to_json().as_object().cloned().unwrap_or_default());
+            }
+            if let Some(changes) = changes {
+                fields["diff"] = json!(changes);
+                fields["patch"] = json!(crate::diff::patch(changes));
+            }
+            if let Some(flags) = flags.filter(|flags| !flags.is_empty()) {
+                fields["flags"] = json!(flags);
+            }
+            ("loaded", Some(file), fields)
+        }
+        Event::Unchanged { file, version } => {
+            ("unchanged", Some(file), json!({ "version": version }))
+        }
+        Event::LoadFailed {
+            file,
+            initial,
+            retrying,
+            error,
+        } => (
+            "load_failed",
+            Some(file),
+            json!({ "initial": initial, "retrying": retrying, "error": error_json(error) }),
+        ),
+        Event::FileError { file, error } => (
+            "file_error",
+            Some(file),
+            json!({ "error": error_json(error) }),
+        ),
+        Event::Declined { file, timed_out } => {
+            ("declined", Some(file), json!({ "timed_out": timed_out }))
+        }
+        Event::Promoted { file, candidate } => (
+            "promoted",
+            Some(file),
+            json!({ "candidate": candidate.display().to_string() }),
+        ),
+        Event::Rejected {
+            file,
+            candidate,
+            error,
+        } => (
+            "rejected",
+            Some(file),
+            json!({ "candidate": candidate.display().to_string(), "error": error_json(error) }),
+        ),
+        Event::SchemaReloaded { file, schema } => (
+            "schema_reloaded",
+            Some(file),
+            json!({ "schema": schema.display().to_string() }),
+        ),
+        Event::SchemaReloadFailed { file, error } => (
+            "schema_reload_failed",
+            Some(file),
+            json!({ "error": error_json(error) }),
+        ),
+        Event::EnvOverridesFailed { file, error } => (
+            "env_overrides_failed",
+            Some(file),
+            json!({ "error": error_json(error) }),
+        ),
+        Event::Shutdown {
+            reason,
+            error,
+            files,
+        } => {
+            let files: Vec<Value> = files
+                .iter()
+                .map(|status| {
+                    json!({
+                        "file": status.file.display().to_string(),
+                        "version": status.version,
+                        "valid": status.config.is_some(),
+                    })
+                })
+                .collect();
+            let reason = match reason {
+                ShutdownReason::Signal => "signal",
+                ShutdownReason::MaxDuration => "max_duration",
+                ShutdownReason::FailFast => "fail_fast",
+                ShutdownReason::ChildExited => "child_exited",
+            };
+            let mut fields = json!({ "reason": reason, "files": files });
+            if let Some(error) = error {
+                fields["error"] = error_json(error);
+            }
+            ("shutdown", None, fields)
+        }
+        Event::Unmodified { file } => ("unmodified", Some(file), json!({})),
+        Event::Timings {
+            file,
+            reload,
+            timings,
+        } => {
+            let ms = |duration: Option<Duration>| duration.map(|d| d.as_secs_f64() * 1000.0);
+            (
+                "timings",
+                Some(file),
+                json!({
+                    "reload": reload,
+                    "total_ms": ms(Some(timings.total)),
+                    "stat_ms": ms(timings.stat),
+                    "read_ms": ms(timings.read),
+                    "parse_ms": ms(timings.parse),
+                    "validate_ms": ms(timings.validate),
+                    "bytes": timings.bytes,
+                }),
+            )
+        }
+        Event::Decision { file, detail } => ("decision", Some(file), json!({ "detail": detail })),
+        Event::Latency {
+            file,
+            latency,
+            stats,
+        } => {
+            let ms = |duration: Option<Duration>| duration.map(|d| d.as_secs_f64() * 1000.0);
+            (
+                "latency",
+                Some(file),
+                json!({
+                    "latency_ms": ms(Some(latency)),
+                    "min_ms": ms(stats.min()),
+                    "avg_ms": ms(stats.avg()),
+                    "p95_ms": ms(stats.p95()),
+                }),
+            )
+        }
+        Event::Stat {
+            file,
+            modified,
+            len,
+        } => (
+            "stat",
+            Some(file),
+            json!({
+                "modified": humantime::format_rfc3339_nanos(modified).to_string(),
+                "len": len,
+            }),
+        ),
+        Event::Provenance { file, provenance } => {
+            let fields: Vec<Value> = provenance
+                .fields()
+                .iter()
+                .filter_map(|(path, chain)| {
+                    let winner = chain.last()?;
+                    Some(json!({
+                        "path": path.to_string(),
+                        "value": provenance::shown(path, &winner.value),
+                        "source": winner.layer.to_string(),
+                    }))
+                })
+                .collect();
+            ("provenance", Some(file), json!({ "fields": fields }))
+        }
+        Event::Transition { file, from, to } => (
+            "state",
+            Some(file),
+            json!({ "from": from.name(), "to": to.name() }),
+        ),
+        Event::Heartbeat {
+            file,
+            version,
+            config,
+            state,
+            since_change,
+            counts,
+        } => (
+            "heartbeat",
+            Some(file),
+            json!({
+                "version": version,
+                "state": state.name(),
+                "app_name": config.map(|config| redact::shown("app_name", &config.app_name)),
+                "app_version": config.map(|config| redact::shown("version", &config.version)),
+                "environment":
+                    config.map(|config| redact::shown("environment", &config.environment)),
+                "since_change_secs": since_change.map(|since| since.as_secs()),
+                "checks": counts.checks,
+                "reloads": counts.reloads,
+                "failures": counts.failures,
+            }),
+        ),
+        Event::OnChange {
+            file,
+            command,
+            outcome,
+        } => (
+            "on_change",
+            Some(file),
+            json!({
+                "command": command,
+                "version": outcome.version,
+                "status": outcome.status.name(),
+                "exit_code": outcome.status.code(),
+                "duration_ms": outcome.duration.as_secs_f64() * 1000.0,
+                "output": outcome.output,
+            }),
+        ),
+        Event::Signaled { file, outcome } => {
+            let mut fields = json!({
+                "signal": outcome.signal.to_string(),
+                "pid": outcome.pid,
+                "version": outcome.version,
+                "status": if outcome.result.is_ok() { "ok" } else { "failed" },
+            });
+            if let Err(ref error) = outcome.result {
+                fields["error"] = json!(error.to_string());
+                fields["reason"] = json!(error.name());
+            }
+            ("signal", Some(file), fields)
+        }
+    };
+
+    record.insert("event".to_string(), json!(name));
+    if let Some(file) = file {
+        record.insert("file".to_string(), json!(file.display().to_string()));
+    }
+    if let Value::Object(fields) = fields {
+        record.extend(fields);
+    }
+    Value::Object(record)
+}
+
+/// `41ms`, with a decimal below 10ms
+fn millis(duration: Duration) -> String {
+    let ms = duration.as_secs_f64() * 1000.0;
+    if ms < 10.0 {
+        format!("{ms:.1}ms")
+    } else {
+        format!("{ms:.0}ms")
+    }
+}
+
+/// An error as `{ "code": ..., "message": ..., "chain": [...], "findings": [...] }`
+pub(crate) fn error_json(error: &anyhow::Error) -> Value {
+    json!({
+        "code": report::code(error),
+        "message": error.to_string(),
+        "chain": error.chain().map(|cause| cause.to_string()).collect::<Vec<_>>(),
+        "findings": report::findings(error),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::TimestampFormat;
+
+    fn keys(value: &Value) -> Vec<&str> {
+        value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect()
+    }
+
+    #[test]
+    fn test_json_events_share_a_stable_prefix() {
+        let config = AppConfig::example();
+        let overrides = Overrides::default();
+        let file = Path::new("app.json");
+        let event = Event::Loaded {
+            file,
+            version: 2,
+            initial: false,
+            summary: Summary {
+                config: &config,
+                overrides: &overrides,
+            },
+            changes: Some(&[]),
+            flags: None,
+            stand_in: None,
+        };
+
+        let value = to_json(&event);
+        assert_eq!(
+            keys(&value),
+            [
+                "timestamp",
+                "event",
+                "file",
+                "version",
+                "initial",
+                "summary",
+                "diff",
+                "patch"
+            ]
+        );
+        assert_eq!(value["event"], "loaded");
+        assert_eq!(value["summary"]["server"]["port"], 8080);
+    }
+
+    #[test]
+    fn test_json_never_contains_secrets() {
+        let config = AppConfig::example();
+        let overrides = Overrides::from_args(
+            &["database.connection_string=postgres://u:hunter2@db/x".to_string()],
+            &[],
+        )
+        .unwrap();
+        let file = Path::new("app.json");
+
+        let started = to_json(&Event::Started {
+            file,
+            interval: Duration::from_secs(2),
+            overrides: &overrides,
+        });
+        let loaded = to_json(&Event::Loaded {
+            file,
+            version: 1,
+            initial: true,
+            summary: Summary {
+                config: &config,
+                overrides: &overrides,
+            },
+            changes: None,
+            flags: None,
+            stand_in: None,
+        });
+        for value in [started, loaded] {
+            let line = value.to_string();
+            assert!(!line.contains("hunter2"), "{line}");
+            assert!(
+                !line.contains(&config.database.clone().unwrap().connection_string),
+                "{line}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_levels_filter_events() {
+        let file = Path::new("app.json");
+        let error = anyhow::anyhow!("boom");
+        let failed = Event::FileError {
+            file,
+            error: &error,
+        };
+        let changed = Event::ChangeDetected { file };
+        let checked = Event::Unmodified { file };
+        let transition = Event::Transition {
+            file,
+            from: WatchState::Valid,
+            to: WatchState::Failing,
+        };
+        let shutdown = Event::Shutdown {
+            reason: ShutdownReason::Signal,
+            error: None,
+            files: &[],
+        };
+
+        let cases = [
+            (Verbosity::Quiet, [true, false, false, false, true]),
+            (Verbosity::Normal, [true, true, false, false, true]),
+            (Verbosity::Verbose, [true, true, true, false, true]),
+            (Verbosity::Debug, [true, true, true, true, true]),
+        ];
+        for (verbosity, expected) in cases {
+            let emitter = Emitter::default().with_verbosity(verbosity);
+            let shown = [&failed, &changed, &checked, &transition, &shutdown]
+                .map(|event| emitter.enabled(event));
+            assert_eq!(shown, expected, "{verbosity:?}");
+        }
+    }
+
+    #[test]
+    fn test_verbosity_changes_for_every_clone() {
+        let emitter = Emitter::default().with_verbosity(Verbosity::Quiet);
+        let clone = emitter.clone().with_label("b.json");
+        let checked = Event::Unmodified {
+            file: Path::new("app.json"),
+        };
+        assert!(!clone.enabled(&checked));
+
+        let mut level = Verbosity::Quiet;
+        for expected in ["normal", "verbose", "debug", "quiet"] {
+            level = level.next();
+            assert_eq!(level.name(), expected);
+        }
+        emitter.set_verbosity(Verbosity::Verbose, "SIGUSR2");
+        assert_eq!(clone.verbosity(), Verbosity::Verbose);
+        assert!(clone.enabled(&checked));
+    }
+
+    #[test]
+    fn test_error_chain_is_kept() {
+        let error = anyhow::anyhow!("root cause").context("outer");
+        let value = to_json(&Event::FileError {
+            file: Path::new("app.json"),
+            error: &error,
+        });
+        assert_eq!(value["error"]["message"], "outer");
+        assert_eq!(value["error"]["chain"], json!(["outer", "root cause"]));
+    }
+
+    #[test]
+    fn test_heartbeat_text_and_json() {
+        let config = AppConfig::example();
+        let event = Event::Heartbeat {
+            file: Path::new("app.json"),
+            version: 3,
+            config: Some(&config),
+            state: WatchState::Valid,
+            since_change: Some(Duration::from_millis(600_400)),
+            counts: HeartbeatCounts {
+                checks: 120,
+                reloads: 1,
+                failures: 2,
+            },
+        };
+        let lines: Vec<String> = to_text(&event, Style::PLAIN, ListLimit::UNLIMITED)
+            .into_iter()
+            .map(|(_, line)| line)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                format!(
+                    "[ALIVE] Heartbeat: {} v{} ({}), config version 3, valid, last change 10m ago",
+                    config.app_name, config.version, config.environment
+                ),
+                "   Since last heartbeat: 120 checks, 1 reloads, 2 failures".to_string(),
+            ]
+        );
+
+        let value = to_json(&event);
+        assert_eq!(value["event"], "heartbeat");
+        assert_eq!(value["since_change_secs"], 600);
+        assert_eq!(value["checks"], 120);
+        assert_eq!(value["state"], "valid");
+        assert!(
+            !Emitter::default()
+                .with_verbosity(Verbosity::Quiet)
+                .enabled(&event)
+        );
+    }
+
+    #[test]
+    fn test_stand_ins_text_and_json() {
+        let config = AppConfig::example();
+        let overrides = Overrides::default();
+        let file = Path::new("app.json");
+        let saved_at = SystemTime::UNIX_EPOCH + Duration::from_secs(86_400);
+        let loaded = |stand_in| Event::Loaded {
+            file,
+            version: 4,
+            initial: true,
+            summary: Summary {
+                config: &config,
+                overrides: &overrides,
+            },
+            changes: None,
+            flags: None,
+            stand_in: Some(stand_in),
+        };
+        let first_line = |event: &Event<'_>| {
+            to_text(event, Style::PLAIN, ListLimit::UNLIMITED)
+                .into_iter()
+                .map(|(_, line)| line)
+                .next()
+                .unwrap()
+        };
+
+        let fallback = loaded(StandIn::Fallback(Path::new("safe.json")));
+        assert!(
+            first_line(&fallback)
+                .ends_with("Running on the fallback safe.json until app.json is valid"),
+            "{}",
+            first_line(&fallback)
+        );
+        assert_eq!(to_json(&fallback)["fallback"], "safe.json");
+
+        let restored = loaded(StandIn::Restored(saved_at));
+        assert!(
+            first_line(&restored).ends_with(
+                "Running on the configuration restored from 1970-01-02T00:00:00Z until app.json is valid"
+            ),
+            "{}",
+            first_line(&restored)
+        );
+        assert_eq!(
+            to_json(&restored)["restored"],
+            json!({ "saved_at": "1970-01-02T00:00:00.000Z" })
+        );
+
+        let switched = Event::SwitchedOver {
+            file,
+            from: StandIn::Restored(saved_at),
+        };
+        assert!(
+            first_line(&switched).ends_with(
+                "app.json is valid, switching over from the configuration restored from 1970-01-02T00:00:00Z"
+            ),
+            "{}",
+            first_line(&switched)
+        );
+        let value = to_json(&switched);
+        assert_eq!(value["event"], "switched_over");
+        assert_eq!(value["restored"]["saved_at"], "1970-01-02T00:00:00.000Z");
+    }
+
+    #[test]
+    fn test_summary_shows_messaging_kind_and_broker_count() {
+        let config = AppConfig::example();
+        let overrides = Overrides::default();
+        let summary = Summary {
+            config: &config,
+            overrides: &overrides,
+        };
+        assert!(
+            summary
+                .text_lines(ListLimit::default())
+                .contains(&"   Messaging: kafka, 2 brokers".to_string())
+        );
+        assert_eq!(
+            summary.to_json()["messaging"],
+            json!({ "kind": "kafka", "brokers": 2 })
+        );
+    }
+
+    #[test]
+    fn test_summary_shows_auth_without_secret_files() {
+        let config = AppConfig::example();
+        let overrides = Overrides::default();
+        let summary = Summary {
+            config: &config,
+            overrides: &overrides,
+        };
+        let lines = summary.text_lines(ListLimit::default());
+        assert!(
+            lines.contains(&"   Auth: jwt (issuer https://auth.example.com/), oauth".to_string())
+        );
+        let json = summary.to_json();
+        assert_eq!(
+            json["auth"],
+            json!({ "jwt_issuer": "https://auth.example.com/", "oauth": true })
+        );
+        assert!(!lines.concat().contains("oauth_client_secret"));
+        assert!(!json.to_string().contains("oauth_client_secret"));
+    }
+
+    #[test]
+    fn test_flag_summary_replaces_or_follows_the_diff() {
+        let old = AppConfig::example();
+        let mut new = old.clone();
+        new.features.insert("debug_mode".to_string(), false);
+        new.features.insert("beta_search".to_string(), true);
+        let overrides = Overrides::default();
+        let text = |new: &AppConfig| {
+            let changes = crate::diff::diff(
+                &serde_json::to_value(&old).unwrap(),
+                &serde_json::to_value(new).unwrap(),
+            );
+            let flags = old.feature_diff(new);
+            let event = Event::Loaded {
+                file: Path::new("app.json"),
+                version: 2,
+                initial: false,
+                summary: Summary {
+                    config: new,
+                    overrides: &overrides,
+                },
+                changes: Some(&changes),
+                flags: Some(&flags),
+                stand_in: None,
+            };
+            assert_eq!(
+                to_json(&event)["flags"],
+                json!({
+                    "enabled": [],
+                    "disabled": ["debug_mode"],
+                    "added": { "beta_search": true },
+                    "removed": []
+                })
+            );
+            to_text(&event, Style::PLAIN, ListLimit::UNLIMITED)
+                .into_iter()
+                .map(|(_, line)| line)
+                .collect::<Vec<_>>()
+        };
+        let flag_line = "   flags disabled: debug_mode; added: beta_search(on)".to_string();
+
+        let only_flags = text(&new);
+        assert!(only_flags.contains(&flag_line), "{only_flags:#?}");
+        assert!(!only_flags.contains(&"   Changes:".to_string()));
+
+        new.server.as_mut().unwrap().port = 9090;
+        let mixed = text(&new);
+        let position = |line: &str| mixed.iter().position(|l| l == line).unwrap();
+        assert!(position("     server.port: 8080 -> 9090") > position("   Changes:"));
+        assert!(position(&flag_line) > position("     server.port: 8080 -> 9090"));
+    }
+
+    #[test]
+    fn test_summary_lists_features_up_to_the_limit() {
+        let mut config = AppConfig::example();
+        config.features = (0..8000)
+            .map(|i| (format!("f{i:04}"), i % 2 == 0))
+            .collect();
+        let overrides = Overrides::default();
+        let summary = Summary {
+            config: &config,
+            overrides: &overrides,
+        };
+
+        let lines = summary.text_lines(ListLimit::default());
+        let start = lines
+            .iter()
+            .position(|line| line == "   Features: 4,000 enabled, 4,000 disabled")
+            .unwrap();
+        assert_eq!(lines[start + 1], "     f0000: on");
+        assert_eq!(lines[start + 50], "     f0049: off");
+        assert_eq!(
+            lines[start + 51],
+            "     ... and 7,950 more (use -v to list)"
+        );
+
+        let all = summary.text_lines(ListLimit::UNLIMITED);
+        assert_eq!(all.len(), lines.len() - 1 + 7950);
+    }
+
+    #[test]
+    fn test_text_and_json_timestamps_agree() {
+        let event = Event::ChangeDetected {
+            file: Path::new("app.json"),
+        };
+        let at = SystemTime::UNIX_EPOCH + Duration::from_millis(1_792_053_005_250);
+        let json = to_json_at(&event, at);
+        assert_eq!(json["timestamp"], "2026-10-15T08:30:05.250Z");
+
+        let utc = Timestamps::new(TimestampFormat::Rfc3339, true);
+        assert_eq!(utc.render(at).as_deref(), json["timestamp"].as_str());
+        // Local text timestamps and --timestamp off leave JSON in UTC
+        for format in [TimestampFormat::TimeOnly, TimestampFormat::Off] {
+            let text = Timestamps::new(format, false).render(at);
+            assert_ne!(text.as_deref(), json["timestamp"].as_str());
+        }
+    }
+
+    #[test]
+    fn test_on_change_lines() {
+        let file = Path::new("app.json");
+        let text = |status, output: &str| {
+            let outcome = HookOutcome {
+                version: 3,
+                status,
+                duration: Duration::from_millis(1250),
+                output: output.to_string(),
+            };
+            let event = Event::OnChange {
+                file,
+                command: "reload-nginx",
+                outcome: &outcome,
+            };
+            let json = to_json(&event);
+            assert_eq!(json["command"], "reload-nginx");
+            assert_eq!(json["version"], 3);
+            (
+                to_text(&event, Style::PLAIN, ListLimit::default()),
+                json["status"].clone(),
+            )
+        };
+
+        let (lines, status) = text(HookStatus::Exited(0), "");
+        assert_eq!(
+            lines,
+            [(
+                Stream::Stdout,
+                "[OK] On-change command succeeded in 1.2s".to_string()
+            )]
+        );
+        assert_eq!(status, "ok");
+
+        let (lines, status) = text(HookStatus::Exited(2), "one\ntwo");
+        assert_eq!(
+            lines,
+            [
+                "[WARN] On-change command exited with status 2 after 1.2s",
+                "   | one",
+                "   | two",
+            ]
+            .map(|line| (Stream::Stderr, line.to_string()))
+        );
+        assert_eq!(status, "failed");
+
+        let (lines, status) = text(HookStatus::TimedOut(Duration::from_secs(30)), "");
+        assert_eq!(
+            lines[0].1,
+            "[WARN] On-change command timed out after 30s and was killed"
+        );
+        assert_eq!(status, "timeout");
+    }
+
+    #[test]
+    fn test_timings_line() {
+        let file = Path::new("app.json");
+        let timings = Timings {
+            stat: Some(Duration::from_micros(150)),
+            read: Some(Duration::from_millis(3)),
+            parse: Some(Duration::from_millis(30)),
+            validate: Some(Duration::from_millis(8)),
+            bytes: Some(1_258_291),
+            total: Duration::from_millis(41),
+        };
+        let text = |reload, timings| {
+            let event = Event::Timings {
+                file,
+                reload,
+                timings,
+            };
+            to_text(&event, Style::PLAIN, ListLimit::default())
+                .into_iter()
+                .map(|(_, line)| line)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            text(true, timings),
+            ["   Reloaded in 41ms: stat 0.1ms, read 3.0ms (1.2 MiB), parse 30ms, validate 8.0ms"]
+        );
+
+        // The initial load failing to parse: no stat, no validation
+        let failed = Timings {
+            stat: None,
+            validate: None,
+            ..timings
+        };
+        assert_eq!(
+            text(false, failed),
+            ["   Loaded in 41ms: read 3.0ms (1.2 MiB), parse 30ms, validate -"]
+        );
+
+        let value = to_json(&Event::Timings {
+            file,
+            reload: true,
+            timings,
+        });
+        assert_eq!(value["total_ms"], 41.0);
+        assert_eq!(value["parse_ms"], 30.0);
+        assert_eq!(value["bytes"], 1_258_291);
+    }
+
+    #[test]
+    fn test_latency_line() {
+        let mut stats = LatencyStats::default();
+        for ms in [400, 1700, 1900] {
+            stats.record(Duration::from_millis(ms));
+        }
+        let event = Event::Latency {
+            file: Path::new("app.json"),
+            latency: Duration::from_millis(1700),
+            stats: &stats,
+        };
+        let lines: Vec<_> = to_text(&event, Style::PLAIN, ListLimit::default())
+            .into_iter()
+            .map(|(_, line)| line)
+            .collect();
+        assert_eq!(
+            lines,
+            ["   Detected 1.7s after write (min 0.4s, avg 1.3s, p95 1.9s)"]
+        );
+        assert_eq!(event.level(), Verbosity::Verbose);
+        assert_eq!(to_json(&event)["p95_ms"], 1900.0);
+    }
+
+    #[test]
+    fn test_stopping_line_goes_through_the_style() {
+        let event = Event::Stopping { force: "Ctrl+C" };
+        let plain = to_text(&event, Style::PLAIN, ListLimit::default());
+        assert_eq!(plain.len(), 1);
+        assert_eq!(plain[0].0, Stream::Stderr);
+        assert!(
+            plain[0]
+                .1
+                .ends_with("Shutting down (press Ctrl+C again to force)..."),
+            "{}",
+            plain[0].1
+        );
+        assert!(plain[0].1.is_ascii());
+        assert_eq!(event.level(), Verbosity::Quiet);
+        assert_eq!(to_json(&event)["event"], "stopping");
+        assert_eq!(to_json(&event)["force"], "Ctrl+C");
+    }
+}
@@ -0,0 +1,449 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`str::split_once`**: Splits `key=value` at the first `=` only
+- **`serde_json::Value` as an intermediate form**: Overrides are applied to
+  the parsed document before it becomes an `AppConfig`
+- **`serde::Deserialize::deserialize(&Value)`**: Type-checks a document
+  without going back through text
+
+**Design decisions**:
+- Overrides use the same `FieldPath` as `get`/`set`, so addressing rules
+  are identical everywhere
+- They are applied to the raw document after every load and before
+  validation, which means they survive reloads and are validated like
+  anything else
+- Unknown paths and values of the wrong type are rejected when the
+  overrides are built, i.e. at startup, never silently ignored
+- Environment variables (`CW_OVERRIDE__SERVER__PORT=9090`) become the same
+  `Override`s; precedence is defaults < file < environment < command line,
+  which is simply the order the overrides are applied in
+
+******************************************************************************/
+
+use crate::cli::ValueType;
+use crate::config::AppConfig;
+use crate::path::FieldPath;
+use crate::schema;
+use anyhow::Context;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Prefix of environment variables holding overrides
+pub const ENV_PREFIX: &str = "CW_OVERRIDE__";
+
+/// Where an override came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// `--override` or `--override-string`
+    CommandLine,
+    /// An environment variable, by name
+    Env(String),
+}
+
+/// One `path=value` override
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override {
+    pub path: FieldPath,
+    pub value: Value,
+    pub source: Source,
+}
+
+impl Override {
+    /// Parses `path=value`, typing the value according to `value_type`
+    pub fn parse(spec: &str, value_type: ValueType) -> anyhow::Result<Self> {
+        let (path, raw) = spec
+            .split_once('=')
+            .with_context(|| format!("Override '{spec}' must have the form path=value"))?;
+        let path = FieldPath::parse(path.trim())
+            .with_context(|| format!("Invalid override path in '{spec}'"))?;
+
+        if schema::lookup(&path).is_none() {
+            let suggestions = schema::suggest(&path.to_string());
+            if suggestions.is_empty() {
+                anyhow::bail!("Unknown field '{path}' in override '{spec}'");
+            }
+            anyhow::bail!(
+                "Unknown field '{path}' in override '{spec}' (did you mean: {}?)",
+                suggestions.join(", ")
+            );
+        }
+
+        let value = parse_value(raw, value_type)
+            .with_context(|| format!("Invalid override value in '{spec}'"))?;
+        Ok(Self {
+            path,
+            value,
+            source: Source::CommandLine,
+        })
+    }
+
+    /// Parses `CW_OVERRIDE__SECTION__FIELD=value`
+    ///
+    /// Each `__`-separated part of the name is one path segment, lowercased;
+    /// map keys are therefore always lowercase (`..._FEATURES__DARK_MODE`
+    /// sets `features.dark_mode`). Returns `None` for other variables.
+    #[cfg(test)]
+    pub fn from_env_var(name: &str, value: &str) -> Option<anyhow::Result<Self>> {
+        Self::from_prefixed_var(ENV_PREFIX, name, value)
+    }
+
+    /// Same as `Override::from_env_var` with another prefix (e.g. `CW__`)
+    pub fn from_prefixed_var(
+        prefix: &str,
+        name: &str,
+        value: &str,
+    ) -> Option<anyhow::Result<Self>> {
+        let rest = name.strip_prefix(prefix)?;
+        Some(Self::env_path(prefix, rest).and_then(|path| {
+            let spec = format!("{path}={value}");
+            let mut entry = Self::parse(&spec, ValueType::Auto)?;
+            entry.source = Source::Env(name.to_string());
+            Ok(entry)
+        }))
+    }
+
+    fn env_path(prefix: &str, rest: &str) -> anyhow::Result<String> {
+        let parts: Vec<&str> = rest.split("__").collect();
+        anyhow::ensure!(
+            parts.iter().all(|part| !part.is_empty()
+                && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')),
+            "expected {prefix}SECTION__FIELD with letters, digits and single underscores"
+        );
+        Ok(parts
+            .iter()
+            .map(|part| part.to_ascii_lowercase())
+            .collect::<Vec<_>>()
+            .join("."))
+    }
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::CommandLine => write!(f, "command line"),
+            Source::Env(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// Ordered list of overrides, later entries win
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    entries: Vec<Override>,
+}
+
+impl Overrides {
+    /// Builds the overrides given on the command line
+    ///
+    /// `typed` entries (`--override`) infer their JSON type; `strings`
+    /// entries (`--override-string`) are always strings and are applied
+    /// after the typed ones.
+    pub fn from_args(typed: &[String], strings: &[String]) -> anyhow::Result<Self> {
+        let mut overrides = Self::default();
+        for spec in typed {
+            overrides.push(Override::parse(spec, ValueType::Auto)?);
+        }
+        for spec in strings {
+            overrides.push(Override::parse(spec, ValueType::String)?);
+        }
+        overrides.check_types()?;
+        Ok(overrides)
+    }
+
+    /// Collects the `CW_OVERRIDE__*` variables among `vars`
+    ///
+    /// Variables are applied in name order. Every unparseable variable is
+    /// reported at once.
+    pub fn from_env(vars: impl IntoIterator<Item = (String, String)>) -> anyhow::Result<Self> {
+        Self::from_prefixed_env(ENV_PREFIX, vars)
+    }
+
+    /// Same as [`Overrides::from_env`] for the variables starting with `prefix`
+    pub fn from_prefixed_env(
+        prefix: &str,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> anyhow::Result<Self> {
+        let mut vars: Vec<_> = vars
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(prefix))
+            .collect();
+        vars.sort();
+
+        let mut overrides = Self::default();
+        let mut problems = Vec::new();
+        for (name, value) in vars {
+            match Override::from_prefixed_var(prefix, &name, &value) {
+                Some(Ok(entry)) => overrides.push(entry),
+                Some(Err(e)) => problems.push(format!("{name}: {e:#}")),
+                None => {}
+            }
+        }
+        if let Err(e) = overrides.check_types() {
+            problems.push(format!("{e:#}"));
+        }
+        anyhow::ensure!(
+            problems.is_empty(),
+            "Invalid environment variables:\n  {}",
+            problems.join("\n  ")
+        );
+        Ok(overrides)
+    }
+
+    /// Appends `other`, whose entries take precedence
+    pub fn extend(&mut self, other: Overrides) {
+        self.entries.extend(other.entries);
+    }
+
+    /// Replaces the environment entries, keeping command-line ones last
+    pub fn replace_env(&mut self, env: Overrides) {
+        let cli = std::mem::take(&mut self.entries)
+            .into_iter()
+            .filter(|entry| entry.source == Source::CommandLine);
+        self.entries = env.entries.into_iter().chain(cli).collect();
+    }
+
+    /// Appends an override
+    pub fn push(&mut self, entry: Override) {
+        self.entries.push(entry);
+    }
+
+    /// Overrides in the order they are applied
+    pub fn entries(&self) -> &[Override] {
+        &self.entries
+    }
+
+    /// Writes every override into a raw configuration document
+    pub fn apply(&self, doc: &mut Value) -> anyhow::Result<()> {
+        for entry in &self.entries {
+            entry
+                .path
+                .set(doc, entry.value.clone())
+                .with_context(|| format!("Failed to apply override {}", entry.path))?;
+        }
+        Ok(())
+    }
+
+    /// Whether an override sets `field` or something below it
+    pub fn touches(&self, field: &str) -> bool {
+        self.entries.iter().any(|entry| {
+            let path = entry.path.to_string();
+            path == field
+                || path
+                    .strip_prefix(field)
+                    .is_some_and(|rest| rest.starts_with(['.', '[']))
+        })
+    }
+
+    /// Rejects values whose type can never deserialize
+    ///
+    /// The overrides are applied to the complete example configuration, so
+    /// every section exists and only the overridden values can fail.
+    fn check_types(&self) -> anyhow::Result<()> {
+        for entry in &self.entries {
+            let mut doc =
+                serde_json::to_value(AppConfig::example()).context("Failed to build example")?;
+            entry.path.set(&mut doc, entry.value.clone())?;
+            AppConfig::deserialize(&doc).with_context(|| {
+                format!(
+                    "Override {} = {} (from {}) has the wrong type",
+                    entry.path, entry.value, entry.source
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Converts a command-line value into JSON according to `value_type`
+pub fn parse_value(raw: &str, value_type: ValueType) -> anyhow::Result<Value> {
+    match value_type {
+        ValueType::String => Ok(Value::String(raw.to_string())),
+        ValueType::Number => {
+            let value: Value = serde_json::from_str(raw.trim())
+                .with_context(|| format!("'{raw}' is not a number"))?;
+            anyhow::ensure!(value.is_number(), "'{raw}' is not a number");
+            Ok(value)
+        }
+        ValueType::Bool => match raw.trim() {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => anyhow::bail!("'{raw}' is not a boolean (expected true or false)"),
+        },
+        ValueType::Json => {
+            serde_json::from_str(raw).with_context(|| format!("'{raw}' is not valid JSON"))
+        }
+        ValueType::Auto => Ok(match serde_json::from_str::<Value>(raw.trim()) {
+            // Scalars and quoted strings keep their JSON type
+            Ok(value) if !value.is_object() && !value.is_array() => value,
+            _ => Value::String(raw.to_string()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn strings(specs: &[&str]) -> Vec<String> {
+        specs.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_value_typing() {
+        let cases = [
+            ("9090", ValueType::Auto, json!(9090)),
+            ("1.5", ValueType::Auto, json!(1.5)),
+            ("true", ValueType::Auto, json!(true)),
+            ("null", ValueType::Auto, json!(null)),
+            ("localhost", ValueType::Auto, json!("localhost")),
+            ("\"8080\"", ValueType::Auto, json!("8080")),
+            ("8080", ValueType::String, json!("8080")),
+            ("false", ValueType::Bool, json!(false)),
+            ("[1, 2]", ValueType::Json, json!([1, 2])),
+        ];
+        for (raw, value_type, expected) in cases {
+            assert_eq!(
+                parse_value(raw, value_type).unwrap(),
+                expected,
+                "raw: {raw}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_value_rejects_mismatched_types() {
+        assert!(parse_value("abc", ValueType::Number).is_err());
+        assert!(parse_value("yes", ValueType::Bool).is_err());
+        assert!(parse_value("{", ValueType::Json).is_err());
+    }
+
+    #[test]
+    fn test_overrides_infer_types_and_apply() {
+        let overrides = Overrides::from_args(
+            &strings(&["server.port=9090", "features.dark_mode=true"]),
+            &strings(&["version=2.0"]),
+        )
+        .unwrap();
+
+        let mut doc = json!({ "app_name": "x", "version": "1.0" });
+        overrides.apply(&mut doc).unwrap();
+        assert_eq!(
+            doc,
+            json!({
+                "app_name": "x",
+                "version": "2.0",
+                "server": { "port": 9090 },
+                "features": { "dark_mode": true }
+            })
+        );
+    }
+
+    #[test]
+    fn test_bad_overrides_fail_early() {
+        let cases = [
+            "server.port",          // no '='
+            "server.prot=1",        // unknown field
+            "server..port=1",       // malformed path
+            "server.port=abc",      // wrong type
+            "server.port=70000",    // out of range for u16
+            "features.dark_mode=1", // map values are booleans
+        ];
+        for spec in cases {
+            assert!(
+                Overrides::from_args(&strings(&[spec]), &[]).is_err(),
+                "{spec}"
+            );
+        }
+
+        let err = Overrides::from_args(&strings(&["server.prot=1"]), &[]).unwrap_err();
+        assert!(err.to_string().contains("server.port"), "{err}");
+    }
+
+    #[test]
+    fn test_env_names_map_to_paths() {
+        let cases = [
+            (
+                "CW_OVERRIDE__SERVER__PORT",
+                "9090",
+                "server.port",
+                json!(9090),
+            ),
+            (
+                "CW_OVERRIDE__FEATURES__DARK_MODE",
+                "true",
+                "features.dark_mode",
+                json!(true),
+            ),
+            ("CW_OVERRIDE__APP_NAME", "Env", "app_name", json!("Env")),
+            (
+                "CW_OVERRIDE__database__POOL_SIZE",
+                "3",
+                "database.pool_size",
+                json!(3),
+            ),
+        ];
+        for (name, value, path, expected) in cases {
+            let entry = Override::from_env_var(name, value).unwrap().unwrap();
+            assert_eq!(entry.path.to_string(), path, "{name}");
+            assert_eq!(entry.value, expected, "{name}");
+            assert_eq!(entry.source, Source::Env(name.to_string()));
+        }
+        assert!(Override::from_env_var("HOME", "/root").is_none());
+    }
+
+    #[test]
+    fn test_bad_env_names_are_listed() {
+        let vars = [
+            ("CW_OVERRIDE__SERVER____PORT", "1"),
+            ("CW_OVERRIDE__SERVER__PROT", "1"),
+            ("CW_OVERRIDE__SERVER__HOST", "ok"),
+            ("PATH", "/bin"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+
+        let err = Overrides::from_env(vars).unwrap_err().to_string();
+        assert!(err.contains("CW_OVERRIDE__SERVER____PORT"), "{err}");
+        assert!(err.contains("CW_OVERRIDE__SERVER__PROT"), "{err}");
+        assert!(!err.contains("CW_OVERRIDE__SERVER__HOST"), "{err}");
+    }
+
+    #[test]
+    fn test_precedence_defaults_file_env_cli() {
+        let file = json!({
+            "app_name": "x", "version": "1.0",
+            "server": { "host": "h", "port": 80 }
+        });
+        let env = || {
+            Overrides::from_env([("CW_OVERRIDE__SERVER__PORT".to_string(), "8000".to_string())])
+                .unwrap()
+        };
+        let cli = Overrides::from_args(&strings(&["server.port=9090"]), &[]).unwrap();
+
+        // file < env
+        let mut doc = file.clone();
+        env().apply(&mut doc).unwrap();
+        assert_eq!(doc["server"]["port"], 8000);
+
+        // env < CLI, whatever order the layers were collected in
+        let mut layered = cli.clone();
+        layered.replace_env(env());
+        let mut doc = file.clone();
+        layered.apply(&mut doc).unwrap();
+        assert_eq!(doc["server"]["port"], 9090);
+
+        // defaults < file: environment is untouched by the overrides
+        let config: AppConfig = serde_json::from_value(doc).unwrap();
+        assert_eq!(config.environment, "development");
+    }
+
+    #[test]
+    fn test_touches() {
+        let overrides = Overrides::from_args(&strings(&["server.port=1"]), &[]).unwrap();
+        assert!(overrides.touches("server"));
+        assert!(overrides.touches("server.port"));
+        assert!(!overrides.touches("server.host"));
+        assert!(!overrides.touches("serv"));
+    }
+}
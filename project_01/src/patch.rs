@@ -0,0 +1,437 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`#[serde(tag = "op")]`**: Internally tagged enum, the exact shape of an
+  RFC 6902 operation object
+- **`&mut Value` navigation**: Each operation walks to the parent of its
+  target and edits it in place
+- **Recursion**: A merge patch is applied object by object, depth first
+
+**Design decisions**:
+- Implements RFC 6902 (JSON Patch) over RFC 6901 pointers (`/server/port`,
+  `~1` for `/`, `~0` for `~`, `-` for the end of an array)
+- All or nothing: operations apply to a copy, so a failing operation (a
+  `test` included) leaves the caller's document untouched
+- Every error names the operation by its index in the patch array; a
+  malformed operation is reported the same way instead of as a bare serde
+  error about the whole array
+- `merge` implements RFC 7386 (JSON Merge Patch), used by `PUT /config`:
+  `null` removes a key, an object merges into an object, anything else
+  replaces. It cannot fail; existing keys keep their position in the file
+
+******************************************************************************/
+
+use crate::error::{ConfigError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// One JSON Patch operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Operation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+impl Operation {
+    /// Name of the operation, as written in the patch
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Add { .. } => "add",
+            Operation::Remove { .. } => "remove",
+            Operation::Replace { .. } => "replace",
+            Operation::Move { .. } => "move",
+            Operation::Copy { .. } => "copy",
+            Operation::Test { .. } => "test",
+        }
+    }
+}
+
+/// Parses a patch document (a JSON array of operations)
+pub fn parse(patch: &Value) -> Result<Vec<Operation>> {
+    let Value::Array(items) = patch else {
+        return Err(failed(
+            0,
+            "?",
+            "a JSON Patch must be an array of operations",
+        ));
+    };
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            Operation::deserialize(item).map_err(|e| {
+                let op = item.get("op").and_then(Value::as_str).unwrap_or("?");
+                failed(index, op, e.to_string())
+            })
+        })
+        .collect()
+}
+
+/// Applies `operations` to `doc`, all or nothing
+pub fn apply(doc: &mut Value, operations: &[Operation]) -> Result<()> {
+    let mut patched = doc.clone();
+    for (index, operation) in operations.iter().enumerate() {
+        apply_one(&mut patched, operation)
+            .map_err(|reason| failed(index, operation.name(), reason))?;
+    }
+    *doc = patched;
+    Ok(())
+}
+
+/// Applies an RFC 7386 merge `patch` to `doc`
+#[cfg(any(test, feature = "http-server"))]
+pub fn merge(doc: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *doc = patch.clone();
+        return;
+    };
+    if !doc.is_object() {
+        *doc = Value::Object(Default::default());
+    }
+    if let Value::Object(map) = doc {
+        for (key, value) in patch {
+            if value.is_null() {
+                map.shift_remove(key);
+            } else {
+                merge(map.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+fn failed(index: usize, op: &str, reason: impl Into<String>) -> ConfigError {
+    ConfigError::PatchFailed {
+        index,
+        op: op.to_string(),
+        reason: reason.into(),
+    }
+}
+
+fn apply_one(doc: &mut Value, operation: &Operation) -> std::result::Result<(), String> {
+    match operation {
+        Operation::Add { path, value } => add(doc, &tokens(path)?, value.clone()),
+        Operation::Remove { path } => remove(doc, &tokens(path)?).map(drop),
+        Operation::Replace { path, value } => {
+            tokens(path)?;
+            // In place, so the key keeps its position in the file
+            let slot = doc
+                .pointer_mut(path)
+                .ok_or_else(|| format!("'{path}' does not exist"))?;
+            *slot = value.clone();
+            Ok(())
+        }
+        Operation::Move { from, path } => {
+            let (source, target) = (tokens(from)?, tokens(path)?);
+            if target.len() > source.len() && target.starts_with(&source) {
+                return Err(format!("cannot move '{from}' into its own child '{path}'"));
+            }
+            let value = remove(doc, &source)?;
+            add(doc, &target, value)
+        }
+        Operation::Copy { from, path } => {
+            let value = get(doc, &tokens(from)?)
+                .ok_or_else(|| format!("'{from}' does not exist"))?
+                .clone();
+            add(doc, &tokens(path)?, value)
+        }
+        Operation::Test { path, value } => match get(doc, &tokens(path)?) {
+            Some(actual) if actual == value => Ok(()),
+            Some(actual) => Err(format!("'{path}' is {actual}, expected {value}")),
+            None => Err(format!("'{path}' does not exist")),
+        },
+    }
+}
+
+/// Splits an RFC 6901 pointer into unescaped reference tokens
+fn tokens(pointer: &str) -> std::result::Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(format!(
+            "'{pointer}' is not a JSON Pointer (must start with '/')"
+        ));
+    };
+    Ok(rest
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+fn get<'a>(doc: &'a Value, tokens: &[String]) -> Option<&'a Value> {
+    tokens.iter().try_fold(doc, |value, token| match value {
+        Value::Object(map) => map.get(token),
+        Value::Array(items) => array_index(token, items.len()).and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+/// Parent container of the target and the last token
+fn parent<'a>(
+    doc: &'a mut Value,
+    tokens: &'a [String],
+) -> std::result::Result<(&'a mut Value, &'a str), String> {
+    let (last, parents) = tokens
+        .split_last()
+        .ok_or_else(|| "the whole document cannot be the target".to_string())?;
+    let mut value = doc;
+    for (depth, token) in parents.iter().enumerate() {
+        let child = match value {
+            Value::Object(map) => map.get_mut(token),
+            Value::Array(items) => {
+                let len = items.len();
+                array_index(token, len).and_then(|i| items.get_mut(i))
+            }
+            _ => None,
+        };
+        value = child.ok_or_else(|| format!("'{}' does not exist", pointer(&tokens[..=depth])))?;
+    }
+    Ok((value, last))
+}
+
+fn add(doc: &mut Value, tokens: &[String], value: Value) -> std::result::Result<(), String> {
+    if tokens.is_empty() {
+        *doc = value;
+        return Ok(());
+    }
+    let (parent, last) = parent(doc, tokens)?;
+    match parent {
+        Value::Object(map) => {
+            map.insert(last.to_string(), value);
+            Ok(())
+        }
+        Value::Array(items) => {
+            let index = if last == "-" {
+                items.len()
+            } else {
+                array_index(last, items.len() + 1)
+                    .ok_or_else(|| format!("'{}' is not a valid array index", pointer(tokens)))?
+            };
+            items.insert(index, value);
+            Ok(())
+        }
+        _ => Err(format!(
+            "the parent of '{}' is not a container",
+            pointer(tokens)
+        )),
+    }
+}
+
+fn remove(doc: &mut Value, tokens: &[String]) -> std::result::Result<Value, String> {
+    let missing = || format!("'{}' does not exist", pointer(tokens));
+    let (parent, last) = parent(doc, tokens)?;
+    match parent {
+        Value::Object(map) => map.shift_remove(last).ok_or_else(missing),
+        Value::Array(items) => {
+            let index = array_index(last, items.len()).ok_or_else(missing)?;
+            Ok(items.remove(index))
+        }
+        _ => Err(missing()),
+    }
+}
+
+/// A decimal index below `len`, without leading zeros
+fn array_index(token: &str, len: usize) -> Option<usize> {
+    if token.len() > 1 && token.starts_with('0') {
+        return None;
+    }
+    token.parse().ok().filter(|&i| i < len)
+}
+
+fn pointer(tokens: &[String]) -> String {
+    tokens
+        .iter()
+        .map(|token| format!("/{}", escape(token)))
+        .collect()
+}
+
+/// Escapes a reference token: `~` as `~0`, then `/` as `~1`
+pub fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn patched(doc: Value, patch: Value) -> Result<Value> {
+        let mut doc = doc;
+        apply(&mut doc, &parse(&patch)?)?;
+        Ok(doc)
+    }
+
+    fn base() -> Value {
+        json!({
+            "app_name": "A",
+            "server": { "host": "h", "port": 80, "allowed_ips": ["10.0.0.1"] },
+            "features": { "a/b": true }
+        })
+    }
+
+    fn failure(result: Result<Value>) -> (usize, String, String) {
+        match result.unwrap_err() {
+            ConfigError::PatchFailed { index, op, reason } => (index, op, reason),
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    fn test_add() {
+        let doc = patched(
+            base(),
+            json!([
+                { "op": "add", "path": "/environment", "value": "staging" },
+                { "op": "add", "path": "/server/allowed_ips/0", "value": "10.0.0.0" },
+                { "op": "add", "path": "/server/allowed_ips/-", "value": "10.0.0.2" }
+            ]),
+        )
+        .unwrap();
+        assert_eq!(doc["environment"], "staging");
+        assert_eq!(
+            doc["server"]["allowed_ips"],
+            json!(["10.0.0.0", "10.0.0.1", "10.0.0.2"])
+        );
+    }
+
+    #[test]
+    fn test_remove_and_replace() {
+        let doc = patched(
+            base(),
+            json!([
+                { "op": "remove", "path": "/features/a~1b" },
+                { "op": "replace", "path": "/server/port", "value": 8080 }
+            ]),
+        )
+        .unwrap();
+        assert_eq!(doc["features"], json!({}));
+        assert_eq!(doc["server"]["port"], 8080);
+        let keys: Vec<_> = doc["server"].as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["host", "port", "allowed_ips"]);
+
+        let (index, op, reason) = failure(patched(
+            base(),
+            json!([{ "op": "replace", "path": "/server/tls", "value": 1 }]),
+        ));
+        assert_eq!((index, op.as_str()), (0, "replace"));
+        assert_eq!(reason, "'/server/tls' does not exist");
+    }
+
+    #[test]
+    fn test_move_and_copy() {
+        let doc = patched(
+            base(),
+            json!([
+                { "op": "copy", "from": "/server/host", "path": "/app_name" },
+                { "op": "move", "from": "/features", "path": "/flags" }
+            ]),
+        )
+        .unwrap();
+        assert_eq!(doc["app_name"], "h");
+        assert_eq!(doc["flags"], json!({ "a/b": true }));
+        assert!(doc.get("features").is_none());
+
+        let (_, op, reason) = failure(patched(
+            base(),
+            json!([{ "op": "move", "from": "/server", "path": "/server/inner" }]),
+        ));
+        assert_eq!(op, "move");
+        assert!(reason.contains("own child"), "{reason}");
+    }
+
+    #[test]
+    fn test_failing_test_aborts_with_its_index() {
+        let mut doc = base();
+        let operations = parse(&json!([
+            { "op": "replace", "path": "/server/port", "value": 81 },
+            { "op": "test", "path": "/app_name", "value": "B" }
+        ]))
+        .unwrap();
+        let err = apply(&mut doc, &operations).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"Patch operation 1 (test) failed: '/app_name' is "A", expected "B""#
+        );
+        // Nothing was applied
+        assert_eq!(doc, base());
+
+        assert!(
+            patched(
+                base(),
+                json!([{ "op": "test", "path": "/server/port", "value": 80 }])
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_malformed_patch() {
+        let (index, op, _) = failure(patched(
+            base(),
+            json!([
+                { "op": "test", "path": "/app_name", "value": "A" },
+                { "op": "add", "path": "/x" }
+            ]),
+        ));
+        assert_eq!((index, op.as_str()), (1, "add"));
+
+        let (_, _, reason) = failure(patched(
+            base(),
+            json!([{ "op": "add", "path": "x", "value": 1 }]),
+        ));
+        assert!(reason.contains("must start with '/'"), "{reason}");
+        assert!(patched(base(), json!({ "op": "add" })).is_err());
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        // The examples of RFC 7386, appendix A
+        let cases = [
+            (json!({"a":"b"}), json!({"a":"c"}), json!({"a":"c"})),
+            (json!({"a":"b"}), json!({"b":"c"}), json!({"a":"b","b":"c"})),
+            (json!({"a":"b"}), json!({"a":null}), json!({})),
+            (
+                json!({"a":"b","b":"c"}),
+                json!({"a":null}),
+                json!({"b":"c"}),
+            ),
+            (json!({"a":["b"]}), json!({"a":"c"}), json!({"a":"c"})),
+            (json!({"a":"c"}), json!({"a":["b"]}), json!({"a":["b"]})),
+            (
+                json!({"a":{"b":"c"}}),
+                json!({"a":{"b":"d","c":null}}),
+                json!({"a":{"b":"d"}}),
+            ),
+            (json!({"a":[{"b":"c"}]}), json!({"a":[1]}), json!({"a":[1]})),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a":"b"}), json!(["c"]), json!(["c"])),
+            (json!({"a":"foo"}), json!(null), json!(null)),
+            (json!({"a":"foo"}), json!("bar"), json!("bar")),
+            (json!({"e":null}), json!({"a":1}), json!({"e":null,"a":1})),
+            (json!([1, 2]), json!({"a":"b","c":null}), json!({"a":"b"})),
+            (
+                json!({}),
+                json!({"a":{"bb":{"ccc":null}}}),
+                json!({"a":{"bb":{}}}),
+            ),
+        ];
+        for (doc, patch, expected) in cases {
+            let mut merged = doc.clone();
+            merge(&mut merged, &patch);
+            assert_eq!(merged, expected, "{doc} + {patch}");
+        }
+
+        // Keys keep their place in the file
+        let mut doc = base();
+        merge(&mut doc, &json!({ "app_name": "B" }));
+        let keys: Vec<&String> = doc.as_object().unwrap().keys().collect();
+        let original = base();
+        let expected: Vec<&String> = original.as_object().unwrap().keys().collect();
+        assert_eq!(keys, expected);
+    }
+}
@@ -0,0 +1,464 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **Enums with data**: `Segment` is either a map key or an array index
+- **Hand-written parser**: `Peekable<Chars>` walks the input one char at a time
+- **Borrowing through a tree**: `resolve` returns `&Value` without cloning
+- **`Display`**: Renders a path back to its canonical textual form
+
+**Design decisions**:
+- Works on `serde_json::Value` so every subcommand (get, set, overrides...)
+  shares one resolver regardless of the struct layout
+- Dotted syntax for the common case, bracket syntax (`features["a.b"]`)
+  for map keys that contain dots, `[0]` for array elements
+- "Not found" errors report the deepest existing ancestor to help the user
+
+******************************************************************************/
+
+use crate::error::{ConfigError, Result};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// One step in a field path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// Object member, e.g. `server` or `"a.b"`
+    Key(String),
+    /// Array element, e.g. `[0]`
+    Index(usize),
+}
+
+/// A parsed dotted path such as `server.port` or `features["a.b"]`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FieldPath {
+    segments: Vec<Segment>,
+}
+
+impl FieldPath {
+    /// The empty path, pointing at the document root
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// Parses a textual path
+    pub fn parse(input: &str) -> Result<Self> {
+        let invalid = |reason: &str| ConfigError::InvalidPath {
+            path: input.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let mut segments = Vec::new();
+        let mut chars = input.chars().peekable();
+        // True right after a '.', where a bare key is mandatory
+        let mut expect_key = true;
+
+        while let Some(&c) = chars.peek() {
+            match c {
+                '.' => {
+                    if expect_key {
+                        return Err(invalid("empty segment"));
+                    }
+                    chars.next();
+                    expect_key = true;
+                    if chars.peek().is_none() {
+                        return Err(invalid("path cannot end with '.'"));
+                    }
+                }
+                '[' => {
+                    if expect_key && !segments.is_empty() {
+                        return Err(invalid("empty segment before '['"));
+                    }
+                    chars.next();
+                    match chars.peek() {
+                        Some('"') => {
+                            chars.next();
+                            let mut key = String::new();
+                            loop {
+                                match chars.next() {
+                                    Some('\\') => match chars.next() {
+                                        Some(escaped) => key.push(escaped),
+                                        None => return Err(invalid("unterminated escape")),
+                                    },
+                                    Some('"') => break,
+                                    Some(other) => key.push(other),
+                                    None => return Err(invalid("unterminated quoted key")),
+                                }
+                            }
+                            if chars.next() != Some(']') {
+                                return Err(invalid("expected ']' after quoted key"));
+                            }
+                            segments.push(Segment::Key(key));
+                        }
+                        _ => {
+                            let mut digits = String::new();
+                            let mut closed = false;
+                            for d in chars.by_ref() {
+                                if d == ']' {
+                                    closed = true;
+                                    break;
+                                }
+                                digits.push(d);
+                            }
+                            if !closed {
+                                return Err(invalid("unterminated '['"));
+                            }
+                            let index = digits.parse::<usize>().map_err(|_| {
+                                invalid("bracket must hold a quoted key or an index")
+                            })?;
+                            segments.push(Segment::Index(index));
+                        }
+                    }
+                    expect_key = false;
+                }
+                _ => {
+                    if !expect_key {
+                        return Err(invalid("expected '.' or '[' between segments"));
+                    }
+                    let mut key = String::new();
+                    while let Some(&k) = chars.peek() {
+                        if k == '.' || k == '[' {
+                            break;
+                        }
+                        if k == ']' || k == '"' {
+                            return Err(invalid("unexpected character in key"));
+                        }
+                        key.push(k);
+                        chars.next();
+                    }
+                    segments.push(Segment::Key(key));
+                    expect_key = false;
+                }
+            }
+        }
+
+        if segments.is_empty() {
+            return Err(invalid("path is empty"));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// The individual segments of the path
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// The path as an RFC 6901 JSON Pointer (`/features/a~1b`)
+    ///
+    /// `~` and `/` in keys are escaped as `~0` and `~1`; the root is `""`.
+    pub fn to_pointer(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Key(key) => format!("/{}", crate::patch::escape(key)),
+                Segment::Index(index) => format!("/{index}"),
+            })
+            .collect()
+    }
+
+    /// Returns a new path with `segment` appended
+    pub fn child(&self, segment: Segment) -> Self {
+        let mut segments = self.segments.clone();
+        segments.push(segment);
+        Self { segments }
+    }
+
+    /// Looks the path up in a JSON document
+    ///
+    /// On failure the error names the deepest ancestor that does exist
+    pub fn resolve<'a>(&self, doc: &'a Value) -> Result<&'a Value> {
+        let mut current = doc;
+        for (depth, segment) in self.segments.iter().enumerate() {
+            let next = match (segment, current) {
+                (Segment::Key(key), Value::Object(map)) => map.get(key),
+                (Segment::Index(i), Value::Array(items)) => items.get(*i),
+                _ => None,
+            };
+            current = match next {
+                Some(value) => value,
+                None => {
+                    return Err(ConfigError::PathNotFound {
+                        path: self.to_string(),
+                        ancestor: self.prefix(depth).to_string(),
+                    });
+                }
+            };
+        }
+        Ok(current)
+    }
+
+    /// Stores `value` at the path, creating intermediate objects as needed
+    ///
+    /// `null` or missing intermediates (e.g. an absent optional section) are
+    /// replaced by empty objects. Walking through a scalar is an error.
+    pub fn set(&self, doc: &mut Value, value: Value) -> Result<()> {
+        let Some((last, parents)) = self.segments.split_last() else {
+            *doc = value;
+            return Ok(());
+        };
+
+        let mut current = doc;
+        for (depth, segment) in parents.iter().enumerate() {
+            current = self.step_mut(current, segment, depth)?;
+        }
+
+        match (last, current) {
+            (Segment::Key(key), slot) => {
+                if slot.is_null() {
+                    *slot = Value::Object(Map::new());
+                }
+                match slot {
+                    Value::Object(map) => {
+                        map.insert(key.clone(), value);
+                        Ok(())
+                    }
+                    _ => Err(self.not_container(parents.len())),
+                }
+            }
+            (Segment::Index(i), Value::Array(items)) if *i < items.len() => {
+                items[*i] = value;
+                Ok(())
+            }
+            (Segment::Index(i), Value::Array(items)) if *i == items.len() => {
+                items.push(value);
+                Ok(())
+            }
+            _ => Err(ConfigError::PathNotFound {
+                path: self.to_string(),
+                ancestor: self.prefix(parents.len()).to_string(),
+            }),
+        }
+    }
+
+    /// Removes the value at the path, returning it
+    pub fn remove(&self, doc: &mut Value) -> Result<Value> {
+        let Some((last, parents)) = self.segments.split_last() else {
+            return Ok(std::mem::take(doc));
+        };
+
+        let not_found = || ConfigError::PathNotFound {
+            path: self.to_string(),
+            ancestor: self.prefix(parents.len()).to_string(),
+        };
+
+        let parent = FieldPath {
+            segments: parents.to_vec(),
+        };
+        let mut current = doc;
+        for (depth, segment) in parent.segments.iter().enumerate() {
+            current = match (segment, current) {
+                (Segment::Key(key), Value::Object(map)) => map.get_mut(key),
+                (Segment::Index(i), Value::Array(items)) => items.get_mut(*i),
+                _ => None,
+            }
+            .ok_or_else(|| ConfigError::PathNotFound {
+                path: self.to_string(),
+                ancestor: self.prefix(depth).to_string(),
+            })?;
+        }
+
+        match (last, current) {
+            (Segment::Key(key), Value::Object(map)) => map.remove(key).ok_or_else(not_found),
+            (Segment::Index(i), Value::Array(items)) if *i < items.len() => Ok(items.remove(*i)),
+            _ => Err(not_found()),
+        }
+    }
+
+    /// Moves one level down for `set`, materializing missing objects
+    fn step_mut<'a>(
+        &self,
+        current: &'a mut Value,
+        segment: &Segment,
+        depth: usize,
+    ) -> Result<&'a mut Value> {
+        match segment {
+            Segment::Key(key) => {
+                if current.is_null() {
+                    *current = Value::Object(Map::new());
+                }
+                match current {
+                    Value::Object(map) => Ok(map.entry(key.clone()).or_insert(Value::Null)),
+                    _ => Err(self.not_container(depth)),
+                }
+            }
+            Segment::Index(i) => match current {
+                Value::Array(items) if *i < items.len() => Ok(&mut items[*i]),
+                _ => Err(ConfigError::PathNotFound {
+                    path: self.to_string(),
+                    ancestor: self.prefix(depth).to_string(),
+                }),
+            },
+        }
+    }
+
+    fn not_container(&self, depth: usize) -> ConfigError {
+        ConfigError::InvalidPath {
+            path: self.to_string(),
+            reason: format!(
+                "'{}' is not an object and cannot hold fields",
+                self.prefix(depth)
+            ),
+        }
+    }
+
+    /// The first `len` segments as a path of their own
+    fn prefix(&self, len: usize) -> FieldPath {
+        FieldPath {
+            segments: self.segments[..len].to_vec(),
+        }
+    }
+}
+
+impl FromStr for FieldPath {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.segments.is_empty() {
+            return write!(f, "<root>");
+        }
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Key(key) if is_plain_key(key) => {
+                    if i > 0 {
+                        write!(f, ".")?;
+                    }
+                    write!(f, "{key}")?;
+                }
+                Segment::Key(key) => {
+                    let escaped = key.replace('\\', "\\\\").replace('"', "\\\"");
+                    write!(f, "[\"{escaped}\"]")?;
+                }
+                Segment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Keys that can be written without brackets
+fn is_plain_key(key: &str) -> bool {
+    !key.is_empty() && !key.contains(['.', '[', ']', '"'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn key(k: &str) -> Segment {
+        Segment::Key(k.to_string())
+    }
+
+    #[test]
+    fn test_parse_table() {
+        let cases: Vec<(&str, Vec<Segment>)> = vec![
+            ("app_name", vec![key("app_name")]),
+            ("server.port", vec![key("server"), key("port")]),
+            ("features[\"a.b\"]", vec![key("features"), key("a.b")]),
+            ("features[\"q\\\"x\"]", vec![key("features"), key("q\"x")]),
+            (
+                "list[0].name",
+                vec![key("list"), Segment::Index(0), key("name")],
+            ),
+            ("[\"odd.root\"].x", vec![key("odd.root"), key("x")]),
+        ];
+
+        for (input, expected) in cases {
+            let path = FieldPath::parse(input).unwrap();
+            assert_eq!(path.segments(), expected.as_slice(), "input: {input}");
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_paths() {
+        for input in [
+            "",
+            ".",
+            "server.",
+            ".server",
+            "server..port",
+            "features[\"a.b\"",
+            "features[abc]",
+            "features[\"a\"]x",
+            "server.[0]",
+            "list[1",
+        ] {
+            assert!(FieldPath::parse(input).is_err(), "should reject: {input:?}");
+        }
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for input in ["server.port", "features[\"a.b\"]", "list[2].name"] {
+            assert_eq!(FieldPath::parse(input).unwrap().to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_to_pointer_escapes_keys() {
+        let cases = [
+            ("server.port", "/server/port"),
+            ("features[\"a/b\"]", "/features/a~1b"),
+            ("features[\"~1\"]", "/features/~01"),
+            ("list[2].name", "/list/2/name"),
+            ("features[\"\"]", "/features/"),
+        ];
+        for (input, pointer) in cases {
+            assert_eq!(FieldPath::parse(input).unwrap().to_pointer(), pointer);
+        }
+        assert_eq!(FieldPath::root().to_pointer(), "");
+    }
+
+    #[test]
+    fn test_resolve_reports_deepest_ancestor() {
+        let doc = json!({ "server": { "host": "localhost" } });
+
+        let path = FieldPath::parse("server.tls.cert").unwrap();
+        match path.resolve(&doc) {
+            Err(ConfigError::PathNotFound { ancestor, .. }) => assert_eq!(ancestor, "server"),
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        let path = FieldPath::parse("missing").unwrap();
+        match path.resolve(&doc) {
+            Err(ConfigError::PathNotFound { ancestor, .. }) => assert_eq!(ancestor, "<root>"),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_set_creates_intermediate_objects() {
+        let mut doc = json!({ "server": null });
+        FieldPath::parse("server.host")
+            .unwrap()
+            .set(&mut doc, json!("localhost"))
+            .unwrap();
+        assert_eq!(doc, json!({ "server": { "host": "localhost" } }));
+
+        let mut doc = json!({ "app_name": "x" });
+        assert!(
+            FieldPath::parse("app_name.inner")
+                .unwrap()
+                .set(&mut doc, json!(1))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut doc = json!({ "features": { "a.b": true, "c": false } });
+        let removed = FieldPath::parse("features[\"a.b\"]")
+            .unwrap()
+            .remove(&mut doc)
+            .unwrap();
+        assert_eq!(removed, json!(true));
+        assert_eq!(doc, json!({ "features": { "c": false } }));
+    }
+}
@@ -0,0 +1,407 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`std::os::unix::fs::MetadataExt`**: `mode()` and `uid()` of a file,
+  behind `#[cfg(unix)]`
+- **Trait object (`Arc<dyn Inspect>`)**: The audit asks an `Inspect` for
+  modes and owners, so tests hand it a table instead of chmod-ing files
+- **`{:04o}`**: Mode bits printed the way `chmod` takes them
+
+**Design decisions**:
+- Runs with the validation step of every load (startup and each change),
+  and its findings join the validation report: an error rejects the load
+  and keeps the last valid configuration, like any other rule
+- A group- or world-writable configuration is always a warning: anyone
+  who can write it can change what the application runs with
+- Secret files are the string values of `*_file` fields, of TLS key
+  fields (`tls_key`, `ssl_key`, `private_key`, `tls.key`...), plus the
+  files given on the command line (`--age-identity`). One readable by
+  group or others, or not owned by the user running the watcher, gets a
+  finding of the configured severity (error by default)
+- Relative paths are resolved against the configuration's directory; a
+  secret file that does not exist is left to the application
+- Every message carries the actual mode bits, e.g. `(mode 0644)`
+- Windows has no mode bits: `Host` reports nothing there and the audit is
+  skipped (an ACL check would need the Win32 security APIs)
+
+******************************************************************************/
+
+use crate::path::{FieldPath, Segment};
+use crate::validation::{Severity, ValidationReport};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Fields named like this hold the path of a TLS private key
+const KEY_FIELDS: &[&str] = &["tls_key", "ssl_key", "private_key", "key_path"];
+
+/// Sections whose `key` field is a TLS private key
+const TLS_SECTIONS: &[&str] = &["tls", "ssl"];
+
+/// What the audit needs to know about a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMode {
+    /// Permission bits, e.g. `0o644`
+    pub mode: u32,
+    /// Owner's user id
+    pub uid: u32,
+}
+
+/// Where the audit gets file modes and the current user from
+pub trait Inspect: Send + Sync {
+    /// Mode and owner of `path`; None when it cannot be inspected (missing
+    /// file, or no mode bits on this platform)
+    fn inspect(&self, path: &Path) -> Option<FileMode>;
+
+    /// The effective user id of the watcher; None without Unix users
+    fn current_uid(&self) -> Option<u32>;
+}
+
+/// The real file system and process
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Host;
+
+impl Inspect for Host {
+    #[cfg(unix)]
+    fn inspect(&self, path: &Path) -> Option<FileMode> {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(FileMode {
+            mode: metadata.mode() & 0o7777,
+            uid: metadata.uid(),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn inspect(&self, _: &Path) -> Option<FileMode> {
+        None
+    }
+
+    #[cfg(unix)]
+    fn current_uid(&self) -> Option<u32> {
+        Some(rustix::process::geteuid().as_raw())
+    }
+
+    #[cfg(not(unix))]
+    fn current_uid(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// Checks the permissions of a configuration and of the secret files it
+/// references
+#[derive(Clone)]
+pub struct PermissionAudit {
+    severity: Severity,
+    inspector: Arc<dyn Inspect>,
+    /// Secret files named outside the configuration, with the option
+    /// that named them
+    secret_files: Vec<(String, PathBuf)>,
+}
+
+impl std::fmt::Debug for PermissionAudit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PermissionAudit")
+            .field("severity", &self.severity)
+            .field("secret_files", &self.secret_files)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PermissionAudit {
+    /// An audit of the real files, reporting secret files at `severity`
+    pub fn new(severity: Severity) -> Self {
+        Self {
+            severity,
+            inspector: Arc::new(Host),
+            secret_files: Vec::new(),
+        }
+    }
+
+    /// Asks `inspector` instead of the file system
+    #[cfg(test)]
+    pub fn with_inspector(mut self, inspector: impl Inspect + 'static) -> Self {
+        self.inspector = Arc::new(inspector);
+        self
+    }
+
+    /// Also audits `path`, a secret file given by `option`
+    pub fn with_secret_file(mut self, option: &str, path: impl Into<PathBuf>) -> Self {
+        self.secret_files.push((option.to_string(), path.into()));
+        self
+    }
+
+    /// The findings for `file` (None for a configuration that is not a
+    /// file) and the secret files of `doc`
+    pub fn check(&self, file: Option<&Path>, doc: &Value) -> ValidationReport {
+        let mut report = ValidationReport::new();
+        if let Some(file) = file
+            && let Some(FileMode { mode, .. }) = self.inspector.inspect(file)
+            && let Some(who) = who(mode, 0o020, 0o002)
+        {
+            report.warning(
+                file.display().to_string(),
+                format!("is writable by {who} (mode {mode:04o})"),
+            );
+        }
+
+        let base = file.and_then(Path::parent).unwrap_or(Path::new(""));
+        let mut secrets = self.secret_files.clone();
+        secrets.extend(
+            secret_references(doc)
+                .into_iter()
+                .map(|(path, value)| (path.to_string(), base.join(value))),
+        );
+        for (label, path) in secrets {
+            self.check_secret(&mut report, &label, &path);
+        }
+        report
+    }
+
+    fn check_secret(&self, report: &mut ValidationReport, label: &str, path: &Path) {
+        let Some(FileMode { mode, uid }) = self.inspector.inspect(path) else {
+            return;
+        };
+        let mut record = |message: String| match self.severity {
+            Severity::Error => report.error(label, message),
+            Severity::Warning => report.warning(label, message),
+        };
+        if let Some(who) = who(mode, 0o040, 0o004) {
+            record(format!(
+                "secret file {} is readable by {who} (mode {mode:04o}), expected 0600",
+                path.display()
+            ));
+        }
+        if let Some(current) = self.inspector.current_uid()
+            && uid != current
+        {
+            record(format!(
+                "secret file {} is owned by uid {uid}, not by uid {current} running the watcher (mode {mode:04o})",
+                path.display()
+            ));
+        }
+    }
+}
+
+/// "group", "others" or both, for the bits of `mode` they have
+fn who(mode: u32, group: u32, others: u32) -> Option<&'static str> {
+    match (mode & group != 0, mode & others != 0) {
+        (true, true) => Some("group and others"),
+        (true, false) => Some("group"),
+        (false, true) => Some("others"),
+        (false, false) => None,
+    }
+}
+
+/// Every string of `doc` naming a secret file, with its path
+pub fn secret_references(doc: &Value) -> Vec<(FieldPath, String)> {
+    let mut found = Vec::new();
+    walk(&FieldPath::root(), None, doc, &mut found);
+    found
+}
+
+fn walk(
+    path: &FieldPath,
+    section: Option<&str>,
+    value: &Value,
+    found: &mut Vec<(FieldPath, String)>,
+) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let child_path = path.child(Segment::Key(key.clone()));
+                match child {
+                    Value::String(file) if is_secret_field(section, key) => {
+                        found.push((child_path, file.clone()));
+                    }
+                    _ => walk(&child_path, Some(key), child, found),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                walk(&path.child(Segment::Index(index)), section, child, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether the string of field `key`, in the object at `section`, is the
+/// path of a secret file
+fn is_secret_field(section: Option<&str>, key: &str) -> bool {
+    key.ends_with("_file")
+        || KEY_FIELDS.contains(&key)
+        || (key == "key" && section.is_some_and(|section| TLS_SECTIONS.contains(&section)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    /// Modes and owners from a table; every file is owned by uid 1000
+    /// unless listed in `owners`
+    #[derive(Default)]
+    struct Table {
+        modes: HashMap<PathBuf, u32>,
+        owners: HashMap<PathBuf, u32>,
+    }
+
+    impl Inspect for Table {
+        fn inspect(&self, path: &Path) -> Option<FileMode> {
+            Some(FileMode {
+                mode: *self.modes.get(path)?,
+                uid: self.owners.get(path).copied().unwrap_or(1000),
+            })
+        }
+
+        fn current_uid(&self) -> Option<u32> {
+            Some(1000)
+        }
+    }
+
+    fn messages(report: &ValidationReport) -> Vec<String> {
+        report
+            .findings()
+            .iter()
+            .map(|f| format!("{} {f}", f.severity))
+            .collect()
+    }
+
+    #[test]
+    fn test_secret_references() {
+        let doc = json!({
+            "database": { "password_file": "db.pass", "pool_size": 4 },
+            "tls": { "key": "/etc/tls/key.pem", "cert": "/etc/tls/cert.pem" },
+            "upstreams": [{ "ssl_key": "up.key" }, { "key": "not a TLS section" }],
+            "log_file": 7
+        });
+        let found: Vec<(String, String)> = secret_references(&doc)
+            .into_iter()
+            .map(|(path, file)| (path.to_string(), file))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("database.password_file".into(), "db.pass".into()),
+                ("tls.key".into(), "/etc/tls/key.pem".into()),
+                ("upstreams[0].ssl_key".into(), "up.key".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_findings_from_a_table() {
+        let table = Table {
+            modes: HashMap::from([
+                (PathBuf::from("/etc/app/config.json"), 0o664),
+                (PathBuf::from("/etc/app/db.pass"), 0o640),
+                (PathBuf::from("/etc/tls/key.pem"), 0o600),
+                (PathBuf::from("/etc/cw/key.txt"), 0o604),
+            ]),
+            owners: HashMap::from([(PathBuf::from("/etc/tls/key.pem"), 0)]),
+        };
+        let audit = PermissionAudit::new(Severity::Error)
+            .with_inspector(table)
+            .with_secret_file("--age-identity", "/etc/cw/key.txt");
+        let doc = json!({
+            "database": { "password_file": "db.pass" },
+            "tls": { "key": "/etc/tls/key.pem" },
+            "cache": { "token_file": "missing.token" }
+        });
+        let report = audit.check(Some(Path::new("/etc/app/config.json")), &doc);
+        assert_eq!(
+            messages(&report),
+            [
+                "warning /etc/app/config.json: is writable by group (mode 0664)",
+                "error --age-identity: secret file /etc/cw/key.txt is readable by others (mode 0604), expected 0600",
+                "error database.password_file: secret file /etc/app/db.pass is readable by group (mode 0640), expected 0600",
+                "error tls.key: secret file /etc/tls/key.pem is owned by uid 0, not by uid 1000 running the watcher (mode 0600)",
+            ]
+        );
+
+        // The same findings as warnings, and nothing for a clean setup
+        let audit = PermissionAudit {
+            severity: Severity::Warning,
+            ..audit
+        };
+        let report = audit.check(Some(Path::new("/etc/app/config.json")), &doc);
+        assert_eq!(report.warnings().count(), 4);
+        assert!(!report.has_errors());
+        assert_eq!(
+            messages(&audit.check(None, &json!({}))),
+            [
+                "warning --age-identity: secret file /etc/cw/key.txt is readable by others (mode 0604), expected 0600"
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_chmod_combinations() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config.json");
+        let secret = dir.path().join("db.pass");
+        std::fs::write(&config, "{}").unwrap();
+        std::fs::write(&secret, "s3cr3t").unwrap();
+        let doc = json!({ "database": { "password_file": "db.pass" } });
+        let audit = PermissionAudit::new(Severity::Error);
+        let chmod = |path: &Path, mode: u32| {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).unwrap();
+        };
+
+        for (config_mode, secret_mode, expected) in [
+            (0o644, 0o600, vec![]),
+            (0o600, 0o400, vec![]),
+            (
+                0o664,
+                0o600,
+                vec!["warning: is writable by group (mode 0664)"],
+            ),
+            (
+                0o646,
+                0o600,
+                vec!["warning: is writable by others (mode 0646)"],
+            ),
+            (
+                0o666,
+                0o600,
+                vec!["warning: is writable by group and others (mode 0666)"],
+            ),
+            (
+                0o644,
+                0o640,
+                vec!["error: is readable by group (mode 0640)"],
+            ),
+            (
+                0o644,
+                0o604,
+                vec!["error: is readable by others (mode 0604)"],
+            ),
+            (
+                0o644,
+                0o644,
+                vec!["error: is readable by group and others (mode 0644)"],
+            ),
+        ] {
+            chmod(&config, config_mode);
+            chmod(&secret, secret_mode);
+            let report = audit.check(Some(&config), &doc);
+            let found: Vec<String> = report
+                .findings()
+                .iter()
+                .map(|f| {
+                    let message = f
+                        .message
+                        .replace(&format!("secret file {} ", secret.display()), "");
+                    format!("{}: {}", f.severity, message.replace(", expected 0600", ""))
+                })
+                .collect();
+            assert_eq!(found, expected, "{config_mode:o} {secret_mode:o}");
+        }
+    }
+}
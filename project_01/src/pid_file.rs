@@ -0,0 +1,219 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`NamedTempFile::persist_noclobber`**: Puts the written file in place
+  only if there is none, so two instances starting at once cannot both
+  write it, and none ever reads it empty
+- **`Drop`**: The guard removes the file when it goes out of scope, on
+  every way out of `watch` (a signal, `--max-duration`, `--fail-fast`, an
+  error returned with `?`, a panic unwinding)
+- **`rustix::process::test_kill_process`**: `kill(pid, 0)`, which checks
+  that a process exists without signalling it
+
+**Design decisions**:
+- For init tooling, unlike the instance lock (`instance_lock`): the file
+  holds the pid and nothing else, followed by a newline, mode 0644, and is
+  removed on shutdown
+- A pid file naming a live process is refused; one naming a dead process
+  (or this very process, reused pid in a container) is taken over
+  silently. `EPERM` counts as alive: the process exists, it is just not
+  ours. Off Unix liveness cannot be checked and the file is taken over
+- The path is made absolute at startup, against the working directory of
+  that moment, so the removal still finds it if the directory changes
+- On removal, a file that no longer holds our pid was taken over by
+  someone else and is left alone
+
+******************************************************************************/
+
+use crate::error::{ConfigError, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The pid file of this process, removed when dropped
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+    pid: u32,
+}
+
+impl PidFile {
+    /// Writes the pid of this process to `path`
+    ///
+    /// Fails with `ConfigError::PidFileInUse` when `path` already names a
+    /// running process.
+    pub fn create(path: &Path) -> Result<Self> {
+        let path = std::path::absolute(path).map_err(|source| ConfigError::WriteError {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::write(path, std::process::id())
+    }
+
+    /// Where it is, absolute
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Removes the pid file at `path` if it still holds the pid of this
+    /// process, for a way out that skips the drop (a forced shutdown)
+    pub fn remove(path: &Path) {
+        remove_if_owned(path, std::process::id());
+    }
+
+    fn write(path: PathBuf, pid: u32) -> Result<Self> {
+        let write_error = |source| ConfigError::WriteError {
+            path: path.clone(),
+            source,
+        };
+        let dir = match path.parent() {
+            Some(parent) => parent,
+            None => Path::new("/"),
+        };
+        let mut staged = tempfile::NamedTempFile::new_in(dir).map_err(write_error)?;
+        writeln!(staged, "{pid}").map_err(write_error)?;
+        // Whatever the umask
+        #[cfg(unix)]
+        staged
+            .as_file()
+            .set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o644))
+            .map_err(write_error)?;
+
+        // Once more after removing a stale file
+        for _ in 0..2 {
+            match staged.persist_noclobber(&path) {
+                Ok(_) => return Ok(Self { path, pid }),
+                Err(e) if e.error.kind() == std::io::ErrorKind::AlreadyExists => staged = e.file,
+                Err(e) => return Err(write_error(e.error)),
+            }
+
+            match read_pid(&path) {
+                Some(owner) if owner != pid && alive(owner) => {
+                    return Err(ConfigError::PidFileInUse { path, pid: owner });
+                }
+                owner => {
+                    tracing::debug!(path = %path.display(), ?owner, "stale pid file taken over")
+                }
+            }
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(write_error(e)),
+                _ => {}
+            }
+        }
+        Err(write_error(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "recreated by another process meanwhile",
+        )))
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        remove_if_owned(&self.path, self.pid);
+    }
+}
+
+/// Removes `path` unless another process took it over
+fn remove_if_owned(path: &Path, pid: u32) {
+    if read_pid(path) != Some(pid) {
+        return;
+    }
+    if let Err(e) = std::fs::remove_file(path) {
+        tracing::warn!(path = %path.display(), error = %e, "pid file not removed");
+    }
+}
+
+/// The pid in `path`; `None` when it cannot be read or holds no pid
+fn read_pid(path: &Path) -> Option<u32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether the process `pid` exists
+#[cfg(unix)]
+fn alive(pid: u32) -> bool {
+    use rustix::io::Errno;
+    use rustix::process::{Pid, test_kill_process};
+    let Some(pid) = i32::try_from(pid).ok().and_then(Pid::from_raw) else {
+        return false;
+    };
+    !matches!(test_kill_process(pid), Err(Errno::SRCH))
+}
+
+/// Cannot be told off Unix: the file is taken over
+#[cfg(not(unix))]
+fn alive(_: u32) -> bool {
+    false
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// The pid of a process that has exited
+    fn dead_pid() -> u32 {
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        pid
+    }
+
+    #[test]
+    fn test_written_then_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("watcher.pid");
+
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o644);
+        drop(pid_file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_stale_pid_file_is_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("watcher.pid");
+
+        for stale in [format!("{}\n", dead_pid()), "garbage".to_string()] {
+            std::fs::write(&path, stale).unwrap();
+            let pid_file = PidFile::create(&path).unwrap();
+            assert_eq!(read_pid(&path), Some(std::process::id()));
+            drop(pid_file);
+        }
+    }
+
+    #[test]
+    fn test_live_pid_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("watcher.pid");
+        let mut live = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        std::fs::write(&path, format!("{}\n", live.id())).unwrap();
+
+        let err = PidFile::create(&path).unwrap_err();
+        assert!(
+            matches!(err, ConfigError::PidFileInUse { pid, .. } if pid == live.id()),
+            "{err}"
+        );
+        assert_eq!(read_pid(&path), Some(live.id()));
+        live.kill().unwrap();
+        live.wait().unwrap();
+    }
+
+    #[test]
+    fn test_someone_elses_file_is_not_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("watcher.pid");
+
+        let pid_file = PidFile::create(&path).unwrap();
+        std::fs::write(&path, "1\n").unwrap();
+        drop(pid_file);
+        assert_eq!(read_pid(&path), Some(1));
+    }
+}
@@ -0,0 +1,113 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`&str` in, values out**: Nothing here reads a file, a clock or the
+  environment, so the same code runs in the watcher, the one-shot commands
+  and the wasm32 build
+- **`anyhow::Context`**: The messages and error codes are the ones of the
+  file-based paths (`report::code` sees the same chain)
+
+**Design decisions**:
+- `parse` gives the document and the configuration it holds: the document
+  for checks on the raw JSON (external schema, file references), the
+  configuration for the business rules
+- `check` is a load short of what needs the file system: no size limit on
+  a path, no `*_file` references, no external schema; its findings are the
+  ones `validate` prints for the same contents
+- `effective` is the document with every default materialized, what `diff`
+  compares; `canonical` is its text as `--write-normalized` writes it
+
+******************************************************************************/
+
+use crate::config::AppConfig;
+use crate::error::ConfigError;
+use crate::redact;
+use crate::validation::ValidationReport;
+use anyhow::Context;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Parses `contents` into the document and the configuration it holds
+pub fn parse(contents: &str) -> anyhow::Result<(Value, AppConfig)> {
+    let doc: Value = serde_json::from_str(contents)
+        .map_err(ConfigError::from)
+        .context("Failed to parse JSON configuration")?;
+    let config = AppConfig::deserialize(&doc).context("Configuration does not match the schema")?;
+    Ok((doc, config))
+}
+
+/// Parses `contents` and runs the business rules
+///
+/// The report holds the warnings of a valid configuration; errors come
+/// back as `ConfigError::ValidationFailed`.
+pub fn check(contents: &str) -> anyhow::Result<(AppConfig, ValidationReport)> {
+    let (_, config) = parse(contents)?;
+    let report = config.check().into_result()?;
+    Ok((config, report))
+}
+
+/// Parses a document into its effective JSON form, defaults filled in
+pub fn effective(contents: &str) -> anyhow::Result<Value> {
+    let config: AppConfig =
+        serde_json::from_str(contents).context("Failed to parse JSON configuration")?;
+    serde_json::to_value(&config).context("Failed to serialize configuration")
+}
+
+/// The canonical text of `config`: pretty-printed in field order, flags
+/// sorted, trailing newline; secrets hidden if `redacted`
+pub fn canonical(config: &AppConfig, redacted: bool) -> serde_json::Result<String> {
+    let mut doc = serde_json::to_value(config)?;
+    if redacted {
+        redact::redact(&mut doc);
+    }
+    Ok(serde_json::to_string_pretty(&doc)? + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report;
+
+    #[test]
+    fn test_check_keeps_warnings_and_rejects_errors() {
+        let (config, report) = check(
+            r#"{ "app_name": "A", "version": "1.0.0", "server": { "host": "h", "port": 80, "max_connections": 200000 } }"#,
+        )
+        .unwrap();
+        assert_eq!(config.app_name, "A");
+        assert!(!report.has_errors());
+        assert!(
+            report
+                .warnings()
+                .any(|w| w.path == "server.max_connections")
+        );
+
+        let e = check(r#"{ "app_name": "", "version": "1.0.0" }"#).unwrap_err();
+        assert_eq!(report::code(&e), "validation_failed");
+        assert_eq!(report::findings(&e)[0].path, "app_name");
+    }
+
+    #[test]
+    fn test_parse_errors_keep_their_codes() {
+        let syntax = check(r#"{ "app_name": "#).unwrap_err();
+        assert_eq!(report::code(&syntax), "invalid_json");
+        let shape = check(r#"{ "app_name": 1, "version": "1.0.0" }"#).unwrap_err();
+        assert_eq!(report::code(&shape), "schema_mismatch");
+    }
+
+    #[test]
+    fn test_canonical_form() {
+        let config = AppConfig::example();
+        let text = canonical(&config, false).unwrap();
+        assert!(text.ends_with("}\n"));
+        assert_eq!(
+            effective(&text).unwrap(),
+            serde_json::to_value(&config).unwrap()
+        );
+        assert!(
+            !canonical(&config, true)
+                .unwrap()
+                .contains("postgres://localhost/mydb")
+        );
+    }
+}
@@ -0,0 +1,20 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **Glob imports**: `use config_watcher::prelude::*;` brings in the types
+  an embedding program names most often, and nothing else
+
+**Design decisions**:
+- Only types: functions stay behind their module path, where their name
+  reads well (`diff::diff`, `redact::redact`)
+- Everything here is also re-exported at the crate root
+
+******************************************************************************/
+
+pub use crate::cli::{EventKind, OutputFormat};
+pub use crate::config::{AppConfig, DatabaseConfig, ServerConfig};
+pub use crate::error::ConfigError;
+pub use crate::notify::{ConfigEvent, Notifier, Notifiers};
+pub use crate::output::{Emitter, Verbosity};
+pub use crate::validation::{Severity, ValidationReport};
+pub use crate::watcher::ConfigWatcher;
@@ -0,0 +1,250 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`AtomicU64` / `AtomicU8`**: Each watcher stores its last tick, state
+  and failure streak in its own atomics: recording them after a tick never
+  takes a lock, and neither does answering a probe
+- **`Arc<Mutex<Vec<...>>>`**: Only the list of watchers is behind a lock,
+  and it is only written at startup, when each watcher registers
+
+**Design decisions**:
+- Liveness is about the loop, not the file: a watcher that keeps ticking
+  is alive even if its file is broken. A watcher whose last tick is older
+  than `STALE_AFTER` intervals is stuck, and the process is not alive
+- Readiness is about the configuration: ready when every watcher serves a
+  valid one. A watcher still serving an older configuration after failed
+  reloads (degraded) stays ready until `max_failures` checks in a row have
+  failed; one that never loaded is never ready, unless it serves its
+  fallback configuration or a restored one
+- A watcher counts as having ticked when it registers, so a slow initial
+  load does not fail liveness before the first check is even due
+- Verdicts are pure functions of the recorded values and a clock, so a
+  stuck loop is testable without waiting for one
+
+******************************************************************************/
+
+use crate::output::WatchState;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Number of check intervals without a tick after which a loop is stuck
+pub const STALE_AFTER: u32 = 3;
+
+/// Failed checks in a row after which a degraded watcher is not ready
+pub const DEFAULT_MAX_FAILURES: u64 = 3;
+
+/// Probe state of every watcher of the process
+#[derive(Debug, Clone)]
+pub struct Probes {
+    interval: Duration,
+    max_failures: u64,
+    files: Arc<Mutex<Vec<FileProbe>>>,
+}
+
+/// Probe state of one watcher, updated after each of its ticks
+#[derive(Debug, Clone)]
+pub struct FileProbe(Arc<ProbeState>);
+
+#[derive(Debug)]
+struct ProbeState {
+    path: PathBuf,
+    /// Milliseconds since the Unix epoch
+    last_tick: AtomicU64,
+    state: AtomicU8,
+    /// Failed checks since the last successful load
+    failures: AtomicU64,
+}
+
+/// Why a watcher is not ready
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotReady {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl Probes {
+    /// Probes for watchers checking every `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            max_failures: DEFAULT_MAX_FAILURES,
+            files: Arc::default(),
+        }
+    }
+
+    /// Failed checks in a row after which a degraded watcher is not ready
+    pub fn with_max_failures(mut self, max_failures: u64) -> Self {
+        self.max_failures = max_failures;
+        self
+    }
+
+    /// Adds a watcher; it counts as having ticked now
+    pub fn register(&self, path: &Path) -> FileProbe {
+        let probe = FileProbe(Arc::new(ProbeState {
+            path: path.to_path_buf(),
+            last_tick: AtomicU64::new(millis(SystemTime::now())),
+            state: AtomicU8::new(encode(WatchState::Waiting)),
+            failures: AtomicU64::new(0),
+        }));
+        self.files
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(probe.clone());
+        probe
+    }
+
+    /// Time since the least recent tick of all watchers, as of `now`: `Ok`
+    /// while alive, `Err` once a loop is stuck
+    pub fn liveness(&self, now: SystemTime) -> Result<Duration, Duration> {
+        let oldest = self
+            .snapshot()
+            .iter()
+            .map(|probe| probe.0.last_tick.load(Ordering::Relaxed))
+            .min()
+            .unwrap_or_else(|| millis(now));
+        let age = Duration::from_millis(millis(now).saturating_sub(oldest));
+        if age > self.interval * STALE_AFTER {
+            Err(age)
+        } else {
+            Ok(age)
+        }
+    }
+
+    /// `Ok` when every watcher serves a valid configuration
+    pub fn readiness(&self) -> Result<(), Vec<NotReady>> {
+        let not_ready: Vec<NotReady> = self
+            .snapshot()
+            .iter()
+            .filter_map(|probe| {
+                let state = decode(probe.0.state.load(Ordering::Relaxed));
+                let failures = probe.0.failures.load(Ordering::Relaxed);
+                let reason = match state {
+                    WatchState::Valid | WatchState::Fallback | WatchState::Restored => {
+                        return None;
+                    }
+                    WatchState::Failing if failures < self.max_failures => return None,
+                    WatchState::Failing => {
+                        format!("failing: the last {failures} checks in a row failed")
+                    }
+                    WatchState::Waiting => "no valid configuration loaded yet".to_string(),
+                };
+                Some(NotReady {
+                    path: probe.0.path.clone(),
+                    reason,
+                })
+            })
+            .collect();
+        if not_ready.is_empty() {
+            Ok(())
+        } else {
+            Err(not_ready)
+        }
+    }
+
+    /// The registered watchers; the lock is only held to clone the list
+    fn snapshot(&self) -> Vec<FileProbe> {
+        self.files.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl FileProbe {
+    /// Records a tick at `now` that left the watcher in `state`, after
+    /// `failures` failed checks in a row
+    pub fn record(&self, now: SystemTime, state: WatchState, failures: u64) {
+        self.0.state.store(encode(state), Ordering::Relaxed);
+        self.0.failures.store(failures, Ordering::Relaxed);
+        self.0.last_tick.store(millis(now), Ordering::Relaxed);
+    }
+}
+
+fn millis(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64)
+}
+
+fn encode(state: WatchState) -> u8 {
+    match state {
+        WatchState::Waiting => 0,
+        WatchState::Valid => 1,
+        WatchState::Failing => 2,
+        WatchState::Fallback => 3,
+        WatchState::Restored => 4,
+    }
+}
+
+fn decode(value: u8) -> WatchState {
+    match value {
+        1 => WatchState::Valid,
+        2 => WatchState::Failing,
+        3 => WatchState::Fallback,
+        4 => WatchState::Restored,
+        _ => WatchState::Waiting,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readiness_across_states() {
+        let probes = Probes::new(Duration::from_secs(1));
+        let (a, b) = (
+            probes.register(Path::new("a.json")),
+            probes.register(Path::new("b.json")),
+        );
+        let now = SystemTime::now();
+
+        // Never loaded
+        let not_ready = probes.readiness().unwrap_err();
+        assert_eq!(not_ready.len(), 2);
+        assert_eq!(not_ready[0].reason, "no valid configuration loaded yet");
+
+        a.record(now, WatchState::Valid, 0);
+        b.record(now, WatchState::Valid, 0);
+        assert_eq!(probes.readiness(), Ok(()));
+
+        // Degraded, but still serving: ready until the threshold
+        b.record(now, WatchState::Failing, 2);
+        assert_eq!(probes.readiness(), Ok(()));
+        b.record(now, WatchState::Failing, 3);
+        let not_ready = probes.readiness().unwrap_err();
+        assert_eq!(not_ready[0].path, Path::new("b.json"));
+        assert!(
+            not_ready[0].reason.contains("last 3 checks"),
+            "{not_ready:?}"
+        );
+
+        let lenient = probes.clone().with_max_failures(10);
+        assert_eq!(lenient.readiness(), Ok(()));
+
+        // The fallback and a restored configuration are valid too
+        b.record(now, WatchState::Fallback, 0);
+        assert_eq!(probes.readiness(), Ok(()));
+        b.record(now, WatchState::Restored, 0);
+        assert_eq!(probes.readiness(), Ok(()));
+    }
+
+    #[test]
+    fn test_stuck_loop_fails_liveness() {
+        let probes = Probes::new(Duration::from_secs(2));
+        let start = SystemTime::now();
+        let (a, b) = (
+            probes.register(Path::new("a.json")),
+            probes.register(Path::new("b.json")),
+        );
+        let at = |secs| start + Duration::from_secs(secs);
+
+        a.record(at(4), WatchState::Valid, 0);
+        b.record(at(4), WatchState::Failing, 1);
+        assert!(probes.liveness(at(9)).is_ok());
+
+        // b keeps ticking, a is stuck: the oldest tick decides
+        b.record(at(10), WatchState::Failing, 2);
+        assert_eq!(probes.liveness(at(11)), Err(Duration::from_secs(7)));
+        a.record(at(11), WatchState::Valid, 0);
+        assert_eq!(probes.liveness(at(11)), Ok(Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,223 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **Enums with data**: `Layer` names the file or the variable a value came
+  from
+- **Recursion over `serde_json::Value`**: The traced fields are the leaves
+  of the effective document
+
+**Design decisions**:
+- Layers, lowest first: schema default, file, environment override, command
+  line override. The chain of a field lists what every layer contributed;
+  the last entry wins
+- Provenance is derived after the fact from the raw document and the
+  overrides, so the normal load path records nothing: the watcher only
+  traces when the table is actually printed (`-vv`)
+- Values are redacted when displayed, never when traced
+
+******************************************************************************/
+
+use crate::overrides::{Overrides, Source};
+use crate::path::{FieldPath, Segment};
+use crate::redact::{REDACTED, is_secret};
+use crate::schema;
+use serde_json::Value;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A configuration layer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layer {
+    /// Built-in default
+    Default,
+    /// The configuration file
+    File(PathBuf),
+    /// An environment override, by variable name
+    EnvVar(String),
+    /// `--override` or `--override-string`
+    CliOverride,
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layer::Default => write!(f, "default"),
+            Layer::File(path) => write!(f, "file {}", path.display()),
+            Layer::EnvVar(name) => write!(f, "environment {name}"),
+            Layer::CliOverride => write!(f, "command line"),
+        }
+    }
+}
+
+/// The value one layer gives a field
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contribution {
+    pub layer: Layer,
+    pub value: Value,
+}
+
+/// Contributions to every field of a configuration
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Provenance {
+    fields: Vec<(FieldPath, Vec<Contribution>)>,
+}
+
+impl Provenance {
+    /// Traces every field of `effective`
+    ///
+    /// `raw` is the document read from `file`, before overrides.
+    pub fn trace(file: &Path, raw: &Value, overrides: &Overrides, effective: &Value) -> Self {
+        let fields = leaves(&FieldPath::root(), effective)
+            .into_iter()
+            .map(|(path, value)| {
+                let mut chain = Vec::new();
+                let default = schema::lookup(&path)
+                    .and_then(|info| info.default)
+                    .and_then(|text| serde_json::from_str(text).ok());
+                if let Some(value) = default {
+                    chain.push(Contribution {
+                        layer: Layer::Default,
+                        value,
+                    });
+                }
+                if let Ok(value) = path.resolve(raw) {
+                    chain.push(Contribution {
+                        layer: Layer::File(file.to_path_buf()),
+                        value: value.clone(),
+                    });
+                }
+                for entry in overrides.entries().iter().filter(|e| e.path == path) {
+                    let layer = match entry.source {
+                        Source::Env(ref name) => Layer::EnvVar(name.clone()),
+                        Source::CommandLine => Layer::CliOverride,
+                    };
+                    chain.push(Contribution {
+                        layer,
+                        value: entry.value.clone(),
+                    });
+                }
+                // A serde default the table does not spell out
+                if chain.is_empty() {
+                    chain.push(Contribution {
+                        layer: Layer::Default,
+                        value,
+                    });
+                }
+                (path, chain)
+            })
+            .collect();
+        Self { fields }
+    }
+
+    /// Contributions to `path`, lowest layer first
+    pub fn chain(&self, path: &FieldPath) -> Option<&[Contribution]> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == path)
+            .map(|(_, chain)| chain.as_slice())
+    }
+
+    /// The contribution that wins for `path`
+    #[cfg(test)]
+    pub fn winner(&self, path: &FieldPath) -> Option<&Contribution> {
+        self.chain(path).and_then(<[Contribution]>::last)
+    }
+
+    /// Every traced field with its chain, in document order
+    pub fn fields(&self) -> &[(FieldPath, Vec<Contribution>)] {
+        &self.fields
+    }
+
+    /// One line per field, `path = value  (layer)`, secrets redacted
+    pub fn table(&self) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter_map(|(path, chain)| {
+                let winner = chain.last()?;
+                Some(format!(
+                    "{path} = {}  ({})",
+                    shown(path, &winner.value),
+                    winner.layer
+                ))
+            })
+            .collect()
+    }
+}
+
+/// A value as it may be printed
+pub fn shown(path: &FieldPath, value: &Value) -> Value {
+    if is_secret(path) && !value.is_null() {
+        Value::String(REDACTED.to_string())
+    } else {
+        value.clone()
+    }
+}
+
+/// Scalars, arrays and empty objects below `path`, in document order
+pub fn leaves(path: &FieldPath, value: &Value) -> Vec<(FieldPath, Value)> {
+    match value {
+        Value::Object(map) if !map.is_empty() => map
+            .iter()
+            .flat_map(|(key, child)| leaves(&path.child(Segment::Key(key.clone())), child))
+            .collect(),
+        _ => vec![(path.clone(), value.clone())],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_all_four_layers_on_one_field() {
+        let raw = json!({ "app_name": "A", "version": "1", "environment": "staging" });
+        let mut overrides = Overrides::from_env([(
+            "CW_OVERRIDE__ENVIRONMENT".to_string(),
+            "production".to_string(),
+        )])
+        .unwrap();
+        overrides
+            .extend(Overrides::from_args(&["environment=development".to_string()], &[]).unwrap());
+        let effective = json!({ "app_name": "A", "version": "1", "environment": "development" });
+
+        let provenance = Provenance::trace(Path::new("app.json"), &raw, &overrides, &effective);
+        let path = FieldPath::parse("environment").unwrap();
+        let chain: Vec<_> = provenance
+            .chain(&path)
+            .unwrap()
+            .iter()
+            .map(|c| (c.layer.clone(), c.value.clone()))
+            .collect();
+        assert_eq!(
+            chain,
+            [
+                (Layer::Default, json!("development")),
+                (Layer::File(PathBuf::from("app.json")), json!("staging")),
+                (
+                    Layer::EnvVar("CW_OVERRIDE__ENVIRONMENT".to_string()),
+                    json!("production")
+                ),
+                (Layer::CliOverride, json!("development")),
+            ]
+        );
+        assert_eq!(provenance.winner(&path).unwrap().layer, Layer::CliOverride);
+
+        let app_name = FieldPath::parse("app_name").unwrap();
+        assert_eq!(
+            provenance.winner(&app_name).unwrap().layer,
+            Layer::File(PathBuf::from("app.json"))
+        );
+    }
+
+    #[test]
+    fn test_table_redacts_secrets() {
+        let raw = json!({ "database": { "connection_string": "postgres://u:p@h/db" } });
+        let provenance =
+            Provenance::trace(Path::new("app.json"), &raw, &Overrides::default(), &raw);
+        assert_eq!(
+            provenance.table(),
+            [r#"database.connection_string = "<redacted>"  (file app.json)"#]
+        );
+    }
+}
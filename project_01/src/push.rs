@@ -0,0 +1,400 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`tokio::net::UnixListener`**: Co-located processes connect to a socket
+  file instead of speaking HTTP
+- **`tokio::sync::mpsc::channel` + `try_send`**: Each client has a bounded
+  queue; publishing never waits, and a full queue means a client that
+  stopped reading
+- **`AbortHandle`**: Evicting a client aborts its task, even while it is
+  stuck writing to a socket nobody reads
+- **`impl Notifier`**: Updates come from the dispatcher, like webhooks, so
+  the watch loop never waits for a client
+
+**Design decisions**:
+- Frames are a 4-byte big-endian length, then one JSON object:
+  `{"file": ..., "version": N, "config": {...}}`
+- A client gets the latest frame of every file on connect, then one frame
+  per load that changes a configuration. The snapshot is queued under the
+  lock that publishing takes, so no update is missed or sent twice
+- The configuration is complete: the socket's permissions (`--push-socket-mode`,
+  660 by default) decide who reads it. `--push-redact` hides secrets anyway
+- The socket is bound in a private (0700) directory next to its path, gets
+  its mode there, then is renamed into place: it is never reachable with
+  the permissions the umask would give it
+- A client whose queue (`CLIENT_QUEUE` frames) is full is disconnected
+  with a warning; it can reconnect and start from the latest state
+- Clients may send lines: `RELOAD` asks every watcher to reload now, with
+  `--push-allow-reload`; anything else is ignored
+- A socket file left by a dead process is replaced; one that still
+  accepts connections is an error. The file is removed when the
+  `PushSocket` is dropped, i.e. when the watch ends
+
+******************************************************************************/
+
+use crate::cli::EventKind;
+use crate::notify::{ConfigEvent, Notifier};
+use crate::watcher::ReloadRequests;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::io;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::{AbortHandle, JoinHandle};
+use tracing::{debug, warn};
+
+/// Frames waiting for one client; one more disconnects it
+pub const CLIENT_QUEUE: usize = 16;
+
+/// Permissions of the socket file
+pub const DEFAULT_MODE: u32 = 0o660;
+
+/// How the socket is served
+#[derive(Debug, Clone)]
+pub struct PushOptions {
+    /// Permissions of the socket file
+    pub mode: u32,
+    /// Hide secrets in the frames
+    pub redact: bool,
+    /// Where `RELOAD` requests go; ignored without
+    pub reload: Option<ReloadRequests>,
+}
+
+impl Default for PushOptions {
+    fn default() -> Self {
+        Self {
+            mode: DEFAULT_MODE,
+            redact: false,
+            reload: None,
+        }
+    }
+}
+
+/// The listening socket; stops and removes its file when dropped
+#[derive(Debug)]
+pub struct PushSocket {
+    path: PathBuf,
+    hub: Hub,
+    redact: bool,
+    task: JoinHandle<()>,
+}
+
+/// The latest frames and the connected clients
+#[derive(Debug, Clone, Default)]
+struct Hub(Arc<Mutex<HubState>>);
+
+#[derive(Debug, Default)]
+struct HubState {
+    latest: BTreeMap<PathBuf, Arc<[u8]>>,
+    clients: Vec<Client>,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+struct Client {
+    id: u64,
+    queue: mpsc::Sender<Arc<[u8]>>,
+    task: AbortHandle,
+}
+
+/// Pushes loads to the clients of a `PushSocket`
+#[derive(Debug)]
+pub struct PushNotifier {
+    hub: Hub,
+    redact: bool,
+}
+
+/// Listens on `path` until the returned `PushSocket` is dropped
+pub async fn serve(path: &Path, options: PushOptions) -> io::Result<PushSocket> {
+    remove_stale(path).await?;
+    let listener = bind(path, options.mode)?;
+    let hub = Hub::default();
+    let redact = options.redact;
+    let task = {
+        let hub = hub.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                hub.connect(stream, options.reload.clone());
+            }
+        })
+    };
+    Ok(PushSocket {
+        path: path.to_path_buf(),
+        hub,
+        redact,
+        task,
+    })
+}
+
+/// Listens on `path`, with permissions `mode` from the moment it exists there
+fn bind(path: &Path, mode: u32) -> io::Result<UnixListener> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    // Created 0700: nobody else can reach the socket before its chmod
+    let private = tempfile::Builder::new()
+        .prefix(".push-")
+        .tempdir_in(parent)?;
+    let staged = private.path().join("socket");
+    let listener = UnixListener::bind(&staged)?;
+    std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(mode))?;
+    std::fs::rename(&staged, path)?;
+    Ok(listener)
+}
+
+/// Removes a socket file nobody listens on anymore
+async fn remove_stale(path: &Path) -> io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            if UnixStream::connect(path).await.is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    "another process is listening on this socket",
+                ));
+            }
+            std::fs::remove_file(path)
+        }
+        // Let bind report what is in the way
+        _ => Ok(()),
+    }
+}
+
+impl PushSocket {
+    /// The notifier feeding this socket's clients
+    pub fn notifier(&self) -> PushNotifier {
+        PushNotifier {
+            hub: self.hub.clone(),
+            redact: self.redact,
+        }
+    }
+
+    /// Number of connected clients
+    #[cfg(test)]
+    pub fn clients(&self) -> usize {
+        self.hub.lock().clients.len()
+    }
+}
+
+impl Drop for PushSocket {
+    fn drop(&mut self) {
+        self.task.abort();
+        for client in self.hub.lock().clients.drain(..) {
+            client.task.abort();
+        }
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!(path = %self.path.display(), error = %e, "push socket not removed");
+        }
+    }
+}
+
+impl Hub {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HubState> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queues the latest frames for a new client, then serves it
+    fn connect(&self, stream: UnixStream, reload: Option<ReloadRequests>) {
+        let mut state = self.lock();
+        let (queue, frames) = mpsc::channel(CLIENT_QUEUE + state.latest.len());
+        for frame in state.latest.values() {
+            // Room was made for them
+            let _ = queue.try_send(frame.clone());
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        let hub = self.clone();
+        let task = tokio::spawn(async move {
+            serve_client(stream, frames, reload).await;
+            hub.lock().clients.retain(|client| client.id != id);
+            debug!(client = id, "push client gone");
+        });
+        debug!(client = id, "push client connected");
+        state.clients.push(Client {
+            id,
+            queue,
+            task: task.abort_handle(),
+        });
+    }
+
+    /// Sends `frame` to every client and keeps it for the next ones
+    fn publish(&self, file: &Path, frame: Arc<[u8]>) {
+        let mut state = self.lock();
+        state.latest.insert(file.to_path_buf(), frame.clone());
+        state
+            .clients
+            .retain(|client| match client.queue.try_send(frame.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    warn!(
+                        client = client.id,
+                        queue = CLIENT_QUEUE,
+                        "push client not reading, disconnected"
+                    );
+                    client.task.abort();
+                    false
+                }
+                Err(TrySendError::Closed(_)) => false,
+            });
+    }
+}
+
+/// Writes the queued frames to the client and reads its requests, until
+/// either side is done
+async fn serve_client(
+    stream: UnixStream,
+    mut frames: mpsc::Receiver<Arc<[u8]>>,
+    reload: Option<ReloadRequests>,
+) {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    // A client may close its side and keep listening
+    let mut reading = true;
+    loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Some(frame) if write.write_all(&frame).await.is_ok() => {}
+                _ => return,
+            },
+            line = lines.next_line(), if reading => match line {
+                Ok(Some(line)) => request(line.trim(), reload.as_ref()),
+                _ => reading = false,
+            },
+        }
+    }
+}
+
+/// Acts on one line sent by a client
+fn request(line: &str, reload: Option<&ReloadRequests>) {
+    match (line, reload) {
+        ("RELOAD", Some(reload)) => reload.request("push socket"),
+        ("RELOAD", None) => debug!("RELOAD ignored without --push-allow-reload"),
+        _ => debug!(request = line, "unknown push socket request ignored"),
+    }
+}
+
+/// A length-prefixed frame holding `value`
+pub fn frame(value: &serde_json::Value) -> Arc<[u8]> {
+    let json = value.to_string();
+    let mut frame = Vec::with_capacity(4 + json.len());
+    frame.extend_from_slice(&(json.len() as u32).to_be_bytes());
+    frame.extend_from_slice(json.as_bytes());
+    frame.into()
+}
+
+impl Notifier for PushNotifier {
+    fn name(&self) -> &str {
+        "push-socket"
+    }
+
+    /// Loads, initial or not, that changed the configuration
+    fn accepts(&self, event: &ConfigEvent) -> bool {
+        matches!(event.kind, EventKind::Initial | EventKind::Reload) && event.config.is_some()
+    }
+
+    async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+        let (Some(file), Some(config)) = (&event.file, &event.config) else {
+            return Ok(());
+        };
+        let mut config = serde_json::to_value(config)?;
+        if self.redact {
+            crate::redact::redact(&mut config);
+        }
+        let frame = frame(&json!({
+            "file": file.display().to_string(),
+            "version": event.version(),
+            "config": config,
+        }));
+        self.hub.publish(file, frame);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    /// Reads one frame
+    async fn read_frame(stream: &mut UnixStream) -> serde_json::Value {
+        let len = stream.read_u32().await.unwrap();
+        let mut json = vec![0; len as usize];
+        stream.read_exact(&mut json).await.unwrap();
+        serde_json::from_slice(&json).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_latest_frame_on_connect_then_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("push.sock");
+        let socket = serve(&path, PushOptions::default()).await.unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o660);
+        // The private directory it was bound in is gone
+        let entries: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, ["push.sock"]);
+
+        socket
+            .hub
+            .publish(Path::new("a.json"), frame(&json!({ "v": 1 })));
+        socket
+            .hub
+            .publish(Path::new("a.json"), frame(&json!({ "v": 2 })));
+        let mut client = UnixStream::connect(&path).await.unwrap();
+        // Only the latest
+        assert_eq!(read_frame(&mut client).await, json!({ "v": 2 }));
+
+        socket
+            .hub
+            .publish(Path::new("a.json"), frame(&json!({ "v": 3 })));
+        assert_eq!(read_frame(&mut client).await, json!({ "v": 3 }));
+
+        drop(socket);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_client_that_stops_reading_is_evicted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("push.sock");
+        let socket = serve(&path, PushOptions::default()).await.unwrap();
+        let mut slow = UnixStream::connect(&path).await.unwrap();
+        let mut fast = UnixStream::connect(&path).await.unwrap();
+        while socket.clients() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        // Far more than the socket buffer and the queue hold
+        let big = frame(&json!({ "padding": "x".repeat(64 * 1024) }));
+        for _ in 0..CLIENT_QUEUE * 16 {
+            socket.hub.publish(Path::new("a.json"), big.clone());
+            read_frame(&mut fast).await;
+        }
+        assert_eq!(socket.clients(), 1);
+
+        // The slow one reads what was written, then the end of the stream
+        let mut rest = Vec::new();
+        slow.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.len() < big.len() * CLIENT_QUEUE * 16);
+    }
+
+    #[tokio::test]
+    async fn test_stale_socket_is_replaced_live_one_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("push.sock");
+        // Left behind by a dead process
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        let socket = serve(&path, PushOptions::default()).await.unwrap();
+
+        let error = serve(&path, PushOptions::default()).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+        drop(socket);
+    }
+}
@@ -0,0 +1,223 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`anyhow::Error::chain` + `downcast_ref`**: Finds the typed error behind
+  layers of context
+- **`serde_json::error::Category`**: Tells syntax errors from shape errors
+- **`#[derive(Serialize)]`**: The report is printed with `serde_json`
+
+**Design decisions**:
+- Error codes come from `ConfigError::code()`; JSON errors that were only
+  wrapped in context get `invalid_json` (syntax) or `schema_mismatch` (valid
+  JSON of the wrong shape)
+- A parse error becomes a single finding carrying serde's line/column; a
+  validation failure contributes all of its findings, warnings included
+- `CODES` is the catalogue of every code, used as the SARIF rule list;
+  `validation_warning` only appears on individual warnings
+- The shape is fixed (`code`, `file`, `message`, `findings`) so CI wrappers
+  do not need to handle optional keys
+
+******************************************************************************/
+
+use crate::error::ConfigError;
+use crate::path::FieldPath;
+use crate::validation::{Finding, Severity};
+use serde::Serialize;
+use serde_json::error::Category;
+use std::path::Path;
+
+/// Every code a report or finding can carry, with a one-line description
+pub const CODES: &[(&str, &str)] = &[
+    ("file_not_found", "The configuration file does not exist"),
+    ("metadata_error", "The file metadata could not be read"),
+    ("invalid_json", "The file is not valid JSON"),
+    (
+        "schema_mismatch",
+        "Valid JSON that does not match the configuration structure",
+    ),
+    (
+        "validation_failed",
+        "A business rule or the external schema rejected a value",
+    ),
+    (
+        "validation_warning",
+        "A business rule flagged a suspicious value",
+    ),
+    ("read_error", "The file could not be read"),
+    ("write_error", "The file could not be written"),
+    ("invalid_schema", "The external JSON Schema is invalid"),
+    ("invalid_path", "A field path is malformed"),
+    ("path_not_found", "A field path does not exist"),
+    ("reload_failed", "A reload failed"),
+    ("no_config_found", "No configuration file was found"),
+    ("ambiguous_config", "Several configuration files were found"),
+    ("too_large", "The configuration exceeds the size limit"),
+    ("empty_input", "No configuration was received on stdin"),
+    (
+        "unsupported_format",
+        "The file extension names an unsupported format",
+    ),
+    (
+        "patch_failed",
+        "A JSON Patch operation could not be applied",
+    ),
+    (
+        "checksum_mismatch",
+        "The file does not match its .sha256 sidecar",
+    ),
+    (
+        "checksum_unavailable",
+        "The .sha256 sidecar is missing or garbled",
+    ),
+    (
+        "signature_missing",
+        "No detached signature next to the file",
+    ),
+    (
+        "signature_invalid",
+        "The detached signature does not verify",
+    ),
+    ("invalid_public_key", "The public key file is invalid"),
+    (
+        "decryption_failed",
+        "An age: value cannot be decrypted with the identities",
+    ),
+    ("invalid_identity", "The age identity file is invalid"),
+    (
+        "already_running",
+        "Another process already watches the file",
+    ),
+    (
+        "pid_file_in_use",
+        "The pid file names a process that is still running",
+    ),
+    (
+        "outside_sandbox",
+        "The file resolves outside the --sandbox paths",
+    ),
+    (
+        "invalid_usage",
+        "Invalid flags, environment variables or settings file",
+    ),
+    ("other", "Any other error"),
+];
+
+/// A load failure in machine-readable form
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorReport {
+    /// Stable identifier, see [`code`]
+    pub code: &'static str,
+    /// File that failed to load
+    pub file: String,
+    /// The whole error chain on one line
+    pub message: String,
+    /// Individual problems, with positions when known
+    pub findings: Vec<Finding>,
+}
+
+impl ErrorReport {
+    /// Builds the report for `error`, raised while loading `file`
+    pub fn new(file: &Path, error: &anyhow::Error) -> Self {
+        Self {
+            code: code(error),
+            file: file.display().to_string(),
+            message: format!("{error:#}"),
+            findings: findings(error),
+        }
+    }
+
+    /// The report as a single JSON line
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Stable code of the first recognized error in the chain
+pub fn code(error: &anyhow::Error) -> &'static str {
+    for cause in error.chain() {
+        if let Some(e) = cause.downcast_ref::<ConfigError>() {
+            return e.code();
+        }
+        if let Some(e) = cause.downcast_ref::<serde_json::Error>() {
+            return match e.classify() {
+                Category::Data => "schema_mismatch",
+                _ => "invalid_json",
+            };
+        }
+    }
+    "other"
+}
+
+/// Findings carried by the error, if any
+pub fn findings(error: &anyhow::Error) -> Vec<Finding> {
+    for cause in error.chain() {
+        if let Some(ConfigError::ValidationFailed { report }) = cause.downcast_ref() {
+            return report.findings().to_vec();
+        }
+        if let Some(e) = cause.downcast_ref::<serde_json::Error>() {
+            // serde reports line 0 when it has no position (from_value)
+            let known = |n: usize| (e.line() > 0).then_some(n);
+            return vec![Finding {
+                severity: Severity::Error,
+                path: FieldPath::root().to_string(),
+                message: e.to_string(),
+                line: known(e.line()),
+                column: known(e.column()),
+            }];
+        }
+    }
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::ValidationReport;
+    use anyhow::Context;
+
+    #[test]
+    fn test_parse_error_has_a_position() {
+        let error = serde_json::from_str::<serde_json::Value>("{\n  \"a\": }")
+            .context("Failed to parse JSON configuration")
+            .unwrap_err();
+        let report = ErrorReport::new(Path::new("app.json"), &error);
+
+        assert_eq!(report.code, "invalid_json");
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].line, Some(2));
+        assert_eq!(report.findings[0].column, Some(8));
+    }
+
+    #[test]
+    fn test_codes() {
+        let missing = anyhow::Error::from(ConfigError::FileNotFound { path: "x".into() });
+        assert_eq!(code(&missing), "file_not_found");
+
+        let shape = serde_json::from_str::<crate::config::AppConfig>("{}").unwrap_err();
+        assert_eq!(code(&shape.into()), "schema_mismatch");
+
+        let mut report = ValidationReport::new();
+        report.error("app_name", "cannot be empty");
+        report.warning("server.port", "is privileged");
+        let error = anyhow::Error::from(report.into_result().unwrap_err()).context("outer");
+        assert_eq!(code(&error), "validation_failed");
+        assert_eq!(findings(&error).len(), 2);
+        assert_eq!(code(&anyhow::anyhow!("boom")), "other");
+    }
+
+    #[test]
+    fn test_codes_are_catalogued() {
+        let errors = [
+            ConfigError::FileNotFound { path: "x".into() },
+            ConfigError::NoConfigFound { tried: vec![] },
+            ConfigError::InvalidPath {
+                path: "a..b".into(),
+                reason: "empty segment".into(),
+            },
+        ];
+        for error in errors {
+            let code = error.code();
+            assert!(CODES.iter().any(|(id, _)| *id == code), "{code}");
+        }
+    }
+}
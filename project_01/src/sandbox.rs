@@ -0,0 +1,500 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`unsafe` system calls**: Landlock has no wrapper in `std` or `rustix`,
+  so its three calls go through `libc::syscall`, each with the reason it
+  is sound; the rest of the crate stays safe code
+- **`#[repr(C)]` / `#[repr(C, packed)]`**: The rule structures have the
+  exact layout the kernel reads
+- **`OnceLock`**: The enforced sandbox is process-wide, like the Landlock
+  domain itself, so later reads can tell why they would fail
+
+**Design decisions**:
+- `--sandbox` (Linux) restricts the watcher to the paths it needs, known
+  before it starts: the directory of each watched file (its companions
+  live there), the schema, identity, key and settings files, the name
+  resolution files of `/etc`, and write access to the directories of the
+  status, audit, log, export, normalized, lock and push socket files. The
+  watched directories are writable with `--http-allow-write` only
+- Landlock restricts the calling thread and the threads it starts later,
+  so the sandbox goes up in `main`, before the runtime starts its workers
+- Executing programs is refused: options that run commands (`--on-change`,
+  `--event-db`, `--git-autocommit`, `--notify-desktop`, `run`) conflict
+  with `--sandbox`, and the local UTC offset is read before it goes up
+- Every right the running kernel knows is handled (best effort over the
+  Landlock ABI versions); a kernel without Landlock, or another OS, runs
+  unconfined with a warning
+- A file that later resolves outside the sandbox (the watched file
+  replaced by a symlink elsewhere) is refused by `check_read` with an
+  error saying so, instead of the kernel's bare `EACCES`
+
+******************************************************************************/
+
+use crate::error::{ConfigError, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Files read to resolve host names, allowed when they exist
+const NAME_RESOLUTION: &[&str] = &[
+    "/etc/hosts",
+    "/etc/resolv.conf",
+    "/etc/nsswitch.conf",
+    "/etc/host.conf",
+    "/etc/gai.conf",
+];
+
+/// The sandbox in force, once enforced
+static ACTIVE: OnceLock<Sandbox> = OnceLock::new();
+
+/// What the watcher may do below a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    Read,
+    /// Read, create, replace and remove files
+    Write,
+}
+
+/// The paths the watcher may use, and how
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sandbox {
+    paths: BTreeMap<PathBuf, Access>,
+}
+
+/// Whether the sandbox could be enforced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Enforcement {
+    /// The process is confined; `abi` is the kernel's Landlock version
+    Enforced { abi: u32 },
+    /// The process runs unconfined, for `reason`
+    Unsupported { reason: String },
+}
+
+impl Sandbox {
+    /// An empty sandbox: nothing allowed
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows `access` below `path`, canonicalized; a path that does not
+    /// exist yet stands for its directory
+    pub fn allow(&mut self, path: &Path, access: Access) {
+        let path = path
+            .canonicalize()
+            .unwrap_or_else(|_| resolve(&directory(path)));
+        let entry = self.paths.entry(path).or_insert(access);
+        *entry = (*entry).max(access);
+    }
+
+    /// Every allowed path, with its access
+    #[cfg(test)]
+    pub fn paths(&self) -> impl Iterator<Item = (&Path, Access)> {
+        self.paths
+            .iter()
+            .map(|(path, access)| (path.as_path(), *access))
+    }
+
+    /// Whether `access` to `path` (canonicalized) is allowed
+    pub fn allows(&self, path: &Path, access: Access) -> bool {
+        self.paths
+            .iter()
+            .any(|(allowed, granted)| path.starts_with(allowed) && *granted >= access)
+    }
+
+    /// Confines this thread and the threads it starts from now on, and
+    /// remembers the sandbox for [`check_read`]
+    pub fn enforce(self) -> std::io::Result<Enforcement> {
+        let enforcement = landlock::restrict(&self.paths)?;
+        if matches!(enforcement, Enforcement::Enforced { .. }) {
+            // Only set once: `--sandbox` is enforced once, from `main`
+            let _ = ACTIVE.set(self);
+        }
+        Ok(enforcement)
+    }
+}
+
+/// The sandbox of the watch options: the files to watch, the settings and
+/// log files, and every path given by a flag
+pub fn plan(
+    args: &crate::cli::WatchArgs,
+    files: &[PathBuf],
+    settings: Option<&Path>,
+    log_file: Option<&Path>,
+) -> Sandbox {
+    let mut sandbox = Sandbox::new();
+    let watched = if args.http_allow_write || args.promote_next {
+        Access::Write
+    } else {
+        Access::Read
+    };
+    for file in files {
+        sandbox.allow(&directory(file), watched);
+    }
+    if let Some(ref fallback) = args.fallback_config {
+        sandbox.allow(&directory(fallback), Access::Read);
+    }
+    let read = [args.schema.as_ref(), args.public_key.as_ref()]
+        .into_iter()
+        .flatten()
+        .chain(&args.age_identity)
+        .chain(&args.slack_template)
+        .chain(&args.signal_pidfile);
+    for path in read {
+        sandbox.allow(path, Access::Read);
+    }
+    if let Some(settings) = settings {
+        sandbox.allow(settings, Access::Read);
+    }
+    for path in NAME_RESOLUTION
+        .iter()
+        .map(Path::new)
+        .filter(|path| path.exists())
+    {
+        sandbox.allow(path, Access::Read);
+    }
+
+    let written = [
+        args.status_file.as_deref(),
+        args.audit_log.as_deref(),
+        args.export_env.as_deref(),
+        args.write_normalized.as_deref(),
+        args.push_socket.as_deref(),
+        args.pid_file.as_deref(),
+    ];
+    for path in written.into_iter().flatten().chain(log_file) {
+        sandbox.allow(&directory(path), Access::Write);
+    }
+    if let Some(ref dir) = args.state_dir {
+        sandbox.allow(dir, Access::Write);
+    }
+    if !args.no_lock && !args.once {
+        // Without one, `watch` fails before needing the sandbox
+        let locks = match args.lock_dir {
+            Some(ref dir) => Some(dir.clone()),
+            None => crate::instance_lock::default_dir(args.state_dir.as_deref()).ok(),
+        };
+        if let Some(ref dir) = locks {
+            sandbox.allow(dir, Access::Write);
+        }
+    }
+    sandbox
+}
+
+/// Fails with `ConfigError::OutsideSandbox` when `path` resolves outside
+/// the enforced sandbox; always Ok without one
+pub fn check_read(path: &Path) -> Result<()> {
+    let Some(sandbox) = ACTIVE.get() else {
+        return Ok(());
+    };
+    let resolved = resolve(path);
+    if sandbox.allows(&resolved, Access::Read) {
+        return Ok(());
+    }
+    Err(ConfigError::OutsideSandbox {
+        path: path.to_path_buf(),
+        resolved,
+    })
+}
+
+/// The directory `path` is in
+fn directory(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// `path` canonicalized, or made absolute when it does not exist
+fn resolve(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(target_os = "linux")]
+mod landlock {
+    use super::{Access, Enforcement};
+    use std::collections::BTreeMap;
+    use std::fs::{File, OpenOptions};
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::path::PathBuf;
+
+    /// `landlock_create_ruleset` flag asking for the ABI version
+    const CREATE_RULESET_VERSION: libc::c_uint = 1;
+    /// `landlock_add_rule` type of `PathBeneath`
+    const RULE_PATH_BENEATH: libc::c_int = 1;
+
+    // Filesystem rights (LANDLOCK_ACCESS_FS_*)
+    const EXECUTE: u64 = 1 << 0;
+    const WRITE_FILE: u64 = 1 << 1;
+    const READ_FILE: u64 = 1 << 2;
+    const READ_DIR: u64 = 1 << 3;
+    const REMOVE_FILE: u64 = 1 << 5;
+    const MAKE_REG: u64 = 1 << 8;
+    const MAKE_SOCK: u64 = 1 << 9;
+    /// ABI 2
+    const REFER: u64 = 1 << 13;
+    /// ABI 3
+    const TRUNCATE: u64 = 1 << 14;
+    /// ABI 5
+    const IOCTL_DEV: u64 = 1 << 15;
+    /// The rights a rule on a file (not a directory) may grant
+    const FILE_RIGHTS: u64 = EXECUTE | WRITE_FILE | READ_FILE | TRUNCATE | IOCTL_DEV;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneath {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    /// The Landlock ABI version of the kernel; None without Landlock
+    fn abi() -> Option<u32> {
+        // SAFETY: with a null attribute, a zero size and the VERSION flag
+        // the kernel reads no memory and only returns the version
+        let version = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                CREATE_RULESET_VERSION,
+            )
+        };
+        u32::try_from(version).ok().filter(|version| *version > 0)
+    }
+
+    /// Every right a kernel of `abi` knows: handling them all denies
+    /// whatever no rule grants
+    fn handled(abi: u32) -> u64 {
+        let mut rights = (1 << 13) - 1;
+        for (version, right) in [(2, REFER), (3, TRUNCATE), (5, IOCTL_DEV)] {
+            if abi >= version {
+                rights |= right;
+            }
+        }
+        rights
+    }
+
+    fn rights(access: Access) -> u64 {
+        match access {
+            Access::Read => READ_FILE | READ_DIR,
+            Access::Write => {
+                READ_FILE
+                    | READ_DIR
+                    | WRITE_FILE
+                    | REMOVE_FILE
+                    | MAKE_REG
+                    | MAKE_SOCK
+                    | REFER
+                    | TRUNCATE
+            }
+        }
+    }
+
+    fn check(result: libc::c_long) -> io::Result<libc::c_long> {
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(result)
+        }
+    }
+
+    pub(super) fn restrict(paths: &BTreeMap<PathBuf, Access>) -> io::Result<Enforcement> {
+        let Some(abi) = abi() else {
+            return Ok(Enforcement::Unsupported {
+                reason: "this kernel has no Landlock support".to_string(),
+            });
+        };
+        let handled = handled(abi);
+
+        // Opened before confining, with O_PATH: no read right needed
+        let mut rules: Vec<(File, u64)> = Vec::new();
+        for (path, access) in paths {
+            let file = OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_PATH | libc::O_CLOEXEC)
+                .open(path)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+            let mut allowed = rights(*access) & handled;
+            if !file.metadata()?.is_dir() {
+                allowed &= FILE_RIGHTS;
+            }
+            rules.push((file, allowed));
+        }
+
+        let attr = RulesetAttr {
+            handled_access_fs: handled,
+        };
+        // SAFETY: `attr` is a live, correctly laid out ruleset attribute
+        // and its size is the one passed; the result is a new descriptor
+        // or an error
+        let fd = check(unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                std::mem::size_of::<RulesetAttr>(),
+                0u32,
+            )
+        })?;
+        let fd = i32::try_from(fd).map_err(io::Error::other)?;
+        // SAFETY: the kernel just returned this descriptor; nothing else
+        // owns it
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        for (file, allowed) in &rules {
+            let rule = PathBeneath {
+                allowed_access: *allowed,
+                parent_fd: file.as_raw_fd(),
+            };
+            // SAFETY: `rule` is a live, packed path-beneath attribute and
+            // both descriptors stay open for the duration of the call
+            check(unsafe {
+                libc::syscall(
+                    libc::SYS_landlock_add_rule,
+                    ruleset.as_raw_fd(),
+                    RULE_PATH_BENEATH,
+                    &rule as *const PathBeneath,
+                    0u32,
+                )
+            })?;
+        }
+
+        // SAFETY: PR_SET_NO_NEW_PRIVS takes plain integers and touches no
+        // memory; it is required before an unprivileged restrict_self
+        check(unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) }.into())?;
+        // SAFETY: the ruleset descriptor is open; the call takes no pointer
+        check(unsafe {
+            libc::syscall(libc::SYS_landlock_restrict_self, ruleset.as_raw_fd(), 0u32)
+        })?;
+        Ok(Enforcement::Enforced { abi })
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod landlock {
+    use super::{Access, Enforcement};
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    pub(super) fn restrict(_: &BTreeMap<PathBuf, Access>) -> std::io::Result<Enforcement> {
+        Ok(Enforcement::Unsupported {
+            reason: "Landlock is only available on Linux".to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::Parser;
+
+    fn watch_args(args: &[&str]) -> crate::cli::WatchArgs {
+        let cli =
+            Cli::try_parse_from(std::iter::once("config-watcher").chain(args.iter().copied()))
+                .unwrap();
+        match cli.into_command() {
+            crate::cli::Command::Watch(args) => *args,
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_plan_collects_the_paths_of_the_flags() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        for sub in ["conf", "keys", "run", "locks", "safe", "state"] {
+            std::fs::create_dir(root.join(sub)).unwrap();
+        }
+        std::fs::write(root.join("conf/config.json"), "{}").unwrap();
+        std::fs::write(root.join("keys/key.txt"), "").unwrap();
+        let path = |sub: &str| root.join(sub).to_str().unwrap().to_string();
+
+        let args = watch_args(&[
+            "-f",
+            &path("conf/config.json"),
+            "--age-identity",
+            &path("keys/key.txt"),
+            "--status-file",
+            &path("run/status.json"),
+            "--lock-dir",
+            &path("locks"),
+            "--fallback-config",
+            &path("safe/config.json"),
+            "--state-dir",
+            &path("state"),
+            "--sandbox",
+        ]);
+        let sandbox = plan(&args, &args.config_file, None, None);
+        let ours: Vec<(PathBuf, Access)> = sandbox
+            .paths()
+            .filter(|(path, _)| path.starts_with(&root))
+            .map(|(path, access)| (path.strip_prefix(&root).unwrap().to_path_buf(), access))
+            .collect();
+        assert_eq!(
+            ours,
+            [
+                (PathBuf::from("conf"), Access::Read),
+                (PathBuf::from("keys/key.txt"), Access::Read),
+                (PathBuf::from("locks"), Access::Write),
+                (PathBuf::from("run"), Access::Write),
+                (PathBuf::from("safe"), Access::Read),
+                (PathBuf::from("state"), Access::Write),
+            ]
+        );
+
+        assert!(sandbox.allows(&root.join("conf/config.json.sha256"), Access::Read));
+        assert!(!sandbox.allows(&root.join("conf/config.json"), Access::Write));
+        assert!(sandbox.allows(&root.join("run/status.json.tmp"), Access::Write));
+        assert!(!sandbox.allows(&root.join("keys/other.txt"), Access::Read));
+        assert!(!sandbox.allows(Path::new("/etc/passwd"), Access::Read));
+    }
+
+    #[test]
+    fn test_check_read_without_a_sandbox() {
+        // Nothing enforced in the test process
+        assert!(check_read(Path::new("/etc/passwd")).is_ok());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_enforced_on_its_own_thread() {
+        let inside = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(inside.path().join("config.json"), "{}").unwrap();
+        std::fs::write(outside.path().join("secret"), "s3cr3t").unwrap();
+        let mut sandbox = Sandbox::new();
+        sandbox.allow(inside.path(), Access::Read);
+
+        // Landlock confines the calling thread only: a thread of its own
+        // keeps the rest of the test process free
+        let (inside, outside) = (inside.path().to_path_buf(), outside.path().to_path_buf());
+        let result = std::thread::spawn(move || {
+            let abi = match landlock::restrict(&sandbox.paths).unwrap() {
+                Enforcement::Enforced { abi } => abi,
+                Enforcement::Unsupported { .. } => return None,
+            };
+            let read_inside = std::fs::read_to_string(inside.join("config.json")).is_ok();
+            let outside = std::fs::read_to_string(outside.join("secret")).unwrap_err();
+            let write_inside = std::fs::write(inside.join("new.json"), "{}").is_ok();
+            Some((abi, read_inside, outside.kind(), write_inside))
+        })
+        .join()
+        .unwrap();
+
+        let Some((abi, read_inside, outside, write_inside)) = result else {
+            eprintln!("Landlock unavailable, skipped");
+            return;
+        };
+        assert!(abi >= 1);
+        assert!(read_inside);
+        assert_eq!(outside, std::io::ErrorKind::PermissionDenied);
+        assert!(!write_inside);
+    }
+}
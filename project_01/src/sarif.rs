@@ -0,0 +1,190 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`serde_json::json!`**: The SARIF log is built as a plain JSON value; the
+  subset we emit is small enough not to need typed structs
+- **`Result<&T, &E>`**: `Result::as_ref` lets the caller keep the outcome
+
+**Design decisions**:
+- One SARIF 2.1.0 run per file; the tool's rules are the stable error codes
+  of `report::CODES`, so dashboards group results the same way CI scripts
+  branch on `--error-format json`
+- Each finding becomes one result; warnings use the `validation_warning`
+  rule, everything else the code of the failure. A failure without findings
+  (missing file...) still produces one result
+- A `region` is only written when the position is known; a passing file
+  produces a run with empty `results`, so the upload step never breaks
+- `lint` writes the same document with its own rule catalogue (`lint::RULES`)
+
+******************************************************************************/
+
+use crate::lint::{self, LintFinding};
+use crate::report::{self, CODES};
+use crate::validation::{Finding, Severity, ValidationReport};
+use serde_json::{Value, json};
+use std::path::Path;
+
+/// SARIF version written by this module
+pub const VERSION: &str = "2.1.0";
+
+/// URI of the SARIF 2.1.0 JSON Schema
+pub const SCHEMA_URI: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Builds the SARIF log for one validation of `file`
+pub fn log(file: &Path, outcome: Result<&ValidationReport, &anyhow::Error>) -> Value {
+    let uri = artifact_uri(file);
+    let results: Vec<Value> = match outcome {
+        Ok(report) => report
+            .warnings()
+            .map(|finding| result(&uri, CODES, "validation_warning", finding))
+            .collect(),
+        Err(error) => {
+            let code = report::code(error);
+            let findings = report::findings(error);
+            if findings.is_empty() {
+                vec![json!({
+                    "ruleId": code,
+                    "ruleIndex": rule_index(CODES, code),
+                    "level": "error",
+                    "message": { "text": format!("{error:#}") },
+                    "locations": [location(&uri, None, None)],
+                })]
+            } else {
+                findings
+                    .iter()
+                    .map(|finding| match finding.severity {
+                        Severity::Error => result(&uri, CODES, code, finding),
+                        Severity::Warning => result(&uri, CODES, "validation_warning", finding),
+                    })
+                    .collect()
+            }
+        }
+    };
+
+    document(CODES, results)
+}
+
+/// Builds the SARIF log for one lint of `file`
+pub fn lint_log(file: &Path, findings: &[LintFinding]) -> Value {
+    let uri = artifact_uri(file);
+    let catalogue: Vec<(&str, &str)> = lint::RULES
+        .iter()
+        .map(|rule| (rule.id, rule.description))
+        .collect();
+    let results = findings
+        .iter()
+        .map(|lint| result(&uri, &catalogue, lint.rule, &lint.finding))
+        .collect();
+    document(&catalogue, results)
+}
+
+/// Writes `log` to `out`
+pub fn write_log(out: &Path, log: &Value) -> crate::error::Result<()> {
+    let mut text = serde_json::to_string_pretty(log).unwrap_or_default();
+    text.push('\n');
+    crate::fs_util::write_atomic(out, text.as_bytes())
+}
+
+/// One run of this tool with the `catalogue` of (rule id, description)
+fn document(catalogue: &[(&str, &str)], results: Vec<Value>) -> Value {
+    let rules: Vec<Value> = catalogue
+        .iter()
+        .map(|(id, description)| json!({ "id": id, "shortDescription": { "text": description } }))
+        .collect();
+
+    json!({
+        "$schema": SCHEMA_URI,
+        "version": VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+/// Writes the SARIF log of one validation to `out`
+pub fn write(
+    out: &Path,
+    file: &Path,
+    outcome: Result<&ValidationReport, &anyhow::Error>,
+) -> crate::error::Result<()> {
+    write_log(out, &log(file, outcome))
+}
+
+fn result(uri: &str, catalogue: &[(&str, &str)], rule: &str, finding: &Finding) -> Value {
+    let level = match finding.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    json!({
+        "ruleId": rule,
+        "ruleIndex": rule_index(catalogue, rule),
+        "level": level,
+        "message": { "text": finding.to_string() },
+        "locations": [location(uri, finding.line, finding.column)],
+    })
+}
+
+fn location(uri: &str, line: Option<usize>, column: Option<usize>) -> Value {
+    let mut physical = json!({ "artifactLocation": { "uri": uri } });
+    if let Some(line) = line {
+        let mut region = json!({ "startLine": line });
+        if let Some(column) = column {
+            region["startColumn"] = json!(column);
+        }
+        physical["region"] = region;
+    }
+    json!({ "physicalLocation": physical })
+}
+
+fn rule_index(catalogue: &[(&str, &str)], rule: &str) -> usize {
+    catalogue
+        .iter()
+        .position(|(id, _)| *id == rule)
+        .unwrap_or(0)
+}
+
+/// `file:` URI for absolute paths, forward-slash relative reference otherwise
+fn artifact_uri(file: &Path) -> String {
+    match url::Url::from_file_path(file) {
+        Ok(url) => url.to_string(),
+        Err(()) => file.to_string_lossy().replace('\\', "/"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_results_and_regions() {
+        let mut report = ValidationReport::new();
+        report.warning("server.port", "is privileged");
+        let log = log(Path::new("conf/app.json"), Ok(&report));
+        let results = &log["runs"][0]["results"];
+        assert_eq!(results.as_array().unwrap().len(), 1);
+        assert_eq!(results[0]["level"], "warning");
+        assert_eq!(results[0]["ruleId"], "validation_warning");
+        let physical = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(physical["artifactLocation"]["uri"], "conf/app.json");
+        assert!(physical.get("region").is_none());
+
+        let error = anyhow::Error::from(serde_json::from_str::<Value>("{\n  x").unwrap_err());
+        let log = super::log(Path::new("/etc/app.json"), Err(&error));
+        let result = &log["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], "invalid_json");
+        assert_eq!(
+            CODES[result["ruleIndex"].as_u64().unwrap() as usize].0,
+            "invalid_json"
+        );
+        let physical = &result["locations"][0]["physicalLocation"];
+        assert_eq!(physical["artifactLocation"]["uri"], "file:///etc/app.json");
+        assert_eq!(physical["region"]["startLine"], 2);
+    }
+}
@@ -0,0 +1,579 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`const` tables of structs**: Metadata lives in static memory, no setup
+- **`&'static str`**: Every string in the table is a literal
+- **Iterator adapters**: `filter`/`map` for lookups and suggestions
+
+**Design decisions**:
+- One table describes every field (type, required, default, rules) and is
+  the only source for `explain`, generated docs and secret redaction
+- Map entries are described once with a `*` wildcard (`features.*`)
+- Tests compare the table against the real structs so the two cannot drift
+
+******************************************************************************/
+
+use crate::path::{FieldPath, Segment};
+
+/// Description of one configuration field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldInfo {
+    /// Dotted path; `*` stands for any map key
+    pub path: &'static str,
+    /// Human-readable type
+    pub ty: &'static str,
+    /// Whether the field must be present (within its section)
+    pub required: bool,
+    /// Default applied when the field is absent, rendered as JSON
+    pub default: Option<&'static str>,
+    /// One-line description
+    pub description: &'static str,
+    /// Validation rules beyond the type
+    pub rules: &'static [&'static str],
+    /// Whether the value must be redacted when printed
+    pub secret: bool,
+}
+
+impl FieldInfo {
+    /// Nesting depth (0 for top-level fields)
+    pub fn depth(&self) -> usize {
+        self.path.matches('.').count()
+    }
+
+    /// Last segment of the path
+    pub fn name(&self) -> &'static str {
+        self.path.rsplit('.').next().unwrap_or(self.path)
+    }
+}
+
+/// Every known field, parents before children
+pub const FIELDS: &[FieldInfo] = &[
+    FieldInfo {
+        path: "app_name",
+        ty: "string",
+        required: true,
+        default: None,
+        description: "Application name",
+        rules: &["must not be empty"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "version",
+        ty: "string",
+        required: true,
+        default: None,
+        description: "Application version",
+        rules: &["should follow semver format (e.g. 1.0.0)"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "environment",
+        ty: "string",
+        required: false,
+        default: Some("\"development\""),
+        description: "Deployment environment",
+        rules: &["one of: development, staging, production"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "server",
+        ty: "object",
+        required: false,
+        default: None,
+        description: "Server configuration",
+        rules: &[],
+        secret: false,
+    },
+    FieldInfo {
+        path: "server.host",
+        ty: "string",
+        required: true,
+        default: None,
+        description: "Host name or address to bind",
+        rules: &["must not be empty"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "server.port",
+        ty: "integer (u16)",
+        required: true,
+        default: None,
+        description: "TCP port to listen on",
+        rules: &["between 1 and 65535"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "server.enable_ssl",
+        ty: "boolean",
+        required: false,
+        default: Some("true"),
+        description: "Serve over TLS",
+        rules: &[],
+        secret: false,
+    },
+    FieldInfo {
+        path: "server.allowed_ips",
+        ty: "array<string>",
+        required: false,
+        default: Some("[]"),
+        description: "Client IPs or CIDR blocks allowed to connect (empty allows all)",
+        rules: &["IP address or CIDR block", "must not also be denied"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "server.denied_ips",
+        ty: "array<string>",
+        required: false,
+        default: Some("[]"),
+        description: "Client IPs or CIDR blocks always refused, even if allowed",
+        rules: &["IP address or CIDR block"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "server.max_connections",
+        ty: "integer (u32)",
+        required: false,
+        default: Some("1024"),
+        description: "Maximum number of simultaneous client connections",
+        rules: &["at least 1", "warning above 100000"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "server.keep_alive",
+        ty: "duration",
+        required: false,
+        default: Some("\"1m 15s\""),
+        description: "How long an idle connection is kept open",
+        rules: &["between 1s and 1h"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "server.request_timeout",
+        ty: "duration",
+        required: false,
+        default: Some("\"30s\""),
+        description: "How long a single request may take",
+        rules: &[
+            "between 1s and 1h",
+            "warning unless shorter than keep_alive",
+        ],
+        secret: false,
+    },
+    FieldInfo {
+        path: "server.shutdown_grace",
+        ty: "duration",
+        required: false,
+        default: Some("\"30s\""),
+        description: "How long in-flight requests get to finish on shutdown",
+        rules: &["at most 10m"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "database",
+        ty: "object",
+        required: false,
+        default: None,
+        description: "Database configuration",
+        rules: &[],
+        secret: false,
+    },
+    FieldInfo {
+        path: "database.connection_string",
+        ty: "string",
+        required: true,
+        default: None,
+        description: "Database connection URL (may contain credentials)",
+        rules: &["must not be empty"],
+        secret: true,
+    },
+    FieldInfo {
+        path: "database.pool_size",
+        ty: "integer (u32)",
+        required: false,
+        default: Some("10"),
+        description: "Maximum number of pooled connections",
+        rules: &["greater than 0"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "database.timeout_seconds",
+        ty: "integer (u64)",
+        required: false,
+        default: Some("30"),
+        description: "Connection timeout in seconds",
+        rules: &[],
+        secret: false,
+    },
+    FieldInfo {
+        path: "proxy",
+        ty: "object",
+        required: false,
+        default: None,
+        description: "Outbound proxy settings",
+        rules: &[],
+        secret: false,
+    },
+    FieldInfo {
+        path: "proxy.http",
+        ty: "string",
+        required: false,
+        default: None,
+        description: "Proxy URL for plain HTTP requests (may contain credentials)",
+        rules: &[
+            "scheme http, https or socks5",
+            "credentials in the URL trigger a warning",
+        ],
+        secret: true,
+    },
+    FieldInfo {
+        path: "proxy.https",
+        ty: "string",
+        required: false,
+        default: None,
+        description: "Proxy URL for HTTPS requests (may contain credentials)",
+        rules: &[
+            "scheme http, https or socks5",
+            "credentials in the URL trigger a warning",
+        ],
+        secret: true,
+    },
+    FieldInfo {
+        path: "proxy.no_proxy",
+        ty: "array<string>",
+        required: false,
+        default: Some("[]"),
+        description: "Hosts reached without the proxy",
+        rules: &["host name, IP address, CIDR range or .domain suffix"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "messaging",
+        ty: "object",
+        required: false,
+        default: None,
+        description: "Message broker settings",
+        rules: &[],
+        secret: false,
+    },
+    FieldInfo {
+        path: "messaging.kind",
+        ty: "string",
+        required: true,
+        default: None,
+        description: "Broker protocol",
+        rules: &["one of: kafka, amqp"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "messaging.brokers",
+        ty: "array<string>",
+        required: false,
+        default: Some("[]"),
+        description: "Broker addresses",
+        rules: &["at least one broker", "host:port, or [addr]:port for IPv6"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "messaging.topic_prefix",
+        ty: "string",
+        required: true,
+        default: None,
+        description: "Prefix of every topic or queue name",
+        rules: &["letters, digits, '.', '_' and '-' only"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "messaging.tls",
+        ty: "boolean",
+        required: false,
+        default: Some("true"),
+        description: "Connect to the brokers over TLS",
+        rules: &["false triggers a warning in production"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "messaging.consumer_group",
+        ty: "string",
+        required: false,
+        default: None,
+        description: "Consumer group",
+        rules: &["required for kafka", "not allowed for amqp"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "auth",
+        ty: "object",
+        required: false,
+        default: None,
+        description: "Authentication settings",
+        rules: &["needs jwt, oauth or both"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "auth.jwt",
+        ty: "object",
+        required: false,
+        default: None,
+        description: "JSON Web Token verification",
+        rules: &["exactly one of jwks_url and secret_file"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "auth.jwt.issuer",
+        ty: "string",
+        required: true,
+        default: None,
+        description: "Expected iss claim",
+        rules: &["https URL, or http in development"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "auth.jwt.audience",
+        ty: "array<string>",
+        required: false,
+        default: Some("[]"),
+        description: "Accepted aud claims",
+        rules: &["at least one audience"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "auth.jwt.jwks_url",
+        ty: "string",
+        required: false,
+        default: None,
+        description: "Where the signing keys are published",
+        rules: &["https URL, or http in development"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "auth.jwt.secret_file",
+        ty: "string",
+        required: false,
+        default: None,
+        description: "File holding the HMAC secret",
+        rules: &["must exist, mode 0600"],
+        secret: true,
+    },
+    FieldInfo {
+        path: "auth.jwt.clock_skew",
+        ty: "duration",
+        required: false,
+        default: Some("\"1m\""),
+        description: "Tolerance on exp and nbf for clocks out of sync",
+        rules: &["at most 5m"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "auth.oauth",
+        ty: "object",
+        required: false,
+        default: None,
+        description: "OAuth 2.0 client",
+        rules: &[],
+        secret: false,
+    },
+    FieldInfo {
+        path: "auth.oauth.authorize_url",
+        ty: "string",
+        required: true,
+        default: None,
+        description: "Authorization endpoint",
+        rules: &["https URL, or http in development"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "auth.oauth.token_url",
+        ty: "string",
+        required: true,
+        default: None,
+        description: "Token endpoint",
+        rules: &["https URL, or http in development"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "auth.oauth.client_id",
+        ty: "string",
+        required: true,
+        default: None,
+        description: "Client identifier registered with the provider",
+        rules: &["must not be empty"],
+        secret: false,
+    },
+    FieldInfo {
+        path: "auth.oauth.client_secret_file",
+        ty: "string",
+        required: true,
+        default: None,
+        description: "File holding the client secret",
+        rules: &["must exist, mode 0600"],
+        secret: true,
+    },
+    FieldInfo {
+        path: "features",
+        ty: "map<string, boolean>",
+        required: false,
+        default: Some("{}"),
+        description: "Feature flags",
+        rules: &[],
+        secret: false,
+    },
+    FieldInfo {
+        path: "features.*",
+        ty: "boolean",
+        required: false,
+        default: None,
+        description: "Whether the named feature is enabled",
+        rules: &[],
+        secret: false,
+    },
+];
+
+/// Finds the entry describing a concrete path such as `features.dark_mode`
+pub fn lookup(path: &FieldPath) -> Option<&'static FieldInfo> {
+    FIELDS.iter().find(|info| matches(info.path, path))
+}
+
+/// Paths of every field that holds a secret
+pub fn secret_fields() -> impl Iterator<Item = &'static FieldInfo> {
+    FIELDS.iter().filter(|info| info.secret)
+}
+
+/// Known paths closest to `input`, best match first
+///
+/// Used to suggest corrections for typos like `server.prot`.
+pub fn suggest(input: &str) -> Vec<&'static str> {
+    let mut scored: Vec<(usize, &'static str)> = FIELDS
+        .iter()
+        .map(|info| (edit_distance(input, info.path), info.path))
+        .filter(|(distance, path)| {
+            *distance <= 2.max(input.len() / 3) || path.ends_with(&format!(".{input}"))
+        })
+        .collect();
+    scored.sort();
+    scored.into_iter().map(|(_, path)| path).take(3).collect()
+}
+
+/// Whether a table pattern (possibly containing `*`) matches a parsed path
+fn matches(pattern: &str, path: &FieldPath) -> bool {
+    let parts: Vec<&str> = pattern.split('.').collect();
+    parts.len() == path.segments().len()
+        && parts
+            .iter()
+            .zip(path.segments())
+            .all(|(part, segment)| match segment {
+                Segment::Key(key) => *part == "*" || part == key,
+                Segment::Index(_) => false,
+            })
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use serde_json::Value;
+
+    /// Collects every object path in a document, using `*` under `features`
+    fn collect_paths(value: &Value, prefix: &str, out: &mut Vec<String>) {
+        if let Value::Object(map) = value {
+            for (key, child) in map {
+                let key = if prefix == "features" {
+                    "*"
+                } else {
+                    key.as_str()
+                };
+                let path = if prefix.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                collect_paths(child, &path, out);
+                if !out.contains(&path) {
+                    out.push(path);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_table_matches_structs() {
+        let doc = serde_json::to_value(AppConfig::example()).unwrap();
+        let mut paths = Vec::new();
+        collect_paths(&doc, "", &mut paths);
+        // The example gets its JWT keys from jwks_url, not secret_file
+        paths.push("auth.jwt.secret_file".to_string());
+
+        for path in &paths {
+            assert!(
+                FIELDS.iter().any(|info| info.path == path),
+                "field {path} is missing from schema::FIELDS"
+            );
+        }
+        for info in FIELDS {
+            assert!(
+                paths.iter().any(|p| p == info.path),
+                "schema::FIELDS describes {} which the structs don't have",
+                info.path
+            );
+        }
+    }
+
+    #[test]
+    fn test_defaults_match_serde() {
+        let minimal = r#"{
+            "app_name": "x", "version": "1.0",
+            "server": { "host": "h", "port": 1 },
+            "database": { "connection_string": "c" },
+            "proxy": {},
+            "messaging": { "kind": "amqp", "topic_prefix": "t" },
+            "auth": { "jwt": { "issuer": "i" } }
+        }"#;
+        let config: AppConfig = serde_json::from_str(minimal).unwrap();
+        let doc = serde_json::to_value(config).unwrap();
+
+        for info in FIELDS {
+            if let Some(default) = info.default {
+                let path = FieldPath::parse(info.path).unwrap();
+                let expected: Value = serde_json::from_str(default).unwrap();
+                assert_eq!(path.resolve(&doc).unwrap(), &expected, "{}", info.path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_representative_entries() {
+        let port = lookup(&FieldPath::parse("server.port").unwrap()).unwrap();
+        assert_eq!(port.ty, "integer (u16)");
+        assert!(port.required);
+
+        let env = lookup(&FieldPath::parse("environment").unwrap()).unwrap();
+        assert_eq!(env.default, Some("\"development\""));
+
+        let flag = lookup(&FieldPath::parse("features.dark_mode").unwrap()).unwrap();
+        assert_eq!(flag.path, "features.*");
+
+        assert!(lookup(&FieldPath::parse("server.nope").unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_suggestions() {
+        assert_eq!(suggest("server.prot")[0], "server.port");
+        assert_eq!(suggest("enviroment")[0], "environment");
+        assert!(suggest("pool_size").contains(&"database.pool_size"));
+        assert!(suggest("completely_unrelated_thing").is_empty());
+    }
+}
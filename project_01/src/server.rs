@@ -0,0 +1,799 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`Arc<str>`**: Each configuration is rendered to JSON once per load and
+  shared; a request clones the pointer, never the configuration
+- **`JoinHandle::abort`**: Dropping the `Server` stops the listener, so the
+  server lives exactly as long as the watch
+- **`#[cfg(feature = "http-server")]`**: The whole module is left out of
+  builds without the feature
+- **`listener::listen`**: hyper reads the requests and writes the answers;
+  `respond` only routes, as a match on the method and the path
+- **`http_body_util::Limited`**: The body of `PUT /config`, chunked or
+  not, is read up to the configuration size limit and no further
+- **`watch::Sender<bool>`**: `Server::shutdown` tells the connections to
+  close (WebSocket sessions with a close frame, the others once their
+  request is answered), then waits for them to let go of their receivers
+- **`Mutex<()>` + `spawn_blocking`**: `PUT /config` reads, patches,
+  validates and writes the file on a blocking thread, one request at a
+  time, so two patches never interleave
+
+**Design decisions**:
+- `--http-addr` serves the watchers' `StatusBoard`, the in-memory twin of
+  the status file: `GET /config` and `GET /status`
+- `/config` is the last valid configuration with every secret redacted,
+  the same document as `get --format json`; with several watched files it
+  is an object keyed by path. Before any valid load it is a 503: there is
+  nothing to serve yet, and a client should retry
+- `/status` is the status file's record (health, version, last reload
+  times, failure counts), so scripts can read either
+- Bind to 127.0.0.1 unless the configuration is meant for the network:
+  there is no authentication
+- `PUT /config` (`--http-allow-write`, with `--http-token`) takes an
+  RFC 7386 merge patch, applies it to the file as written (not to the
+  effective configuration: no defaults or overrides get written), runs the
+  same checks as `validate` and writes the file atomically. The watch loop
+  then picks it up like any other edit, so a patch is reported, hooked and
+  notified exactly once, by the reload, at the next tick
+- Like `set` and `patch`, a patch may not store in clear a field that
+  holds an `age:` value: it is a 422 naming the field
+- A rejected patch is a 422 with the error's JSON (code, message,
+  findings), the file untouched; a missing token is a 401, a wrong one a
+  403, a body over the configuration size limit a 413, and one that takes
+  over `READ_TIMEOUT` to arrive a 408
+- The answer to a successful `PUT` is the patched document, redacted like
+  `/config`
+- `GET /livez` and `GET /readyz` answer from the watchers' `Probes`
+  (see `probes.rs`): 200, or 500 for a stuck loop and 503 with the reason
+  per file when not ready. `--health-addr` serves only these two, so they
+  can be exposed without `/config`
+- `GET /ws` upgrades to a WebSocket streaming the events (see
+  `websocket.rs`); connections are closed cleanly when the watch ends,
+  within `CLOSE_TIMEOUT`
+- `POST /dump` prints a status dump to the watcher's output, as SIGUSR1
+  does on Unix (see `dump.rs`): 202, or 429 within a second of the last
+  one. Nothing is answered but that: `/status` and `/config` are there to
+  read
+- Any other path is a 404, any other method on these paths a 405
+
+******************************************************************************/
+
+use crate::commands::{self, validate};
+use crate::config::MAX_CONFIG_SIZE;
+use crate::dump::DumpRequests;
+use crate::error::ConfigError;
+use crate::external_schema::ExternalSchema;
+use crate::fs_util::{self, Backups};
+use crate::listener::{self, Body, READ_TIMEOUT};
+use crate::output::error_json;
+use crate::patch;
+use crate::probes::Probes;
+use crate::redact;
+use crate::status::StatusBoard;
+use crate::validation::ValidationReport;
+use crate::websocket::{self, EventStream};
+use anyhow::Context;
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use hyper::body::{Body as _, Incoming};
+use hyper::header::{ALLOW, AUTHORIZATION, HeaderValue, RETRY_AFTER, WWW_AUTHENTICATE};
+use hyper::{Method, Request, Response, StatusCode};
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::time::SystemTime;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Time given to WebSocket connections to close on shutdown
+pub const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What the server answers; nothing until given something to serve
+#[derive(Clone, Default)]
+pub struct Endpoints {
+    board: Option<StatusBoard>,
+    writer: Option<Arc<ConfigWriter>>,
+    probes: Option<Probes>,
+    events: Option<EventStream>,
+    dumps: Option<DumpRequests>,
+}
+
+impl Endpoints {
+    /// Serves `GET /config` and `GET /status` from `board`
+    pub fn with_board(mut self, board: StatusBoard) -> Self {
+        self.board = Some(board);
+        self
+    }
+
+    /// Serves `GET /livez` and `GET /readyz` from `probes`
+    pub fn with_probes(mut self, probes: Probes) -> Self {
+        self.probes = Some(probes);
+        self
+    }
+
+    /// Streams `events` to `GET /ws` connections
+    pub fn with_events(mut self, events: EventStream) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Accepts `POST /dump`, printing a status dump through `dumps`
+    pub fn with_dumps(mut self, dumps: DumpRequests) -> Self {
+        self.dumps = Some(dumps);
+        self
+    }
+
+    /// Accepts `PUT /config` through `writer`, along with the `board`
+    pub fn with_writer(mut self, writer: ConfigWriter) -> Self {
+        self.writer = Some(Arc::new(writer));
+        self
+    }
+}
+
+/// Applies the merge patches of `PUT /config` to a configuration file
+pub struct ConfigWriter {
+    path: PathBuf,
+    token: String,
+    schema: Option<ExternalSchema>,
+    backups: Backups,
+    lock: Mutex<()>,
+}
+
+/// Why a patch was not written
+#[derive(Debug)]
+enum Rejection {
+    /// The patched configuration does not validate (422)
+    Invalid(anyhow::Error),
+    /// The file could not be read or written (500)
+    Failed(anyhow::Error),
+}
+
+impl ConfigWriter {
+    /// Writes to `path` for requests bearing `token`
+    pub fn new(path: impl Into<PathBuf>, token: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            token: token.into(),
+            schema: None,
+            backups: Backups::default(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Also checks patched configurations against `schema`
+    pub fn with_schema(mut self, schema: ExternalSchema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Keeps `backups` of the file, instead of one
+    pub fn with_backups(mut self, backups: Backups) -> Self {
+        self.backups = backups;
+        self
+    }
+
+    /// Whether `token` is the expected one
+    ///
+    /// The comparison takes as long whatever the first differing byte.
+    fn authorizes(&self, token: &str) -> bool {
+        let (given, expected) = (token.trim().as_bytes(), self.token.as_bytes());
+        given.len() == expected.len()
+            && given
+                .iter()
+                .zip(expected)
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+
+    /// Patches the file, checks the result and writes it; returns the
+    /// patched document, redacted
+    fn apply(&self, patch: &Value) -> Result<Value, Rejection> {
+        let _serialized = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let (original, mut doc) = commands::load_document(&self.path).map_err(Rejection::Failed)?;
+        let before = doc.clone();
+        patch::merge(&mut doc, patch);
+        let exposed = commands::exposed_secrets(&before, &doc);
+        if !exposed.is_empty() {
+            let mut report = ValidationReport::new();
+            for path in exposed {
+                report.error(
+                    path,
+                    "holds an encrypted (age:) value: set it to a value encrypted with age, \
+                     as age:<base64>",
+                );
+            }
+            let error = ConfigError::ValidationFailed { report };
+            return Err(Rejection::Invalid(error.into()));
+        }
+        let rendered = commands::render_document(&doc, &original).map_err(Rejection::Failed)?;
+        validate::validate_contents(&self.path, &rendered, self.schema.as_ref())
+            .map_err(Rejection::Invalid)?;
+        fs_util::safe_write(&self.path, rendered.as_bytes(), self.backups)
+            .with_context(|| format!("Cannot write {}", self.path.display()))
+            .map_err(Rejection::Failed)?;
+        tracing::info!(path = %self.path.display(), "configuration patched over HTTP");
+        redact::redact(&mut doc);
+        Ok(doc)
+    }
+}
+
+/// The running server; dropping it stops the listener
+#[derive(Debug)]
+pub struct Server {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+    closing: watch::Sender<bool>,
+}
+
+impl Server {
+    /// The bound address, useful with port 0
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stops the listener and closes the WebSocket connections, waiting
+    /// at most `CLOSE_TIMEOUT` for them
+    pub async fn shutdown(self) {
+        self.task.abort();
+        self.closing.send_replace(true);
+        let _ = tokio::time::timeout(CLOSE_TIMEOUT, self.closing.closed()).await;
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Serves `endpoints` on `addr` until the returned `Server` is dropped
+pub async fn serve(addr: SocketAddr, endpoints: Endpoints) -> std::io::Result<Server> {
+    let (closing, closing_receiver) = watch::channel(false);
+    let connections = closing_receiver.clone();
+    let (addr, task) = listener::listen(addr, Some(connections), move |request| {
+        respond(request, endpoints.clone(), closing_receiver.clone())
+    })
+    .await?;
+    Ok(Server {
+        addr,
+        task,
+        closing,
+    })
+}
+
+/// The answer to one request
+async fn respond(
+    request: Request<Incoming>,
+    endpoints: Endpoints,
+    closing: watch::Receiver<bool>,
+) -> Response<Body> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    if let (Some(events), "/ws") = (&endpoints.events, path.as_str()) {
+        if method != Method::GET {
+            return refuse("GET");
+        }
+        return websocket::serve(request, events, endpoints.board.as_ref(), closing);
+    }
+    if let (Some(dumps), "/dump") = (&endpoints.dumps, path.as_str()) {
+        return match method {
+            Method::POST if dumps.request("POST /dump") => answer(
+                StatusCode::ACCEPTED,
+                json!({ "status": "dumped" }).to_string(),
+            ),
+            Method::POST => {
+                let mut response = answer(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    error("a dump was printed less than a second ago"),
+                );
+                let retry = HeaderValue::from_static("1");
+                response.headers_mut().insert(RETRY_AFTER, retry);
+                response
+            }
+            _ => refuse("POST"),
+        };
+    }
+    let (status, body) = match (
+        method,
+        path.as_str(),
+        &endpoints.board,
+        &endpoints.writer,
+        &endpoints.probes,
+    ) {
+        (Method::GET, "/livez", _, _, Some(probes)) => livez(probes),
+        (Method::GET, "/readyz", _, _, Some(probes)) => readyz(probes),
+        (Method::GET, "/config", Some(board), _, _) => config(board),
+        (Method::GET, "/status", Some(board), _, _) => (
+            StatusCode::OK,
+            serde_json::to_string(&board.record()).unwrap_or_default(),
+        ),
+        (Method::PUT, "/config", Some(_), Some(writer), _) => {
+            return put_config(writer.clone(), request).await;
+        }
+        (_, "/livez" | "/readyz", _, _, Some(_)) => return refuse("GET"),
+        (_, "/config", Some(_), Some(_), _) => return refuse("GET, PUT"),
+        (_, "/config" | "/status", Some(_), _, _) => return refuse("GET"),
+        _ => (StatusCode::NOT_FOUND, error("not found")),
+    };
+    answer(status, body)
+}
+
+/// The answer to `PUT /config`
+async fn put_config(writer: Arc<ConfigWriter>, request: Request<Incoming>) -> Response<Body> {
+    let token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|authorization| authorization.to_str().ok())
+        .and_then(|authorization| authorization.strip_prefix("Bearer "));
+    match token {
+        None => {
+            let mut response = answer(
+                StatusCode::UNAUTHORIZED,
+                error("a bearer token is required"),
+            );
+            let bearer = HeaderValue::from_static("Bearer");
+            response.headers_mut().insert(WWW_AUTHENTICATE, bearer);
+            return response;
+        }
+        Some(token) if !writer.authorizes(token) => {
+            return answer(StatusCode::FORBIDDEN, error("invalid token"));
+        }
+        Some(_) => {}
+    }
+    let too_large = || {
+        answer(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            error("the patch is too large"),
+        )
+    };
+    // Refused on its Content-Length, else as soon as it gets over
+    if request.body().size_hint().lower() > MAX_CONFIG_SIZE {
+        return too_large();
+    }
+    let body = Limited::new(request.into_body(), MAX_CONFIG_SIZE as usize);
+    let body = match tokio::time::timeout(READ_TIMEOUT, body.collect()).await {
+        Ok(Ok(body)) => body.to_bytes(),
+        Ok(Err(e)) if e.is::<LengthLimitError>() => return too_large(),
+        Ok(Err(e)) => {
+            return answer(
+                StatusCode::BAD_REQUEST,
+                error(&format!("cannot read the patch: {e}")),
+            );
+        }
+        Err(_) => {
+            return answer(
+                StatusCode::REQUEST_TIMEOUT,
+                error("the patch took too long to arrive"),
+            );
+        }
+    };
+    let patch: Value = match serde_json::from_slice(&body) {
+        Ok(patch) => patch,
+        Err(e) => {
+            return answer(
+                StatusCode::BAD_REQUEST,
+                error(&format!("not a JSON merge patch: {e}")),
+            );
+        }
+    };
+    let (status, body) = match tokio::task::spawn_blocking(move || writer.apply(&patch)).await {
+        Ok(Ok(doc)) => (StatusCode::OK, doc.to_string()),
+        Ok(Err(Rejection::Invalid(e))) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            json!({ "error": error_json(&e) }).to_string(),
+        ),
+        Ok(Err(Rejection::Failed(e))) => {
+            tracing::warn!(error = %format!("{e:#}"), "PUT /config failed");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                json!({ "error": error_json(&e) }).to_string(),
+            )
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, error(&e.to_string())),
+    };
+    answer(status, body)
+}
+
+/// The answer to `GET /livez`
+fn livez(probes: &Probes) -> (StatusCode, String) {
+    match probes.liveness(SystemTime::now()) {
+        Ok(age) => (
+            StatusCode::OK,
+            json!({ "status": "alive", "last_tick_secs": age.as_secs_f64() }).to_string(),
+        ),
+        Err(age) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "status": "stuck", "last_tick_secs": age.as_secs_f64() }).to_string(),
+        ),
+    }
+}
+
+/// The answer to `GET /readyz`
+fn readyz(probes: &Probes) -> (StatusCode, String) {
+    match probes.readiness() {
+        Ok(()) => (StatusCode::OK, json!({ "status": "ready" }).to_string()),
+        Err(not_ready) => {
+            let files: Vec<Value> = not_ready
+                .iter()
+                .map(|file| json!({ "path": file.path, "reason": file.reason }))
+                .collect();
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                json!({ "status": "not_ready", "files": files }).to_string(),
+            )
+        }
+    }
+}
+
+/// The body of `GET /config`
+fn config(board: &StatusBoard) -> (StatusCode, String) {
+    let configs = board.configs();
+    match configs.as_slice() {
+        [] => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            error("no valid configuration loaded yet"),
+        ),
+        [(_, config)] => (StatusCode::OK, config.to_string()),
+        _ => {
+            // Documents already are JSON; only the keys need quoting
+            let entries: Vec<String> = configs
+                .iter()
+                .map(|(path, config)| format!("{}:{config}", json!(path.display().to_string())))
+                .collect();
+            (StatusCode::OK, format!("{{{}}}", entries.join(",")))
+        }
+    }
+}
+
+/// A JSON answer
+fn answer(status: StatusCode, body: String) -> Response<Body> {
+    listener::respond(status, "application/json", body)
+}
+
+/// A 405, naming the methods `allow`ed
+fn refuse(allow: &'static str) -> Response<Body> {
+    let mut response = answer(
+        StatusCode::METHOD_NOT_ALLOWED,
+        error(&format!("use {allow}")),
+    );
+    let allow = HeaderValue::from_static(allow);
+    response.headers_mut().insert(ALLOW, allow);
+    response
+}
+
+fn error(message: &str) -> String {
+    json!({ "error": message }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::output::WatchState;
+    use crate::status::FileRecord;
+    use serde_json::Value;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    async fn request(addr: SocketAddr, method: &str, path: &str) -> (u16, Value) {
+        send(
+            addr,
+            &format!("{method} {path} HTTP/1.1\r\nHost: test\r\nConnection: close\r\n\r\n"),
+        )
+        .await
+    }
+
+    async fn put(addr: SocketAddr, token: Option<&str>, body: &str) -> (u16, Value) {
+        let authorization = token
+            .map(|token| format!("Authorization: Bearer {token}\r\n"))
+            .unwrap_or_default();
+        let request = format!(
+            "PUT /config HTTP/1.1\r\nHost: test\r\nConnection: close\r\n{authorization}\
+             Content-Type: application/merge-patch+json\r\n\
+             Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        send(addr, &request).await
+    }
+
+    async fn send(addr: SocketAddr, request: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split_once("\r\n\r\n").unwrap().1;
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    /// A server writing to a fresh `config.json`
+    async fn writable() -> (tempfile::TempDir, PathBuf, Server) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(
+            &path,
+            "{\n  \"app_name\": \"TestApp\",\n  \"version\": \"1.0.0\",\n  \"database\": { \"connection_string\": \"postgres://u:s3cret@db\" }\n}\n",
+        )
+        .unwrap();
+        let endpoints = Endpoints::default()
+            .with_board(StatusBoard::new(Duration::from_secs(1)))
+            .with_writer(ConfigWriter::new(&path, "t0ken"));
+        let server = serve("127.0.0.1:0".parse().unwrap(), endpoints)
+            .await
+            .unwrap();
+        (dir, path, server)
+    }
+
+    fn record(path: &str, version: u64, state: WatchState) -> FileRecord {
+        FileRecord {
+            path: PathBuf::from(path),
+            health: state.into(),
+            version,
+            last_success_at: None,
+            last_failure_at: None,
+            last_error: None,
+            fallback: None,
+            restored_at: None,
+            reloads: version.saturating_sub(1),
+            failures: 0,
+        }
+    }
+
+    fn config(version: &str) -> AppConfig {
+        let mut config = AppConfig {
+            version: version.to_string(),
+            ..AppConfig::example()
+        };
+        if let Some(ref mut database) = config.database {
+            database.connection_string = "postgres://admin:hunter2@db/app".to_string();
+        }
+        config
+    }
+
+    #[tokio::test]
+    async fn test_config_and_status_across_loads() {
+        let board = StatusBoard::new(Duration::from_secs(1));
+        let server = serve(
+            "127.0.0.1:0".parse().unwrap(),
+            Endpoints::default().with_board(board.clone()),
+        )
+        .await
+        .unwrap();
+        let addr = server.addr();
+
+        // Nothing valid loaded yet
+        board.update(record("app.json", 0, WatchState::Waiting));
+        let (status, body) = request(addr, "GET", "/config").await;
+        assert_eq!(status, 503);
+        assert!(body["error"].is_string());
+        let (status, body) = request(addr, "GET", "/status").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["health"], "unhealthy");
+        assert_eq!(body["files"][0]["version"], 0);
+
+        board.publish(Path::new("app.json"), &config("1.0.0"));
+        board.update(record("app.json", 1, WatchState::Valid));
+        let (status, body) = request(addr, "GET", "/config?pretty").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["version"], "1.0.0");
+        assert_eq!(body["database"]["connection_string"], "<redacted>");
+
+        board.publish(Path::new("app.json"), &config("2.0.0"));
+        board.update(record("app.json", 2, WatchState::Valid));
+        let (_, body) = request(addr, "GET", "/config").await;
+        assert_eq!(body["version"], "2.0.0");
+        let (_, body) = request(addr, "GET", "/status").await;
+        assert_eq!(body["health"], "healthy");
+        assert_eq!(body["files"][0]["version"], 2);
+        assert_eq!(body["files"][0]["reloads"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_several_files_are_keyed_by_path() {
+        let board = StatusBoard::new(Duration::from_secs(1));
+        let server = serve(
+            "127.0.0.1:0".parse().unwrap(),
+            Endpoints::default().with_board(board.clone()),
+        )
+        .await
+        .unwrap();
+        board.publish(Path::new("a.json"), &config("1.0.0"));
+        board.publish(Path::new("b.json"), &config("3.0.0"));
+
+        let (status, body) = request(server.addr(), "GET", "/config").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["a.json"]["version"], "1.0.0");
+        assert_eq!(body["b.json"]["version"], "3.0.0");
+        assert_eq!(
+            body["b.json"]["database"]["connection_string"],
+            "<redacted>"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dump_is_rate_limited() {
+        let board = StatusBoard::new(Duration::from_secs(1));
+        let emitter = crate::output::Emitter::new(crate::cli::OutputFormat::Json);
+        let dumps = DumpRequests::new(emitter, board.clone(), Vec::new());
+        let server = serve(
+            "127.0.0.1:0".parse().unwrap(),
+            Endpoints::default().with_board(board).with_dumps(dumps),
+        )
+        .await
+        .unwrap();
+        let (status, body) = request(server.addr(), "POST", "/dump").await;
+        assert_eq!((status, &body["status"]), (202, &json!("dumped")));
+        assert_eq!(request(server.addr(), "POST", "/dump").await.0, 429);
+        assert_eq!(request(server.addr(), "GET", "/dump").await.0, 405);
+    }
+
+    #[tokio::test]
+    async fn test_other_requests_are_refused() {
+        let board = StatusBoard::new(Duration::from_secs(1));
+        let server = serve(
+            "127.0.0.1:0".parse().unwrap(),
+            Endpoints::default().with_board(board),
+        )
+        .await
+        .unwrap();
+        assert_eq!(request(server.addr(), "GET", "/nope").await.0, 404);
+        assert_eq!(request(server.addr(), "DELETE", "/config").await.0, 405);
+
+        // Dropping the server closes the listener
+        let addr = server.addr();
+        drop(server);
+        tokio::task::yield_now().await;
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_put_config_round_trip() {
+        let (_dir, path, server) = writable().await;
+        let (status, body) = put(
+            server.addr(),
+            Some("t0ken"),
+            r#"{ "version": "1.1.0", "server": { "host": "0.0.0.0", "port": 9090 } }"#,
+        )
+        .await;
+        assert_eq!(status, 200, "{body}");
+        assert_eq!(body["version"], "1.1.0");
+        assert_eq!(body["database"]["connection_string"], "<redacted>");
+
+        // Written with the file's own indentation, secrets kept
+        let written = std::fs::read_to_string(&path).unwrap();
+        let doc: Value = serde_json::from_str(&written).unwrap();
+        assert_eq!(doc["server"]["port"], 9090);
+        assert_eq!(
+            doc["database"]["connection_string"],
+            "postgres://u:s3cret@db"
+        );
+        assert!(written.contains("\n  \"version\": \"1.1.0\""), "{written}");
+    }
+
+    #[tokio::test]
+    async fn test_chunked_and_oversized_patches() {
+        let (_dir, path, server) = writable().await;
+        let chunked = "PUT /config HTTP/1.1\r\nHost: test\r\nConnection: close\r\n\
+             Authorization: Bearer t0ken\r\nTransfer-Encoding: chunked\r\n\r\n\
+             d\r\n{ \"version\": \r\n9\r\n\"1.2.0\" }\r\n0\r\n\r\n";
+        let (status, body) = send(server.addr(), chunked).await;
+        assert_eq!(status, 200, "{body}");
+        let doc: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(doc["version"], "1.2.0");
+
+        // Refused from its announced length, before it is sent
+        let large = format!(
+            "PUT /config HTTP/1.1\r\nHost: test\r\nAuthorization: Bearer t0ken\r\n\
+             Content-Length: {}\r\n\r\n{{",
+            MAX_CONFIG_SIZE + 1
+        );
+        assert_eq!(send(server.addr(), &large).await.0, 413);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_patch_is_refused() {
+        let (_dir, path, server) = writable().await;
+        let before = std::fs::read_to_string(&path).unwrap();
+
+        let (status, body) = put(server.addr(), Some("t0ken"), r#"{ "app_name": "" }"#).await;
+        assert_eq!(status, 422);
+        assert_eq!(body["error"]["code"], "validation_failed");
+        assert_eq!(body["error"]["findings"][0]["path"], "app_name");
+
+        let (status, _) = put(
+            server.addr(),
+            Some("t0ken"),
+            r#"{ "server": { "port": "x" } }"#,
+        )
+        .await;
+        assert_eq!(status, 422);
+        let (status, _) = put(server.addr(), Some("t0ken"), "{ not json").await;
+        assert_eq!(status, 400);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), before);
+    }
+
+    #[tokio::test]
+    async fn test_put_needs_the_token() {
+        let (_dir, path, server) = writable().await;
+        let before = std::fs::read_to_string(&path).unwrap();
+        let patch = r#"{ "version": "6.6.6" }"#;
+
+        assert_eq!(put(server.addr(), None, patch).await.0, 401);
+        assert_eq!(put(server.addr(), Some("t0kem"), patch).await.0, 403);
+        assert_eq!(put(server.addr(), Some("t0ke"), patch).await.0, 403);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), before);
+
+        // Read-only unless a writer is given
+        let board = StatusBoard::new(Duration::from_secs(1));
+        let read_only = serve(
+            "127.0.0.1:0".parse().unwrap(),
+            Endpoints::default().with_board(board),
+        )
+        .await
+        .unwrap();
+        assert_eq!(put(read_only.addr(), Some("t0ken"), patch).await.0, 405);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_puts_are_serialized() {
+        let (_dir, path, server) = writable().await;
+        let addr = server.addr();
+        let puts: Vec<_> = (0..16)
+            .map(|i| {
+                tokio::spawn(async move {
+                    let patch = format!(r#"{{ "features": {{ "flag_{i}": true }} }}"#);
+                    put(addr, Some("t0ken"), &patch).await.0
+                })
+            })
+            .collect();
+        for put in puts {
+            assert_eq!(put.await.unwrap(), 200);
+        }
+
+        // No patch overwrote another one
+        let doc: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(doc["features"].as_object().unwrap().len(), 16);
+    }
+
+    #[tokio::test]
+    async fn test_probes_only() {
+        let probes = Probes::new(Duration::from_secs(1));
+        let probe = probes.register(Path::new("app.json"));
+        let endpoints = Endpoints::default().with_probes(probes);
+        let server = serve("127.0.0.1:0".parse().unwrap(), endpoints)
+            .await
+            .unwrap();
+        let addr = server.addr();
+
+        let (status, body) = request(addr, "GET", "/readyz").await;
+        assert_eq!(status, 503);
+        assert_eq!(body["files"][0]["path"], "app.json");
+        assert_eq!(
+            body["files"][0]["reason"],
+            "no valid configuration loaded yet"
+        );
+        let (status, body) = request(addr, "GET", "/livez").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["status"], "alive");
+
+        probe.record(SystemTime::now(), WatchState::Valid, 0);
+        assert_eq!(request(addr, "GET", "/readyz").await.0, 200);
+        // Degraded, but serving the previous configuration
+        probe.record(SystemTime::now(), WatchState::Failing, 1);
+        assert_eq!(request(addr, "GET", "/readyz").await.0, 200);
+
+        // A loop that stopped ticking
+        let stuck = SystemTime::now() - Duration::from_secs(10);
+        probe.record(stuck, WatchState::Valid, 0);
+        let (status, body) = request(addr, "GET", "/livez").await;
+        assert_eq!(status, 500);
+        assert_eq!(body["status"], "stuck");
+
+        // Nothing else is served
+        assert_eq!(request(addr, "GET", "/config").await.0, 404);
+        assert_eq!(request(addr, "GET", "/status").await.0, 404);
+    }
+}
@@ -0,0 +1,461 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`windows_service::define_windows_service!`**: Generates the
+  `extern "system"` entry point that the service control manager calls on
+  a thread of its own, while `service_dispatcher::start` blocks `main`
+- **`tokio::sync::watch`**: A stop request is a value that changes once;
+  every `stop_requested` future sees it, even one created afterwards
+- **`OnceLock`**: The controller of the running service, found by
+  `current()` from the watch loop instead of being threaded through `run`
+
+**Design decisions**:
+- `Controller` is the control handler minus Windows: it turns controls
+  into state changes and stop requests, and hands each state to a
+  `report` callback. It builds (and is tested) on every platform; only the
+  thin layer around the Windows API at the bottom does not
+- Stop and Shutdown take the same graceful path as Ctrl+C: `watch` selects
+  on `stop_requested` next to its signals, so the guards (locks, pid file,
+  audit log) clean up and the shutdown event goes out
+- Running is reported once every watcher made its first load attempt, as
+  `READY=1` is under systemd; until then the state is StartPending, with
+  Stop already accepted
+- A watch that ends with code 0 stops with `NO_ERROR`; any other code of
+  `exit` becomes the service-specific exit code, so `sc query` tells why
+  the service stopped. A Windows error before the watch starts (its
+  working directory is gone) is reported as that error
+- There is no console: events and diagnostics go to the `--log-file`,
+  which `service install` requires
+
+******************************************************************************/
+
+use crate::output::Event;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+#[cfg(windows)]
+use std::time::Duration;
+
+/// How long the service control manager waits on a pending state before
+/// it considers the service hung
+#[cfg(windows)]
+pub const WAIT_HINT: Duration = Duration::from_secs(30);
+
+/// What the service control manager asks of the service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Stop,
+    /// The system is shutting down
+    Shutdown,
+    /// Report the current state again
+    Interrogate,
+    /// Anything else (pause, parameter change...), not implemented
+    Other,
+}
+
+/// The state reported to the service control manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    StartPending,
+    Running,
+    StopPending,
+    Stopped(ExitStatus),
+}
+
+impl State {
+    /// Whether Stop and Shutdown are accepted in this state
+    pub fn accepts_stop(self) -> bool {
+        matches!(self, Self::StartPending | Self::Running)
+    }
+}
+
+/// How the service ended, as the service control manager records it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// A Windows error code; 0 (`NO_ERROR`) for success
+    Win32(u32),
+    /// An exit code of `exit`, under `ERROR_SERVICE_SPECIFIC_ERROR`
+    ServiceSpecific(u32),
+}
+
+impl ExitStatus {
+    /// The status of a watch that ended with the exit `code`
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            crate::exit::SUCCESS => Self::Win32(0),
+            code => Self::ServiceSpecific(code.into()),
+        }
+    }
+
+    /// The status of a Windows error before the watch started; the
+    /// `exit::INTERNAL` code when it carries no Windows error code
+    pub fn from_io(error: &std::io::Error) -> Self {
+        match error.raw_os_error() {
+            Some(code) => Self::Win32(code as u32),
+            None => Self::from_code(crate::exit::INTERNAL),
+        }
+    }
+}
+
+/// The control handler of the service, shared with the watch loop
+#[derive(Clone)]
+pub struct Controller {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    report: Box<dyn Fn(State) + Send + Sync>,
+    service: Mutex<Service>,
+    stop: tokio::sync::watch::Sender<bool>,
+}
+
+struct Service {
+    state: State,
+    /// Watchers that must make a load attempt before Running
+    watchers: usize,
+    attempted: HashSet<PathBuf>,
+}
+
+impl fmt::Debug for Controller {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Controller")
+            .field("state", &self.state())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Controller {
+    /// Starts in StartPending, for one watcher; every state change goes
+    /// to `report`
+    pub fn new(report: impl Fn(State) + Send + Sync + 'static) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                report: Box::new(report),
+                service: Mutex::new(Service {
+                    state: State::StartPending,
+                    watchers: 1,
+                    attempted: HashSet::new(),
+                }),
+                stop: tokio::sync::watch::Sender::new(false),
+            }),
+        }
+    }
+
+    /// The current state
+    pub fn state(&self) -> State {
+        self.service().state
+    }
+
+    /// Reports the current state again
+    pub fn announce(&self) {
+        let service = self.service();
+        (self.inner.report)(service.state);
+    }
+
+    /// Waits for the first load attempt of `watchers` watchers before
+    /// reporting Running
+    pub fn expect(&self, watchers: usize) {
+        self.service().watchers = watchers;
+    }
+
+    /// Handles one control; false when it is not implemented
+    pub fn handle(&self, control: Control) -> bool {
+        match control {
+            Control::Stop | Control::Shutdown => {
+                let mut service = self.service();
+                if service.state.accepts_stop() {
+                    self.set(&mut service, State::StopPending);
+                    self.inner.stop.send_replace(true);
+                }
+                true
+            }
+            Control::Interrogate => {
+                self.announce();
+                true
+            }
+            Control::Other => false,
+        }
+    }
+
+    /// Resolves once Stop or Shutdown was received, even before the call
+    pub async fn stop_requested(&self) {
+        let mut stop = self.inner.stop.subscribe();
+        // The sender lives as long as `self`
+        let _ = stop.wait_for(|&requested| requested).await;
+    }
+
+    /// Reports Running once every watcher made its first load attempt
+    pub fn record(&self, event: &Event<'_>) {
+        let file = match *event {
+            Event::Loaded { file, .. }
+            | Event::Unchanged { file, .. }
+            | Event::LoadFailed { file, .. } => file,
+            _ => return,
+        };
+        let mut service = self.service();
+        service.attempted.insert(file.to_path_buf());
+        if service.state == State::StartPending && service.attempted.len() >= service.watchers {
+            self.set(&mut service, State::Running);
+        }
+    }
+
+    /// Reports that the service stopped, with `status`
+    pub fn stopped(&self, status: ExitStatus) {
+        let mut service = self.service();
+        self.set(&mut service, State::Stopped(status));
+    }
+
+    fn set(&self, service: &mut Service, state: State) {
+        service.state = state;
+        (self.inner.report)(state);
+    }
+
+    fn service(&self) -> std::sync::MutexGuard<'_, Service> {
+        self.inner.service.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The controller of the service this process runs, if it runs one
+static CURRENT: OnceLock<Controller> = OnceLock::new();
+
+/// The controller, when the service control manager started the watch
+pub fn current() -> Option<&'static Controller> {
+    CURRENT.get()
+}
+
+/// The work of the service, with its name, until the service control
+/// manager calls it back
+#[cfg(windows)]
+type Body = (String, Box<dyn FnOnce() -> ExitStatus + Send>);
+
+#[cfg(windows)]
+static BODY: Mutex<Option<Body>> = Mutex::new(None);
+
+/// Runs `body` as the service `name`, on the thread the service control
+/// manager starts; returns once the service stopped
+///
+/// Fails at once when the process was not started by the service control
+/// manager.
+#[cfg(windows)]
+pub fn dispatch(
+    name: &str,
+    body: impl FnOnce() -> ExitStatus + Send + 'static,
+) -> windows_service::Result<()> {
+    *BODY.lock().unwrap_or_else(|e| e.into_inner()) = Some((name.to_string(), Box::new(body)));
+    windows_service::service_dispatcher::start(name, ffi_service_main)
+}
+
+#[cfg(windows)]
+windows_service::define_windows_service!(ffi_service_main, service_main);
+
+/// Called back by the service control manager, on a thread of its own
+#[cfg(windows)]
+fn service_main(_: Vec<std::ffi::OsString>) {
+    use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+
+    let Some((name, body)) = BODY.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        return;
+    };
+    // Reports are dropped until the handler is registered
+    let handle = Arc::new(OnceLock::new());
+    let controller = Controller::new({
+        let handle = Arc::clone(&handle);
+        move |state| {
+            if let Some(handle) = handle.get()
+                && let Err(e) = service_control_handler::ServiceStatusHandle::set_service_status(
+                    handle,
+                    service_status(state),
+                )
+            {
+                tracing::warn!(?state, error = %e, "service status not reported");
+            }
+        }
+    });
+    let handler = {
+        let controller = controller.clone();
+        move |control| match controller.handle(from_control(control)) {
+            true => ServiceControlHandlerResult::NoError,
+            false => ServiceControlHandlerResult::NotImplemented,
+        }
+    };
+    match service_control_handler::register(&name, handler) {
+        Ok(registered) => {
+            let _ = handle.set(registered);
+        }
+        // Nothing to report to: the service control manager sees the
+        // service exit before it started
+        Err(_) => return,
+    }
+    let _ = CURRENT.set(controller.clone());
+    controller.announce();
+    let status = body();
+    controller.stopped(status);
+}
+
+#[cfg(windows)]
+fn from_control(control: windows_service::service::ServiceControl) -> Control {
+    use windows_service::service::ServiceControl;
+    match control {
+        ServiceControl::Stop => Control::Stop,
+        ServiceControl::Shutdown => Control::Shutdown,
+        ServiceControl::Interrogate => Control::Interrogate,
+        _ => Control::Other,
+    }
+}
+
+#[cfg(windows)]
+fn service_status(state: State) -> windows_service::service::ServiceStatus {
+    use windows_service::service::{
+        ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus, ServiceType,
+    };
+    let (current_state, exit_code) = match state {
+        State::StartPending => (ServiceState::StartPending, ServiceExitCode::NO_ERROR),
+        State::Running => (ServiceState::Running, ServiceExitCode::NO_ERROR),
+        State::StopPending => (ServiceState::StopPending, ServiceExitCode::NO_ERROR),
+        State::Stopped(ExitStatus::Win32(code)) => {
+            (ServiceState::Stopped, ServiceExitCode::Win32(code))
+        }
+        State::Stopped(ExitStatus::ServiceSpecific(code)) => (
+            ServiceState::Stopped,
+            ServiceExitCode::ServiceSpecific(code),
+        ),
+    };
+    let pending = matches!(state, State::StartPending | State::StopPending);
+    ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state,
+        controls_accepted: if state.accepts_stop() {
+            ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+        } else {
+            ServiceControlAccept::empty()
+        },
+        exit_code,
+        checkpoint: 0,
+        wait_hint: if pending { WAIT_HINT } else { Duration::ZERO },
+        process_id: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::output::Summary;
+    use crate::overrides::Overrides;
+    use std::path::Path;
+    use std::time::Duration;
+
+    /// A controller and the states it reported
+    fn controller() -> (Controller, Arc<Mutex<Vec<State>>>) {
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let controller = Controller::new({
+            let reported = Arc::clone(&reported);
+            move |state| reported.lock().unwrap().push(state)
+        });
+        (controller, reported)
+    }
+
+    fn unchanged(file: &Path) -> Event<'_> {
+        Event::Unchanged { file, version: 1 }
+    }
+
+    #[tokio::test]
+    async fn test_stop_request_takes_the_graceful_path() {
+        let (controller, reported) = controller();
+        controller.announce();
+        controller.record(&unchanged(Path::new("app.json")));
+
+        // What `watch` does: its loop raced against the stop request
+        let watch_loop = std::future::pending::<()>();
+        let stop = {
+            let controller = controller.clone();
+            tokio::spawn(async move { controller.handle(Control::Stop) })
+        };
+        let stopped = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::select! {
+                _ = watch_loop => "watch loop ended",
+                _ = controller.stop_requested() => "graceful shutdown",
+            }
+        })
+        .await;
+        assert_eq!(stopped, Ok("graceful shutdown"));
+        assert!(stop.await.unwrap());
+
+        // Reported once the shutdown completed, with the exit code
+        controller.stopped(ExitStatus::from_code(crate::exit::SUCCESS));
+        assert_eq!(
+            *reported.lock().unwrap(),
+            [
+                State::StartPending,
+                State::Running,
+                State::StopPending,
+                State::Stopped(ExitStatus::Win32(0)),
+            ]
+        );
+        // A stop request made before waiting is not missed
+        tokio::time::timeout(Duration::from_secs(5), controller.stop_requested())
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_running_after_every_first_load_attempt() {
+        let (controller, reported) = controller();
+        controller.expect(2);
+        controller.record(&Event::ChangeDetected {
+            file: Path::new("a.json"),
+        });
+        controller.record(&unchanged(Path::new("a.json")));
+        controller.record(&unchanged(Path::new("a.json")));
+        assert_eq!(controller.state(), State::StartPending);
+
+        let config: AppConfig =
+            serde_json::from_value(serde_json::json!({ "app_name": "App", "version": "1.0.0" }))
+                .unwrap();
+        controller.record(&Event::Loaded {
+            file: Path::new("b.json"),
+            version: 1,
+            initial: true,
+            summary: Summary {
+                config: &config,
+                overrides: &Overrides::default(),
+            },
+            changes: None,
+            flags: None,
+            stand_in: None,
+        });
+        assert_eq!(controller.state(), State::Running);
+        assert_eq!(*reported.lock().unwrap(), [State::Running]);
+    }
+
+    #[test]
+    fn test_stop_while_starting_and_other_controls() {
+        let (controller, reported) = controller();
+        assert!(!controller.handle(Control::Other));
+        assert!(controller.handle(Control::Shutdown));
+        assert_eq!(controller.state(), State::StopPending);
+        // Neither a second request nor a late first load change it
+        assert!(controller.handle(Control::Stop));
+        controller.record(&unchanged(Path::new("app.json")));
+        assert!(controller.handle(Control::Interrogate));
+        assert_eq!(
+            *reported.lock().unwrap(),
+            [State::StopPending, State::StopPending]
+        );
+        assert!(!State::StopPending.accepts_stop());
+    }
+
+    #[test]
+    fn test_exit_status() {
+        assert_eq!(ExitStatus::from_code(0), ExitStatus::Win32(0));
+        assert_eq!(
+            ExitStatus::from_code(crate::exit::FAIL_FAST),
+            ExitStatus::ServiceSpecific(6)
+        );
+        let denied = std::io::Error::from_raw_os_error(5);
+        assert_eq!(ExitStatus::from_io(&denied), ExitStatus::Win32(5));
+        let other = std::io::Error::other("no code");
+        assert_eq!(ExitStatus::from_io(&other), ExitStatus::ServiceSpecific(7));
+    }
+}
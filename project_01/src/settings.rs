@@ -0,0 +1,335 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`toml`**: The settings file is TOML, read through serde like the JSON
+  configuration
+- **`#[serde(flatten)]` into a map**: Collects the keys no field claimed, so
+  they can be reported instead of silently ignored
+- **`ArgMatches::value_source`**: Tells a value typed on the command line or
+  taken from the environment apart from a clap default
+
+**Design decisions**:
+- These are the watcher's own options, not the watched `AppConfig`: hence a
+  separate module, a separate format (TOML) and a separate file name
+- `./.config-watcher.toml` is looked up first, then
+  `$XDG_CONFIG_HOME/config-watcher/config.toml`; the first one found is used
+- Keys mirror the long flags (`interval`, `error-format`...). Precedence is
+  flag > `CONFIG_WATCHER_*` variable > settings file > built-in default:
+  settings only fill options whose value clap took from its defaults
+- Unknown keys are warnings, so an older binary still accepts a newer file;
+  a relative `schema` is resolved against the settings file's directory
+
+******************************************************************************/
+
+use crate::cli::{ColorChoice, ErrorFormat, OutputFormat, WatchArgs};
+use anyhow::Context;
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Name of the settings file in the working directory
+pub const LOCAL_FILE: &str = ".config-watcher.toml";
+
+/// Name of the settings file under the XDG config directory
+pub const USER_FILE: &str = "config.toml";
+
+/// Watcher options read from a settings file; every key is optional
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    pub interval: Option<u64>,
+    pub output: Option<OutputFormat>,
+    pub color: Option<ColorChoice>,
+    pub error_format: Option<ErrorFormat>,
+    pub fail_fast: Option<bool>,
+    /// Humantime string, e.g. `"30m"`
+    pub max_duration: Option<String>,
+    pub max_duration_exit_code: Option<u8>,
+    /// Humantime string, e.g. `"10m"`; `"0s"` disables it
+    pub heartbeat: Option<String>,
+    pub schema: Option<PathBuf>,
+    pub reread_env: Option<bool>,
+
+    /// Keys no field claimed
+    #[serde(flatten, skip_serializing)]
+    unknown: BTreeMap<String, toml::Value>,
+}
+
+/// Where an effective setting comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    Default,
+    SettingsFile,
+    Environment,
+    CommandLine,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => write!(f, "default"),
+            Origin::SettingsFile => write!(f, "settings file"),
+            Origin::Environment => write!(f, "environment"),
+            Origin::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+/// Origin of each setting, in display order
+pub type Origins = Vec<(&'static str, Origin)>;
+
+impl Settings {
+    /// Parses a settings document; `base_dir` resolves a relative `schema`
+    ///
+    /// Returns the settings and one warning per unknown key.
+    pub fn parse(text: &str, base_dir: &Path) -> anyhow::Result<(Self, Vec<String>)> {
+        let mut settings: Settings = toml::from_str(text)?;
+        if let Some(ref schema) = settings.schema {
+            settings.schema = Some(base_dir.join(schema));
+        }
+        let warnings = std::mem::take(&mut settings.unknown)
+            .into_keys()
+            .map(|key| format!("unknown key '{key}' in settings file, ignored"))
+            .collect();
+        Ok((settings, warnings))
+    }
+
+    /// Reads and parses the settings file at `path`
+    pub fn load(path: &Path) -> anyhow::Result<(Self, Vec<String>)> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read settings file {}", path.display()))?;
+        Self::parse(&text, path.parent().unwrap_or(Path::new("")))
+            .with_context(|| format!("Invalid settings file {}", path.display()))
+    }
+
+    /// Fills the options of `args` that neither a flag nor the environment set
+    ///
+    /// `matches` are the ones `args` was parsed from. Returns the origin of
+    /// every setting.
+    pub fn apply(&self, args: &mut WatchArgs, matches: &ArgMatches) -> anyhow::Result<Origins> {
+        let given = |id: &str| match matches.value_source(id) {
+            Some(ValueSource::CommandLine) => Some(Origin::CommandLine),
+            Some(ValueSource::EnvVariable) => Some(Origin::Environment),
+            _ => None,
+        };
+        let mut origins = Vec::new();
+        let mut merge = |key: &'static str, id: &str, from_file: bool| {
+            let origin = match given(id) {
+                Some(origin) => origin,
+                None if from_file => Origin::SettingsFile,
+                None => Origin::Default,
+            };
+            origins.push((key, origin));
+            origin == Origin::SettingsFile
+        };
+
+        if merge("interval", "interval", self.interval.is_some()) {
+            args.interval = self.interval;
+        }
+        if merge("output", "output", self.output.is_some()) {
+            args.output = self.output.unwrap_or_default();
+        }
+        if merge("color", "color", self.color.is_some()) {
+            args.color = self.color.unwrap_or_default();
+        }
+        if merge("error-format", "error_format", self.error_format.is_some()) {
+            args.error_format = self.error_format.unwrap_or_default();
+        }
+        if merge("fail-fast", "fail_fast", self.fail_fast.is_some()) {
+            args.fail_fast = self.fail_fast.unwrap_or_default();
+        }
+        if merge("max-duration", "max_duration", self.max_duration.is_some())
+            && let Some(ref text) = self.max_duration
+        {
+            let duration = humantime::parse_duration(text)
+                .with_context(|| format!("Invalid max-duration '{text}' in settings file"))?;
+            args.max_duration = Some(duration);
+        }
+        if merge(
+            "max-duration-exit-code",
+            "max_duration_exit_code",
+            self.max_duration_exit_code.is_some(),
+        ) {
+            args.max_duration_exit_code = self.max_duration_exit_code.unwrap_or_default();
+        }
+        if merge("heartbeat", "heartbeat", self.heartbeat.is_some())
+            && let Some(ref text) = self.heartbeat
+        {
+            let duration = humantime::parse_duration(text)
+                .with_context(|| format!("Invalid heartbeat '{text}' in settings file"))?;
+            args.heartbeat = Some(duration);
+        }
+        if merge("schema", "schema", self.schema.is_some()) {
+            args.schema = self.schema.clone();
+        }
+        if merge("reread-env", "reread_env", self.reread_env.is_some()) {
+            args.reread_env = self.reread_env.unwrap_or_default();
+        }
+        Ok(origins)
+    }
+}
+
+/// The settings file to use: `./.config-watcher.toml`, then the user one
+pub fn locate(
+    cwd: &Path,
+    xdg_config_home: Option<&Path>,
+    is_file: impl Fn(&Path) -> bool,
+) -> Option<PathBuf> {
+    let mut candidates = vec![cwd.join(LOCAL_FILE)];
+    if let Some(xdg) = xdg_config_home {
+        candidates.push(xdg.join(crate::discovery::APP_DIR).join(USER_FILE));
+    }
+    candidates.into_iter().find(|path| is_file(path))
+}
+
+/// `locate` against the real file system and environment
+pub fn locate_default() -> Option<PathBuf> {
+    locate(
+        Path::new("."),
+        crate::discovery::xdg_config_home().as_deref(),
+        Path::is_file,
+    )
+}
+
+/// One effective setting
+#[derive(Debug, Clone, PartialEq)]
+pub struct Effective {
+    pub key: &'static str,
+    /// None when it is not set
+    pub value: Option<toml::Value>,
+    pub origin: Origin,
+}
+
+/// The value and origin of every setting, in display order
+pub fn effective(args: &WatchArgs, origins: &Origins) -> Vec<Effective> {
+    let value = |key: &str| -> Option<toml::Value> {
+        let string = |s: String| Some(toml::Value::String(s));
+        match key {
+            "interval" => Some(toml::Value::Integer(args.interval() as i64)),
+            "output" => toml::Value::try_from(args.output).ok(),
+            "color" => toml::Value::try_from(args.color).ok(),
+            "error-format" => toml::Value::try_from(args.error_format).ok(),
+            "fail-fast" => Some(toml::Value::Boolean(args.fail_fast)),
+            "max-duration" => args
+                .max_duration
+                .and_then(|d| string(humantime::format_duration(d).to_string())),
+            "max-duration-exit-code" => {
+                Some(toml::Value::Integer(args.max_duration_exit_code.into()))
+            }
+            "heartbeat" => args
+                .heartbeat
+                .and_then(|d| string(humantime::format_duration(d).to_string())),
+            "schema" => args
+                .schema
+                .as_ref()
+                .and_then(|path| string(path.display().to_string())),
+            "reread-env" => Some(toml::Value::Boolean(args.reread_env)),
+            _ => None,
+        }
+    };
+    origins
+        .iter()
+        .map(|&(key, origin)| Effective {
+            key,
+            value: value(key),
+            origin,
+        })
+        .collect()
+}
+
+/// The effective settings as TOML, each line commented with its origin
+pub fn render(args: &WatchArgs, origins: &Origins, file: Option<&Path>) -> String {
+    let mut out = match file {
+        Some(path) => format!("# settings file: {}\n", path.display()),
+        None => "# settings file: none\n".to_string(),
+    };
+    for setting in effective(args, origins) {
+        let key = setting.key;
+        match setting.value {
+            Some(value) => out.push_str(&format!("{key} = {value}  # {}\n", setting.origin)),
+            None => out.push_str(&format!("# {key} is not set\n")),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::{CommandFactory, FromArgMatches};
+
+    fn parse(args: &[&str]) -> (WatchArgs, ArgMatches) {
+        let matches = Cli::command()
+            .try_get_matches_from(std::iter::once("config-watcher").chain(args.iter().copied()))
+            .unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        (cli.watch, matches)
+    }
+
+    #[test]
+    fn test_parse_and_unknown_keys() {
+        let text = r#"
+            interval = 5
+            output = "json"
+            schema = "schemas/app.json"
+            colour = "never"
+            notify = { slack = "x" }
+        "#;
+        let (settings, warnings) = Settings::parse(text, Path::new("/etc/cw")).unwrap();
+        assert_eq!(settings.interval, Some(5));
+        assert_eq!(settings.output, Some(OutputFormat::Json));
+        assert_eq!(
+            settings.schema,
+            Some(PathBuf::from("/etc/cw/schemas/app.json"))
+        );
+        assert_eq!(
+            warnings,
+            [
+                "unknown key 'colour' in settings file, ignored",
+                "unknown key 'notify' in settings file, ignored"
+            ]
+        );
+
+        assert!(Settings::parse("interval = \"soon\"", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_flags_win_over_the_settings_file() {
+        let (settings, _) = Settings::parse(
+            "interval = 5\ncolor = \"never\"\nfail-fast = true\nmax-duration = \"1m\"",
+            Path::new(""),
+        )
+        .unwrap();
+
+        let (mut args, matches) = parse(&["--interval", "9", "-f", "a.json"]);
+        let origins = settings.apply(&mut args, &matches).unwrap();
+
+        assert_eq!(args.interval, Some(9));
+        assert_eq!(args.color, ColorChoice::Never);
+        assert!(args.fail_fast);
+        assert_eq!(args.max_duration, Some(std::time::Duration::from_secs(60)));
+        assert_eq!(args.output, OutputFormat::Text);
+
+        let origin = |key| origins.iter().find(|(k, _)| *k == key).unwrap().1;
+        assert_eq!(origin("interval"), Origin::CommandLine);
+        assert_eq!(origin("color"), Origin::SettingsFile);
+        assert_eq!(origin("output"), Origin::Default);
+    }
+
+    #[test]
+    fn test_locate_prefers_the_local_file() {
+        let local = PathBuf::from("/w/.config-watcher.toml");
+        let user = PathBuf::from("/home/u/.config/config-watcher/config.toml");
+        let xdg = Some(Path::new("/home/u/.config"));
+
+        let found = locate(Path::new("/w"), xdg, |p| p == local || p == user);
+        assert_eq!(found, Some(local));
+        let found = locate(Path::new("/w"), xdg, |p| p == user);
+        assert_eq!(found, Some(user));
+        assert_eq!(locate(Path::new("/w"), xdg, |_| false), None);
+    }
+}
@@ -0,0 +1,177 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`mpsc::Receiver::recv_timeout`**: The watchdog thread waits for the
+  deadline, a `Force`, or the `Deadline` being dropped, whichever comes
+  first
+- **`Drop` order**: Locals are dropped in reverse order of declaration, so
+  a `Deadline` declared first in `watch` outlives every other guard
+
+**Design decisions**:
+- The deadline covers the cleanup only: it starts when the watch stops
+  (signal, --max-duration, --fail-fast, the command of `run` exiting) and
+  ends once the last guard of `watch` (audit log, event history, pid file,
+  locks) is dropped
+- The cleanup names the stage it is in; past the deadline the watchdog
+  gets that stage, to report what was abandoned before the process exits
+  without finishing it
+- A thread, not a task: the stuck stage may be a blocking one (joining the
+  notifier thread) that a timer on the runtime would never interrupt
+- A second Ctrl+C or SIGTERM goes through the same watchdog (`Force`): the
+  cleanup is abandoned at once rather than at the deadline, with the same
+  report of the stage under way
+
+******************************************************************************/
+
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Default of --shutdown-timeout
+pub const DEFAULT_TIMEOUT: &str = "10s";
+
+/// Why the cleanup was abandoned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Abandoned {
+    /// It outlasted the timeout
+    TimedOut,
+    /// `Force::now` was called
+    Forced,
+}
+
+/// What the watchdog thread is told
+#[derive(Debug)]
+enum Message {
+    /// The cleanup finished
+    Done,
+    /// Abandon it now
+    Force,
+}
+
+/// How long the cleanup may take once the watch stopped; cancelled when
+/// dropped
+#[derive(Debug)]
+pub struct Deadline {
+    timeout: Duration,
+    stage: Arc<Mutex<&'static str>>,
+    /// To the watchdog, once started
+    watchdog: Option<mpsc::Sender<Message>>,
+}
+
+/// Abandons the cleanup without waiting for the deadline
+#[derive(Debug, Clone)]
+pub struct Force(Option<mpsc::Sender<Message>>);
+
+impl Force {
+    /// Hands the stage under way to the `expired` callback of the
+    /// deadline, as `Abandoned::Forced`; nothing once the cleanup finished
+    pub fn now(&self) {
+        if let Some(ref watchdog) = self.0 {
+            let _ = watchdog.send(Message::Force);
+        }
+    }
+}
+
+impl Deadline {
+    /// A deadline of `timeout`, not counting yet
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            stage: Arc::new(Mutex::new("shutting down")),
+            watchdog: None,
+        }
+    }
+
+    /// Starts counting: past the timeout, or once the returned `Force` is
+    /// used, `expired` gets why and the stage under way, on a thread of its
+    /// own. Started once; later calls only return another `Force`
+    pub fn start(
+        &mut self,
+        expired: impl FnOnce(Abandoned, &'static str) + Send + 'static,
+    ) -> Force {
+        if self.watchdog.is_some() {
+            return Force(self.watchdog.clone());
+        }
+        let (watchdog, messages) = mpsc::channel();
+        let stage = Arc::clone(&self.stage);
+        let timeout = self.timeout;
+        let started = std::thread::Builder::new()
+            .name("shutdown-deadline".into())
+            .spawn(move || {
+                let why = match messages.recv_timeout(timeout) {
+                    Ok(Message::Done) | Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                    Ok(Message::Force) => Abandoned::Forced,
+                    Err(mpsc::RecvTimeoutError::Timeout) => Abandoned::TimedOut,
+                };
+                let stage = *stage.lock().unwrap_or_else(|e| e.into_inner());
+                expired(why, stage);
+            });
+        match started {
+            Ok(_) => self.watchdog = Some(watchdog),
+            // Without the watchdog the cleanup just runs to the end
+            Err(e) => tracing::warn!(error = %e, "shutdown deadline not enforced"),
+        }
+        Force(self.watchdog.clone())
+    }
+
+    /// Records the stage the cleanup enters, reported if it never ends
+    pub fn stage(&self, what: &'static str) {
+        *self.stage.lock().unwrap_or_else(|e| e.into_inner()) = what;
+    }
+}
+
+impl Drop for Deadline {
+    /// The cleanup finished: `Force` handles still around do nothing
+    fn drop(&mut self) {
+        if let Some(ref watchdog) = self.watchdog {
+            let _ = watchdog.send(Message::Done);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expires_with_the_stage_under_way() {
+        let (expired, stages) = mpsc::channel();
+        let mut deadline = Deadline::new(Duration::from_millis(50));
+        deadline.start(move |why, stage| expired.send((why, stage)).unwrap());
+        deadline.stage("draining the notifier queue");
+        assert_eq!(
+            stages.recv_timeout(Duration::from_secs(5)),
+            Ok((Abandoned::TimedOut, "draining the notifier queue"))
+        );
+    }
+
+    #[test]
+    fn test_forced_before_the_deadline() {
+        let (expired, stages) = mpsc::channel();
+        let mut deadline = Deadline::new(Duration::from_secs(60));
+        let force = deadline.start(move |why, stage| expired.send((why, stage)).unwrap());
+        deadline.stage("closing the HTTP servers");
+        force.now();
+        assert_eq!(
+            stages.recv_timeout(Duration::from_secs(5)),
+            Ok((Abandoned::Forced, "closing the HTTP servers"))
+        );
+    }
+
+    #[test]
+    fn test_cancelled_when_dropped_in_time() {
+        let (expired, stages) = mpsc::channel();
+        let mut deadline = Deadline::new(Duration::from_millis(200));
+        let force = deadline.start({
+            let expired = expired.clone();
+            move |why, stage| expired.send((why, stage)).unwrap()
+        });
+        drop(deadline);
+        // Too late once the cleanup finished
+        force.now();
+        assert_eq!(
+            stages.recv_timeout(Duration::from_millis(500)),
+            Err(mpsc::RecvTimeoutError::Timeout)
+        );
+    }
+}
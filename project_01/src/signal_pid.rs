@@ -0,0 +1,429 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`rustix::process::kill_process`**: `kill(2)` without `unsafe`; its
+  `Errno` tells a process that is gone (`ESRCH`) from one we may not
+  signal (`EPERM`), which the exit status of `kill(1)` does not
+- **`impl Notifier`**: Signalling runs on the dispatcher, with a queue of
+  one, so reloads arriving while it waits collapse into one signal
+- **`#[cfg(unix)]`**: Other platforms have no signals; `main` refuses the
+  flags there before anything starts
+
+**Design decisions**:
+- `--signal-pid PID` or `--signal-pidfile FILE` sends `--signal` (SIGHUP
+  by default, the reload signal of nginx, haproxy and most daemons) after
+  every accepted reload; not after the initial load, which the daemon
+  read itself when it started
+- The pidfile is read again at every reload: daemons restart and change
+  pid. A missing, empty or garbled pidfile is a failure of that reload's
+  signal, not of the watcher
+- Sending is the liveness check: a pid that is not running fails with "no
+  process", one owned by another user with "not allowed". Pid 0 and
+  values beyond `i32` (process groups, `-1`) are refused as invalid
+- The outcome is an event (`signal`), like `--on-change`'s; a failure is
+  a warning and never touches the configuration state
+- At most one signal per `--signal-min-interval` (1s by default): a burst
+  of reloads gives a signal now and one for the latest configuration when
+  the interval is over, never a storm, and the last change is never lost
+
+******************************************************************************/
+
+use crate::cli::EventKind;
+use crate::notify::{self, ConfigEvent, Notifier, NotifierOptions};
+use crate::output::{Emitter, Event};
+use anyhow::bail;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Default `--signal-min-interval`
+pub const DEFAULT_MIN_INTERVAL: &str = "1s";
+
+/// The signals worth sending to a daemon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Signal {
+    #[default]
+    Hup,
+    Int,
+    Quit,
+    Term,
+    Usr1,
+    Usr2,
+    Winch,
+}
+
+impl Signal {
+    const ALL: [Signal; 7] = [
+        Signal::Hup,
+        Signal::Int,
+        Signal::Quit,
+        Signal::Term,
+        Signal::Usr1,
+        Signal::Usr2,
+        Signal::Winch,
+    ];
+
+    /// The name without its `SIG` prefix
+    fn short_name(self) -> &'static str {
+        match self {
+            Signal::Hup => "HUP",
+            Signal::Int => "INT",
+            Signal::Quit => "QUIT",
+            Signal::Term => "TERM",
+            Signal::Usr1 => "USR1",
+            Signal::Usr2 => "USR2",
+            Signal::Winch => "WINCH",
+        }
+    }
+
+    #[cfg(unix)]
+    fn to_rustix(self) -> rustix::process::Signal {
+        use rustix::process::Signal as S;
+        match self {
+            Signal::Hup => S::HUP,
+            Signal::Int => S::INT,
+            Signal::Quit => S::QUIT,
+            Signal::Term => S::TERM,
+            Signal::Usr1 => S::USR1,
+            Signal::Usr2 => S::USR2,
+            Signal::Winch => S::WINCH,
+        }
+    }
+}
+
+/// `SIGHUP`, `HUP` or `hup`
+impl FromStr for Signal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_ascii_uppercase();
+        let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+        Signal::ALL
+            .into_iter()
+            .find(|signal| signal.short_name() == name)
+            .ok_or_else(|| {
+                let known: Vec<_> = Signal::ALL.iter().map(ToString::to_string).collect();
+                format!("unknown signal '{s}' (expected {})", known.join(", "))
+            })
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SIG{}", self.short_name())
+    }
+}
+
+/// The process to signal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Pid(u32),
+    /// Read at every reload
+    Pidfile(PathBuf),
+}
+
+impl Target {
+    /// The process id, read from the pidfile if there is one
+    pub fn resolve(&self) -> Result<u32, SignalError> {
+        match self {
+            Target::Pid(pid) => Ok(*pid),
+            Target::Pidfile(path) => {
+                let text = std::fs::read_to_string(path).map_err(|e| SignalError::Pidfile {
+                    path: path.clone(),
+                    error: e.to_string(),
+                })?;
+                text.trim()
+                    .parse()
+                    .map_err(|_| SignalError::InvalidPid(text.trim().to_string()))
+            }
+        }
+    }
+}
+
+/// Why a signal was not delivered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignalError {
+    /// The pidfile cannot be read
+    Pidfile {
+        path: PathBuf,
+        error: String,
+    },
+    /// Not a process id, or one that names a group
+    InvalidPid(String),
+    NoSuchProcess(u32),
+    PermissionDenied(u32),
+    Other {
+        pid: u32,
+        error: String,
+    },
+}
+
+impl SignalError {
+    /// `pidfile`, `invalid_pid`, `no_such_process`, `permission_denied` or
+    /// `error`, for JSON
+    pub fn name(&self) -> &'static str {
+        match self {
+            SignalError::Pidfile { .. } => "pidfile",
+            SignalError::InvalidPid(_) => "invalid_pid",
+            SignalError::NoSuchProcess(_) => "no_such_process",
+            SignalError::PermissionDenied(_) => "permission_denied",
+            SignalError::Other { .. } => "error",
+        }
+    }
+}
+
+impl fmt::Display for SignalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignalError::Pidfile { path, error } => {
+                write!(f, "cannot read pidfile {}: {error}", path.display())
+            }
+            SignalError::InvalidPid(text) => write!(f, "'{text}' is not a process id"),
+            SignalError::NoSuchProcess(pid) => write!(f, "no process {pid}"),
+            SignalError::PermissionDenied(pid) => write!(f, "not allowed to signal process {pid}"),
+            SignalError::Other { pid, error } => write!(f, "cannot signal process {pid}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SignalError {}
+
+/// Sends `signal` to the process `pid`
+#[cfg(unix)]
+pub fn send(pid: u32, signal: Signal) -> Result<(), SignalError> {
+    use rustix::io::Errno;
+    use rustix::process::{Pid, kill_process};
+    let target = i32::try_from(pid)
+        .ok()
+        .and_then(Pid::from_raw)
+        .ok_or_else(|| SignalError::InvalidPid(pid.to_string()))?;
+    kill_process(target, signal.to_rustix()).map_err(|errno| match errno {
+        Errno::SRCH => SignalError::NoSuchProcess(pid),
+        Errno::PERM => SignalError::PermissionDenied(pid),
+        errno => SignalError::Other {
+            pid,
+            error: errno.to_string(),
+        },
+    })
+}
+
+/// What happened to the signal of one reload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalOutcome {
+    pub version: u64,
+    pub signal: Signal,
+    /// The process signalled, when the pidfile gave one
+    pub pid: Option<u32>,
+    pub result: Result<(), SignalError>,
+}
+
+/// Signals a process after every reload
+#[derive(Debug)]
+pub struct SignalNotifier {
+    target: Target,
+    signal: Signal,
+    min_interval: Duration,
+    emitter: Emitter,
+    /// When the last signal went out
+    last: Mutex<Option<Instant>>,
+}
+
+impl SignalNotifier {
+    /// Sends `signal` to `target`, reporting to `emitter`
+    pub fn new(target: Target, signal: Signal, emitter: Emitter) -> Self {
+        Self {
+            target,
+            signal,
+            min_interval: humantime::parse_duration(DEFAULT_MIN_INTERVAL).expect("valid default"),
+            emitter,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Leaves at least `interval` between two signals
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = interval;
+        self
+    }
+
+    /// Reads the pid and sends the signal
+    #[cfg(unix)]
+    fn deliver(&self, version: u64) -> SignalOutcome {
+        let pid = self.target.resolve();
+        SignalOutcome {
+            version,
+            signal: self.signal,
+            pid: pid.as_ref().ok().copied(),
+            result: pid.and_then(|pid| send(pid, self.signal)),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn deliver(&self, version: u64) -> SignalOutcome {
+        let pid = self.target.resolve();
+        SignalOutcome {
+            version,
+            signal: self.signal,
+            pid: pid.as_ref().ok().copied(),
+            result: pid.and_then(|pid| {
+                Err(SignalError::Other {
+                    pid,
+                    error: "signals are a Unix feature".to_string(),
+                })
+            }),
+        }
+    }
+}
+
+impl Notifier for SignalNotifier {
+    fn name(&self) -> &str {
+        "signal"
+    }
+
+    /// A queue of one folds the reloads of a burst into one signal; the
+    /// wait for the interval counts in the timeout
+    fn options(&self) -> NotifierOptions {
+        NotifierOptions {
+            timeout: notify::DEFAULT_TIMEOUT + self.min_interval,
+            queue: 1,
+            ..NotifierOptions::default()
+        }
+    }
+
+    fn accepts(&self, event: &ConfigEvent) -> bool {
+        event.kind == EventKind::Reload
+    }
+
+    async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+        let last = *self.last.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(last) = last {
+            tokio::time::sleep_until(last + self.min_interval).await;
+        }
+        let outcome = self.deliver(event.version().unwrap_or_default());
+        *self.last.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+        let file = event.file.as_deref().unwrap_or(Path::new("-"));
+        self.emitter.emit(&Event::Signaled {
+            file,
+            outcome: &outcome,
+        });
+        if let Err(ref e) = outcome.result {
+            bail!("{} not sent: {e}", outcome.signal);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::output::Summary;
+    use crate::overrides::Overrides;
+    use std::process::{Command, Stdio};
+    use std::time::SystemTime;
+
+    fn reload(version: u64) -> ConfigEvent {
+        let config = AppConfig::example();
+        let overrides = Overrides::default();
+        let event = Event::Loaded {
+            file: Path::new("/etc/app.json"),
+            version,
+            initial: false,
+            summary: Summary {
+                config: &config,
+                overrides: &overrides,
+            },
+            changes: Some(&[]),
+            flags: None,
+            stand_in: None,
+        };
+        ConfigEvent::new(&event, SystemTime::UNIX_EPOCH).unwrap()
+    }
+
+    #[test]
+    fn test_signal_names() {
+        assert_eq!("SIGHUP".parse(), Ok(Signal::Hup));
+        assert_eq!("usr1".parse(), Ok(Signal::Usr1));
+        assert_eq!("SigTerm".parse(), Ok(Signal::Term));
+        assert_eq!(Signal::Winch.to_string(), "SIGWINCH");
+        let error = "SIGKILL".parse::<Signal>().unwrap_err();
+        assert!(error.contains("SIGHUP, SIGINT"), "{error}");
+    }
+
+    #[test]
+    fn test_pidfile_is_read_each_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.pid");
+        let target = Target::Pidfile(path.clone());
+        assert!(matches!(target.resolve(), Err(SignalError::Pidfile { .. })));
+        std::fs::write(&path, "1234\n").unwrap();
+        assert_eq!(target.resolve(), Ok(1234));
+        std::fs::write(&path, "4321").unwrap();
+        assert_eq!(target.resolve(), Ok(4321));
+        std::fs::write(&path, "nginx").unwrap();
+        assert_eq!(
+            target.resolve(),
+            Err(SignalError::InvalidPid("nginx".to_string()))
+        );
+        assert_eq!(
+            send(0, Signal::Hup),
+            Err(SignalError::InvalidPid("0".to_string()))
+        );
+        assert_eq!(
+            send(u32::MAX, Signal::Hup),
+            Err(SignalError::InvalidPid(u32::MAX.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_send_reports_a_dead_process() {
+        let mut child = Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        assert_eq!(send(pid, Signal::Hup), Err(SignalError::NoSuchProcess(pid)));
+    }
+
+    #[tokio::test]
+    async fn test_signals_are_spaced_by_the_min_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log");
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(format!(
+                "trap 'echo hup >> {}' HUP; echo ready; while :; do sleep 0.05; done",
+                log.display()
+            ))
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        // The trap is set once the script speaks
+        let mut ready = [0; 6];
+        std::io::Read::read_exact(child.stdout.as_mut().unwrap(), &mut ready).unwrap();
+
+        let notifier = SignalNotifier::new(
+            Target::Pid(child.id()),
+            Signal::Hup,
+            Emitter::default().with_verbosity(crate::output::Verbosity::Quiet),
+        )
+        .with_min_interval(Duration::from_millis(300));
+        let started = Instant::now();
+        notifier.notify(&reload(2)).await.unwrap();
+        notifier.notify(&reload(3)).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        for _ in 0..100 {
+            if std::fs::read_to_string(&log).unwrap_or_default() == "hup\nhup\n" {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "hup\nhup\n");
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        let error = notifier.notify(&reload(4)).await.unwrap_err();
+        assert!(error.to_string().contains("no process"), "{error}");
+    }
+}
@@ -0,0 +1,397 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`[u8; N]::try_from(&[u8])`**: Fixed-size fields (algorithm, key id,
+  signature) are cut out of the decoded blobs with their lengths checked
+- **`Result<_, String>` inside, `ConfigError` outside**: Parsing says
+  what is wrong; the caller adds which file
+
+**Design decisions**:
+- `--verify-signature --public-key FILE` only loads files signed by that
+  key: authenticity, where `--verify-checksum` is integrity only. The
+  check runs on the exact bytes read, before parsing; a failure rejects the
+  load and keeps the last valid configuration
+- Detached signatures sit next to the file: `config.json.minisig`
+  (minisign) first, else `config.json.sig` (the 64 raw bytes of an Ed25519
+  signature of the file, or their base64)
+- minisign: the signature line is checked against the file's BLAKE2b-512
+  digest (`ED`, current minisign) or the file itself (`Ed`, legacy), its
+  key id against the key's, then the global signature over the signature
+  and the trusted comment. The trusted comment is only logged (`-vv`);
+  a tampered comment fails like a tampered signature
+- The key file is a minisign public key (`minisign -G` output, comment
+  line optional), or a raw Ed25519 key: base64 text or 32 bytes. A raw
+  key has no id, so any minisign key id is accepted with it. A key that
+  does not parse fails startup
+- Every load is a single file (this tree has no includes or `extends`);
+  when it gains them, each included file must carry its own signature
+- The companions may be updated after the file or before it: the watcher
+  reads through `fs_util::read_consistent`, which re-reads the pair a few
+  times before a missing or failing signature counts
+- Ed25519 is `ed25519-dalek`, with `verify_strict`: like libsodium,
+  which minisign uses, it also rejects small-order keys and `R` points.
+  BLAKE2b-512 is the `blake2` crate, base64 the `base64` crate's strict
+  standard engine. `sign` and `public_key_file` produce
+  minisign files, for tests and tooling
+
+******************************************************************************/
+
+use crate::error::{ConfigError, Result};
+use crate::fs_util;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use blake2::{Blake2b512, Digest};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// minisign signature, checked first
+pub const MINISIG_SUFFIX: &str = ".minisig";
+
+/// Raw Ed25519 signature, when there is no `.minisig`
+pub const RAW_SUFFIX: &str = ".sig";
+
+/// minisign algorithm of keys and legacy signatures: Ed25519 of the file
+const ALG_ED: &[u8; 2] = b"Ed";
+
+/// minisign algorithm of prehashed signatures: Ed25519 of BLAKE2b-512
+const ALG_ED_PREHASHED: &[u8; 2] = b"ED";
+
+const UNTRUSTED: &str = "untrusted comment:";
+const TRUSTED: &str = "trusted comment: ";
+
+/// The key configurations must be signed with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustedKey {
+    key: VerifyingKey,
+    id: Option<[u8; 8]>,
+}
+
+impl TrustedKey {
+    /// A raw Ed25519 key, without minisign key id
+    pub fn new(key: VerifyingKey) -> Self {
+        Self { key, id: None }
+    }
+
+    /// A minisign key
+    pub fn minisign(key: VerifyingKey, id: [u8; 8]) -> Self {
+        Self { key, id: Some(id) }
+    }
+
+    /// Reads a key file, failing with `InvalidPublicKey`
+    pub fn load(path: &Path) -> Result<Self> {
+        let invalid = |reason: String| ConfigError::InvalidPublicKey {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let bytes = std::fs::read(path).map_err(|e| invalid(e.to_string()))?;
+        Self::parse(&bytes).map_err(invalid)
+    }
+
+    /// A key from the contents of a key file
+    pub fn parse(bytes: &[u8]) -> std::result::Result<Self, String> {
+        let blob = std::str::from_utf8(bytes)
+            .ok()
+            .and_then(|text| {
+                text.lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty() && !line.starts_with(UNTRUSTED))
+            })
+            .and_then(|line| STANDARD.decode(line).ok());
+        match blob.as_deref() {
+            Some(blob) if blob.len() == 42 && blob.starts_with(ALG_ED) => {
+                return Ok(Self::minisign(
+                    public_key(&blob[10..])?,
+                    blob[2..10].try_into().unwrap(),
+                ));
+            }
+            Some(blob) if blob.len() == 42 => {
+                return Err("not an Ed25519 minisign key".to_string());
+            }
+            Some(blob) if blob.len() == 32 => return Ok(Self::new(public_key(blob)?)),
+            _ => {}
+        }
+        if bytes.len() == 32 {
+            return Ok(Self::new(public_key(bytes)?));
+        }
+        Err(
+            "expected a minisign public key, or an Ed25519 key as base64 or 32 raw bytes"
+                .to_string(),
+        )
+    }
+
+    /// The Ed25519 key
+    pub fn key(&self) -> &VerifyingKey {
+        &self.key
+    }
+
+    /// The minisign key id, as minisign prints it
+    pub fn id(&self) -> Option<String> {
+        self.id.map(key_id)
+    }
+}
+
+fn public_key(bytes: &[u8]) -> std::result::Result<VerifyingKey, String> {
+    let bytes: [u8; 32] = bytes.try_into().map_err(|_| "truncated key".to_string())?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| "not an Ed25519 public key".to_string())
+}
+
+/// True when `signature` is `key`'s signature of `message`
+fn verify(key: &VerifyingKey, message: &[u8], signature: &[u8; 64]) -> bool {
+    key.verify_strict(message, &Signature::from_bytes(signature))
+        .is_ok()
+}
+
+/// What `ED` signatures sign
+fn prehash(contents: &[u8]) -> Vec<u8> {
+    Blake2b512::digest(contents).to_vec()
+}
+
+/// minisign prints key ids as the hex of a little-endian u64
+fn key_id(id: [u8; 8]) -> String {
+    format!("{:016X}", u64::from_le_bytes(id))
+}
+
+/// `config.json.minisig`
+pub fn minisig_path(file: &Path) -> PathBuf {
+    fs_util::companion(file, MINISIG_SUFFIX)
+}
+
+/// `config.json.sig`
+pub fn raw_path(file: &Path) -> PathBuf {
+    fs_util::companion(file, RAW_SUFFIX)
+}
+
+/// Checks `contents`, just read from `file`, against its detached
+/// signature as it is now on disk
+pub fn check(file: &Path, contents: &[u8], key: &TrustedKey) -> Result<()> {
+    let invalid = |reason: String| ConfigError::SignatureInvalid {
+        path: file.to_path_buf(),
+        reason,
+    };
+    let read = |path: &Path| match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(invalid(format!("cannot read {}: {e}", path.display()))),
+    };
+    if let Some(minisig) = read(&minisig_path(file))? {
+        let comment = verify_minisign(contents, &minisig, key).map_err(invalid)?;
+        tracing::trace!(file = %file.display(), trusted_comment = comment, "signature verified");
+        return Ok(());
+    }
+    if let Some(raw) = read(&raw_path(file))? {
+        return verify_raw(contents, &raw, key).map_err(invalid);
+    }
+    Err(ConfigError::SignatureMissing {
+        path: file.to_path_buf(),
+    })
+}
+
+/// Verifies a `.minisig`, returning its trusted comment
+pub fn verify_minisign(
+    contents: &[u8],
+    minisig: &[u8],
+    key: &TrustedKey,
+) -> std::result::Result<String, String> {
+    let text = std::str::from_utf8(minisig).map_err(|_| "the .minisig is not text")?;
+    let mut lines = text.lines().map(|line| line.trim_end_matches('\r'));
+    let mut next = || lines.next().ok_or("the .minisig is truncated");
+    if !next()?.starts_with(UNTRUSTED) {
+        return Err("the .minisig does not start with an untrusted comment".to_string());
+    }
+    let blob = STANDARD
+        .decode(next()?.trim())
+        .map_err(|_| "the .minisig signature is not base64")?;
+    let comment = next()?
+        .strip_prefix(TRUSTED)
+        .ok_or("the .minisig has no trusted comment")?
+        .to_string();
+    let global = STANDARD
+        .decode(next()?.trim())
+        .map_err(|_| "the .minisig global signature is not base64")?;
+    let global: [u8; 64] = global
+        .try_into()
+        .map_err(|_| "the .minisig global signature is truncated")?;
+    if blob.len() != 74 {
+        return Err("the .minisig signature is truncated".to_string());
+    }
+
+    let (algorithm, id, signature) = (&blob[..2], &blob[2..10], &blob[10..]);
+    let signature: [u8; 64] = signature.try_into().unwrap();
+    if let Some(expected) = key.id
+        && id != expected
+    {
+        return Err(format!(
+            "signed with key {}, expected key {}",
+            key_id(id.try_into().unwrap()),
+            key_id(expected)
+        ));
+    }
+    let valid = if algorithm == ALG_ED_PREHASHED {
+        verify(&key.key, &prehash(contents), &signature)
+    } else if algorithm == ALG_ED {
+        verify(&key.key, contents, &signature)
+    } else {
+        return Err("unsupported minisign signature algorithm".to_string());
+    };
+    if !valid {
+        return Err("the signature does not match the file".to_string());
+    }
+    let signed_comment = [&signature[..], comment.as_bytes()].concat();
+    if !verify(&key.key, &signed_comment, &global) {
+        return Err("the trusted comment was altered".to_string());
+    }
+    Ok(comment)
+}
+
+/// Verifies a `.sig`: 64 raw bytes, or their base64
+pub fn verify_raw(
+    contents: &[u8],
+    sig: &[u8],
+    key: &TrustedKey,
+) -> std::result::Result<(), String> {
+    let signature = match <[u8; 64]>::try_from(sig) {
+        Ok(signature) => signature,
+        Err(_) => std::str::from_utf8(sig)
+            .ok()
+            .and_then(|text| STANDARD.decode(text.trim()).ok())
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or("the .sig is not a 64-byte Ed25519 signature")?,
+    };
+    if !verify(&key.key, contents, &signature) {
+        return Err("the signature does not match the file".to_string());
+    }
+    Ok(())
+}
+
+/// A `.minisig` for `contents` (prehashed, as current minisign writes)
+pub fn sign(key: &SigningKey, id: [u8; 8], contents: &[u8], trusted_comment: &str) -> String {
+    let signature = key.sign(&prehash(contents)).to_bytes();
+    let blob = [&ALG_ED_PREHASHED[..], &id, &signature].concat();
+    let global = key
+        .sign(&[&signature[..], trusted_comment.as_bytes()].concat())
+        .to_bytes();
+    format!(
+        "{UNTRUSTED} signature from config-watcher key {}\n{}\n{TRUSTED}{trusted_comment}\n{}\n",
+        key_id(id),
+        STANDARD.encode(&blob),
+        STANDARD.encode(global)
+    )
+}
+
+/// A minisign public key file for `key`
+pub fn public_key_file(key: &VerifyingKey, id: [u8; 8]) -> String {
+    let blob = [&ALG_ED[..], &id, key.as_bytes()].concat();
+    format!(
+        "{UNTRUSTED} minisign public key {}\n{}\n",
+        key_id(id),
+        STANDARD.encode(&blob)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &[u8] = br#"{ "app_name": "TestApp", "version": "1.0.0" }"#;
+    const ID: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+    fn keys() -> (SigningKey, TrustedKey) {
+        let seed: [u8; 32] = std::array::from_fn(|_| fastrand::u8(..));
+        let signing = SigningKey::from_bytes(&seed);
+        let trusted =
+            TrustedKey::parse(public_key_file(&signing.verifying_key(), ID).as_bytes()).unwrap();
+        (signing, trusted)
+    }
+
+    #[test]
+    fn test_key_files() {
+        let (signing, trusted) = keys();
+        assert_eq!(trusted, TrustedKey::minisign(signing.verifying_key(), ID));
+        assert_eq!(trusted.id().unwrap(), "0807060504030201");
+
+        let raw = signing.verifying_key().as_bytes().to_vec();
+        let expected = TrustedKey::new(signing.verifying_key());
+        assert_eq!(TrustedKey::parse(&raw), Ok(expected.clone()));
+        assert_eq!(
+            TrustedKey::parse(STANDARD.encode(&raw).as_bytes()),
+            Ok(expected)
+        );
+
+        for garbage in [&b"not a key"[..], b"", b"untrusted comment: x\nAAAA\n"] {
+            assert!(TrustedKey::parse(garbage).is_err(), "{garbage:?}");
+        }
+    }
+
+    #[test]
+    fn test_minisign_signatures() {
+        let (signing, trusted) = keys();
+        let minisig = sign(
+            &signing,
+            ID,
+            CONFIG,
+            "timestamp:1700000000\tfile:config.json",
+        );
+        assert_eq!(
+            verify_minisign(CONFIG, minisig.as_bytes(), &trusted).unwrap(),
+            "timestamp:1700000000\tfile:config.json"
+        );
+        // A raw key accepts any key id
+        let raw = TrustedKey::new(signing.verifying_key());
+        assert!(verify_minisign(CONFIG, minisig.as_bytes(), &raw).is_ok());
+
+        let tampered = CONFIG.to_vec().into_iter().rev().collect::<Vec<_>>();
+        assert!(verify_minisign(&tampered, minisig.as_bytes(), &trusted).is_err());
+
+        let altered = minisig.replace("file:config.json", "file:other.json");
+        assert_eq!(
+            verify_minisign(CONFIG, altered.as_bytes(), &trusted),
+            Err("the trusted comment was altered".to_string())
+        );
+
+        let other = sign(&signing, [9; 8], CONFIG, "c");
+        let error = verify_minisign(CONFIG, other.as_bytes(), &trusted).unwrap_err();
+        assert!(
+            error.starts_with("signed with key 0909090909090909"),
+            "{error}"
+        );
+
+        let (stranger, _) = keys();
+        let forged = sign(&stranger, ID, CONFIG, "c");
+        assert!(verify_minisign(CONFIG, forged.as_bytes(), &trusted).is_err());
+    }
+
+    #[test]
+    fn test_check_finds_the_signature_next_to_the_file() {
+        let (signing, trusted) = keys();
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config.json");
+        std::fs::write(&file, CONFIG).unwrap();
+        let code = |result: Result<()>| result.map_err(|e| e.code());
+
+        assert_eq!(
+            code(check(&file, CONFIG, &trusted)),
+            Err("signature_missing")
+        );
+
+        std::fs::write(raw_path(&file), signing.sign(CONFIG).to_bytes()).unwrap();
+        assert_eq!(code(check(&file, CONFIG, &trusted)), Ok(()));
+        std::fs::write(
+            raw_path(&file),
+            STANDARD.encode(signing.sign(CONFIG).to_bytes()),
+        )
+        .unwrap();
+        assert_eq!(code(check(&file, CONFIG, &trusted)), Ok(()));
+        assert_eq!(
+            code(check(&file, b"{}", &trusted)),
+            Err("signature_invalid")
+        );
+
+        // The .minisig wins over the .sig
+        std::fs::write(minisig_path(&file), sign(&signing, ID, b"{}", "c")).unwrap();
+        assert_eq!(
+            code(check(&file, CONFIG, &trusted)),
+            Err("signature_invalid")
+        );
+    }
+}
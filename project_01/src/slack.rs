@@ -0,0 +1,516 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`impl Notifier`**: Slack is one of the dispatcher's notifiers, with the
+  same queue, timeout, rate limit and failure counting as the others
+- **`#[serde(deny_unknown_fields)]`**: A misspelled key in the template
+  file is an error at startup rather than a silently ignored template
+
+**Design decisions**:
+- Slack incoming webhooks take `{"text", "blocks", "attachments"}`; the
+  message is Slack markdown (`mrkdwn`) in one section block, and `text`
+  repeats it for notifications and clients without blocks
+- A reload is one compact line; a failure also gets a red attachment
+  listing its findings (or its error chain when there are none)
+- `--slack-template` points to a TOML file with `reload` and/or `failure`
+  templates. Placeholders are `{name}`, `{{` and `}}` are literal braces,
+  and an unknown placeholder is refused when the file is loaded
+- Failed loads carry no summary: the application of each file is
+  remembered from its loads, so that a failure can still name it
+- Texts come from the event's JSON form: the summary and diff are the
+  redacted ones, and only field paths are listed, never values
+- Delivery is the webhook's: straight to Slack's https endpoint, through
+  the configured proxy if any, with retries on 5xx and connection errors
+
+******************************************************************************/
+
+use crate::cli::EventKind;
+use crate::notify::{ConfigEvent, Notifier, NotifierOptions};
+use crate::proxy::CurrentProxy;
+use crate::webhook::{self, WebhookConfig};
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use url::Url;
+
+/// Every placeholder a template may use
+pub const PLACEHOLDERS: [&str; 10] = [
+    "event",
+    "app_name",
+    "version",
+    "environment",
+    "host",
+    "file",
+    "changes",
+    "changed_paths",
+    "error",
+    "findings",
+];
+
+/// Attachment color of failures
+const DANGER: &str = "#d0021b";
+
+/// Message templates, one per kind of event
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Templates {
+    pub reload: String,
+    pub failure: String,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self {
+            reload: "✅ *{app_name}* v{version} reloaded on {host}, {changes} changed".to_string(),
+            failure: "❌ *{app_name}* configuration rejected on {host}: {error}".to_string(),
+        }
+    }
+}
+
+impl Templates {
+    /// Reads a TOML file with `reload` and/or `failure` templates; the
+    /// missing ones keep their default
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        let templates: Templates = toml::from_str(&text)
+            .with_context(|| format!("Invalid Slack templates in {}", path.display()))?;
+        let empty: BTreeMap<&str, String> = PLACEHOLDERS
+            .iter()
+            .map(|name| (*name, String::new()))
+            .collect();
+        for (kind, template) in [
+            ("reload", &templates.reload),
+            ("failure", &templates.failure),
+        ] {
+            render(template, &empty)
+                .with_context(|| format!("Invalid '{kind}' template in {}", path.display()))?;
+        }
+        Ok(templates)
+    }
+}
+
+/// Replaces every `{name}` in `template` by its value in `values`
+pub fn render(template: &str, values: &BTreeMap<&str, String>) -> anyhow::Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let Some(end) = rest.find('}') else {
+                    bail!("unclosed '{{' in '{template}'");
+                };
+                let name = &rest[..end];
+                match values.get(name) {
+                    Some(value) => out.push_str(value),
+                    None => bail!(
+                        "unknown placeholder {{{name}}}, expected one of {}",
+                        PLACEHOLDERS.join(", ")
+                    ),
+                }
+                chars = rest[end + 1..].chars();
+            }
+            '}' => bail!("unmatched '}}' in '{template}', write '}}}}' for a brace"),
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// Posts reloads and failures to a Slack incoming webhook
+#[derive(Debug)]
+pub struct SlackNotifier {
+    webhook: WebhookConfig,
+    channel: Option<String>,
+    templates: Templates,
+    host: String,
+    /// `[app_name, version, environment]` of each file's last load
+    apps: Mutex<HashMap<PathBuf, [String; 3]>>,
+}
+
+impl SlackNotifier {
+    /// Posts to `url`, into the webhook's own channel unless `channel`
+    pub fn new(url: Url, channel: Option<String>, templates: Templates) -> Self {
+        Self {
+            webhook: WebhookConfig::new(url, vec![EventKind::Reload, EventKind::Failure]),
+            channel,
+            templates,
+            host: webhook::host_name(),
+            apps: Mutex::default(),
+        }
+    }
+
+    /// Posts through the proxy `proxy` picks for the webhook's URL
+    pub fn with_proxy(mut self, proxy: CurrentProxy) -> Self {
+        self.webhook.proxy = proxy;
+        self
+    }
+
+    /// The message for `event`, if it gets one
+    pub fn payload(&self, event: &ConfigEvent) -> anyhow::Result<Option<Value>> {
+        let template = match event.kind {
+            EventKind::Reload => &self.templates.reload,
+            EventKind::Failure => &self.templates.failure,
+            _ => return Ok(None),
+        };
+        let text = render(template, &self.values(event))?;
+        let mut payload = json!({
+            "text": text,
+            "blocks": [{ "type": "section", "text": { "type": "mrkdwn", "text": text } }],
+        });
+        if let Some(ref channel) = self.channel {
+            payload["channel"] = json!(channel);
+        }
+        if event.kind == EventKind::Failure {
+            payload["attachments"] = json!([{
+                "color": DANGER,
+                "title": "Findings",
+                "text": findings(&event.data["error"]),
+                "mrkdwn_in": ["text"],
+            }]);
+        }
+        Ok(Some(payload))
+    }
+
+    /// The value of every placeholder for `event`
+    fn values(&self, event: &ConfigEvent) -> BTreeMap<&'static str, String> {
+        let data = &event.data;
+        let file = event.file.as_deref().unwrap_or(Path::new(""));
+        let remembered = || {
+            self.apps
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(file)
+                .cloned()
+        };
+        let [app_name, version, environment] =
+            application(event).or_else(remembered).unwrap_or_else(|| {
+                // Before any load, the file name stands for the application
+                let name = file.file_name().unwrap_or(file.as_os_str());
+                [name.to_string_lossy().to_string(), "?".into(), "?".into()]
+            });
+        let paths: Vec<&str> = data["diff"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|change| change["path"].as_str())
+            .collect();
+        let s = if paths.len() == 1 { "" } else { "s" };
+        let error = &data["error"];
+
+        BTreeMap::from([
+            (
+                "event",
+                match event.kind {
+                    EventKind::Reload => "reload".to_string(),
+                    _ => "failure".to_string(),
+                },
+            ),
+            ("app_name", app_name),
+            ("version", version),
+            ("environment", environment),
+            ("host", self.host.clone()),
+            ("file", file.display().to_string()),
+            ("changes", format!("{} field{s}", paths.len())),
+            ("changed_paths", paths.join(", ")),
+            (
+                "error",
+                error["message"].as_str().unwrap_or_default().to_string(),
+            ),
+            ("findings", findings(error)),
+        ])
+    }
+
+    /// Remembers the application of the file `event` loaded, if any
+    fn remember(&self, event: &ConfigEvent) {
+        if let (Some(file), Some(app)) = (&event.file, application(event)) {
+            self.apps
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(file.clone(), app);
+        }
+    }
+}
+
+/// `[app_name, version, environment]` from the summary of a load
+fn application(event: &ConfigEvent) -> Option<[String; 3]> {
+    let summary = event.data["summary"].as_object()?;
+    let field = |key: &str| summary[key].as_str().unwrap_or_default().to_string();
+    Some([field("app_name"), field("version"), field("environment")])
+}
+
+/// One `• path: message` line per finding, or the error chain
+fn findings(error: &Value) -> String {
+    let lines: Vec<String> = match error["findings"].as_array() {
+        Some(findings) if !findings.is_empty() => findings
+            .iter()
+            .map(|finding| {
+                format!(
+                    "• `{}`: {}",
+                    finding["path"].as_str().unwrap_or_default(),
+                    finding["message"].as_str().unwrap_or_default()
+                )
+            })
+            .collect(),
+        _ => error["chain"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(|cause| format!("• {cause}"))
+            .collect(),
+    };
+    lines.join("\n")
+}
+
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    fn options(&self) -> NotifierOptions {
+        webhook::Webhook::new(self.webhook.clone()).options()
+    }
+
+    /// Reloads and failures; initial loads are only remembered
+    fn accepts(&self, event: &ConfigEvent) -> bool {
+        self.remember(event);
+        matches!(event.kind, EventKind::Reload | EventKind::Failure)
+    }
+
+    async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+        let Some(payload) = self.payload(event)? else {
+            return Ok(());
+        };
+        let body = payload.to_string();
+        let config = self.webhook.clone();
+        tokio::task::spawn_blocking(move || webhook::deliver(&config, &body))
+            .await?
+            .map_err(|e| anyhow::anyhow!("{}: {e}", self.webhook.url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::diff::Change;
+    use crate::error::ConfigError;
+    use crate::http_client::testing::receiver;
+    use crate::notify::Notifiers;
+    use crate::output::{Event, Summary};
+    use crate::overrides::Overrides;
+    use crate::validation::ValidationReport;
+    use std::time::{Duration, SystemTime};
+
+    fn slack(templates: Templates) -> SlackNotifier {
+        let url = Url::parse("http://127.0.0.1:9/slack").unwrap();
+        SlackNotifier {
+            host: "web-1".to_string(),
+            ..SlackNotifier::new(url, Some("#ops".to_string()), templates)
+        }
+    }
+
+    fn loaded(initial: bool) -> ConfigEvent {
+        let config = AppConfig {
+            app_name: "TestApp".to_string(),
+            version: "2.0.0".to_string(),
+            ..AppConfig::example()
+        };
+        let overrides = Overrides::default();
+        let changes: Vec<Change> = ["server.port", "version", "database.password"]
+            .map(|path| Change {
+                path: path.to_string(),
+                old: Some(json!("<redacted>")),
+                new: Some(json!("<redacted>")),
+            })
+            .to_vec();
+        let event = Event::Loaded {
+            file: Path::new("/etc/app.json"),
+            version: 2,
+            initial,
+            summary: Summary {
+                config: &config,
+                overrides: &overrides,
+            },
+            changes: (!initial).then_some(&changes[..]),
+            flags: None,
+            stand_in: None,
+        };
+        ConfigEvent::new(&event, SystemTime::UNIX_EPOCH).unwrap()
+    }
+
+    fn failed(error: anyhow::Error) -> ConfigEvent {
+        let event = Event::LoadFailed {
+            file: Path::new("/etc/app.json"),
+            error: &error,
+            initial: false,
+            retrying: true,
+        };
+        ConfigEvent::new(&event, SystemTime::UNIX_EPOCH).unwrap()
+    }
+
+    fn validation_error() -> anyhow::Error {
+        let mut report = ValidationReport::new();
+        report.error("server.port", "must be between 1 and 65535");
+        report.error("database.pool_size", "must be positive");
+        ConfigError::ValidationFailed { report }.into()
+    }
+
+    #[test]
+    fn test_render_placeholders_and_braces() {
+        let values = BTreeMap::from([("app_name", "TestApp".to_string())]);
+        assert_eq!(
+            render("*{app_name}* {{literal}} {app_name}", &values).unwrap(),
+            "*TestApp* {literal} TestApp"
+        );
+        for bad in ["{nope}", "{app_name", "closing }"] {
+            assert!(render(bad, &values).is_err(), "{bad}");
+        }
+        let error = render("{nope}", &values).unwrap_err().to_string();
+        assert!(error.contains("unknown placeholder {nope}"), "{error}");
+    }
+
+    #[test]
+    fn test_reload_payload() {
+        let slack = slack(Templates::default());
+        let event = loaded(false);
+        assert!(slack.accepts(&event));
+        let text = "✅ *TestApp* v2.0.0 reloaded on web-1, 3 fields changed";
+        assert_eq!(
+            slack.payload(&event).unwrap().unwrap(),
+            json!({
+                "text": text,
+                "blocks": [{ "type": "section", "text": { "type": "mrkdwn", "text": text } }],
+                "channel": "#ops",
+            })
+        );
+    }
+
+    #[test]
+    fn test_failure_payload_lists_the_findings() {
+        let slack = slack(Templates::default());
+        // The initial load names the application for later failures
+        assert!(!slack.accepts(&loaded(true)));
+        let event = failed(validation_error());
+        assert!(slack.accepts(&event));
+
+        let payload = slack.payload(&event).unwrap().unwrap();
+        assert_eq!(
+            payload["text"],
+            "❌ *TestApp* configuration rejected on web-1: Configuration validation failed: \
+             server.port: must be between 1 and 65535; database.pool_size: must be positive"
+        );
+        assert_eq!(
+            payload["attachments"],
+            json!([{
+                "color": DANGER,
+                "title": "Findings",
+                "text": "• `server.port`: must be between 1 and 65535\n• `database.pool_size`: must be positive",
+                "mrkdwn_in": ["text"],
+            }])
+        );
+
+        // No findings: the error chain
+        let error = anyhow::anyhow!("Permission denied").context("Cannot read /etc/app.json");
+        let payload = slack.payload(&failed(error)).unwrap().unwrap();
+        assert_eq!(
+            payload["attachments"][0]["text"],
+            "• Cannot read /etc/app.json\n• Permission denied"
+        );
+    }
+
+    #[test]
+    fn test_custom_templates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("slack.toml");
+        std::fs::write(
+            &path,
+            r#"reload = "{app_name} ({environment}) now at {version}: {changed_paths}""#,
+        )
+        .unwrap();
+        let templates = Templates::load(&path).unwrap();
+        assert_eq!(templates.failure, Templates::default().failure);
+
+        let slack = slack(templates);
+        let payload = slack.payload(&loaded(false)).unwrap().unwrap();
+        assert_eq!(
+            payload["text"],
+            format!(
+                "TestApp ({}) now at 2.0.0: server.port, version, database.password",
+                AppConfig::example().environment
+            )
+        );
+
+        for bad in [r#"reload = "{secret}""#, r#"relaod = "{version}""#] {
+            std::fs::write(&path, bad).unwrap();
+            assert!(Templates::load(&path).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_delivery_to_a_stub_server() {
+        let (url, requests) = receiver(&[503]);
+        let mut slack = SlackNotifier::new(url, None, Templates::default());
+        slack.webhook.backoff = Duration::from_millis(10);
+        let (dispatcher, mut guard) = Notifiers::new().with(slack).start().unwrap();
+        dispatcher.send(loaded(true));
+        dispatcher.send(failed(validation_error()));
+        assert!(guard.shutdown(Duration::from_secs(5)));
+
+        // Retried once after the 503
+        let bodies: Vec<Value> = requests
+            .try_iter()
+            .map(|(head, body)| {
+                assert!(head.starts_with("POST /hooks/config HTTP/1.1"), "{head}");
+                serde_json::from_str(&body).unwrap()
+            })
+            .collect();
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0], bodies[1]);
+        assert!(bodies[0].get("channel").is_none());
+        assert_eq!(bodies[0]["attachments"][0]["title"], "Findings");
+        assert_eq!(dispatcher.stats()[0].1.delivered, 1);
+    }
+
+    #[test]
+    fn test_https_endpoint_through_the_proxy() {
+        // The proxy records the tunnel it is asked for and refuses it
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy_url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, asked) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            use std::io::{BufRead, BufReader, Write};
+            let (stream, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+            let _ = (&stream).write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n");
+            sender.send(line).unwrap();
+        });
+
+        let proxy = CurrentProxy::default();
+        proxy.set(Some(crate::config::ProxyConfig {
+            http: None,
+            https: Some(proxy_url),
+            no_proxy: Vec::new(),
+        }));
+        let url = Url::parse("https://hooks.slack.com/services/T000/B000/XXXX").unwrap();
+        let mut slack = SlackNotifier::new(url, None, Templates::default()).with_proxy(proxy);
+        slack.webhook.retries = 0;
+        assert!(webhook::deliver(&slack.webhook, "{}").is_err());
+        let line = asked.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(line.starts_with("CONNECT hooks.slack.com:443 "), "{line}");
+    }
+}
@@ -0,0 +1,605 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`Arc<Mutex<...>>`**: Every watcher of the process shares one status file
+  and records its own state in it
+- **`#[derive(Ord)]` on `Health`**: The process is as healthy as its worst
+  file, a `max()`
+- **`fs_util::write_atomic_with_mode`**: Readers never see a half-written
+  file, and never see it with the wrong permissions either
+- **`#[serde(default)]`**: Records written by older versions, without `pid`
+  or `files`, still parse
+
+**Design decisions**:
+- The running watcher rewrites the status file after every tick, so its
+  timestamp doubles as a liveness signal: a stale file means a hung or dead
+  process even if the last recorded health was good
+- Health derives from the watch state: valid is healthy, failing (an older
+  configuration is still served) is degraded, no valid configuration yet is
+  unhealthy. Running on the fallback configuration, or on a configuration
+  restored from `--state-dir`, is degraded too, and the entry names the
+  fallback file or the time the restored one was saved
+- Each watched file gets its own entry (version, last successful and failed
+  reloads, last error) so a script can tell which file is in trouble
+- The last failure stays recorded after a recovery: together with the
+  health it tells "failing now" from "failed earlier today"
+- Mode 0644 by default since the file holds no configuration values, only
+  error messages; `--status-file-mode 600` tightens it
+- The file is removed on a clean shutdown, so a leftover file means the
+  process died; after fail-fast it stays behind for the post-mortem
+- `evaluate` is a pure function of the record and the clock, so every
+  verdict of `healthcheck` is unit-testable
+- `StatusBoard` holds the same records in memory, with the redacted JSON
+  of each file's configuration rendered once per load, for `--http-addr`:
+  a request clones an `Arc<str>`, never the configuration
+
+******************************************************************************/
+
+use crate::config::AppConfig;
+use crate::output::WatchState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Overall health recorded in the status file, best first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Health {
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+
+impl From<WatchState> for Health {
+    fn from(state: WatchState) -> Self {
+        match state {
+            WatchState::Valid => Health::Healthy,
+            WatchState::Failing | WatchState::Fallback | WatchState::Restored => Health::Degraded,
+            WatchState::Waiting => Health::Unhealthy,
+        }
+    }
+}
+
+/// Contents of the status file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusRecord {
+    /// When the file was last written (RFC 3339)
+    pub updated_at: String,
+    /// Check interval of the watcher, in seconds
+    pub interval_secs: u64,
+    /// Worst health of the watched files
+    pub health: Health,
+    /// Process writing the file
+    #[serde(default)]
+    pub pid: u32,
+    /// One entry per watched file, sorted by path
+    #[serde(default)]
+    pub files: Vec<FileRecord>,
+}
+
+/// State of one watched file in the status record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileRecord {
+    pub path: PathBuf,
+    pub health: Health,
+    /// Configuration version, 0 until the first successful load
+    pub version: u64,
+    /// Last successful load (RFC 3339)
+    #[serde(default)]
+    pub last_success_at: Option<String>,
+    /// Last failed load or check (RFC 3339)
+    #[serde(default)]
+    pub last_failure_at: Option<String>,
+    /// Error of the last failure, even if the file recovered since
+    #[serde(default)]
+    pub last_error: Option<ErrorSummary>,
+    /// Fallback configuration served while the file has never loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<PathBuf>,
+    /// When the last good configuration restored from a previous run,
+    /// served while the file has never loaded, was saved (RFC 3339)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restored_at: Option<String>,
+    /// Successful reloads since the start, the initial load excluded
+    #[serde(default)]
+    pub reloads: u64,
+    /// Failed loads and checks since the start
+    #[serde(default)]
+    pub failures: u64,
+}
+
+impl StatusRecord {
+    /// The record of `files`, as of now
+    pub fn new<'a>(interval: Duration, files: impl IntoIterator<Item = &'a FileRecord>) -> Self {
+        let files: Vec<FileRecord> = files.into_iter().cloned().collect();
+        Self {
+            updated_at: timestamp(SystemTime::now()),
+            interval_secs: interval.as_secs(),
+            health: files
+                .iter()
+                .map(|file| file.health)
+                .max()
+                .unwrap_or(Health::Unhealthy),
+            pid: std::process::id(),
+            files,
+        }
+    }
+}
+
+/// Short form of a load failure
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorSummary {
+    /// Stable code, see `config-watcher explain`
+    pub code: String,
+    /// The whole error chain on one line
+    pub message: String,
+}
+
+impl ErrorSummary {
+    pub fn new(error: &anyhow::Error) -> Self {
+        Self {
+            code: crate::report::code(error).to_string(),
+            message: format!("{error:#}"),
+        }
+    }
+}
+
+/// Permissions of a new status file
+pub const DEFAULT_MODE: u32 = 0o644;
+
+/// Parses an octal mode such as `600` or `0640`
+pub fn parse_mode(text: &str) -> Result<u32, String> {
+    let digits = text.strip_prefix("0o").unwrap_or(text);
+    match u32::from_str_radix(digits, 8) {
+        Ok(mode) if mode <= 0o777 => Ok(mode),
+        _ => Err(format!("'{text}' is not an octal mode such as 644 or 600")),
+    }
+}
+
+/// Formats a `SystemTime` the way the status file does
+pub fn timestamp(at: SystemTime) -> String {
+    humantime::format_rfc3339_millis(at).to_string()
+}
+
+/// Status file shared by the watchers of one process
+#[derive(Debug, Clone)]
+pub struct StatusFile {
+    path: PathBuf,
+    interval: Duration,
+    mode: u32,
+    files: Arc<Mutex<BTreeMap<PathBuf, FileRecord>>>,
+}
+
+impl StatusFile {
+    /// A status file at `path` for watchers checking every `interval`
+    pub fn new(path: impl Into<PathBuf>, interval: Duration) -> Self {
+        Self {
+            path: path.into(),
+            interval,
+            mode: DEFAULT_MODE,
+            files: Arc::default(),
+        }
+    }
+
+    /// Sets the permissions of the file (Unix only)
+    pub fn with_mode(mut self, mode: u32) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Path of the status file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records the state of one file and rewrites the status file
+    ///
+    /// The lock is held while writing, so two watchers never race to
+    /// rename an older record over a newer one.
+    pub fn update(&self, file: FileRecord) -> crate::error::Result<()> {
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files.insert(file.path.clone(), file);
+        let record = StatusRecord::new(self.interval, files.values());
+        let mut json = serde_json::to_string(&record).unwrap_or_default();
+        json.push('\n');
+        crate::fs_util::write_atomic_with_mode(&self.path, json.as_bytes(), self.mode)
+    }
+
+    /// Deletes the status file, on a clean shutdown
+    pub fn remove(&self) -> std::io::Result<()> {
+        let _files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Status and redacted configuration of every watched file, in memory
+///
+/// Shared by the watchers, which post to it, and the HTTP server, which
+/// reads it.
+#[derive(Debug, Clone)]
+pub struct StatusBoard {
+    interval: Duration,
+    files: Arc<Mutex<BTreeMap<PathBuf, Posted>>>,
+}
+
+/// What the board holds for one file
+#[derive(Debug, Clone)]
+struct Posted {
+    record: FileRecord,
+    /// Redacted JSON of the last valid configuration
+    config: Option<Arc<str>>,
+}
+
+impl StatusBoard {
+    /// A board for watchers checking every `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            files: Arc::default(),
+        }
+    }
+
+    /// Records the state of one file
+    pub fn update(&self, file: FileRecord) {
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        match files.get_mut(&file.path) {
+            Some(posted) => posted.record = file,
+            None => {
+                let path = file.path.clone();
+                files.insert(
+                    path,
+                    Posted {
+                        record: file,
+                        config: None,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Makes `config` the configuration served for `path`, secrets redacted
+    ///
+    /// The state of a file first seen here is filled in by its next
+    /// [`StatusBoard::update`].
+    pub fn publish(&self, path: &Path, config: &AppConfig) {
+        let mut doc = serde_json::to_value(config).unwrap_or_default();
+        crate::redact::redact(&mut doc);
+        let json: Arc<str> = doc.to_string().into();
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files
+            .entry(path.to_path_buf())
+            .or_insert_with(|| Posted {
+                record: FileRecord {
+                    path: path.to_path_buf(),
+                    health: Health::Unhealthy,
+                    version: 0,
+                    last_success_at: None,
+                    last_failure_at: None,
+                    last_error: None,
+                    fallback: None,
+                    restored_at: None,
+                    reloads: 0,
+                    failures: 0,
+                },
+                config: None,
+            })
+            .config = Some(json);
+    }
+
+    /// The status of every file, as written to the status file
+    pub fn record(&self) -> StatusRecord {
+        let files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        StatusRecord::new(self.interval, files.values().map(|posted| &posted.record))
+    }
+
+    /// The redacted JSON of each file's configuration, for the files that
+    /// have one, sorted by path
+    pub fn configs(&self) -> Vec<(PathBuf, Arc<str>)> {
+        let files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files
+            .iter()
+            .filter_map(|(path, posted)| Some((path.clone(), posted.config.clone()?)))
+            .collect()
+    }
+}
+
+/// Thresholds applied by `healthcheck`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Thresholds {
+    /// Oldest acceptable record; `None` means `stale_after` intervals
+    pub max_age: Option<Duration>,
+    /// Number of intervals after which a record is stale
+    pub stale_after: u32,
+    /// Whether a degraded watcher counts as a failure
+    pub fail_on_degraded: bool,
+}
+
+/// Decides whether `record`, read at `now`, is healthy
+///
+/// Returns a one-line explanation either way.
+pub fn evaluate(
+    record: &StatusRecord,
+    now: SystemTime,
+    thresholds: &Thresholds,
+) -> Result<String, String> {
+    let updated = humantime::parse_rfc3339(&record.updated_at)
+        .map_err(|e| format!("invalid timestamp '{}': {e}", record.updated_at))?;
+    let age = now.duration_since(updated).unwrap_or_default();
+    let max_age = thresholds
+        .max_age
+        .unwrap_or(Duration::from_secs(record.interval_secs) * thresholds.stale_after);
+    let age_text = humantime::format_duration(Duration::from_secs(age.as_secs()));
+
+    if age > max_age {
+        return Err(format!(
+            "stale: last update {age_text} ago (limit {})",
+            humantime::format_duration(max_age)
+        ));
+    }
+    let stand_in = record.files.iter().find_map(|file| {
+        let fallback = file
+            .fallback
+            .as_ref()
+            .map(|fallback| format!("running on fallback {}", fallback.display()));
+        let restored = || {
+            file.restored_at
+                .as_ref()
+                .map(|at| format!("running on the configuration restored from {at}"))
+        };
+        fallback.or_else(restored)
+    });
+    if let Some(stand_in) = stand_in {
+        return if thresholds.fail_on_degraded {
+            Err(format!("degraded: {stand_in}"))
+        } else {
+            Ok(format!("degraded ({stand_in}), updated {age_text} ago"))
+        };
+    }
+    match record.health {
+        Health::Healthy => Ok(format!("healthy, updated {age_text} ago")),
+        Health::Degraded if !thresholds.fail_on_degraded => Ok(format!(
+            "degraded (serving the last good configuration), updated {age_text} ago"
+        )),
+        Health::Degraded => Err("degraded: the last reload failed".to_string()),
+        Health::Unhealthy => Err("unhealthy: no valid configuration loaded".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULTS: Thresholds = Thresholds {
+        max_age: None,
+        stale_after: 3,
+        fail_on_degraded: false,
+    };
+
+    fn record(age_secs: u64, health: Health) -> (StatusRecord, SystemTime) {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let updated = now - Duration::from_secs(age_secs);
+        let record = StatusRecord {
+            updated_at: humantime::format_rfc3339_millis(updated).to_string(),
+            interval_secs: 2,
+            health,
+            pid: 1,
+            files: Vec::new(),
+        };
+        (record, now)
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let (fresh, now) = record(1, Health::Healthy);
+        assert!(evaluate(&fresh, now, &DEFAULTS).is_ok());
+
+        let (degraded, now) = record(1, Health::Degraded);
+        assert!(evaluate(&degraded, now, &DEFAULTS).is_ok());
+        let strict = Thresholds {
+            fail_on_degraded: true,
+            ..DEFAULTS
+        };
+        assert!(evaluate(&degraded, now, &strict).is_err());
+
+        let (stale, now) = record(7, Health::Healthy);
+        let err = evaluate(&stale, now, &DEFAULTS).unwrap_err();
+        assert!(err.starts_with("stale"), "{err}");
+        let lenient = Thresholds {
+            max_age: Some(Duration::from_secs(10)),
+            ..DEFAULTS
+        };
+        assert!(evaluate(&stale, now, &lenient).is_ok());
+
+        let (unhealthy, now) = record(0, Health::Unhealthy);
+        assert!(evaluate(&unhealthy, now, &DEFAULTS).is_err());
+
+        let (mut fallback, now) = record(1, Health::Degraded);
+        fallback.files.push(FileRecord {
+            fallback: Some(PathBuf::from("safe.json")),
+            ..file("app.json", WatchState::Fallback)
+        });
+        let summary = evaluate(&fallback, now, &DEFAULTS).unwrap();
+        assert!(
+            summary.contains("running on fallback safe.json"),
+            "{summary}"
+        );
+        let err = evaluate(&fallback, now, &strict).unwrap_err();
+        assert!(err.contains("fallback"), "{err}");
+
+        let (mut restored, now) = record(1, Health::Degraded);
+        restored.files.push(FileRecord {
+            restored_at: Some("2026-10-14T08:00:00.000Z".to_string()),
+            ..file("app.json", WatchState::Restored)
+        });
+        let summary = evaluate(&restored, now, &DEFAULTS).unwrap();
+        assert!(
+            summary.contains("restored from 2026-10-14T08:00:00.000Z"),
+            "{summary}"
+        );
+        assert!(evaluate(&restored, now, &strict).is_err());
+    }
+
+    fn file(path: &str, state: WatchState) -> FileRecord {
+        FileRecord {
+            path: PathBuf::from(path),
+            health: state.into(),
+            version: 1,
+            last_success_at: None,
+            last_failure_at: None,
+            last_error: None,
+            fallback: None,
+            restored_at: None,
+            reloads: 0,
+            failures: 0,
+        }
+    }
+
+    fn read(status: &StatusFile) -> StatusRecord {
+        let text = std::fs::read_to_string(status.path()).unwrap();
+        serde_json::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn test_worst_file_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        let status = StatusFile::new(dir.path().join("status.json"), Duration::from_secs(2));
+        status.update(file("b.json", WatchState::Failing)).unwrap();
+        status.update(file("a.json", WatchState::Valid)).unwrap();
+
+        let record = read(&status);
+        assert_eq!(record.health, Health::Degraded);
+        assert_eq!(record.interval_secs, 2);
+        assert_eq!(record.pid, std::process::id());
+        let paths: Vec<_> = record.files.iter().map(|f| f.path.clone()).collect();
+        assert_eq!(paths, [PathBuf::from("a.json"), PathBuf::from("b.json")]);
+    }
+
+    #[test]
+    fn test_file_details_and_removal() {
+        let dir = tempfile::tempdir().unwrap();
+        let status = StatusFile::new(dir.path().join("status.json"), Duration::from_secs(2));
+        let error = anyhow::Error::new(crate::error::ConfigError::FileNotFound {
+            path: PathBuf::from("a.json"),
+        });
+        status
+            .update(FileRecord {
+                last_failure_at: Some(timestamp(SystemTime::UNIX_EPOCH)),
+                last_error: Some(ErrorSummary::new(&error)),
+                ..file("a.json", WatchState::Failing)
+            })
+            .unwrap();
+
+        let entry = &read(&status).files[0];
+        assert_eq!(
+            entry.last_failure_at.as_deref(),
+            Some("1970-01-01T00:00:00.000Z")
+        );
+        let last_error = entry.last_error.as_ref().unwrap();
+        assert_eq!(last_error.code, "file_not_found");
+        assert!(
+            last_error.message.contains("a.json"),
+            "{}",
+            last_error.message
+        );
+
+        status.remove().unwrap();
+        assert!(!status.path().exists());
+        // Already gone is fine
+        status.remove().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mode = |status: &StatusFile| {
+            std::fs::metadata(status.path())
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777
+        };
+        let status = StatusFile::new(dir.path().join("status.json"), Duration::from_secs(2));
+        status.update(file("a.json", WatchState::Valid)).unwrap();
+        assert_eq!(mode(&status), 0o644);
+
+        let status = status.with_mode(0o600);
+        status.update(file("a.json", WatchState::Valid)).unwrap();
+        assert_eq!(mode(&status), 0o600);
+
+        assert_eq!(parse_mode("0640"), Ok(0o640));
+        assert_eq!(parse_mode("600"), Ok(0o600));
+        assert!(parse_mode("999").is_err());
+        assert!(parse_mode("1777").is_err());
+    }
+
+    #[test]
+    fn test_readers_never_see_a_partial_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let status = StatusFile::new(dir.path().join("status.json"), Duration::from_secs(2));
+        status.update(file("a.json", WatchState::Valid)).unwrap();
+
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let reader = {
+            let (path, done) = (status.path().to_path_buf(), done.clone());
+            std::thread::spawn(move || {
+                let mut reads = 0;
+                while !done.load(std::sync::atomic::Ordering::Relaxed) {
+                    let text = std::fs::read_to_string(&path).unwrap();
+                    serde_json::from_str::<StatusRecord>(&text).unwrap();
+                    reads += 1;
+                }
+                reads
+            })
+        };
+        for version in 0..500 {
+            let state = if version % 2 == 0 {
+                WatchState::Valid
+            } else {
+                WatchState::Failing
+            };
+            status
+                .update(FileRecord {
+                    version,
+                    ..file("a.json", state)
+                })
+                .unwrap();
+        }
+        done.store(true, std::sync::atomic::Ordering::Relaxed);
+        assert!(reader.join().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_board_serves_redacted_configs() {
+        let board = StatusBoard::new(Duration::from_secs(2));
+        assert!(board.configs().is_empty());
+        board.update(file("b.json", WatchState::Waiting));
+
+        let mut config = AppConfig::example();
+        if let Some(ref mut database) = config.database {
+            database.connection_string = "postgres://admin:hunter2@db/app".to_string();
+        }
+        board.publish(Path::new("a.json"), &config);
+        board.update(file("a.json", WatchState::Valid));
+
+        let configs = board.configs();
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].0, Path::new("a.json"));
+        assert!(!configs[0].1.contains("hunter2"), "{}", configs[0].1);
+        assert!(configs[0].1.contains(crate::redact::REDACTED));
+
+        // Publishing again swaps the served document, the record stays
+        let record = board.record();
+        assert_eq!(record.health, Health::Unhealthy);
+        assert_eq!(record.files.len(), 2);
+        board.publish(Path::new("a.json"), &AppConfig::example());
+        assert_eq!(board.record().files, record.files);
+    }
+}
@@ -0,0 +1,169 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`std::io::IsTerminal`**: Tells whether stdout is an interactive terminal
+- **ANSI escape codes**: `\x1b[32m` ... `\x1b[0m` color a span of text
+- **`Copy` types**: `Style` is a single flag, passed around by value
+
+**Design decisions**:
+- One switch controls both color and emoji: a terminal that renders one
+  renders the other, and CI logs want neither
+- Plain mode uses ASCII tags (`[OK]`, `[ERR]`...) so output stays greppable
+  and never turns into mojibake
+- `--color always|never` wins; with `auto`, `NO_COLOR` (any non-empty value,
+  see no-color.org) or a non-terminal stdout selects plain output
+
+******************************************************************************/
+
+use crate::cli::ColorChoice;
+use std::io::IsTerminal;
+
+/// Marker at the start of a status line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Icon {
+    File,
+    Watch,
+    Interval,
+    Override,
+    Change,
+    Ok,
+    Update,
+    Error,
+    Warning,
+    Timeout,
+    Stop,
+    Heartbeat,
+    Dump,
+    Check,
+}
+
+impl Icon {
+    /// Emoji form, padded so the text after it lines up
+    fn emoji(self) -> &'static str {
+        match self {
+            Icon::File => "📄",
+            Icon::Watch => "👀",
+            Icon::Interval => "⏱️ ",
+            Icon::Override => "🔧",
+            Icon::Change => "🔄",
+            Icon::Ok => "✅",
+            Icon::Update => "📝",
+            Icon::Error => "❌",
+            Icon::Warning => "⚠️ ",
+            Icon::Timeout => "⏰",
+            Icon::Stop => "👋",
+            Icon::Heartbeat => "💓",
+            Icon::Dump => "📋",
+            Icon::Check => "🩺",
+        }
+    }
+
+    /// ASCII form
+    fn tag(self) -> &'static str {
+        match self {
+            Icon::File => "[FILE]",
+            Icon::Watch => "[WATCH]",
+            Icon::Interval => "[INTERVAL]",
+            Icon::Override => "[OVERRIDE]",
+            Icon::Change => "[CHANGE]",
+            Icon::Ok => "[OK]",
+            Icon::Update => "[UPDATE]",
+            Icon::Error => "[ERR]",
+            Icon::Warning => "[WARN]",
+            Icon::Timeout | Icon::Stop => "[STOP]",
+            Icon::Heartbeat => "[ALIVE]",
+            Icon::Dump => "[DUMP]",
+            Icon::Check => "[CHECK]",
+        }
+    }
+
+    /// ANSI color of the line, if any
+    fn color(self) -> Option<&'static str> {
+        match self {
+            Icon::Ok => Some("32"),
+            Icon::Error => Some("31"),
+            Icon::Warning | Icon::Timeout => Some("33"),
+            Icon::Change | Icon::Update => Some("36"),
+            _ => None,
+        }
+    }
+}
+
+/// How status lines are decorated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    fancy: bool,
+}
+
+impl Style {
+    /// ASCII tags, no escape codes
+    pub const PLAIN: Style = Style { fancy: false };
+
+    /// Emoji and colors
+    pub const FANCY: Style = Style { fancy: true };
+
+    /// Picks the style from the flag, `NO_COLOR` and the terminal
+    pub fn resolve(choice: ColorChoice, no_color: bool, is_terminal: bool) -> Self {
+        match choice {
+            ColorChoice::Always => Self::FANCY,
+            ColorChoice::Never => Self::PLAIN,
+            ColorChoice::Auto if no_color || !is_terminal => Self::PLAIN,
+            ColorChoice::Auto => Self::FANCY,
+        }
+    }
+
+    /// `resolve` against the real environment and stdout
+    pub fn detect(choice: ColorChoice) -> Self {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Self::resolve(choice, no_color, std::io::stdout().is_terminal())
+    }
+
+    /// Whether emoji and colors are used
+    #[cfg(test)]
+    pub fn is_fancy(self) -> bool {
+        self.fancy
+    }
+
+    /// `text` preceded by `icon`, colored when fancy
+    pub fn line(self, icon: Icon, text: impl std::fmt::Display) -> String {
+        if !self.fancy {
+            return format!("{} {}", icon.tag(), text);
+        }
+        match icon.color() {
+            Some(color) => format!("{} \x1b[{color}m{text}\x1b[0m", icon.emoji()),
+            None => format!("{} {}", icon.emoji(), text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        use ColorChoice::*;
+        let cases = [
+            // (choice, NO_COLOR, terminal, fancy)
+            (Auto, false, true, true),
+            (Auto, false, false, false),
+            (Auto, true, true, false),
+            (Always, true, false, true),
+            (Never, false, true, false),
+        ];
+        for (choice, no_color, terminal, fancy) in cases {
+            let style = Style::resolve(choice, no_color, terminal);
+            assert_eq!(style.is_fancy(), fancy, "{choice:?} {no_color} {terminal}");
+        }
+    }
+
+    #[test]
+    fn test_plain_lines_are_ascii() {
+        let line = Style::PLAIN.line(Icon::Interval, "Check interval: 2s");
+        assert_eq!(line, "[INTERVAL] Check interval: 2s");
+
+        let line = Style::FANCY.line(Icon::Ok, "done");
+        assert_eq!(line, "✅ \x1b[32mdone\x1b[0m");
+        assert_eq!(Style::FANCY.line(Icon::Watch, "x"), "👀 x");
+    }
+}
@@ -0,0 +1,525 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`tokio::process::Child`**: The supervised command runs next to the
+  watch loop; `wait` is awaited in a `select!` with the control channel,
+  so a reload and the command's own exit are handled by one task
+- **`impl Notifier`**: Loads reach the supervisor through the dispatcher
+  like any notifier's events; the notifier only forwards them, the
+  restart itself happens in the supervisor's task
+- **`JoinHandle` as a future**: `main` races the task's end (the command
+  exited for good) against the watch loops and Ctrl+C
+
+**Design decisions**:
+- `config-watcher run -f config.json -- mycmd --args` starts `mycmd` once
+  the configuration is valid, with every field in its environment (named
+  like `--from-env` reads them, `CW__SERVER__PORT`, secrets included: it
+  is the application the configuration is for) plus CONFIG_PATH and
+  CONFIG_VERSION. An invalid initial configuration starts nothing until a
+  valid one is saved
+- Each reload that changes the configuration restarts it: SIGTERM, up to
+  `--kill-timeout` to exit, then SIGKILL, then the new instance. Reloads
+  queued during a restart collapse into one. A failed reload leaves the
+  running instance alone, like every other consumer of the configuration
+- SIGTERM goes through `signal_pid::send`; only the command itself is
+  signalled, not its children. Windows has no SIGTERM: the command is
+  terminated at once
+- When the command exits on its own it is reported; with
+  `--restart-on-exit` it is started again after a second, otherwise the
+  watcher stops with the command's outcome: 0 after a clean exit,
+  `exit::CHILD_FAILED` otherwise, so a failing command can be told apart
+  from a failing watcher (2 to 7)
+- The watcher stopping stops the command first, the same way, then emits
+  its shutdown event
+
+******************************************************************************/
+
+use crate::cli::EventKind;
+use crate::env_config;
+use crate::notify::{ConfigEvent, Notifier};
+#[cfg(unix)]
+use crate::signal_pid::{self, Signal};
+use std::fmt;
+use std::process::ExitStatus;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Default `--kill-timeout`
+pub const DEFAULT_KILL_TIMEOUT: &str = "10s";
+
+/// Pause before starting a command that exited on its own
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// The supervised command and how to treat it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupervisorConfig {
+    /// Program and arguments, not run through a shell
+    pub command: Vec<String>,
+    /// Time between SIGTERM and SIGKILL
+    pub kill_timeout: Duration,
+    /// Starts the command again when it exits on its own
+    pub restart_on_exit: bool,
+    /// Prefix of the configuration variables
+    pub prefix: String,
+}
+
+impl SupervisorConfig {
+    /// Runs `command` with the default timeout and prefix, no restart on
+    /// exit
+    pub fn new(command: Vec<String>) -> Self {
+        Self {
+            command,
+            kill_timeout: humantime::parse_duration(DEFAULT_KILL_TIMEOUT).expect("valid default"),
+            restart_on_exit: false,
+            prefix: env_config::DEFAULT_PREFIX.to_string(),
+        }
+    }
+
+    /// The command as typed, for messages
+    fn display(&self) -> String {
+        self.command.join(" ")
+    }
+}
+
+/// How one instance of the command ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChildStatus {
+    Exited(i32),
+    /// Killed by a signal
+    Signaled,
+    /// Could not be started
+    SpawnFailed(String),
+}
+
+impl ChildStatus {
+    /// True after an exit with status 0
+    pub fn success(&self) -> bool {
+        *self == ChildStatus::Exited(0)
+    }
+}
+
+impl From<ExitStatus> for ChildStatus {
+    fn from(status: ExitStatus) -> Self {
+        match status.code() {
+            Some(code) => ChildStatus::Exited(code),
+            None => ChildStatus::Signaled,
+        }
+    }
+}
+
+impl fmt::Display for ChildStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChildStatus::Exited(code) => write!(f, "exited with status {code}"),
+            ChildStatus::Signaled => write!(f, "was killed by a signal"),
+            ChildStatus::SpawnFailed(error) => write!(f, "could not be started: {error}"),
+        }
+    }
+}
+
+/// Why the supervision ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ended {
+    /// Stopped by the watcher, or never started
+    Stopped,
+    /// The command ended on its own and is not restarted
+    Exited(ChildStatus),
+}
+
+/// One configuration to start the command with
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Launch {
+    version: u64,
+    env: Vec<(String, String)>,
+}
+
+#[derive(Debug)]
+enum Control {
+    Launch(Launch),
+    Stop,
+}
+
+/// Runs the command in a task of its own, restarted on every launch
+#[derive(Debug)]
+pub struct Supervisor {
+    control: mpsc::UnboundedSender<Control>,
+    task: Option<JoinHandle<Ended>>,
+    prefix: String,
+}
+
+impl Supervisor {
+    /// Starts the task; the command starts with the first configuration
+    pub fn spawn(config: SupervisorConfig) -> Self {
+        let (control, receiver) = mpsc::unbounded_channel();
+        let prefix = config.prefix.clone();
+        Self {
+            control,
+            task: Some(tokio::spawn(supervise(config, receiver))),
+            prefix,
+        }
+    }
+
+    /// The notifier forwarding loads to the task
+    pub fn notifier(&self) -> SupervisorNotifier {
+        SupervisorNotifier {
+            control: self.control.clone(),
+            prefix: self.prefix.clone(),
+        }
+    }
+
+    /// Resolves when the command ended for good, never after that
+    pub async fn exited(&mut self) -> Ended {
+        let Some(ref mut task) = self.task else {
+            return std::future::pending().await;
+        };
+        let ended = task.await.unwrap_or_else(|e| {
+            tracing::error!(error = %e, "supervisor task failed");
+            Ended::Stopped
+        });
+        self.task = None;
+        ended
+    }
+
+    /// Stops the command, SIGTERM then SIGKILL, and waits for it
+    pub async fn stop(mut self) {
+        let _ = self.control.send(Control::Stop);
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+/// Forwards the loads of the watched file to a [`Supervisor`]
+#[derive(Debug)]
+pub struct SupervisorNotifier {
+    control: mpsc::UnboundedSender<Control>,
+    prefix: String,
+}
+
+impl Notifier for SupervisorNotifier {
+    fn name(&self) -> &str {
+        "run"
+    }
+
+    /// The initial load and the reloads: a rewrite with identical content
+    /// is not a reload
+    fn accepts(&self, event: &ConfigEvent) -> bool {
+        matches!(event.kind, EventKind::Initial | EventKind::Reload) && event.config.is_some()
+    }
+
+    async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+        let Some(ref config) = event.config else {
+            return Ok(());
+        };
+        let version = event.version().unwrap_or_default();
+        let mut env = env_config::to_env_vars(&self.prefix, &serde_json::to_value(config)?);
+        if let Some(ref file) = event.file {
+            env.push(("CONFIG_PATH".to_string(), file.display().to_string()));
+        }
+        env.push(("CONFIG_VERSION".to_string(), version.to_string()));
+        // Closed once the command ended for good: the watcher is stopping
+        let _ = self.control.send(Control::Launch(Launch { version, env }));
+        Ok(())
+    }
+}
+
+/// The supervisor's task: runs until stopped, or until the command exits
+/// and is not to be restarted
+async fn supervise(
+    config: SupervisorConfig,
+    mut control: mpsc::UnboundedReceiver<Control>,
+) -> Ended {
+    let mut launch: Option<Launch> = None;
+    let mut child: Option<Child> = None;
+    let mut restart_at: Option<Instant> = None;
+    loop {
+        let status = tokio::select! {
+            message = control.recv() => {
+                let next = match message {
+                    Some(Control::Launch(next)) => latest(next, &mut control),
+                    Some(Control::Stop) | None => None,
+                };
+                let Some(next) = next else {
+                    if let Some(running) = child.take() {
+                        terminate(running, config.kill_timeout).await;
+                    }
+                    return Ended::Stopped;
+                };
+                if let Some(running) = child.take() {
+                    tracing::info!(version = next.version, "configuration changed, restarting the command");
+                    let status = terminate(running, config.kill_timeout).await;
+                    tracing::debug!(%status, "command stopped");
+                }
+                restart_at = None;
+                match start(&config, &next) {
+                    Ok(started) => {
+                        child = Some(started);
+                        launch = Some(next);
+                        continue;
+                    }
+                    Err(status) => {
+                        launch = Some(next);
+                        status
+                    }
+                }
+            }
+            status = wait(&mut child) => {
+                child = None;
+                status
+            }
+            () = sleep_until(restart_at) => {
+                restart_at = None;
+                let Some(ref last) = launch else { continue };
+                match start(&config, last) {
+                    Ok(started) => {
+                        child = Some(started);
+                        continue;
+                    }
+                    Err(status) => status,
+                }
+            }
+        };
+
+        // The command ended without being asked to
+        if !config.restart_on_exit {
+            tracing::warn!(command = config.display(), %status, "command ended");
+            return Ended::Exited(status);
+        }
+        tracing::warn!(
+            command = config.display(),
+            %status,
+            "command ended, restarting it in {}s",
+            RESTART_DELAY.as_secs()
+        );
+        restart_at = Some(Instant::now() + RESTART_DELAY);
+    }
+}
+
+/// The last of the launches already queued after `first`; `None` when a
+/// stop is queued
+fn latest(first: Launch, control: &mut mpsc::UnboundedReceiver<Control>) -> Option<Launch> {
+    let mut next = first;
+    while let Ok(message) = control.try_recv() {
+        match message {
+            Control::Launch(launch) => next = launch,
+            Control::Stop => return None,
+        }
+    }
+    Some(next)
+}
+
+/// Starts one instance with `launch`'s environment
+fn start(config: &SupervisorConfig, launch: &Launch) -> Result<Child, ChildStatus> {
+    let (program, args) = config
+        .command
+        .split_first()
+        .ok_or_else(|| ChildStatus::SpawnFailed("no command given".to_string()))?;
+    let child = Command::new(program)
+        .args(args)
+        .envs(launch.env.iter().map(|(name, value)| (name, value)))
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| ChildStatus::SpawnFailed(e.to_string()))?;
+    tracing::info!(
+        command = config.display(),
+        pid = child.id(),
+        version = launch.version,
+        "command started"
+    );
+    Ok(child)
+}
+
+/// How the running instance ends; pending when there is none
+async fn wait(child: &mut Option<Child>) -> ChildStatus {
+    match child {
+        Some(child) => match child.wait().await {
+            Ok(status) => status.into(),
+            Err(e) => ChildStatus::SpawnFailed(e.to_string()),
+        },
+        None => std::future::pending().await,
+    }
+}
+
+/// Resolves at `at`; pending when there is nothing to wait for
+async fn sleep_until(at: Option<Instant>) {
+    match at {
+        Some(at) => tokio::time::sleep_until(at).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// SIGTERM, then SIGKILL if it has not exited within `timeout`
+async fn terminate(mut child: Child, timeout: Duration) -> ChildStatus {
+    if let Some(pid) = child.id()
+        && send_sigterm(pid)
+        && let Ok(status) = tokio::time::timeout(timeout, child.wait()).await
+    {
+        return status.map_or_else(|e| ChildStatus::SpawnFailed(e.to_string()), Into::into);
+    }
+    tracing::warn!(
+        pid = child.id(),
+        "command did not stop within {}, killing it",
+        humantime::format_duration(timeout)
+    );
+    let _ = child.start_kill();
+    match child.wait().await {
+        Ok(status) => status.into(),
+        Err(e) => ChildStatus::SpawnFailed(e.to_string()),
+    }
+}
+
+/// Asks the process to exit; false when it cannot be asked
+#[cfg(unix)]
+fn send_sigterm(pid: u32) -> bool {
+    match signal_pid::send(pid, Signal::Term) {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!(pid, error = %e, "cannot send SIGTERM");
+            false
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn send_sigterm(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::output::{Event, Summary};
+    use crate::overrides::Overrides;
+    use std::path::Path;
+    use std::time::SystemTime;
+
+    fn launch(version: u64) -> Control {
+        Control::Launch(Launch {
+            version,
+            env: vec![("CONFIG_VERSION".to_string(), version.to_string())],
+        })
+    }
+
+    /// A shell script as the command, writing to `log`
+    fn script(body: &str) -> SupervisorConfig {
+        SupervisorConfig {
+            kill_timeout: Duration::from_millis(500),
+            ..SupervisorConfig::new(vec!["sh".into(), "-c".into(), body.into()])
+        }
+    }
+
+    async fn read_until(log: &Path, expected: &str) -> String {
+        for _ in 0..100 {
+            let text = std::fs::read_to_string(log).unwrap_or_default();
+            if text.contains(expected) {
+                return text;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!(
+            "{expected:?} never logged: {:?}",
+            std::fs::read_to_string(log)
+        );
+    }
+
+    #[test]
+    fn test_accepts_loads_only() {
+        let (control, _receiver) = mpsc::unbounded_channel();
+        let notifier = SupervisorNotifier {
+            control,
+            prefix: "CW".to_string(),
+        };
+        let config = AppConfig::example();
+        let overrides = Overrides::default();
+        let loaded = Event::Loaded {
+            file: Path::new("/etc/app.json"),
+            version: 2,
+            initial: false,
+            summary: Summary {
+                config: &config,
+                overrides: &overrides,
+            },
+            changes: Some(&[]),
+            flags: None,
+            stand_in: None,
+        };
+        let mut event = ConfigEvent::new(&loaded, SystemTime::UNIX_EPOCH).unwrap();
+        assert!(notifier.accepts(&event));
+        event.kind = EventKind::Failure;
+        assert!(!notifier.accepts(&event));
+    }
+
+    #[tokio::test]
+    async fn test_restarts_with_the_new_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log");
+        let config = script(&format!(
+            "echo \"start $CONFIG_VERSION\" >> {0}; trap 'echo \"term $CONFIG_VERSION\" >> {0}; exit 0' TERM; while :; do sleep 0.05; done",
+            log.display()
+        ));
+        let supervisor = Supervisor::spawn(config);
+        supervisor.control.send(launch(1)).unwrap();
+        read_until(&log, "start 1").await;
+        supervisor.control.send(launch(2)).unwrap();
+        read_until(&log, "start 2").await;
+        supervisor.stop().await;
+        let text = read_until(&log, "term 2").await;
+        assert_eq!(text, "start 1\nterm 1\nstart 2\nterm 2\n");
+    }
+
+    #[tokio::test]
+    async fn test_kills_a_command_ignoring_sigterm() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log");
+        let config = script(&format!(
+            "trap 'echo ignored >> {0}' TERM; echo started >> {0}; while :; do sleep 0.05; done",
+            log.display()
+        ));
+        let supervisor = Supervisor::spawn(config);
+        supervisor.control.send(launch(1)).unwrap();
+        read_until(&log, "started").await;
+        let started = Instant::now();
+        supervisor.stop().await;
+        assert!(started.elapsed() >= Duration::from_millis(500));
+        read_until(&log, "ignored").await;
+    }
+
+    #[tokio::test]
+    async fn test_reports_or_restarts_a_command_exiting_on_its_own() {
+        let mut supervisor = Supervisor::spawn(script("exit 3"));
+        supervisor.control.send(launch(1)).unwrap();
+        assert_eq!(
+            supervisor.exited().await,
+            Ended::Exited(ChildStatus::Exited(3))
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log");
+        let config = SupervisorConfig {
+            restart_on_exit: true,
+            ..script(&format!("echo run >> {}; exit 1", log.display()))
+        };
+        let mut supervisor = Supervisor::spawn(config);
+        supervisor.control.send(launch(1)).unwrap();
+        read_until(&log, "run\nrun\n").await;
+        let exited = tokio::time::timeout(Duration::from_millis(100), supervisor.exited()).await;
+        assert!(exited.is_err(), "restarted, not ended");
+        supervisor.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_a_missing_program_is_a_failure() {
+        let mut supervisor =
+            Supervisor::spawn(SupervisorConfig::new(vec!["/nonexistent/cmd".into()]));
+        supervisor.control.send(launch(1)).unwrap();
+        let Ended::Exited(status) = supervisor.exited().await else {
+            panic!("expected an exit");
+        };
+        assert!(matches!(status, ChildStatus::SpawnFailed(_)), "{status}");
+        assert!(!status.success());
+    }
+}
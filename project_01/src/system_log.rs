@@ -0,0 +1,306 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`std::os::unix::net::UnixDatagram`**: Both syslog (`/dev/log`) and
+  journald (`/run/systemd/journal/socket`) take one datagram per record
+- **`#[cfg(all(unix, feature = "system-log"))]`**: The module only exists on
+  Unix builds with the `system-log` feature (on by default)
+- **`Layer`**: `SystemLogLayer` sits next to the env filter like `JsonLayer`
+
+**Design decisions**:
+- `--log-target syslog` sends `<PRI>config-watcher[PID]: message key=value`
+  lines; the facility comes from `--syslog-facility`
+- `--log-target journald` uses the native protocol, so every field is a
+  journal field: `CONFIG_PATH` (from `path`), `EVENT`, `ERROR_KIND` (from
+  `error.kind`), and the others upper-cased (`duration_ms` becomes
+  `DURATION_MS`); multi-line values use the length-prefixed encoding
+- Levels map to priorities: error is `err` (3), warn is `warning` (4), info
+  is `info` (6), debug and trace are `debug` (7)
+- The socket is connected at startup, so a missing daemon is an error right
+  away instead of logs silently going nowhere; a send that fails later
+  reconnects once, then the record is dropped
+
+******************************************************************************/
+
+use crate::logging::{self, Facility};
+use serde_json::{Map, Value};
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Socket of the local syslog daemon
+pub const SYSLOG_SOCKET: &str = "/dev/log";
+
+/// Socket of journald's native protocol
+pub const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Identifier attached to every record
+pub const IDENTIFIER: &str = "config-watcher";
+
+/// Record format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Syslog(Facility),
+    Journald,
+}
+
+/// Sends every event to syslog or journald
+#[derive(Debug)]
+pub struct SystemLogLayer {
+    path: PathBuf,
+    protocol: Protocol,
+    socket: Mutex<UnixDatagram>,
+}
+
+impl SystemLogLayer {
+    /// Connects to the daemon listening on `path`
+    pub fn connect(path: &Path, protocol: Protocol) -> io::Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            protocol,
+            socket: Mutex::new(connect(path)?),
+        })
+    }
+
+    /// Sends one record, reconnecting once if the daemon went away
+    fn send(&self, datagram: &[u8]) {
+        let mut socket = self.socket.lock().unwrap_or_else(|e| e.into_inner());
+        if socket.send(datagram).is_err()
+            && let Ok(fresh) = connect(&self.path)
+        {
+            *socket = fresh;
+            let _ = socket.send(datagram);
+        }
+    }
+}
+
+fn connect(path: &Path) -> io::Result<UnixDatagram> {
+    let socket = UnixDatagram::unbound()?;
+    socket.connect(path)?;
+    Ok(socket)
+}
+
+/// Syslog severity of a tracing level
+pub fn severity(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        _ => 7,
+    }
+}
+
+/// A field value as text; arrays become one element per line
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items.iter().map(text).collect::<Vec<_>>().join("\n"),
+        other => other.to_string(),
+    }
+}
+
+/// One syslog line: `<PRI>IDENT[PID]: message key=value ...`
+///
+/// Values are JSON-encoded, so a record never spans several lines.
+pub fn syslog_record(
+    facility: Facility,
+    level: &Level,
+    message: &str,
+    fields: &Map<String, Value>,
+) -> String {
+    let priority = u16::from(facility.code()) * 8 + u16::from(severity(level));
+    let mut record = format!(
+        "<{priority}>{IDENTIFIER}[{}]: {message}",
+        std::process::id()
+    );
+    for (key, value) in fields {
+        record.push_str(&format!(" {key}={value}"));
+    }
+    record
+}
+
+/// Journal field name for a tracing field
+pub fn journald_name(field: &str) -> String {
+    match field {
+        "path" => "CONFIG_PATH".to_string(),
+        field => field
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' => c.to_ascii_uppercase(),
+                _ => '_',
+            })
+            .collect::<String>()
+            .trim_start_matches('_')
+            .to_string(),
+    }
+}
+
+/// One record of journald's native protocol
+pub fn journald_record(level: &Level, message: &str, fields: &Map<String, Value>) -> Vec<u8> {
+    let mut record = Vec::new();
+    let mut push = |name: &str, value: &str| {
+        if name.is_empty() {
+            return;
+        }
+        if value.contains('\n') {
+            // NAME\n, little-endian 64-bit length, the value, \n
+            record.extend_from_slice(name.as_bytes());
+            record.push(b'\n');
+            record.extend_from_slice(&(value.len() as u64).to_le_bytes());
+            record.extend_from_slice(value.as_bytes());
+        } else {
+            record.extend_from_slice(format!("{name}={value}").as_bytes());
+        }
+        record.push(b'\n');
+    };
+    push("MESSAGE", message);
+    push("PRIORITY", &severity(level).to_string());
+    push("SYSLOG_IDENTIFIER", IDENTIFIER);
+    for (key, value) in fields {
+        push(&journald_name(key), &text(value));
+    }
+    record
+}
+
+impl<S> Layer<S> for SystemLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        logging::store_span_fields(attrs, id, &ctx);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        logging::update_span_fields(id, values, &ctx);
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let (message, fields, _) = logging::event_fields(event, &ctx);
+        let message = message.as_ref().map(text).unwrap_or_default();
+        let level = event.metadata().level();
+        match self.protocol {
+            Protocol::Syslog(facility) => {
+                self.send(syslog_record(facility, level, &message, &fields).as_bytes())
+            }
+            Protocol::Journald => self.send(&journald_record(level, &message, &fields)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// A mock daemon: binds `name` in a temporary directory
+    fn daemon(name: &str) -> (tempfile::TempDir, PathBuf, UnixDatagram) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(name);
+        let socket = UnixDatagram::bind(&path).unwrap();
+        socket
+            .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+        (dir, path, socket)
+    }
+
+    fn receive(socket: &UnixDatagram) -> Vec<u8> {
+        let mut buf = vec![0; 65536];
+        let len = socket.recv(&mut buf).unwrap();
+        buf.truncate(len);
+        buf
+    }
+
+    #[test]
+    fn test_syslog_priority_and_message() {
+        let (_dir, path, daemon) = daemon("log");
+        let layer = SystemLogLayer::connect(&path, Protocol::Syslog(Facility::Daemon)).unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("reload", path = "app.json");
+            let _entered = span.enter();
+            tracing::error!(error.kind = "validation_failed", "reload failed");
+            tracing::warn!("degraded");
+            tracing::info!(version = 2, "reloaded");
+        });
+
+        let pid = std::process::id();
+        let records: Vec<String> = (0..3)
+            .map(|_| String::from_utf8(receive(&daemon)).unwrap())
+            .collect();
+        // daemon is facility 3: 3 * 8 + severity
+        assert_eq!(
+            records,
+            [
+                format!(
+                    r#"<27>config-watcher[{pid}]: reload failed path="app.json" error.kind="validation_failed""#
+                ),
+                format!(r#"<28>config-watcher[{pid}]: degraded path="app.json""#),
+                format!(r#"<30>config-watcher[{pid}]: reloaded path="app.json" version=2"#),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_syslog_facility() {
+        let record = syslog_record(Facility::Local3, &Level::DEBUG, "m", &Map::new());
+        // local3 is facility 19: 19 * 8 + 7
+        assert!(record.starts_with("<159>config-watcher["), "{record}");
+    }
+
+    #[test]
+    fn test_journald_fields() {
+        let (_dir, path, daemon) = daemon("socket");
+        let layer = SystemLogLayer::connect(&path, Protocol::Journald).unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::error!(
+                path = "app.json",
+                event = "load_failed",
+                error.kind = "validation_failed",
+                findings = "a: bad\nb: worse\n",
+                "failed to load initial configuration"
+            );
+        });
+
+        let record = receive(&daemon);
+        let text = String::from_utf8_lossy(&record);
+        for line in [
+            "MESSAGE=failed to load initial configuration\n",
+            "PRIORITY=3\n",
+            "SYSLOG_IDENTIFIER=config-watcher\n",
+            "CONFIG_PATH=app.json\n",
+            "EVENT=load_failed\n",
+            "ERROR_KIND=validation_failed\n",
+        ] {
+            assert!(text.contains(line), "{line:?} missing from {text:?}");
+        }
+        // Multi-line values are length-prefixed
+        let mut findings = b"FINDINGS\n".to_vec();
+        findings.extend_from_slice(&15u64.to_le_bytes());
+        findings.extend_from_slice(b"a: bad\nb: worse\n");
+        assert!(
+            record.windows(findings.len()).any(|w| w == findings),
+            "{text:?}"
+        );
+    }
+
+    #[test]
+    fn test_journald_names() {
+        assert_eq!(journald_name("path"), "CONFIG_PATH");
+        assert_eq!(journald_name("error.kind"), "ERROR_KIND");
+        assert_eq!(journald_name("duration_ms"), "DURATION_MS");
+        assert_eq!(journald_name("_secret"), "SECRET");
+    }
+
+    #[test]
+    fn test_missing_daemon_fails_at_connect() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = SystemLogLayer::connect(&dir.path().join("log"), Protocol::Journald).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
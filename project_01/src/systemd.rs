@@ -0,0 +1,550 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`std::os::unix::net::UnixDatagram`**: The `sd_notify` protocol is one
+  datagram of `KEY=value` lines sent to `$NOTIFY_SOCKET`, so no crate is
+  needed
+- **`std::os::linux::net::SocketAddrExt`**: A `NOTIFY_SOCKET` starting with
+  `@` names a socket in Linux's abstract namespace
+- **`#[cfg(all(unix, feature = "systemd"))]`**: The module only exists on
+  Unix builds with the `systemd` feature (on by default)
+- **`Arc<Mutex<...>>`**: Every watcher's emitter shares one `Systemd`, so
+  readiness is decided once for all the watched files
+
+**Design decisions**:
+- Without `NOTIFY_SOCKET` nothing is created: the emitter has no `Systemd`
+  and no socket is opened
+- `Systemd` reads the events the watchers already emit, like the audit log
+  and the notifiers; the watch loop knows nothing about systemd
+- `READY=1` goes out once every watcher made its first load attempt, or,
+  with `--require-initial`, once every file loaded successfully
+- `STATUS=` is sent whenever the text changes: `serving v2.0.0, last reload
+  12:03:11`, `degraded: validation failing`, `serving fallback v1.0.0`,
+  `serving restored v1.0.0`...
+  one `file: status` part per file when several are watched
+- A reload request (`Event::ReloadRequested`: SIGHUP, or `RELOAD` on the
+  push socket) sends `RELOADING=1`; `READY=1` follows once every watcher
+  has reported the outcome of its reload. `STOPPING=1`
+  goes out with the shutdown event
+- `WATCHDOG=1` pings come from their own task at half of `WATCHDOG_USEC`,
+  and only while the watch loops tick (`Probes::liveness`, as for `/livez`):
+  a blocked runtime or a stuck loop stops them, and systemd restarts the
+  service
+- A failed send is logged once as a warning, then at debug level: the
+  watcher keeps running without systemd
+
+******************************************************************************/
+
+use crate::cli::TimestampFormat;
+use crate::output::{Event, StandIn};
+use crate::probes::Probes;
+use crate::report;
+use crate::timestamp::Timestamps;
+use std::io;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, warn};
+
+/// Reports the state of the watchers to systemd through `sd_notify`
+#[derive(Debug, Clone)]
+pub struct Systemd {
+    service: Arc<Mutex<Service>>,
+}
+
+#[derive(Debug)]
+struct Service {
+    socket: UnixDatagram,
+    addr: SocketAddr,
+    /// Number of watchers, registered as their first event arrives
+    watchers: usize,
+    files: Vec<FileState>,
+    require_initial: bool,
+    ready: bool,
+    reloading: bool,
+    /// Last `STATUS=` sent
+    status: String,
+    clock: Timestamps,
+    warned: bool,
+}
+
+/// What systemd is told about one watched file
+#[derive(Debug)]
+struct FileState {
+    path: PathBuf,
+    /// Application version of the configuration served, if any
+    serving: Option<String>,
+    reloaded_at: Option<SystemTime>,
+    /// Error code of the last load, if it failed
+    failing: Option<&'static str>,
+    /// What serves in place of the file, if anything (`StandIn::name`)
+    stand_in: Option<&'static str>,
+    attempted: bool,
+    /// A requested reload is under way
+    pending: bool,
+}
+
+impl Systemd {
+    /// Talks to `$NOTIFY_SOCKET` for `watchers` watchers; `None` when the
+    /// variable is not set, or when the socket cannot be used (logged)
+    pub fn from_env(watchers: usize, require_initial: bool) -> Option<Self> {
+        let target = std::env::var("NOTIFY_SOCKET").ok()?;
+        match Self::new(&target, watchers, require_initial) {
+            Ok(systemd) => Some(systemd),
+            Err(e) => {
+                warn!(notify_socket = %target, error = %e, "systemd notifications disabled");
+                None
+            }
+        }
+    }
+
+    /// Talks to the socket named by `target`, a path or `@abstract-name`
+    pub fn new(target: &str, watchers: usize, require_initial: bool) -> io::Result<Self> {
+        Ok(Self {
+            service: Arc::new(Mutex::new(Service {
+                socket: UnixDatagram::unbound()?,
+                addr: address(target)?,
+                watchers,
+                files: Vec::new(),
+                require_initial,
+                ready: false,
+                reloading: false,
+                status: String::new(),
+                clock: Timestamps::new(TimestampFormat::TimeOnly, false),
+                warned: false,
+            })),
+        })
+    }
+
+    /// Updates the state after `event` and tells systemd what changed
+    pub fn record(&self, event: &Event<'_>) {
+        let mut service = self.service.lock().unwrap_or_else(|e| e.into_inner());
+        let mut lines = Vec::new();
+        let now = SystemTime::now();
+        match *event {
+            Event::Started { file, .. } => {
+                service.file(file);
+            }
+            Event::Loaded {
+                file,
+                summary,
+                stand_in,
+                ..
+            } => {
+                let state = service.file(file);
+                state.serving = Some(summary.config.version.clone());
+                state.stand_in = stand_in.map(StandIn::name);
+                state.reloaded_at = Some(now);
+                state.loaded();
+            }
+            Event::Unchanged { file, .. } => {
+                let state = service.file(file);
+                state.reloaded_at = Some(now);
+                state.loaded();
+            }
+            Event::LoadFailed { file, error, .. } => {
+                let state = service.file(file);
+                state.failing = Some(report::code(error));
+                state.attempted = true;
+                state.pending = false;
+            }
+            Event::FileError { file, error } => {
+                service.file(file).failing = Some(report::code(error));
+            }
+            Event::ReloadRequested { .. } if service.ready => {
+                service.reloading = true;
+                for state in &mut service.files {
+                    state.pending = true;
+                }
+                lines.push("RELOADING=1".to_string());
+            }
+            Event::Shutdown { .. } => lines.push("STOPPING=1".to_string()),
+            _ => return,
+        }
+
+        if !service.ready && service.is_ready() {
+            service.ready = true;
+            lines.push("READY=1".to_string());
+        }
+        if service.reloading && service.files.iter().all(|state| !state.pending) {
+            service.reloading = false;
+            lines.push("READY=1".to_string());
+        }
+        let status = match event {
+            Event::Shutdown { .. } => "stopping".to_string(),
+            _ if service.reloading => "reloading".to_string(),
+            _ => service.describe(),
+        };
+        if status != service.status {
+            lines.push(format!("STATUS={status}"));
+            service.status = status;
+        }
+        if !lines.is_empty() {
+            service.send(&lines.join("\n"));
+        }
+    }
+
+    /// Sends `WATCHDOG=1` every `every` from a task of its own, as long as
+    /// no watch loop of `probes` is stuck
+    pub fn spawn_watchdog(&self, every: Duration, probes: Probes) -> tokio::task::JoinHandle<()> {
+        let systemd = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(every);
+            let mut stale = false;
+            loop {
+                ticker.tick().await;
+                match probes.liveness(SystemTime::now()) {
+                    Ok(_) => {
+                        stale = false;
+                        systemd
+                            .service
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .send("WATCHDOG=1");
+                    }
+                    Err(age) if !stale => {
+                        stale = true;
+                        warn!(since = ?age, "a watch loop is stuck, no more systemd watchdog pings");
+                    }
+                    Err(_) => {}
+                }
+            }
+        })
+    }
+}
+
+impl Service {
+    /// The state of `path`, registered on first use
+    fn file(&mut self, path: &Path) -> &mut FileState {
+        let index = match self.files.iter().position(|state| state.path == path) {
+            Some(index) => index,
+            None => {
+                self.files.push(FileState {
+                    path: path.to_path_buf(),
+                    serving: None,
+                    reloaded_at: None,
+                    failing: None,
+                    stand_in: None,
+                    attempted: false,
+                    pending: false,
+                });
+                self.files.len() - 1
+            }
+        };
+        &mut self.files[index]
+    }
+
+    /// Whether every watcher got as far as readiness asks
+    fn is_ready(&self) -> bool {
+        self.files.len() >= self.watchers
+            && self
+                .files
+                .iter()
+                .all(|state| state.attempted && (!self.require_initial || state.serving.is_some()))
+    }
+
+    /// The `STATUS=` text: one part per file when there are several
+    fn describe(&self) -> String {
+        let describe = |state: &FileState| match (&state.serving, state.failing, state.stand_in) {
+            (Some(_), Some(code), _) => format!("degraded: {} failing", failing(code)),
+            (Some(version), None, Some(stand_in)) => format!("serving {stand_in} v{version}"),
+            (Some(version), None, None) => {
+                let at = state
+                    .reloaded_at
+                    .and_then(|at| self.clock.render(at))
+                    .unwrap_or_default();
+                // `12:03:11.250` without the milliseconds
+                format!(
+                    "serving v{version}, last reload {}",
+                    at.get(..8).unwrap_or(&at)
+                )
+            }
+            (None, Some(code), _) => {
+                format!("no valid configuration: {} failing", failing(code))
+            }
+            (None, None, _) => "starting".to_string(),
+        };
+        match self.files.as_slice() {
+            [] => "starting".to_string(),
+            [state] => describe(state),
+            files => files
+                .iter()
+                .map(|state| {
+                    let name = state.path.file_name().unwrap_or(state.path.as_os_str());
+                    format!("{}: {}", name.to_string_lossy(), describe(state))
+                })
+                .collect::<Vec<_>>()
+                .join("; "),
+        }
+    }
+
+    /// Sends one datagram; failures are only logged
+    fn send(&mut self, message: &str) {
+        if let Err(e) = self.socket.send_to_addr(message.as_bytes(), &self.addr) {
+            if self.warned {
+                debug!(error = %e, "systemd notification not sent");
+            } else {
+                self.warned = true;
+                warn!(error = %e, "systemd notification not sent");
+            }
+        }
+    }
+}
+
+impl FileState {
+    fn loaded(&mut self) {
+        self.failing = None;
+        self.attempted = true;
+        self.pending = false;
+    }
+}
+
+/// What fails, for an error code
+fn failing(code: &str) -> &'static str {
+    match code {
+        "validation_failed" => "validation",
+        "invalid_json" | "schema_mismatch" | "unsupported_format" => "parsing",
+        "file_not_found" | "metadata_error" | "read_error" => "reading",
+        _ => "loading",
+    }
+}
+
+/// The address of `$NOTIFY_SOCKET`: a path, or `@name` in the abstract
+/// namespace (Linux only)
+fn address(target: &str) -> io::Result<SocketAddr> {
+    match target.strip_prefix('@') {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Some(name) => {
+            #[cfg(target_os = "android")]
+            use std::os::android::net::SocketAddrExt;
+            #[cfg(target_os = "linux")]
+            use std::os::linux::net::SocketAddrExt;
+            SocketAddr::from_abstract_name(name)
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        Some(_) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "abstract sockets are Linux only",
+        )),
+        None => SocketAddr::from_pathname(target),
+    }
+}
+
+/// Half of `WATCHDOG_USEC`, when the watchdog is enabled for this process
+/// (`WATCHDOG_PID` unset or equal to `pid`)
+pub fn watchdog_interval(
+    usec: Option<&str>,
+    watchdog_pid: Option<&str>,
+    pid: u32,
+) -> Option<Duration> {
+    if watchdog_pid.is_some_and(|watchdog_pid| watchdog_pid.parse() != Ok(pid)) {
+        return None;
+    }
+    let usec: u64 = usec?.parse().ok().filter(|usec| *usec > 0)?;
+    Some(Duration::from_micros(usec) / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::error::ConfigError;
+    use crate::output::{ShutdownReason, Summary, WatchState};
+    use crate::overrides::Overrides;
+    use crate::validation::ValidationReport;
+
+    /// A fake `NOTIFY_SOCKET` and a `Systemd` talking to it
+    fn fake(watchers: usize, require_initial: bool) -> (tempfile::TempDir, UnixDatagram, Systemd) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notify");
+        let socket = UnixDatagram::bind(&path).unwrap();
+        socket.set_nonblocking(true).unwrap();
+        let systemd = Systemd::new(path.to_str().unwrap(), watchers, require_initial).unwrap();
+        (dir, socket, systemd)
+    }
+
+    /// Every datagram received so far
+    fn received(socket: &UnixDatagram) -> Vec<String> {
+        let mut messages = Vec::new();
+        let mut buffer = [0; 1024];
+        while let Ok(len) = socket.recv(&mut buffer) {
+            messages.push(String::from_utf8_lossy(&buffer[..len]).into_owned());
+        }
+        messages
+    }
+
+    fn config(version: &str) -> AppConfig {
+        serde_json::from_value(serde_json::json!({ "app_name": "TestApp", "version": version }))
+            .unwrap()
+    }
+
+    fn loaded(systemd: &Systemd, file: &Path, config: &AppConfig) {
+        systemd.record(&Event::Loaded {
+            file,
+            version: 1,
+            initial: false,
+            summary: Summary {
+                config,
+                overrides: &Overrides::default(),
+            },
+            changes: None,
+            flags: None,
+            stand_in: None,
+        });
+    }
+
+    fn failed(systemd: &Systemd, file: &Path) {
+        let mut report = ValidationReport::new();
+        report.error("server.port", "must be between 1 and 65535");
+        let error = ConfigError::ValidationFailed { report }.into();
+        systemd.record(&Event::LoadFailed {
+            file,
+            initial: false,
+            retrying: true,
+            error: &error,
+        });
+    }
+
+    #[test]
+    fn test_startup_sequence() {
+        let (_dir, socket, systemd) = fake(2, false);
+        let (a, b) = (Path::new("/etc/a.json"), Path::new("/etc/b.json"));
+
+        failed(&systemd, a);
+        // b has not tried yet
+        assert_eq!(
+            received(&socket),
+            ["STATUS=no valid configuration: validation failing"]
+        );
+
+        loaded(&systemd, b, &config("2.0.0"));
+        let messages = received(&socket);
+        assert_eq!(messages.len(), 1);
+        assert!(
+            messages[0].starts_with(
+                "READY=1\nSTATUS=a.json: no valid configuration: validation failing; \
+                 b.json: serving v2.0.0, last reload "
+            ),
+            "{messages:?}"
+        );
+
+        // Nothing new, nothing sent
+        failed(&systemd, a);
+        assert!(received(&socket).is_empty());
+        systemd.record(&Event::Shutdown {
+            reason: ShutdownReason::Signal,
+            error: None,
+            files: &[],
+        });
+        assert_eq!(received(&socket), ["STOPPING=1\nSTATUS=stopping"]);
+    }
+
+    #[test]
+    fn test_ready_waits_for_a_valid_load_with_require_initial() {
+        let (_dir, socket, systemd) = fake(1, true);
+        let file = Path::new("config.json");
+
+        failed(&systemd, file);
+        assert_eq!(
+            received(&socket),
+            ["STATUS=no valid configuration: validation failing"]
+        );
+        loaded(&systemd, file, &config("1.0.0"));
+        let messages = received(&socket);
+        assert!(
+            messages[0].starts_with("READY=1\nSTATUS=serving v1.0.0, last reload "),
+            "{messages:?}"
+        );
+        // The time is HH:MM:SS
+        assert_eq!(messages[0].rsplit(' ').next().unwrap().len(), 8);
+
+        failed(&systemd, file);
+        assert_eq!(received(&socket), ["STATUS=degraded: validation failing"]);
+    }
+
+    #[test]
+    fn test_reload_sequence() {
+        let (_dir, socket, systemd) = fake(2, false);
+        let (a, b) = (Path::new("a.json"), Path::new("b.json"));
+
+        // Not ready yet: no reload markers
+        systemd.record(&Event::ReloadRequested { by: "SIGHUP" });
+        loaded(&systemd, a, &config("1.0.0"));
+        loaded(&systemd, b, &config("1.0.0"));
+        let messages = received(&socket);
+        assert!(messages.iter().all(|m| !m.contains("RELOADING")));
+        assert!(messages.last().unwrap().starts_with("READY=1\n"));
+
+        systemd.record(&Event::ReloadRequested { by: "SIGHUP" });
+        assert_eq!(received(&socket), ["RELOADING=1\nSTATUS=reloading"]);
+        loaded(&systemd, a, &config("2.0.0"));
+        // Still waiting for b
+        assert!(received(&socket).is_empty());
+        systemd.record(&Event::Unchanged {
+            file: b,
+            version: 1,
+        });
+        let messages = received(&socket);
+        assert_eq!(messages.len(), 1);
+        assert!(
+            messages[0].starts_with("READY=1\nSTATUS=a.json: serving v2.0.0"),
+            "{messages:?}"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_watchdog_pings() {
+        let (_dir, socket, systemd) = fake(1, false);
+        let every = watchdog_interval(Some("10000000"), None, 42).unwrap();
+        assert_eq!(every, Duration::from_secs(5));
+
+        let watchdog = systemd.spawn_watchdog(every, Probes::new(Duration::from_secs(5)));
+        tokio::time::sleep(every * 3 + every / 2).await;
+        watchdog.abort();
+        // One right away, then one every 5s
+        assert_eq!(received(&socket), ["WATCHDOG=1"; 4]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_watchdog_stops_while_a_loop_is_stuck() {
+        let (_dir, socket, systemd) = fake(1, false);
+        let every = Duration::from_secs(5);
+        let probes = Probes::new(Duration::from_secs(1));
+        let probe = probes.register(Path::new("config.json"));
+        // Last tick a minute ago: well past 3 intervals
+        let stuck = SystemTime::now() - Duration::from_secs(60);
+        probe.record(stuck, WatchState::Valid, 0);
+
+        let watchdog = systemd.spawn_watchdog(every, probes);
+        tokio::time::sleep(every * 2 + every / 2).await;
+        assert!(received(&socket).is_empty());
+
+        // The loop ticks again: so do the pings
+        probe.record(SystemTime::now(), WatchState::Valid, 0);
+        tokio::time::sleep(every).await;
+        watchdog.abort();
+        assert_eq!(received(&socket), ["WATCHDOG=1"]);
+    }
+
+    #[test]
+    fn test_watchdog_interval() {
+        assert_eq!(watchdog_interval(None, None, 42), None);
+        assert_eq!(
+            watchdog_interval(Some("3000000"), Some("42"), 42),
+            Some(Duration::from_millis(1500))
+        );
+        // Meant for another process
+        assert_eq!(watchdog_interval(Some("3000000"), Some("7"), 42), None);
+        assert_eq!(watchdog_interval(Some("0"), None, 42), None);
+        assert_eq!(watchdog_interval(Some("soon"), None, 42), None);
+    }
+
+    #[test]
+    fn test_abstract_and_path_addresses() {
+        let path = address("/run/systemd/notify").unwrap();
+        assert_eq!(path.as_pathname(), Some(Path::new("/run/systemd/notify")));
+        #[cfg(target_os = "linux")]
+        assert!(address("@/org/freedesktop/systemd1/notify").is_ok());
+    }
+}
@@ -0,0 +1,280 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`proptest::Strategy`**: A generator of values that also knows how to
+  shrink a failing one towards a simpler one
+- **`prop_oneof!` / `Just`**: Boundary values listed first, so shrinking
+  ends on them rather than on random noise
+- **`impl Arbitrary`**: `any::<AppConfig>()` works like `any::<u32>()`
+- **`#[cfg(any(test, feature = "testing"))]`**: Compiled for the crate's own
+  tests, and for other crates with the `testing` feature
+
+**Design decisions**:
+- Two families: `*_config()` mixes valid and boundary values (empty
+  strings, port 0, the limits of `TIMEOUT_RANGE`...), `valid_*()` only
+  produces configurations that pass `validate()`
+- Values are built from short, readable pieces (`"a.b"`, `10.0.0.0/33`)
+  plus a little arbitrary Unicode, so a shrunk counterexample reads like
+  a hand-written fixture
+- Durations stay under a century: longer ones only exercise humantime
+- The proxy, messaging and auth sections are left out (`None`); their own
+  modules test their rules on fixtures
+
+******************************************************************************/
+
+use crate::config::{
+    AppConfig, DatabaseConfig, ENVIRONMENTS, MAX_CONNECTIONS_WARNING, MAX_SHUTDOWN_GRACE,
+    ServerConfig, TIMEOUT_RANGE,
+};
+use proptest::collection::{hash_map, vec};
+use proptest::prelude::*;
+use proptest::sample::select;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Longest duration generated, about a century
+const MAX_SECS: u64 = 100 * 365 * 24 * 3600;
+
+/// Entries `allowed_ips` accepts
+const VALID_IPS: [&str; 4] = ["10.0.0.0/8", "192.168.1.1", "::1", "2001:db8::/32"];
+
+/// Any string: empty, blank, plain, or arbitrary Unicode
+pub fn text() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just(String::new()),
+        Just(" ".to_string()),
+        "[a-zA-Z0-9._-]{1,12}",
+        any::<String>(),
+    ]
+}
+
+/// A version: semver, a bare number, or any string
+pub fn version() -> impl Strategy<Value = String> {
+    prop_oneof!["[0-9]{1,3}\\.[0-9]{1,3}\\.[0-9]{1,3}", "[0-9]{1,3}", text()]
+}
+
+/// One of `ENVIRONMENTS`, or any string
+pub fn environment() -> impl Strategy<Value = String> {
+    prop_oneof![select(ENVIRONMENTS.to_vec()).prop_map(String::from), text()]
+}
+
+/// A duration at a validation bound, or anywhere up to a century
+pub fn duration() -> impl Strategy<Value = Duration> {
+    prop_oneof![
+        Just(Duration::ZERO),
+        Just(*TIMEOUT_RANGE.start()),
+        Just(*TIMEOUT_RANGE.end()),
+        Just(*TIMEOUT_RANGE.end() + Duration::from_nanos(1)),
+        Just(MAX_SHUTDOWN_GRACE),
+        (0..MAX_SECS, 0..1_000_000_000u32).prop_map(|(secs, nanos)| Duration::new(secs, nanos)),
+    ]
+}
+
+/// An `allowed_ips`/`denied_ips` entry, valid or not
+pub fn ip_entry() -> impl Strategy<Value = String> {
+    prop_oneof![
+        select(VALID_IPS.to_vec()).prop_map(String::from),
+        select(vec!["10.0.0.0/33", "localhost", ""]).prop_map(String::from),
+        (any::<std::net::Ipv4Addr>(), 0..=32u8)
+            .prop_map(|(addr, prefix)| format!("{addr}/{prefix}")),
+    ]
+}
+
+/// A server section, valid or at the edges of every rule
+pub fn server_config() -> impl Strategy<Value = ServerConfig> {
+    (
+        prop_oneof![Just("localhost".to_string()), text()],
+        prop_oneof![Just(0), Just(1), Just(u16::MAX), any::<u16>()],
+        any::<bool>(),
+        vec(ip_entry(), 0..4),
+        vec(ip_entry(), 0..4),
+        prop_oneof![
+            Just(0),
+            Just(1),
+            Just(MAX_CONNECTIONS_WARNING),
+            Just(MAX_CONNECTIONS_WARNING + 1),
+            Just(u32::MAX),
+            any::<u32>(),
+        ],
+        (duration(), duration(), duration()),
+    )
+        .prop_map(
+            |(
+                host,
+                port,
+                enable_ssl,
+                allowed_ips,
+                denied_ips,
+                max_connections,
+                (keep_alive, request_timeout, shutdown_grace),
+            )| ServerConfig {
+                host,
+                port,
+                enable_ssl,
+                allowed_ips,
+                denied_ips,
+                max_connections,
+                keep_alive,
+                request_timeout,
+                shutdown_grace,
+            },
+        )
+}
+
+/// A database section, valid or at the edges of every rule
+pub fn database_config() -> impl Strategy<Value = DatabaseConfig> {
+    (
+        prop_oneof![
+            Just("postgres://localhost/db".to_string()),
+            "postgres://[a-z]{1,8}:[a-z]{1,8}@[a-z]{1,8}:[0-9]{4}/[a-z]{1,8}",
+            text(),
+        ],
+        prop_oneof![Just(0), Just(1), Just(u32::MAX), any::<u32>()],
+        prop_oneof![Just(0), Just(u64::MAX), any::<u64>()],
+    )
+        .prop_map(
+            |(connection_string, pool_size, timeout_seconds)| DatabaseConfig {
+                connection_string,
+                pool_size,
+                timeout_seconds,
+            },
+        )
+}
+
+/// A feature map, with keys that need the bracket syntax now and then
+pub fn features() -> impl Strategy<Value = HashMap<String, bool>> {
+    let key = prop_oneof!["[a-z_]{1,12}", "[a-z]{1,4}\\.[a-z]{1,4}", text()];
+    hash_map(key, any::<bool>(), 0..8)
+}
+
+/// A whole configuration, valid or at the edges of every rule
+pub fn app_config() -> impl Strategy<Value = AppConfig> {
+    (
+        text(),
+        version(),
+        environment(),
+        proptest::option::of(server_config()),
+        proptest::option::of(database_config()),
+        features(),
+    )
+        .prop_map(
+            |(app_name, version, environment, server, database, features)| AppConfig {
+                app_name,
+                version,
+                environment,
+                server,
+                database,
+                proxy: None,
+                messaging: None,
+                auth: None,
+                features,
+            },
+        )
+}
+
+/// A server section that passes validation, warnings allowed
+pub fn valid_server_config() -> impl Strategy<Value = ServerConfig> {
+    let timeout = (TIMEOUT_RANGE.start().as_secs()..=TIMEOUT_RANGE.end().as_secs())
+        .prop_map(Duration::from_secs);
+    (
+        "[a-z]{1,10}(\\.[a-z]{2,5})?",
+        1..=u16::MAX,
+        any::<bool>(),
+        vec(select(VALID_IPS.to_vec()), 0..4),
+        1..=u32::MAX,
+        (
+            timeout.clone(),
+            timeout,
+            (0..=MAX_SHUTDOWN_GRACE.as_secs()).prop_map(Duration::from_secs),
+        ),
+    )
+        .prop_map(
+            |(
+                host,
+                port,
+                enable_ssl,
+                allowed_ips,
+                max_connections,
+                (keep_alive, request_timeout, shutdown_grace),
+            )| ServerConfig {
+                host,
+                port,
+                enable_ssl,
+                allowed_ips: allowed_ips.into_iter().map(String::from).collect(),
+                // Empty, so nothing is both allowed and denied
+                denied_ips: Vec::new(),
+                max_connections,
+                keep_alive,
+                request_timeout,
+                shutdown_grace,
+            },
+        )
+}
+
+/// A database section that passes validation
+pub fn valid_database_config() -> impl Strategy<Value = DatabaseConfig> {
+    (
+        "postgres://[a-z]{1,8}/[a-z]{1,8}",
+        1..=u32::MAX,
+        any::<u64>(),
+    )
+        .prop_map(
+            |(connection_string, pool_size, timeout_seconds)| DatabaseConfig {
+                connection_string,
+                pool_size,
+                timeout_seconds,
+            },
+        )
+}
+
+/// A whole configuration that passes validation, warnings allowed
+pub fn valid_app_config() -> impl Strategy<Value = AppConfig> {
+    (
+        "[A-Za-z][A-Za-z0-9]{0,10}",
+        "[0-9]{1,3}\\.[0-9]{1,3}\\.[0-9]{1,3}",
+        select(ENVIRONMENTS.to_vec()),
+        proptest::option::of(valid_server_config()),
+        proptest::option::of(valid_database_config()),
+        features(),
+    )
+        .prop_map(
+            |(app_name, version, environment, server, database, features)| AppConfig {
+                app_name,
+                version,
+                environment: environment.to_string(),
+                server,
+                database,
+                proxy: None,
+                messaging: None,
+                auth: None,
+                features,
+            },
+        )
+}
+
+impl Arbitrary for AppConfig {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        app_config().boxed()
+    }
+}
+
+impl Arbitrary for ServerConfig {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        server_config().boxed()
+    }
+}
+
+impl Arbitrary for DatabaseConfig {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        database_config().boxed()
+    }
+}
@@ -0,0 +1,212 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`jiff::tz::TimeZone`**: The system time zone with all its rules, so
+  the UTC offset is looked up for each instant and follows DST changes
+- **`OnceLock`**: The zone is loaded once, before `--sandbox` hides
+  /etc/localtime; the rules it holds cover any later date
+- **`jiff::Zoned`**: The instant in that zone does the calendar work, and
+  `strftime` writes it with its offset
+
+**Design decisions**:
+- One helper, `Timestamps::render`, formats the prefix of every text event
+  line, from the same instant as the JSON event's `timestamp`
+- JSON events always carry an RFC 3339 UTC timestamp, whatever the flags:
+  they are meant for machines
+- Local time by default, like the other logs on the machine; `--utc` for
+  UTC. The zone is only looked up when a timestamp shows local time, and
+  one that cannot be determined (no tzdata in a container) is a warning
+  and UTC, not a watcher that does not start
+- `relative` counts seconds since the watcher started, `off` keeps the
+  lines as they were
+
+******************************************************************************/
+
+use crate::cli::TimestampFormat;
+use anyhow::Context;
+use jiff::tz::{Offset, TimeZone};
+use std::sync::{Once, OnceLock};
+use std::time::SystemTime;
+
+/// Renders the timestamp prefix of text event lines
+#[derive(Debug, Clone)]
+pub struct Timestamps {
+    format: TimestampFormat,
+    /// Zone of the text timestamps
+    zone: TimeZone,
+    started: SystemTime,
+}
+
+impl Default for Timestamps {
+    fn default() -> Self {
+        Self {
+            format: TimestampFormat::Off,
+            zone: TimeZone::UTC,
+            started: SystemTime::now(),
+        }
+    }
+}
+
+impl Timestamps {
+    /// Starts the clock of `relative` now
+    ///
+    /// Shows UTC, with a warning, when the format shows local time and the
+    /// local time zone cannot be determined.
+    pub fn new(format: TimestampFormat, utc: bool) -> Self {
+        Self {
+            format,
+            zone: if shows_local(format, utc) {
+                local_or_utc()
+            } else {
+                TimeZone::UTC
+            },
+            started: SystemTime::now(),
+        }
+    }
+
+    /// The prefix for an event that happened `at`, `None` with `off`
+    pub fn render(&self, at: SystemTime) -> Option<String> {
+        match self.format {
+            TimestampFormat::Off => None,
+            TimestampFormat::Relative => {
+                let since = at.duration_since(self.started).unwrap_or_default();
+                Some(format!("+{:.3}s", since.as_secs_f64()))
+            }
+            TimestampFormat::Rfc3339 => {
+                let at = self.zoned(at)?;
+                if at.offset() == Offset::UTC {
+                    Some(format!("{:.3}", at.timestamp()))
+                } else {
+                    Some(at.strftime("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string())
+                }
+            }
+            TimestampFormat::TimeOnly => Some(self.zoned(at)?.strftime("%H:%M:%S%.3f").to_string()),
+        }
+    }
+
+    /// `at` in the zone of the text timestamps, `None` past the years
+    /// jiff handles
+    fn zoned(&self, at: SystemTime) -> Option<jiff::Zoned> {
+        let at = jiff::Timestamp::try_from(at).ok()?;
+        Some(at.to_zoned(self.zone.clone()))
+    }
+}
+
+/// Whether `format` shows local time, unless `--utc`
+pub fn shows_local(format: TimestampFormat, utc: bool) -> bool {
+    !utc && matches!(format, TimestampFormat::Rfc3339 | TimestampFormat::TimeOnly)
+}
+
+/// The JSON event timestamp: RFC 3339, UTC, milliseconds
+pub fn utc(at: SystemTime) -> String {
+    humantime::format_rfc3339_millis(at).to_string()
+}
+
+/// The local time zone, or UTC with a warning (given once) when it cannot
+/// be determined
+fn local_or_utc() -> TimeZone {
+    static WARNED: Once = Once::new();
+    local_zone().unwrap_or_else(|e| {
+        WARNED.call_once(|| tracing::warn!("{e:#}, showing UTC timestamps"));
+        TimeZone::UTC
+    })
+}
+
+/// The local time zone (`TZ`, /etc/localtime, or the Windows setting),
+/// loaded once
+///
+/// Call it before `--sandbox` goes up, which hides the zone files.
+pub fn local_zone() -> anyhow::Result<TimeZone> {
+    static ZONE: OnceLock<Result<TimeZone, String>> = OnceLock::new();
+    ZONE.get_or_init(|| TimeZone::try_system().map_err(|e| e.to_string()))
+        .clone()
+        .map_err(anyhow::Error::msg)
+        .context("Cannot determine the local time zone")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// 2026-10-15T08:30:05.250Z
+    fn at() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_millis(1_792_053_005_250)
+    }
+
+    fn stamps(format: TimestampFormat, zone: TimeZone) -> Timestamps {
+        Timestamps {
+            format,
+            zone,
+            started: at() - Duration::from_millis(12_345),
+        }
+    }
+
+    fn fixed(seconds: i32) -> TimeZone {
+        TimeZone::fixed(Offset::from_seconds(seconds).unwrap())
+    }
+
+    #[test]
+    fn test_each_format() {
+        let cases = [
+            (
+                TimestampFormat::Rfc3339,
+                0,
+                Some("2026-10-15T08:30:05.250Z"),
+            ),
+            (
+                TimestampFormat::Rfc3339,
+                7200,
+                Some("2026-10-15T10:30:05.250+02:00"),
+            ),
+            (
+                TimestampFormat::Rfc3339,
+                -(5 * 3600 + 1800),
+                Some("2026-10-15T03:00:05.250-05:30"),
+            ),
+            (TimestampFormat::TimeOnly, 0, Some("08:30:05.250")),
+            (TimestampFormat::TimeOnly, 7200, Some("10:30:05.250")),
+            (TimestampFormat::Relative, 7200, Some("+12.345s")),
+            (TimestampFormat::Off, 7200, None),
+        ];
+        for (format, offset, expected) in cases {
+            assert_eq!(
+                stamps(format, fixed(offset)).render(at()).as_deref(),
+                expected,
+                "{format:?} {offset}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_offset_follows_dst() {
+        // Central European time: +01:00, +02:00 from the last Sunday of
+        // March to the last Sunday of October
+        let paris = TimeZone::posix("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        let stamps = stamps(TimestampFormat::Rfc3339, paris);
+        let day = Duration::from_secs(86_400);
+        // 2026-10-25 is the last Sunday of October
+        assert_eq!(
+            stamps.render(at()).as_deref(),
+            Some("2026-10-15T10:30:05.250+02:00")
+        );
+        assert_eq!(
+            stamps.render(at() + day * 20).as_deref(),
+            Some("2026-11-04T09:30:05.250+01:00")
+        );
+    }
+
+    #[test]
+    fn test_utc_matches_the_json_timestamp() {
+        let stamps = Timestamps::new(TimestampFormat::Rfc3339, true);
+        assert_eq!(stamps.render(at()), Some(utc(at())));
+        assert_eq!(utc(at()), "2026-10-15T08:30:05.250Z");
+    }
+
+    #[test]
+    fn test_relative_before_start_is_zero() {
+        let stamps = stamps(TimestampFormat::Relative, fixed(0));
+        let early = stamps.started - Duration::from_secs(1);
+        assert_eq!(stamps.render(early).as_deref(), Some("+0.000s"));
+    }
+}
@@ -0,0 +1,190 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`tokio_rustls::TlsConnector`**: Wraps a connected `TcpStream` in TLS;
+  the result reads and writes like the plain stream
+- **`Box<dyn Stream>`**: Plain and TLS connections are one type, so the
+  MQTT and Redis clients speak their protocol without knowing which
+- **`OnceLock`**: The client configuration with the Mozilla roots is built
+  once and shared by every connection
+
+**Design decisions**:
+- rustls with the `ring` provider and the Mozilla roots (`webpki-roots`),
+  as for the `https://` requests of ureq: nothing depends on the system's
+  certificate store, and no client certificate is sent
+- The server name checked is the URL's host, an IP address included
+- Connecting and the handshake each have the caller's timeout
+
+******************************************************************************/
+
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, crypto};
+
+/// A connection, plain or over TLS
+pub trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+impl std::fmt::Debug for dyn Stream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Stream").finish_non_exhaustive()
+    }
+}
+
+/// How servers are authenticated
+#[derive(Clone)]
+pub struct Tls(Arc<ClientConfig>);
+
+impl Tls {
+    /// Trusts the Mozilla roots
+    pub fn webpki() -> Self {
+        static WEBPKI: OnceLock<Tls> = OnceLock::new();
+        WEBPKI
+            .get_or_init(|| {
+                Self::with_roots(RootCertStore {
+                    roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+                })
+            })
+            .clone()
+    }
+
+    /// Trusts `roots` only
+    pub fn with_roots(roots: RootCertStore) -> Self {
+        let config =
+            ClientConfig::builder_with_provider(Arc::new(crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .expect("ring supports the default protocol versions")
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+        Self(Arc::new(config))
+    }
+}
+
+impl std::fmt::Debug for Tls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tls").finish_non_exhaustive()
+    }
+}
+
+/// The same configuration: two `webpki()` are equal
+impl PartialEq for Tls {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Tls {}
+
+/// Connects to `host:port`, over TLS with `tls`; `timeout` for the
+/// connection, then for the handshake
+pub async fn connect(
+    host: &str,
+    port: u16,
+    tls: Option<&Tls>,
+    timeout: Duration,
+) -> anyhow::Result<Box<dyn Stream>> {
+    let stream = tokio::time::timeout(timeout, TcpStream::connect((host, port)))
+        .await
+        .map_err(|_| anyhow::anyhow!("no connection after {timeout:?}"))??;
+    stream.set_nodelay(true)?;
+    let Some(tls) = tls else {
+        return Ok(Box::new(stream));
+    };
+    let name = ServerName::try_from(host.to_string())
+        .map_err(|_| anyhow::anyhow!("'{host}' is not a valid TLS server name"))?;
+    let stream = tokio::time::timeout(
+        timeout,
+        TlsConnector::from(tls.0.clone()).connect(name, stream),
+    )
+    .await
+    .map_err(|_| anyhow::anyhow!("no TLS handshake after {timeout:?}"))?
+    .map_err(|e| anyhow::anyhow!("TLS handshake failed: {e}"))?;
+    Ok(Box::new(stream))
+}
+
+/// A server certificate for `localhost`, and a `Tls` trusting only it
+#[cfg(test)]
+pub(crate) fn test_pair() -> (Tls, tokio_rustls::TlsAcceptor) {
+    use tokio_rustls::rustls::ServerConfig;
+    use tokio_rustls::rustls::pki_types::PrivateKeyDer;
+
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let cert = certified.cert.der().clone();
+    let key = PrivateKeyDer::Pkcs8(certified.signing_key.serialize_der().into());
+    let mut roots = RootCertStore::empty();
+    roots.add(cert.clone()).unwrap();
+    let server = ServerConfig::builder_with_provider(Arc::new(crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert], key)
+        .unwrap();
+    (
+        Tls::with_roots(roots),
+        tokio_rustls::TlsAcceptor::from(Arc::new(server)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_connects_over_tls_to_a_trusted_server() {
+        let (tls, acceptor) = test_pair();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = acceptor.accept(stream).await.unwrap();
+            let mut hello = [0; 5];
+            stream.read_exact(&mut hello).await.unwrap();
+            stream.write_all(b"world").await.unwrap();
+            stream.flush().await.unwrap();
+            hello
+        });
+
+        let timeout = Duration::from_secs(5);
+        let mut stream = connect("localhost", port, Some(&tls), timeout)
+            .await
+            .unwrap();
+        stream.write_all(b"hello").await.unwrap();
+        stream.flush().await.unwrap();
+        let mut world = [0; 5];
+        stream.read_exact(&mut world).await.unwrap();
+        assert_eq!(&world, b"world");
+        assert_eq!(&server.await.unwrap(), b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_refuses_an_untrusted_certificate() {
+        let (_, acceptor) = test_pair();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ = acceptor.accept(stream).await;
+        });
+
+        // Self-signed: not under the Mozilla roots
+        let error = connect(
+            "localhost",
+            port,
+            Some(&Tls::webpki()),
+            Duration::from_secs(5),
+        )
+        .await
+        .err()
+        .unwrap()
+        .to_string();
+        assert!(error.contains("TLS handshake failed"), "{error}");
+        assert_eq!(Tls::webpki(), Tls::webpki());
+    }
+}
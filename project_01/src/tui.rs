@@ -0,0 +1,571 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **A pure `render`**: A `Frame` (plain data) in, the cells of a ratatui
+  `Buffer` out; the terminal is only written by the redraw task, so the
+  layout is tested against fixture frames without a terminal
+- **`VecDeque`**: A ring of the latest output lines, oldest dropped first
+- **`Mutex<bool>`**: Whether the feed is open, held for a whole redraw so
+  that closing the dashboard never races a frame being written
+
+**Design decisions**:
+- ratatui draws the panes (blocks, paragraphs and a vertical layout) on
+  its crossterm backend, which also switches to the alternate screen. The
+  keys are still read by `keys`, so the dashboard and the plain output
+  share one key handler
+- Nothing is tracked twice: health, versions, counters and the redacted
+  configurations come from the `StatusBoard` (the one behind `/status` and
+  the dumps); the `Feed` only keeps what the board does not have, the
+  text output lines the emitter would have printed and the last diff
+- The redraw task renders a frame 5 times a second; ratatui writes only
+  the cells that changed since the last one. The board changes on every
+  tick and load, so the dashboard follows them, and a resize is picked up
+  on the next frame
+- The alternate screen is a `keys::Terminal`: left when the dashboard is
+  dropped (once the watch stops, so the shutdown prints on the normal
+  screen), on a panic, or before an abandoned shutdown exits
+- Only with a terminal on stdin and stdout, on Unix: anywhere else --tui
+  falls back to the usual output
+- The events pane follows the newest line; `k`/`j` or the arrows scroll
+  back and forth
+
+******************************************************************************/
+
+use crate::diff;
+use crate::keys::{RawMode, Terminal};
+use crate::listing::ListLimit;
+use crate::output::{Event, health_name};
+use crate::status::Health;
+use crate::status::{StatusBoard, StatusRecord};
+use crate::timestamp::Timestamps;
+use crate::watcher::Pause;
+use ratatui::Terminal as Screen;
+use ratatui::backend::CrosstermBackend;
+use ratatui::buffer::Buffer;
+use ratatui::crossterm::{cursor, execute, terminal};
+use ratatui::layout::{Alignment, Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Widget};
+use std::collections::VecDeque;
+use std::io::{self, IsTerminal, Stdout};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+/// Output lines kept for the events pane
+pub const RECENT: usize = 500;
+
+/// How often a frame is rendered
+const REFRESH: Duration = Duration::from_millis(200);
+
+/// Smallest screen the panes fit in
+const MIN_WIDTH: u16 = 30;
+const MIN_HEIGHT: u16 = 10;
+
+/// The last line of the screen
+const FOOTER: &str = "q quit  p pause/resume  r reload  d dump  j/k scroll";
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Whether --tui can draw here: a terminal on stdin (keys) and stdout
+pub fn supported() -> bool {
+    cfg!(unix) && io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// One line of output, as the events pane shows it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Local time, `HH:MM:SS.mmm`
+    pub at: String,
+    pub line: String,
+}
+
+/// The changes of the last reload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LastDiff {
+    pub file: PathBuf,
+    pub version: u64,
+    pub lines: Vec<String>,
+}
+
+/// What the feed holds
+#[derive(Debug, Default)]
+struct Recent {
+    events: VecDeque<Entry>,
+    diff: Option<LastDiff>,
+}
+
+/// The text output, taken from the emitter while the dashboard is open
+#[derive(Debug, Clone)]
+pub struct Feed {
+    recent: Arc<Mutex<Recent>>,
+    open: Arc<Mutex<bool>>,
+    timestamps: Timestamps,
+}
+
+impl Feed {
+    /// A closed feed: the emitter prints as usual until the dashboard
+    /// shows; event times are rendered by `timestamps`
+    pub fn new(timestamps: Timestamps) -> Self {
+        Self {
+            recent: Arc::default(),
+            open: Arc::default(),
+            timestamps,
+        }
+    }
+
+    /// Whether the emitter hands its lines here instead of printing them
+    pub fn is_open(&self) -> bool {
+        *lock(&self.open)
+    }
+
+    /// Takes the output of the emitter
+    fn open(&self) {
+        *lock(&self.open) = true;
+    }
+
+    /// Gives the output back to the emitter, after the frame being drawn
+    pub fn close(&self) {
+        *lock(&self.open) = false;
+    }
+
+    /// Keeps the text `lines` of `event`, which happened `at`, and its
+    /// changes if it is a reload
+    pub fn record(&self, event: &Event<'_>, at: SystemTime, lines: Vec<String>) {
+        let stamp = self.timestamps.render(at).unwrap_or_default();
+        let mut recent = lock(&self.recent);
+        if let Event::Loaded {
+            file,
+            version,
+            changes: Some(changes),
+            ..
+        } = *event
+        {
+            recent.diff = Some(LastDiff {
+                file: file.to_path_buf(),
+                version,
+                lines: diff::render(changes, ListLimit::UNLIMITED),
+            });
+        }
+        for line in lines.iter().flat_map(|line| line.lines()) {
+            if recent.events.len() == RECENT {
+                recent.events.pop_front();
+            }
+            recent.events.push_back(Entry {
+                at: stamp.clone(),
+                line: line.to_string(),
+            });
+        }
+    }
+}
+
+/// Everything one frame shows
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub status: StatusRecord,
+    /// Redacted JSON of each file's configuration
+    pub configs: Vec<(PathBuf, Arc<str>)>,
+    pub events: Vec<Entry>,
+    pub diff: Option<LastDiff>,
+    pub paused: bool,
+}
+
+/// The live state of the watch, drawn on the alternate screen
+#[derive(Debug, Clone)]
+pub struct Dashboard {
+    board: StatusBoard,
+    feed: Feed,
+    pause: Pause,
+    /// Lines scrolled back from the newest event
+    scroll: Arc<AtomicUsize>,
+}
+
+impl Dashboard {
+    pub fn new(board: StatusBoard, feed: Feed, pause: Pause) -> Self {
+        Self {
+            board,
+            feed,
+            pause,
+            scroll: Arc::default(),
+        }
+    }
+
+    /// What is drawn, as of now
+    pub fn frame(&self) -> Frame {
+        let recent = lock(&self.feed.recent);
+        Frame {
+            status: self.board.record(),
+            configs: self.board.configs(),
+            events: recent.events.iter().cloned().collect(),
+            diff: recent.diff.clone(),
+            paused: self.pause.is_paused(),
+        }
+    }
+
+    /// Scrolls the events pane `lines` back (negative: forward)
+    pub fn scroll(&self, lines: isize) {
+        let events = lock(&self.feed.recent).events.len();
+        let scroll = self.scroll.load(Ordering::Relaxed);
+        let scroll = scroll
+            .saturating_add_signed(lines)
+            .min(events.saturating_sub(1));
+        self.scroll.store(scroll, Ordering::Relaxed);
+    }
+
+    /// Switches to the alternate screen and redraws it until the returned
+    /// guard is dropped
+    pub fn show(&self) -> io::Result<Shown> {
+        let raw_mode = RawMode::enter(Box::new(AltScreen))?;
+        let mut screen = Screen::new(CrosstermBackend::new(io::stdout()))?;
+        self.feed.open();
+        let dashboard = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(REFRESH);
+            loop {
+                ticker.tick().await;
+                if !dashboard.draw(&mut screen) {
+                    break;
+                }
+            }
+        });
+        Ok(Shown {
+            feed: self.feed.clone(),
+            _raw_mode: raw_mode,
+        })
+    }
+
+    /// Draws the frame; false once the feed is closed
+    fn draw(&self, screen: &mut Screen<CrosstermBackend<Stdout>>) -> bool {
+        let frame = self.frame();
+        let scroll = self.scroll.load(Ordering::Relaxed);
+        let open = lock(&self.feed.open);
+        if !*open {
+            return false;
+        }
+        let drawn = screen.draw(|f| {
+            let area = f.area();
+            render(&frame, scroll, area, f.buffer_mut());
+        });
+        if let Err(e) = drawn {
+            tracing::debug!(error = %e, "dashboard not drawn");
+        }
+        true
+    }
+}
+
+/// The dashboard on screen; dropping it closes the feed and goes back to
+/// the normal screen
+#[derive(Debug)]
+pub struct Shown {
+    feed: Feed,
+    _raw_mode: RawMode,
+}
+
+impl Drop for Shown {
+    fn drop(&mut self) {
+        self.feed.close();
+    }
+}
+
+/// The alternate screen, without the cursor
+struct AltScreen;
+
+impl Terminal for AltScreen {
+    fn raw(&mut self) -> io::Result<()> {
+        execute!(
+            io::stdout(),
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            terminal::Clear(terminal::ClearType::All)
+        )
+    }
+
+    fn restore(&mut self) -> io::Result<()> {
+        execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen)
+    }
+}
+
+/// Draws `frame` on `area` of `buf`, the events pane `scroll` lines back
+/// from the newest
+pub fn render(frame: &Frame, scroll: usize, area: Rect, buf: &mut Buffer) {
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        Paragraph::new("Terminal too small for the dashboard").render(area, buf);
+        return;
+    }
+
+    let mut summary = Vec::new();
+    for file in &frame.status.files {
+        summary.push(Line::from(format!(
+            " {}  {}  version {}  {} reloads  {} failures",
+            file.path.display(),
+            health_name(file.health),
+            file.version,
+            file.reloads,
+            file.failures
+        )));
+        if let Some(ref error) = file.last_error {
+            summary.push(
+                Line::from(format!("   last error: {}: {}", error.code, error.message)).red(),
+            );
+        }
+    }
+    if frame.status.files.is_empty() {
+        summary.push(Line::from(" (no file loaded yet)"));
+    }
+    summary.push(Line::from(format!(
+        " pid {}, checking every {}s, updated {}",
+        frame.status.pid, frame.status.interval_secs, frame.status.updated_at
+    )));
+
+    // Up to a third of what is left, the events get the rest
+    let used = 2 + 1 + summary.len();
+    let left = usize::from(area.height).saturating_sub(used + 3);
+    let (name, change) = match frame.diff {
+        Some(ref diff) => {
+            let name = format!(
+                "Last change ({}, version {})",
+                diff.file.display(),
+                diff.version
+            );
+            let room = (left / 3).max(1);
+            let mut lines: Vec<Line> = diff
+                .lines
+                .iter()
+                .map(|line| Line::from(format!(" {line}")))
+                .collect();
+            if lines.len() > room {
+                lines.truncate(room - 1);
+                lines.push(Line::from(format!(
+                    " ... and {} more",
+                    diff.lines.len() - (room - 1)
+                )));
+            }
+            (name, lines)
+        }
+        None => (
+            "Last change".to_string(),
+            vec![Line::from(" (no change since the start)")],
+        ),
+    };
+
+    let [top, summary_area, change_area, events_area, footer] = Layout::vertical([
+        Constraint::Length(2),
+        Constraint::Length(1 + summary.len() as u16),
+        Constraint::Length(1 + change.len() as u16),
+        Constraint::Fill(1),
+        Constraint::Length(1),
+    ])
+    .areas(area);
+
+    header(frame, top, buf);
+    Paragraph::new(summary)
+        .block(pane("Summary"))
+        .render(summary_area, buf);
+    Paragraph::new(change)
+        .block(pane(&name))
+        .render(change_area, buf);
+
+    let room = usize::from(events_area.height.saturating_sub(1));
+    let scroll = scroll.min(frame.events.len().saturating_sub(room));
+    let name = match scroll {
+        0 => format!("Events ({})", frame.events.len()),
+        _ => format!("Events ({}, {scroll} back)", frame.events.len()),
+    };
+    let end = frame.events.len() - scroll;
+    let start = end.saturating_sub(room);
+    let events: Vec<Line> = frame.events[start..end]
+        .iter()
+        .map(|entry| {
+            Line::from(vec![
+                format!(" {} ", entry.at).dim(),
+                entry.line.clone().into(),
+            ])
+        })
+        .collect();
+    Paragraph::new(events)
+        .block(pane(&name))
+        .render(events_area, buf);
+
+    Paragraph::new(FOOTER).dim().render(footer, buf);
+}
+
+/// App, version and environment of the first configuration on the left,
+/// health (and pause) on the right, above a rule
+fn header(frame: &Frame, area: Rect, buf: &mut Buffer) {
+    let app = frame.configs.first().and_then(|(_, config)| {
+        let config: serde_json::Value = serde_json::from_str(config).ok()?;
+        Some(format!(
+            "{} v{} ({})",
+            config["app_name"].as_str()?,
+            config["version"].as_str()?,
+            config["environment"].as_str().unwrap_or("development")
+        ))
+    });
+    let mut left = format!(
+        " config_watcher  {}",
+        app.as_deref()
+            .unwrap_or("waiting for a valid configuration")
+    );
+    if frame.configs.len() > 1 {
+        left.push_str(&format!("  +{} files", frame.configs.len() - 1));
+    }
+    let color = match frame.status.health {
+        Health::Healthy => Color::Green,
+        Health::Degraded => Color::Yellow,
+        Health::Unhealthy => Color::Red,
+    };
+    let mut right = vec![health_name(frame.status.health).to_uppercase().fg(color)];
+    if frame.paused {
+        right.push("  [PAUSED]".reversed());
+    }
+    let block = Block::new().borders(Borders::BOTTOM);
+    let inner = block.inner(area);
+    block.render(area, buf);
+    Paragraph::new(left).bold().render(inner, buf);
+    Paragraph::new(Line::from(right).bold())
+        .alignment(Alignment::Right)
+        .render(inner, buf);
+}
+
+/// A pane under a `─ name ───` rule
+fn pane(name: &str) -> Block<'_> {
+    Block::new()
+        .borders(Borders::TOP)
+        .title(format!("─ {name} "))
+        .title_style(Style::new().add_modifier(Modifier::BOLD))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::TimestampFormat;
+    use crate::status::{ErrorSummary, FileRecord};
+
+    fn record(health: Health, version: u64, last_error: Option<ErrorSummary>) -> FileRecord {
+        FileRecord {
+            path: "config.json".into(),
+            health,
+            version,
+            last_success_at: Some("2026-10-15T10:00:00.000Z".to_string()),
+            last_failure_at: None,
+            last_error,
+            fallback: None,
+            restored_at: None,
+            reloads: version.saturating_sub(1),
+            failures: 0,
+        }
+    }
+
+    fn frame(file: FileRecord, events: usize) -> Frame {
+        let mut status = StatusRecord::new(Duration::from_secs(2), [&file]);
+        status.updated_at = "2026-10-15T10:00:05.000Z".to_string();
+        status.pid = 4242;
+        Frame {
+            status,
+            configs: vec![(
+                "config.json".into(),
+                r#"{"app_name":"Demo","version":"1.2.0","environment":"staging"}"#.into(),
+            )],
+            events: (0..events)
+                .map(|i| Entry {
+                    at: format!("10:00:{i:02}.000"),
+                    line: format!("[OK] event {i}"),
+                })
+                .collect(),
+            diff: Some(LastDiff {
+                file: "config.json".into(),
+                version: 3,
+                lines: (0..6)
+                    .map(|i| format!("field_{i}: {i} -> {}", i + 1))
+                    .collect(),
+            }),
+            paused: false,
+        }
+    }
+
+    /// The text of `frame` on a `width` by `height` screen, without the
+    /// trailing blanks
+    fn screen(frame: &Frame, scroll: usize, width: u16, height: u16) -> Vec<String> {
+        let area = Rect::new(0, 0, width, height);
+        let mut buf = Buffer::empty(area);
+        render(frame, scroll, area, &mut buf);
+        (0..height)
+            .map(|y| {
+                let line: String = (0..width).map(|x| buf[(x, y)].symbol()).collect();
+                line.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    /// Compares the rendered screen with `tests/snapshots/NAME.txt`;
+    /// regenerate with `UPDATE_GOLDEN=1`
+    fn assert_snapshot(name: &str, lines: &[String]) {
+        let path = format!("{}/tests/snapshots/{name}.txt", env!("CARGO_MANIFEST_DIR"));
+        let screen = lines.join("\n") + "\n";
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, &screen).unwrap();
+        }
+        let golden = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(golden == screen, "{name} changed:\n{screen}");
+    }
+
+    #[test]
+    fn test_healthy_frame() {
+        let frame = frame(record(Health::Healthy, 3, None), 30);
+        let lines = screen(&frame, 0, 72, 24);
+        assert_eq!(lines.len(), 24);
+        assert_snapshot("dashboard_healthy", &lines);
+    }
+
+    #[test]
+    fn test_degraded_paused_and_scrolled_frame() {
+        let error = ErrorSummary {
+            code: "parse_error".to_string(),
+            message: "expected `,` at line 3".to_string(),
+        };
+        let mut frame = frame(record(Health::Degraded, 3, Some(error)), 30);
+        frame.status.health = Health::Degraded;
+        frame.paused = true;
+        let lines = screen(&frame, 5, 72, 20);
+        assert_eq!(lines.len(), 20);
+        assert_snapshot("dashboard_degraded", &lines);
+    }
+
+    #[test]
+    fn test_waiting_frame_and_small_screens() {
+        let mut frame = frame(record(Health::Unhealthy, 0, None), 0);
+        frame.configs.clear();
+        frame.diff = None;
+        assert_snapshot("dashboard_waiting", &screen(&frame, 3, 60, 12));
+        let small = screen(&frame, 0, 20, 40);
+        assert_eq!(small[0], "Terminal too small f");
+        assert!(small[1..].iter().all(String::is_empty));
+    }
+
+    #[test]
+    fn test_feed_keeps_the_latest_lines() {
+        let feed = Feed::new(Timestamps::new(TimestampFormat::TimeOnly, true));
+        let unmodified = Event::Unmodified {
+            file: std::path::Path::new("config.json"),
+        };
+        for i in 0..RECENT + 2 {
+            let lines = vec![format!("line {i}"), "two\nlines".to_string()];
+            feed.record(&unmodified, SystemTime::now(), lines);
+        }
+        let recent = lock(&feed.recent);
+        assert_eq!(recent.events.len(), RECENT);
+        assert_eq!(recent.events.back().unwrap().line, "lines");
+        assert!(recent.diff.is_none());
+        drop(recent);
+
+        assert!(!feed.is_open());
+        feed.open();
+        assert!(feed.is_open());
+        feed.close();
+        assert!(!feed.is_open());
+    }
+}
@@ -0,0 +1,180 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **Collecting instead of failing fast**: Every rule runs and records a
+  `Finding`, so users see all problems at once
+- **`impl Display`**: The report renders as a one-line summary for errors
+- **Ordering of enum variants**: `Severity` derives `Ord` (warning < error)
+
+**Design decisions**:
+- Business rules (in `config.rs`) and external JSON Schema checks feed the
+  same report, so callers have one place to look
+- Warnings never make a configuration invalid; only errors do
+- Paths are dotted for business rules and JSON Pointers for schema checks
+- Reports serialize to JSON for `--error-format json`; severities are
+  lowercase strings and unknown positions are `null`
+
+******************************************************************************/
+
+use crate::error::{ConfigError, Result};
+use serde::Serialize;
+use std::fmt;
+
+/// How serious a finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Worth a look; the configuration is still accepted
+    Warning,
+    /// The configuration is rejected
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A single problem found in a configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    /// Whether it rejects the configuration
+    pub severity: Severity,
+    /// Field the finding is about (dotted path or JSON Pointer)
+    pub path: String,
+    /// What is wrong, for a human
+    pub message: String,
+    /// 1-based line in the source file, when known
+    pub line: Option<usize>,
+    /// 1-based column in the source file, when known
+    pub column: Option<usize>,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// All findings produced while validating one configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct ValidationReport {
+    findings: Vec<Finding>,
+}
+
+impl ValidationReport {
+    /// Creates an empty report
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an error
+    pub fn error(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Error, path, message);
+    }
+
+    /// Records a warning
+    pub fn warning(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Warning, path, message);
+    }
+
+    fn push(&mut self, severity: Severity, path: impl Into<String>, message: impl Into<String>) {
+        self.findings.push(Finding {
+            severity,
+            path: path.into(),
+            message: message.into(),
+            line: None,
+            column: None,
+        });
+    }
+
+    /// Appends every finding of `other`
+    pub fn merge(&mut self, other: ValidationReport) {
+        self.findings.extend(other.findings);
+    }
+
+    /// All findings, in the order they were recorded
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// Findings with `Severity::Error`
+    pub fn errors(&self) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(|f| f.severity == Severity::Error)
+    }
+
+    /// Findings with `Severity::Warning`
+    pub fn warnings(&self) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(|f| f.severity == Severity::Warning)
+    }
+
+    /// True when at least one error was recorded
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    /// True when nothing was recorded
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Turns a report with errors into `ConfigError::ValidationFailed`
+    ///
+    /// A report holding only warnings is returned unchanged.
+    pub fn into_result(self) -> Result<ValidationReport> {
+        if self.has_errors() {
+            Err(ConfigError::ValidationFailed { report: self })
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    /// Errors joined on one line (warnings are left out)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, finding) in self.errors().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{finding}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warnings_alone_are_ok() {
+        let mut report = ValidationReport::new();
+        report.warning("server.port", "is a privileged port");
+        let report = report.into_result().unwrap();
+        assert_eq!(report.warnings().count(), 1);
+    }
+
+    #[test]
+    fn test_errors_fail_and_render_on_one_line() {
+        let mut report = ValidationReport::new();
+        report.error("app_name", "cannot be empty");
+        report.warning("x", "ignored in display");
+        report.error("version", "should follow semver");
+
+        let err = report.into_result().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Configuration validation failed: app_name: cannot be empty; version: should follow semver"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`FromStr`**: `rollback --to` parses into a `Selector` (`7`, `latest`,
+  `latest-1`)
+- **Tuple ordering**: Versions sort by `(saved_at, number)`, so copies
+  saved within the same millisecond still come out in order
+- **`str::get`**: Slicing a file name that may be anything, without
+  panicking on a short one
+
+**Design decisions**:
+- With `--keep-versions N`, every accepted load of the watched file copies
+  it to `<state-dir>/versions/<timestamp>-<version>.json`, then removes
+  the oldest copies beyond N
+- The file is copied as it is on disk, not as it was served: `age:` values
+  stay encrypted and overrides are not baked in, so a rollback writes back
+  what someone wrote
+- A load whose content is that of the newest copy (a restart, a touch)
+  adds nothing
+- Timestamps are RFC 3339 in UTC without separators
+  (`20261015T120000.123Z`), valid on every filesystem; files that do not
+  follow the scheme are ignored, never pruned
+- Version numbers restart at 1 with each run, unless `--state-dir`
+  restores the last good configuration, so several copies may share one:
+  `--to N` picks the newest of them
+- Copies are written with mode 0600, like the last good snapshot
+
+******************************************************************************/
+
+use crate::fs_util;
+use anyhow::Context;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// A copy of the watched file in the versions directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    /// The configuration version it loaded as
+    pub number: u64,
+    /// When it was copied
+    pub saved_at: SystemTime,
+    pub path: PathBuf,
+}
+
+impl Version {
+    /// `saved_at` as RFC 3339, to the millisecond
+    pub fn saved_at_rfc3339(&self) -> String {
+        humantime::format_rfc3339_millis(self.saved_at).to_string()
+    }
+}
+
+/// The versions directory of `state_dir`
+pub fn dir(state_dir: &Path) -> PathBuf {
+    state_dir.join("versions")
+}
+
+/// Copies `contents`, loaded as `number`, then prunes down to `keep` copies
+///
+/// Returns the copy, `None` when `contents` is that of the newest one.
+pub fn save(
+    state_dir: &Path,
+    number: u64,
+    contents: &[u8],
+    keep: usize,
+) -> anyhow::Result<Option<PathBuf>> {
+    let dir = dir(state_dir);
+    let existing = list(state_dir)?;
+    if let Some(newest) = existing.last()
+        && std::fs::read(&newest.path).is_ok_and(|newest| newest == contents)
+    {
+        return Ok(None);
+    }
+
+    std::fs::create_dir_all(&dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+    let path = dir.join(file_name(SystemTime::now(), number));
+    fs_util::write_atomic_with_mode(&path, contents, 0o600)?;
+    prune(state_dir, keep)?;
+    Ok(Some(path))
+}
+
+/// The copies in `state_dir`, oldest first; none without a versions
+/// directory
+pub fn list(state_dir: &Path) -> anyhow::Result<Vec<Version>> {
+    let dir = dir(state_dir);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Cannot read {}", dir.display())),
+    };
+    let mut versions = Vec::new();
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Cannot read {}", dir.display()))?;
+        if let Some((saved_at, number)) = entry.file_name().to_str().and_then(parse_name) {
+            versions.push(Version {
+                number,
+                saved_at,
+                path: entry.path(),
+            });
+        }
+    }
+    versions.sort_by_key(|version| (version.saved_at, version.number));
+    Ok(versions)
+}
+
+/// Removes the oldest copies beyond `keep`
+fn prune(state_dir: &Path, keep: usize) -> anyhow::Result<()> {
+    let versions = list(state_dir)?;
+    let excess = versions.len().saturating_sub(keep);
+    for version in &versions[..excess] {
+        std::fs::remove_file(&version.path)
+            .with_context(|| format!("Cannot remove {}", version.path.display()))?;
+    }
+    Ok(())
+}
+
+/// Which copy `rollback --to` means
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selector {
+    /// The newest copy with this version number
+    Number(u64),
+    /// `latest-N`: N copies before the newest
+    Latest(usize),
+}
+
+impl Selector {
+    /// The copy this selects in `versions` (oldest first)
+    pub fn find<'a>(&self, versions: &'a [Version]) -> Option<&'a Version> {
+        match *self {
+            Selector::Number(number) => versions.iter().rev().find(|v| v.number == number),
+            Selector::Latest(back) => versions.iter().rev().nth(back),
+        }
+    }
+}
+
+/// `7`, `latest` or `latest-1`
+impl FromStr for Selector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{s}' is not a version (expected N, latest or latest-N)");
+        match s.strip_prefix("latest") {
+            Some("") => Ok(Selector::Latest(0)),
+            Some(back) => back
+                .strip_prefix('-')
+                .and_then(|back| back.parse().ok())
+                .map(Selector::Latest)
+                .ok_or_else(invalid),
+            None => s.parse().map(Selector::Number).map_err(|_| invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Number(number) => write!(f, "version {number}"),
+            Selector::Latest(0) => write!(f, "latest"),
+            Selector::Latest(back) => write!(f, "latest-{back}"),
+        }
+    }
+}
+
+/// `20261015T120000.123Z-7.json`
+fn file_name(saved_at: SystemTime, number: u64) -> String {
+    let stamp: String = humantime::format_rfc3339_millis(saved_at)
+        .to_string()
+        .chars()
+        .filter(|c| !matches!(c, '-' | ':'))
+        .collect();
+    format!("{stamp}-{number}.json")
+}
+
+/// The inverse of [`file_name`]; `None` for any other name
+fn parse_name(name: &str) -> Option<(SystemTime, u64)> {
+    let (stamp, number) = name.strip_suffix(".json")?.rsplit_once('-')?;
+    if stamp.get(8..9)? != "T" {
+        return None;
+    }
+    let rfc3339 = format!(
+        "{}-{}-{}T{}:{}:{}",
+        stamp.get(..4)?,
+        stamp.get(4..6)?,
+        stamp.get(6..8)?,
+        stamp.get(9..11)?,
+        stamp.get(11..13)?,
+        stamp.get(13..)?
+    );
+    let saved_at = humantime::parse_rfc3339(&rfc3339).ok()?;
+    Some((saved_at, number.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_file_names_round_trip() {
+        let at = SystemTime::UNIX_EPOCH + Duration::from_millis(1_792_065_600_123);
+        let name = file_name(at, 42);
+        assert_eq!(name, "20261015T120000.123Z-42.json");
+        assert_eq!(parse_name(&name), Some((at, 42)));
+
+        for other in [
+            "notes.txt",
+            "latest.json",
+            "2026-1.json",
+            "20261015X120000Z-1.json",
+        ] {
+            assert_eq!(parse_name(other), None, "{other}");
+        }
+    }
+
+    #[test]
+    fn test_save_prunes_the_oldest() {
+        let state = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir(state.path())).unwrap();
+        std::fs::write(dir(state.path()).join("README"), "kept").unwrap();
+
+        for number in 1..=5 {
+            let saved = save(
+                state.path(),
+                number,
+                format!("{{\"n\": {number}}}").as_bytes(),
+                3,
+            );
+            assert!(saved.unwrap().is_some());
+        }
+        let versions = list(state.path()).unwrap();
+        let numbers: Vec<_> = versions.iter().map(|v| v.number).collect();
+        assert_eq!(numbers, [3, 4, 5]);
+        assert_eq!(
+            std::fs::read_to_string(&versions[2].path).unwrap(),
+            "{\"n\": 5}"
+        );
+        assert!(dir(state.path()).join("README").exists());
+
+        // The same content again adds nothing
+        assert_eq!(save(state.path(), 6, b"{\"n\": 5}", 3).unwrap(), None);
+        assert_eq!(list(state.path()).unwrap().len(), 3);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&versions[0].path)
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_selectors() {
+        assert_eq!("7".parse(), Ok(Selector::Number(7)));
+        assert_eq!("latest".parse(), Ok(Selector::Latest(0)));
+        assert_eq!("latest-2".parse(), Ok(Selector::Latest(2)));
+        for invalid in ["", "latest-", "latest+1", "v7", "-1"] {
+            assert!(invalid.parse::<Selector>().is_err(), "{invalid}");
+        }
+
+        let at = |ms| SystemTime::UNIX_EPOCH + Duration::from_millis(ms);
+        let version = |number, ms| Version {
+            number,
+            saved_at: at(ms),
+            path: PathBuf::from(format!("{ms}")),
+        };
+        // A restart started counting again
+        let versions = [version(1, 10), version(2, 20), version(1, 30)];
+        assert_eq!(Selector::Number(1).find(&versions), Some(&versions[2]));
+        assert_eq!(Selector::Number(3).find(&versions), None);
+        assert_eq!(Selector::Latest(0).find(&versions), Some(&versions[2]));
+        assert_eq!(Selector::Latest(2).find(&versions), Some(&versions[0]));
+        assert_eq!(Selector::Latest(3).find(&versions), None);
+    }
+}
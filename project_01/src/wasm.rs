@@ -0,0 +1,193 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`#[wasm_bindgen]`**: Exports a function to JavaScript; `&str` comes in
+  as a JS string, `JsValue` goes out as any JS value
+- **`serde_wasm_bindgen::Serializer::json_compatible`**: Maps become plain
+  objects rather than `Map`s, so the result reads like the CLI's JSON
+- **`Result<JsValue, JsValue>`**: An `Err` is thrown on the JS side
+
+**Design decisions**:
+- Built with `--features wasm` for `wasm32-unknown-unknown`, where only the
+  pure modules exist (`pipeline`, `config`, `validation`, `diff`...): the
+  checker runs the watcher's own code, not a copy of it
+- `validate_json` never throws: a document that fails comes back as a
+  report with `valid: false`, its `code` and its findings, as
+  `validate --error-format json` prints them. A valid one also carries its
+  warnings and its canonical form, secrets redacted
+- What needs the file system is left out: `*_file` references and
+  `auth` secret files are not checked, there is no external schema
+- `diff_json` compares the effective forms, as `config-watcher diff`
+  does, and throws the report of the side that does not parse
+- Input above `MAX_CONFIG_SIZE` is rejected before parsing, as a file
+  would be
+
+******************************************************************************/
+
+use crate::config::MAX_CONFIG_SIZE;
+use crate::diff::{self, Change};
+use crate::error::ConfigError;
+use crate::pipeline;
+use crate::report;
+use crate::validation::Finding;
+use anyhow::Context;
+use serde::Serialize;
+use std::path::Path;
+use wasm_bindgen::prelude::*;
+
+/// What the findings are reported against, in place of a file name
+const INPUT: &str = "input";
+
+/// The outcome of `validate_json`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Checked {
+    /// Whether the configuration would load
+    pub valid: bool,
+    /// Error code (`report::CODES`), `null` when valid
+    pub code: Option<&'static str>,
+    /// The whole error chain, `null` when valid
+    pub message: Option<String>,
+    /// Errors of an invalid document, warnings of a valid one
+    pub findings: Vec<Finding>,
+    /// Canonical form of a valid document, secrets redacted
+    pub normalized: Option<String>,
+}
+
+/// The outcome of `diff_json`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Compared {
+    /// Whether the effective configurations are the same
+    pub identical: bool,
+    /// One entry per changed field, secrets redacted
+    pub diff: Vec<Change>,
+}
+
+/// Validates `input` as the watcher would load it
+pub fn check(input: &str) -> Checked {
+    let checked = too_large(input).and_then(|()| pipeline::check(input));
+    match checked {
+        Ok((config, warnings)) => Checked {
+            valid: true,
+            code: None,
+            message: None,
+            findings: warnings.findings().to_vec(),
+            normalized: pipeline::canonical(&config, true).ok(),
+        },
+        Err(e) => Checked {
+            valid: false,
+            code: Some(report::code(&e)),
+            message: Some(format!("{e:#}")),
+            findings: report::findings(&e),
+            normalized: None,
+        },
+    }
+}
+
+/// Compares the effective forms of `old` and `new`
+pub fn compare(old: &str, new: &str) -> anyhow::Result<Compared> {
+    let effective = |side: &str, input: &str| {
+        too_large(input)
+            .and_then(|()| pipeline::effective(input))
+            .with_context(|| format!("In {side}"))
+    };
+    let changes = diff::diff(&effective("a", old)?, &effective("b", new)?);
+    Ok(Compared {
+        identical: changes.is_empty(),
+        diff: changes,
+    })
+}
+
+/// Validates a configuration document
+///
+/// Returns `{ valid, code, message, findings, normalized }`.
+#[wasm_bindgen(js_name = validateJson)]
+pub fn validate_json(input: &str) -> JsValue {
+    to_js(&check(input))
+}
+
+/// Compares two configuration documents
+///
+/// Returns `{ identical, diff }`; throws `{ code, file, message, findings }`
+/// when a side does not parse.
+#[wasm_bindgen(js_name = diffJson)]
+pub fn diff_json(a: &str, b: &str) -> Result<JsValue, JsValue> {
+    compare(a, b)
+        .map(|compared| to_js(&compared))
+        .map_err(|e| to_js(&report::ErrorReport::new(Path::new(INPUT), &e)))
+}
+
+fn too_large(input: &str) -> anyhow::Result<()> {
+    if input.len() as u64 > MAX_CONFIG_SIZE {
+        return Err(ConfigError::TooLarge {
+            path: INPUT.into(),
+            limit: MAX_CONFIG_SIZE,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// `value` as a plain JS object
+fn to_js(value: &impl Serialize) -> JsValue {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .unwrap_or(JsValue::NULL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::Severity;
+
+    #[test]
+    fn test_check_reports_like_validate() {
+        let valid = check(
+            r#"{ "app_name": "A", "version": "1.0.0", "features": { "b": true, "a": false } }"#,
+        );
+        assert!(valid.valid, "{valid:?}");
+        assert_eq!(valid.code, None);
+        let normalized = valid.normalized.unwrap();
+        assert!(
+            normalized.find("\"a\"") < normalized.find("\"b\""),
+            "{normalized}"
+        );
+
+        let invalid = check(r#"{ "app_name": "A", "version": "1", "environment": "qa" }"#);
+        assert!(!invalid.valid);
+        assert_eq!(invalid.code, Some("validation_failed"));
+        let paths: Vec<_> = invalid.findings.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["version", "environment"]);
+        assert!(
+            invalid
+                .findings
+                .iter()
+                .all(|f| f.severity == Severity::Error)
+        );
+        assert_eq!(invalid.normalized, None);
+
+        let broken = check("{");
+        assert_eq!(broken.code, Some("invalid_json"));
+        assert_eq!(broken.findings[0].line, Some(1));
+    }
+
+    #[test]
+    fn test_compare_effective_forms() {
+        let same = compare(
+            r#"{ "app_name": "A", "version": "1.0.0" }"#,
+            r#"{"version":"1.0.0","app_name":"A","environment":"development"}"#,
+        )
+        .unwrap();
+        assert!(same.identical);
+
+        let changed = compare(
+            r#"{ "app_name": "A", "version": "1.0.0" }"#,
+            r#"{ "app_name": "A", "version": "1.1.0" }"#,
+        )
+        .unwrap();
+        assert_eq!(changed.diff.len(), 1);
+        assert_eq!(changed.diff[0].path, "version");
+
+        let e = compare("{}", "{").unwrap_err();
+        assert!(format!("{e:#}").starts_with("In a:"), "{e:#}");
+    }
+}
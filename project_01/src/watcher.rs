@@ -13,16 +13,153 @@
 - Keeping last valid config to fall back on errors
 - Using `anyhow::Context` for rich error messages
 - Separating concerns: reading, parsing, validating, watching
+- An optional external JSON Schema is a companion file: editing it
+  triggers a revalidation just like editing the config itself
+- With fail-fast, a reload failure after a good load ends the loop with
+  `ConfigError::ReloadFailed`; the file gets one short second chance first
+  so a writer caught mid-save does not trigger it
+- An optional maximum duration ends the loop between ticks, never in the
+  middle of a reload
+- Command-line and environment overrides are re-applied to every load
+  before validation, and the summary flags the fields they touch
+- Nothing is printed directly: every event goes through an `Emitter`, which
+  renders it as text or as one JSON line
+- With `from_env` there is no file: the document is built from environment
+  variables, and a change means a different set of variables; everything
+  else (overrides, validation, events) is shared
+- An optional status file is rewritten after every tick with the state of
+  the watcher, for `healthcheck`; an optional `StatusBoard` gets the same
+  record, plus each accepted configuration, for the HTTP server
+- An optional `FileProbe` gets the state and failure streak after every
+  tick, for `/livez` and `/readyz`: a few atomic stores, no lock
+- Diagnostics (timings, error kinds, retries) are `tracing` events inside
+  `reload` and `read_config` spans, independent of the user-facing events
+- Each step of a reload is a child span (`stat`, `read`, `parse`,
+  `validate`, `apply`) recording `duration_ms` and `outcome`; a failing
+  step also logs its error chain inside its span. Anything a reload
+  triggers later (hooks, notifiers) gets its own child span the same way
+- Every wait of the loop (ticks, heartbeats, --max-duration, the fail-fast
+  second chance) is on tokio's clock: under a paused clock a test runs
+  minutes of watching in milliseconds
+- A reload request (`ReloadRequests`, a `watch` channel) re-reads the file
+  right away, changed or not, between two ticks
+- While paused (`Pause`, an `AtomicBool` shared by every watcher) the
+  ticks check nothing; reload requests and heartbeats still go through.
+  The first tick after the resume catches up on whatever changed
+- An optional heartbeat summarizes the state on its own `tokio` interval,
+  on the same clock as the checks; it never triggers a check, and a
+  heartbeat due at the same time as a check goes out first
+- With `--metrics-addr`, every load and every failed stat is counted in a
+  shared `Metrics`, read by the `/metrics` endpoint
+- Notifiers (`--on-change`, webhooks...) get the events through the
+  emitter and run on their own: the watch loop never waits for them
+- Each reload triggered by a file change records its detection latency
+  (mtime to applied) in `LatencyStats`, the metrics and a `-v` line
+- With `--verify-checksum` or `--verify-signature`, the file is read
+  through `fs_util::read_consistent` with the checks of its companions
+  (`.sha256`, `.minisig`/`.sig`): a file that does not match them fails
+  the read step like a file that cannot be read
+- With `--age-identity`, the `age:` values of the document are decrypted
+  as part of the read step, before overrides and validation, and their
+  paths marked secret for redaction (`redact::mark_secret`)
+- Under `--sandbox`, a file that now resolves outside it fails the read
+  step with `outside_sandbox` before the kernel refuses it
+- An optional `PermissionAudit` joins the validation step: loose secret
+  files add errors (or warnings) to the report, and its warnings, which
+  the report would drop on success, are logged
+- The secret files of the `auth` section must exist, relative to the
+  file's directory (`AuthConfig::check_files`); `AppConfig::check` stays
+  free of I/O
+- With a fallback (`--fallback-config`), a file that fails its initial
+  load is replaced by the fallback, through the same read, parse and
+  validate steps, in the `Fallback` state. The file is still checked on
+  every tick; its first valid load announces the switch (`SwitchedOver`)
+  and the fallback is dropped, so it can never come back
+- With a state directory (`--state-dir`), every accepted load of the file
+  is saved there (`last_good`). A file that fails its initial load is
+  replaced by that snapshot, in the `Restored` state, before the fallback
+  is even tried: the configuration of the previous run is more recent than
+  any baseline. From there it works like the fallback; an unusable
+  snapshot is logged and skipped. Watchers of environment variables keep
+  no snapshot
+- With `--keep-versions N` as well, every accepted load also copies the
+  file as it is on disk to the versions directory (`versions`), for
+  `config-watcher rollback`. A failed copy is only logged
+- With `--promote-next`, every tick also looks at `FILE.next`, after the
+  file itself so direct edits keep working. A candidate not seen before
+  (by mtime, size and inode) goes through the same read, parse and
+  validate steps; if it passes, it gets the permissions of the file and is
+  renamed over it, then applied (`Promoted`), otherwise it is reported
+  (`Rejected`) and both files stay as they are. A candidate replaced while
+  it was being validated is not promoted: the new one is validated instead
+- With `--confirm`, a valid change (from the file or a candidate) only
+  applies once approved (`Confirm`), before anything reports it. A
+  declined one still records the file's mtime, so it is not read again
+  until the file changes, and its hash, so a touch does not ask again
+- Span fields are recorded lazily (`%`, `format_args!`): with no subscriber
+  interested, the spans are disabled and the hot path allocates nothing
+- Every read of the file is one blocking task (open and read together)
+  into the same buffer, which keeps the capacity of the largest read; it
+  is parsed in place, and the document is deserialized by reference, never
+  copied
+- A read whose bytes hash (`fs_util::content_hash`) like those of the last
+  load that passed validation reuses its configuration, skipping parse and
+  validation: a touch or an editor saving unchanged content costs a read
+  and a hash. Only a read the size of the last validated load is hashed,
+  and only then is its configuration kept: a first load, or one of a new
+  size, pays for neither, and the second save of the same bytes is the
+  first to skip. Not when anything else decides the outcome (overrides
+  re-read from the environment, the permission audit, the secret files of
+  `auth`, `-vv` provenance), and a forced reload (new schema, reload
+  request) always validates afresh
 
 ******************************************************************************/
 
+#[cfg(feature = "age")]
+use crate::age::{self, Identity};
+use crate::checksum;
 use crate::config::AppConfig;
+use crate::confirm::{Answer, Confirm};
+use crate::diff;
+use crate::env_config;
 use crate::error::{ConfigError, Result};
+use crate::external_schema::ExternalSchema;
+use crate::fs_util::{self, Backups};
+use crate::last_good::{self, Snapshot};
+use crate::latency::{self, LatencyStats};
+use crate::metrics::Metrics;
+use crate::notify::Dispatcher;
+use crate::output::{
+    Emitter, Event, FileStatus, HeartbeatCounts, StandIn, Summary, Timings, Verbosity, WatchState,
+};
+use crate::overrides::Overrides;
+use crate::permissions::PermissionAudit;
+use crate::probes::FileProbe;
+use crate::provenance::Provenance;
+use crate::proxy::CurrentProxy;
+#[cfg(feature = "age")]
+use crate::redact;
+use crate::report;
+use crate::sandbox;
+use crate::signature::{self, TrustedKey};
+use crate::status::{self, ErrorSummary, FileRecord, StatusBoard, StatusFile};
+use crate::validation::ValidationReport;
+use crate::versions;
 use anyhow::Context;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::io::Read as _;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::SystemTime;
 use tokio::fs;
-use tokio::time::{Duration, interval};
+use tokio::sync::watch;
+use tokio::time::{
+    Duration, Instant, Interval, MissedTickBehavior, interval, interval_at, sleep, sleep_until,
+};
+use tracing::field::Empty;
+use tracing::{Instrument, Span, debug, debug_span, trace, warn};
 
 /// Watches a configuration file for changes and validates it
 pub struct ConfigWatcher {
@@ -30,8 +167,149 @@ pub struct ConfigWatcher {
     check_interval: Duration,
     last_modified: Option<SystemTime>,
     last_valid_config: Option<AppConfig>,
+    schema: Option<ExternalSchema>,
+    verify_checksum: bool,
+    signature_key: Option<TrustedKey>,
+    #[cfg(feature = "age")]
+    age_identities: Vec<Identity>,
+    permissions: Option<PermissionAudit>,
+    /// Loaded when the file fails at startup, until the file is valid
+    fallback: Option<PathBuf>,
+    /// Where the last good configuration is saved and restored from
+    state_dir: Option<PathBuf>,
+    /// When the restored configuration was saved, until the file is valid
+    restored_at: Option<SystemTime>,
+    /// Copies of the file kept in the state directory
+    keep_versions: Option<usize>,
+    promote_next: bool,
+    /// Kept of the file before a candidate is promoted over it
+    backups: Backups,
+    confirm: Option<Confirm>,
+    /// Hash of the last configuration declined at the prompt
+    declined: Option<String>,
+    /// The last `.next` candidate validated, promoted or not
+    last_candidate: Option<Stamp>,
+    overrides: Overrides,
+    reread_env: bool,
+    emitter: Emitter,
+    version: u64,
+    state: WatchState,
+    max_duration: Option<Duration>,
+    fail_fast: bool,
+    last_valid_at: Option<SystemTime>,
+    env_prefix: Option<String>,
+    last_env: Option<Vec<(String, String)>>,
+    status_file: Option<StatusFile>,
+    status_board: Option<StatusBoard>,
+    probe: Option<FileProbe>,
+    reload_requests: Option<watch::Receiver<()>>,
+    pause: Option<Pause>,
+    metrics: Option<Metrics>,
+    proxy: Option<CurrentProxy>,
+    heartbeat: Option<Duration>,
+    counts: HeartbeatCounts,
+    /// Like `counts`, but never reset by a heartbeat
+    totals: HeartbeatCounts,
+    /// Failed loads and checks since the last successful load
+    failure_streak: u64,
+    last_change: Option<Instant>,
+    last_error: Option<&'static str>,
+    last_failure: Option<(SystemTime, ErrorSummary)>,
+    /// Duration of the last stat, reported with the reload it triggers
+    last_stat: Option<Duration>,
+    latency: LatencyStats,
+    /// Whether the clock skew warning was already logged
+    skew_warned: bool,
+    /// Reused by every read, so a tick allocates nothing once it has grown
+    read_buffer: Mutex<Vec<u8>>,
+    /// The last bytes that passed validation, and what they validated to
+    validated: Mutex<Option<Validated>>,
 }
 
+/// The last load that passed validation
+struct Validated {
+    path: PathBuf,
+    /// Size of its bytes: only a read of the same size is hashed
+    len: usize,
+    /// The hash of its bytes and what they validated to, kept when it
+    /// was itself the size of the load before
+    reused: Option<(u64, AppConfig)>,
+}
+
+/// What the read step produced
+enum Read {
+    /// A document to parse and validate, with the size of its bytes when
+    /// its validation may be reused, and their hash when it may be reused
+    /// by the next read
+    Document {
+        doc: serde_json::Value,
+        len: Option<usize>,
+        hash: Option<u64>,
+    },
+    /// The configuration of the last validated load with the same bytes
+    /// (boxed: a configuration is much larger than a document)
+    Validated(Box<AppConfig>),
+}
+
+/// Asks every watcher to reload now, changed or not (SIGHUP, `RELOAD` on
+/// the push socket)
+#[derive(Debug, Clone)]
+pub struct ReloadRequests {
+    emitter: Emitter,
+    sender: Arc<watch::Sender<()>>,
+}
+
+impl ReloadRequests {
+    /// Requests announced through `emitter`
+    pub fn new(emitter: Emitter) -> Self {
+        Self {
+            emitter,
+            sender: Arc::new(watch::channel(()).0),
+        }
+    }
+
+    /// Announces the request, then wakes every watcher; requests made
+    /// while a watcher is busy count as one
+    pub fn request(&self, by: &str) {
+        self.emitter.emit(&Event::ReloadRequested { by });
+        self.sender.send_replace(());
+    }
+}
+
+/// Pauses and resumes the checks of every watcher (`p` while watching in a
+/// terminal)
+#[derive(Debug, Clone)]
+pub struct Pause {
+    emitter: Emitter,
+    paused: Arc<AtomicBool>,
+}
+
+impl Pause {
+    /// Running to begin with; changes announced through `emitter`
+    pub fn new(emitter: Emitter) -> Self {
+        Self {
+            emitter,
+            paused: Arc::default(),
+        }
+    }
+
+    /// Pauses if running, resumes if paused, and announces it; returns
+    /// whether the watchers are now paused
+    pub fn toggle(&self, by: &str) -> bool {
+        let paused = !self.paused.fetch_xor(true, Ordering::Relaxed);
+        self.emitter.emit(&Event::Paused { by, paused });
+        paused
+    }
+
+    /// Whether the watchers are paused
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+}
+
+/// How long fail-fast waits before re-checking a file that failed to load
+const FAIL_FAST_SETTLE: Duration = Duration::from_millis(250);
+
 impl ConfigWatcher {
     /// Creates a new ConfigWatcher instance
     ///
@@ -44,44 +322,571 @@ impl ConfigWatcher {
             check_interval: Duration::from_secs(check_interval_secs),
             last_modified: None,
             last_valid_config: None,
+            schema: None,
+            verify_checksum: false,
+            signature_key: None,
+            #[cfg(feature = "age")]
+            age_identities: Vec::new(),
+            permissions: None,
+            fallback: None,
+            state_dir: None,
+            restored_at: None,
+            keep_versions: None,
+            promote_next: false,
+            backups: Backups::default(),
+            confirm: None,
+            declined: None,
+            last_candidate: None,
+            overrides: Overrides::default(),
+            reread_env: false,
+            emitter: Emitter::default(),
+            version: 0,
+            state: WatchState::Waiting,
+            max_duration: None,
+            fail_fast: false,
+            last_valid_at: None,
+            env_prefix: None,
+            last_env: None,
+            status_file: None,
+            status_board: None,
+            probe: None,
+            reload_requests: None,
+            pause: None,
+            metrics: None,
+            proxy: None,
+            heartbeat: None,
+            counts: HeartbeatCounts::default(),
+            totals: HeartbeatCounts::default(),
+            failure_streak: 0,
+            last_change: None,
+            last_error: None,
+            last_failure: None,
+            last_stat: None,
+            latency: LatencyStats::default(),
+            skew_warned: false,
+            read_buffer: Mutex::default(),
+            validated: Mutex::default(),
         }
     }
 
-    /// Reads and parses the configuration file
+    /// Creates a watcher whose configuration comes from the `PREFIX__*`
+    /// environment variables instead of a file
     ///
-    /// Uses anyhow::Context to add contextual information to errors
-    async fn read_config(&self) -> anyhow::Result<AppConfig> {
+    /// Events name it `env:PREFIX`.
+    pub fn from_env(prefix: &str, check_interval_secs: u64) -> Self {
+        Self {
+            env_prefix: Some(prefix.to_string()),
+            ..Self::new(format!("env:{prefix}"), check_interval_secs)
+        }
+    }
+
+    /// Enforces an external JSON Schema on every load
+    pub(crate) fn with_schema(mut self, schema: ExternalSchema) -> Self {
+        self.schema = Some(schema);
+        self.forget_validation();
+        self
+    }
+
+    /// Applies `overrides` on top of every load, before validation
+    pub(crate) fn with_overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Collects `CW_OVERRIDE__*` variables again before every reload
+    pub fn with_env_reread(mut self) -> Self {
+        self.reread_env = true;
+        self
+    }
+
+    /// Prefixes every output line with `[label]`
+    ///
+    /// Used when several files are watched by one process.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.emitter = self.emitter.with_label(label);
+        self
+    }
+
+    /// Prints events through `emitter` (format and style)
+    ///
+    /// Call before [`ConfigWatcher::with_label`], which labels the emitter.
+    pub fn with_emitter(mut self, emitter: Emitter) -> Self {
+        self.emitter = emitter;
+        self
+    }
+
+    /// Makes [`ConfigWatcher::watch`] return after `max_duration`
+    ///
+    /// The deadline is only checked while waiting for the next tick, so a
+    /// reload that is due at the deadline still completes.
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    /// Records the watcher's state in `status_file` after every tick
+    pub(crate) fn with_status_file(mut self, status_file: StatusFile) -> Self {
+        self.status_file = Some(status_file);
+        self
+    }
+
+    /// Posts the watcher's state and configuration on `board` after every
+    /// tick and load
+    pub(crate) fn with_status_board(mut self, board: StatusBoard) -> Self {
+        self.status_board = Some(board);
+        self
+    }
+
+    /// Records the state of the watcher in `probe` after every tick
+    pub(crate) fn with_probe(mut self, probe: FileProbe) -> Self {
+        self.probe = Some(probe);
+        self
+    }
+
+    /// Reloads the file, changed or not, on every request made through
+    /// `requests`
+    pub fn with_reload_requests(mut self, requests: &ReloadRequests) -> Self {
+        self.reload_requests = Some(requests.sender.subscribe());
+        self
+    }
+
+    /// Skips the checks while `pause` is paused
+    pub fn with_pause(mut self, pause: &Pause) -> Self {
+        self.pause = Some(pause.clone());
+        self
+    }
+
+    /// Counts every load and watch error in `metrics`
+    pub(crate) fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Shares the `proxy` section of every accepted configuration through
+    /// `proxy`, for the outbound clients
+    pub(crate) fn with_proxy(mut self, proxy: CurrentProxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Emits a heartbeat every `every` while watching; zero disables it
+    pub fn with_heartbeat(mut self, every: Duration) -> Self {
+        self.heartbeat = (!every.is_zero()).then_some(every);
+        self
+    }
+
+    /// Also hands its events to the notifiers behind `dispatcher`
+    pub fn with_notifiers(mut self, dispatcher: Dispatcher) -> Self {
+        self.emitter = self.emitter.with_notifiers(dispatcher);
+        self
+    }
+
+    /// Checks every read against the file's `.sha256` sidecar
+    pub fn with_checksum(mut self) -> Self {
+        self.verify_checksum = true;
+        self
+    }
+
+    /// Only accepts reads signed with `key`
+    pub fn with_signature_key(mut self, key: TrustedKey) -> Self {
+        self.signature_key = Some(key);
+        self
+    }
+
+    /// Decrypts the `age:` values of every read with `identities`
+    #[cfg(feature = "age")]
+    pub(crate) fn with_age_identities(mut self, identities: Vec<Identity>) -> Self {
+        self.age_identities = identities;
+        self
+    }
+
+    /// Audits the permissions of the file and its secret files on every
+    /// load
+    pub(crate) fn with_permission_audit(mut self, audit: PermissionAudit) -> Self {
+        self.permissions = Some(audit);
+        self
+    }
+
+    /// Loads `fallback` instead when the file cannot be loaded at startup
+    ///
+    /// The file is still watched and replaces the fallback as soon as it
+    /// is valid; the fallback is never loaded again after that.
+    pub fn with_fallback(mut self, fallback: impl Into<PathBuf>) -> Self {
+        self.fallback = Some(fallback.into());
+        self
+    }
+
+    /// Saves every configuration of the file in `dir`, and restores the
+    /// last one when the file cannot be loaded at startup
+    ///
+    /// A restored configuration is replaced like the fallback, which it
+    /// takes precedence over.
+    pub fn with_state_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.state_dir = Some(dir.into());
+        self
+    }
+
+    /// Also copies the file to the versions directory of the state
+    /// directory on every accepted load, keeping the `keep` newest copies
+    pub fn with_keep_versions(mut self, keep: usize) -> Self {
+        self.keep_versions = Some(keep);
+        self
+    }
+
+    /// Keeps `backups` of the file when a candidate is promoted, instead of
+    /// one
+    pub(crate) fn with_backups(mut self, backups: Backups) -> Self {
+        self.backups = backups;
+        self
+    }
+
+    /// Validates `FILE.next` whenever it appears or changes, and renames
+    /// it over the file when it passes
+    pub fn with_promote_next(mut self) -> Self {
+        self.promote_next = true;
+        self
+    }
+
+    /// Asks through `confirm` before applying each change
+    pub(crate) fn with_confirm(mut self, confirm: Confirm) -> Self {
+        self.confirm = Some(confirm);
+        self
+    }
+
+    /// Ends [`ConfigWatcher::watch`] with an error on the first reload
+    /// failure after a successful load
+    pub fn with_fail_fast(mut self) -> Self {
+        self.fail_fast = true;
+        self
+    }
+
+    /// Path of the watched file
+    pub fn file_path(&self) -> &Path {
+        &self.file_path
+    }
+
+    /// Where its events go
+    pub fn emitter(&self) -> &Emitter {
+        &self.emitter
+    }
+
+    /// The last configuration that loaded and validated, if any
+    pub fn last_valid_config(&self) -> Option<&AppConfig> {
+        self.last_valid_config.as_ref()
+    }
+
+    /// Error code (`report::CODES`) of the last load, if it failed
+    pub fn last_error_code(&self) -> Option<&'static str> {
+        self.last_error
+    }
+
+    /// Number of distinct valid configurations loaded so far
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Number of reloads that applied a configuration, the initial load
+    /// not included
+    pub fn reloads(&self) -> u64 {
+        self.totals.reloads
+    }
+
+    /// Final state, for the shutdown event
+    pub(crate) fn status(&self) -> FileStatus<'_> {
+        FileStatus {
+            file: &self.file_path,
+            version: self.version,
+            config: self.last_valid_config.as_ref(),
+        }
+    }
+
+    /// Reads, parses and validates `path`, the watched file or its
+    /// fallback
+    ///
+    /// The time spent in each step is reported at `-v`.
+    #[tracing::instrument(
+        name = "read_config",
+        level = "debug",
+        skip_all,
+        fields(path = %path.display())
+    )]
+    async fn read_config(&self, path: &Path) -> anyhow::Result<AppConfig> {
+        let started = std::time::Instant::now();
+        let mut timings = Timings {
+            stat: self.last_stat,
+            ..Timings::default()
+        };
+        let result = self.read_config_timed(path, &mut timings).await;
+        timings.total = started.elapsed();
+        self.emitter.emit(&Event::Timings {
+            file: path,
+            reload: self.last_valid_config.is_some(),
+            timings,
+        });
+        let result = result.map(|(config, provenance)| {
+            if let Some(ref provenance) = provenance {
+                self.emitter.emit(&Event::Provenance {
+                    file: path,
+                    provenance,
+                });
+            }
+            config
+        });
+
+        if let Some(ref metrics) = self.metrics {
+            metrics.record_load(path, result.as_ref(), &timings);
+        }
+        let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+        match result {
+            Ok(_) => debug!(duration_ms, "configuration read"),
+            Err(ref e) => debug!(
+                duration_ms,
+                error.kind = report::code(e),
+                error = %format!("{e:#}"),
+                "configuration read failed"
+            ),
+        }
+        result
+    }
+
+    /// The steps of [`ConfigWatcher::read_config`]
+    ///
+    /// Uses anyhow::Context to add contextual information to errors. The
+    /// provenance of every field is only traced when `-vv` shows it.
+    async fn read_config_timed(
+        &self,
+        path: &Path,
+        timings: &mut Timings,
+    ) -> anyhow::Result<(AppConfig, Option<Provenance>)> {
+        let span = debug_span!("read", duration_ms = Empty, outcome = Empty);
+        let started = std::time::Instant::now();
+        let doc = self
+            .read_document(path, timings)
+            .instrument(span.clone())
+            .await;
+        timings.read = Some(started.elapsed());
+        record_step(&span, started, &doc);
+        let (mut doc, len, hash) = match doc? {
+            Read::Document { doc, len, hash } => (doc, len, hash),
+            Read::Validated(config) => {
+                debug!("same bytes as the last validated load, parse and validation skipped");
+                return Ok((*config, None));
+            }
+        };
+        let raw = (self.emitter.verbosity() >= Verbosity::Debug).then(|| doc.clone());
+
+        // Apply command-line overrides, then parse
+        let span = debug_span!("parse", duration_ms = Empty, outcome = Empty);
+        let started = std::time::Instant::now();
+        let config = span.in_scope(|| -> anyhow::Result<AppConfig> {
+            self.overrides.apply(&mut doc)?;
+            if let Some(ref prefix) = self.env_prefix {
+                env_config::check_required(prefix, &doc)?;
+            }
+            AppConfig::deserialize(&doc).context("Failed to parse JSON configuration")
+        });
+        timings.parse = Some(started.elapsed());
+        record_step(&span, started, &config);
+        let config = config?;
+
+        // Validate business rules, plus the external schema and the
+        // permission audit if any
+        let span = debug_span!("validate", duration_ms = Empty, outcome = Empty);
+        let started = std::time::Instant::now();
+        let result = span.in_scope(|| {
+            let mut report = ValidationReport::new();
+            if let Some(ref schema) = self.schema {
+                report.merge(schema.check(&doc));
+            }
+            if let Some(ref audit) = self.permissions {
+                let file = self.env_prefix.is_none().then_some(path);
+                let findings = audit.check(file, &doc);
+                for finding in findings.warnings() {
+                    warn!(path = %path.display(), "permission audit: {finding}");
+                }
+                report.merge(findings);
+            }
+            report.merge(config.check());
+            if let Some(ref auth) = config.auth {
+                let base = match self.env_prefix {
+                    Some(_) => Path::new(""),
+                    None => path.parent().unwrap_or(Path::new("")),
+                };
+                auth.check_files(base, &mut report);
+            }
+            report
+                .into_result()
+                .context("Configuration validation failed")
+        });
+        timings.validate = Some(started.elapsed());
+        record_step(&span, started, &result);
+        result?;
+        // The secret files of `auth` are checked on disk: never reused
+        if let Some(len) = len
+            && config.auth.is_none()
+        {
+            *lock(&self.validated) = Some(Validated {
+                path: path.to_path_buf(),
+                len,
+                reused: hash.map(|hash| (hash, config.clone())),
+            });
+        }
+
+        let provenance = raw.map(|raw| {
+            let effective = serde_json::to_value(&config).unwrap_or_default();
+            Provenance::trace(path, &raw, &self.overrides, &effective)
+        });
+        Ok((config, provenance))
+    }
+
+    /// The raw document: the parsed file, or the environment variables,
+    /// with its `age:` values decrypted
+    ///
+    /// Records the size of the file in `timings`.
+    async fn read_document(&self, path: &Path, timings: &mut Timings) -> anyhow::Result<Read> {
+        match self.read_encrypted_document(path, timings).await? {
+            Read::Document { doc, len, hash } => Ok(Read::Document {
+                doc: self.decrypt(path, doc)?,
+                len,
+                hash,
+            }),
+            validated => Ok(validated),
+        }
+    }
+
+    /// `doc` with its `age:` values decrypted, their paths marked secret
+    #[cfg(feature = "age")]
+    fn decrypt(
+        &self,
+        path: &Path,
+        mut doc: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        if !self.age_identities.is_empty() {
+            let decrypted = age::decrypt_document(path, &mut doc, &self.age_identities)?;
+            decrypted.iter().for_each(redact::mark_secret);
+        }
+        Ok(doc)
+    }
+
+    /// Without the `age` feature, `age:` values are plain strings
+    #[cfg(not(feature = "age"))]
+    fn decrypt(&self, _path: &Path, doc: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+        Ok(doc)
+    }
+
+    /// The document as read, `age:` values still encrypted
+    async fn read_encrypted_document(
+        &self,
+        path: &Path,
+        timings: &mut Timings,
+    ) -> anyhow::Result<Read> {
+        if let Some(ref prefix) = self.env_prefix {
+            let doc = env_config::document(prefix, std::env::vars())?;
+            return Ok(Read::Document {
+                doc,
+                len: None,
+                hash: None,
+            });
+        }
+
         // Check if file exists
-        if !self.file_path.exists() {
+        if !path.exists() {
             return Err(ConfigError::FileNotFound {
-                path: self.file_path.clone(),
+                path: path.to_path_buf(),
             }
             .into());
         }
+        sandbox::check_read(path)?;
 
-        // Read file contents asynchronously
-        let contents = fs::read_to_string(&self.file_path)
+        if self.verify_checksum || self.signature_key.is_some() {
+            let contents = fs_util::read_consistent(path, |contents| {
+                if self.verify_checksum {
+                    checksum::check(path, contents)?;
+                }
+                if let Some(ref key) = self.signature_key {
+                    signature::check(path, contents, key)?;
+                }
+                Ok(())
+            })
             .await
+            .context("Failed to read configuration file")?;
+            timings.bytes = Some(contents.len() as u64);
+            return self.parse_document(path, contents.as_bytes());
+        }
+
+        // Taken out of its mutex for the read: no lock is held across await
+        let buffer = std::mem::take(&mut *lock(&self.read_buffer));
+        let (buffer, read) = read_into(path, buffer).await;
+        let result = read
+            .context("Failed to read configuration file")
+            .and_then(|()| {
+                timings.bytes = Some(buffer.len() as u64);
+                self.parse_document(path, &buffer)
+            });
+        *lock(&self.read_buffer) = buffer;
+        result
+    }
+
+    /// Parses the bytes read from `path`, unless they are those of its
+    /// last validated load
+    fn parse_document(&self, path: &Path, bytes: &[u8]) -> anyhow::Result<Read> {
+        let len = self.reuses_validation().then_some(bytes.len());
+        let same_size = matches!(
+            *lock(&self.validated),
+            Some(ref validated) if Some(validated.len) == len && validated.path == path
+        );
+        let hash = same_size.then(|| fs_util::content_hash(bytes));
+        if let Some(hash) = hash
+            && let Some(ref validated) = *lock(&self.validated)
+            && let Some((reused, ref config)) = validated.reused
+            && reused == hash
+        {
+            return Ok(Read::Validated(Box::new(config.clone())));
+        }
+
+        let contents = std::str::from_utf8(bytes)
             .map_err(|e| ConfigError::ReadError {
-                path: self.file_path.clone(),
-                source: e,
+                path: path.to_path_buf(),
+                source: std::io::Error::new(std::io::ErrorKind::InvalidData, e),
             })
             .context("Failed to read configuration file")?;
+        let doc = serde_json::from_str(contents).context("Failed to parse JSON configuration")?;
+        Ok(Read::Document { doc, len, hash })
+    }
 
-        // Parse JSON
-        let config: AppConfig =
-            serde_json::from_str(&contents).context("Failed to parse JSON configuration")?;
+    /// Drops the last validated bytes: the next load is validated again,
+    /// whatever its bytes
+    fn forget_validation(&mut self) {
+        *self.validated.get_mut().unwrap_or_else(|e| e.into_inner()) = None;
+    }
 
-        // Validate business rules
-        config
-            .validate()
-            .context("Configuration validation failed")?;
+    /// Whether unchanged bytes may skip parsing and validation
+    ///
+    /// Not when something besides the bytes decides the outcome: overrides
+    /// re-read from the environment, the permission audit, or `-vv`, which
+    /// traces the provenance of every load.
+    fn reuses_validation(&self) -> bool {
+        !self.reread_env
+            && self.permissions.is_none()
+            && self.emitter.verbosity() < Verbosity::Debug
+    }
 
-        Ok(config)
+    /// Gets the last modified timestamp and size of the file
+    #[tracing::instrument(
+        name = "stat",
+        level = "trace",
+        skip_all,
+        fields(duration_ms = Empty, outcome = Empty)
+    )]
+    async fn stat(&self) -> Result<(SystemTime, u64)> {
+        let started = std::time::Instant::now();
+        let result = self.stat_untraced().await;
+        record_step(&Span::current(), started, &result);
+        result
     }
 
-    /// Gets the last modified timestamp of the file
-    async fn get_modified_time(&self) -> Result<SystemTime> {
+    /// The metadata read of [`ConfigWatcher::stat`]
+    async fn stat_untraced(&self) -> Result<(SystemTime, u64)> {
         let metadata =
             fs::metadata(&self.file_path)
                 .await
@@ -90,118 +895,1818 @@ impl ConfigWatcher {
                     source: e,
                 })?;
 
-        metadata.modified().map_err(|e| ConfigError::MetadataError {
-            path: self.file_path.clone(),
-            source: e,
-        })
+        let modified = metadata
+            .modified()
+            .map_err(|e| ConfigError::MetadataError {
+                path: self.file_path.clone(),
+                source: e,
+            })?;
+        Ok((modified, metadata.len()))
+    }
+
+    /// Gets the last modified timestamp of the file
+    async fn get_modified_time(&self) -> Result<SystemTime> {
+        self.stat().await.map(|(modified, _)| modified)
     }
 
     /// Checks if the file has been modified since last check
-    async fn has_changed(&self) -> Result<bool> {
-        let current_modified = self.get_modified_time().await?;
+    async fn has_changed(&mut self) -> Result<bool> {
+        if let Some(ref prefix) = self.env_prefix {
+            let changed =
+                self.last_env.as_ref() != Some(&env_config::snapshot(prefix, std::env::vars()));
+            if changed {
+                self.emitter.emit(&Event::Decision {
+                    file: &self.file_path,
+                    detail: "Environment variables changed, reloading",
+                });
+            }
+            return Ok(changed);
+        }
 
-        Ok(match self.last_modified {
-            Some(last) => current_modified > last,
-            None => true, // First check always returns true
-        })
+        let started = std::time::Instant::now();
+        let (current_modified, len) = self.stat().await?;
+        self.last_stat = Some(started.elapsed());
+        trace!(modified = ?current_modified, len, "stat");
+        self.emitter.emit(&Event::Stat {
+            file: &self.file_path,
+            modified: current_modified,
+            len,
+        });
+
+        let detail = match self.last_modified {
+            Some(last) if current_modified > last => format!(
+                "Modification time moved ({} -> {}), reloading",
+                humantime::format_rfc3339_nanos(last),
+                humantime::format_rfc3339_nanos(current_modified)
+            ),
+            Some(_) => return Ok(false),
+            // First check always returns true
+            None => "No successful load yet, reloading".to_string(),
+        };
+        self.emitter.emit(&Event::Decision {
+            file: &self.file_path,
+            detail: &detail,
+        });
+        Ok(true)
+    }
+
+    /// Records the watcher's state, reported at `-vv`
+    fn set_state(&mut self, state: WatchState) {
+        if state != self.state {
+            trace!(from = ?self.state, to = ?state, "state transition");
+            self.emitter.emit(&Event::Transition {
+                file: &self.file_path,
+                from: self.state,
+                to: state,
+            });
+            self.state = state;
+        }
+    }
+
+    /// Recompiles the external schema if its file changed
+    ///
+    /// Returns true when a new schema is in place and the config must be
+    /// revalidated. A schema that no longer compiles is reported and the
+    /// previous one is kept.
+    fn refresh_schema(&mut self) -> bool {
+        let Some(ref schema) = self.schema else {
+            return false;
+        };
+        if !schema.is_stale() {
+            return false;
+        }
+
+        match ExternalSchema::load(schema.path()) {
+            Ok(schema) => {
+                debug!(schema = %schema.path().display(), "schema recompiled");
+                self.emitter.emit(&Event::SchemaReloaded {
+                    file: &self.file_path,
+                    schema: schema.path(),
+                });
+                self.schema = Some(schema);
+                self.forget_validation();
+                true
+            }
+            Err(e) => {
+                self.emitter.emit(&Event::SchemaReloadFailed {
+                    file: &self.file_path,
+                    error: &e.into(),
+                });
+                false
+            }
+        }
+    }
+
+    /// Re-reads environment overrides when enabled
+    ///
+    /// Invalid variables are reported and the previous overrides kept.
+    fn refresh_env_overrides(&mut self) {
+        if !self.reread_env {
+            return;
+        }
+        match Overrides::from_env(std::env::vars()) {
+            Ok(env) => {
+                debug!(count = env.entries().len(), "environment overrides re-read");
+                self.overrides.replace_env(env);
+            }
+            Err(e) => self.emitter.emit(&Event::EnvOverridesFailed {
+                file: &self.file_path,
+                error: &e,
+            }),
+        }
+    }
+
+    /// Loads the file once: read, validate and print the summary
+    ///
+    /// Returns whether the configuration is valid. This is all of `--once`;
+    /// [`ConfigWatcher::watch`] starts the same way but keeps waiting on
+    /// failure.
+    ///
+    /// ```
+    /// # use config_watcher::{ConfigWatcher, Emitter, OutputFormat, Verbosity};
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let file = tempfile::NamedTempFile::new()?;
+    /// std::fs::write(file.path(), r#"{ "app_name": "", "version": "1.0.0" }"#)?;
+    ///
+    /// let quiet = Emitter::new(OutputFormat::Json).with_verbosity(Verbosity::Quiet);
+    /// let mut watcher = ConfigWatcher::new(file.path(), 1).with_emitter(quiet);
+    /// assert!(!watcher.load_initial().await?);
+    /// assert_eq!(watcher.last_error_code(), Some("validation_failed"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn load_initial(&mut self) -> anyhow::Result<bool> {
+        self.initial_load(false).await
+    }
+
+    async fn initial_load(&mut self, retrying: bool) -> anyhow::Result<bool> {
+        self.record_env();
+        let valid = match self.read_config(&self.file_path).await {
+            Ok(config) => {
+                self.apply(config, true).await?;
+                true
+            }
+            Err(e) => {
+                self.record_failure(&e);
+                self.emitter.emit(&Event::LoadFailed {
+                    file: &self.file_path,
+                    initial: true,
+                    retrying,
+                    error: &e,
+                });
+                self.restore_last_good() || self.load_fallback(retrying).await
+            }
+        };
+        self.write_status();
+        Ok(valid)
+    }
+
+    /// Restores the snapshot of a previous run, if any, after the file
+    /// failed at startup
+    ///
+    /// Returns whether it is now in use, with the version it had. An
+    /// unusable snapshot is only logged.
+    fn restore_last_good(&mut self) -> bool {
+        let Some(dir) = self.snapshot_dir() else {
+            return false;
+        };
+        let snapshot = match last_good::load(&self.file_path, dir) {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => return false,
+            Err(e) => {
+                warn!(
+                    path = %self.file_path.display(),
+                    error = %format!("{e:#}"),
+                    "last good configuration ignored"
+                );
+                return false;
+            }
+        };
+        self.emitter.emit(&Event::Decision {
+            file: &self.file_path,
+            detail: "Initial load failed, restoring the last good configuration",
+        });
+        self.restored_at = snapshot.saved_at().ok();
+        // `accept` counts it again
+        self.version = snapshot.version.saturating_sub(1);
+        self.accept(snapshot.config, true, WatchState::Restored);
+        true
+    }
+
+    /// Saves the configuration just accepted, if there is a state
+    /// directory; failures are only logged
+    fn save_last_good(&self) {
+        let (Some(dir), Some(config)) = (self.snapshot_dir(), &self.last_valid_config) else {
+            return;
+        };
+        let snapshot = Snapshot::new(
+            &self.file_path,
+            self.version,
+            self.last_modified,
+            config.clone(),
+        );
+        if let Err(e) = last_good::save(dir, &snapshot) {
+            warn!(
+                state_dir = %dir.display(),
+                error = %e,
+                "last good configuration not saved"
+            );
+        }
+    }
+
+    /// Copies the file just accepted to the versions directory, if versions
+    /// are kept; failures are only logged
+    fn save_version(&self) {
+        let (Some(dir), Some(keep)) = (self.snapshot_dir(), self.keep_versions) else {
+            return;
+        };
+        let saved = std::fs::read(&self.file_path)
+            .with_context(|| format!("Cannot read {}", self.file_path.display()))
+            .and_then(|contents| versions::save(dir, self.version, &contents, keep));
+        match saved {
+            Ok(Some(path)) => debug!(path = %path.display(), "configuration version kept"),
+            Ok(None) => {}
+            Err(e) => warn!(
+                state_dir = %dir.display(),
+                error = %format!("{e:#}"),
+                "configuration version not kept"
+            ),
+        }
+    }
+
+    /// The state directory, unless the configuration comes from
+    /// environment variables
+    fn snapshot_dir(&self) -> Option<&Path> {
+        self.state_dir
+            .as_deref()
+            .filter(|_| self.env_prefix.is_none())
+    }
+
+    /// Loads the fallback, if any, after the file failed at startup
+    ///
+    /// Returns whether it is now in use. The file's modification time is
+    /// not recorded: it is re-read on every check until it loads.
+    async fn load_fallback(&mut self, retrying: bool) -> bool {
+        let Some(fallback) = self.fallback.clone() else {
+            return false;
+        };
+        self.emitter.emit(&Event::Decision {
+            file: &self.file_path,
+            detail: "Initial load failed, loading the fallback configuration",
+        });
+        match self.read_config(&fallback).await {
+            Ok(config) => {
+                self.accept(config, true, WatchState::Fallback);
+                true
+            }
+            Err(e) => {
+                // As if there were no fallback: wait for the file
+                let e = e.context(format!(
+                    "Fallback configuration {} cannot be loaded either",
+                    fallback.display()
+                ));
+                self.emitter.emit(&Event::LoadFailed {
+                    file: &self.file_path,
+                    initial: true,
+                    retrying,
+                    error: &e,
+                });
+                false
+            }
+        }
+    }
+
+    /// What serves in place of the file in `state`, if anything
+    fn stand_in(&self, state: WatchState) -> Option<StandIn<'_>> {
+        match state {
+            WatchState::Fallback => self.fallback.as_deref().map(StandIn::Fallback),
+            WatchState::Restored => self.restored_at.map(StandIn::Restored),
+            _ => None,
+        }
+    }
+
+    /// The stand-in in use, until the file loads for the first time
+    fn active_stand_in(&self) -> Option<StandIn<'_>> {
+        self.stand_in(self.state)
+    }
+
+    /// Records the file's modification time, then accepts `config`
+    async fn apply(&mut self, config: AppConfig, initial: bool) -> Result<()> {
+        let span = debug_span!("apply", duration_ms = Empty, outcome = Empty);
+        let started = std::time::Instant::now();
+        let result = self.mark_loaded().instrument(span.clone()).await;
+        if result.is_ok() {
+            span.in_scope(|| self.accept(config, initial, WatchState::Valid));
+            self.save_last_good();
+            self.save_version();
+        }
+        record_step(&span, started, &result);
+        result
+    }
+
+    /// Makes `config` the last valid configuration and reports it
+    ///
+    /// `served` is `Valid` for a configuration of the file, or the state of
+    /// the stand-in it came from; the first configuration of the file
+    /// after a stand-in replaces it for good.
+    fn accept(&mut self, config: AppConfig, initial: bool, served: WatchState) {
+        if served == WatchState::Valid
+            && let Some(from) = self.active_stand_in()
+        {
+            self.emitter.emit(&Event::SwitchedOver {
+                file: &self.file_path,
+                from,
+            });
+        }
+
+        let changes = self.last_valid_config.as_ref().map(|last| {
+            diff::diff(
+                &serde_json::to_value(last).unwrap_or_default(),
+                &serde_json::to_value(&config).unwrap_or_default(),
+            )
+        });
+
+        let flags = self
+            .last_valid_config
+            .as_ref()
+            .map(|last| last.feature_diff(&config));
+
+        // Before the event: its deliveries go through the new proxy
+        if let Some(ref proxy) = self.proxy {
+            proxy.set(config.proxy.clone());
+        }
+
+        if changes.as_ref().is_some_and(Vec::is_empty) {
+            self.emitter.emit(&Event::Unchanged {
+                file: &self.file_path,
+                version: self.version,
+            });
+        } else {
+            self.version += 1;
+            self.last_change = Some(Instant::now());
+            self.emitter.emit(&Event::Loaded {
+                file: &self.file_path,
+                version: self.version,
+                initial,
+                summary: Summary {
+                    config: &config,
+                    overrides: &self.overrides,
+                },
+                changes: changes.as_deref(),
+                flags: flags.as_ref(),
+                stand_in: self.stand_in(served),
+            });
+        }
+
+        if !initial {
+            self.counts.reloads += 1;
+            self.totals.reloads += 1;
+        }
+        if let Some(ref board) = self.status_board {
+            board.publish(&self.file_path, &config);
+        }
+        self.last_valid_config = Some(config);
+        self.last_valid_at = Some(SystemTime::now());
+        self.failure_streak = 0;
+        self.last_error = None;
+        if served == WatchState::Valid {
+            // The file loaded: its stand-ins are never used again
+            self.fallback = None;
+            self.restored_at = None;
+        }
+        self.set_state(served);
     }
 
     /// Main watch loop - monitors file for changes
     ///
-    /// This is the core async logic using tokio
+    /// This is the core async logic using tokio. It only returns on error,
+    /// or when the maximum duration (if any) has elapsed.
+    ///
+    /// What it does is reported through the emitter; a program observes it
+    /// with [`Emitter::with_events`]:
+    ///
+    /// ```no_run
+    /// # use config_watcher::{ConfigWatcher, Emitter, EventKind, OutputFormat};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+    /// let emitter = Emitter::new(OutputFormat::Json).with_events(sender);
+    /// let mut watcher = ConfigWatcher::new("config.json", 2).with_emitter(emitter);
+    /// tokio::spawn(async move { watcher.watch().await });
+    ///
+    /// while let Some(event) = events.recv().await {
+    ///     if event.kind == EventKind::Reload {
+    ///         println!("now at version {:?}", event.version());
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
     pub async fn watch(&mut self) -> anyhow::Result<()> {
-        println!(
-            "👀 Watching configuration file: {}",
-            self.file_path.display()
-        );
-        println!("⏱️  Check interval: {:?}", self.check_interval);
-        println!("Press Ctrl+C to stop\n");
+        self.emitter.emit(&Event::Started {
+            file: &self.file_path,
+            interval: self.check_interval,
+            overrides: &self.overrides,
+        });
 
         // Create an interval timer
         let mut ticker = interval(self.check_interval);
 
         // Initial load
-        match self.read_config().await {
-            Ok(config) => {
-                println!("✅ Initial configuration loaded successfully");
-                self.print_config_summary(&config);
-                self.last_modified = Some(self.get_modified_time().await?);
-                self.last_valid_config = Some(config);
+        self.initial_load(true).await?;
+
+        let deadline = self.max_duration.map(|max| Instant::now() + max);
+        let mut heartbeat = self.heartbeat.map(|every| {
+            let mut heartbeat = interval_at(Instant::now() + every, every);
+            heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            heartbeat
+        });
+
+        // Watch loop
+        loop {
+            // Wait for next interval, or stop once the deadline has passed.
+            // Ticks win ties so a reload due at the deadline still happens.
+            let requested = tokio::select! {
+                biased;
+                _ = next_tick(&mut heartbeat) => {
+                    self.emit_heartbeat();
+                    continue;
+                }
+                _ = ticker.tick() => false,
+                _ = next_request(&mut self.reload_requests) => true,
+                _ = wait_until(deadline) => return Ok(()),
+            };
+            if requested {
+                self.emitter.emit(&Event::Decision {
+                    file: &self.file_path,
+                    detail: "Reload requested, reloading whether the file changed or not",
+                });
+                self.reload(true).await?;
+                self.write_status();
+                continue;
+            }
+            if self.pause.as_ref().is_some_and(Pause::is_paused) {
+                // Paused, not stuck: the probe still hears from the loop
+                if let Some(ref probe) = self.probe {
+                    probe.record(SystemTime::now(), self.state, self.failure_streak);
+                }
+                continue;
+            }
+            self.tick().await?;
+        }
+    }
+
+    /// One check of the file, as the watch loop runs it every interval:
+    /// a stat, and a reload if the file (or its schema) changed
+    ///
+    /// Call [`ConfigWatcher::load_initial`] first. Like the loop, it only
+    /// fails when fail-fast ends the watch.
+    pub async fn tick(&mut self) -> anyhow::Result<()> {
+        self.counts.checks += 1;
+
+        let schema_changed = self.refresh_schema();
+
+        match self
+            .has_changed()
+            .await
+            .map(|changed| changed || schema_changed)
+        {
+            Ok(true) => self.reload(schema_changed).await?,
+            Ok(false) => {
+                // No changes, only reported at -v
+                self.emitter.emit(&Event::Unmodified {
+                    file: &self.file_path,
+                });
+            }
+            Err(e) if self.fails_fast() => {
+                let e = e.into();
+                self.record_watch_error(&e);
+                // The file may be mid-replace; give up only if it stays gone
+                sleep(FAIL_FAST_SETTLE).await;
+                if self.get_modified_time().await.is_err() {
+                    return Err(self.reload_failed(e));
+                }
             }
             Err(e) => {
-                eprintln!("❌ Failed to load initial configuration: {:#}", e);
-                eprintln!("   Waiting for valid configuration...\n");
+                let e = e.into();
+                self.record_watch_error(&e);
+                self.emitter.emit(&Event::FileError {
+                    file: &self.file_path,
+                    error: &e,
+                });
             }
         }
+        self.check_candidate().await?;
+        self.write_status();
+        Ok(())
+    }
 
-        // Watch loop
+    /// `FILE.next`, with `--promote-next`
+    fn candidate_path(&self) -> Option<PathBuf> {
+        if !self.promote_next || self.env_prefix.is_some() {
+            return None;
+        }
+        let mut path = self.file_path.clone().into_os_string();
+        path.push(".next");
+        Some(path.into())
+    }
+
+    /// Validates the `.next` candidate if it appeared or changed since the
+    /// last one, and promotes it over the file if it passes
+    async fn check_candidate(&mut self) -> Result<()> {
+        let Some(candidate) = self.candidate_path() else {
+            return Ok(());
+        };
         loop {
-            ticker.tick().await; // Wait for next interval
-
-            match self.has_changed().await {
-                Ok(true) => {
-                    println!("🔄 File change detected, reloading...");
-
-                    match self.read_config().await {
-                        Ok(config) => {
-                            println!("✅ Configuration reloaded successfully");
-
-                            // Show what changed
-                            if let Some(ref last_config) = self.last_valid_config {
-                                if last_config != &config {
-                                    println!("📝 Configuration has been updated");
-                                    self.print_config_summary(&config);
-                                } else {
-                                    println!("   (File modified but content unchanged)");
-                                }
-                            } else {
-                                self.print_config_summary(&config);
-                            }
-
-                            self.last_modified = Some(self.get_modified_time().await?);
-                            self.last_valid_config = Some(config);
-                        }
-                        Err(e) => {
-                            eprintln!("❌ Configuration reload failed: {:#}", e);
-                            eprintln!("   Keeping last valid configuration\n");
-                        }
-                    }
+            let before = match stamp(&candidate) {
+                Ok(Some(before)) if self.last_candidate != Some(before) => before,
+                Ok(_) => return Ok(()),
+                Err(e) => {
+                    debug!(candidate = %candidate.display(), error = %e, "candidate not checked");
+                    return Ok(());
                 }
-                Ok(false) => {
-                    // No changes, continue watching silently
+            };
+            self.emitter.emit(&Event::Decision {
+                file: &self.file_path,
+                detail: "New .next candidate, validating it",
+            });
+            let replaced = |candidate: &Path| stamp(candidate).ok().flatten() != Some(before);
+            let result = self.read_config(&candidate).await;
+            let approved = match result {
+                Ok(ref config) if !replaced(&candidate) => self.confirmed(config).await,
+                _ => true,
+            };
+            if replaced(&candidate) {
+                // Only what was validated (and approved) may be promoted
+                self.emitter.emit(&Event::Decision {
+                    file: &self.file_path,
+                    detail: "The .next candidate changed meanwhile, validating the new one",
+                });
+                continue;
+            }
+            self.last_candidate = Some(before);
+            if !approved {
+                return Ok(());
+            }
+
+            let promoted = result.and_then(|config| {
+                promote(&candidate, &self.file_path, self.backups)?;
+                Ok(config)
+            });
+            match promoted {
+                Ok(config) => {
+                    self.last_candidate = None;
+                    self.emitter.emit(&Event::Promoted {
+                        file: &self.file_path,
+                        candidate: &candidate,
+                    });
+                    self.apply(config, false).await?;
                 }
-                Err(e) => {
-                    eprintln!("⚠️  Error checking file: {:#}", e);
+                Err(e) => self.emitter.emit(&Event::Rejected {
+                    file: &self.file_path,
+                    candidate: &candidate,
+                    error: &e,
+                }),
+            }
+            return Ok(());
+        }
+    }
+
+    /// Whether `config` may replace the configuration in use: always
+    /// without `--confirm`, when nothing changes, or when it is the first
+    /// one; otherwise as answered
+    ///
+    /// A declined configuration is remembered by hash and declined again
+    /// without asking.
+    async fn confirmed(&mut self, config: &AppConfig) -> bool {
+        let (Some(confirm), Some(last)) = (&self.confirm, &self.last_valid_config) else {
+            return true;
+        };
+        let new = serde_json::to_value(config).unwrap_or_default();
+        let changes = diff::diff(&serde_json::to_value(last).unwrap_or_default(), &new);
+        if changes.is_empty() {
+            return true;
+        }
+        let hash = format!("{:x}", Sha256::digest(new.to_string().as_bytes()));
+        if self.declined.as_ref() == Some(&hash) {
+            self.emitter.emit(&Event::Decision {
+                file: &self.file_path,
+                detail: "Same configuration as the one declined, not asking again",
+            });
+            return false;
+        }
+
+        let answer = match confirm.ask(&self.file_path, &changes, config).await {
+            Ok(answer) => answer,
+            Err(e) => {
+                warn!(error = %e, "confirmation prompt failed, declining");
+                Answer::Declined
+            }
+        };
+        if answer.approves() {
+            self.declined = None;
+            return true;
+        }
+        self.declined = Some(hash);
+        self.emitter.emit(&Event::Declined {
+            file: &self.file_path,
+            timed_out: matches!(answer, Answer::TimedOut(_)),
+        });
+        false
+    }
+
+    /// Summarizes the state, then starts counting again
+    fn emit_heartbeat(&mut self) {
+        let counts = std::mem::take(&mut self.counts);
+        self.emitter.emit(&Event::Heartbeat {
+            file: &self.file_path,
+            version: self.version,
+            config: self.last_valid_config.as_ref(),
+            state: self.state,
+            since_change: self.last_change.map(|at| at.elapsed()),
+            counts,
+        });
+    }
+
+    /// Records how long after the file's write the change was `applied`
+    ///
+    /// An mtime in the future counts as 0, with a warning the first time.
+    fn record_latency(&mut self, applied: SystemTime) {
+        let Some(modified) = self.last_modified.filter(|_| self.env_prefix.is_none()) else {
+            return;
+        };
+        let latency = latency::between(modified, applied).unwrap_or_else(|| {
+            if !self.skew_warned {
+                self.skew_warned = true;
+                warn!(
+                    path = %self.file_path.display(),
+                    "modification time is in the future (clock skew?), counting detection latency as 0"
+                );
+            }
+            Duration::ZERO
+        });
+        self.latency.record(latency);
+        if let Some(ref metrics) = self.metrics {
+            metrics.record_latency(latency);
+        }
+        self.emitter.emit(&Event::Latency {
+            file: &self.file_path,
+            latency,
+            stats: &self.latency,
+        });
+    }
+
+    /// How long changes took to be applied after they were written
+    #[cfg(test)]
+    pub(crate) fn latency(&self) -> &LatencyStats {
+        &self.latency
+    }
+
+    /// Counts a failed load and remembers it for the status file
+    fn record_failure(&mut self, error: &anyhow::Error) {
+        self.last_error = Some(report::code(error));
+        self.counts.failures += 1;
+        self.totals.failures += 1;
+        self.failure_streak += 1;
+        self.last_failure = Some((SystemTime::now(), ErrorSummary::new(error)));
+    }
+
+    /// Counts a file that could not even be checked
+    fn record_watch_error(&mut self, error: &anyhow::Error) {
+        self.counts.failures += 1;
+        self.totals.failures += 1;
+        self.failure_streak += 1;
+        self.last_failure = Some((SystemTime::now(), ErrorSummary::new(error)));
+        if let Some(ref metrics) = self.metrics {
+            metrics.record_watch_error();
+        }
+    }
+
+    /// Re-reads the file after a change and reports the outcome
+    ///
+    /// A `forced` reload (new schema, reload request) did not come from a
+    /// change of the file: no change detected event, no detection latency.
+    /// Only fails when fail-fast ends the watch.
+    #[tracing::instrument(
+        name = "reload",
+        skip_all,
+        fields(path = %self.file_path.display(), version = self.version, outcome = Empty)
+    )]
+    async fn reload(&mut self, forced: bool) -> anyhow::Result<()> {
+        if forced {
+            // A new schema, or someone asking for a fresh look
+            self.forget_validation();
+        } else {
+            self.emitter.emit(&Event::ChangeDetected {
+                file: &self.file_path,
+            });
+        }
+        self.refresh_env_overrides();
+        self.record_env();
+
+        let mut result = self.read_config(&self.file_path).await;
+        if result.is_err() && self.fails_fast() {
+            // One more chance for a writer caught mid-save
+            self.emitter.emit(&Event::Decision {
+                file: &self.file_path,
+                detail: "Reload failed with --fail-fast, retrying once",
+            });
+            debug!(delay = ?FAIL_FAST_SETTLE, "retrying failed reload");
+            sleep(FAIL_FAST_SETTLE).await;
+            result = self.read_config(&self.file_path).await;
+        }
+
+        match result {
+            Ok(config) if !self.confirmed(&config).await => {
+                // Not read again until the file changes
+                self.mark_loaded().await?;
+                Span::current().record("outcome", "declined");
+            }
+            Ok(config) => {
+                self.apply(config, false).await?;
+                if !forced {
+                    self.record_latency(SystemTime::now());
+                }
+                Span::current().record("version", self.version);
+                Span::current().record("outcome", "ok");
+                debug!(version = self.version, "reload accepted");
+            }
+            Err(e) if self.fails_fast() => {
+                self.record_failure(&e);
+                return Err(self.reload_failed(e));
+            }
+            Err(e) => {
+                Span::current().record("outcome", "error");
+                self.record_failure(&e);
+                self.emitter.emit(&Event::LoadFailed {
+                    file: &self.file_path,
+                    initial: false,
+                    retrying: true,
+                    error: &e,
+                });
+                // On a stand-in, the file has yet to load: nothing degrades
+                if self.state == WatchState::Valid {
+                    self.set_state(WatchState::Failing);
                 }
             }
         }
+        Ok(())
     }
 
-    /// Prints a summary of the configuration
-    fn print_config_summary(&self, config: &AppConfig) {
-        println!("   App: {} v{}", config.app_name, config.version);
-        println!("   Environment: {}", config.environment);
+    /// Rewrites the status file, posts on the status board and updates
+    /// the probe, if any; failures are only logged
+    fn write_status(&self) {
+        if let Some(ref probe) = self.probe {
+            probe.record(SystemTime::now(), self.state, self.failure_streak);
+        }
+        if self.status_file.is_none() && self.status_board.is_none() {
+            return;
+        }
+        let status = FileRecord {
+            path: self.file_path.clone(),
+            health: self.state.into(),
+            version: self.version,
+            last_success_at: self.last_valid_at.map(status::timestamp),
+            last_failure_at: self
+                .last_failure
+                .as_ref()
+                .map(|(at, _)| status::timestamp(*at)),
+            last_error: self.last_failure.as_ref().map(|(_, error)| error.clone()),
+            fallback: match self.active_stand_in() {
+                Some(StandIn::Fallback(path)) => Some(path.to_path_buf()),
+                _ => None,
+            },
+            restored_at: match self.active_stand_in() {
+                Some(StandIn::Restored(at)) => Some(status::timestamp(at)),
+                _ => None,
+            },
+            reloads: self.totals.reloads,
+            failures: self.totals.failures,
+        };
+        if let Some(ref board) = self.status_board {
+            board.update(status.clone());
+        }
+        if let Some(ref status_file) = self.status_file
+            && let Err(e) = status_file.update(status)
+        {
+            warn!(status_file = %status_file.path().display(), error = %e, "status file not written");
+        }
+    }
 
-        if let Some(ref server) = config.server {
-            println!(
-                "   Server: {}:{} (SSL: {})",
-                server.host, server.port, server.enable_ssl
-            );
+    /// Remembers the variables about to be read, so the next change is
+    /// detected even if this load fails
+    fn record_env(&mut self) {
+        if let Some(ref prefix) = self.env_prefix {
+            self.last_env = Some(env_config::snapshot(prefix, std::env::vars()));
         }
+    }
 
-        if let Some(ref db) = config.database {
-            println!(
-                "   Database: pool_size={}, timeout={}s",
-                db.pool_size, db.timeout_seconds
-            );
+    /// Remembers the modification time of a file that just loaded
+    async fn mark_loaded(&mut self) -> Result<()> {
+        if self.env_prefix.is_none() {
+            self.last_modified = Some(self.get_modified_time().await?);
         }
+        Ok(())
+    }
 
-        if !config.features.is_empty() {
-            println!(
-                "   Features: {} enabled",
-                config.features.iter().filter(|&(_, v)| *v).count()
-            );
+    /// Whether a reload failure must end the watch
+    ///
+    /// Not before the file itself loaded, even on a stand-in.
+    fn fails_fast(&self) -> bool {
+        self.fail_fast && self.last_valid_config.is_some() && self.active_stand_in().is_none()
+    }
+
+    /// Wraps a reload failure for fail-fast
+    ///
+    /// The status file is left behind with the failure recorded.
+    fn reload_failed(&mut self, error: anyhow::Error) -> anyhow::Error {
+        self.set_state(WatchState::Failing);
+        self.write_status();
+        let last_good = self
+            .last_valid_at
+            .map(|at| humantime::format_rfc3339_seconds(at).to_string())
+            .unwrap_or_else(|| "never".to_string());
+        error.context(ConfigError::ReloadFailed {
+            path: self.file_path.clone(),
+            last_good,
+        })
+    }
+}
+
+/// Reads the whole of `path` into `buffer`, which keeps its capacity, and
+/// hands the buffer back
+///
+/// One blocking task opens and reads the file: `tokio::fs::File` would
+/// send every chunk of it to the blocking pool in turn, which cost a small
+/// file twice its parse.
+async fn read_into(path: &Path, mut buffer: Vec<u8>) -> (Vec<u8>, Result<()>) {
+    let owned = path.to_path_buf();
+    let read = tokio::task::spawn_blocking(move || {
+        buffer.clear();
+        let read = std::fs::File::open(owned).and_then(|mut file| file.read_to_end(&mut buffer));
+        (buffer, read)
+    })
+    .await;
+    let (buffer, read) = read.unwrap_or_else(|e| (Vec::new(), Err(std::io::Error::other(e))));
+    let read = read.map(drop).map_err(|source| ConfigError::ReadError {
+        path: path.to_path_buf(),
+        source,
+    });
+    (buffer, read)
+}
+
+/// Locks `mutex`, poisoned or not: its data is only ever replaced whole
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// What tells one version of a file from the next: modification time,
+/// size and, on Unix, inode
+type Stamp = (SystemTime, u64, u64);
+
+/// The stamp of `path`, `None` when there is no such file
+fn stamp(path: &Path) -> std::io::Result<Option<Stamp>> {
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    #[cfg(unix)]
+    let inode = std::os::unix::fs::MetadataExt::ino(&metadata);
+    #[cfg(not(unix))]
+    let inode = 0;
+    Ok(Some((metadata.modified()?, metadata.len(), inode)))
+}
+
+/// Renames `candidate` over `file`, with the permissions of `file`, once
+/// `file` is backed up
+fn promote(candidate: &Path, file: &Path, backups: Backups) -> Result<()> {
+    let write_error = |source| ConfigError::WriteError {
+        path: file.to_path_buf(),
+        source,
+    };
+    if let Ok(metadata) = std::fs::metadata(file) {
+        std::fs::set_permissions(candidate, metadata.permissions()).map_err(write_error)?;
+    }
+    fs_util::backup(file, backups)?;
+    std::fs::rename(candidate, file).map_err(write_error)
+}
+
+/// Records the duration and outcome of a step on its span
+///
+/// A failure is also logged inside the span with its error chain.
+fn record_step<T, E: std::fmt::Display>(
+    span: &Span,
+    started: std::time::Instant,
+    result: &std::result::Result<T, E>,
+) {
+    if span.is_disabled() {
+        return;
+    }
+    span.record("duration_ms", started.elapsed().as_secs_f64() * 1000.0);
+    match result {
+        Ok(_) => {
+            span.record("outcome", "ok");
+        }
+        Err(e) => {
+            span.record("outcome", "error");
+            span.in_scope(|| debug!(error = %format_args!("{e:#}"), "step failed"));
+        }
+    }
+}
+
+/// The next tick of `interval`, or never when there is none
+async fn next_tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// The next reload request, or never when there are none
+async fn next_request(requests: &mut Option<watch::Receiver<()>>) {
+    if let Some(requests) = requests
+        && requests.changed().await.is_ok()
+    {
+        return;
+    }
+    // Nobody left to send requests
+    std::future::pending().await
+}
+
+/// Completes at `deadline`, or never when there is none
+async fn wait_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tracing::Subscriber;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+    use tracing_subscriber::registry::LookupSpan;
+
+    fn overrides(specs: &[&str]) -> Overrides {
+        let specs: Vec<String> = specs.iter().map(|s| s.to_string()).collect();
+        Overrides::from_args(&specs, &[]).unwrap()
+    }
+
+    /// A FIFO whose content only arrives after `delay`, i.e. a slow disk
+    #[cfg(unix)]
+    fn slow_file(
+        path: &Path,
+        contents: &'static str,
+        delay: Duration,
+    ) -> std::thread::JoinHandle<()> {
+        if !path.exists() {
+            let status = std::process::Command::new("mkfifo")
+                .arg(path)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+        let path = path.to_path_buf();
+        std::thread::spawn(move || {
+            std::thread::sleep(delay);
+            std::fs::write(path, contents).unwrap();
+        })
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_step_timings_reach_the_metrics() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.json");
+        let contents = r#"{ "app_name": "A", "version": "1.0.0" }"#;
+        let metrics = Metrics::default();
+        let mut watcher = ConfigWatcher::new(&path, 1).with_metrics(metrics.clone());
+
+        let writer = slow_file(&path, contents, Duration::from_millis(300));
+        assert!(watcher.load_initial().await.unwrap());
+        writer.join().unwrap();
+
+        let snapshot = metrics.snapshot();
+        let [stat, read, parse, validate] = snapshot.step_seconds;
+        assert_eq!(stat, 0.0, "no stat before the initial load");
+        assert!(read >= 0.3, "{read}");
+        assert!(parse > 0.0 && validate > 0.0, "{parse} {validate}");
+        assert_eq!(snapshot.config_bytes, contents.len() as u64);
+        assert!(snapshot.duration_sum >= read + parse + validate);
+
+        // A reload carries the stat of the check that triggered it
+        watcher.has_changed().await.unwrap();
+        let writer = slow_file(&path, contents, Duration::from_millis(100));
+        watcher.reload(false).await.unwrap();
+        writer.join().unwrap();
+        let snapshot = metrics.snapshot();
+        assert!(snapshot.step_seconds[0] > 0.0);
+        assert!(snapshot.step_seconds[1] >= 0.4);
+    }
+
+    #[tokio::test]
+    async fn test_schema_change_invalidates_the_validation_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.json");
+        let schema = dir.path().join("schema.json");
+        std::fs::write(&path, r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+        std::fs::write(&schema, r#"{ "type": "object" }"#).unwrap();
+        let mut watcher =
+            ConfigWatcher::new(&path, 1).with_schema(ExternalSchema::load(&schema).unwrap());
+        assert!(watcher.load_initial().await.unwrap());
+        assert!(lock(&watcher.validated).is_some());
+
+        // Same bytes, stricter schema: validated again, and rejected
+        std::fs::write(&schema, r#"{ "type": "object", "required": ["owner"] }"#).unwrap();
+        let later = SystemTime::now() + Duration::from_secs(5);
+        std::fs::File::options()
+            .write(true)
+            .open(&schema)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        watcher.tick().await.unwrap();
+        assert_eq!(watcher.state, WatchState::Failing);
+        assert_eq!(watcher.last_valid_config().unwrap().app_name, "A");
+    }
+
+    #[tokio::test]
+    async fn test_new_schema_invalidates_the_validation_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.json");
+        let schema = dir.path().join("schema.json");
+        std::fs::write(&path, r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+        std::fs::write(&schema, r#"{ "required": ["owner"] }"#).unwrap();
+        let mut watcher = ConfigWatcher::new(&path, 1);
+        assert!(watcher.load_initial().await.unwrap());
+        assert!(lock(&watcher.validated).is_some());
+
+        let watcher = watcher.with_schema(ExternalSchema::load(&schema).unwrap());
+        assert!(lock(&watcher.validated).is_none());
+        let err = watcher.read_config(&path).await.unwrap_err();
+        assert!(format!("{err:#}").contains("owner"), "{err:#}");
+    }
+
+    #[tokio::test]
+    async fn test_accepted_loads_share_their_proxy_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.json");
+        std::fs::write(
+            &path,
+            r#"{ "app_name": "A", "version": "1.0.0",
+                 "proxy": { "https": "http://proxy:3128", "no_proxy": ["localhost"] } }"#,
+        )
+        .unwrap();
+        let proxy = CurrentProxy::default();
+        let mut watcher = ConfigWatcher::new(&path, 1).with_proxy(proxy.clone());
+        let url = url::Url::parse("https://hooks.example.com/").unwrap();
+
+        assert!(watcher.load_initial().await.unwrap());
+        assert_eq!(proxy.proxy_for(&url).as_deref(), Some("http://proxy:3128"));
+
+        // A rejected file leaves the section of the last valid one
+        std::fs::write(&path, "{ invalid json }").unwrap();
+        watcher.reload(false).await.unwrap();
+        assert_eq!(proxy.proxy_for(&url).as_deref(), Some("http://proxy:3128"));
+
+        std::fs::write(
+            &path,
+            r#"{ "app_name": "A", "version": "1.0.0",
+                 "proxy": { "https": "http://other:3128", "no_proxy": ["hooks.example.com"] } }"#,
+        )
+        .unwrap();
+        watcher.reload(false).await.unwrap();
+        assert_eq!(proxy.proxy_for(&url), None);
+    }
+
+    #[tokio::test]
+    async fn test_overrides_survive_reloads() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            r#"{ "app_name": "A", "version": "1.0.0", "server": { "host": "h", "port": 80 } }"#,
+        )
+        .unwrap();
+        let watcher =
+            ConfigWatcher::new(file.path(), 1).with_overrides(overrides(&["server.port=9090"]));
+
+        let config = watcher.read_config(file.path()).await.unwrap();
+        assert_eq!(config.server.unwrap().port, 9090);
+
+        std::fs::write(
+            file.path(),
+            r#"{ "app_name": "B", "version": "1.0.0", "server": { "host": "h", "port": 81 } }"#,
+        )
+        .unwrap();
+        let config = watcher.read_config(file.path()).await.unwrap();
+        assert_eq!(config.app_name, "B");
+        assert_eq!(config.server.unwrap().port, 9090);
+    }
+
+    #[tokio::test]
+    async fn test_overrides_are_validated() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            r#"{ "app_name": "A", "version": "1.0.0", "environment": "staging" }"#,
+        )
+        .unwrap();
+
+        // An override can make a valid file invalid...
+        let watcher =
+            ConfigWatcher::new(file.path(), 1).with_overrides(overrides(&["environment=qa"]));
+        let err = watcher.read_config(file.path()).await.unwrap_err();
+        assert!(format!("{err:#}").contains("environment"), "{err:#}");
+
+        // ...and fix an invalid one
+        std::fs::write(file.path(), r#"{ "app_name": "", "version": "1.0.0" }"#).unwrap();
+        let watcher =
+            ConfigWatcher::new(file.path(), 1).with_overrides(overrides(&["app_name=Fixed"]));
+        assert_eq!(
+            watcher.read_config(file.path()).await.unwrap().app_name,
+            "Fixed"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fallback_until_the_file_is_valid() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.json");
+        let fallback = dir.path().join("safe.json");
+        std::fs::write(&path, "{ invalid json }").unwrap();
+        std::fs::write(&fallback, r#"{ "app_name": "Safe", "version": "1.0.0" }"#).unwrap();
+        let mut watcher = ConfigWatcher::new(&path, 1).with_fallback(&fallback);
+
+        assert!(watcher.load_initial().await.unwrap());
+        assert_eq!(watcher.state, WatchState::Fallback);
+        assert_eq!(
+            watcher.active_stand_in(),
+            Some(StandIn::Fallback(&fallback))
+        );
+        assert_eq!(watcher.last_valid_config().unwrap().app_name, "Safe");
+        // Still serving: no fail-fast exit on the fallback
+        assert!(!watcher.with_fail_fast().fails_fast());
+
+        // Still invalid: the fallback stays
+        let mut watcher = ConfigWatcher::new(&path, 1).with_fallback(&fallback);
+        watcher.load_initial().await.unwrap();
+        watcher.reload(false).await.unwrap();
+        assert_eq!(watcher.state, WatchState::Fallback);
+
+        std::fs::write(&path, r#"{ "app_name": "App", "version": "1.0.0" }"#).unwrap();
+        watcher.reload(false).await.unwrap();
+        assert_eq!(watcher.state, WatchState::Valid);
+        assert_eq!(watcher.last_valid_config().unwrap().app_name, "App");
+        assert_eq!(watcher.active_stand_in(), None);
+
+        // Never back to the fallback once the file loaded
+        std::fs::write(&path, "{ invalid json }").unwrap();
+        watcher.reload(false).await.unwrap();
+        assert_eq!(watcher.state, WatchState::Failing);
+        assert_eq!(watcher.last_valid_config().unwrap().app_name, "App");
+    }
+
+    #[tokio::test]
+    async fn test_restores_the_last_good_configuration() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.json");
+        let fallback = dir.path().join("safe.json");
+        std::fs::write(&fallback, r#"{ "app_name": "Safe", "version": "1.0.0" }"#).unwrap();
+
+        // A first run saves each version
+        std::fs::write(&path, r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+        let mut first = ConfigWatcher::new(&path, 1).with_state_dir(state.path());
+        assert!(first.load_initial().await.unwrap());
+        std::fs::write(&path, r#"{ "app_name": "B", "version": "1.0.0" }"#).unwrap();
+        first.reload(false).await.unwrap();
+        assert_eq!(first.version(), 2);
+
+        // The next one starts on a broken file: the snapshot wins over
+        // the fallback
+        std::fs::write(&path, "{ invalid json }").unwrap();
+        let mut second = ConfigWatcher::new(&path, 1)
+            .with_state_dir(state.path())
+            .with_fallback(&fallback);
+        assert!(second.load_initial().await.unwrap());
+        assert_eq!(second.state, WatchState::Restored);
+        assert!(matches!(
+            second.active_stand_in(),
+            Some(StandIn::Restored(_))
+        ));
+        assert_eq!(second.last_valid_config().unwrap().app_name, "B");
+        assert_eq!(second.version(), 2);
+
+        std::fs::write(&path, r#"{ "app_name": "C", "version": "1.0.0" }"#).unwrap();
+        second.reload(false).await.unwrap();
+        assert_eq!(second.state, WatchState::Valid);
+        assert_eq!(second.active_stand_in(), None);
+        assert_eq!(second.version(), 3);
+        let saved = last_good::load(&path, state.path()).unwrap().unwrap();
+        assert_eq!((saved.version, saved.config.app_name.as_str()), (3, "C"));
+
+        // A corrupted snapshot is skipped, on to the fallback
+        std::fs::write(last_good::path(&path, state.path()), "{").unwrap();
+        std::fs::write(&path, "{ invalid json }").unwrap();
+        let mut third = ConfigWatcher::new(&path, 1)
+            .with_state_dir(state.path())
+            .with_fallback(&fallback);
+        assert!(third.load_initial().await.unwrap());
+        assert_eq!(third.state, WatchState::Fallback);
+    }
+
+    #[tokio::test]
+    async fn test_valid_candidate_is_promoted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.json");
+        let next = dir.path().join("app.json.next");
+        std::fs::write(&path, r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+        let mut watcher = ConfigWatcher::new(&path, 1).with_promote_next();
+        assert!(watcher.load_initial().await.unwrap());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
         }
-        println!();
+
+        // Nothing staged
+        watcher.check_candidate().await.unwrap();
+        assert_eq!(watcher.version(), 1);
+
+        let contents = r#"{ "app_name": "B", "version": "1.0.0" }"#;
+        std::fs::write(&next, contents).unwrap();
+        watcher.check_candidate().await.unwrap();
+        assert!(!next.exists());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+        let backup = std::fs::read_to_string(dir.path().join("app.json.bak")).unwrap();
+        assert!(backup.contains(r#""A""#), "{backup}");
+        assert_eq!(watcher.last_valid_config().unwrap().app_name, "B");
+        assert_eq!(watcher.version(), 2);
+        // Applied along with its mtime: no second reload
+        assert!(!watcher.has_changed().await.unwrap());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_candidate_is_rejected_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.json");
+        let next = dir.path().join("app.json.next");
+        let contents = r#"{ "app_name": "A", "version": "1.0.0" }"#;
+        std::fs::write(&path, contents).unwrap();
+        let mut watcher = ConfigWatcher::new(&path, 1).with_promote_next();
+        assert!(watcher.load_initial().await.unwrap());
+
+        std::fs::write(&next, r#"{ "app_name": "", "version": "1.0.0" }"#).unwrap();
+        watcher.check_candidate().await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+        assert!(next.exists());
+        assert_eq!(watcher.last_valid_config().unwrap().app_name, "A");
+        assert_eq!(watcher.state, WatchState::Valid);
+        let rejected = watcher.last_candidate;
+        assert!(rejected.is_some());
+
+        // Not validated again until it changes
+        watcher.check_candidate().await.unwrap();
+        assert_eq!(watcher.last_candidate, rejected);
+        std::fs::write(&next, r#"{ "app_name": "C", "version": "1.0.0" }"#).unwrap();
+        watcher.check_candidate().await.unwrap();
+        assert_eq!(watcher.last_valid_config().unwrap().app_name, "C");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_candidate_replaced_during_validation_is_not_promoted() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.json");
+        let next = dir.path().join("app.json.next");
+        std::fs::write(&path, r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+        let mut watcher = ConfigWatcher::new(&path, 1).with_promote_next();
+        assert!(watcher.load_initial().await.unwrap());
+
+        // The first candidate is still being read when the second one is
+        // renamed into place
+        let status = std::process::Command::new("mkfifo")
+            .arg(&next)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let second = r#"{ "app_name": "Second", "version": "1.0.0" }"#;
+        let writer = {
+            let (next, staged) = (next.clone(), dir.path().join("staged"));
+            std::thread::spawn(move || {
+                let mut fifo = std::fs::OpenOptions::new().write(true).open(&next).unwrap();
+                fifo.write_all(br#"{ "app_name": "First", "version": "1.0.0" }"#)
+                    .unwrap();
+                std::fs::write(&staged, second).unwrap();
+                std::fs::rename(&staged, &next).unwrap();
+            })
+        };
+        watcher.check_candidate().await.unwrap();
+        writer.join().unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), second);
+        assert_eq!(watcher.last_valid_config().unwrap().app_name, "Second");
+        assert_eq!(watcher.version(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_confirm_gates_changes() {
+        use crate::cli::OnConfirmTimeout;
+        use std::io::Write;
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let write = |name: &str| {
+            let contents = format!(r#"{{ "app_name": "{name}", "version": "1.0.0" }}"#);
+            std::fs::write(file.path(), contents).unwrap();
+        };
+        // Unanswered questions apply, so a question that should not have
+        // been asked shows
+        let (input, mut answers) = std::io::pipe().unwrap();
+        let confirm = Confirm::new(std::io::BufReader::new(input), std::io::sink())
+            .with_timeout(Duration::from_secs(2), OnConfirmTimeout::Apply);
+        write("A");
+        let mut watcher = ConfigWatcher::new(file.path(), 1).with_confirm(confirm);
+
+        // The initial load does not ask
+        assert!(watcher.load_initial().await.unwrap());
+
+        writeln!(answers, "n").unwrap();
+        write("B");
+        watcher.reload(false).await.unwrap();
+        assert_eq!(watcher.last_valid_config().unwrap().app_name, "A");
+        assert_eq!(watcher.version(), 1);
+        // Recorded: not read again until the file changes
+        assert!(!watcher.has_changed().await.unwrap());
+
+        // Same content again: declined without asking
+        write("B");
+        watcher.reload(false).await.unwrap();
+        assert_eq!(watcher.last_valid_config().unwrap().app_name, "A");
+
+        // A further change asks again
+        writeln!(answers, "y").unwrap();
+        write("C");
+        watcher.reload(false).await.unwrap();
+        assert_eq!(watcher.last_valid_config().unwrap().app_name, "C");
+        assert_eq!(watcher.version(), 2);
+        assert_eq!(watcher.declined, None);
+    }
+
+    #[tokio::test]
+    async fn test_broken_fallback_is_like_none() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.json");
+        let fallback = dir.path().join("safe.json");
+        std::fs::write(&path, "{ invalid json }").unwrap();
+        let mut watcher = ConfigWatcher::new(&path, 1).with_fallback(&fallback);
+
+        assert!(!watcher.load_initial().await.unwrap());
+        assert_eq!(watcher.state, WatchState::Waiting);
+        assert!(watcher.last_valid_config().is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_max_duration_stops_the_loop() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+        let mut watcher =
+            ConfigWatcher::new(file.path(), 1).with_max_duration(Duration::from_secs(5));
+
+        let start = Instant::now();
+        watcher.watch().await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reload_at_the_deadline_completes() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+
+        // The second tick (t = 2s) coincides with the deadline
+        let mut watcher =
+            ConfigWatcher::new(file.path(), 2).with_max_duration(Duration::from_secs(2));
+        let edit = async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            std::fs::write(file.path(), r#"{ "app_name": "A", "version": "2.0.0" }"#).unwrap();
+        };
+        let (result, ()) = tokio::join!(watcher.watch(), edit);
+        result.unwrap();
+
+        assert_eq!(watcher.last_valid_config().unwrap().version, "2.0.0");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pause_skips_the_checks() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+        let pause = Pause::new(Emitter::new(crate::cli::OutputFormat::Json));
+        assert!(pause.toggle("keyboard"));
+        let mut watcher = ConfigWatcher::new(file.path(), 1)
+            .with_pause(&pause)
+            .with_max_duration(Duration::from_secs(5));
+        let edit_then_resume = async {
+            tokio::time::sleep(Duration::from_millis(1500)).await;
+            std::fs::write(file.path(), r#"{ "app_name": "A", "version": "2.0.0" }"#).unwrap();
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            assert!(!pause.toggle("keyboard"));
+        };
+        let (result, ()) = tokio::join!(watcher.watch(), edit_then_resume);
+        result.unwrap();
+
+        // The ticks at 4s and 5s only, the first one catching up
+        assert_eq!(watcher.counts.checks, 2);
+        assert_eq!(watcher.last_valid_config().unwrap().version, "2.0.0");
+    }
+
+    /// A tracing layer that records every event with its fields and spans,
+    /// and every closed span with its final fields
+    #[derive(Clone, Default)]
+    struct Captured {
+        events: Arc<Mutex<Vec<CapturedEvent>>>,
+        spans: Arc<Mutex<Vec<CapturedSpan>>>,
+    }
+
+    #[derive(Debug)]
+    struct CapturedEvent {
+        spans: Vec<String>,
+        fields: HashMap<String, String>,
+    }
+
+    /// `path` holds the names from the root span down to this one
+    #[derive(Debug)]
+    struct CapturedSpan {
+        path: Vec<String>,
+        fields: HashMap<String, String>,
+    }
+
+    /// Fields of an open span, kept in its extensions
+    #[derive(Default)]
+    struct SpanFields(HashMap<String, String>);
+
+    struct Fields<'a>(&'a mut HashMap<String, String>);
+
+    impl Visit for Fields<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{value:?}"));
+        }
+    }
+
+    impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Captured {
+        fn on_event(&self, event: &tracing::Event<'_>, ctx: Context<'_, S>) {
+            let mut fields = HashMap::new();
+            event.record(&mut Fields(&mut fields));
+            let spans = ctx
+                .event_scope(event)
+                .map(|scope| {
+                    scope
+                        .from_root()
+                        .map(|span| span.name().to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+            self.events
+                .lock()
+                .unwrap()
+                .push(CapturedEvent { spans, fields });
+        }
+
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let mut fields = SpanFields::default();
+            attrs.record(&mut Fields(&mut fields.0));
+            ctx.span(id).unwrap().extensions_mut().insert(fields);
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+            let span = ctx.span(id).unwrap();
+            let mut extensions = span.extensions_mut();
+            if let Some(fields) = extensions.get_mut::<SpanFields>() {
+                values.record(&mut Fields(&mut fields.0));
+            }
+        }
+
+        fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+            let span = ctx.span(&id).unwrap();
+            let fields = span
+                .extensions_mut()
+                .remove::<SpanFields>()
+                .unwrap_or_default()
+                .0;
+            let path = span
+                .scope()
+                .from_root()
+                .map(|span| span.name().to_string())
+                .collect();
+            self.spans
+                .lock()
+                .unwrap()
+                .push(CapturedSpan { path, fields });
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reload_emits_diagnostics() {
+        let captured = Captured::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(captured.clone()));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+        let mut watcher = ConfigWatcher::new(file.path(), 1);
+        assert!(watcher.load_initial().await.unwrap());
+
+        std::fs::write(file.path(), r#"{ "app_name": "B", "version": "1.0.0" }"#).unwrap();
+        watcher.reload(false).await.unwrap();
+        std::fs::write(file.path(), "{ invalid json }").unwrap();
+        watcher.reload(false).await.unwrap();
+
+        let events = captured.events.lock().unwrap();
+        let find = |message: &str| {
+            events
+                .iter()
+                .filter(|event| event.fields["message"] == message)
+                .collect::<Vec<_>>()
+        };
+
+        let read = find("configuration read");
+        assert_eq!(read.len(), 2, "{events:?}");
+        assert_eq!(read[1].spans, ["reload", "read_config"]);
+        assert!(read[1].fields.contains_key("duration_ms"));
+
+        let accepted = find("reload accepted");
+        assert_eq!(accepted[0].fields["version"], "2");
+
+        let failed = find("configuration read failed");
+        assert_eq!(failed.len(), 1, "{events:?}");
+        assert_eq!(failed[0].spans, ["reload", "read_config"]);
+        assert_eq!(failed[0].fields["error.kind"], "invalid_json");
+        assert!(failed[0].fields["error"].contains("Failed to parse JSON"));
+    }
+
+    #[tokio::test]
+    async fn test_detection_latency_from_controlled_mtimes() {
+        let captured = Captured::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(captured.clone()));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let write = |app_name: &str, mtime: SystemTime| {
+            let text = format!(r#"{{ "app_name": "{app_name}", "version": "1.0.0" }}"#);
+            std::fs::write(file.path(), text).unwrap();
+            let handle = std::fs::File::options()
+                .write(true)
+                .open(file.path())
+                .unwrap();
+            handle.set_modified(mtime).unwrap();
+        };
+        let metrics = Metrics::default();
+        let mut watcher = ConfigWatcher::new(file.path(), 1).with_metrics(metrics.clone());
+
+        write("A", SystemTime::now());
+        assert!(watcher.load_initial().await.unwrap());
+        assert_eq!(watcher.latency().count(), 0, "not a detected change");
+
+        write("B", SystemTime::now() - Duration::from_secs(2));
+        watcher.reload(false).await.unwrap();
+        let latency = watcher.latency().min().unwrap();
+        assert!(
+            latency >= Duration::from_secs(2) && latency < Duration::from_secs(3),
+            "{latency:?}"
+        );
+
+        // Written "in the future": counted as 0, warned about once
+        for app_name in ["C", "D"] {
+            write(app_name, SystemTime::now() + Duration::from_secs(3600));
+            watcher.reload(false).await.unwrap();
+        }
+        assert_eq!(watcher.latency().count(), 3);
+        assert_eq!(watcher.latency().min(), Some(Duration::ZERO));
+        assert_eq!(metrics.snapshot().latency_count, 3);
+        let warnings = captured
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| event.fields["message"].contains("clock skew"))
+            .count();
+        assert_eq!(warnings, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reload_spans() {
+        let captured = Captured::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(captured.clone()));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+        let mut watcher = ConfigWatcher::new(file.path(), 1);
+        assert!(watcher.load_initial().await.unwrap());
+        let reload = async |watcher: &mut ConfigWatcher, text: &str| {
+            std::fs::write(file.path(), text).unwrap();
+            captured.spans.lock().unwrap().clear();
+            watcher.reload(false).await.unwrap();
+            std::mem::take(&mut *captured.spans.lock().unwrap())
+        };
+        let find = |spans: &[CapturedSpan], path: &[&str]| {
+            spans
+                .iter()
+                .find(|span| span.path == path)
+                .map(|span| span.fields.clone())
+        };
+
+        // A successful reload: every step under `reload`, all "ok"
+        let spans = reload(&mut watcher, r#"{ "app_name": "B", "version": "1.0.0" }"#).await;
+        let root = find(&spans, &["reload"]).unwrap();
+        assert_eq!(root["version"], "2");
+        assert_eq!(root["outcome"], "ok");
+        assert!(root["path"].ends_with(&*file.path().display().to_string()));
+        for path in [
+            &["reload", "read_config", "read"][..],
+            &["reload", "read_config", "parse"],
+            &["reload", "read_config", "validate"],
+            &["reload", "apply"],
+            &["reload", "apply", "stat"],
+        ] {
+            let fields = find(&spans, path).unwrap_or_else(|| panic!("{path:?}: {spans:?}"));
+            assert_eq!(fields["outcome"], "ok", "{path:?}");
+            assert!(fields["duration_ms"].parse::<f64>().is_ok(), "{path:?}");
+        }
+
+        // A failed one stops at the failing step and is never applied
+        let spans = reload(&mut watcher, r#"{ "app_name": "", "version": "1.0.0" }"#).await;
+        assert_eq!(find(&spans, &["reload"]).unwrap()["outcome"], "error");
+        assert_eq!(find(&spans, &["reload"]).unwrap()["version"], "2");
+        assert_eq!(
+            find(&spans, &["reload", "read_config", "parse"]).unwrap()["outcome"],
+            "ok"
+        );
+        assert_eq!(
+            find(&spans, &["reload", "read_config", "validate"]).unwrap()["outcome"],
+            "error"
+        );
+        assert!(find(&spans, &["reload", "apply"]).is_none(), "{spans:?}");
+
+        let events = captured.events.lock().unwrap();
+        let failed = events
+            .iter()
+            .find(|event| event.fields["message"] == "step failed")
+            .unwrap();
+        assert_eq!(failed.spans, ["reload", "read_config", "validate"]);
+        assert!(
+            failed.fields["error"].contains("Configuration validation failed: "),
+            "{failed:?}"
+        );
+        assert!(failed.fields["error"].contains("app_name"), "{failed:?}");
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_bytes_skip_parse_and_validation() {
+        let captured = Captured::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(captured.clone()));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let text = r#"{ "app_name": "A", "version": "1.0.0" }"#;
+        std::fs::write(file.path(), text).unwrap();
+        let mut watcher = ConfigWatcher::new(file.path(), 1);
+        assert!(watcher.load_initial().await.unwrap());
+        // A first load is neither hashed nor kept
+        assert!(lock(&watcher.validated).as_ref().unwrap().reused.is_none());
+        let rewrite = async |watcher: &mut ConfigWatcher, forced: bool| {
+            std::fs::write(file.path(), text).unwrap();
+            captured.spans.lock().unwrap().clear();
+            watcher.reload(forced).await.unwrap();
+            let spans = std::mem::take(&mut *captured.spans.lock().unwrap());
+            spans
+                .into_iter()
+                .map(|span| span.path.join("/"))
+                .collect::<Vec<_>>()
+        };
+
+        // The same bytes again: validated once more, then kept
+        let spans = rewrite(&mut watcher, false).await;
+        assert!(
+            spans
+                .iter()
+                .any(|span| span == "reload/read_config/validate"),
+            "{spans:?}"
+        );
+        assert!(lock(&watcher.validated).as_ref().unwrap().reused.is_some());
+
+        // And again: read, then straight to apply
+        let spans = rewrite(&mut watcher, false).await;
+        for step in ["reload/read_config/read", "reload/apply"] {
+            assert!(spans.iter().any(|span| span == step), "{step}: {spans:?}");
+        }
+        for step in ["reload/read_config/parse", "reload/read_config/validate"] {
+            assert!(!spans.iter().any(|span| span == step), "{step}: {spans:?}");
+        }
+        assert_eq!(watcher.version(), 1);
+
+        // A forced reload validates afresh
+        let spans = rewrite(&mut watcher, true).await;
+        assert!(
+            spans
+                .iter()
+                .any(|span| span == "reload/read_config/validate"),
+            "{spans:?}"
+        );
+    }
+
+    /// Heartbeats logged while watching `file` for 35s of paused time,
+    /// with `edit` running alongside
+    async fn heartbeats(
+        file: &Path,
+        every: Duration,
+        edit: impl std::future::Future<Output = ()>,
+    ) -> Vec<HashMap<String, String>> {
+        let captured = Captured::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(captured.clone()));
+        let emitter = Emitter::new(crate::cli::OutputFormat::Json)
+            .with_verbosity(Verbosity::Quiet)
+            .with_event_log(crate::output::EventLog::All);
+        let mut watcher = ConfigWatcher::new(file, 1)
+            .with_emitter(emitter)
+            .with_heartbeat(every)
+            .with_max_duration(Duration::from_secs(35));
+        let (result, ()) = tokio::join!(watcher.watch(), edit);
+        result.unwrap();
+
+        let events = captured.events.lock().unwrap();
+        events
+            .iter()
+            .filter(|event| {
+                event
+                    .fields
+                    .get("event")
+                    .is_some_and(|name| name == "heartbeat")
+            })
+            .map(|event| event.fields.clone())
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_heartbeat_cadence_and_content() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+        let edit = async {
+            sleep(Duration::from_millis(12_500)).await;
+            std::fs::write(file.path(), r#"{ "app_name": "A", "version": "2.0.0" }"#).unwrap();
+            sleep(Duration::from_secs(10)).await;
+            std::fs::write(file.path(), "{ invalid json }").unwrap();
+        };
+        let beats = heartbeats(file.path(), Duration::from_secs(10), edit).await;
+
+        // At 10s, 20s and 30s; a beat goes out before the check due with it
+        let summary = |beat: &HashMap<String, String>| {
+            [
+                "version",
+                "state",
+                "since_change_secs",
+                "checks",
+                "reloads",
+                "failures",
+            ]
+            .map(|key| beat[key].clone())
+        };
+        assert_eq!(beats.len(), 3, "{beats:?}");
+        assert_eq!(summary(&beats[0]), ["1", "valid", "10", "10", "0", "0"]);
+        assert_eq!(summary(&beats[1]), ["2", "valid", "7", "10", "1", "0"]);
+        // Failed reloads are retried on every check from 23s
+        assert_eq!(summary(&beats[2]), ["2", "failing", "17", "10", "0", "7"]);
+        assert_eq!(beats[1]["app_name"], "A");
+        assert_eq!(beats[1]["app_version"], "2.0.0");
+        assert_eq!(beats[1]["environment"], "development");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_heartbeat_zero_is_disabled() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+        let beats = heartbeats(file.path(), Duration::ZERO, async {}).await;
+        assert!(beats.is_empty(), "{beats:?}");
     }
 }
@@ -0,0 +1,403 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`impl Notifier`**: The webhook is one of the dispatcher's notifiers;
+  queueing, timeouts and failure counting are the dispatcher's job
+- **`tokio::task::spawn_blocking`**: A delivery is a blocking HTTP request
+  (`http_client::post`) with retries; it runs on a blocking thread of the
+  notifiers' runtime
+- **Shared state**: [`CurrentProxy`] is the `proxy` section the watchers
+  loaded last, looked up again for every attempt
+
+**Design decisions**:
+- The payload is the event's JSON form (the one `--output json` prints,
+  secrets redacted, `diff` and `patch` included) plus the `host` name, so
+  receivers parse one format whatever the transport
+- `http://` and `https://` receivers, through the proxy the configuration's
+  `proxy` section picks for the URL (`no_proxy` included)
+- `--webhook-events` picks the events: by default accepted reloads
+  (`reload`) and failed loads (`failure`)
+- A request gives up after `--webhook-timeout`. Connection errors and 5xx
+  answers are retried twice, after 0.5s then 1s; other answers are final.
+  The dispatcher's timeout covers every attempt
+- Up to `QUEUE` payloads wait for delivery; a full queue drops the oldest
+  one (`configwatcher_notifier_dropped_total{notifier="webhook"}`)
+- With `--webhook-secret`, `X-Signature-256: sha256=<hex>` is the
+  HMAC-SHA256 of the body, as GitHub and most receivers expect
+
+******************************************************************************/
+
+use crate::cli::EventKind;
+use crate::http_client;
+use crate::notify::{ConfigEvent, Notifier, NotifierOptions};
+use crate::proxy::CurrentProxy;
+use hmac::{Hmac, Mac};
+use serde_json::{Value, json};
+use sha2::Sha256;
+use std::time::Duration;
+use url::Url;
+
+/// Default `--webhook-timeout`
+pub const DEFAULT_TIMEOUT: &str = "5s";
+
+/// Payloads waiting for delivery; more drop the oldest
+pub const QUEUE: usize = 64;
+
+/// Where and how to deliver
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub url: Url,
+    pub events: Vec<EventKind>,
+    pub secret: Option<String>,
+    pub timeout: Duration,
+    /// Extra attempts after a connection error or a 5xx answer
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each next one
+    pub backoff: Duration,
+    pub queue: usize,
+    /// Proxy section honored by every attempt
+    pub proxy: CurrentProxy,
+}
+
+impl WebhookConfig {
+    /// Delivery of `events` to `url`, with the default retries
+    pub fn new(url: Url, events: Vec<EventKind>) -> Self {
+        Self {
+            url,
+            events,
+            secret: None,
+            timeout: humantime::parse_duration(DEFAULT_TIMEOUT).unwrap_or_default(),
+            retries: 2,
+            backoff: Duration::from_millis(500),
+            queue: QUEUE,
+            proxy: CurrentProxy::default(),
+        }
+    }
+}
+
+/// Posts configuration events to a webhook
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    config: WebhookConfig,
+    host: String,
+}
+
+impl Webhook {
+    /// A webhook delivering as `config` says
+    pub fn new(config: WebhookConfig) -> Self {
+        Self {
+            config,
+            host: host_name(),
+        }
+    }
+
+    /// The body posted for `event`
+    fn payload(&self, event: &ConfigEvent) -> Value {
+        let mut payload = json!({ "host": self.host });
+        if let (Value::Object(payload), Value::Object(data)) = (&mut payload, &event.data) {
+            payload.extend(data.clone());
+        }
+        payload
+    }
+}
+
+impl Notifier for Webhook {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    /// Every attempt with its timeout, and the backoffs between them
+    fn options(&self) -> NotifierOptions {
+        let config = &self.config;
+        let attempts = config.retries + 1;
+        let backoffs = config.backoff * (2u32.saturating_pow(config.retries) - 1);
+        NotifierOptions {
+            timeout: config.timeout * attempts + backoffs,
+            queue: config.queue,
+            ..NotifierOptions::default()
+        }
+    }
+
+    fn accepts(&self, event: &ConfigEvent) -> bool {
+        self.config.events.contains(&event.kind)
+    }
+
+    async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+        let body = self.payload(event).to_string();
+        let config = self.config.clone();
+        tokio::task::spawn_blocking(move || deliver(&config, &body))
+            .await?
+            .map_err(|e| anyhow::anyhow!("{}: {e}", self.config.url))
+    }
+}
+
+/// Posts `body`, retrying connection errors and 5xx answers
+pub(crate) fn deliver(config: &WebhookConfig, body: &str) -> Result<(), String> {
+    let mut headers = vec![(
+        "User-Agent".to_string(),
+        format!("config-watcher/{}", env!("CARGO_PKG_VERSION")),
+    )];
+    if let Some(ref secret) = config.secret {
+        headers.push(("X-Signature-256".to_string(), signature(secret, body)));
+    }
+
+    let mut backoff = config.backoff;
+    let mut attempt = 0;
+    loop {
+        let proxy = config.proxy.proxy_for(&config.url);
+        let error = match http_client::post(
+            &config.url,
+            &headers,
+            config.timeout,
+            proxy.as_deref(),
+            body,
+        ) {
+            Ok(response) if response.is_success() => return Ok(()),
+            Ok(response) if response.status < 500 => {
+                return Err(format!("receiver answered '{}'", response.line));
+            }
+            Ok(response) => format!("receiver answered '{}'", response.line),
+            Err(e) => e.to_string(),
+        };
+        if attempt == config.retries {
+            return Err(error);
+        }
+        attempt += 1;
+        tracing::debug!(url = %config.url, error, attempt, "webhook delivery failed, retrying");
+        std::thread::sleep(backoff);
+        backoff *= 2;
+    }
+}
+
+/// `sha256=<hex>` of the body, keyed with `secret`
+pub fn signature(secret: &str, body: &str) -> String {
+    // HMAC takes keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("any key length");
+    mac.update(body.as_bytes());
+    format!("sha256={:x}", mac.finalize().into_bytes())
+}
+
+/// This machine's name, for the payload
+pub(crate) fn host_name() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::http_client::testing::receiver;
+    use crate::metrics::Metrics;
+    use crate::notify::{Dispatcher, Notifiers};
+    use crate::output::{Event, Summary};
+    use crate::overrides::Overrides;
+    use std::net::TcpListener;
+    use std::path::Path;
+    use std::time::{Instant, SystemTime};
+
+    fn config(url: Url) -> WebhookConfig {
+        WebhookConfig {
+            backoff: Duration::from_millis(10),
+            timeout: Duration::from_secs(2),
+            ..WebhookConfig::new(url, vec![EventKind::Reload, EventKind::Failure])
+        }
+    }
+
+    fn reload<'a>(config: &'a AppConfig, overrides: &'a Overrides) -> Event<'a> {
+        Event::Loaded {
+            file: Path::new("app.json"),
+            version: 2,
+            initial: false,
+            summary: Summary { config, overrides },
+            changes: Some(&[]),
+            flags: None,
+            stand_in: None,
+        }
+    }
+
+    /// Runs `webhook` in a dispatcher and hands it `events`
+    fn send(webhook: Webhook, events: &[Event<'_>]) -> Dispatcher {
+        let (dispatcher, mut guard) = Notifiers::new().with(webhook).start().unwrap();
+        for event in events {
+            dispatcher.dispatch(event, SystemTime::UNIX_EPOCH);
+        }
+        assert!(guard.shutdown(Duration::from_secs(5)));
+        dispatcher
+    }
+
+    #[test]
+    fn test_signature_known_answers() {
+        // RFC 4231, test cases 1 and 2
+        assert_eq!(
+            signature(&"\x0b".repeat(20), "Hi There"),
+            "sha256=b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+        assert_eq!(
+            signature("Jefe", "what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_payload_shape_and_signature() {
+        let (url, requests) = receiver(&[]);
+        let webhook = Webhook::new(WebhookConfig {
+            secret: Some("s3cret".to_string()),
+            ..config(url)
+        });
+
+        let app = AppConfig::example();
+        let overrides = Overrides::default();
+        let error = anyhow::anyhow!("expected value at line 1 column 3");
+        let dispatcher = send(
+            webhook,
+            &[
+                reload(&app, &overrides),
+                Event::LoadFailed {
+                    file: Path::new("app.json"),
+                    initial: false,
+                    retrying: true,
+                    error: &error,
+                },
+                // Not selected
+                Event::FileError {
+                    file: Path::new("app.json"),
+                    error: &error,
+                },
+            ],
+        );
+
+        let (head, body) = requests.recv().unwrap();
+        assert!(head.starts_with("POST /hooks/config HTTP/1.1"), "{head}");
+        let head = head.to_ascii_lowercase();
+        assert!(head.contains("content-type: application/json"), "{head}");
+        assert!(
+            head.contains(&format!("x-signature-256: {}", signature("s3cret", &body))),
+            "{head}"
+        );
+        let payload: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(payload["timestamp"], "1970-01-01T00:00:00.000Z");
+        assert_eq!(payload["host"], host_name());
+        assert_eq!(payload["event"], "loaded");
+        assert_eq!(payload["file"], "app.json");
+        assert_eq!(payload["version"], 2);
+        assert_eq!(payload["summary"]["app_name"], app.app_name);
+        assert_eq!(payload["diff"], json!([]));
+
+        let (_, body) = requests.recv().unwrap();
+        let payload: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(payload["event"], "load_failed");
+        assert_eq!(
+            payload["error"]["message"],
+            "expected value at line 1 column 3"
+        );
+        assert!(requests.try_recv().is_err());
+        assert_eq!(dispatcher.stats()[0].1.delivered, 2);
+    }
+
+    #[test]
+    fn test_retries_server_errors_only() {
+        let (url, requests) = receiver(&[503, 502]);
+        assert_eq!(deliver(&config(url), "{}"), Ok(()));
+        assert_eq!(requests.try_iter().count(), 3);
+
+        let (url, requests) = receiver(&[500, 500, 500]);
+        let error = deliver(&config(url), "{}").unwrap_err();
+        assert!(error.contains("500"), "{error}");
+        assert_eq!(requests.try_iter().count(), 3);
+
+        let (url, requests) = receiver(&[404]);
+        assert!(deliver(&config(url), "{}").is_err());
+        assert_eq!(requests.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn test_connection_errors_are_retried() {
+        // Bound then closed: nothing listens there any more
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let url = Url::parse(&format!("http://{addr}/")).unwrap();
+        let started = Instant::now();
+        assert!(deliver(&config(url), "{}").is_err());
+        // Two backoffs: 10ms then 20ms
+        assert!(started.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn test_https_receivers_are_accepted() {
+        let url = http_client::parse_url("https://hooks.example.com/config").unwrap();
+        assert_eq!(url.port_or_known_default(), Some(443));
+        let error = http_client::parse_url("ftp://hooks.example.com/").unwrap_err();
+        assert!(error.to_string().contains("https://"), "{error}");
+    }
+
+    #[test]
+    fn test_delivery_goes_through_the_loaded_proxy() {
+        // The proxy records the request it is asked to forward and refuses it
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy_url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, asked) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            use std::io::{BufRead, BufReader, Write};
+            let (stream, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+            let _ = (&stream).write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n");
+            sender.send(line).unwrap();
+        });
+
+        let config = WebhookConfig {
+            retries: 0,
+            ..config(Url::parse("https://hooks.example.com/config").unwrap())
+        };
+        config.proxy.set(Some(crate::config::ProxyConfig {
+            http: None,
+            https: Some(proxy_url),
+            no_proxy: vec!["localhost".to_string()],
+        }));
+        assert!(deliver(&config, "{}").is_err());
+        let line = asked.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(line.starts_with("CONNECT hooks.example.com:443 "), "{line}");
+    }
+
+    #[test]
+    fn test_slow_receiver_never_blocks_and_drops_the_oldest() {
+        // Accepts connections but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let metrics = Metrics::default();
+        let (dispatcher, mut guard) = Notifiers::new()
+            .with(Webhook::new(WebhookConfig {
+                queue: 2,
+                timeout: Duration::from_secs(30),
+                ..config(url)
+            }))
+            .with_metrics(metrics.clone())
+            .start()
+            .unwrap();
+
+        let app = AppConfig::example();
+        let overrides = Overrides::default();
+        let started = Instant::now();
+        for _ in 0..10 {
+            dispatcher.dispatch(&reload(&app, &overrides), SystemTime::now());
+        }
+        assert!(started.elapsed() < Duration::from_secs(1));
+        // At most one payload is in flight, two wait: the rest were dropped
+        let dropped = dispatcher.stats()[0].1.dropped;
+        assert!(dropped >= 7, "{dropped}");
+        assert_eq!(metrics.snapshot().notifiers["webhook"].queue_full, dropped);
+
+        assert!(!guard.shutdown(Duration::from_millis(100)));
+        drop(listener);
+    }
+}
@@ -0,0 +1,485 @@
+/******************************************************************************
+
+**Key Rust concepts**:
+- **`tokio::sync::broadcast`**: One channel for all the connections; each
+  connection reads at its own pace, and one that falls more than `BUFFER`
+  messages behind gets `RecvError::Lagged(missed)` instead of slowing the
+  others or the watch loop
+- **`tokio::select!`**: A connection waits for an event, a frame from the
+  client, its ping timer or the server's shutdown, whichever comes first
+- **`hyper::upgrade::on`**: hyper hands over the connection once the 101
+  answer is written; `WebSocketStream::from_raw_socket` takes it from
+  there. Its `next()` keeps a frame half read in the stream, so one is
+  never lost when another branch of the `select!` wins
+- **`#[cfg(feature = "http-server")]`**: Left out of builds without the
+  feature, like the server itself
+
+**Design decisions**:
+- tokio-tungstenite does the framing, masking and close handshake of
+  RFC 6455 and answers the clients' pings; only the 101 response is
+  built here, with its `derive_accept_key`. What clients send otherwise
+  is read and ignored, up to `MAX_FRAME` bytes a message
+- `GET /ws` on `--http-addr` first sends one `{"event": "current"}` message
+  per file with its redacted configuration, as `GET /config` serves it,
+  then every event notifiers get, as `--output json` prints it (secrets
+  redacted): loads with their diff, failures, file errors, the shutdown
+- `?events=failures` subscribes to failed loads and file errors only, with
+  no `current` messages
+- Events come from a `Notifier`, so the watch loop only queues them. A
+  connection that lags is sent `{"event": "lagged", "missed": N}` and
+  resumes with the oldest message still buffered
+- The server pings every `PING_INTERVAL` (30s); a client that has not
+  answered by the next ping is disconnected, so is one that does not take
+  a frame within `WRITE_TIMEOUT`
+- On shutdown every connection gets a close frame (1001, going away)
+
+******************************************************************************/
+
+use crate::cli::EventKind;
+use crate::listener::{self, Body};
+use crate::notify::{ConfigEvent, Notifier};
+use crate::status::StatusBoard;
+use futures::{SinkExt, StreamExt};
+use hyper::body::Incoming;
+use hyper::header::{
+    CONNECTION, HeaderValue, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION,
+    UPGRADE,
+};
+use hyper::upgrade::Upgraded;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::json;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::watch;
+use tokio::time::{Instant, interval_at, timeout};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message as Frame;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, Role, WebSocketConfig};
+
+/// Messages buffered for a connection; a slower one misses the oldest
+pub const BUFFER: usize = 64;
+
+/// Time between two pings
+pub const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time allowed to a client to take one frame
+pub const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest message accepted from a client
+const MAX_FRAME: usize = 64 * 1024;
+
+/// A connection past the upgrade
+type Socket = WebSocketStream<TokioIo<Upgraded>>;
+
+/// Events for the `/ws` connections, fed by an [`EventStreamNotifier`]
+#[derive(Debug, Clone)]
+pub struct EventStream {
+    sender: broadcast::Sender<Message>,
+    ping_interval: Duration,
+}
+
+#[derive(Debug, Clone)]
+struct Message {
+    kind: EventKind,
+    json: Arc<str>,
+}
+
+/// Which events a connection gets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subscription {
+    All,
+    /// Failed loads and file errors
+    Failures,
+}
+
+/// Hands the notifiers' events to an [`EventStream`]
+#[derive(Debug)]
+pub struct EventStreamNotifier {
+    sender: broadcast::Sender<Message>,
+}
+
+impl Default for EventStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventStream {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(BUFFER).0,
+            ping_interval: PING_INTERVAL,
+        }
+    }
+
+    /// Pings every `every` instead of every `PING_INTERVAL`
+    #[cfg(test)]
+    pub fn with_ping_interval(mut self, every: Duration) -> Self {
+        self.ping_interval = every;
+        self
+    }
+
+    /// The notifier feeding this stream
+    pub fn notifier(&self) -> EventStreamNotifier {
+        EventStreamNotifier {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl Subscription {
+    /// The subscription asked for by the query string of `/ws`
+    pub fn from_query(query: &str) -> Result<Self, String> {
+        let events = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find_map(|(name, value)| (name == "events").then_some(value));
+        match events {
+            None | Some("all") => Ok(Subscription::All),
+            Some("failures") => Ok(Subscription::Failures),
+            Some(other) => Err(format!(
+                "unknown events '{other}', expected all or failures"
+            )),
+        }
+    }
+
+    fn wants(self, kind: EventKind) -> bool {
+        match self {
+            Subscription::All => true,
+            Subscription::Failures => matches!(kind, EventKind::Failure | EventKind::FileError),
+        }
+    }
+}
+
+/// Answers the upgrade `request`, then sends the configurations of `board`
+/// (unless only failures are wanted) and the events of `events` until the
+/// client leaves or `closing` changes
+pub(crate) fn serve(
+    mut request: Request<Incoming>,
+    events: &EventStream,
+    board: Option<&StatusBoard>,
+    closing: watch::Receiver<bool>,
+) -> Response<Body> {
+    let query = request.uri().query().unwrap_or_default();
+    let subscription = match Subscription::from_query(query) {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            let body = json!({ "error": e }).to_string();
+            return listener::respond(StatusCode::BAD_REQUEST, "application/json", body);
+        }
+    };
+    let headers = request.headers();
+    let upgrade = headers
+        .get(UPGRADE)
+        .is_some_and(|upgrade| upgrade.as_bytes().eq_ignore_ascii_case(b"websocket"));
+    let Some(key) = headers.get(SEC_WEBSOCKET_KEY).filter(|_| upgrade) else {
+        let body = json!({ "error": "a WebSocket upgrade is required" }).to_string();
+        let mut response =
+            listener::respond(StatusCode::UPGRADE_REQUIRED, "application/json", body);
+        let headers = response.headers_mut();
+        headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(SEC_WEBSOCKET_VERSION, HeaderValue::from_static("13"));
+        return response;
+    };
+    let accept = derive_accept_key(key.as_bytes());
+
+    // Subscribed before the configurations are read: a load in between is
+    // sent twice rather than missed
+    let receiver = events.sender.subscribe();
+    let current = match (subscription, board) {
+        (Subscription::All, Some(board)) => board
+            .configs()
+            .iter()
+            .map(|(path, config)| {
+                format!(
+                    r#"{{"event":"current","file":{},"config":{config}}}"#,
+                    json!(path.display().to_string())
+                )
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let upgraded = hyper::upgrade::on(&mut request);
+    let ping_interval = events.ping_interval;
+    tokio::spawn(async move {
+        let result = match upgraded.await {
+            Ok(upgraded) => {
+                let stream = TokioIo::new(upgraded);
+                session(
+                    stream,
+                    receiver,
+                    subscription,
+                    current,
+                    closing,
+                    ping_interval,
+                )
+                .await
+            }
+            Err(e) => Err(io::Error::other(e)),
+        };
+        if let Err(e) = result {
+            tracing::debug!(error = %e, "websocket connection failed");
+        }
+    });
+
+    let mut response = Response::new(Body::default());
+    *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+    let headers = response.headers_mut();
+    headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(CONNECTION, HeaderValue::from_static("Upgrade"));
+    let accept = HeaderValue::from_str(&accept).expect("base64 is a valid header value");
+    headers.insert(SEC_WEBSOCKET_ACCEPT, accept);
+    response
+}
+
+async fn session(
+    stream: TokioIo<Upgraded>,
+    mut events: broadcast::Receiver<Message>,
+    subscription: Subscription,
+    current: Vec<String>,
+    mut closing: watch::Receiver<bool>,
+    ping_interval: Duration,
+) -> io::Result<()> {
+    let config = WebSocketConfig::default()
+        .max_frame_size(Some(MAX_FRAME))
+        .max_message_size(Some(MAX_FRAME));
+    let mut ws = WebSocketStream::from_raw_socket(stream, Role::Server, Some(config)).await;
+
+    for message in current {
+        send(&mut ws, Frame::text(message)).await?;
+    }
+    let mut ping = interval_at(Instant::now() + ping_interval, ping_interval);
+    let mut awaiting_pong = false;
+    loop {
+        // Queued events go out before a close
+        let frame = tokio::select! {
+            biased;
+            message = events.recv() => match message {
+                Ok(message) if subscription.wants(message.kind) => Frame::text(&*message.json),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(missed)) => {
+                    Frame::text(json!({ "event": "lagged", "missed": missed }).to_string())
+                }
+                Err(RecvError::Closed) => return close(&mut ws, CloseCode::Away, "going away").await,
+            },
+            frame = ws.next() => match frame {
+                Some(Ok(Frame::Pong(_))) => {
+                    awaiting_pong = false;
+                    continue;
+                }
+                // tungstenite queues the answer to a close, sent by the
+                // next flush, and to a ping, sent by the next read
+                Some(Ok(Frame::Close(_))) => return flush(&mut ws).await,
+                Some(Ok(_)) => continue,
+                Some(Err(_)) | None => return Ok(()),
+            },
+            _ = ping.tick() => {
+                if awaiting_pong {
+                    return close(&mut ws, CloseCode::Away, "no pong").await;
+                }
+                awaiting_pong = true;
+                Frame::Ping(Default::default())
+            }
+            _ = closing.changed() => return close(&mut ws, CloseCode::Away, "shutting down").await,
+        };
+        send(&mut ws, frame).await?;
+    }
+}
+
+/// Sends one frame, giving up after `WRITE_TIMEOUT`
+async fn send(ws: &mut Socket, frame: Frame) -> io::Result<()> {
+    match timeout(WRITE_TIMEOUT, ws.send(frame)).await {
+        Ok(result) => result.map_err(io::Error::other),
+        Err(_) => Err(io::ErrorKind::TimedOut.into()),
+    }
+}
+
+/// Sends what tungstenite queued, giving up after `WRITE_TIMEOUT`
+async fn flush(ws: &mut Socket) -> io::Result<()> {
+    match timeout(WRITE_TIMEOUT, ws.flush()).await {
+        Ok(result) => result.map_err(io::Error::other),
+        Err(_) => Err(io::ErrorKind::TimedOut.into()),
+    }
+}
+
+/// Sends a close frame with `code` and `reason`, then waits up to
+/// `WRITE_TIMEOUT` for the client's
+async fn close(ws: &mut Socket, code: CloseCode, reason: &str) -> io::Result<()> {
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+    send(ws, Frame::Close(Some(frame))).await?;
+    let _ = timeout(WRITE_TIMEOUT, async {
+        while let Some(Ok(_)) = ws.next().await {}
+    })
+    .await;
+    Ok(())
+}
+
+impl Notifier for EventStreamNotifier {
+    fn name(&self) -> &str {
+        "websocket"
+    }
+
+    /// Nobody listening is not a failure
+    async fn notify(&self, event: &ConfigEvent) -> anyhow::Result<()> {
+        let _ = self.sender.send(Message {
+            kind: event.kind,
+            json: event.data.to_string().into(),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpStream;
+    use tokio_tungstenite::client_async;
+
+    type Client = WebSocketStream<TcpStream>;
+
+    /// A client connected to `/ws?{query}`, past the handshake
+    async fn connect(events: &EventStream, query: &str, closing: watch::Receiver<bool>) -> Client {
+        let events = events.clone();
+        let (addr, _) = listener::listen("127.0.0.1:0".parse().unwrap(), None, move |request| {
+            let response = serve(request, &events, None, closing.clone());
+            async move { response }
+        })
+        .await
+        .unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        // tungstenite checks Sec-WebSocket-Accept
+        let (client, response) = client_async(format!("ws://test/ws?{query}"), client)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 101);
+        client
+    }
+
+    fn publish(events: &EventStream, kind: EventKind, n: usize) {
+        let _ = events.sender.send(Message {
+            kind,
+            json: json!({ "n": n }).to_string().into(),
+        });
+    }
+
+    async fn read_json(client: &mut Client) -> serde_json::Value {
+        match client.next().await.unwrap().unwrap() {
+            Frame::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("not text: {other:?}"),
+        }
+    }
+
+    /// The code and reason of the close frame, past any ping
+    async fn read_close(client: &mut Client) -> (CloseCode, String) {
+        loop {
+            match client.next().await.unwrap().unwrap() {
+                Frame::Close(Some(frame)) => return (frame.code, frame.reason.to_string()),
+                Frame::Ping(_) => continue,
+                other => panic!("not a close: {other:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unread_connection_lags_then_catches_up() {
+        let events = EventStream::new();
+        let (closing, closing_receiver) = watch::channel(false);
+        let mut client = connect(&events, "", closing_receiver).await;
+
+        // All at once, before the connection's task gets to run
+        for n in 0..BUFFER * 3 {
+            publish(&events, EventKind::Reload, n);
+        }
+        assert_eq!(
+            read_json(&mut client).await,
+            json!({ "event": "lagged", "missed": BUFFER * 2 })
+        );
+        for n in BUFFER * 2..BUFFER * 3 {
+            assert_eq!(read_json(&mut client).await, json!({ "n": n }));
+        }
+
+        // Pings are answered, then shutdown closes with 1001 and ends the
+        // stream once the client has answered
+        client.send(Frame::Ping("hi".into())).await.unwrap();
+        assert_eq!(
+            client.next().await.unwrap().unwrap(),
+            Frame::Pong("hi".into())
+        );
+        closing.send_replace(true);
+        assert_eq!(
+            read_close(&mut client).await,
+            (CloseCode::Away, "shutting down".to_string())
+        );
+        assert!(client.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failures_only() {
+        let events = EventStream::new();
+        let (_closing, closing_receiver) = watch::channel(false);
+        let mut client = connect(&events, "events=failures", closing_receiver).await;
+        publish(&events, EventKind::Reload, 0);
+        publish(&events, EventKind::Failure, 1);
+        publish(&events, EventKind::Shutdown, 2);
+        publish(&events, EventKind::FileError, 3);
+        assert_eq!(read_json(&mut client).await, json!({ "n": 1 }));
+        assert_eq!(read_json(&mut client).await, json!({ "n": 3 }));
+    }
+
+    #[tokio::test]
+    async fn test_client_close_is_answered() {
+        let events = EventStream::new();
+        let (_closing, closing_receiver) = watch::channel(false);
+        let mut client = connect(&events, "", closing_receiver).await;
+        client.close(None).await.unwrap();
+        assert!(matches!(
+            client.next().await,
+            Some(Ok(Frame::Close(_))) | None
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_pings_disconnect() {
+        let events = EventStream::new().with_ping_interval(Duration::from_millis(20));
+        let (_closing, closing_receiver) = watch::channel(false);
+        let mut client = connect(&events, "", closing_receiver).await;
+        // A client only answers pings while it reads
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(
+            read_close(&mut client).await,
+            (CloseCode::Away, "no pong".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_oversized_message_disconnects() {
+        let events = EventStream::new();
+        let (_closing, closing_receiver) = watch::channel(false);
+        let mut client = connect(&events, "", closing_receiver).await;
+        client
+            .send(Frame::text("x".repeat(MAX_FRAME + 1)))
+            .await
+            .unwrap();
+        assert!(!matches!(client.next().await, Some(Ok(Frame::Text(_)))));
+    }
+
+    #[test]
+    fn test_subscription_from_query() {
+        assert_eq!(Subscription::from_query(""), Ok(Subscription::All));
+        assert_eq!(
+            Subscription::from_query("x=1&events=failures"),
+            Ok(Subscription::Failures)
+        );
+        assert!(Subscription::from_query("events=reloads").is_err());
+        assert!(Subscription::Failures.wants(EventKind::FileError));
+        assert!(!Subscription::Failures.wants(EventKind::Reload));
+    }
+}
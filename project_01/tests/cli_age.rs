@@ -0,0 +1,186 @@
+// Runs the real binary with --age-identity against configuration files
+// holding the age-encrypted values of tests/fixtures/age.
+
+mod common;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/age")
+        .join(name)
+}
+
+/// A copy of the identity fixture `name` next to `file`, readable by its
+/// owner only, as --permission-audit expects of a secret file
+fn identity(file: &Path, name: &str) -> PathBuf {
+    let copy = file.with_file_name(name);
+    fs::copy(fixture(name), &copy).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&copy, fs::Permissions::from_mode(0o600)).unwrap();
+    }
+    copy
+}
+
+/// An encrypted value of `values.json`
+fn value(name: &str) -> String {
+    let values: Value =
+        serde_json::from_str(&fs::read_to_string(fixture("values.json")).unwrap()).unwrap();
+    values[name].as_str().unwrap().to_string()
+}
+
+fn secret_config(connection_string: &str, host: &str) -> String {
+    format!(
+        r#"{{
+    "app_name": "TestApp",
+    "version": "1.0.0",
+    "server": {{ "host": "{host}", "port": 8080 }},
+    "database": {{ "connection_string": "{connection_string}" }}
+}}"#
+    )
+}
+
+fn once(file: &Path, name: &str) -> Output {
+    common::config_watcher()
+        .args(["-f", file.to_str().unwrap(), "--once"])
+        .arg("--age-identity")
+        .arg(identity(file, name))
+        .args(["--output", "json"])
+        .output()
+        .unwrap()
+}
+
+/// The error of the `load_failed` event
+fn load_error(output: &Output) -> Value {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .find(|event| event["event"] == "load_failed")
+        .unwrap_or_else(|| panic!("no load_failed event: {stdout}"))["error"]
+        .clone()
+}
+
+#[test]
+fn test_once_decrypts_with_the_identity() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    fs::write(
+        &file,
+        secret_config(&value("connection_string"), &value("host")),
+    )
+    .unwrap();
+
+    let output = once(&file, "identity.txt");
+    assert_eq!(output.status.code(), Some(0), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\"loaded\""), "{stdout}");
+    // The decrypted host is as secret as the connection string
+    assert!(!stdout.contains("db.internal"), "{stdout}");
+    assert!(stdout.contains("<redacted>"), "{stdout}");
+}
+
+#[test]
+fn test_wrong_key_and_corrupted_values_name_the_fields() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    let encrypted = value("connection_string");
+
+    // Encrypted to us, read with another key
+    fs::write(&file, secret_config(&encrypted, "localhost")).unwrap();
+    let output = once(&file, "other_identity.txt");
+    assert_eq!(output.status.code(), Some(3));
+    let error = load_error(&output);
+    assert_eq!(error["code"], "decryption_failed");
+    let message = error["message"].as_str().unwrap();
+    assert!(
+        message.contains("database.connection_string: no identity matches"),
+        "{message}"
+    );
+
+    // The last byte of the payload flipped
+    let mut data = STANDARD
+        .decode(encrypted.strip_prefix("age:").unwrap())
+        .unwrap();
+    *data.last_mut().unwrap() ^= 1;
+    let corrupted = format!("age:{}", STANDARD.encode(&data));
+    fs::write(&file, secret_config(&corrupted, &value("host"))).unwrap();
+    let output = once(&file, "identity.txt");
+    assert_eq!(output.status.code(), Some(3));
+    let message = load_error(&output)["message"].as_str().unwrap().to_string();
+    assert!(
+        message.contains("database.connection_string: corrupted"),
+        "{message}"
+    );
+    // Never the ciphertext, nor the plaintext of the value that did decrypt
+    let all = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!all.contains(&corrupted[4..40]), "{all}");
+    assert!(!all.contains("db.internal"), "{all}");
+}
+
+#[test]
+fn test_invalid_identity_file_fails_startup() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    fs::write(&file, secret_config("plain", "localhost")).unwrap();
+
+    let output = common::config_watcher()
+        .args(["-f", file.to_str().unwrap(), "--once"])
+        .arg("--age-identity")
+        .arg(fixture("values.json"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Invalid age identity file"), "{stderr}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_reload_diff_redacts_decrypted_values() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    fs::write(
+        &file,
+        secret_config(&value("connection_string"), &value("host")),
+    )
+    .unwrap();
+
+    let watch = common::Watch::spawn(
+        common::config_watcher()
+            .args(["-f", file.to_str().unwrap(), "--interval", "1"])
+            .arg("--age-identity")
+            .arg(identity(&file, "identity.txt"))
+            .args(["--output", "json"]),
+    );
+    watch.wait_for_event("loaded", 1);
+    fs::write(
+        &file,
+        secret_config(&value("connection_string"), &value("host_2")),
+    )
+    .unwrap();
+    watch.wait_for_event("loaded", 2);
+    let output = watch.stop();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("internal"), "{stdout}");
+    let events = common::events(&output.stdout);
+    let reload = events
+        .iter()
+        .find(|e| e["event"] == "loaded" && e["initial"] == false)
+        .unwrap_or_else(|| panic!("no reload: {stdout}"));
+    assert_eq!(reload["diff"][0]["path"], "server.host");
+    assert_eq!(reload["diff"][0]["new"], "<redacted>");
+}
@@ -0,0 +1,92 @@
+// Runs the real binary with --verify-checksum against configuration files
+// and their .sha256 sidecars.
+
+mod common;
+
+use common::{config, error_code};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::time::Duration;
+
+fn sidecar(file: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.sha256", file.display()))
+}
+
+/// Writes the `sha256sum` line of `contents` next to `file`
+fn write_sidecar(file: &Path, contents: &str) {
+    fs::write(
+        sidecar(file),
+        format!("{:x}  config.json\n", Sha256::digest(contents.as_bytes())),
+    )
+    .unwrap();
+}
+
+fn once(file: &Path) -> Output {
+    common::config_watcher()
+        .args(["-f", file.to_str().unwrap(), "--once", "--verify-checksum"])
+        .args(["--error-format", "json"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_once_checks_the_sidecar() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    fs::write(&file, config("TestApp", "1.0.0")).unwrap();
+
+    // Missing sidecar
+    let output = once(&file);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(error_code(&output), "checksum_unavailable");
+
+    write_sidecar(&file, &config("TestApp", "1.0.0"));
+    assert_eq!(once(&file).status.code(), Some(0));
+
+    // Well-formed JSON, mangled value
+    fs::write(&file, config("TestApp", "1.0.1")).unwrap();
+    let output = once(&file);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(error_code(&output), "checksum_mismatch");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_reload_waits_for_the_sidecar_and_keeps_the_last_valid_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    fs::write(&file, config("TestApp", "1.0.0")).unwrap();
+    write_sidecar(&file, &config("TestApp", "1.0.0"));
+
+    let watch = common::Watch::spawn(
+        common::config_watcher()
+            .args(["-f", file.to_str().unwrap(), "--interval", "1"])
+            .args(["--verify-checksum", "--output", "json"]),
+    );
+    watch.wait_for_event("loaded", 1);
+    // The file first, its sidecar a moment later: accepted
+    fs::write(&file, config("TestApp", "2.0.0")).unwrap();
+    std::thread::sleep(Duration::from_millis(150));
+    write_sidecar(&file, &config("TestApp", "2.0.0"));
+    watch.wait_for_event("loaded", 2);
+    // Corrupted, the sidecar unchanged: rejected
+    fs::write(&file, config("TestApp", "2.0.1")).unwrap();
+    let mismatch = |events: &[serde_json::Value]| {
+        common::named(events, "load_failed")
+            .iter()
+            .any(|e| e["error"]["code"] == "checksum_mismatch")
+    };
+    watch.until("checksum mismatch", |watch| mismatch(&watch.events()));
+    let output = watch.stop();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let events = common::events(&output.stdout);
+    let versions: Vec<_> = common::named(&events, "loaded")
+        .iter()
+        .map(|e| e["version"].clone())
+        .collect();
+    assert_eq!(versions, [1, 2], "{stdout}");
+}
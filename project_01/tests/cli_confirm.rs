@@ -0,0 +1,45 @@
+// Runs the real binary with --confirm: without a terminal on stdin nobody
+// could answer, so it refuses to start. The prompt itself is covered by
+// the unit tests of `confirm` and `watcher`.
+
+use std::fs;
+use std::process::{Command, Stdio};
+
+#[test]
+fn test_confirm_needs_a_terminal() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    fs::write(&file, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin("config_watcher"))
+        .args(["-f", file.to_str().unwrap(), "--confirm"])
+        .args(["--confirm-timeout", "5m", "--max-duration", "5s"])
+        .arg("--lock-dir")
+        .arg(dir.path())
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("needs an interactive terminal"), "{stderr}");
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_confirm_flags_depend_on_each_other() {
+    let run = |args: &[&str]| {
+        Command::new(assert_cmd::cargo::cargo_bin("config_watcher"))
+            .args(["-f", "config.json"])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    assert_eq!(run(&["--confirm-timeout", "5m"]).status.code(), Some(2));
+    assert_eq!(
+        run(&["--confirm", "--on-confirm-timeout", "apply"])
+            .status
+            .code(),
+        Some(2)
+    );
+    assert_eq!(run(&["--confirm", "--once"]).status.code(), Some(2));
+}
@@ -0,0 +1,144 @@
+// Runs the real binary with --daemonize: the command returns at once, the
+// daemon runs in its own session with its output in the log file, keeps
+// handling SIGHUP, and stops on SIGTERM. Startup failures still reach the
+// terminal.
+#![cfg(unix)]
+
+mod common;
+
+use common::{eventually, send};
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+
+const CONFIG: &str = r#"{ "app_name": "Daemon", "version": "1.0.0" }"#;
+
+fn daemonize(dir: &Path, args: &[&str]) -> Output {
+    let started = Instant::now();
+    let output = Command::new(assert_cmd::cargo::cargo_bin("config_watcher"))
+        .current_dir(dir)
+        .args(["-f", "config.json", "--interval", "1", "--no-lock"])
+        .arg("--daemonize")
+        .args(args)
+        .output()
+        .unwrap();
+    // Returns once the daemon is up, without waiting for it
+    assert!(started.elapsed() < Duration::from_secs(5), "{output:?}");
+    output
+}
+
+fn alive(pid: u32) -> bool {
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .unwrap()
+        .success()
+}
+
+#[test]
+fn test_daemon_runs_detached_until_sigterm() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.json"), CONFIG).unwrap();
+    let log = dir.path().join("watcher.log");
+    let pid_file = dir.path().join("watcher.pid");
+
+    let output = daemonize(
+        dir.path(),
+        &["--log-file", "watcher.log", "--pid-file", "watcher.pid"],
+    );
+    assert!(output.status.success(), "{output:?}");
+    // The pid file is in place before the command returns
+    let pid: u32 = fs::read_to_string(&pid_file)
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("pid {pid}")), "{stdout}");
+    // Piped: through the plain style
+    assert!(
+        stdout.starts_with("[OK] config-watcher running"),
+        "{stdout}"
+    );
+    assert!(alive(pid));
+
+    // Its own session, away from the terminal's, in /
+    #[cfg(target_os = "linux")]
+    {
+        let session = |pid: &str| {
+            let stat = fs::read_to_string(format!("/proc/{pid}/stat")).unwrap();
+            // After the ")" ending the command name: state, ppid, pgrp, session
+            let fields: Vec<_> = stat
+                .rsplit_once(')')
+                .unwrap()
+                .1
+                .split_whitespace()
+                .collect();
+            fields[3].to_string()
+        };
+        let daemon = session(&pid.to_string());
+        assert_ne!(daemon, session("self"));
+        assert_ne!(
+            daemon,
+            pid.to_string(),
+            "the daemon must not lead its session"
+        );
+        assert_eq!(
+            fs::read_link(format!("/proc/{pid}/cwd")).unwrap(),
+            Path::new("/")
+        );
+    }
+
+    // Output goes to the log file; SIGHUP still reloads
+    eventually("initial load logged", || {
+        fs::read_to_string(&log).is_ok_and(|text| text.contains("Initial configuration loaded"))
+    });
+    send("-HUP", pid);
+    eventually("SIGHUP reload logged", || {
+        fs::read_to_string(&log).is_ok_and(|text| text.contains("Reload requested by SIGHUP"))
+    });
+
+    send("-TERM", pid);
+    eventually("daemon stopped", || !alive(pid));
+    assert!(!pid_file.exists());
+    let text = fs::read_to_string(&log).unwrap();
+    assert!(text.contains("Shutting down"), "{text}");
+}
+
+#[test]
+fn test_startup_failures_reach_the_terminal() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.json"), CONFIG).unwrap();
+
+    // No log file to write to
+    let output = daemonize(dir.path(), &[]);
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--log-file"), "{stderr}");
+
+    // Failing in the daemon, after the forks: the pid file is held by this
+    // (live) test process
+    fs::write(
+        dir.path().join("watcher.pid"),
+        format!("{}\n", std::process::id()),
+    )
+    .unwrap();
+    let output = daemonize(
+        dir.path(),
+        &["--log-file", "watcher.log", "--pid-file", "watcher.pid"],
+    );
+    assert_eq!(output.status.code(), Some(9), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("still running"), "{stderr}");
+
+    // Not for `run`
+    let output = Command::new(assert_cmd::cargo::cargo_bin("config_watcher"))
+        .current_dir(dir.path())
+        .args(["run", "-f", "config.json", "--daemonize"])
+        .args(["--log-file", "watcher.log", "--", "true"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+}
@@ -0,0 +1,107 @@
+// Runs the real binary and sends it SIGUSR1: each signal prints one status
+// dump (redacted configuration, version, health, counters, settings) to the
+// normal output, JSON with --output json, and the watch goes on.
+#![cfg(unix)]
+
+mod common;
+
+use common::{config_watcher, eventually, read, send};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::path::Path;
+use std::process::{Child, Stdio};
+use std::time::Duration;
+
+const CONFIG: &str = r#"{
+  "app_name": "Dumped",
+  "version": "1.0.0",
+  "database": { "connection_string": "postgres://admin:hunter2@db/app" }
+}"#;
+
+fn watch(dir: &Path, args: &[&str]) -> Child {
+    config_watcher()
+        .current_dir(dir)
+        .args(["-f", "config.json", "--interval", "1", "--no-lock"])
+        .args(args)
+        .stdout(File::create(dir.join("out.log")).unwrap())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap()
+}
+
+fn output(dir: &Path) -> String {
+    read(dir, "out.log")
+}
+
+/// The `dump` events printed so far
+fn dumps(dir: &Path) -> Vec<Value> {
+    output(dir)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|event| event["event"] == "dump")
+        .collect()
+}
+
+#[test]
+fn test_one_json_dump_per_signal() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.json"), CONFIG).unwrap();
+    let mut child = watch(dir.path(), &["--output", "json"]);
+    eventually("initial load", || output(dir.path()).contains("\"loaded\""));
+
+    send("-USR1", child.id());
+    eventually("first dump", || dumps(dir.path()).len() == 1);
+    // Past the rate limit
+    std::thread::sleep(Duration::from_millis(1200));
+    send("-USR1", child.id());
+    eventually("second dump", || dumps(dir.path()).len() == 2);
+    std::thread::sleep(Duration::from_millis(500));
+
+    send("-TERM", child.id());
+    assert!(child.wait().unwrap().success());
+    let dumps = dumps(dir.path());
+    assert_eq!(dumps.len(), 2, "{}", output(dir.path()));
+    let dump = &dumps[0];
+    assert_eq!(dump["by"], "SIGUSR1");
+    assert_eq!(dump["status"]["health"], "healthy");
+    assert_eq!(dump["status"]["files"][0]["version"], 1);
+    let config = &dump["configs"]["config.json"];
+    assert_eq!(config["app_name"], "Dumped");
+    assert_eq!(config["database"]["connection_string"], "<redacted>");
+    assert_eq!(dump["settings"]["interval"]["value"], 1);
+    assert_eq!(dump["settings"]["interval"]["origin"], "command line");
+    // Still watching after the dumps
+    let output = output(dir.path());
+    assert!(output.contains("\"shutdown\""), "{output}");
+}
+
+#[test]
+fn test_text_dump_and_signal_flood() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.json"), CONFIG).unwrap();
+    let mut child = watch(dir.path(), &[]);
+    eventually("initial load", || {
+        output(dir.path()).contains("Initial configuration loaded")
+    });
+
+    for _ in 0..20 {
+        send("-USR1", child.id());
+    }
+    eventually("dump", || output(dir.path()).contains("Status dump"));
+    std::thread::sleep(Duration::from_millis(500));
+    send("-TERM", child.id());
+    assert!(child.wait().unwrap().success());
+
+    let output = output(dir.path());
+    assert_eq!(output.matches("Status dump").count(), 1, "{output}");
+    assert!(
+        output.contains("Status dump (requested by SIGUSR1): healthy"),
+        "{output}"
+    );
+    assert!(
+        output.contains("config.json: healthy, version 1"),
+        "{output}"
+    );
+    assert!(output.contains("interval = 1 (command line)"), "{output}");
+    assert!(!output.contains("hunter2"), "{output}");
+}
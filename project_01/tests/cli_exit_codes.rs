@@ -0,0 +1,106 @@
+// Pins the exit-code table of `src/exit.rs`: one case per category, through
+// the real binary.
+
+mod common;
+
+use assert_cmd::Command;
+use std::fs;
+
+fn status(args: &[&str]) -> Option<i32> {
+    Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(args)
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap()
+        .status
+        .code()
+}
+
+#[test]
+fn test_exit_code_per_category() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |name: &str, text: &str| {
+        let path = dir.path().join(name);
+        fs::write(&path, text).unwrap();
+        path.to_str().unwrap().to_string()
+    };
+    let valid = write("valid.json", r#"{ "app_name": "A", "version": "1.0.0" }"#);
+    let invalid = write("invalid.json", r#"{ "app_name": "", "version": "1.0.0" }"#);
+    let broken = write("broken.json", "{ not json");
+    let missing = dir.path().join("missing.json");
+    let missing = missing.to_str().unwrap();
+
+    assert_eq!(status(&["validate", "-f", &valid]), Some(0));
+    assert_eq!(status(&["-f", &valid, "--once"]), Some(0));
+
+    // Usage
+    assert_eq!(status(&["validate", "--no-such-flag"]), Some(2));
+    assert_eq!(
+        status(&["-f", &valid, "--once", "--override", "no_equals_sign"]),
+        Some(2)
+    );
+    assert_eq!(status(&["get", "-f", &valid, "server..port"]), Some(2));
+
+    // Input, parse, validation
+    assert_eq!(status(&["validate", "-f", missing]), Some(3));
+    assert_eq!(status(&["-f", missing, "--once"]), Some(3));
+    assert_eq!(status(&["validate", "-f", &broken]), Some(4));
+    assert_eq!(status(&["-f", &broken, "--once"]), Some(4));
+    assert_eq!(status(&["validate", "-f", &invalid]), Some(5));
+    assert_eq!(status(&["-f", &invalid, "--once"]), Some(5));
+    assert_eq!(
+        status(&["set", "-f", &valid, "environment", "moon"]),
+        Some(5)
+    );
+}
+
+#[test]
+fn test_fail_fast_exit_code() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+
+    let watch = common::Watch::spawn(common::config_watcher().args([
+        "-f",
+        config.to_str().unwrap(),
+        "--interval",
+        "1",
+        "--fail-fast",
+    ]));
+    watch.wait_for("Initial configuration loaded");
+    fs::write(&config, r#"{ "app_name": "", "version": "1.0.0" }"#).unwrap();
+    assert_eq!(watch.wait().status.code(), Some(6));
+}
+
+// A `git` that cannot be started (found, but not executable) is none of the
+// expected failures
+#[cfg(unix)]
+#[test]
+fn test_internal_error_exit_code() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+    let bin = dir.path().join("bin");
+    fs::create_dir(&bin).unwrap();
+    let git = bin.join("git");
+    fs::write(&git, "not a program").unwrap();
+    fs::set_permissions(&git, fs::Permissions::from_mode(0o600)).unwrap();
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args([
+            "diff",
+            "-f",
+            config.to_str().unwrap(),
+            "--against-git",
+            "HEAD",
+        ])
+        .env("PATH", &bin)
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(7), "{output:?}");
+}
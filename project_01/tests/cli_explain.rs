@@ -0,0 +1,73 @@
+// Exercises `config-watcher explain` through the real binary.
+
+use assert_cmd::Command;
+
+#[test]
+fn test_explain_field() {
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["explain", "database.pool_size"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("integer (u32)"));
+    assert!(stdout.contains("Default:     10"));
+}
+
+#[test]
+fn test_explain_unknown_path_suggests() {
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["explain", "server.prot"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Did you mean: server.port"), "{stderr}");
+}
+
+#[test]
+fn test_explain_source_reports_every_layer() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("app.json");
+    std::fs::write(
+        &file,
+        r#"{ "app_name": "A", "version": "1.0.0", "server": { "host": "h", "port": 8080, "max_connections": 100 } }"#,
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["explain-source", "server.max_connections", "-f"])
+        .arg(&file)
+        .args(["--override", "server.max_connections=300"])
+        .env("CW_OVERRIDE__SERVER__MAX_CONNECTIONS", "200")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines[0],
+        "server.max_connections = 300  (from command line)"
+    );
+    assert_eq!(lines[1], "  default: 1024");
+    assert_eq!(lines[2], format!("  file {}: 100", file.display()));
+    assert_eq!(
+        lines[3],
+        "  environment CW_OVERRIDE__SERVER__MAX_CONNECTIONS: 200"
+    );
+    assert_eq!(lines[4], "  command line: 300  <- wins");
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["explain-source", "server.tls", "-f"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
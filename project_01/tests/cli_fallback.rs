@@ -0,0 +1,133 @@
+// Runs the real binary with --fallback-config: the fallback stands in for a
+// watched file that cannot be loaded at startup, until that file is valid.
+
+mod common;
+
+use common::{config, events, named};
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn once(file: &Path, fallback: &Path) -> Output {
+    Command::new(assert_cmd::cargo::cargo_bin("config_watcher"))
+        .args(["-f", file.to_str().unwrap(), "--once", "--output", "json"])
+        .arg("--fallback-config")
+        .arg(fallback)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_valid_file_ignores_the_fallback() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    let fallback = dir.path().join("safe.json");
+    fs::write(&file, config("App", "1.0.0")).unwrap();
+    fs::write(&fallback, config("Safe", "1.0.0")).unwrap();
+
+    let output = once(&file, &fallback);
+    assert_eq!(output.status.code(), Some(0), "{output:?}");
+    let events = events(&output.stdout);
+    let loaded = named(&events, "loaded");
+    assert_eq!(loaded.len(), 1, "{events:?}");
+    assert_eq!(loaded[0]["summary"]["app_name"], "App");
+    assert!(loaded[0].get("fallback").is_none(), "{events:?}");
+}
+
+#[test]
+fn test_missing_or_invalid_file_loads_the_fallback() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    let fallback = dir.path().join("safe.json");
+    fs::write(&fallback, config("Safe", "1.0.0")).unwrap();
+
+    for contents in [None, Some("{ invalid json }")] {
+        if let Some(contents) = contents {
+            fs::write(&file, contents).unwrap();
+        }
+        let output = once(&file, &fallback);
+        assert_eq!(output.status.code(), Some(0), "{output:?}");
+        let events = events(&output.stdout);
+        assert_eq!(named(&events, "load_failed").len(), 1, "{events:?}");
+        let loaded = named(&events, "loaded");
+        assert_eq!(loaded.len(), 1, "{events:?}");
+        assert_eq!(loaded[0]["summary"]["app_name"], "Safe");
+        assert_eq!(loaded[0]["fallback"], fallback.to_str().unwrap());
+    }
+}
+
+#[test]
+fn test_both_invalid_fails_like_without_a_fallback() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    let fallback = dir.path().join("safe.json");
+    fs::write(&file, "{ invalid json }").unwrap();
+    fs::write(&fallback, r#"{ "app_name": "" }"#).unwrap();
+
+    let output = once(&file, &fallback);
+    assert_ne!(output.status.code(), Some(0), "{output:?}");
+    let events = events(&output.stdout);
+    assert!(named(&events, "loaded").is_empty(), "{events:?}");
+    let failures = named(&events, "load_failed");
+    assert_eq!(failures.len(), 2, "{events:?}");
+    let message = failures[1]["error"]["message"].as_str().unwrap();
+    assert!(message.contains("cannot be loaded either"), "{message}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_switches_over_once_the_file_is_valid() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    let fallback = dir.path().join("safe.json");
+    fs::write(&fallback, config("Safe", "1.0.0")).unwrap();
+
+    let watch = common::Watch::spawn(
+        common::config_watcher()
+            .args(["-f", file.to_str().unwrap(), "--interval", "1"])
+            .arg("--lock-dir")
+            .arg(dir.path())
+            .arg("--fallback-config")
+            .arg(&fallback)
+            .args(["--output", "json"]),
+    );
+    watch.wait_for_event("loaded", 1);
+    fs::write(&file, config("App", "1.0.0")).unwrap();
+    watch.wait_for_event("loaded", 2);
+
+    let output = watch.stop();
+    assert!(output.status.success(), "{output:?}");
+    let events = events(&output.stdout);
+    let loaded = named(&events, "loaded");
+    assert_eq!(loaded.len(), 2, "{events:?}");
+    assert_eq!(loaded[0]["summary"]["app_name"], "Safe");
+    assert_eq!(loaded[1]["summary"]["app_name"], "App");
+    assert_eq!(loaded[1]["version"], 2);
+    assert!(loaded[1].get("fallback").is_none(), "{events:?}");
+    let switched = named(&events, "switched_over");
+    assert_eq!(switched.len(), 1, "{events:?}");
+    assert_eq!(switched[0]["fallback"], fallback.to_str().unwrap());
+}
+
+#[test]
+fn test_fallback_needs_a_single_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = (dir.path().join("a.json"), dir.path().join("b.json"));
+    fs::write(&a, config("A", "1.0.0")).unwrap();
+    fs::write(&b, config("B", "1.0.0")).unwrap();
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin("config_watcher"))
+        .args([
+            "-f",
+            a.to_str().unwrap(),
+            "-f",
+            b.to_str().unwrap(),
+            "--once",
+        ])
+        .args(["--fallback-config", a.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("needs a single watched file"), "{stderr}");
+}
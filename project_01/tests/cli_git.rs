@@ -0,0 +1,195 @@
+// Runs the real binary against configuration files in temporary git
+// repositories: --git-autocommit and diff --against-git.
+
+mod common;
+
+use common::Watch;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+/// `git` in `dir`, without the user's global configuration
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {args:?}: {output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn watch(args: &[&str]) -> Output {
+    common::config_watcher()
+        .args(args)
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .stdout(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+/// A repository holding a committed config.json
+fn repository(dir: &Path) -> std::path::PathBuf {
+    git(dir, &["init", "-q"]);
+    git(dir, &["config", "user.name", "Test"]);
+    git(dir, &["config", "user.email", "test@example.com"]);
+    let config = dir.join("config.json");
+    fs::write(
+        &config,
+        r#"{ "app_name": "TestApp", "version": "1.2.0", "server": { "host": "localhost", "port": 8080 } }"#,
+    )
+    .unwrap();
+    git(dir, &["add", "config.json"]);
+    git(dir, &["commit", "-q", "-m", "initial"]);
+    config
+}
+
+#[cfg(unix)]
+#[test]
+fn test_autocommits_valid_reloads_and_never_failed_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = repository(dir.path());
+
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .arg("--git-autocommit")
+            .env("GIT_CONFIG_GLOBAL", "/dev/null"),
+    );
+    watch.wait_for("Initial configuration loaded");
+    fs::write(
+        &config,
+        r#"{ "app_name": "TestApp", "version": "1.3.0", "server": { "host": "localhost", "port": 9090 } }"#,
+    )
+    .unwrap();
+    watch.until("the autocommit", |_| {
+        git(dir.path(), &["log", "--format=%s"]).lines().count() == 2
+    });
+    fs::write(&config, "{ not json").unwrap();
+    watch.wait_for("Configuration reload failed");
+    assert!(watch.stop().status.success());
+
+    assert_eq!(
+        git(dir.path(), &["log", "--format=%s"]),
+        "config-watcher: TestApp 1.2.0→1.3.0; changed server.port\ninitial\n"
+    );
+    assert!(git(dir.path(), &["show", "HEAD:config.json"]).contains("9090"));
+}
+
+#[test]
+fn test_autocommit_outside_a_work_tree_is_a_usage_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    let output = watch(&["-f", config.to_str().unwrap(), "--git-autocommit"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("not inside a git work tree"), "{stderr}");
+}
+
+/// `config-watcher diff` with `args`, stdout captured
+fn diff(args: &[&str]) -> Output {
+    common::config_watcher()
+        .arg("diff")
+        .args(args)
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_diff_against_git_lists_the_changed_fields() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = repository(dir.path());
+    let file = config.to_str().unwrap();
+    fs::write(
+        &config,
+        r#"{ "app_name": "TestApp", "version": "1.2.0", "server": { "host": "localhost", "port": 9090 } }"#,
+    )
+    .unwrap();
+
+    let output = diff(&["-f", file, "--against-git", "HEAD"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("--- HEAD:"), "{stdout}");
+    assert!(stdout.contains("server.port"), "{stdout}");
+
+    let output = diff(&["-f", file, "--against-git", "HEAD", "--output", "json"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["identical"], false);
+    assert_eq!(json["diff"][0]["path"], "server.port");
+    assert_eq!(json["diff"][0]["old"], 8080);
+    assert_eq!(json["diff"][0]["new"], 9090);
+}
+
+#[test]
+fn test_diff_against_git_ignores_formatting() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = repository(dir.path());
+    // Same values, keys reordered and reindented
+    fs::write(
+        &config,
+        "{\n  \"server\": {\n    \"port\": 8080,\n    \"host\": \"localhost\"\n  },\n  \"version\": \"1.2.0\",\n  \"app_name\": \"TestApp\"\n}\n",
+    )
+    .unwrap();
+    let output = diff(&["-f", config.to_str().unwrap(), "--against-git", "HEAD"]);
+    assert_eq!(output.status.code(), Some(0), "{output:?}");
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_diff_against_git_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = repository(dir.path());
+    let stderr = |output: &Output| String::from_utf8_lossy(&output.stderr).into_owned();
+
+    let output = diff(&["-f", config.to_str().unwrap(), "--against-git", "nope"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("unknown revision 'nope'"));
+
+    let untracked = dir.path().join("untracked.json");
+    fs::write(
+        &untracked,
+        r#"{ "app_name": "TestApp", "version": "1.0.0" }"#,
+    )
+    .unwrap();
+    let output = diff(&["-f", untracked.to_str().unwrap(), "--against-git", "HEAD"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).contains("is not under version control"));
+
+    git(dir.path(), &["add", "untracked.json"]);
+    git(dir.path(), &["commit", "-q", "-m", "second"]);
+    let output = diff(&["-f", untracked.to_str().unwrap(), "--against-git", "HEAD~1"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).contains("untracked.json does not exist at revision 'HEAD~1'"));
+
+    let outside = tempfile::tempdir().unwrap();
+    let file = outside.path().join("config.json");
+    fs::write(&file, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    let output = diff(&["-f", file.to_str().unwrap(), "--against-git", "HEAD"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).contains("not inside a git work tree"));
+}
+
+#[test]
+fn test_diff_against_another_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let old = dir.path().join("old.json");
+    let new = dir.path().join("new.json");
+    fs::write(&old, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    fs::write(&new, r#"{"version":"1.0.0","app_name":"TestApp"}"#).unwrap();
+    let args = [
+        "-f",
+        new.to_str().unwrap(),
+        "--against",
+        old.to_str().unwrap(),
+    ];
+    assert_eq!(diff(&args).status.code(), Some(0));
+
+    fs::write(&new, r#"{ "app_name": "TestApp", "version": "1.1.0" }"#).unwrap();
+    let output = diff(&args);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("version"));
+}
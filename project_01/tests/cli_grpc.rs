@@ -0,0 +1,151 @@
+// Calls GetConfig and WatchConfig on --grpc-addr of the real binary, across
+// two reloads and the shutdown, with the client generated from the proto.
+#![cfg(all(feature = "grpc", unix))]
+
+use config_watcher::grpc::proto::config::Payload;
+use config_watcher::grpc::proto::config_watcher_client::ConfigWatcherClient;
+use config_watcher::grpc::proto::{Config, GetConfigRequest, WatchConfigRequest};
+mod common;
+
+use common::{Watch, config_watcher, free_port};
+use serde_json::Value;
+use std::fs;
+use std::process::Output;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tonic::Code;
+use tonic::transport::Channel;
+
+/// A client of the server on `port`, once it shows up
+async fn connect(port: u16) -> ConfigWatcherClient<Channel> {
+    for _ in 0..50 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        if let Ok(client) = ConfigWatcherClient::connect(format!("http://127.0.0.1:{port}")).await {
+            return client;
+        }
+    }
+    panic!("gRPC server never showed up");
+}
+
+/// Stops the watcher without blocking the runtime
+fn stop(watch: Watch) -> JoinHandle<Output> {
+    tokio::task::spawn_blocking(move || watch.stop())
+}
+
+fn json(config: &Config) -> Value {
+    match config.payload {
+        Some(Payload::Config(ref json) | Payload::Patch(ref json)) => {
+            serde_json::from_slice(json).unwrap()
+        }
+        None => panic!("no payload in {config:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_get_and_watch_config_across_two_reloads() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let write = |version: &str| {
+        fs::write(
+            &config,
+            format!(
+                r#"{{ "app_name": "TestApp", "version": "{version}", "database": {{ "connection_string": "postgres://u:s3cret@db" }} }}"#
+            ),
+        )
+        .unwrap()
+    };
+    write("1.0.0");
+    let port = free_port();
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--grpc-addr", &format!("127.0.0.1:{port}")]),
+    );
+    // Served from the start, with something to serve only after the
+    // initial load
+    watch.wait_for("Initial configuration loaded");
+    let mut client = connect(port).await;
+
+    let got = client
+        .get_config(GetConfigRequest::default())
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(got.file, config.to_str().unwrap());
+    assert_eq!(got.version, 1);
+    let doc = json(&got);
+    assert_eq!(doc["version"], "1.0.0");
+    assert_eq!(doc["database"]["connection_string"], "<redacted>");
+
+    // Whole documents on one stream, patches on the other: both start with
+    // the current one
+    let mut whole = client
+        .watch_config(WatchConfigRequest::default())
+        .await
+        .unwrap()
+        .into_inner();
+    let mut patches = client
+        .watch_config(WatchConfigRequest {
+            file: config.to_str().unwrap().to_string(),
+            patches: true,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    for stream in [&mut whole, &mut patches] {
+        let initial = stream.message().await.unwrap().unwrap();
+        assert_eq!(initial.version, 1);
+        assert_eq!(json(&initial)["version"], "1.0.0");
+    }
+
+    for (version, expected) in [("2.0.0", 2), ("3.0.0", 3)] {
+        write(version);
+        let update = whole.message().await.unwrap().unwrap();
+        assert_eq!(update.version, expected);
+        assert!(matches!(update.payload, Some(Payload::Config(_))));
+        assert_eq!(json(&update)["version"], version);
+        let update = patches.message().await.unwrap().unwrap();
+        assert_eq!(update.version, expected);
+        assert!(matches!(update.payload, Some(Payload::Patch(_))));
+        assert_eq!(
+            json(&update),
+            serde_json::json!([{ "op": "replace", "path": "/version", "value": version }])
+        );
+    }
+
+    let got = client
+        .get_config(GetConfigRequest::default())
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(got.version, 3);
+
+    // Both streams end with UNAVAILABLE when the watch does
+    let stopped = stop(watch);
+    for stream in [&mut whole, &mut patches] {
+        let status = stream.message().await.unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable, "{status:?}");
+    }
+    assert!(stopped.await.unwrap().status.success());
+}
+
+#[tokio::test]
+async fn test_unknown_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    let port = free_port();
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--grpc-addr", &format!("127.0.0.1:{port}")]),
+    );
+    let mut client = connect(port).await;
+
+    let request = GetConfigRequest {
+        file: "other.json".to_string(),
+    };
+    let status = client.get_config(request).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+    assert!(stop(watch).await.unwrap().status.success());
+}
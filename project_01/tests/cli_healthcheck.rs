@@ -0,0 +1,151 @@
+// Exercises `config-watcher healthcheck` against hand-written and real status files.
+
+mod common;
+
+use assert_cmd::Command;
+use common::{Watch, read};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+fn write_status(path: &Path, age: Duration, health: &str) {
+    let updated = humantime::format_rfc3339_millis(SystemTime::now() - age);
+    fs::write(
+        path,
+        format!(r#"{{"updated_at":"{updated}","interval_secs":2,"health":"{health}"}}"#),
+    )
+    .unwrap();
+}
+
+fn healthcheck(status: &Path, extra: &[&str]) -> (Option<i32>, String) {
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["healthcheck", "--status-file", status.to_str().unwrap()])
+        .args(extra)
+        .timeout(Duration::from_secs(10))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    (output.status.code(), stdout)
+}
+
+#[test]
+fn test_fresh_healthy_and_degraded() {
+    let dir = tempfile::tempdir().unwrap();
+    let status = dir.path().join("status.json");
+
+    write_status(&status, Duration::ZERO, "healthy");
+    let (code, stdout) = healthcheck(&status, &[]);
+    assert_eq!(code, Some(0), "{stdout}");
+    assert!(stdout.starts_with("OK: healthy"), "{stdout}");
+
+    write_status(&status, Duration::ZERO, "degraded");
+    assert_eq!(healthcheck(&status, &[]).0, Some(0));
+    let (code, stdout) = healthcheck(&status, &["--fail-on-degraded"]);
+    assert_eq!(code, Some(1));
+    assert!(stdout.starts_with("FAIL: degraded"), "{stdout}");
+
+    write_status(&status, Duration::ZERO, "unhealthy");
+    assert_eq!(healthcheck(&status, &[]).0, Some(1));
+}
+
+#[test]
+fn test_stale_and_missing() {
+    let dir = tempfile::tempdir().unwrap();
+    let status = dir.path().join("status.json");
+
+    // 3 intervals of 2s by default
+    write_status(&status, Duration::from_secs(10), "healthy");
+    let (code, stdout) = healthcheck(&status, &[]);
+    assert_eq!(code, Some(1));
+    assert!(stdout.starts_with("FAIL: stale"), "{stdout}");
+    assert_eq!(healthcheck(&status, &["--stale-after", "10"]).0, Some(0));
+    assert_eq!(healthcheck(&status, &["--max-age", "1m"]).0, Some(0));
+
+    let (code, stdout) = healthcheck(&dir.path().join("nope.json"), &[]);
+    assert_eq!(code, Some(1));
+    assert!(stdout.contains("cannot read"), "{stdout}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_running_watcher_records_failures_and_cleans_up() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let status = dir.path().join("status.json");
+    fs::write(&config, r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--status-file", status.to_str().unwrap()]),
+    );
+    let record =
+        || -> Value { serde_json::from_str(&read(dir.path(), "status.json")).unwrap_or_default() };
+    watch.until("healthy", |_| record()["health"] == "healthy");
+    let (healthy, check) = (record(), healthcheck(&status, &[]));
+    fs::write(&config, "{ invalid json }").unwrap();
+    watch.until("degraded", |_| record()["health"] == "degraded");
+    let (degraded, recheck) = (record(), healthcheck(&status, &[]));
+    assert!(watch.stop().status.success());
+
+    assert_eq!(check.0, Some(0), "{}", check.1);
+    assert_eq!(healthy["health"], "healthy");
+    assert!(healthy["pid"].as_u64().unwrap() > 0);
+    let file = &healthy["files"][0];
+    assert_eq!(file["path"], config.to_str().unwrap());
+    assert_eq!(file["version"], 1);
+    assert!(file["last_success_at"].is_string(), "{file}");
+    assert!(file["last_error"].is_null(), "{file}");
+
+    // Still serving version 1, so degraded rather than unhealthy
+    assert_eq!(recheck.0, Some(0), "{}", recheck.1);
+    assert_eq!(degraded["health"], "degraded");
+    let file = &degraded["files"][0];
+    assert_eq!(file["version"], 1);
+    assert!(file["last_failure_at"].is_string(), "{file}");
+    assert_eq!(file["last_error"]["code"], "invalid_json");
+
+    // Removed on a clean exit
+    assert!(!status.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_status_file_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let status = dir.path().join("status.json");
+    fs::write(&config, r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+
+    // Fail-fast leaves the file behind, which lets us look at it
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--fail-fast"])
+            .args(["--status-file", status.to_str().unwrap()])
+            .args(["--interval", "1", "--status-file-mode", "600"]),
+    );
+    watch.wait_for("Initial configuration loaded");
+    fs::write(&config, "{ invalid json }").unwrap();
+    assert!(!watch.wait().status.success());
+
+    let mode = fs::metadata(&status).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    let record: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&status).unwrap()).unwrap();
+    assert_eq!(record["health"], "degraded");
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["-f", config.to_str().unwrap(), "--fail-fast"])
+        .args(["--status-file", status.to_str().unwrap()])
+        .args(["--status-file-mode", "9"])
+        .timeout(Duration::from_secs(10))
+        .assert()
+        .code(2);
+    let stderr = String::from_utf8_lossy(&output.get_output().stderr);
+    assert!(stderr.contains("octal"), "{stderr}");
+}
@@ -0,0 +1,362 @@
+// Queries the --http-addr endpoints of the real binary while its file is
+// broken, fixed and edited.
+#![cfg(feature = "http-server")]
+
+mod common;
+
+use common::{Watch, free_port};
+use serde_json::Value;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::{self, Message, WebSocket, stream::MaybeTlsStream};
+
+/// Status code and JSON body of `GET path`
+fn get(port: u16, path: &str) -> (u16, Value) {
+    send(
+        port,
+        &format!("GET {path} HTTP/1.1\r\nHost: test\r\nConnection: close\r\n\r\n"),
+    )
+}
+
+fn put(port: u16, token: &str, patch: &str) -> (u16, Value) {
+    send(
+        port,
+        &format!(
+            "PUT /config HTTP/1.1\r\nHost: test\r\nConnection: close\r\n\
+             Authorization: Bearer {token}\r\n\
+             Content-Length: {}\r\n\r\n{patch}",
+            patch.len()
+        ),
+    )
+}
+
+fn send(port: u16, request: &str) -> (u16, Value) {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response[9..12].parse().unwrap();
+    let body = response.split_once("\r\n\r\n").unwrap().1;
+    (status, serde_json::from_str(body).unwrap())
+}
+
+/// A WebSocket client of `ws://127.0.0.1:{port}{path}`, once the server
+/// shows up
+fn websocket(port: u16, path: &str) -> WebSocket<MaybeTlsStream<TcpStream>> {
+    let (socket, response) = (0..50)
+        .find_map(|_| {
+            std::thread::sleep(Duration::from_millis(100));
+            tungstenite::connect(format!("ws://127.0.0.1:{port}{path}")).ok()
+        })
+        .expect("server never showed up");
+    assert_eq!(response.status(), 101);
+    if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+        stream
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+    }
+    socket
+}
+
+/// The next text message from the server, as JSON
+fn message(socket: &mut WebSocket<MaybeTlsStream<TcpStream>>) -> Value {
+    loop {
+        match socket.read().unwrap() {
+            Message::Text(text) => return serde_json::from_str(&text).unwrap(),
+            Message::Close(frame) => panic!("closed early: {frame:?}"),
+            _ => {}
+        }
+    }
+}
+
+/// The JSON messages up to the close frame, and its status code
+fn messages(socket: &mut WebSocket<MaybeTlsStream<TcpStream>>) -> (Vec<Value>, u16) {
+    let mut messages = Vec::new();
+    loop {
+        match socket.read().unwrap() {
+            Message::Text(text) => messages.push(serde_json::from_str(&text).unwrap()),
+            Message::Close(frame) => return (messages, frame.unwrap().code.into()),
+            _ => {}
+        }
+    }
+}
+
+/// A watch of `config` with `flags`, once its initial load is done
+fn serve(config: &std::path::Path, flags: &[&str]) -> Watch {
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(flags),
+    );
+    // Both messages come after the servers are up
+    watch.until("initial load", |watch| {
+        let out = watch.stdout() + &watch.stderr();
+        out.contains("Initial configuration loaded") || out.contains("Failed to load initial")
+    });
+    watch
+}
+
+#[cfg(unix)]
+#[test]
+fn test_config_and_status_before_and_after_a_reload() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, "{ invalid json }").unwrap();
+    let port = free_port();
+
+    let watch = serve(&config, &["--http-addr", &format!("127.0.0.1:{port}")]);
+    let (config_waiting, status_waiting) = (get(port, "/config"), get(port, "/status"));
+    fs::write(
+        &config,
+        r#"{ "app_name": "TestApp", "version": "1.0.0", "database": { "connection_string": "postgres://u:s3cret@db" } }"#,
+    )
+    .unwrap();
+    watch.until("load", |_| get(port, "/config").0 == 200);
+    let loaded = get(port, "/config");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "2.0.0" }"#).unwrap();
+    watch.until("reload", |_| get(port, "/config").1["version"] == "2.0.0");
+    let (reloaded, status) = (get(port, "/config"), get(port, "/status"));
+    let output = watch.stop();
+    assert!(output.status.success(), "{output:?}");
+
+    // No valid configuration yet
+    assert_eq!(config_waiting.0, 503);
+    assert_eq!(status_waiting.1["health"], "unhealthy");
+    assert!(status_waiting.1["files"][0]["failures"].as_u64().unwrap() >= 1);
+
+    assert_eq!(loaded.0, 200);
+    assert_eq!(loaded.1["version"], "1.0.0");
+    assert_eq!(loaded.1["database"]["connection_string"], "<redacted>");
+    assert!(!loaded.1.to_string().contains("s3cret"));
+
+    assert_eq!(reloaded.1["version"], "2.0.0");
+    let file = &status.1["files"][0];
+    assert_eq!(status.1["health"], "healthy");
+    assert_eq!(file["version"], 2);
+    // The initial load failed: both valid loads came from the watch loop
+    assert_eq!(file["reloads"], 2);
+    assert!(file["last_success_at"].is_string());
+    assert_eq!(file["last_error"]["code"], "invalid_json");
+
+    // The server stopped with the watcher
+    assert!(TcpStream::connect(("127.0.0.1", port)).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_put_config_is_reloaded_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    let port = free_port();
+
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--http-addr", &format!("127.0.0.1:{port}")])
+            .args(["--http-allow-write", "--http-token", "s3cret"])
+            .args(["--output", "json"]),
+    );
+    watch.wait_for_event("loaded", 1);
+    let rejected = put(port, "s3cret", r#"{ "app_name": "" }"#);
+    let accepted = put(port, "s3cret", r#"{ "version": "2.0.0" }"#);
+    watch.wait_for_event("loaded", 2);
+    let served = get(port, "/config");
+    let output = watch.stop();
+    assert!(output.status.success(), "{output:?}");
+
+    assert_eq!(rejected.0, 422);
+    assert_eq!(rejected.1["error"]["findings"][0]["path"], "app_name");
+    assert_eq!(accepted.0, 200);
+    assert_eq!(served.1["version"], "2.0.0");
+
+    // The write is an edit like any other: one reload, no rejection
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let events = common::events(&output.stdout);
+    let loaded = common::named(&events, "loaded");
+    assert_eq!(loaded.len(), 2, "{stdout}");
+    assert_eq!(loaded[1]["version"], 2);
+    assert!(
+        !events.iter().any(|e| e["event"] == "load_failed"),
+        "{stdout}"
+    );
+}
+
+#[test]
+#[cfg(all(unix, feature = "age"))]
+fn test_put_config_keeps_encrypted_values_encrypted() {
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/age");
+    let values: Value =
+        serde_json::from_str(&fs::read_to_string(fixtures.join("values.json")).unwrap()).unwrap();
+    let encrypted = values["connection_string"].as_str().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(
+        &config,
+        format!(
+            r#"{{ "app_name": "TestApp", "version": "1.0.0",
+                 "database": {{ "connection_string": "{encrypted}" }} }}"#
+        ),
+    )
+    .unwrap();
+    let port = free_port();
+
+    let identity = fixtures.join("identity.txt");
+    let watch = serve(
+        &config,
+        &[
+            "--age-identity",
+            identity.to_str().unwrap(),
+            "--http-addr",
+            &format!("127.0.0.1:{port}"),
+            "--http-allow-write",
+            "--http-token",
+            "s3cret",
+        ],
+    );
+    let exposed = put(
+        port,
+        "s3cret",
+        r#"{ "database": { "connection_string": "postgres://app:plain@db/app" } }"#,
+    );
+    let untouched = put(port, "s3cret", r#"{ "version": "2.0.0" }"#);
+    let output = watch.stop();
+    assert!(output.status.success(), "{output:?}");
+
+    assert_eq!(exposed.0, 422, "{:?}", exposed.1);
+    assert_eq!(exposed.1["error"]["code"], "validation_failed");
+    let finding = &exposed.1["error"]["findings"][0];
+    assert_eq!(finding["path"], "database.connection_string");
+    assert!(
+        finding["message"].as_str().unwrap().contains("age:"),
+        "{finding}"
+    );
+
+    // A patch that leaves the secret alone is written, the secret still
+    // encrypted
+    assert_eq!(untouched.0, 200, "{:?}", untouched.1);
+    let written: Value = serde_json::from_str(&fs::read_to_string(&config).unwrap()).unwrap();
+    assert_eq!(written["version"], "2.0.0");
+    assert_eq!(written["database"]["connection_string"], encrypted);
+}
+
+#[test]
+fn test_allow_write_needs_a_token_and_one_file() {
+    let run = |args: &[&str]| {
+        let output = common::config_watcher()
+            .args(["--http-addr", "127.0.0.1:0", "--http-allow-write"])
+            .args(args)
+            .env_remove("CONFIG_WATCHER_HTTP_TOKEN")
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(2), "{output:?}");
+        String::from_utf8_lossy(&output.stderr).to_string()
+    };
+    let stderr = run(&["-f", "a.json"]);
+    assert!(stderr.contains("--http-token"), "{stderr}");
+    let stderr = run(&["-f", "a.json", "-f", "b.json", "--http-token", "t"]);
+    assert!(stderr.contains("needs a single watched file"), "{stderr}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_probes_on_the_health_address() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, "{ invalid json }").unwrap();
+    let port = free_port();
+
+    let watch = serve(&config, &["--health-addr", &format!("127.0.0.1:{port}")]);
+    let (livez, readyz, config_endpoint) = (
+        get(port, "/livez"),
+        get(port, "/readyz"),
+        get(port, "/config"),
+    );
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    watch.until("ready", |_| get(port, "/readyz").0 == 200);
+    let loaded = get(port, "/readyz");
+    fs::write(&config, "{ broken again }").unwrap();
+    watch.wait_for("Configuration reload failed");
+    let degraded = get(port, "/readyz");
+    let output = watch.stop();
+    assert!(output.status.success(), "{output:?}");
+
+    // Alive while waiting for a valid file, but not ready
+    assert_eq!(livez.0, 200);
+    assert_eq!(readyz.0, 503);
+    assert_eq!(
+        readyz.1["files"][0]["reason"],
+        "no valid configuration loaded yet"
+    );
+    // The configuration is not exposed on the health address
+    assert_eq!(config_endpoint.0, 404);
+
+    assert_eq!(loaded, (200, serde_json::json!({ "status": "ready" })));
+    // Broken again, but version 1 is still served
+    assert_eq!(degraded.0, 200);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_events_over_websocket_across_a_reload_and_a_failure() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(
+        &config,
+        r#"{ "app_name": "TestApp", "version": "1.0.0", "database": { "connection_string": "postgres://u:s3cret@db" } }"#,
+    )
+    .unwrap();
+    let port = free_port();
+
+    let watch = serve(&config, &["--http-addr", &format!("127.0.0.1:{port}")]);
+    let mut all = websocket(port, "/ws");
+    let mut failures = websocket(port, "/ws?events=failures");
+    let current = message(&mut all);
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "2.0.0" }"#).unwrap();
+    watch.wait_for("Configuration has been updated");
+    fs::write(&config, "{ invalid json }").unwrap();
+    watch.wait_for("Configuration reload failed");
+    let output = watch.stop();
+    assert!(output.status.success(), "{output:?}");
+    let ((all, all_close), (failures, failures_close)) =
+        (messages(&mut all), messages(&mut failures));
+
+    // The current configuration first, redacted
+    assert_eq!(current["event"], "current");
+    assert_eq!(current["file"], config.to_str().unwrap());
+    assert_eq!(current["config"]["version"], "1.0.0");
+    assert_eq!(
+        current["config"]["database"]["connection_string"],
+        "<redacted>"
+    );
+
+    // Then the reload with its diff, the failure (once per retry) and the
+    // shutdown
+    let mut names: Vec<&str> = all.iter().filter_map(|m| m["event"].as_str()).collect();
+    names.dedup();
+    assert_eq!(names, ["loaded", "load_failed", "shutdown"], "{all:?}");
+    assert_eq!(all[0]["version"], 2);
+    assert!(
+        all[0]["diff"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|change| change["path"] == "version"),
+        "{}",
+        all[0]
+    );
+    assert_eq!(all[1]["error"]["code"], "invalid_json");
+
+    // Failures only, no current configuration
+    let mut names: Vec<&str> = failures
+        .iter()
+        .filter_map(|m| m["event"].as_str())
+        .collect();
+    names.dedup();
+    assert_eq!(names, ["load_failed"]);
+
+    // Both closed as going away
+    assert_eq!((all_close, failures_close), (1001, 1001));
+}
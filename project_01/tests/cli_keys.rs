@@ -0,0 +1,76 @@
+// Runs the real binary with keys on a piped stdin: the single-key commands
+// are only read from a terminal, so they are ignored and the watch goes on
+// until SIGTERM. Without a terminal --tui falls back to the usual output.
+#![cfg(unix)]
+
+mod common;
+
+use common::{config_watcher, eventually, read, send};
+use std::fs::{self, File};
+use std::io::Write;
+use std::process::Stdio;
+use std::time::Duration;
+
+const CONFIG: &str = r#"{ "app_name": "Keyed", "version": "1.0.0" }"#;
+
+#[test]
+fn test_keys_on_a_pipe_are_ignored() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    fs::write(dir.join("config.json"), CONFIG).unwrap();
+    let mut child = config_watcher()
+        .current_dir(dir)
+        .args(["-f", "config.json", "--interval", "1", "--no-lock"])
+        .stdin(Stdio::piped())
+        .stdout(File::create(dir.join("out.log")).unwrap())
+        .stderr(File::create(dir.join("err.log")).unwrap())
+        .spawn()
+        .unwrap();
+    eventually("initial load", || {
+        read(dir, "out.log").contains("Initial configuration loaded")
+    });
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"rdpq?").unwrap();
+    drop(stdin);
+    std::thread::sleep(Duration::from_millis(1500));
+    assert_eq!(child.try_wait().unwrap(), None, "stopped by a piped q");
+
+    send("-TERM", child.id());
+    assert!(child.wait().unwrap().success());
+    let output = read(dir, "out.log") + &read(dir, "err.log");
+    for line in [
+        "by keyboard",
+        "Status dump",
+        "paused",
+        "Press ? for the keys",
+    ] {
+        assert!(!output.contains(line), "{line}: {output}");
+    }
+}
+
+#[test]
+fn test_tui_without_a_terminal_prints_the_usual_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    fs::write(dir.join("config.json"), CONFIG).unwrap();
+    let mut child = config_watcher()
+        .current_dir(dir)
+        .args(["-f", "config.json", "--interval", "1", "--no-lock", "--tui"])
+        .stdin(Stdio::null())
+        .stdout(File::create(dir.join("out.log")).unwrap())
+        .stderr(File::create(dir.join("err.log")).unwrap())
+        .spawn()
+        .unwrap();
+    eventually("initial load", || {
+        read(dir, "out.log").contains("Initial configuration loaded")
+    });
+    send("-TERM", child.id());
+    assert!(child.wait().unwrap().success());
+    assert!(
+        read(dir, "err.log").contains("--tui needs a terminal"),
+        "{}",
+        read(dir, "err.log")
+    );
+    assert!(!read(dir, "out.log").contains("\x1b[?1049h"));
+}
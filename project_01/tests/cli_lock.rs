@@ -0,0 +1,82 @@
+// Runs two instances of the real binary against the same file: the second
+// one is refused while the first holds the lock.
+#![cfg(unix)]
+
+mod common;
+
+use common::{Watch, config_watcher, eventually};
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+
+fn watch(file: &Path, lock_dir: &Path) -> Command {
+    let mut command = config_watcher();
+    command
+        .args(["-f", file.to_str().unwrap(), "--interval", "1"])
+        .arg("--lock-dir")
+        .arg(lock_dir);
+    command
+}
+
+/// Waits until `pid` is written in the only lock file of `lock_dir`
+fn wait_for_lock(pid: u32, lock_dir: &Path) {
+    eventually("the first instance taking the lock", || {
+        fs::read_dir(lock_dir)
+            .unwrap()
+            .filter_map(|entry| fs::read_to_string(entry.unwrap().path()).ok())
+            .any(|locked| locked.trim() == pid.to_string())
+    });
+}
+
+/// Runs `command` until its initial load, then stops it
+fn watch_once(command: &mut Command) -> Output {
+    let watch = Watch::spawn(command);
+    watch.wait_for("Initial configuration loaded");
+    watch.stop()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_second_instance_fails_fast_then_takes_over() {
+    let dir = tempfile::tempdir().unwrap();
+    let locks = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    fs::write(&file, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+
+    let first = Watch::spawn(&mut watch(&file, locks.path()));
+    wait_for_lock(first.id(), locks.path());
+
+    // Refused right away, naming the first one
+    let started = Instant::now();
+    let second = Watch::spawn(&mut watch(&file, locks.path())).wait();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(second.status.code(), Some(9), "{second:?}");
+    let message = stderr(&second);
+    assert!(message.contains("already watched"), "{message}");
+    assert!(
+        message.contains(&format!("pid {}", first.id())),
+        "{message}"
+    );
+
+    // Through another path to the same file, too
+    let dotted = dir.path().join(".").join("config.json");
+    let third = Watch::spawn(&mut watch(&dotted, locks.path())).wait();
+    assert_eq!(third.status.code(), Some(9));
+
+    // --no-lock watches regardless
+    let unlocked = watch_once(
+        config_watcher()
+            .args(["-f", file.to_str().unwrap(), "--interval", "1"])
+            .arg("--no-lock"),
+    );
+    assert_eq!(unlocked.status.code(), Some(0), "{unlocked:?}");
+
+    // Once the first one is gone, the lock is free again
+    assert!(first.stop().status.success());
+    let after = watch_once(&mut watch(&file, locks.path()));
+    assert_eq!(after.status.code(), Some(0), "{after:?}");
+}
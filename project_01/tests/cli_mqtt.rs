@@ -0,0 +1,140 @@
+// Runs the real binary against a minimal in-process MQTT broker and checks
+// what it publishes across a reload and a failed reload.
+#![cfg(feature = "mqtt")]
+
+mod common;
+
+use common::Watch;
+use serde_json::Value;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// One PUBLISH as the broker saw it
+#[derive(Debug)]
+struct Published {
+    topic: String,
+    retain: bool,
+    qos: u8,
+    payload: Value,
+}
+
+/// Reads one packet: fixed header byte and body
+fn packet(stream: &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+    let mut header = [0; 1];
+    stream.read_exact(&mut header).ok()?;
+    let mut len = 0;
+    for shift in (0..4).map(|i| 7 * i) {
+        let mut byte = [0; 1];
+        stream.read_exact(&mut byte).ok()?;
+        len |= usize::from(byte[0] & 0x7F) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut body = vec![0; len];
+    stream.read_exact(&mut body).ok()?;
+    Some((header[0], body))
+}
+
+/// Accepts one client and reports its PUBLISH packets, acknowledged,
+/// until it disconnects
+fn broker(listener: TcpListener, published: mpsc::Sender<Published>) {
+    let (mut stream, _) = listener.accept().unwrap();
+    let (header, _) = packet(&mut stream).unwrap();
+    assert_eq!(header, 0x10, "CONNECT first");
+    stream.write_all(&[0x20, 2, 0, 0]).unwrap();
+    while let Some((header, body)) = packet(&mut stream) {
+        if header >> 4 != 3 {
+            continue;
+        }
+        let len = u16::from_be_bytes([body[0], body[1]]) as usize;
+        let topic = String::from_utf8(body[2..2 + len].to_vec()).unwrap();
+        let qos = (header >> 1) & 0x3;
+        let mut rest = &body[2 + len..];
+        if qos == 1 {
+            stream.write_all(&[0x40, 2, rest[0], rest[1]]).unwrap();
+            rest = &rest[2..];
+        }
+        let payload = serde_json::from_slice(rest).unwrap();
+        let message = Published {
+            topic,
+            retain: header & 0x1 == 1,
+            qos,
+            payload,
+        };
+        if published.send(message).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_publishes_loads_retained_and_failures_not() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let write = |version: &str| {
+        fs::write(
+            &config,
+            format!(
+                r#"{{ "app_name": "TestApp", "version": "{version}", "environment": "staging", "database": {{ "connection_string": "postgres://u:s3cret@db" }} }}"#
+            ),
+        )
+        .unwrap()
+    };
+    write("1.0.0");
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (sender, published) = mpsc::channel();
+    std::thread::spawn(move || broker(listener, sender));
+
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--mqtt-url", &format!("mqtt://127.0.0.1:{port}")])
+            .args(["--mqtt-topic", "config/{app_name}/{environment}/events"]),
+    );
+    let next = || published.recv_timeout(Duration::from_secs(10)).unwrap();
+
+    let initial = next();
+    assert_eq!(initial.topic, "config/TestApp/staging/events");
+    assert!(initial.retain);
+    assert_eq!(initial.qos, 1);
+    assert_eq!(initial.payload["config"]["version"], "1.0.0");
+    assert_eq!(
+        initial.payload["config"]["database"]["connection_string"],
+        "<redacted>"
+    );
+
+    write("2.0.0");
+    let reload = next();
+    assert!(reload.retain);
+    assert_eq!(reload.payload["event"], "loaded");
+    assert_eq!(reload.payload["config"]["version"], "2.0.0");
+
+    fs::write(&config, "{ not json").unwrap();
+    let failure = next();
+    assert_eq!(failure.topic, "config/TestApp/staging/events");
+    assert!(!failure.retain);
+    assert_eq!(failure.payload["event"], "load_failed");
+
+    assert!(watch.stop().status.success());
+}
+
+#[test]
+fn test_other_schemes_are_a_usage_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    let output = common::config_watcher()
+        .args(["-f", config.to_str().unwrap()])
+        .args(["--mqtt-url", "ws://broker:8083"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("only mqtt:// and mqtts://"), "{stderr}");
+}
@@ -0,0 +1,161 @@
+// Exports to an in-process fake OpenTelemetry collector through the real
+// binary.
+#![cfg(feature = "otlp")]
+
+mod common;
+
+use assert_cmd::Command;
+use common::{Watch, config_watcher, free_port};
+use serde_json::Value;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+
+/// Answers 200 to every request and hands over its path and JSON body
+fn collector() -> (SocketAddr, Receiver<(String, Value)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { break };
+            let request = read_request(&mut stream);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+            if sender.send(request).is_err() {
+                break;
+            }
+        }
+    });
+    (addr, receiver)
+}
+
+fn read_request(stream: &mut TcpStream) -> (String, Value) {
+    let mut data = Vec::new();
+    let mut buf = [0; 4096];
+    let (head, length) = loop {
+        let n = stream.read(&mut buf).unwrap();
+        data.extend_from_slice(&buf[..n]);
+        if let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&data[..end]).to_string();
+            let length: usize = head
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+                .unwrap()
+                .1
+                .trim()
+                .parse()
+                .unwrap();
+            data.drain(..end + 4);
+            break (head, length);
+        }
+    };
+    while data.len() < length {
+        let n = stream.read(&mut buf).unwrap();
+        data.extend_from_slice(&buf[..n]);
+    }
+    let path = head.split_whitespace().nth(1).unwrap().to_string();
+    (path, serde_json::from_slice(&data).unwrap())
+}
+
+/// The value of attribute `key`, whatever its type
+fn attribute<'a>(attributes: &'a Value, key: &str) -> Option<&'a Value> {
+    let value = &attributes
+        .as_array()?
+        .iter()
+        .find(|attribute| attribute["key"] == key)?["value"];
+    value.as_object()?.values().next()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_reload_spans_reach_the_collector() {
+    let (addr, requests) = collector();
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+
+    // The standard variable instead of --otlp-endpoint
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .env("OTEL_EXPORTER_OTLP_ENDPOINT", format!("http://{addr}")),
+    );
+    watch.wait_for("Initial configuration loaded");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "2.0.0" }"#).unwrap();
+    watch.wait_for("Configuration has been updated");
+    // Exported on the way out
+    let output = watch.stop();
+    assert!(output.status.success(), "{output:?}");
+
+    let requests: Vec<_> = requests.try_iter().collect();
+    let spans: Vec<&Value> = requests
+        .iter()
+        .filter(|(path, _)| path == "/v1/traces")
+        .flat_map(|(_, body)| {
+            let batch = &body["resourceSpans"][0];
+            assert_eq!(
+                attribute(&batch["resource"]["attributes"], "service.name"),
+                Some(&Value::from("TestApp"))
+            );
+            batch["scopeSpans"][0]["spans"].as_array().unwrap()
+        })
+        .collect();
+    let reload = spans
+        .iter()
+        .find(|span| span["name"] == "reload")
+        .unwrap_or_else(|| panic!("no reload span in {spans:?}"));
+    assert_eq!(
+        attribute(&reload["attributes"], "path"),
+        Some(&Value::from(config.to_str().unwrap()))
+    );
+    assert_eq!(
+        attribute(&reload["attributes"], "outcome"),
+        Some(&Value::from("ok"))
+    );
+    for name in ["read_config", "read", "parse", "validate", "apply"] {
+        assert!(
+            spans
+                .iter()
+                .any(|span| span["name"] == name && span["parentSpanId"].is_string()),
+            "no child {name} span"
+        );
+    }
+    assert!(
+        requests.iter().any(|(path, _)| path == "/v1/metrics"),
+        "metrics not flushed at shutdown"
+    );
+}
+
+#[test]
+fn test_unreachable_collector_does_not_affect_watching() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    let port = free_port();
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["-f", config.to_str().unwrap(), "--once"])
+        .args(["--otlp-endpoint", &format!("http://127.0.0.1:{port}")])
+        .timeout(std::time::Duration::from_secs(15))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("OTLP export failed"), "{stderr}");
+}
+
+#[test]
+fn test_grpc_protocol_is_refused() {
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["--once", "--otlp-endpoint", "http://collector:4317"])
+        .env("OTEL_EXPORTER_OTLP_PROTOCOL", "grpc")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("only http/json"), "{stderr}");
+}
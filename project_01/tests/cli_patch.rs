@@ -0,0 +1,140 @@
+// Exercises `config-watcher patch` through the real binary.
+
+use assert_cmd::Command;
+use serde_json::{Value, json};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+const CONFIG: &str = r#"{
+  "app_name": "TestApp",
+  "version": "1.0.0",
+  "server": { "host": "localhost", "port": 8080 },
+  "features": { "dark_mode": false, "beta": true }
+}
+"#;
+
+fn setup() -> (TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    fs::write(&path, CONFIG).unwrap();
+    (dir, path)
+}
+
+fn patch(path: &Path, operations: Value, args: &[&str]) -> std::process::Output {
+    let patch_file = path.with_file_name("changes.json");
+    fs::write(&patch_file, operations.to_string()).unwrap();
+    Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["patch", "-f", path.to_str().unwrap()])
+        .arg("--patch")
+        .arg(&patch_file)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn read(path: &Path) -> Value {
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn test_patch_applies_every_op_type() {
+    let (_dir, path) = setup();
+    let output = patch(
+        &path,
+        json!([
+            { "op": "test", "path": "/app_name", "value": "TestApp" },
+            { "op": "add", "path": "/environment", "value": "staging" },
+            { "op": "replace", "path": "/server/port", "value": 9090 },
+            { "op": "remove", "path": "/features/beta" },
+            { "op": "copy", "from": "/features/dark_mode", "path": "/features/compact" },
+            { "op": "move", "from": "/features/dark_mode", "path": "/features/night" }
+        ]),
+        &[],
+    );
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Applied 6 operation(s)"));
+
+    let doc = read(&path);
+    assert_eq!(doc["environment"], "staging");
+    assert_eq!(doc["server"]["port"], 9090);
+    assert_eq!(doc["features"], json!({ "compact": false, "night": false }));
+    // Indentation of the original file is kept
+    assert!(
+        fs::read_to_string(&path)
+            .unwrap()
+            .contains("\n  \"app_name\"")
+    );
+}
+
+#[test]
+fn test_failing_test_op_names_its_index() {
+    let (_dir, path) = setup();
+    let output = patch(
+        &path,
+        json!([
+            { "op": "replace", "path": "/server/port", "value": 9090 },
+            { "op": "test", "path": "/server/host", "value": "example.com" }
+        ]),
+        &[],
+    );
+    assert_eq!(output.status.code(), Some(5));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Patch operation 1 (test) failed: '/server/host' is \"localhost\""),
+        "{stderr}"
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), CONFIG);
+}
+
+#[test]
+fn test_invalid_result_is_refused_unless_no_validate() {
+    let (_dir, path) = setup();
+    let operations = json!([{ "op": "replace", "path": "/server/port", "value": 0 }]);
+
+    let output = patch(&path, operations.clone(), &[]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Refusing to write"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), CONFIG);
+
+    assert!(
+        patch(&path, operations, &["--no-validate"])
+            .status
+            .success()
+    );
+    assert_eq!(read(&path)["server"]["port"], 0);
+}
+
+#[test]
+fn test_dry_run_prints_without_writing() {
+    let (_dir, path) = setup();
+    let output = patch(
+        &path,
+        json!([{ "op": "add", "path": "/environment", "value": "production" }]),
+        &["--dry-run"],
+    );
+    assert!(output.status.success());
+    let printed: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(printed["environment"], "production");
+    assert_eq!(fs::read_to_string(&path).unwrap(), CONFIG);
+}
+
+#[test]
+fn test_encrypted_values_are_never_replaced_with_plaintext() {
+    let (_dir, path) = setup();
+    let encrypted = CONFIG.replace(r#""host": "localhost""#, r#""host": "age:QUJD""#);
+    fs::write(&path, &encrypted).unwrap();
+
+    let plaintext = json!([{ "op": "replace", "path": "/server/host", "value": "db.internal" }]);
+    for args in [&[][..], &["--dry-run"]] {
+        let output = patch(&path, plaintext.clone(), args);
+        assert_eq!(output.status.code(), Some(5));
+        assert!(output.stdout.is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), encrypted);
+    }
+
+    let reencrypted = json!([{ "op": "replace", "path": "/server/host", "value": "age:REVG" }]);
+    assert!(patch(&path, reencrypted, &[]).status.success());
+    assert_eq!(read(&path)["server"]["host"], json!("age:REVG"));
+}
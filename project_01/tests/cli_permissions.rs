@@ -0,0 +1,93 @@
+// Runs the real binary with --permission-audit against configuration files
+// referencing secret files with various modes.
+#![cfg(unix)]
+
+use serde_json::Value;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn chmod(path: &Path, mode: u32) {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+}
+
+/// A configuration next to its `db.pass` secret file
+fn setup(dir: &Path, secret_mode: u32) -> std::path::PathBuf {
+    let file = dir.join("config.json");
+    fs::write(
+        &file,
+        r#"{
+    "app_name": "TestApp",
+    "version": "1.0.0",
+    "database": { "connection_string": "postgres://db/app", "password_file": "db.pass" }
+}"#,
+    )
+    .unwrap();
+    let secret = dir.join("db.pass");
+    fs::write(&secret, "s3cr3t").unwrap();
+    chmod(&secret, secret_mode);
+    file
+}
+
+fn once(file: &Path, level: &str) -> Output {
+    Command::new(assert_cmd::cargo::cargo_bin("config_watcher"))
+        .args(["-f", file.to_str().unwrap(), "--once"])
+        .args(["--permission-audit", level, "--error-format", "json"])
+        .output()
+        .unwrap()
+}
+
+fn error_report(output: &Output) -> Value {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let line = stderr
+        .lines()
+        .find(|line| line.starts_with('{'))
+        .unwrap_or_else(|| panic!("no JSON error: {stderr}"));
+    serde_json::from_str(line).unwrap()
+}
+
+#[test]
+fn test_readable_secret_file_rejects_the_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = setup(dir.path(), 0o644);
+
+    let output = once(&file, "error");
+    assert_eq!(output.status.code(), Some(5), "{output:?}");
+    let report = error_report(&output);
+    assert_eq!(report["code"], "validation_failed");
+    let finding = &report["findings"][0];
+    assert_eq!(finding["path"], "database.password_file");
+    let message = finding["message"].as_str().unwrap();
+    assert!(
+        message.contains("is readable by group and others (mode 0644)"),
+        "{message}"
+    );
+
+    // Tightened, it loads
+    chmod(&dir.path().join("db.pass"), 0o600);
+    assert_eq!(once(&file, "error").status.code(), Some(0));
+}
+
+#[test]
+fn test_warn_and_off_levels() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = setup(dir.path(), 0o640);
+    chmod(&file, 0o666);
+
+    let output = once(&file, "warn");
+    assert_eq!(output.status.code(), Some(0), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("is writable by group and others (mode 0666)"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("is readable by group (mode 0640)"),
+        "{stderr}"
+    );
+
+    let output = once(&file, "off");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("permission audit"));
+}
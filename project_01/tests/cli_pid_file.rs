@@ -0,0 +1,105 @@
+// Runs the real binary with --pid-file: the file holds the pid while it
+// runs and is gone after every kind of exit (SIGTERM, Ctrl+C, --fail-fast,
+// a startup error); a live owner is refused, a dead one taken over.
+#![cfg(unix)]
+
+mod common;
+
+use common::{Watch, config_watcher, eventually, send};
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const CONFIG: &str = r#"{ "app_name": "PidApp", "version": "1.0.0" }"#;
+
+fn watch(dir: &Path, pid_file: &str) -> Command {
+    let mut command = config_watcher();
+    command
+        .current_dir(dir)
+        .args(["-f", "config.json", "--interval", "1", "--no-lock"])
+        .args(["--pid-file", pid_file])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    command
+}
+
+/// Waits until `path` holds `pid`
+fn wait_for_pid(path: &Path, pid: u32) {
+    let expected = format!("{pid}\n");
+    eventually(&format!("pid file at {}", path.display()), || {
+        fs::read_to_string(path).ok().as_deref() == Some(&expected)
+    });
+}
+
+fn setup() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.json"), CONFIG).unwrap();
+    dir
+}
+
+#[test]
+fn test_removed_on_sigterm_and_ctrl_c() {
+    for signal in ["-TERM", "-INT"] {
+        let dir = setup();
+        let pid_file = dir.path().join("watcher.pid");
+        let child = watch(dir.path(), pid_file.to_str().unwrap())
+            .spawn()
+            .unwrap();
+        wait_for_pid(&pid_file, child.id());
+
+        send(signal, child.id());
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "{signal}: {output:?}");
+        assert!(!pid_file.exists(), "{signal}");
+    }
+}
+
+#[test]
+fn test_removed_on_fail_fast_and_startup_errors() {
+    let dir = setup();
+    let pid_file = dir.path().join("watcher.pid");
+    let fail_fast = Watch::spawn(watch(dir.path(), "watcher.pid").arg("--fail-fast"));
+    wait_for_pid(&pid_file, fail_fast.id());
+    fail_fast.wait_for("Initial configuration loaded");
+    fs::write(dir.path().join("config.json"), "{ broken").unwrap();
+    let output = fail_fast.wait();
+    assert_eq!(output.status.code(), Some(6), "{output:?}");
+    assert!(!pid_file.exists());
+
+    // A key that cannot be loaded fails the startup after the pid file
+    let dir = setup();
+    let output = watch(dir.path(), "watcher.pid")
+        .args(["--verify-signature", "--public-key", "missing.pub"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{output:?}");
+    assert!(!dir.path().join("watcher.pid").exists());
+}
+
+#[test]
+fn test_live_owner_refused_dead_owner_taken_over() {
+    let dir = setup();
+    let pid_file = dir.path().join("watcher.pid");
+
+    // This test process is alive
+    fs::write(&pid_file, format!("{}\n", std::process::id())).unwrap();
+    let output = watch(dir.path(), "watcher.pid").output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(9), "{stderr}");
+    assert!(stderr.contains("still running"), "{stderr}");
+    assert_eq!(
+        fs::read_to_string(&pid_file).unwrap(),
+        format!("{}\n", std::process::id())
+    );
+
+    // A process that exited; the relative path is taken from the
+    // working directory
+    let mut dead = Command::new("true").spawn().unwrap();
+    dead.wait().unwrap();
+    fs::write(&pid_file, format!("{}\n", dead.id())).unwrap();
+    let child = watch(dir.path(), "watcher.pid").spawn().unwrap();
+    wait_for_pid(&pid_file, child.id());
+    send("-TERM", child.id());
+    assert!(child.wait_with_output().unwrap().status.success());
+    assert!(!pid_file.exists());
+}
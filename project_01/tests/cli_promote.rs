@@ -0,0 +1,80 @@
+// Runs the real binary with --promote-next: a valid FILE.next replaces
+// FILE, an invalid one is rejected with its findings, and direct edits of
+// FILE still load.
+
+mod common;
+
+use common::{Watch, config, config_watcher, events, named};
+use std::fs;
+
+#[cfg(unix)]
+#[test]
+fn test_promotions_rejections_and_direct_edits() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    let next = dir.path().join("config.json.next");
+    fs::write(&file, config("First", "1.0.0")).unwrap();
+
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", file.to_str().unwrap(), "--interval", "1"])
+            .arg("--lock-dir")
+            .arg(dir.path())
+            .args(["--promote-next", "--output", "json"]),
+    );
+
+    watch.wait_for_event("loaded", 1);
+    fs::write(&next, config("Promoted", "1.0.0")).unwrap();
+    watch.wait_for_event("loaded", 2);
+    let promoted = fs::read_to_string(&file).unwrap();
+    assert!(!next.exists());
+    fs::write(&next, r#"{ "app_name": "", "version": "1.0.0" }"#).unwrap();
+    watch.until("the rejection", |watch| {
+        !named(&watch.events(), "rejected").is_empty()
+    });
+    fs::write(&file, config("Edited", "1.0.0")).unwrap();
+    watch.wait_for_event("loaded", 3);
+
+    let output = watch.stop();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(promoted, config("Promoted", "1.0.0"));
+    // The rejected candidate is left where it was
+    assert!(next.exists());
+
+    let events = events(&output.stdout);
+    let names: Vec<_> = events
+        .iter()
+        .filter_map(|event| event["event"].as_str())
+        .filter(|name| ["loaded", "promoted", "rejected"].contains(name))
+        .collect();
+    assert_eq!(
+        names,
+        ["loaded", "promoted", "loaded", "rejected", "loaded"],
+        "{events:?}"
+    );
+    let apps: Vec<_> = events
+        .iter()
+        .filter(|event| event["event"] == "loaded")
+        .map(|event| event["summary"]["app_name"].clone())
+        .collect();
+    assert_eq!(apps, ["First", "Promoted", "Edited"]);
+
+    let rejected = events
+        .iter()
+        .find(|event| event["event"] == "rejected")
+        .unwrap();
+    assert_eq!(rejected["candidate"], next.to_str().unwrap());
+    assert_eq!(rejected["error"]["code"], "validation_failed");
+    assert_eq!(rejected["error"]["findings"][0]["path"], "app_name");
+}
+
+#[test]
+fn test_promote_next_refuses_companion_checks() {
+    let output = config_watcher()
+        .args(["-f", "config.json", "--promote-next", "--verify-checksum"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot be used with"), "{stderr}");
+}
@@ -0,0 +1,87 @@
+// Reads the frames --push-socket sends to a client of the real binary, with
+// reloads requested by the client itself.
+#![cfg(unix)]
+
+mod common;
+
+use common::{Watch, config_watcher};
+use serde_json::Value;
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+fn read_frame(stream: &mut UnixStream) -> Value {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len).unwrap();
+    let mut json = vec![0; u32::from_be_bytes(len) as usize];
+    stream.read_exact(&mut json).unwrap();
+    serde_json::from_slice(&json).unwrap()
+}
+
+#[test]
+fn test_frames_on_connect_and_after_two_reloads() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let write = |version: &str| {
+        fs::write(
+            &config,
+            format!(
+                r#"{{ "app_name": "TestApp", "version": "{version}", "database": {{ "connection_string": "postgres://u:s3cret@db" }} }}"#
+            ),
+        )
+        .unwrap()
+    };
+    write("1.0.0");
+    let socket = dir.path().join("push.sock");
+
+    // No tick during the test: only RELOAD requests reload
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "60"])
+            .args([
+                "--push-socket",
+                socket.to_str().unwrap(),
+                "--push-allow-reload",
+            ]),
+    );
+    let mut client = (0..50)
+        .find_map(|_| {
+            std::thread::sleep(Duration::from_millis(100));
+            UnixStream::connect(&socket).ok()
+        })
+        .expect("push socket never showed up");
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    let first = read_frame(&mut client);
+    assert_eq!(first["file"], config.to_str().unwrap());
+    assert_eq!(first["version"], 1);
+    assert_eq!(first["config"]["version"], "1.0.0");
+    // Not redacted without --push-redact
+    assert_eq!(
+        first["config"]["database"]["connection_string"],
+        "postgres://u:s3cret@db"
+    );
+
+    for (version, expected) in [("2.0.0", 2), ("3.0.0", 3)] {
+        write(version);
+        client.write_all(b"RELOAD\n").unwrap();
+        let frame = read_frame(&mut client);
+        assert_eq!(frame["version"], expected);
+        assert_eq!(frame["config"]["version"], version);
+    }
+
+    let output = watch.stop();
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout
+            .matches("Reload requested by push socket, reloading...")
+            .count(),
+        2
+    );
+    // Removed on exit
+    assert!(!socket.exists());
+}
@@ -0,0 +1,94 @@
+// Runs the real binary against a minimal in-process Redis server and checks
+// the announcements of the initial load and of a reload.
+#![cfg(feature = "redis")]
+
+mod common;
+
+use common::{Watch, config_watcher};
+use serde_json::Value;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Reads one command (a RESP array of bulk strings)
+fn command(reader: &mut impl BufRead) -> Option<Vec<String>> {
+    let mut line = String::new();
+    let mut number = |reader: &mut dyn BufRead, prefix: char| -> Option<usize> {
+        line.clear();
+        reader.read_line(&mut line).ok()?;
+        line.trim_end().strip_prefix(prefix)?.parse().ok()
+    };
+    let count = number(reader, '*')?;
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = number(reader, '$')?;
+        let mut arg = vec![0; len + 2];
+        reader.read_exact(&mut arg).ok()?;
+        arg.truncate(len);
+        args.push(String::from_utf8(arg).ok()?);
+    }
+    Some(args)
+}
+
+/// Answers the commands of one client and hands them over
+fn server(listener: TcpListener, commands: mpsc::Sender<Vec<String>>) {
+    let (stream, _) = listener.accept().unwrap();
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    while let Some(args) = command(&mut reader) {
+        let reply: &[u8] = match args[0].as_str() {
+            "PUBLISH" => b":1\r\n",
+            _ => b"+OK\r\n",
+        };
+        writer.write_all(reply).unwrap();
+        if commands.send(args).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_announces_the_initial_load_and_a_reload() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let write = |version: &str| {
+        fs::write(
+            &config,
+            format!(r#"{{ "app_name": "TestApp", "version": "{version}" }}"#),
+        )
+        .unwrap()
+    };
+    write("1.0.0");
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (sender, commands) = mpsc::channel();
+    std::thread::spawn(move || server(listener, sender));
+
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--redis-url", &format!("redis://127.0.0.1:{port}")])
+            .args(["--redis-version-key", "config:{app_name}:version"]),
+    );
+    let next = || commands.recv_timeout(Duration::from_secs(10)).unwrap();
+
+    let publish = next();
+    assert_eq!(publish[..2], ["PUBLISH", "config:TestApp"]);
+    let body: Value = serde_json::from_str(&publish[2]).unwrap();
+    assert_eq!(body["event"], "initial");
+    assert_eq!(body["version"], 1);
+    assert_eq!(next(), ["SET", "config:TestApp:version", &publish[2]]);
+
+    write("2.0.0");
+    let publish = next();
+    let body: Value = serde_json::from_str(&publish[2]).unwrap();
+    assert_eq!(body["event"], "reload");
+    assert_eq!(body["version"], 2);
+    assert_eq!(body["changed_paths"], serde_json::json!(["version"]));
+    assert_eq!(next()[0], "SET");
+
+    assert!(watch.stop().status.success());
+}
@@ -0,0 +1,172 @@
+// Runs the real binary with --keep-versions, then `rollback` against the
+// versions it kept: listing, a round trip through the running watcher, and
+// the refusal of a version that no longer validates.
+
+mod common;
+
+use common::{Watch, config, config_watcher, events, named};
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+fn rollback(file: &Path, state: &Path, args: &[&str]) -> Output {
+    config_watcher()
+        .args(["rollback", "-f", file.to_str().unwrap()])
+        .arg("--state-dir")
+        .arg(state)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn loaded_names(output: &Output) -> Vec<String> {
+    named(&events(&output.stdout), "loaded")
+        .iter()
+        .map(|event| event["summary"]["app_name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+fn kept(state: &Path) -> Vec<String> {
+    let mut names: Vec<_> = fs::read_dir(state.join("versions"))
+        .unwrap()
+        .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect();
+    names.sort();
+    names
+}
+
+#[cfg(unix)]
+#[test]
+fn test_keep_versions_then_roll_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+
+    fs::write(&file, config("First", "1.0.0")).unwrap();
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", file.to_str().unwrap(), "--interval", "1"])
+            .arg("--lock-dir")
+            .arg(state.path())
+            .arg("--state-dir")
+            .arg(state.path())
+            .args(["--keep-versions", "2"])
+            .args(["--output", "json"]),
+    );
+    watch.wait_for_event("loaded", 1);
+    for (name, version) in [("Second", 2), ("Third", 3)] {
+        fs::write(&file, config(name, "1.0.0")).unwrap();
+        watch.wait_for_event("loaded", version);
+    }
+
+    // Only the two newest are kept, newest first, the current one marked
+    let newest = [config("Second", "1.0.0"), config("Third", "1.0.0")];
+    watch.until("the two newest versions kept", |_| {
+        kept(state.path()) == newest
+    });
+    let list = rollback(&file, state.path(), &["--list"]);
+    assert!(list.status.success(), "{list:?}");
+    let stdout = String::from_utf8_lossy(&list.stdout);
+    let lines: Vec<_> = stdout
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .collect();
+    assert_eq!(lines.len(), 2, "{lines:?}");
+    assert_eq!(lines[0][0], "3");
+    assert_eq!(lines[0].get(2), Some(&"(current)"));
+    assert_eq!(lines[1][0], "2");
+    assert_eq!(lines[1].get(2), None);
+
+    // The watcher loads the version written back, and keeps it again
+    let output = rollback(&file, state.path(), &["--to", "latest-1"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("Rolled back"),
+        "{output:?}"
+    );
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        config("Second", "1.0.0")
+    );
+    watch.wait_for_event("loaded", 4);
+    watch.until("the rolled back version kept", |_| {
+        kept(state.path()) == newest
+    });
+
+    let output = watch.stop();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        loaded_names(&output),
+        ["First", "Second", "Third", "Second"]
+    );
+
+    // Writing back what the file holds changes nothing
+    let output = rollback(&file, state.path(), &["--to", "latest"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("already holds"),
+        "{output:?}"
+    );
+}
+
+#[test]
+fn test_rollback_refuses_a_version_that_no_longer_validates() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    fs::write(&file, config("Current", "1.0.0")).unwrap();
+    let versions = state.path().join("versions");
+    fs::create_dir(&versions).unwrap();
+    fs::write(
+        versions.join("20261015T120000.000Z-1.json"),
+        config("", "1.0.0"),
+    )
+    .unwrap();
+
+    let output = rollback(&file, state.path(), &["--to", "1"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(5), "{stderr}");
+    assert!(stderr.contains("Refusing to roll back"), "{stderr}");
+    assert!(stderr.contains("app_name"), "{stderr}");
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        config("Current", "1.0.0")
+    );
+}
+
+#[test]
+fn test_rollback_usage_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    fs::write(&file, config("Current", "1.0.0")).unwrap();
+
+    // Nothing kept yet
+    let list = rollback(&file, dir.path(), &["--list"]);
+    assert!(list.status.success(), "{list:?}");
+    assert!(list.stdout.is_empty());
+    assert_eq!(
+        rollback(&file, dir.path(), &["--to", "latest-1"])
+            .status
+            .code(),
+        Some(2)
+    );
+    assert_eq!(
+        rollback(&file, dir.path(), &["--to", "yesterday"])
+            .status
+            .code(),
+        Some(2)
+    );
+
+    // --keep-versions needs a state directory
+    let output = config_watcher()
+        .args([
+            "-f",
+            file.to_str().unwrap(),
+            "--once",
+            "--keep-versions",
+            "3",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+}
@@ -0,0 +1,103 @@
+// Runs the real binary as a supervisor of a shell script and checks the
+// restarts, the shutdown order and the exit statuses.
+#![cfg(unix)]
+
+mod common;
+
+use common::{Watch, config_watcher, read};
+use std::fs;
+
+#[test]
+fn test_restarts_on_change_and_stops_the_command_first() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let log = dir.path().join("log");
+    fs::write(&config, common::config("TestApp", "1.0.0")).unwrap();
+    let script = format!(
+        r#"echo "start $CW__VERSION $CONFIG_VERSION $CONFIG_PATH" >> {0}; trap 'echo term >> {0}; exit 0' TERM; while :; do sleep 0.1; done"#,
+        log.display()
+    );
+
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["run", "-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--", "sh", "-c", &script]),
+    );
+    let logged =
+        |expected: &str| watch.until(expected, |_| read(dir.path(), "log").contains(expected));
+    logged("start 1.0.0 1");
+    fs::write(&config, common::config("TestApp", "2.0.0")).unwrap();
+    logged("start 2.0.0 2");
+    // A failed reload leaves the running instance alone
+    fs::write(&config, "{ not json").unwrap();
+    watch.wait_for("Configuration reload failed");
+
+    let output = watch.stop();
+    assert!(output.status.success());
+    // The last instance got SIGTERM before the watcher exited
+    assert_eq!(
+        fs::read_to_string(&log).unwrap(),
+        format!(
+            "start 1.0.0 1 {0}\nterm\nstart 2.0.0 2 {0}\nterm\n",
+            config.display()
+        )
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Shutting down gracefully"), "{stdout}");
+}
+
+#[test]
+fn test_kills_a_command_ignoring_sigterm() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let log = dir.path().join("log");
+    fs::write(&config, common::config("TestApp", "1.0.0")).unwrap();
+    let script = format!(
+        r#"trap 'echo ignored >> {0}' TERM; echo started >> {0}; while :; do sleep 0.1; done"#,
+        log.display()
+    );
+
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["run", "-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--kill-timeout", "500ms"])
+            .args(["--", "sh", "-c", &script]),
+    );
+    watch.until("the command", |_| read(dir.path(), "log") == "started\n");
+    let output = watch.stop();
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&log).unwrap(), "started\nignored\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("killing it"), "{stderr}");
+}
+
+#[test]
+fn test_exit_status_tells_the_command_from_the_watcher() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, common::config("TestApp", "1.0.0")).unwrap();
+    let run = |command: &[&str]| {
+        config_watcher()
+            .args(["run", "-f", config.to_str().unwrap(), "--"])
+            .args(command)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["sh", "-c", "exit 3"]);
+    assert_eq!(output.status.code(), Some(8));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Command exited with status 3"), "{stderr}");
+
+    let output = run(&["true"]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Command exited, shutting down"), "{stdout}");
+
+    let output = config_watcher()
+        .args(["run", "-f", config.to_str().unwrap(), "-f", "other.json"])
+        .args(["--", "true"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
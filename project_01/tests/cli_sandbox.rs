@@ -0,0 +1,86 @@
+// Runs the real binary with --sandbox (Landlock, Linux only): normal
+// watching goes on, a file resolving outside the sandbox is refused.
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::{Watch, config, config_watcher, events, named};
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_watching_inside_the_sandbox_and_refusing_outside() {
+    let inside = tempfile::tempdir().unwrap();
+    let outside = tempfile::tempdir().unwrap();
+    let file = inside.path().join("config.json");
+    let elsewhere = outside.path().join("config.json");
+    fs::write(&file, config("TestApp", "1.0.0")).unwrap();
+
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", file.to_str().unwrap(), "--interval", "1"])
+            .arg("--lock-dir")
+            .arg(inside.path())
+            .args(["--sandbox", "--output", "json"]),
+    );
+    watch.wait_for_event("loaded", 1);
+    if watch.stderr().contains("not enforced") {
+        eprintln!("Landlock unavailable, skipped: {}", watch.stderr());
+        return;
+    }
+
+    // A regular change, then the file swapped for a link out of the
+    // sandbox, then a regular file again
+    fs::write(&file, config("TestApp", "2.0.0")).unwrap();
+    watch.wait_for_event("loaded", 2);
+    fs::write(&elsewhere, config("TestApp", "3.0.0")).unwrap();
+    fs::remove_file(&file).unwrap();
+    std::os::unix::fs::symlink(&elsewhere, &file).unwrap();
+    watch.until("the refusal", |watch| {
+        !named(&watch.events(), "load_failed").is_empty()
+    });
+    fs::remove_file(&file).unwrap();
+    fs::write(&file, config("TestApp", "4.0.0")).unwrap();
+    watch.wait_for_event("loaded", 3);
+
+    let output = watch.stop();
+    assert!(output.status.success(), "{output:?}");
+    let events = events(&output.stdout);
+
+    // 1.0.0, 2.0.0 and 4.0.0; never 3.0.0
+    let versions: Vec<_> = events
+        .iter()
+        .filter(|event| event["event"] == "loaded")
+        .map(|event| event["version"].clone())
+        .collect();
+    assert_eq!(versions, [1, 2, 3], "{events:?}");
+
+    let failure = events
+        .iter()
+        .find(|event| event["event"] == "load_failed")
+        .unwrap_or_else(|| panic!("no load_failed event: {events:?}"));
+    assert_eq!(failure["error"]["code"], "outside_sandbox");
+    let message = failure["error"]["message"].as_str().unwrap();
+    assert!(message.contains("outside the --sandbox"), "{message}");
+    assert!(
+        message.contains(elsewhere.canonicalize().unwrap().to_str().unwrap()),
+        "{message}"
+    );
+}
+
+#[test]
+fn test_sandbox_refuses_commands() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    fs::write(&file, config("TestApp", "1.0.0")).unwrap();
+
+    let output = config_watcher()
+        .args(["-f", file.to_str().unwrap(), "--once", "--sandbox"])
+        .args(["--on-change", "true"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot be used with"), "{stderr}");
+    assert!(Path::new(&file).exists());
+}
@@ -0,0 +1,33 @@
+// Runs the real binary's `service` command off Windows: the arguments of
+// `install` are still checked, then refused; `service run` from a console
+// is a usage error everywhere.
+#![cfg(not(windows))]
+
+use assert_cmd::Command;
+
+fn service(args: &[&str]) -> std::process::Output {
+    Command::cargo_bin("config_watcher")
+        .unwrap()
+        .arg("service")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_service_off_windows() {
+    let output = service(&["install", "--", "-f", "app.json"]);
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--log-file"), "{stderr}");
+
+    let output = service(&["install", "--", "-f", "app.json", "--log-file", "cw.log"]);
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("only supported on Windows"), "{stderr}");
+
+    let output = service(&["run", "-f", "app.json"]);
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("service control manager"), "{stderr}");
+}
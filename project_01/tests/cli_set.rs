@@ -0,0 +1,190 @@
+// Exercises `config-watcher set` through the real binary.
+
+use assert_cmd::Command;
+use serde_json::{Value, json};
+use std::fs;
+use tempfile::TempDir;
+
+const CONFIG: &str = r#"{
+    "app_name": "TestApp",
+    "version": "1.0.0",
+    "server": null,
+    "features": {
+        "dark_mode": false
+    }
+}
+"#;
+
+fn setup() -> (TempDir, std::path::PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    fs::write(&path, CONFIG).unwrap();
+    (dir, path)
+}
+
+fn set(path: &std::path::Path, args: &[&str]) -> std::process::Output {
+    Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["set", "-f", path.to_str().unwrap()])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn read(path: &std::path::Path) -> Value {
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn test_set_keeps_json_types() {
+    let (_dir, path) = setup();
+
+    assert!(set(&path, &["features.dark_mode", "true"]).status.success());
+    // `2.0` is inferred as a number, which the schema rejects for `version`
+    assert_eq!(set(&path, &["version", "2.0"]).status.code(), Some(5));
+    assert!(
+        set(&path, &["version", "2.0", "--type", "string"])
+            .status
+            .success()
+    );
+
+    let doc = read(&path);
+    assert_eq!(doc["features"]["dark_mode"], json!(true));
+    assert_eq!(doc["version"], json!("2.0"));
+}
+
+#[test]
+fn test_set_creates_absent_sections() {
+    let (_dir, path) = setup();
+
+    assert!(
+        set(&path, &["server.host", "localhost", "--no-validate"])
+            .status
+            .success()
+    );
+    assert!(set(&path, &["server.port", "9090"]).status.success());
+
+    let doc = read(&path);
+    assert_eq!(doc["server"], json!({ "host": "localhost", "port": 9090 }));
+}
+
+#[test]
+fn test_set_refuses_invalid_result() {
+    let (_dir, path) = setup();
+
+    let output = set(&path, &["environment", "moon"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("environment: must be one of"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), CONFIG);
+
+    // A value of the wrong type is refused as well
+    let output = set(&path, &["app_name", "42"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(fs::read_to_string(&path).unwrap(), CONFIG);
+
+    assert!(
+        set(&path, &["environment", "moon", "--no-validate"])
+            .status
+            .success()
+    );
+    assert_eq!(read(&path)["environment"], json!("moon"));
+}
+
+#[test]
+fn test_set_rewrites_atomically_in_place() {
+    let (dir, path) = setup();
+
+    assert!(set(&path, &["app_name", "Renamed"]).status.success());
+
+    // No temp file left behind, only the backup; key order and
+    // indentation preserved
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    assert_eq!(
+        fs::read_to_string(dir.path().join("config.json.bak")).unwrap(),
+        CONFIG
+    );
+    let expected = CONFIG.replace("TestApp", "Renamed");
+    assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+}
+
+#[test]
+fn test_set_backups() {
+    let (dir, path) = setup();
+    let backup = |name: &str| fs::read_to_string(dir.path().join(name)).ok();
+
+    for name in ["One", "Two", "Three"] {
+        assert!(
+            set(&path, &["app_name", name, "--backups", "2"])
+                .status
+                .success()
+        );
+    }
+    assert_eq!(read(&path)["app_name"], json!("Three"));
+    assert_eq!(
+        backup("config.json.bak.1"),
+        Some(CONFIG.replace("TestApp", "Two"))
+    );
+    assert_eq!(
+        backup("config.json.bak.2"),
+        Some(CONFIG.replace("TestApp", "One"))
+    );
+    assert_eq!(backup("config.json.bak"), None);
+
+    assert!(
+        set(&path, &["app_name", "Four", "--no-backup"])
+            .status
+            .success()
+    );
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    assert_eq!(
+        set(
+            &path,
+            &["app_name", "Five", "--no-backup", "--backups", "2"]
+        )
+        .status
+        .code(),
+        Some(2)
+    );
+}
+
+#[test]
+fn test_set_never_stores_an_encrypted_value_in_clear() {
+    let (_dir, path) = setup();
+    let encrypted = CONFIG.replace(
+        r#""server": null,"#,
+        r#""server": null,
+    "database": {
+        "connection_string": "age:YWdlLWVuY3J5cHRpb24ub3JnL3YxCg=="
+    },"#,
+    );
+    fs::write(&path, &encrypted).unwrap();
+
+    for args in [
+        &["database.connection_string", "postgres://u:p@db/app"][..],
+        &[
+            "database.connection_string",
+            "postgres://u:p@db/app",
+            "--no-validate",
+        ],
+    ] {
+        let output = set(&path, args);
+        assert_eq!(output.status.code(), Some(5));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("database.connection_string holds an encrypted (age:) value"),
+            "{stderr}"
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), encrypted);
+    }
+
+    // Another encrypted value is fine
+    assert!(
+        set(&path, &["database.connection_string", "age:QUJD"])
+            .status
+            .success()
+    );
+    assert_eq!(
+        read(&path)["database"]["connection_string"],
+        json!("age:QUJD")
+    );
+}
@@ -0,0 +1,109 @@
+// Runs the real binary and stops it with SIGTERM: the cleanup runs (status
+// and pid files removed, the shutdown recorded in the audit log) and the
+// exit code is 0; a cleanup stuck past --shutdown-timeout is abandoned with
+// exit code 10, or at once with exit code 11 on a second signal.
+#![cfg(unix)]
+
+mod common;
+
+use common::{config_watcher, eventually, send};
+use std::fs;
+use std::path::Path;
+use std::process::{Child, Stdio};
+use std::time::{Duration, Instant};
+
+const CONFIG: &str = r#"{ "app_name": "Stopping", "version": "1.0.0" }"#;
+
+fn watch(dir: &Path, args: &[&str]) -> Child {
+    config_watcher()
+        .current_dir(dir)
+        .args(["-f", "config.json", "--interval", "1", "--no-lock"])
+        .args(["--status-file", "status.json", "--pid-file", "watcher.pid"])
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap()
+}
+
+fn setup() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.json"), CONFIG).unwrap();
+    dir
+}
+
+#[test]
+fn test_sigterm_runs_the_cleanup() {
+    let dir = setup();
+    let child = watch(dir.path(), &["--audit-log", "audit.jsonl"]);
+    eventually("status file", || dir.path().join("status.json").exists());
+    eventually("pid file", || dir.path().join("watcher.pid").exists());
+
+    send("-TERM", child.id());
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert!(!dir.path().join("status.json").exists());
+    assert!(!dir.path().join("watcher.pid").exists());
+    let audit = fs::read_to_string(dir.path().join("audit.jsonl")).unwrap();
+    let last: serde_json::Value = serde_json::from_str(audit.lines().last().unwrap()).unwrap();
+    assert_eq!(last["event"], "shutdown", "{audit}");
+    assert_eq!(last["reason"], "signal", "{audit}");
+}
+
+#[test]
+fn test_stuck_cleanup_is_abandoned_at_the_deadline() {
+    let dir = setup();
+    let child = stuck_hook(dir.path(), "1s");
+
+    let stopping = Instant::now();
+    send("-TERM", child.id());
+    let output = child.wait_with_output().unwrap();
+    assert!(stopping.elapsed() < Duration::from_secs(4), "{output:?}");
+    assert_eq!(output.status.code(), Some(10), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--shutdown-timeout 1s"), "{stderr}");
+    assert!(stderr.contains("draining the notifier queue"), "{stderr}");
+}
+
+#[test]
+fn test_second_signal_forces_the_shutdown() {
+    let dir = setup();
+    let mut child = stuck_hook(dir.path(), "30s");
+
+    // The first Ctrl+C waits for the cleanup
+    send("-INT", child.id());
+    std::thread::sleep(Duration::from_secs(1));
+    assert!(
+        child.try_wait().unwrap().is_none(),
+        "exited on the first Ctrl+C"
+    );
+
+    let forcing = Instant::now();
+    send("-INT", child.id());
+    let output = child.wait_with_output().unwrap();
+    assert!(forcing.elapsed() < Duration::from_secs(3), "{output:?}");
+    assert_eq!(output.status.code(), Some(11), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("forced by a second signal"), "{stderr}");
+    // Still removed
+    assert!(!dir.path().join("status.json").exists());
+    assert!(!dir.path().join("watcher.pid").exists());
+}
+
+/// A watcher whose --on-change hook is still running: it holds up the
+/// notifier queue once stopped
+fn stuck_hook(dir: &Path, shutdown_timeout: &str) -> Child {
+    let child = watch(
+        dir,
+        &[
+            "--on-change",
+            "touch hook-started; sleep 30",
+            "--shutdown-timeout",
+            shutdown_timeout,
+        ],
+    );
+    eventually("status file", || dir.join("status.json").exists());
+    fs::write(dir.join("config.json"), CONFIG.replace("1.0.0", "2.0.0")).unwrap();
+    eventually("hook started", || dir.join("hook-started").exists());
+    child
+}
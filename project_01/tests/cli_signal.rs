@@ -0,0 +1,84 @@
+// Runs the real binary with --signal-pidfile against a shell script that
+// records its signals, and against a stale pidfile.
+#![cfg(unix)]
+
+mod common;
+
+use common::{Watch, events, eventually, named, read};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Watches `config` until a reload has been signalled, returning the events
+fn watch_with_reload(config: &Path, pidfile: &Path) -> Vec<Value> {
+    fs::write(config, common::config("TestApp", "1.0.0")).unwrap();
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--signal-pidfile", pidfile.to_str().unwrap()])
+            .args(["--output", "json"]),
+    );
+    watch.wait_for_event("loaded", 1);
+    fs::write(config, common::config("TestApp", "2.0.0")).unwrap();
+    watch.wait_for_event("signal", 2);
+    let output = watch.stop();
+    assert!(output.status.success());
+    events(&output.stdout)
+}
+
+#[test]
+fn test_sends_sighup_to_the_process_of_the_pidfile() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let pidfile = dir.path().join("daemon.pid");
+    let log = dir.path().join("log");
+    let mut daemon = Command::new("sh")
+        .arg("-c")
+        .arg(format!(
+            "trap 'echo hup >> {}' HUP; echo $$ > {}; while :; do sleep 0.1; done",
+            log.display(),
+            pidfile.display()
+        ))
+        .spawn()
+        .unwrap();
+    eventually("the pidfile", || {
+        read(dir.path(), "daemon.pid").ends_with('\n')
+    });
+
+    let events = watch_with_reload(&config, &pidfile);
+    // The shell runs its trap once its sleep is over
+    eventually("the trap", || read(dir.path(), "log") == "hup\n");
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+
+    // Only the reload is signalled, not the initial load
+    let signals = named(&events, "signal");
+    assert_eq!(signals.len(), 1, "{events:?}");
+    assert_eq!(signals[0]["status"], "ok");
+    assert_eq!(signals[0]["signal"], "SIGHUP");
+    assert_eq!(signals[0]["pid"], daemon.id());
+    assert_eq!(signals[0]["version"], 2);
+}
+
+#[test]
+fn test_a_stale_pidfile_is_reported_and_the_reload_kept() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let pidfile = dir.path().join("daemon.pid");
+    let mut gone = Command::new("true").spawn().unwrap();
+    gone.wait().unwrap();
+    fs::write(&pidfile, format!("{}\n", gone.id())).unwrap();
+
+    let events = watch_with_reload(&config, &pidfile);
+    let signals = named(&events, "signal");
+    assert_eq!(signals.len(), 1, "{events:?}");
+    assert_eq!(signals[0]["status"], "failed");
+    assert_eq!(signals[0]["reason"], "no_such_process");
+    // The reload itself was accepted
+    assert!(
+        events
+            .iter()
+            .any(|e| e["event"] == "loaded" && e["version"] == 2)
+    );
+}
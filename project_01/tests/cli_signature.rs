@@ -0,0 +1,148 @@
+// Runs the real binary with --verify-signature against configuration files
+// signed in the test with freshly generated keys.
+
+use config_watcher::signature;
+use ed25519_dalek::{Signer, SigningKey};
+mod common;
+
+use common::{Watch, config, config_watcher, error_code, events, named};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+const KEY_ID: [u8; 8] = [0x5e, 0xed, 0, 0, 0, 0, 0, 1];
+
+fn signing_key() -> SigningKey {
+    let seed: [u8; 32] = std::array::from_fn(|_| fastrand::u8(..));
+    SigningKey::from_bytes(&seed)
+}
+
+/// A random signing key, its public key written to `dir/config.pub`
+fn keys(dir: &Path) -> (SigningKey, PathBuf) {
+    let key = signing_key();
+    let public = dir.join("config.pub");
+    fs::write(
+        &public,
+        signature::public_key_file(&key.verifying_key(), KEY_ID),
+    )
+    .unwrap();
+    (key, public)
+}
+
+/// Writes `contents` to `file` and its `.minisig`
+fn write_signed(file: &Path, key: &SigningKey, contents: &str) {
+    fs::write(file, contents).unwrap();
+    fs::write(
+        signature::minisig_path(file),
+        signature::sign(key, KEY_ID, contents.as_bytes(), "file:config.json"),
+    )
+    .unwrap();
+}
+
+fn once(file: &Path, public: &Path) -> Output {
+    config_watcher()
+        .args(["-f", file.to_str().unwrap(), "--once"])
+        .args([
+            "--verify-signature",
+            "--public-key",
+            public.to_str().unwrap(),
+        ])
+        .args(["--error-format", "json"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_once_verifies_the_signature() {
+    let dir = tempfile::tempdir().unwrap();
+    let (key, public) = keys(dir.path());
+    let file = dir.path().join("config.json");
+
+    // Missing signature
+    fs::write(&file, config("TestApp", "1.0.0")).unwrap();
+    let output = once(&file, &public);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(error_code(&output), "signature_missing");
+
+    // Valid, minisign then raw
+    write_signed(&file, &key, &config("TestApp", "1.0.0"));
+    assert_eq!(once(&file, &public).status.code(), Some(0));
+    fs::remove_file(signature::minisig_path(&file)).unwrap();
+    fs::write(
+        signature::raw_path(&file),
+        key.sign(config("TestApp", "1.0.0").as_bytes()).to_bytes(),
+    )
+    .unwrap();
+    assert_eq!(once(&file, &public).status.code(), Some(0));
+    fs::remove_file(signature::raw_path(&file)).unwrap();
+
+    // Tampered content
+    write_signed(&file, &key, &config("TestApp", "1.0.0"));
+    fs::write(&file, config("TestApp", "6.6.6")).unwrap();
+    let output = once(&file, &public);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(error_code(&output), "signature_invalid");
+
+    // Tampered signature
+    write_signed(&file, &key, &config("TestApp", "1.0.0"));
+    let minisig = fs::read_to_string(signature::minisig_path(&file)).unwrap();
+    let mut lines: Vec<String> = minisig.lines().map(String::from).collect();
+    let at = lines[1].len() - 5;
+    let flipped = if &lines[1][at..=at] == "A" { "B" } else { "A" };
+    lines[1].replace_range(at..=at, flipped);
+    fs::write(signature::minisig_path(&file), lines.join("\n")).unwrap();
+    let output = once(&file, &public);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(error_code(&output), "signature_invalid");
+}
+
+#[test]
+fn test_an_invalid_public_key_fails_startup() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    fs::write(&file, config("TestApp", "1.0.0")).unwrap();
+    let public = dir.path().join("config.pub");
+    fs::write(
+        &public,
+        "untrusted comment: minisign public key\nnot base64!\n",
+    )
+    .unwrap();
+
+    let output = once(&file, &public);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Invalid public key"), "{stderr}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_an_unsigned_reload_keeps_the_last_valid_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let (key, public) = keys(dir.path());
+    let file = dir.path().join("config.json");
+    write_signed(&file, &key, &config("TestApp", "1.0.0"));
+
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", file.to_str().unwrap(), "--interval", "1"])
+            .args([
+                "--verify-signature",
+                "--public-key",
+                public.to_str().unwrap(),
+            ])
+            .args(["--output", "json"]),
+    );
+    watch.wait_for_event("loaded", 1);
+    // Signed by another key
+    write_signed(&file, &signing_key(), &config("TestApp", "2.0.0"));
+    watch.until("the rejected signature", |watch| {
+        named(&watch.events(), "load_failed")
+            .iter()
+            .any(|event| event["error"]["code"] == "signature_invalid")
+    });
+    let output = watch.stop();
+    assert!(output.status.success());
+
+    let events = events(&output.stdout);
+    assert_eq!(named(&events, "loaded").len(), 1, "{events:?}");
+}
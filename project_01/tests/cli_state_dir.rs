@@ -0,0 +1,111 @@
+// Runs the real binary with --state-dir across two runs: the second one
+// starts on a broken file and serves the configuration saved by the first,
+// until the file is fixed.
+
+mod common;
+
+use common::{Watch, config, config_watcher, events, named, read};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn watch(file: &Path, state: &Path) -> Command {
+    let mut command = config_watcher();
+    command
+        .args(["-f", file.to_str().unwrap(), "--interval", "1"])
+        .arg("--lock-dir")
+        .arg(state)
+        .arg("--state-dir")
+        .arg(state)
+        .args(["--output", "json"]);
+    command
+}
+
+#[cfg(unix)]
+#[test]
+fn test_restart_on_a_broken_file_restores_the_last_good() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    let status = dir.path().join("status.json");
+
+    // First run: two versions, the second one saved
+    fs::write(&file, config("First", "1.0.0")).unwrap();
+    let first = Watch::spawn(&mut watch(&file, state.path()));
+    first.wait_for_event("loaded", 1);
+    fs::write(&file, config("Second", "1.0.0")).unwrap();
+    first.wait_for_event("loaded", 2);
+    let output = first.stop();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(named(&events(&output.stdout), "loaded").len(), 2);
+
+    // Second run, on a broken file
+    fs::write(&file, "{ invalid json }").unwrap();
+    let second = Watch::spawn(watch(&file, state.path()).arg("--status-file").arg(&status));
+    second.until("the restored configuration", |_| {
+        read(dir.path(), "status.json").contains("restored_at")
+    });
+
+    // Served, and reported as restored meanwhile
+    let health = config_watcher()
+        .args(["healthcheck", "--status-file", status.to_str().unwrap()])
+        .output()
+        .unwrap();
+    let verdict = String::from_utf8_lossy(&health.stdout);
+    assert_eq!(health.status.code(), Some(0), "{health:?}");
+    assert!(verdict.contains("restored from"), "{verdict}");
+    let record: Value = serde_json::from_str(&fs::read_to_string(&status).unwrap()).unwrap();
+    assert_eq!(record["health"], "degraded");
+    assert!(record["files"][0]["restored_at"].is_string(), "{record}");
+
+    fs::write(&file, config("Third", "1.0.0")).unwrap();
+    second.wait_for_event("loaded", 3);
+    let output = second.stop();
+    assert!(output.status.success(), "{output:?}");
+    let events = events(&output.stdout);
+
+    let loaded = named(&events, "loaded");
+    assert_eq!(loaded.len(), 2, "{events:?}");
+    assert_eq!(loaded[0]["summary"]["app_name"], "Second");
+    assert_eq!(loaded[0]["version"], 2);
+    assert!(loaded[0]["restored"]["saved_at"].is_string(), "{events:?}");
+    assert_eq!(loaded[1]["summary"]["app_name"], "Third");
+    assert_eq!(loaded[1]["version"], 3);
+    assert!(loaded[1].get("restored").is_none(), "{events:?}");
+    assert_eq!(named(&events, "switched_over").len(), 1, "{events:?}");
+}
+
+#[test]
+fn test_corrupted_snapshot_is_ignored_with_a_warning() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.json");
+    fs::write(&file, config("First", "1.0.0")).unwrap();
+
+    let run = || {
+        config_watcher()
+            .args(["-f", file.to_str().unwrap(), "--once", "--output", "json"])
+            .arg("--state-dir")
+            .arg(state.path())
+            .output()
+            .unwrap()
+    };
+    assert!(run().status.success());
+    let snapshots: Vec<_> = fs::read_dir(state.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(snapshots.len(), 1, "{snapshots:?}");
+    fs::write(&snapshots[0], "{ truncated").unwrap();
+
+    fs::write(&file, "{ invalid json }").unwrap();
+    let output = run();
+    assert_ne!(output.status.code(), Some(0), "{output:?}");
+    assert!(named(&events(&output.stdout), "loaded").is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("last good configuration ignored"),
+        "{stderr}"
+    );
+}
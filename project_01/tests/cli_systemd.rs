@@ -0,0 +1,116 @@
+// Runs the real binary against a fake systemd notify socket: readiness at
+// startup, a SIGHUP reload, watchdog pings and shutdown.
+#![cfg(all(unix, feature = "systemd"))]
+
+mod common;
+
+use common::{Watch, config_watcher, send};
+use std::fs;
+use std::os::unix::net::UnixDatagram;
+use std::time::Duration;
+
+/// The next notification that is not a watchdog ping, counting the pings
+fn next(socket: &UnixDatagram, pings: &mut usize) -> String {
+    let mut buffer = [0; 1024];
+    loop {
+        let len = socket.recv(&mut buffer).expect("no notification");
+        let message = String::from_utf8_lossy(&buffer[..len]).into_owned();
+        if message == "WATCHDOG=1" {
+            *pings += 1;
+        } else {
+            return message;
+        }
+    }
+}
+
+#[test]
+fn test_notifications_for_startup_reload_and_shutdown() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    let notify = dir.path().join("notify");
+    let socket = UnixDatagram::bind(&notify).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+
+    // No tick during the test: only SIGHUP reloads
+    // The watchdog is pinged until --max-duration
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "60"])
+            .args(["--max-duration", "3s"])
+            .env("NOTIFY_SOCKET", &notify)
+            .env("WATCHDOG_USEC", "500000"),
+    );
+    let mut pings = 0;
+
+    assert_eq!(next(&socket, &mut pings), "STATUS=starting");
+    let ready = next(&socket, &mut pings);
+    assert!(
+        ready.starts_with("READY=1\nSTATUS=serving v1.0.0, last reload "),
+        "{ready}"
+    );
+
+    // Edited after the check that follows the initial load, so only
+    // picked up on SIGHUP
+    std::thread::sleep(Duration::from_millis(500));
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "2.0.0" }"#).unwrap();
+    send("-HUP", watch.id());
+    assert_eq!(next(&socket, &mut pings), "RELOADING=1\nSTATUS=reloading");
+    let reloaded = next(&socket, &mut pings);
+    assert!(
+        reloaded.starts_with("READY=1\nSTATUS=serving v2.0.0, last reload "),
+        "{reloaded}"
+    );
+
+    assert_eq!(next(&socket, &mut pings), "STOPPING=1\nSTATUS=stopping");
+    let output = watch.wait();
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout
+            .matches("Reload requested by SIGHUP, reloading...")
+            .count(),
+        1
+    );
+    // Every 250ms for 3s
+    assert!(pings >= 5, "{pings} watchdog pings");
+}
+
+#[test]
+fn test_require_initial_delays_readiness() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, "{ invalid json }").unwrap();
+    let notify = dir.path().join("notify");
+    let socket = UnixDatagram::bind(&notify).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .arg("--require-initial")
+            .env("NOTIFY_SOCKET", &notify),
+    );
+    let mut pings = 0;
+
+    assert_eq!(next(&socket, &mut pings), "STATUS=starting");
+    assert_eq!(
+        next(&socket, &mut pings),
+        "STATUS=no valid configuration: parsing failing"
+    );
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    let ready = next(&socket, &mut pings);
+    assert!(
+        ready.starts_with("READY=1\nSTATUS=serving v1.0.0"),
+        "{ready}"
+    );
+    let output = watch.stop();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(next(&socket, &mut pings), "STOPPING=1\nSTATUS=stopping");
+    // No WATCHDOG_USEC, no pings
+    assert_eq!(pings, 0);
+}
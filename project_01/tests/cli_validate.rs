@@ -0,0 +1,366 @@
+// Exercises `config-watcher validate` and --error-format through the real binary.
+
+use assert_cmd::Command;
+use serde_json::Value;
+use std::fs;
+use tempfile::NamedTempFile;
+
+fn validate(contents: &str, extra: &[&str]) -> (NamedTempFile, std::process::Output) {
+    let file = NamedTempFile::new().unwrap();
+    fs::write(file.path(), contents).unwrap();
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["validate", "-f", file.path().to_str().unwrap()])
+        .args(extra)
+        .env_remove("GITHUB_ACTIONS")
+        .output()
+        .unwrap();
+    (file, output)
+}
+
+fn json_report(stdout: &[u8]) -> Value {
+    let stdout = String::from_utf8_lossy(stdout);
+    assert_eq!(stdout.lines().count(), 1, "{stdout}");
+    serde_json::from_str(&stdout).unwrap()
+}
+
+#[test]
+fn test_valid_file() {
+    let (_file, output) = validate(r#"{ "app_name": "A", "version": "1.0.0" }"#, &[]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("is valid"));
+}
+
+#[test]
+fn test_color_goes_through_the_style() {
+    let valid = r#"{ "app_name": "A", "version": "1.0.0" }"#;
+    // Piped: ASCII tags
+    let (_file, output) = validate(valid, &["--color", "auto"]);
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("[OK] "));
+    let (_file, output) = validate(valid, &["--color", "always"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("✅ \x1b[32m"), "{stdout}");
+
+    let (_file, output) = validate("{", &["--color", "never"]);
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("[ERR] "));
+}
+
+#[test]
+fn test_parse_error_as_json() {
+    let (file, output) = validate(
+        "{\n  \"app_name\": \"A\",\n  \"version\": }\n",
+        &["--error-format", "json"],
+    );
+    assert_eq!(output.status.code(), Some(4));
+
+    let report = json_report(&output.stdout);
+    assert_eq!(report["code"], "invalid_json");
+    assert_eq!(report["file"], file.path().to_str().unwrap());
+    assert!(report["message"].as_str().unwrap().contains("parse"));
+    let findings = report["findings"].as_array().unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0]["severity"], "error");
+    assert_eq!(findings[0]["line"], 3);
+    assert_eq!(findings[0]["column"], 14);
+}
+
+#[test]
+fn test_validation_failure_as_json() {
+    let (_file, output) = validate(
+        r#"{ "app_name": "", "version": "1",
+             "server": { "host": "h", "port": 80, "keep_alive": "10s", "request_timeout": "20s" } }"#,
+        &["--error-format", "json"],
+    );
+    assert_eq!(output.status.code(), Some(5));
+
+    let report = json_report(&output.stdout);
+    assert_eq!(report["code"], "validation_failed");
+    let findings: Vec<(&str, &str)> = report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| {
+            for key in ["path", "severity", "message", "line", "column"] {
+                assert!(f.get(key).is_some(), "missing '{key}' in {f}");
+            }
+            (f["path"].as_str().unwrap(), f["severity"].as_str().unwrap())
+        })
+        .collect();
+    assert_eq!(
+        findings,
+        [
+            ("app_name", "error"),
+            ("version", "error"),
+            ("server.request_timeout", "warning")
+        ]
+    );
+}
+
+#[test]
+fn test_missing_file_code() {
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args([
+            "validate",
+            "-f",
+            "/nonexistent/app.json",
+            "--error-format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(json_report(&output.stdout)["code"], "file_not_found");
+}
+
+#[test]
+fn test_invalid_utf8_is_a_read_error() {
+    let file = NamedTempFile::new().unwrap();
+    fs::write(
+        file.path(),
+        b"{ \"app_name\": \"A\xff\", \"version\": \"1.0.0\" }",
+    )
+    .unwrap();
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["validate", "-f", file.path().to_str().unwrap()])
+        .args(["--error-format", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(json_report(&output.stdout)["code"], "read_error");
+}
+
+#[test]
+fn test_watch_once_reports_json_on_stderr() {
+    let file = NamedTempFile::new().unwrap();
+    fs::write(file.path(), r#"{ "app_name": "", "version": "1.0.0" }"#).unwrap();
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["-f", file.path().to_str().unwrap(), "--once"])
+        .args(["--error-format", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(5));
+
+    let report = json_report(&output.stderr);
+    assert_eq!(report["code"], "validation_failed");
+    assert_eq!(report["findings"][0]["path"], "app_name");
+}
+
+#[test]
+fn test_github_annotations() {
+    let (file, output) = validate(
+        r#"{ "app_name": "", "version": "1.0.0",
+             "server": { "host": "h", "port": 80, "keep_alive": "10s", "request_timeout": "20s" } }"#,
+        &["--output-annotations", "github"],
+    );
+    assert_eq!(output.status.code(), Some(5));
+
+    let path = file.path().display();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            format!("::error file={path},line=1::app_name: cannot be empty"),
+            format!(
+                "::warning file={path},line=1::server.request_timeout: should be shorter than server.keep_alive"
+            ),
+        ]
+    );
+    // The human-readable report is still there, on stderr
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be empty"));
+}
+
+#[test]
+fn test_github_annotations_are_detected() {
+    let file = NamedTempFile::new().unwrap();
+    fs::write(file.path(), "{\n  \"app_name\": }").unwrap();
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["validate", "-f", file.path().to_str().unwrap()])
+        .env("GITHUB_ACTIONS", "true")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let prefix = format!(
+        "::error file={},line=2,col=15::<root>: ",
+        file.path().display()
+    );
+    assert!(stdout.starts_with(&prefix), "{stdout}");
+    assert_eq!(stdout.lines().count(), 1);
+}
+
+/// Runs `validate` on `contents` saved as `config.json`, with a relative
+/// path so the SARIF artifact URI is stable, and returns the SARIF log
+fn validate_sarif(contents: &str) -> (std::process::Output, Value) {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.json"), contents).unwrap();
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .current_dir(dir.path())
+        .args([
+            "validate",
+            "-f",
+            "config.json",
+            "--report-sarif",
+            "out.sarif",
+        ])
+        .env_remove("GITHUB_ACTIONS")
+        .output()
+        .unwrap();
+    let sarif = fs::read_to_string(dir.path().join("out.sarif")).unwrap();
+    (output, serde_json::from_str(&sarif).unwrap())
+}
+
+fn assert_valid_sarif(log: &Value) {
+    let schema: Value =
+        serde_json::from_str(include_str!("fixtures/sarif-schema-2.1.0.json")).unwrap();
+    let validator = jsonschema::validator_for(&schema).unwrap();
+    let errors: Vec<String> = validator.iter_errors(log).map(|e| e.to_string()).collect();
+    assert!(errors.is_empty(), "{errors:#?}");
+}
+
+/// Regenerate with `UPDATE_GOLDEN=1 cargo test -p config_watcher --test cli_validate`
+#[test]
+fn test_sarif_report_matches_snapshot() {
+    let (output, log) = validate_sarif(
+        "{\n  \"app_name\": \"\",\n  \"version\": \"1.0.0\",\n  \"server\": { \"host\": \"h\", \"port\": 80, \"keep_alive\": \"10s\", \"request_timeout\": \"20s\" }\n}\n",
+    );
+    assert_eq!(output.status.code(), Some(5));
+    assert_valid_sarif(&log);
+
+    let snapshot_path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/snapshots/validate.sarif"
+    );
+    let mut generated = serde_json::to_string_pretty(&log).unwrap();
+    generated.push('\n');
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(snapshot_path, &generated).unwrap();
+    }
+    let snapshot = fs::read_to_string(snapshot_path).unwrap_or_default();
+    assert!(
+        snapshot == generated,
+        "tests/snapshots/validate.sarif is out of date:\n{generated}"
+    );
+}
+
+#[test]
+fn test_sarif_report_is_written_for_valid_and_unparsable_files() {
+    let (output, log) = validate_sarif(r#"{ "app_name": "A", "version": "1.0.0" }"#);
+    assert!(output.status.success());
+    assert_valid_sarif(&log);
+    assert_eq!(log["runs"][0]["results"], serde_json::json!([]));
+
+    let (output, log) = validate_sarif("{\n  \"app_name\": }");
+    assert_eq!(output.status.code(), Some(4));
+    assert_valid_sarif(&log);
+    let result = &log["runs"][0]["results"][0];
+    assert_eq!(result["ruleId"], "invalid_json");
+    assert_eq!(
+        result["locations"][0]["physicalLocation"]["region"]["startLine"],
+        2
+    );
+}
+
+/// Pipes `stdin` into `validate --stdin --assume-path config/app.json`, run
+/// from a directory holding `config/present.pem` and a different file on disk
+fn validate_stdin(stdin: impl Into<Vec<u8>>, extra: &[&str]) -> std::process::Output {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("config")).unwrap();
+    fs::write(dir.path().join("config/present.pem"), "cert").unwrap();
+    fs::write(dir.path().join("config/app.json"), "not what is staged").unwrap();
+    Command::cargo_bin("config_watcher")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["validate", "--stdin", "--assume-path", "config/app.json"])
+        .args(extra)
+        .env_remove("GITHUB_ACTIONS")
+        .write_stdin(stdin)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_stdin_resolves_references_against_the_assumed_path() {
+    let staged = |cert: &str| {
+        format!(
+            r#"{{ "app_name": "A", "version": "1.0.0",
+                 "server": {{ "host": "h", "port": 8080, "cert_file": "{cert}" }} }}"#
+        )
+    };
+
+    let output = validate_stdin(staged("present.pem"), &[]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("config/app.json is valid"));
+
+    let output = validate_stdin(staged("missing.pem"), &["--error-format", "json"]);
+    assert_eq!(output.status.code(), Some(5));
+    let report = json_report(&output.stdout);
+    assert_eq!(report["code"], "validation_failed");
+    assert_eq!(report["file"], "config/app.json");
+    assert_eq!(report["findings"][0]["path"], "server.cert_file");
+    let message = report["findings"][0]["message"].as_str().unwrap();
+    assert!(message.contains("config/missing.pem"), "{message}");
+}
+
+#[test]
+fn test_stdin_failures_name_the_assumed_path() {
+    let output = validate_stdin(r#"{ "app_name": "", "version": "1.0.0" }"#, &[]);
+    assert_eq!(output.status.code(), Some(5));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("config/app.json"), "{stderr}");
+    assert!(stderr.contains("app_name: cannot be empty"), "{stderr}");
+
+    let code = |stdin: Vec<u8>, extra: &[&str]| {
+        let mut args = vec!["--error-format", "json"];
+        args.extend(extra);
+        let output = validate_stdin(stdin, &args);
+        let report = json_report(&output.stdout)["code"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        (report, output.status.code())
+    };
+    assert_eq!(
+        code(b" \n".to_vec(), &[]),
+        ("empty_input".to_string(), Some(4))
+    );
+    assert_eq!(
+        code(vec![b' '; 8 * 1024 * 1024 + 1], &[]),
+        ("too_large".to_string(), Some(3))
+    );
+
+    let output = validate_stdin("", &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No content on stdin"), "{stderr}");
+}
+
+#[test]
+fn test_stdin_format_comes_from_the_assumed_path() {
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args([
+            "validate",
+            "--stdin",
+            "--assume-path",
+            "app.yaml",
+            "--error-format",
+            "json",
+        ])
+        .write_stdin(r#"{ "app_name": "A", "version": "1.0.0" }"#)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(json_report(&output.stdout)["code"], "unsupported_format");
+
+    // --stdin without --assume-path is a usage error
+    Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["validate", "--stdin"])
+        .assert()
+        .code(2);
+}
@@ -0,0 +1,72 @@
+// Runs the real binary and sends it SIGUSR2: each signal moves to the next
+// verbosity (quiet, normal, verbose, debug, quiet...), announced on the
+// output; the per-check lines of -v and the debug diagnostics start and stop
+// with it.
+#![cfg(unix)]
+
+mod common;
+
+use common::{config_watcher, eventually, read, send};
+use std::fs::{self, File};
+use std::path::Path;
+use std::process::Child;
+use std::time::Duration;
+
+const CONFIG: &str = r#"{ "app_name": "Chatty", "version": "1.0.0" }"#;
+
+/// The per-check line of -v
+const CHECKED: &str = "Checked, unchanged";
+
+fn watch(dir: &Path) -> Child {
+    config_watcher()
+        .current_dir(dir)
+        .args(["-f", "config.json", "--interval", "1", "--no-lock"])
+        .env_remove("RUST_LOG")
+        .stdout(File::create(dir.join("out.log")).unwrap())
+        .stderr(File::create(dir.join("err.log")).unwrap())
+        .spawn()
+        .unwrap()
+}
+
+#[test]
+fn test_sigusr2_cycles_the_verbosity() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    fs::write(dir.join("config.json"), CONFIG).unwrap();
+    let mut child = watch(dir);
+    let stdout = || read(dir, "out.log");
+    eventually("initial load", || {
+        stdout().contains("Initial configuration loaded")
+    });
+    std::thread::sleep(Duration::from_millis(2500));
+    assert!(!stdout().contains(CHECKED), "{}", stdout());
+
+    // Verbose: the per-check lines, and debug diagnostics
+    send("-USR2", child.id());
+    eventually("verbose", || {
+        stdout().contains("Verbosity set to verbose by SIGUSR2")
+    });
+    eventually("per-check lines", || stdout().contains(CHECKED));
+    send("-USR1", child.id());
+    eventually("dump", || stdout().contains("Status dump"));
+    send("-USR1", child.id());
+    eventually("debug line", || {
+        read(dir, "err.log").contains("status dump skipped")
+    });
+
+    // Debug, then back to quiet: no more per-check lines
+    send("-USR2", child.id());
+    eventually("debug", || {
+        stdout().contains("Verbosity set to debug by SIGUSR2")
+    });
+    send("-USR2", child.id());
+    eventually("quiet", || {
+        stdout().contains("Verbosity set to quiet by SIGUSR2")
+    });
+    let checks = stdout().matches(CHECKED).count();
+    std::thread::sleep(Duration::from_millis(2500));
+    assert_eq!(stdout().matches(CHECKED).count(), checks, "{}", stdout());
+
+    send("-TERM", child.id());
+    assert!(child.wait().unwrap().success());
+}
@@ -0,0 +1,1140 @@
+// Exercises startup behavior of the watch command through the real binary.
+
+mod common;
+
+use assert_cmd::Command;
+use common::Watch;
+use std::fs;
+
+#[test]
+fn test_uncompilable_schema_aborts_startup() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let schema = dir.path().join("schema.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    fs::write(&schema, r#"{ "type": 42 }"#).unwrap();
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["-f", config.to_str().unwrap()])
+        .args(["--schema", schema.to_str().unwrap()])
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Failed to compile JSON Schema"), "{stderr}");
+}
+
+#[test]
+fn test_bad_override_aborts_startup() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["-f", config.to_str().unwrap()])
+        .args(["--override", "server.prot=9090"])
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Unknown field 'server.prot'"), "{stderr}");
+    assert!(stderr.contains("server.port"), "{stderr}");
+}
+
+#[test]
+fn test_override_precedence_file_env_cli() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(
+        &config,
+        r#"{ "app_name": "TestApp", "version": "1.0.0",
+             "server": { "host": "localhost", "port": 80 } }"#,
+    )
+    .unwrap();
+
+    let summary = |cli: &[&str], expected: &str| {
+        Watch::spawn(
+            common::config_watcher()
+                .args(["-f", config.to_str().unwrap()])
+                .args(cli)
+                .env("CW_OVERRIDE__SERVER__PORT", "8000"),
+        )
+        .wait_for(expected);
+    };
+
+    summary(
+        &[],
+        "localhost:8000 (SSL: true, max_connections: 1024, request_timeout: 30s) (override)",
+    );
+    summary(
+        &["--override", "server.port=9090"],
+        "localhost:9090 (SSL: true, max_connections: 1024, request_timeout: 30s) (override)",
+    );
+}
+
+#[test]
+fn test_bad_env_override_aborts_startup() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["-f", config.to_str().unwrap()])
+        .env("CW_OVERRIDE__SERVER____PORT", "1")
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("CW_OVERRIDE__SERVER____PORT"), "{stderr}");
+}
+
+#[test]
+fn test_config_file_is_discovered_when_omitted() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("config")).unwrap();
+    fs::write(
+        dir.path().join("config/config.json"),
+        r#"{ "app_name": "Discovered", "version": "1.0.0" }"#,
+    )
+    .unwrap();
+
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .current_dir(dir.path())
+            .env("XDG_CONFIG_HOME", dir.path().join("xdg")),
+    );
+    watch.wait_for("App: Discovered");
+
+    let stdout = watch.stdout();
+    assert!(
+        stdout.contains("Using configuration file: ./config/config.json"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn test_multiple_files_attribute_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let a = dir.path().join("a.json");
+    let b = dir.path().join("b.json");
+    let write = |path: &std::path::Path, app: &str, version: &str| {
+        fs::write(
+            path,
+            format!(r#"{{ "app_name": "{app}", "version": "{version}" }}"#),
+        )
+        .unwrap();
+    };
+    write(&a, "Alpha", "1.0.0");
+    write(&b, "Beta", "1.0.0");
+
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", a.to_str().unwrap(), "-f", b.to_str().unwrap()])
+            .args(["--interval", "1"]),
+    );
+    watch.wait_for("[a.json]    App: Alpha v1.0.0");
+    watch.wait_for("[b.json]    App: Beta v1.0.0");
+    write(&a, "Alpha", "2.0.0");
+    watch.wait_for("[a.json]    App: Alpha v2.0.0");
+    write(&b, "Beta", "3.0.0");
+    watch.wait_for("[b.json]    App: Beta v3.0.0");
+
+    let stdout = watch.stdout();
+    assert!(!stdout.contains("[b.json]    App: Alpha"), "{stdout}");
+    assert!(!stdout.contains("[a.json]    App: Beta"), "{stdout}");
+}
+
+#[test]
+fn test_once_reports_validity_through_exit_code() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let run = || {
+        Command::cargo_bin("config_watcher")
+            .unwrap()
+            .args(["-f", config.to_str().unwrap(), "--once"])
+            .timeout(std::time::Duration::from_secs(10))
+            .output()
+            .unwrap()
+    };
+
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    let output = run();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("App: TestApp v1.0.0"), "{stdout}");
+    assert!(!stdout.contains("Press Ctrl+C"), "{stdout}");
+
+    fs::write(&config, r#"{ "app_name": "", "version": "1.0.0" }"#).unwrap();
+    let output = run();
+    assert_eq!(output.status.code(), Some(5));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("app_name: cannot be empty"), "{stderr}");
+}
+
+#[test]
+fn test_max_duration_exits_on_its_own() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["-f", config.to_str().unwrap()])
+        .args(["--max-duration", "1s", "--max-duration-exit-code", "3"])
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Maximum duration reached"), "{stdout}");
+}
+
+#[test]
+fn test_fail_fast_exits_on_invalid_reload() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+
+    let watch = Watch::spawn(common::config_watcher().args([
+        "-f",
+        config.to_str().unwrap(),
+        "--interval",
+        "1",
+        "--fail-fast",
+    ]));
+    watch.wait_for("Initial configuration loaded");
+    fs::write(&config, "{ invalid json }").unwrap();
+    let output = watch.wait();
+
+    assert_eq!(output.status.code(), Some(6));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("failed with --fail-fast"), "{stderr}");
+    assert!(
+        stderr.contains("last good configuration loaded at 20"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("Failed to parse JSON configuration"),
+        "{stderr}"
+    );
+}
+
+fn assert_keys(event: &serde_json::Value, keys: &[&str]) {
+    for key in ["timestamp", "event"].iter().chain(keys) {
+        assert!(event.get(key).is_some(), "missing '{key}' in {event}");
+    }
+}
+
+#[test]
+fn test_once_json_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let run = || {
+        Command::cargo_bin("config_watcher")
+            .unwrap()
+            .args(["-f", config.to_str().unwrap(), "--once", "--output", "json"])
+            .timeout(std::time::Duration::from_secs(10))
+            .output()
+            .unwrap()
+    };
+
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    let output = run();
+    assert!(output.status.success());
+    let events = common::events(&output.stdout);
+    assert_eq!(events.len(), 1, "{events:?}");
+    assert_eq!(events[0]["event"], "loaded");
+    assert_keys(&events[0], &["file", "version", "initial", "summary"]);
+    assert_eq!(events[0]["summary"]["app_name"], "TestApp");
+
+    fs::write(&config, "{ invalid json }").unwrap();
+    let output = run();
+    assert_eq!(output.status.code(), Some(4));
+    let events = common::events(&output.stdout);
+    assert_eq!(events.len(), 1, "{events:?}");
+    assert_eq!(events[0]["event"], "load_failed");
+    assert_keys(&events[0], &["file", "initial", "error"]);
+    assert!(events[0]["error"]["chain"].as_array().unwrap().len() > 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_watch_json_output_covers_the_lifecycle() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let secret = "postgres://admin:hunter2@db/app";
+    let doc = |port: u16| {
+        format!(
+            r#"{{ "app_name": "TestApp", "version": "1.0.0",
+                 "server": {{ "host": "localhost", "port": {port} }},
+                 "database": {{ "connection_string": "{secret}-{port}" }} }}"#
+        )
+    };
+    fs::write(&config, doc(8080)).unwrap();
+
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--output", "json"]),
+    );
+    watch.wait_for_event("loaded", 1);
+    fs::write(&config, doc(9090)).unwrap();
+    watch.wait_for_event("loaded", 2);
+    fs::write(&config, "{ invalid json }").unwrap();
+    watch.until("failed reload", |watch| {
+        !common::named(&watch.events(), "load_failed").is_empty()
+    });
+    let output = watch.stop();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("hunter2"), "{stdout}");
+
+    let events = common::events(&output.stdout);
+    let find = |name: &str| common::named(&events, name);
+
+    assert_keys(find("started")[0], &["file", "interval_secs", "overrides"]);
+
+    let loaded = find("loaded");
+    assert_eq!(loaded.len(), 2, "{stdout}");
+    for event in &loaded {
+        assert_keys(event, &["file", "version", "initial", "summary"]);
+    }
+    assert_eq!(loaded[0]["version"], 1);
+    assert_eq!(loaded[1]["version"], 2);
+    assert_eq!(loaded[1]["summary"]["server"]["port"], 9090);
+    let diff = loaded[1]["diff"].as_array().unwrap();
+    assert!(diff.iter().any(|change| change["path"] == "server.port"
+        && change["old"] == 8080
+        && change["new"] == 9090));
+    assert!(
+        diff.iter()
+            .any(|change| change["path"] == "database.connection_string")
+    );
+    let patch = loaded[1]["patch"].as_array().unwrap();
+    assert_eq!(patch.len(), diff.len());
+    assert!(
+        patch
+            .iter()
+            .any(|op| op["op"] == "replace" && op["path"] == "/server/port" && op["value"] == 9090)
+    );
+    assert!(
+        patch
+            .iter()
+            .any(|op| op["path"] == "/database/connection_string" && op["value"] == "<redacted>")
+    );
+
+    assert!(!find("change_detected").is_empty(), "{stdout}");
+    let failed = find("load_failed");
+    assert!(!failed.is_empty(), "{stdout}");
+    assert_keys(failed[0], &["file", "initial", "retrying", "error"]);
+
+    let shutdown = find("shutdown");
+    assert_eq!(shutdown.len(), 1, "{stdout}");
+    assert_keys(shutdown[0], &["reason", "files"]);
+    assert_eq!(shutdown[0]["reason"], "signal");
+    assert_eq!(events.last().unwrap()["event"], "shutdown");
+}
+
+/// Runs a watch through an unchanged check, one good and one bad reload,
+/// with extra args, and stops it with SIGTERM
+#[cfg(unix)]
+fn watch_with_reloads(extra: &[&str], env: &[(&str, &str)]) -> std::process::Output {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, common::config("TestApp", "1.0.0")).unwrap();
+    // Rewritten after every check: the progress whatever the verbosity
+    let status = || {
+        serde_json::from_str::<serde_json::Value>(&common::read(dir.path(), "status.json"))
+            .unwrap_or_default()
+    };
+
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .arg("--status-file")
+            .arg(dir.path().join("status.json"))
+            .args(extra)
+            .envs(env.iter().copied()),
+    );
+    watch.until("initial load", |_| status()["files"][0]["version"] == 1);
+    let loaded = status()["updated_at"].clone();
+    watch.until("unchanged check", |_| status()["updated_at"] != loaded);
+    fs::write(&config, common::config("TestApp", "2.0.0")).unwrap();
+    watch.until("reload", |_| status()["files"][0]["version"] == 2);
+    fs::write(&config, "{ invalid json }").unwrap();
+    watch.until("failed reload", |_| {
+        status()["files"][0]["failures"].as_u64() >= Some(1)
+    });
+    watch.stop()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_on_change_runs_after_a_reload() {
+    let output = watch_with_reloads(
+        &[
+            "--color",
+            "never",
+            "--timestamp",
+            "off",
+            "--on-change",
+            r#"echo "v$CONFIG_VERSION $CHANGED_PATHS"; grep -c 2.0.0; exit 4"#,
+        ],
+        &[],
+    );
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for line in [
+        "[WARN] On-change command exited with status 4 after ",
+        "   | v2 version",
+        "   | 1",
+    ] {
+        assert!(stderr.contains(line), "missing '{line}' in {stderr}");
+    }
+    // Neither the initial load nor the failed reload runs it
+    assert_eq!(stderr.matches("On-change command").count(), 1, "{stderr}");
+}
+
+/// Without a notification tool on the PATH, one warning and the watch goes on
+#[cfg(all(unix, not(target_os = "macos"), feature = "desktop-notify"))]
+#[test]
+fn test_notify_desktop_without_a_daemon() {
+    let empty = tempfile::tempdir().unwrap();
+    let output = watch_with_reloads(
+        &["--notify-desktop", "--notify-interval", "0s"],
+        &[("PATH", empty.path().to_str().unwrap())],
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("v2.0.0"), "{stdout}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.matches("notifier failed").count(), 1, "{stderr}");
+    assert!(stderr.contains("cannot run notify-send"), "{stderr}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_plain_output_is_ascii() {
+    for (extra, env) in [
+        (&["--color", "never"][..], &[][..]),
+        (&[][..], &[("NO_COLOR", "1")][..]),
+    ] {
+        let output = watch_with_reloads(extra, env);
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        for text in [&stdout, &stderr] {
+            assert!(text.is_ascii(), "{text}");
+            assert!(!text.contains('\x1b'), "{text}");
+        }
+        for line in [
+            "[WATCH] Watching configuration file:",
+            "[OK] Initial configuration loaded successfully",
+            "[CHANGE] File change detected, reloading...",
+            "[UPDATE] Configuration has been updated (1 change)",
+            "     version: \"1.0.0\" -> \"2.0.0\"",
+            "[STOP] Shutting down gracefully",
+        ] {
+            assert!(stdout.contains(line), "missing '{line}' in {stdout}");
+        }
+        assert!(
+            stderr.contains("[ERR] Configuration reload failed"),
+            "{stderr}"
+        );
+    }
+}
+
+#[test]
+fn test_color_always_uses_emoji_and_escapes() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args([
+            "-f",
+            config.to_str().unwrap(),
+            "--once",
+            "--color",
+            "always",
+        ])
+        .env("NO_COLOR", "1")
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("✅ \x1b[32mInitial configuration loaded"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn test_timestamp_formats() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+
+    let first_line = |flags: &[&str]| {
+        let output = Command::cargo_bin("config_watcher")
+            .unwrap()
+            .args(["-f", config.to_str().unwrap(), "--once", "--color", "never"])
+            .args(flags)
+            .timeout(std::time::Duration::from_secs(10))
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        stdout.lines().next().unwrap_or_default().to_string()
+    };
+    let prefix = |line: &str| line.split(' ').next().unwrap_or_default().to_string();
+
+    let rfc3339 = prefix(&first_line(&["--utc"]));
+    assert!(
+        rfc3339.len() == 24 && rfc3339.as_bytes()[10] == b'T' && rfc3339.ends_with('Z'),
+        "{rfc3339}"
+    );
+    let time_only = prefix(&first_line(&["--utc", "--timestamp", "time-only"]));
+    assert!(
+        time_only.len() == 12 && time_only.as_bytes()[2] == b':',
+        "{time_only}"
+    );
+    let relative = prefix(&first_line(&["--timestamp", "relative"]));
+    assert!(
+        relative.starts_with('+') && relative.ends_with('s'),
+        "{relative}"
+    );
+    let off = first_line(&["--timestamp", "off"]);
+    assert!(off.starts_with("[OK] Initial"), "{off}");
+}
+
+#[test]
+fn test_unknown_time_zone_falls_back_to_utc() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    let run = |output: &str| {
+        Command::cargo_bin("config_watcher")
+            .unwrap()
+            .args(["-f", config.to_str().unwrap(), "--once", "--color", "never"])
+            .args(["--output", output])
+            .env("TZ", "Invalid/Zone")
+            .timeout(std::time::Duration::from_secs(10))
+            .output()
+            .unwrap()
+    };
+
+    // Local text timestamps: a warning, then UTC
+    let text = run("text");
+    assert!(text.status.success());
+    let stderr = String::from_utf8_lossy(&text.stderr);
+    assert!(
+        stderr.contains("Cannot determine the local time zone"),
+        "{stderr}"
+    );
+    let stdout = String::from_utf8_lossy(&text.stdout);
+    let stamp = stdout.split(' ').next().unwrap_or_default();
+    assert!(stamp.len() == 24 && stamp.ends_with('Z'), "{stdout}");
+
+    // JSON shows no local time: the zone is not looked up
+    let json = run("json");
+    assert!(json.status.success());
+    let stderr = String::from_utf8_lossy(&json.stderr);
+    assert!(!stderr.contains("time zone"), "{stderr}");
+}
+
+/// Asserts which of `lines` appear in the output of a run at `flags`
+#[cfg(unix)]
+fn assert_verbosity(flags: &[&str], present: &[&str], absent: &[&str]) {
+    let mut args = vec!["--color", "never"];
+    args.extend(flags);
+    let output = watch_with_reloads(&args, &[]);
+    assert!(output.status.success());
+    let all = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    for line in present {
+        assert!(all.contains(line), "{flags:?}: missing '{line}' in {all}");
+    }
+    for line in absent {
+        assert!(
+            !all.contains(line),
+            "{flags:?}: unexpected '{line}' in {all}"
+        );
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_quiet_prints_errors_and_shutdown_only() {
+    assert_verbosity(
+        &["-q"],
+        &[
+            "[ERR] Configuration reload failed",
+            "[STOP] Shutting down gracefully",
+        ],
+        &["[WATCH]", "[OK]", "Checked, unchanged"],
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_default_verbosity() {
+    assert_verbosity(
+        &[],
+        &[
+            "[OK] Initial configuration loaded",
+            "[ERR] Configuration reload failed",
+        ],
+        &[
+            "Checked, unchanged",
+            "Loaded in ",
+            "Reloaded in ",
+            "stat:",
+            "state:",
+        ],
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_verbose_adds_checks_timings_and_decisions() {
+    assert_verbosity(
+        &["-v"],
+        &[
+            "[OK] Initial configuration loaded",
+            "Checked, unchanged",
+            "Loaded in ",
+            "Reloaded in ",
+            ": stat ",
+            "after write (min ",
+            "Modification time moved",
+        ],
+        &["stat:", "state:"],
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_very_verbose_adds_stat_and_state() {
+    assert_verbosity(
+        &["-vv"],
+        &[
+            "Checked, unchanged",
+            "stat: modified=",
+            "state: valid -> failing",
+        ],
+        &[],
+    );
+}
+
+fn from_env(extra: &[&str], env: &[(&str, &str)]) -> std::process::Output {
+    let mut cmd = Command::cargo_bin("config_watcher").unwrap();
+    cmd.args(["--from-env=APP", "--color", "never"]).args(extra);
+    for (name, value) in env {
+        cmd.env(name, value);
+    }
+    cmd.output().unwrap()
+}
+
+#[test]
+fn test_from_env_builds_the_whole_config() {
+    let output = from_env(
+        &[],
+        &[
+            ("APP__APP_NAME", "FromEnv"),
+            ("APP__VERSION", "3.1.4"),
+            ("APP__SERVER__HOST", "0.0.0.0"),
+            ("APP__SERVER__PORT", "8081"),
+            ("APP__FEATURES__DARK_MODE", "true"),
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{output:?}");
+    assert!(stdout.contains("App: FromEnv v3.1.4"), "{stdout}");
+    assert!(stdout.contains("Server: 0.0.0.0:8081"), "{stdout}");
+    // Unset fields take their defaults
+    assert!(stdout.contains("Environment: development"), "{stdout}");
+    assert!(stdout.contains("Features: 1 enabled"), "{stdout}");
+}
+
+#[test]
+fn test_from_env_names_missing_and_mistyped_variables() {
+    let output = from_env(&[], &[("APP__SERVER__PORT", "8081")]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("APP__APP_NAME, APP__VERSION, APP__SERVER__HOST"),
+        "{stderr}"
+    );
+
+    let output = from_env(
+        &[],
+        &[
+            ("APP__APP_NAME", "A"),
+            ("APP__VERSION", "1.0.0"),
+            ("APP__FEATURES__DARK_MODE", "maybe"),
+        ],
+    );
+    assert_ne!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("APP__FEATURES__DARK_MODE"), "{stderr}");
+}
+
+#[test]
+fn test_settings_file_precedence() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join(".config-watcher.toml"),
+        "interval = 7\noutput = \"json\"\ncolor = \"never\"\nfail-fast = true\nbogus = 1\n",
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["watch", "--show-settings", "--interval", "9"])
+        .env("XDG_CONFIG_HOME", dir.path().join("xdg"))
+        .env("CONFIG_WATCHER_OUTPUT", "text")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for line in [
+        "# settings file: ./.config-watcher.toml",
+        "interval = 9  # command line",
+        "output = \"text\"  # environment",
+        "color = \"never\"  # settings file",
+        "fail-fast = true  # settings file",
+        "error-format = \"text\"  # default",
+        "# schema is not set",
+    ] {
+        assert!(
+            stdout.lines().any(|l| l == line),
+            "missing '{line}' in:\n{stdout}"
+        );
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown key 'bogus'"), "{stderr}");
+}
+
+#[test]
+fn test_json_log_format_keeps_stderr_parseable() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(
+        &config,
+        r#"{ "app_name": "", "version": "1.0.0", "environment": "moon" }"#,
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["-f", config.to_str().unwrap(), "--once"])
+        .args(["--log-format", "json", "--log-level", "debug"])
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(5));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<serde_json::Value> = stderr
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|e| panic!("{e}: {line}")))
+        .collect();
+    for line in &lines {
+        for key in ["timestamp", "level", "target", "message"] {
+            assert!(line.get(key).is_some(), "{key} missing: {line}");
+        }
+    }
+
+    let failure = lines
+        .iter()
+        .find(|line| line["level"] == "ERROR")
+        .unwrap_or_else(|| panic!("{stderr}"));
+    assert_eq!(failure["path"], config.display().to_string());
+    assert_eq!(failure["error.kind"], "validation_failed");
+    assert_eq!(
+        failure["findings"],
+        serde_json::json!([
+            "app_name: cannot be empty",
+            "environment: must be one of: development, staging, production"
+        ])
+    );
+    assert!(
+        lines
+            .iter()
+            .any(|line| line["message"] == "configuration read failed"
+                && line["duration_ms"].is_f64()),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_log_file_receives_diagnostics() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "", "version": "1.0.0" }"#).unwrap();
+    let log = dir.path().join("watcher.log");
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args([
+            "-f",
+            config.to_str().unwrap(),
+            "--once",
+            "--log-format",
+            "json",
+        ])
+        .args(["--log-file", log.to_str().unwrap(), "--log-max-size", "1K"])
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(5));
+    assert!(output.stderr.is_empty(), "{output:?}");
+    let logged = fs::read_to_string(&log).unwrap();
+    let line: serde_json::Value = serde_json::from_str(logged.lines().next().unwrap()).unwrap();
+    assert_eq!(line["error.kind"], "validation_failed", "{logged}");
+}
+
+#[test]
+fn test_log_file_falls_back_to_stderr() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "A", "version": "1.0.0" }"#).unwrap();
+    let log = dir.path().join("missing").join("watcher.log");
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["-f", config.to_str().unwrap(), "--once"])
+        .args(["--log-file", log.to_str().unwrap()])
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot open log file"), "{stderr}");
+    assert!(stderr.contains("logging to stderr instead"), "{stderr}");
+}
+
+#[test]
+fn test_log_target_excludes_log_file() {
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["--once", "--log-target", "syslog", "--log-file", "x.log"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot be used with"), "{stderr}");
+}
+
+/// `GET path` on `port`, the whole response
+fn scrape(port: u16, path: &str) -> String {
+    use std::io::{Read, Write};
+    let mut stream = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nHost: test\r\nConnection: close\r\n\r\n"
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+/// The value of `series` in `metrics`
+fn metric(metrics: &str, series: &str) -> u64 {
+    metrics
+        .lines()
+        .find_map(|l| l.strip_prefix(series)?.strip_prefix(' '))
+        .unwrap_or_else(|| panic!("{series} missing from {metrics}"))
+        .parse()
+        .unwrap()
+}
+
+/// The `configwatcher_reloads_total` series of `result`
+fn reloads_series(result: &str) -> String {
+    format!("configwatcher_reloads_total{{result=\"{result}\"}}")
+}
+
+#[cfg(unix)]
+#[test]
+fn test_metrics_endpoint_counts_reloads() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    let port = common::free_port();
+
+    // Served before the initial load
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--metrics-addr", &format!("127.0.0.1:{port}")]),
+    );
+    watch.wait_for("Initial configuration loaded");
+    let counted = |result: &str, at_least: u64| {
+        watch.until(result, |_| {
+            metric(&scrape(port, "/metrics"), &reloads_series(result)) >= at_least
+        });
+    };
+    fs::write(&config, "{ invalid json }").unwrap();
+    counted("parse_error", 1);
+    fs::write(&config, r#"{ "app_name": "", "version": "1.0.0" }"#).unwrap();
+    counted("validation_error", 1);
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "2.0.0" }"#).unwrap();
+    counted("success", 2);
+    let metrics = scrape(port, "/metrics");
+    let output = watch.stop();
+    assert!(output.status.success(), "{output:?}");
+
+    let value = |series: &str| metric(&metrics, series);
+    let reloads = |result: &str| value(&reloads_series(result));
+    assert_eq!(reloads("success"), 2);
+    // A failing file is retried on every tick until it changes
+    assert!(reloads("parse_error") >= 1);
+    assert!(reloads("validation_error") >= 1);
+    assert_eq!(reloads("read_error"), 0);
+    assert_eq!(
+        value("configwatcher_reload_duration_seconds_count"),
+        reloads("success") + reloads("parse_error") + reloads("validation_error")
+    );
+    assert_eq!(value("configwatcher_watch_errors_total"), 0);
+    assert_eq!(
+        value(
+            r#"configwatcher_config_info{app_name="TestApp",version="2.0.0",environment="development"}"#
+        ),
+        1
+    );
+}
+
+fn audit_entries(path: &std::path::Path) -> Vec<serde_json::Value> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_audit_log_records_the_edit_sequence() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let audit = dir.path().join("audit.jsonl");
+    fs::write(
+        &config,
+        r#"{ "app_name": "TestApp", "version": "1.0.0", "database": { "connection_string": "postgres://u:old@db" } }"#,
+    )
+    .unwrap();
+
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1", "-q"])
+            .args(["--audit-log", audit.to_str().unwrap()]),
+    );
+    let recorded = |event: &str| {
+        let entry = format!(r#""event":"{event}""#);
+        watch.until(event, |_| {
+            common::read(dir.path(), "audit.jsonl").contains(&entry)
+        });
+    };
+    recorded("loaded");
+    fs::write(&config, "{ invalid json }").unwrap();
+    recorded("rejected");
+    let fixed = r#"{ "app_name": "TestApp", "version": "2.0.0", "database": { "connection_string": "postgres://u:new@db" } }"#;
+    fs::write(&config, fixed).unwrap();
+    recorded("changed");
+    fs::write(&config, fixed).unwrap();
+    recorded("unchanged");
+    assert!(watch.stop().status.success());
+
+    let entries = audit_entries(&audit);
+    let events: Vec<_> = entries
+        .iter()
+        .map(|entry| {
+            (
+                entry["event"].as_str().unwrap(),
+                entry["version"].as_u64(),
+                entry["accepted"].as_bool(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        events,
+        [
+            ("started", None, None),
+            ("loaded", Some(1), Some(true)),
+            // Retried on every tick, recorded once
+            ("rejected", Some(1), Some(false)),
+            ("changed", Some(2), Some(true)),
+            ("unchanged", Some(2), Some(true)),
+            ("shutdown", None, None),
+        ]
+    );
+    assert_eq!(entries[2]["error"]["code"], "invalid_json");
+    let diff = entries[3]["diff"].to_string();
+    assert!(diff.contains(r#""new":"2.0.0""#), "{diff}");
+    assert!(diff.contains("database.connection_string"), "{diff}");
+    assert!(diff.contains("<redacted>"), "{diff}");
+    assert!(!diff.contains("old@") && !diff.contains("new@"), "{diff}");
+    assert_eq!(entries[5]["reason"], "signal");
+}
+
+#[test]
+fn test_audit_log_must_be_writable() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+    let audit = dir.path().join("missing").join("audit.jsonl");
+
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["-f", config.to_str().unwrap()])
+        .args(["--audit-log", audit.to_str().unwrap()])
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Cannot open audit log"), "{stderr}");
+}
+
+#[test]
+fn test_audit_log_survives_a_kill() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let audit = dir.path().join("audit.jsonl");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+
+    // Killed: no shutdown entry, but no torn line
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--audit-log", audit.to_str().unwrap()]),
+    );
+    watch.until("loaded", |_| {
+        common::read(dir.path(), "audit.jsonl").contains(r#""event":"loaded""#)
+    });
+    assert!(!watch.kill().status.success());
+
+    let text = fs::read_to_string(&audit).unwrap();
+    assert!(text.ends_with('\n'), "{text}");
+    let events: Vec<_> = audit_entries(&audit)
+        .iter()
+        .map(|entry| entry["event"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(events, ["started", "loaded"]);
+}
+
+#[cfg(all(unix, feature = "event-db"))]
+#[test]
+fn test_event_db_failure_does_not_stop_the_watcher() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+
+    // A directory cannot be opened as a database
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap()])
+            .args(["--event-db", dir.path().to_str().unwrap()]),
+    );
+    watch.wait_for("event database disabled");
+    watch.wait_for("Initial configuration loaded");
+    let output = watch.stop();
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_export_env_is_sourceable_and_kept_on_write_failure() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let env = dir.path().join("app.env");
+    fs::write(
+        &config,
+        r#"{ "app_name": "It's \"my\" app", "version": "1.0.0",
+             "database": { "connection_string": "postgres://u:s3cret@db" } }"#,
+    )
+    .unwrap();
+
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap()])
+            .args(["--export-env", env.to_str().unwrap()])
+            .args(["--export-env-format", "shell", "--export-env-prefix", "APP"]),
+    );
+    watch.until("the env file", |_| env.exists());
+    assert!(watch.stop().status.success());
+
+    let sourced = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!(
+            ". '{}' && printf '%s|%s|%s' \"$APP__APP_NAME\" \"$APP__VERSION\" \"${{APP__DATABASE__CONNECTION_STRING-unset}}\"",
+            env.display()
+        ))
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&sourced.stdout),
+        r#"It's "my" app|1.0.0|unset"#
+    );
+
+    // A file that cannot be written is reported, the configuration is not
+    // rejected
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap()])
+            .args(["--export-env", "/nonexistent/dir/app.env"]),
+    );
+    watch.wait_for("export-env");
+    watch.wait_for("Initial configuration loaded");
+    assert!(watch.stop().status.success());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_write_normalized_resolves_defaults_and_overrides() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    let normalized = dir.path().join("effective-config.json");
+    fs::write(
+        &config,
+        r#"{ "app_name": "TestApp", "version": "1.0.0", "server": { "host": "localhost", "port": 8080 } }"#,
+    )
+    .unwrap();
+
+    let watch = Watch::spawn(
+        common::config_watcher()
+            .args(["-f", config.to_str().unwrap()])
+            .args(["--write-normalized", normalized.to_str().unwrap()])
+            .args(["--override", "server.port=9090"]),
+    );
+    watch.until("the normalized file", |_| normalized.exists());
+    assert!(watch.stop().status.success());
+
+    let effective: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&normalized).unwrap()).unwrap();
+    assert_eq!(effective["server"]["port"], 9090);
+    assert_eq!(effective["server"]["max_connections"], 1024);
+    assert_eq!(effective["environment"], "development");
+}
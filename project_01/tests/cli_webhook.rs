@@ -0,0 +1,232 @@
+// Delivers webhook payloads to an in-process fake receiver through the real
+// binary.
+
+mod common;
+
+use assert_cmd::Command;
+use common::{Watch, config_watcher};
+use serde_json::Value;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+/// Answers 200 to every request and hands over its head and JSON body
+fn receiver() -> (SocketAddr, Receiver<(String, Value)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { break };
+            let request = read_request(&mut stream);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+            if sender.send(request).is_err() {
+                break;
+            }
+        }
+    });
+    (addr, receiver)
+}
+
+fn read_request(stream: &mut TcpStream) -> (String, Value) {
+    let mut data = Vec::new();
+    let mut buf = [0; 4096];
+    let (head, length) = loop {
+        let n = stream.read(&mut buf).unwrap();
+        data.extend_from_slice(&buf[..n]);
+        if let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&data[..end]).to_string();
+            let length: usize = head
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+                .unwrap()
+                .1
+                .trim()
+                .parse()
+                .unwrap();
+            data.drain(..end + 4);
+            break (head, length);
+        }
+    };
+    while data.len() < length {
+        let n = stream.read(&mut buf).unwrap();
+        data.extend_from_slice(&buf[..n]);
+    }
+    (head, serde_json::from_slice(&data).unwrap())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_reload_and_failure_reach_the_receiver() {
+    let (addr, requests) = receiver();
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--webhook-url", &format!("http://{addr}/hooks")])
+            .args(["--webhook-secret", "s3cret"]),
+    );
+    let next = || requests.recv_timeout(Duration::from_secs(10)).unwrap();
+    watch.wait_for("Initial configuration loaded");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "2.0.0" }"#).unwrap();
+    let mut payloads = vec![next()];
+    fs::write(&config, "{ invalid json }").unwrap();
+    payloads.push(next());
+    assert!(watch.stop().status.success());
+
+    // Neither the initial load nor the shutdown is selected by default
+    payloads.extend(requests.try_iter());
+    let events: Vec<&Value> = payloads.iter().map(|(_, body)| &body["event"]).collect();
+    // The broken file is retried, and fails again, until the end
+    assert!(events.len() >= 2, "{payloads:?}");
+    assert_eq!(events[0], "loaded");
+    assert!(events[1..].iter().all(|event| *event == "load_failed"));
+
+    let (head, reload) = &payloads[0];
+    assert!(head.starts_with("POST /hooks HTTP/1.1"), "{head}");
+    let lowercase = head.to_ascii_lowercase();
+    assert!(lowercase.contains("x-signature-256: sha256="), "{head}");
+    assert_eq!(reload["file"], config.to_str().unwrap());
+    assert_eq!(reload["version"], 2);
+    assert_eq!(reload["initial"], false);
+    assert_eq!(reload["diff"][0]["path"], "version");
+    assert!(reload["host"].is_string());
+    assert!(reload["timestamp"].is_string());
+
+    let failure = &payloads[1].1;
+    assert_eq!(failure["error"]["code"], "invalid_json");
+    assert_eq!(failure["initial"], false);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_rate_limit_drops_what_is_beyond_it() {
+    let (addr, requests) = receiver();
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, "{ invalid json }").unwrap();
+
+    // The broken file fails on every check: one token, one payload
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--webhook-url", &format!("http://{addr}/hooks")])
+            .args(["--notify-rate-limit", "1/1h"]),
+    );
+    watch.wait_for("notifier rate limit reached");
+    let output = watch.stop();
+    assert!(output.status.success());
+
+    let payloads: Vec<(String, Value)> = requests.try_iter().collect();
+    assert_eq!(payloads.len(), 1, "{payloads:?}");
+    assert_eq!(payloads[0].1["event"], "load_failed");
+    assert_eq!(payloads[0].1["initial"], true);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("notifier rate limit reached"), "{stderr}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_https_goes_through_the_configured_proxy() {
+    // The proxy records the tunnel it is asked for and refuses it
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy = listener.local_addr().unwrap();
+    let (sender, tunnels) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { break };
+            let mut head = Vec::new();
+            let mut buf = [0; 4096];
+            while !head.ends_with(b"\r\n\r\n") {
+                match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => head.extend_from_slice(&buf[..n]),
+                }
+            }
+            let head = String::from_utf8_lossy(&head).to_string();
+            let _ = stream.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n");
+            if sender.send(head).is_err() {
+                break;
+            }
+        }
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(
+        &config,
+        format!(
+            r#"{{ "app_name": "TestApp", "version": "1.0.0",
+                 "proxy": {{ "https": "http://{proxy}" }} }}"#
+        ),
+    )
+    .unwrap();
+
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--webhook-url", "https://hooks.example.com/config"])
+            .args(["--webhook-events", "initial"]),
+    );
+    let head = tunnels
+        .recv_timeout(Duration::from_secs(10))
+        .expect("no request reached the proxy");
+    assert!(watch.stop().status.success());
+
+    assert!(
+        head.starts_with("CONNECT hooks.example.com:443 HTTP/1.1"),
+        "{head}"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_slack_message_for_a_failed_reload() {
+    let (addr, requests) = receiver();
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{ "app_name": "TestApp", "version": "1.0.0" }"#).unwrap();
+
+    let watch = Watch::spawn(
+        config_watcher()
+            .args(["-f", config.to_str().unwrap(), "--interval", "1"])
+            .args(["--slack-webhook-url", &format!("http://{addr}/slack")])
+            .args(["--slack-channel", "#ops"]),
+    );
+    watch.wait_for("Initial configuration loaded");
+    fs::write(&config, "{ invalid json }").unwrap();
+    let (head, message) = requests.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert!(watch.stop().status.success());
+
+    // The initial load is not posted, but names the application
+    assert!(head.starts_with("POST /slack HTTP/1.1"), "{head}");
+    let text = message["text"].as_str().unwrap();
+    assert!(
+        text.starts_with("❌ *TestApp* configuration rejected on "),
+        "{text}"
+    );
+    assert_eq!(message["channel"], "#ops");
+    assert_eq!(message["attachments"][0]["title"], "Findings");
+}
+
+#[test]
+fn test_slack_template_with_an_unknown_placeholder() {
+    let dir = tempfile::tempdir().unwrap();
+    let templates = dir.path().join("slack.toml");
+    fs::write(&templates, r#"failure = "{app} is broken""#).unwrap();
+    let output = Command::cargo_bin("config_watcher")
+        .unwrap()
+        .args(["-f", "config.json"])
+        .args(["--slack-webhook-url", "http://127.0.0.1:9/slack"])
+        .args(["--slack-template", templates.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown placeholder {app}"), "{stderr}");
+}
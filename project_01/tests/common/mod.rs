@@ -0,0 +1,215 @@
+// Helpers shared by the tests/cli_*.rs binaries. Each binary compiles its
+// own copy with `mod common;` and uses only a few of them, hence the
+// dead_code allowance.
+//
+// Waits poll with a deadline instead of sleeping a fixed time: a test is
+// as slow as the binary, and a slow machine only fails it past the deadline.
+#![allow(dead_code)]
+
+use serde_json::Value;
+use std::fs;
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Output};
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
+
+/// How long `eventually` and `Watch` wait before failing the test
+const DEADLINE: Duration = Duration::from_secs(10);
+
+/// The binary under test
+pub fn config_watcher() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin("config_watcher"))
+}
+
+/// A valid configuration with only the required fields
+pub fn config(app_name: &str, version: &str) -> String {
+    format!(r#"{{ "app_name": "{app_name}", "version": "{version}" }}"#)
+}
+
+/// Polls `condition` until it holds, failing the test past the deadline
+pub fn eventually(what: &str, condition: impl Fn() -> bool) {
+    let deadline = Instant::now() + DEADLINE;
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting: {what}");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// `dir/name`, empty until it exists
+pub fn read(dir: &Path, name: &str) -> String {
+    fs::read_to_string(dir.join(name)).unwrap_or_default()
+}
+
+/// Sends `signal` (`-TERM`, `-HUP`...) to `pid` with kill(1)
+#[cfg(unix)]
+pub fn send(signal: &str, pid: u32) {
+    let status = Command::new("kill")
+        .args([signal, &pid.to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success(), "kill {signal} {pid}");
+}
+
+/// A loopback port nothing listens on, for a server of the binary
+pub fn free_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+/// Every line of `stdout` as one JSON event (`--output json`)
+pub fn events(stdout: &[u8]) -> Vec<Value> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|e| panic!("{e}: {line}")))
+        .collect()
+}
+
+/// The events of `events` called `name`
+pub fn named<'a>(events: &'a [Value], name: &str) -> Vec<&'a Value> {
+    events
+        .iter()
+        .filter(|event| event["event"] == name)
+        .collect()
+}
+
+/// Code of the JSON error report on stderr (`--error-format json`)
+pub fn error_code(output: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let line = stderr
+        .lines()
+        .find(|line| line.starts_with('{'))
+        .unwrap_or_else(|| panic!("no JSON error: {stderr}"));
+    let report: Value = serde_json::from_str(line).unwrap();
+    report["code"].as_str().unwrap().to_string()
+}
+
+/// The binary running in the background, its stdout and stderr captured in
+/// files the test polls while it runs
+///
+/// Killed when dropped, so a failing assertion does not leave it behind.
+pub struct Watch {
+    child: Child,
+    stdout: NamedTempFile,
+    stderr: NamedTempFile,
+}
+
+impl Watch {
+    /// Spawns `command`, whatever its stdout and stderr were set to
+    pub fn spawn(command: &mut Command) -> Self {
+        let stdout = NamedTempFile::new().unwrap();
+        let stderr = NamedTempFile::new().unwrap();
+        let child = command
+            .stdout(stdout.reopen().unwrap())
+            .stderr(stderr.reopen().unwrap())
+            .spawn()
+            .unwrap();
+        Self {
+            child,
+            stdout,
+            stderr,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Everything printed on stdout so far
+    pub fn stdout(&self) -> String {
+        String::from_utf8_lossy(&fs::read(self.stdout.path()).unwrap()).into_owned()
+    }
+
+    /// Everything printed on stderr so far
+    pub fn stderr(&self) -> String {
+        String::from_utf8_lossy(&fs::read(self.stderr.path()).unwrap()).into_owned()
+    }
+
+    /// The events printed so far, with `--output json`, a line still being
+    /// written left out
+    pub fn events(&self) -> Vec<Value> {
+        let stdout = self.stdout();
+        let complete = stdout.rfind('\n').map_or("", |end| &stdout[..end]);
+        events(complete.as_bytes())
+    }
+
+    /// Polls `condition` like `eventually`, failing with what the binary
+    /// printed
+    pub fn until(&self, what: &str, condition: impl Fn(&Self) -> bool) {
+        let deadline = Instant::now() + DEADLINE;
+        while !condition(self) {
+            assert!(
+                Instant::now() < deadline,
+                "timed out waiting: {what}\nstdout:\n{}\nstderr:\n{}",
+                self.stdout(),
+                self.stderr()
+            );
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    /// Waits until `text` shows up on stdout or stderr
+    pub fn wait_for(&self, text: &str) {
+        self.until(&format!("{text:?}"), |watch| {
+            watch.stdout().contains(text) || watch.stderr().contains(text)
+        });
+    }
+
+    /// Waits until the `name` event of configuration `version` shows up,
+    /// with `--output json`
+    pub fn wait_for_event(&self, name: &str, version: u64) {
+        self.until(&format!("{name} of version {version}"), |watch| {
+            named(&watch.events(), name)
+                .iter()
+                .any(|event| event["version"] == version)
+        });
+    }
+
+    /// Waits for the binary to exit on its own, and what it printed
+    pub fn wait(mut self) -> Output {
+        let deadline = Instant::now() + DEADLINE;
+        let status = loop {
+            if let Some(status) = self.child.try_wait().unwrap() {
+                break status;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "still running\nstdout:\n{}\nstderr:\n{}",
+                self.stdout(),
+                self.stderr()
+            );
+            std::thread::sleep(Duration::from_millis(50));
+        };
+        self.output(status)
+    }
+
+    /// Stops the binary with SIGTERM, as a service manager would, and
+    /// waits for its graceful shutdown
+    #[cfg(unix)]
+    pub fn stop(self) -> Output {
+        send("-TERM", self.id());
+        self.wait()
+    }
+
+    /// Kills the binary: no shutdown, for a test done with it
+    pub fn kill(mut self) -> Output {
+        self.child.kill().unwrap();
+        let status = self.child.wait().unwrap();
+        self.output(status)
+    }
+
+    fn output(&self, status: ExitStatus) -> Output {
+        Output {
+            status,
+            stdout: fs::read(self.stdout.path()).unwrap(),
+            stderr: fs::read(self.stderr.path()).unwrap(),
+        }
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
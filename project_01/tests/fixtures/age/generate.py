@@ -0,0 +1,133 @@
+#!/usr/bin/env python3
+"""Regenerates the age fixtures of config_watcher's tests.
+
+Written against the age v1 specification (age-encryption.org/v1) with
+Python's `cryptography` package, independently of the Rust code, so the
+tests check the decryption against a second implementation. Every run
+makes new keys: the tests only depend on the files, not on their values.
+
+    python3 tests/fixtures/age/generate.py
+"""
+
+import base64
+import hashlib
+import hmac
+import json
+import os
+from pathlib import Path
+
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric.x25519 import (
+    X25519PrivateKey,
+    X25519PublicKey,
+)
+from cryptography.hazmat.primitives.ciphers.aead import ChaCha20Poly1305
+from cryptography.hazmat.primitives.kdf.hkdf import HKDF
+
+HERE = Path(__file__).parent
+CHARSET = "qpzry9x8gf2tvdw0s3jn54khce6mua7l"
+CHUNK = 64 * 1024
+
+
+def bech32_polymod(values):
+    gen = [0x3B6A57B2, 0x26508E6D, 0x1EA119FA, 0x3D4233DD, 0x2A1462B3]
+    chk = 1
+    for v in values:
+        top = chk >> 25
+        chk = (chk & 0x1FFFFFF) << 5 ^ v
+        for i in range(5):
+            chk ^= gen[i] if (top >> i) & 1 else 0
+    return chk
+
+
+def bech32_encode(hrp, data):
+    bits, acc, out = 0, 0, []
+    for byte in data:
+        acc = acc << 8 | byte
+        bits += 8
+        while bits >= 5:
+            bits -= 5
+            out.append(acc >> bits & 31)
+    if bits:
+        out.append(acc << (5 - bits) & 31)
+    expanded = [ord(c) >> 5 for c in hrp] + [0] + [ord(c) & 31 for c in hrp]
+    polymod = bech32_polymod(expanded + out + [0] * 6) ^ 1
+    checksum = [polymod >> 5 * (5 - i) & 31 for i in range(6)]
+    return hrp + "1" + "".join(CHARSET[d] for d in out + checksum)
+
+
+def b64(data):
+    return base64.b64encode(data).decode().rstrip("=")
+
+
+def hkdf(ikm, salt, info):
+    return HKDF(hashes.SHA256(), 32, salt, info).derive(ikm)
+
+
+def raw(key):
+    return key.public_bytes(
+        serialization.Encoding.Raw, serialization.PublicFormat.Raw
+    )
+
+
+def keygen():
+    secret = os.urandom(32)
+    public = raw(X25519PrivateKey.from_private_bytes(secret).public_key())
+    return secret, public
+
+
+def encrypt(recipients, plaintext):
+    file_key = os.urandom(16)
+    header = "age-encryption.org/v1\n"
+    for public in recipients:
+        ephemeral = X25519PrivateKey.generate()
+        share = raw(ephemeral.public_key())
+        shared = ephemeral.exchange(X25519PublicKey.from_public_bytes(public))
+        wrap = hkdf(shared, share + public, b"age-encryption.org/v1/X25519")
+        body = ChaCha20Poly1305(wrap).encrypt(bytes(12), file_key, None)
+        header += f"-> X25519 {b64(share)}\n{b64(body)}\n"
+    header += "---"
+    mac = hmac.new(hkdf(file_key, b"", b"header"), header.encode(), hashlib.sha256)
+    header += f" {b64(mac.digest())}\n"
+
+    nonce = os.urandom(16)
+    aead = ChaCha20Poly1305(hkdf(file_key, nonce, b"payload"))
+    chunks = [plaintext[i : i + CHUNK] for i in range(0, len(plaintext), CHUNK)] or [b""]
+    payload = b""
+    for counter, chunk in enumerate(chunks):
+        last = b"\x01" if counter == len(chunks) - 1 else b"\x00"
+        payload += aead.encrypt(counter.to_bytes(11, "big") + last, chunk, None)
+    return header.encode() + nonce + payload
+
+
+def value(recipients, plaintext):
+    return "age:" + base64.b64encode(encrypt(recipients, plaintext.encode())).decode()
+
+
+def identity_file(path, keys):
+    lines = []
+    for secret, public in keys:
+        lines.append("# created: 2026-10-15T00:00:00Z")
+        lines.append(f"# public key: {bech32_encode('age', public)}")
+        lines.append(bech32_encode("age-secret-key-", secret).upper())
+    path.write_text("\n".join(lines) + "\n")
+
+
+def main():
+    ours, theirs = keygen(), keygen()
+    identity_file(HERE / "identity.txt", [ours])
+    identity_file(HERE / "other_identity.txt", [theirs])
+    values = {
+        "recipient": bech32_encode("age", ours[1]),
+        "connection_string": value([ours[1]], "postgres://app:s3cr3t@db/app"),
+        "both_recipients": value([theirs[1], ours[1]], "shared secret"),
+        "empty": value([ours[1]], ""),
+        "other_recipient": value([theirs[1]], "not for us"),
+        "host": value([ours[1]], "db.internal"),
+        "host_2": value([ours[1]], "db2.internal"),
+    }
+    (HERE / "values.json").write_text(json.dumps(values, indent=4) + "\n")
+
+
+if __name__ == "__main__":
+    main()
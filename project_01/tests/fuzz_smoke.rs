@@ -0,0 +1,46 @@
+// Short `cargo fuzz` runs of every target in fuzz/, for CI. They need a
+// nightly toolchain and cargo-fuzz (`cargo install cargo-fuzz`), hence
+// ignored by default:
+//
+//   cargo test -p config_watcher --test fuzz_smoke -- --ignored
+//
+// FUZZ_SECONDS shortens or lengthens each run (60 by default).
+
+use std::path::Path;
+use std::process::Command;
+
+/// Targets of fuzz/Cargo.toml
+const TARGETS: [&str; 2] = ["pipeline", "near_valid"];
+
+fn fuzz(target: &str) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let seconds = std::env::var("FUZZ_SECONDS").unwrap_or_else(|_| "60".to_string());
+    // New inputs go to the (ignored) corpus, the committed seeds are only read
+    let corpus = root.join("fuzz/corpus").join(target);
+    std::fs::create_dir_all(&corpus).unwrap();
+    let mut command = Command::new("cargo");
+    command
+        .current_dir(root)
+        .args(["+nightly", "fuzz", "run", target])
+        .arg(&corpus);
+    let seeds = root.join("fuzz/seeds").join(target);
+    if seeds.is_dir() {
+        command.arg(seeds);
+    }
+    // A single input slower than 10s counts as a hang
+    let status = command
+        .arg("--")
+        .arg(format!("-max_total_time={seconds}"))
+        .arg("-timeout=10")
+        .status()
+        .expect("cargo fuzz could not be started");
+    assert!(status.success(), "fuzz target {target} failed: {status}");
+}
+
+#[test]
+#[ignore = "needs nightly and cargo-fuzz"]
+fn test_fuzz_targets_smoke() {
+    for target in TARGETS {
+        fuzz(target);
+    }
+}
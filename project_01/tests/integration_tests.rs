@@ -1,71 +1,207 @@
-// For integration tests to work, I need to expose modules.
-// See Update `src/lib.rs`:
+// Only the public API of the library: the types re-exported at its root.
+//
+// The watch loop runs on tokio's clock: with `start_paused` every interval
+// and sleep completes as soon as the runtime is idle, so these tests take
+// milliseconds. What the watcher did is observed through the events of its
+// emitter (`Emitter::with_events`) and its counters, never through output.
 
-use config_watcher::*;
+use config_watcher::{ConfigEvent, ConfigWatcher, Emitter, EventKind, OutputFormat, Verbosity};
 use std::fs;
-use tempfile::NamedTempFile;
-use tokio::time::{Duration, sleep};
+use std::path::Path;
+use std::time::SystemTime;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::time::{Duration, sleep, timeout};
 
-#[tokio::test]
-async fn test_watcher_detects_changes() {
-    // Create a temporary config file
-    let temp_file = NamedTempFile::new().unwrap();
-    let path = temp_file.path();
-
-    // Write initial config
-    let initial_config = r#"
-    {
-        "app_name": "TestApp",
-        "version": "1.0.0",
-        "environment": "development"
+const V1: &str = r#"
+{
+    "app_name": "TestApp",
+    "version": "1.0.0",
+    "environment": "development"
+}
+"#;
+
+const V2: &str = r#"
+{
+    "app_name": "TestApp",
+    "version": "2.0.0",
+    "environment": "production"
+}
+"#;
+
+/// Writes `contents` with a modification time `secs` seconds after a fixed
+/// point in the past: each write is newer than the previous one, whatever
+/// the resolution of the file system
+fn write(path: &Path, contents: &str, secs: u64) {
+    fs::write(path, contents).unwrap();
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + secs);
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+}
+
+/// A watcher checking `path` every second, and its events
+fn watcher(path: &Path) -> (ConfigWatcher, UnboundedReceiver<ConfigEvent>) {
+    let (sender, events) = mpsc::unbounded_channel();
+    let emitter = Emitter::new(OutputFormat::Json)
+        .with_verbosity(Verbosity::Quiet)
+        .with_events(sender);
+    (ConfigWatcher::new(path, 1).with_emitter(emitter), events)
+}
+
+/// Runs the watch loop until `script` is done
+async fn watching(watcher: &mut ConfigWatcher, script: impl Future<Output = ()>) {
+    tokio::select! {
+        result = watcher.watch() => panic!("the watch loop ended: {result:?}"),
+        () = script => {}
     }
-    "#;
-    fs::write(path, initial_config).unwrap();
-
-    // Create watcher with short interval
-    let mut watcher = watcher::ConfigWatcher::new(path, 1);
-
-    // Spawn watcher in background
-    let watcher_handle = tokio::spawn(async move {
-        let _ = watcher.watch().await;
-    });
-
-    // Wait a bit
-    sleep(Duration::from_secs(2)).await;
-
-    // Modify file
-    let updated_config = r#"
-    {
-        "app_name": "TestApp",
-        "version": "2.0.0",
-        "environment": "production"
+}
+
+/// The next event, within 30s of the paused clock
+async fn next(events: &mut UnboundedReceiver<ConfigEvent>) -> ConfigEvent {
+    timeout(Duration::from_secs(30), events.recv())
+        .await
+        .expect("no event within 30s")
+        .expect("emitter dropped")
+}
+
+/// The version of the configuration a load event carries
+fn loaded_version(event: &ConfigEvent) -> &str {
+    &event.config.as_ref().expect("not a load").version
+}
+
+/// Nothing else happens over the next 10 ticks
+async fn assert_quiet(events: &mut UnboundedReceiver<ConfigEvent>) {
+    sleep(Duration::from_secs(10)).await;
+    if let Ok(event) = events.try_recv() {
+        panic!("unexpected {:?} event: {}", event.kind, event.data);
     }
-    "#;
-    fs::write(path, updated_config).unwrap();
+}
 
-    // Wait for detection
-    sleep(Duration::from_secs(2)).await;
+/// A file that fails to load is retried, and reported, on every check
+async fn assert_retried(events: &mut UnboundedReceiver<ConfigEvent>) {
+    sleep(Duration::from_millis(3500)).await;
+    let mut retries = 0;
+    while let Ok(event) = events.try_recv() {
+        assert_eq!(event.kind, EventKind::Failure, "{}", event.data);
+        assert_eq!(event.data["initial"], false);
+        retries += 1;
+    }
+    assert!(retries >= 3, "{retries} retries in 3.5s");
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_watcher_detects_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    write(&path, V1, 0);
+    let (mut watcher, mut events) = watcher(&path);
+
+    watching(&mut watcher, async {
+        let initial = next(&mut events).await;
+        assert_eq!(initial.kind, EventKind::Initial);
+        assert_eq!(loaded_version(&initial), "1.0.0");
+        assert_quiet(&mut events).await;
 
-    // Cleanup
-    watcher_handle.abort();
+        write(&path, V2, 1);
+        let reload = next(&mut events).await;
+        assert_eq!(reload.kind, EventKind::Reload);
+        assert_eq!(reload.version(), Some(2));
+        assert_eq!(loaded_version(&reload), "2.0.0");
+        assert_quiet(&mut events).await;
+    })
+    .await;
+
+    assert_eq!(watcher.reloads(), 1);
+    assert_eq!(watcher.version(), 2);
+    assert_eq!(
+        watcher.last_valid_config().unwrap().environment,
+        "production"
+    );
 }
 
-#[tokio::test]
+#[tokio::test(start_paused = true)]
 async fn test_watcher_handles_invalid_json() {
-    let temp_file = NamedTempFile::new().unwrap();
-    let path = temp_file.path();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    write(&path, "{ invalid json }", 0);
+    let (mut watcher, mut events) = watcher(&path);
+
+    watching(&mut watcher, async {
+        let failure = next(&mut events).await;
+        assert_eq!(failure.kind, EventKind::Failure);
+        assert_eq!(failure.data["initial"], true);
+        assert_retried(&mut events).await;
+
+        write(&path, V1, 1);
+        let loaded = next(&mut events).await;
+        assert_eq!(loaded.version(), Some(1));
+        assert_eq!(loaded_version(&loaded), "1.0.0");
+        assert_quiet(&mut events).await;
+    })
+    .await;
+
+    assert_eq!(watcher.version(), 1);
+    assert_eq!(watcher.last_error_code(), None);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_watcher_keeps_the_last_valid_configuration() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    write(&path, V1, 0);
+    let (mut watcher, mut events) = watcher(&path);
+
+    watching(&mut watcher, async {
+        assert_eq!(next(&mut events).await.kind, EventKind::Initial);
+
+        write(&path, r#"{ "app_name": "", "version": "1.1.0" }"#, 1);
+        let failure = next(&mut events).await;
+        assert_eq!(failure.kind, EventKind::Failure);
+        assert_eq!(failure.data["initial"], false);
+        assert_retried(&mut events).await;
+
+        write(&path, V2, 2);
+        let reload = next(&mut events).await;
+        assert_eq!(reload.kind, EventKind::Reload);
+        assert_eq!(loaded_version(&reload), "2.0.0");
+        assert_quiet(&mut events).await;
+    })
+    .await;
+
+    assert_eq!(watcher.reloads(), 1);
+    assert_eq!(watcher.version(), 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_watcher_reports_a_removed_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    write(&path, V1, 0);
+    let (mut watcher, mut events) = watcher(&path);
 
-    // Write invalid JSON
-    fs::write(path, "{ invalid json }").unwrap();
+    watching(&mut watcher, async {
+        assert_eq!(next(&mut events).await.kind, EventKind::Initial);
 
-    let mut watcher = watcher::ConfigWatcher::new(path, 1);
+        fs::remove_file(&path).unwrap();
+        let error = next(&mut events).await;
+        assert_eq!(error.kind, EventKind::FileError);
+        // Once per check while it is missing, nothing else
+        sleep(Duration::from_secs(3)).await;
+        while let Ok(event) = events.try_recv() {
+            assert_eq!(event.kind, EventKind::FileError);
+        }
 
-    // Watcher should handle the error gracefully
-    // We'll just verify it doesn't panic
-    let watcher_handle = tokio::spawn(async move {
-        let _ = watcher.watch().await;
-    });
+        write(&path, V2, 1);
+        let reload = next(&mut events).await;
+        assert_eq!(reload.kind, EventKind::Reload);
+        assert_eq!(loaded_version(&reload), "2.0.0");
+        assert_quiet(&mut events).await;
+    })
+    .await;
 
-    sleep(Duration::from_secs(2)).await;
-    watcher_handle.abort();
+    assert_eq!(watcher.reloads(), 1);
+    assert_eq!(watcher.last_valid_config().unwrap().version, "2.0.0");
 }
@@ -0,0 +1,28 @@
+// The wasm32 build of the checker (wasm/) and its wasm-bindgen tests, for
+// CI. They need the target (`rustup target add wasm32-unknown-unknown`),
+// wasm-pack and Node.js, hence ignored by default:
+//
+//   cargo test -p config_watcher --test wasm_build -- --ignored
+
+use std::path::Path;
+use std::process::Command;
+
+fn run(program: &str, args: &[&str]) {
+    let wasm = Path::new(env!("CARGO_MANIFEST_DIR")).join("wasm");
+    let status = Command::new(program)
+        .current_dir(&wasm)
+        .args(args)
+        .status()
+        .unwrap_or_else(|e| panic!("{program} could not be started: {e}"));
+    assert!(status.success(), "{program} {args:?} failed: {status}");
+}
+
+#[test]
+#[ignore = "needs the wasm32 target, wasm-pack and Node.js"]
+fn test_wasm_build_and_bindings() {
+    run(
+        "cargo",
+        &["build", "--release", "--target", "wasm32-unknown-unknown"],
+    );
+    run("wasm-pack", &["test", "--node"]);
+}
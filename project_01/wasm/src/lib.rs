@@ -0,0 +1,7 @@
+//! The `wasm` module of config_watcher, as a package for the browser
+//!
+//!   wasm-pack build --target web
+//!
+//! Only re-exports: the checker runs the library's own code.
+
+pub use config_watcher::wasm::{diff_json, validate_json};
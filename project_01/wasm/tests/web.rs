@@ -0,0 +1,54 @@
+// validateJson and diffJson as JavaScript sees them, in Node.js:
+//
+//   wasm-pack test --node
+//
+// The rules themselves are tested natively (`cargo test --features wasm`);
+// these only check what crosses the boundary.
+
+#![cfg(target_arch = "wasm32")]
+
+use config_watcher_wasm::{diff_json, validate_json};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+/// `value[key]`, through the JS API
+fn get(value: &JsValue, key: &str) -> JsValue {
+    js_sys::Reflect::get(value, &JsValue::from_str(key)).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_valid_document() {
+    let report = validate_json(r#"{ "app_name": "A", "version": "1.0.0" }"#);
+    assert_eq!(get(&report, "valid"), JsValue::TRUE);
+    assert!(get(&report, "code").is_null());
+    assert!(get(&report, "normalized").is_string());
+}
+
+#[wasm_bindgen_test]
+fn test_invalid_document() {
+    let report = validate_json(r#"{ "app_name": "", "version": "1.0.0" }"#);
+    assert_eq!(get(&report, "valid"), JsValue::FALSE);
+    assert_eq!(
+        get(&report, "code").as_string().unwrap(),
+        "validation_failed"
+    );
+    let finding = js_sys::Array::from(&get(&report, "findings")).get(0);
+    assert_eq!(get(&finding, "path").as_string().unwrap(), "app_name");
+    assert_eq!(get(&finding, "severity").as_string().unwrap(), "error");
+
+    let broken = validate_json("{");
+    assert_eq!(get(&broken, "code").as_string().unwrap(), "invalid_json");
+}
+
+#[wasm_bindgen_test]
+fn test_diff() {
+    let old = r#"{ "app_name": "A", "version": "1.0.0" }"#;
+    let new = r#"{ "app_name": "A", "version": "1.1.0" }"#;
+    let compared = diff_json(old, new).unwrap();
+    assert_eq!(get(&compared, "identical"), JsValue::FALSE);
+    let change = js_sys::Array::from(&get(&compared, "diff")).get(0);
+    assert_eq!(get(&change, "path").as_string().unwrap(), "version");
+
+    let thrown = diff_json(old, "{").unwrap_err();
+    assert_eq!(get(&thrown, "code").as_string().unwrap(), "invalid_json");
+}